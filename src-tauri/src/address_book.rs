@@ -0,0 +1,188 @@
+// Address book
+// Remembers every address a server has been shared on (LAN, external IP,
+// bridge tunnel, DDNS names) together with its reachability history, so owners
+// can tell which invite links still work after network changes.
+
+use crate::clock::now_secs;
+use crate::paths::AppPaths;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Reachability checks kept per address
+const MAX_HISTORY: usize = 20;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum AddressKind {
+    Lan,
+    External,
+    Bridge,
+    Ddns,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReachabilityRecord {
+    pub timestamp: u64,
+    pub reachable: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishedAddress {
+    pub server_id: String,
+    pub kind: AddressKind,
+    pub address: String, // "host:port"
+    pub first_seen: u64,
+    pub last_seen: u64,
+    #[serde(default)]
+    pub reachable: Option<bool>, // Result of the latest check
+    #[serde(default)]
+    pub history: Vec<ReachabilityRecord>, // Newest last
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+struct AddressBookData {
+    addresses: Vec<PublishedAddress>,
+}
+
+pub struct AddressBook {
+    config_path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl AddressBook {
+    pub fn new(paths: &AppPaths) -> Self {
+        let config_path = paths.config_file("address_book.json");
+
+        Self {
+            config_path,
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn load(&self) -> AddressBookData {
+        std::fs::read_to_string(&self.config_path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, data: &AddressBookData) -> Result<()> {
+        if let Some(parent) = self.config_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.config_path, serde_json::to_string_pretty(data)?)?;
+        Ok(())
+    }
+
+    pub fn get_addresses(&self, server_id: &str) -> Vec<PublishedAddress> {
+        self.load()
+            .addresses
+            .into_iter()
+            .filter(|a| a.server_id == server_id)
+            .collect()
+    }
+
+    /// Remember an address (or refresh its last_seen timestamp)
+    pub fn record(&self, server_id: &str, kind: AddressKind, address: &str) -> Result<()> {
+        let _guard = self.lock.lock().unwrap();
+        let mut data = self.load();
+        let now = now_secs();
+
+        if let Some(existing) = data
+            .addresses
+            .iter_mut()
+            .find(|a| a.server_id == server_id && a.address == address)
+        {
+            existing.last_seen = now;
+            existing.kind = kind;
+        } else {
+            data.addresses.push(PublishedAddress {
+                server_id: server_id.to_string(),
+                kind,
+                address: address.to_string(),
+                first_seen: now,
+                last_seen: now,
+                reachable: None,
+                history: Vec::new(),
+            });
+        }
+
+        self.save(&data)
+    }
+
+    pub fn remove(&self, server_id: &str, address: &str) -> Result<()> {
+        let _guard = self.lock.lock().unwrap();
+        let mut data = self.load();
+        data.addresses
+            .retain(|a| !(a.server_id == server_id && a.address == address));
+        self.save(&data)
+    }
+
+    /// Forget every address of a server (e.g. when it is deleted)
+    pub fn remove_server(&self, server_id: &str) -> Result<()> {
+        let _guard = self.lock.lock().unwrap();
+        let mut data = self.load();
+        data.addresses.retain(|a| a.server_id != server_id);
+        self.save(&data)
+    }
+
+    /// Probe every address of a server and append the result to its history
+    pub async fn check_server(&self, server_id: &str) -> Result<Vec<PublishedAddress>> {
+        let addresses = self.get_addresses(server_id);
+
+        let mut results = Vec::new();
+        for entry in &addresses {
+            results.push((entry.address.clone(), probe(&entry.address).await));
+        }
+
+        {
+            let _guard = self.lock.lock().unwrap();
+            let mut data = self.load();
+            let now = now_secs();
+            for (address, reachable) in results {
+                if let Some(entry) = data
+                    .addresses
+                    .iter_mut()
+                    .find(|a| a.server_id == server_id && a.address == address)
+                {
+                    entry.reachable = Some(reachable);
+                    entry.history.push(ReachabilityRecord {
+                        timestamp: now,
+                        reachable,
+                    });
+                    if entry.history.len() > MAX_HISTORY {
+                        let excess = entry.history.len() - MAX_HISTORY;
+                        entry.history.drain(..excess);
+                    }
+                }
+            }
+            self.save(&data)?;
+        }
+
+        Ok(self.get_addresses(server_id))
+    }
+}
+
+/// Try a TCP connection to "host:port"
+async fn probe(address: &str) -> bool {
+    let connect = tokio::net::TcpStream::connect(address.to_string());
+    matches!(
+        tokio::time::timeout(Duration::from_secs(5), connect).await,
+        Ok(Ok(_))
+    )
+}
+
+/// Validate a user-supplied "host:port" address
+pub fn parse_address(address: &str) -> Result<String> {
+    let address = address.trim();
+    let (host, port) = address
+        .rsplit_once(':')
+        .context("Address must be in host:port format")?;
+    if host.is_empty() {
+        anyhow::bail!("Address must include a host name");
+    }
+    port.parse::<u16>().context("Invalid port number")?;
+    Ok(address.to_string())
+}
@@ -0,0 +1,51 @@
+// App log
+// Points the process's stdout and stderr at prismarine.log in the log folder,
+// so every "[Tag] ..." line of a GUI build without a console still ends up
+// somewhere a bug report can pick it up. The previous session's log is kept
+// as prismarine.log.1.
+
+use anyhow::{Context, Result};
+use std::path::Path;
+
+const LOG_FILE: &str = "prismarine.log";
+const PREVIOUS_LOG_FILE: &str = "prismarine.log.1";
+
+pub fn init(log_dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(log_dir).context("Failed to create the log folder")?;
+    let path = log_dir.join(LOG_FILE);
+    if path.exists() {
+        let _ = std::fs::rename(&path, log_dir.join(PREVIOUS_LOG_FILE));
+    }
+    let file = std::fs::File::create(&path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    redirect(file)?;
+    println!("[Log] Started {}", chrono::Local::now().to_rfc3339());
+    Ok(())
+}
+
+#[cfg(unix)]
+fn redirect(file: std::fs::File) -> Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    for fd in [libc::STDOUT_FILENO, libc::STDERR_FILENO] {
+        if unsafe { libc::dup2(file.as_raw_fd(), fd) } < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn redirect(file: std::fs::File) -> Result<()> {
+    use std::os::windows::io::IntoRawHandle;
+    use windows::Win32::Foundation::HANDLE;
+    use windows::Win32::System::Console::{SetStdHandle, STD_ERROR_HANDLE, STD_OUTPUT_HANDLE};
+
+    // std looks the handle up on every write; the file stays open for the process
+    let handle = HANDLE(file.into_raw_handle());
+    unsafe {
+        SetStdHandle(STD_OUTPUT_HANDLE, handle)?;
+        SetStdHandle(STD_ERROR_HANDLE, handle)?;
+    }
+    Ok(())
+}
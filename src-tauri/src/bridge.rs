@@ -1,157 +1,354 @@
-// Prismarine Bridge - Tunneling service using bore
-// This provides a reliable way to expose Minecraft servers without port forwarding
-// bore is super simple - no registration, no tokens, just works!
+// Prismarine Bridge - Tunneling service
+// This provides a reliable way to expose Minecraft servers without port forwarding.
+// bore is the default (no registration, no tokens); playit.gg, frp and
+// cloudflared are available as alternatives, see tunnels.rs.
 
+use crate::paths::AppPaths;
+use crate::tunnels::{self, BinaryDownload, TunnelKind, TunnelParams, TunnelProvider};
 use anyhow::{Context, Result};
+use std::collections::HashMap;
 use std::io::{BufRead, BufReader};
 use std::path::PathBuf;
-use std::process::{Child, Command, Stdio};
+use std::process::{Child, Stdio};
 use std::sync::{Arc, Mutex};
 use std::thread;
 
-pub struct PrismarineBridge {
+/// Provider and parameters of the last start
+type StartParams = (TunnelKind, TunnelParams);
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TunnelProviderInfo {
+    pub kind: TunnelKind,
+    pub name: String,
+    pub installed: bool,
+    pub downloadable: bool, // false when the client must be installed by hand
+    pub needs_remote_server: bool, // e.g. frp needs the frps address
+    pub supports_udp: bool, // Can carry Geyser's Bedrock port
+    pub secret_name: String, // Secret store entry for its token
+}
+
+/// Key of the tunnel started without a server (bridge view, network failover)
+const DEFAULT_TUNNEL: &str = "";
+
+/// Key of a server's Bedrock (UDP) tunnel, next to its Java tunnel keyed by the id
+pub fn bedrock_key(server_id: &str) -> String {
+    format!("{}:bedrock", server_id)
+}
+
+/// A server tunnel that should be up, see `sync_server_tunnels`
+#[derive(Debug, Clone)]
+pub struct TunnelTarget {
+    pub key: String, // Server id, or `bedrock_key` of it
+    pub kind: TunnelKind,
+    pub port: u16,
+    pub remote_server: Option<String>,
+    pub udp: bool,
+}
+
+/// One running tunnel client
+struct Tunnel {
     process: Mutex<Option<Child>>,
-    bore_path: PathBuf,
-    config_dir: PathBuf,
     status: Arc<Mutex<BridgeStatus>>,
+    /// Used to reconnect with the same parameters
+    last_start: Mutex<Option<StartParams>>,
 }
 
-impl PrismarineBridge {
-    pub fn new() -> Self {
-        let app_data = dirs::data_local_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join("Prismarine")
-            .join("bridge");
-
+impl Tunnel {
+    fn new() -> Self {
         Self {
             process: Mutex::new(None),
-            bore_path: app_data.join("bore.exe"),
-            config_dir: app_data.clone(),
             status: Arc::new(Mutex::new(BridgeStatus::Stopped)),
+            last_start: Mutex::new(None),
         }
     }
 
-    /// Check if bore binary exists
-    pub fn is_installed(&self) -> bool {
-        self.bore_path.exists()
+    fn kill_process(&self) {
+        if let Some(mut child) = self.process.lock().unwrap().take() {
+            println!("[Prismarine Bridge] Stopping tunnel");
+            let _ = child.kill();
+        }
     }
 
-    /// bore doesn't need authtoken, so always return true
-    pub fn has_authtoken(&self) -> bool {
-        true // bore doesn't need tokens!
+    fn stop(&self) {
+        self.kill_process();
+        *self.last_start.lock().unwrap() = None;
+        *self.status.lock().unwrap() = BridgeStatus::Stopped;
     }
 
-    /// bore doesn't need authtoken, this is a no-op
-    pub fn set_authtoken(&self, _token: &str) -> Result<()> {
-        Ok(()) // bore doesn't need tokens!
+    /// The client exited on its own (crash, kicked by the tunnel server)
+    fn has_exited(&self) -> bool {
+        match self.process.lock().unwrap().as_mut() {
+            Some(child) => child.try_wait().map(|s| s.is_some()).unwrap_or(true),
+            None => false,
+        }
     }
+}
 
-    /// Download bore binary if not present
-    pub async fn ensure_installed(&self) -> Result<()> {
-        if self.is_installed() {
-            return Ok(());
+pub struct PrismarineBridge {
+    config_dir: PathBuf,
+    provider: Mutex<TunnelKind>, // Used by the next start of the default tunnel
+    /// Server id (or DEFAULT_TUNNEL) -> tunnel
+    tunnels: Mutex<HashMap<String, Arc<Tunnel>>>,
+}
+
+impl PrismarineBridge {
+    pub fn new(paths: &AppPaths) -> Self {
+        let app_data = paths.data_dir.join("bridge");
+
+        Self {
+            config_dir: app_data,
+            provider: Mutex::new(TunnelKind::default()),
+            tunnels: Mutex::new(HashMap::new()),
         }
+    }
 
-        // Create directory
-        std::fs::create_dir_all(&self.config_dir)?;
+    fn tunnel(&self, key: &str) -> Arc<Tunnel> {
+        Arc::clone(
+            self.tunnels
+                .lock()
+                .unwrap()
+                .entry(key.to_string())
+                .or_insert_with(|| Arc::new(Tunnel::new())),
+        )
+    }
 
-        // Update status
-        *self.status.lock().unwrap() = BridgeStatus::Downloading;
+    /// Choose the tunnel provider for the next start
+    pub fn set_provider(&self, kind: TunnelKind) {
+        *self.provider.lock().unwrap() = kind;
+    }
 
-        // Download bore for Windows (x86_64)
-        let download_url = "https://github.com/ekzhang/bore/releases/download/v0.6.0/bore-v0.6.0-x86_64-pc-windows-msvc.zip";
+    pub fn provider(&self) -> TunnelKind {
+        *self.provider.lock().unwrap()
+    }
 
-        println!("[Prismarine Bridge] Downloading bore...");
+    /// All providers with their install state
+    pub fn list_providers(&self) -> Vec<TunnelProviderInfo> {
+        TunnelKind::ALL
+            .iter()
+            .map(|kind| {
+                let provider = kind.provider();
+                TunnelProviderInfo {
+                    kind: *kind,
+                    name: provider.name().to_string(),
+                    installed: tunnels::binary_path(&self.config_dir, provider.as_ref()).exists(),
+                    downloadable: provider.download().is_some(),
+                    needs_remote_server: provider.needs_remote_server(),
+                    supports_udp: provider.supports_udp(),
+                    secret_name: provider.secret_name().to_string(),
+                }
+            })
+            .collect()
+    }
 
-        let response = reqwest::get(download_url)
+    fn binary_path(&self, provider: &dyn TunnelProvider) -> PathBuf {
+        tunnels::binary_path(&self.config_dir, provider)
+    }
+
+    /// Check if the selected provider's client exists
+    pub fn is_installed(&self) -> bool {
+        self.binary_path(self.provider().provider().as_ref())
+            .exists()
+    }
+
+    /// No provider strictly needs a token (bore and cloudflared quick tunnels
+    /// work without, playit.gg links through a claim URL, frp tokens are optional)
+    pub fn has_authtoken(&self) -> bool {
+        true
+    }
+
+    /// Store the token of the selected provider in the secret store
+    pub fn set_authtoken(&self, token: &str) -> Result<()> {
+        let provider = self.provider().provider();
+        crate::secrets::set(provider.secret_name(), token)
+    }
+
+    /// Download the selected provider's client if not present
+    pub async fn ensure_installed(&self) -> Result<()> {
+        self.install(self.provider(), &self.tunnel(DEFAULT_TUNNEL))
             .await
-            .context("Failed to download bore")?;
+    }
 
-        let bytes = response.bytes().await?;
+    /// Download a provider's client if not present, reporting progress on `tunnel`
+    async fn install(&self, kind: TunnelKind, tunnel: &Tunnel) -> Result<()> {
+        crate::offline::ensure_online("Starting a tunnel")?;
+        let provider = kind.provider();
+        let binary_path = self.binary_path(provider.as_ref());
+        if binary_path.exists() {
+            return Ok(());
+        }
 
-        // Save zip to temp file
-        let zip_path = self.config_dir.join("bore.zip");
-        std::fs::write(&zip_path, &bytes)?;
+        let download = provider.download().with_context(|| {
+            format!(
+                "No {} download for this platform, place the client at {}",
+                provider.name(),
+                binary_path.display()
+            )
+        })?;
 
-        // Extract bore.exe from zip
-        let file = std::fs::File::open(&zip_path)?;
-        let mut archive = zip::ZipArchive::new(file)?;
+        // Create directory
+        std::fs::create_dir_all(&self.config_dir)?;
 
-        for i in 0..archive.len() {
-            let mut file = archive.by_index(i)?;
-            let name = file.name();
-            if name.ends_with("bore.exe") || name == "bore.exe" {
-                let mut outfile = std::fs::File::create(&self.bore_path)?;
-                std::io::copy(&mut file, &mut outfile)?;
-                break;
+        // Update status
+        *tunnel.status.lock().unwrap() = BridgeStatus::Downloading;
+
+        println!("[Prismarine Bridge] Downloading {}...", provider.name());
+
+        let client = reqwest::Client::builder()
+            .user_agent("MinecraftServerManager/0.1.0")
+            .build()?;
+        match download {
+            BinaryDownload::Raw(url) => {
+                crate::download::download_file(
+                    &client,
+                    &[url],
+                    &binary_path,
+                    provider.name(),
+                    None,
+                )
+                .await
+                .with_context(|| format!("Failed to download {}", provider.name()))?;
+            }
+            BinaryDownload::Zip { url, entry } => {
+                // Save zip to temp file
+                let zip_path = self
+                    .config_dir
+                    .join(format!("{}.zip", provider.binary_name()));
+                crate::download::download_file(&client, &[url], &zip_path, provider.name(), None)
+                    .await
+                    .with_context(|| format!("Failed to download {}", provider.name()))?;
+
+                // Extract the client from zip
+                let file = std::fs::File::open(&zip_path)?;
+                let mut archive = zip::ZipArchive::new(file)?;
+
+                for i in 0..archive.len() {
+                    let mut file = archive.by_index(i)?;
+                    if file.name().ends_with(entry) {
+                        let mut outfile = std::fs::File::create(&binary_path)?;
+                        std::io::copy(&mut file, &mut outfile)?;
+                        break;
+                    }
+                }
+
+                // Delete zip
+                let _ = std::fs::remove_file(&zip_path);
+                if !binary_path.exists() {
+                    anyhow::bail!("{} not found in the {} archive", entry, provider.name());
+                }
             }
         }
 
-        // Delete zip
-        let _ = std::fs::remove_file(&zip_path);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&binary_path, std::fs::Permissions::from_mode(0o755))?;
+        }
 
         println!("[Prismarine Bridge] Download complete!");
 
         Ok(())
     }
 
-    /// Start the bridge
+    /// Start the default tunnel with the selected provider
     pub fn start(
         &self,
         port: u16,
         remote_server: Option<String>,
         secret: Option<String>,
     ) -> Result<()> {
+        let params = TunnelParams {
+            port,
+            remote_server,
+            secret,
+            udp: false,
+        };
+        self.start_tunnel(DEFAULT_TUNNEL, self.provider(), params)
+    }
+
+    /// Start (or restart) a server tunnel, downloading the client first
+    pub async fn start_for_server(&self, target: &TunnelTarget) -> Result<()> {
+        self.install(target.kind, &self.tunnel(&target.key)).await?;
+        let params = TunnelParams {
+            port: target.port,
+            remote_server: target.remote_server.clone(),
+            secret: None,
+            udp: target.udp,
+        };
+        self.start_tunnel(&target.key, target.kind, params)
+    }
+
+    fn start_tunnel(&self, key: &str, kind: TunnelKind, params: TunnelParams) -> Result<()> {
+        let tunnel = self.tunnel(key);
+
         // Kill existing process if any
-        self.stop()?;
+        tunnel.stop();
+
+        let provider: Arc<dyn TunnelProvider> = Arc::from(kind.provider());
+        if params.udp && !provider.supports_udp() {
+            let message = format!("{} cannot tunnel UDP", provider.name());
+            *tunnel.status.lock().unwrap() = BridgeStatus::Error(message.clone());
+            anyhow::bail!(message);
+        }
 
-        *self.status.lock().unwrap() = BridgeStatus::Starting;
+        // Fall back to the provider's secret saved in the secret store
+        let params = TunnelParams {
+            remote_server: params.remote_server.filter(|s| !s.is_empty()),
+            secret: params
+                .secret
+                .filter(|s| !s.is_empty())
+                .or_else(|| crate::secrets::get(provider.secret_name()).ok().flatten()),
+            ..params
+        };
+
+        *tunnel.status.lock().unwrap() = BridgeStatus::Starting;
+        *tunnel.last_start.lock().unwrap() = Some((kind, params.clone()));
 
-        let server = remote_server.unwrap_or_else(|| "bore.pub".to_string());
         println!(
-            "[Prismarine Bridge] Starting bore local {} --to {}...",
-            port, server
+            "[Prismarine Bridge] Starting {} for local port {}/{}...",
+            provider.name(),
+            params.port,
+            if params.udp { "UDP" } else { "TCP" }
         );
 
-        let mut command = Command::new(&self.bore_path);
-        command.arg("local");
-        command.arg(port.to_string());
-        command.arg("--to");
-        command.arg(server);
-
-        if let Some(s) = secret {
-            // Only add secret if not empty
-            if !s.is_empty() {
-                command.arg("--secret");
-                command.arg(s);
+        let mut command = match provider.command(
+            &self.binary_path(provider.as_ref()),
+            &self.config_dir,
+            &params,
+        ) {
+            Ok(command) => command,
+            Err(e) => {
+                *tunnel.status.lock().unwrap() = BridgeStatus::Error(e.to_string());
+                return Err(e);
             }
-        }
+        };
 
         let mut child = command
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .spawn()
-            .context("Failed to start bore")?;
+            .with_context(|| format!("Failed to start {}", provider.name()))?;
 
         let stdout = child.stdout.take();
         let stderr = child.stderr.take();
 
         // Store process
-        *self.process.lock().unwrap() = Some(child);
+        *tunnel.process.lock().unwrap() = Some(child);
 
-        // bore outputs to stderr, so monitor both
-        let status_arc = Arc::clone(&self.status);
+        // Most clients log to stderr, so monitor both
+        let status_arc = Arc::clone(&tunnel.status);
 
         // Monitor stderr (main output)
         if let Some(stderr) = stderr {
             let status_clone = Arc::clone(&status_arc);
+            let provider = Arc::clone(&provider);
+            let params = params.clone();
             thread::spawn(move || {
                 println!("[Prismarine Bridge] Stderr monitor thread started");
                 let reader = BufReader::new(stderr);
                 for line in reader.lines() {
                     if let Ok(line) = line {
-                        println!("[bore stderr] {}", line);
-                        parse_bore_output(&line, &status_clone);
+                        println!("[{} stderr] {}", provider.name(), line);
+                        apply_output(provider.as_ref(), &line, &params, &status_clone);
                     }
                 }
                 println!("[Prismarine Bridge] Stderr monitor thread ended");
@@ -161,13 +358,14 @@ impl PrismarineBridge {
         // Monitor stdout
         if let Some(stdout) = stdout {
             let status_clone = Arc::clone(&status_arc);
+            let provider = Arc::clone(&provider);
             thread::spawn(move || {
                 println!("[Prismarine Bridge] Stdout monitor thread started");
                 let reader = BufReader::new(stdout);
                 for line in reader.lines() {
                     if let Ok(line) = line {
-                        println!("[bore stdout] {}", line);
-                        parse_bore_output(&line, &status_clone);
+                        println!("[{} stdout] {}", provider.name(), line);
+                        apply_output(provider.as_ref(), &line, &params, &status_clone);
                     }
                 }
                 println!("[Prismarine Bridge] Stdout monitor thread ended");
@@ -177,80 +375,182 @@ impl PrismarineBridge {
         Ok(())
     }
 
-    /// Stop the bridge
+    /// Stop the default tunnel
     pub fn stop(&self) -> Result<()> {
-        if let Some(mut child) = self.process.lock().unwrap().take() {
-            println!("[Prismarine Bridge] Stopping bore");
-            let _ = child.kill();
-        }
-        *self.status.lock().unwrap() = BridgeStatus::Stopped;
+        self.tunnel(DEFAULT_TUNNEL).stop();
         Ok(())
     }
 
-    /// Check if running
-    pub fn is_running(&self) -> bool {
-        self.process.lock().unwrap().is_some()
+    /// Stop and forget a server tunnel (server id or `bedrock_key`)
+    pub fn stop_for_server(&self, key: &str) {
+        if let Some(tunnel) = self.tunnels.lock().unwrap().remove(key) {
+            tunnel.stop();
+        }
     }
 
-    /// Get current status
-    pub fn get_status(&self) -> BridgeStatus {
-        self.status.lock().unwrap().clone()
+    /// Stop every tunnel, and tunnel clients a crashed session left running
+    /// from the bridge folder. Returns how many leftover processes were killed.
+    pub fn stop_all(&self) -> usize {
+        let tunnels: Vec<Arc<Tunnel>> = self
+            .tunnels
+            .lock()
+            .unwrap()
+            .drain()
+            .map(|(_, t)| t)
+            .collect();
+        for tunnel in tunnels {
+            tunnel.stop();
+        }
+
+        let mut system = sysinfo::System::new();
+        system.refresh_processes(sysinfo::ProcessesToUpdate::All, true);
+        let mut killed = 0;
+        for process in system.processes().values() {
+            if process
+                .exe()
+                .is_some_and(|exe| exe.starts_with(&self.config_dir))
+                && process.kill()
+            {
+                println!(
+                    "[Prismarine Bridge] Killed leftover tunnel client {:?}",
+                    process.name()
+                );
+                killed += 1;
+            }
+        }
+        killed
     }
-}
 
-// Parse bore output to find connection address
-fn parse_bore_output(line: &str, status: &Arc<Mutex<BridgeStatus>>) {
-    let mut status = status.lock().unwrap();
+    /// Re-establish every tunnel with its last parameters (e.g. after system resume).
+    /// Returns false if no tunnel was started.
+    pub fn reconnect(&self) -> Result<bool> {
+        let keys: Vec<String> = self.tunnels.lock().unwrap().keys().cloned().collect();
+        let mut reconnected = false;
+        for key in keys {
+            reconnected |= self.reconnect_tunnel(&key)?;
+        }
+        Ok(reconnected)
+    }
+
+    fn reconnect_tunnel(&self, key: &str) -> Result<bool> {
+        let tunnel = self.tunnel(key);
+        let last_start = tunnel.last_start.lock().unwrap().clone();
+        let Some((kind, params)) = last_start else {
+            return Ok(false);
+        };
+
+        // The old connection is usually dead even if the process is still alive
+        println!(
+            "[Prismarine Bridge] Reconnecting tunnel for port {}",
+            params.port
+        );
+        tunnel.kill_process();
+        self.start_tunnel(key, kind, params)?;
+        Ok(true)
+    }
 
-    // bore outputs: "listening at bore.pub:XXXXX"
-    if line.to_lowercase().contains("listening") && line.contains("bore.pub") {
-        // Extract the address
-        if let Some(addr) = extract_bore_address(line) {
-            println!("[Prismarine Bridge] Found bore address: {}", addr);
-            *status = BridgeStatus::Connected(addr);
-            return;
+    /// Keep server tunnels in line with the servers: `wanted` are the tunnels of
+    /// the running servers. Tunnels of stopped servers are closed, missing or
+    /// crashed ones (re)started, and tunnels whose port changed are restarted.
+    pub async fn sync_server_tunnels(&self, wanted: &[TunnelTarget]) {
+        let running: Vec<String> = self
+            .tunnels
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|key| key.as_str() != DEFAULT_TUNNEL)
+            .cloned()
+            .collect();
+        for key in running {
+            if !wanted.iter().any(|target| target.key == key) {
+                println!(
+                    "[Prismarine Bridge] Closing tunnel of stopped server {}",
+                    key
+                );
+                self.stop_for_server(&key);
+            }
+        }
+
+        for target in wanted {
+            let tunnel = self.tunnel(&target.key);
+            let current = tunnel.last_start.lock().unwrap().clone();
+            let up_to_date = matches!(
+                &current,
+                Some((kind, p)) if *kind == target.kind
+                    && p.port == target.port
+                    && p.remote_server == target.remote_server
+                    && p.udp == target.udp
+            );
+            if up_to_date && !tunnel.has_exited() {
+                continue;
+            }
+            if let Err(e) = self.start_for_server(target).await {
+                println!(
+                    "[Prismarine Bridge] Failed to start tunnel for {}: {}",
+                    target.key, e
+                );
+                *tunnel.status.lock().unwrap() = BridgeStatus::Error(e.to_string());
+            }
         }
     }
 
-    // Check for errors
-    if line.to_lowercase().contains("error") {
-        *status = BridgeStatus::Error(line.to_string());
-        return;
+    /// Local port the default tunnel forwards to, if started
+    pub fn local_port(&self) -> Option<u16> {
+        self.tunnel(DEFAULT_TUNNEL)
+            .last_start
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|(_, params)| params.port)
     }
 
-    // If we see any output and still starting, mark as running
-    if matches!(*status, BridgeStatus::Starting) {
-        *status = BridgeStatus::Running;
+    /// Check if the default tunnel is running
+    pub fn is_running(&self) -> bool {
+        self.tunnel(DEFAULT_TUNNEL)
+            .process
+            .lock()
+            .unwrap()
+            .is_some()
     }
-}
 
-// Extract bore.pub:PORT from output
-fn extract_bore_address(text: &str) -> Option<String> {
-    // Look for pattern like "bore.pub:XXXXX"
-    for word in text.split_whitespace() {
-        if word.contains("bore.pub:") {
-            let clean = word.trim_matches(|c| c == '"' || c == '\'' || c == ',' || c == '.');
-            return Some(clean.to_string());
+    /// Status of a server tunnel (server id or `bedrock_key`), or of the default tunnel
+    pub fn get_status(&self, key: Option<&str>) -> BridgeStatus {
+        let key = key.unwrap_or(DEFAULT_TUNNEL);
+        match self.tunnels.lock().unwrap().get(key) {
+            Some(tunnel) => tunnel.status.lock().unwrap().clone(),
+            None => BridgeStatus::Stopped,
         }
     }
+}
 
-    // Alternative: look for "at" keyword
-    if let Some(at_idx) = text.find(" at ") {
-        let rest = &text[at_idx + 4..];
-        let addr = rest.split_whitespace().next()?;
-        if addr.contains("bore.pub") {
-            return Some(addr.trim().to_string());
+// Update the status from a line of client output
+fn apply_output(
+    provider: &dyn TunnelProvider,
+    line: &str,
+    params: &TunnelParams,
+    status: &Arc<Mutex<BridgeStatus>>,
+) {
+    let mut status = status.lock().unwrap();
+
+    if let Some(new_status) = provider.parse_output(line, params) {
+        if let BridgeStatus::Connected(addr) = &new_status {
+            println!("[Prismarine Bridge] Found tunnel address: {}", addr);
         }
+        *status = new_status;
+        return;
     }
 
-    None
+    // If we see any output and still starting, mark as running
+    if matches!(*status, BridgeStatus::Starting) {
+        *status = BridgeStatus::Running;
+    }
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
 pub enum BridgeStatus {
     /// Not started
     Stopped,
-    /// Downloading bore binary
+    /// Downloading the tunnel client
     Downloading,
     /// Starting up
     Starting,
@@ -258,6 +558,10 @@ pub enum BridgeStatus {
     Running,
     /// Connected with tunnel address
     Connected(String),
+    /// The user has to open this URL first (playit.gg agent claim)
+    ActionRequired(String),
+    /// Up, but players connect through `cloudflared access tcp` to this hostname
+    AccessRequired(String),
     /// Error occurred
     Error(String),
 }
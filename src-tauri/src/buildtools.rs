@@ -0,0 +1,252 @@
+// Spigot BuildTools
+// BuildTools clones Spigot's repositories with git and compiles them with the
+// Java it runs on, so both are checked before a build. On Windows a portable
+// MinGit can be downloaded into the app data directory when git is missing.
+// Failed builds are matched against the usual causes for a readable error.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildToolsCheck {
+    pub mc_version: String,
+    pub git_version: Option<String>,
+    pub git_portable: bool,           // Found in the portable MinGit directory
+    pub portable_git_supported: bool, // MinGit can be downloaded (Windows only)
+    pub java_path: Option<String>,    // None: the recommended Java is downloaded on build
+    pub java_version: Option<u8>,
+    pub java_required: u8,
+    pub java_max: Option<u8>,
+    pub problems: Vec<String>,
+}
+
+/// Directory of the portable MinGit
+pub fn portable_git_dir() -> PathBuf {
+    crate::paths::get().data_dir.join("git")
+}
+
+fn git_version(git: &Path) -> Option<String> {
+    let output = Command::new(git).arg("--version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    // "git version 2.47.1.windows.1"
+    let version = String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .trim_start_matches("git version ")
+        .to_string();
+    Some(version)
+}
+
+/// Installed git version and whether it is the portable one
+pub fn find_git() -> Option<(String, bool)> {
+    if let Some(version) = git_version(Path::new("git")) {
+        return Some((version, false));
+    }
+    let portable = portable_git_dir().join("cmd").join("git.exe");
+    git_version(&portable).map(|version| (version, true))
+}
+
+/// PATH for the BuildTools process, with the portable MinGit in front when it is installed
+pub fn path_with_portable_git() -> Option<OsString> {
+    let cmd = portable_git_dir().join("cmd");
+    if !cmd.exists() {
+        return None;
+    }
+    let mut paths = vec![cmd];
+    if let Some(path) = std::env::var_os("PATH") {
+        paths.extend(std::env::split_paths(&path));
+    }
+    std::env::join_paths(paths).ok()
+}
+
+/// Check git and the Java BuildTools would run on for `mc_version`
+pub fn check(mc_version: &str) -> BuildToolsCheck {
+    let recommendation = crate::java_manager::JavaManager::new().recommended(mc_version, false);
+    let git = find_git();
+    let java_version = recommendation
+        .installed_path
+        .as_deref()
+        .and_then(crate::java_detector::get_java_version);
+
+    let mut problems = Vec::new();
+    if git.is_none() {
+        problems.push(if cfg!(target_os = "windows") {
+            "Git is not installed. Install Git for Windows or download the portable Git".to_string()
+        } else if cfg!(target_os = "macos") {
+            "Git is not installed. Run `xcode-select --install` or install git with Homebrew"
+                .to_string()
+        } else {
+            "Git is not installed. Install it with your package manager (e.g. `sudo apt install git`)"
+                .to_string()
+        });
+    }
+    if let (Some(path), None) = (&recommendation.installed_path, java_version) {
+        problems.push(format!("Java at {} could not be run", path));
+    }
+
+    BuildToolsCheck {
+        mc_version: mc_version.to_string(),
+        git_version: git.as_ref().map(|(version, _)| version.clone()),
+        git_portable: git.as_ref().is_some_and(|(_, portable)| *portable),
+        portable_git_supported: cfg!(target_os = "windows"),
+        java_path: recommendation.installed_path,
+        java_version,
+        java_required: recommendation.required,
+        java_max: recommendation.max,
+        problems,
+    }
+}
+
+/// Whether BuildTools accepts Java `version` for `mc_version`
+pub fn java_supported(mc_version: &str, version: u8) -> Result<()> {
+    let requirements = crate::versions::requirements(mc_version);
+    if version < requirements.min_java {
+        anyhow::bail!(
+            "BuildTools needs Java {} or newer to build Spigot {}, but Java {} was selected",
+            requirements.min_java,
+            mc_version,
+            version
+        );
+    }
+    if let Some(max) = requirements.max_java.filter(|max| version > *max) {
+        anyhow::bail!(
+            "BuildTools cannot build Spigot {} with Java {}, use Java {} - {}",
+            mc_version,
+            version,
+            requirements.min_java,
+            max
+        );
+    }
+    Ok(())
+}
+
+/// Download and extract the latest 64-bit MinGit from Git for Windows
+pub async fn install_portable_git() -> Result<String> {
+    crate::offline::ensure_online("Downloading portable Git")?;
+    if !cfg!(target_os = "windows") {
+        anyhow::bail!(
+            "Portable Git is only available on Windows, install git with your package manager"
+        );
+    }
+
+    let client = reqwest::Client::builder()
+        .user_agent("MinecraftServerManager/0.1.0")
+        .build()?;
+    let release: serde_json::Value = client
+        .get("https://api.github.com/repos/git-for-windows/git/releases/latest")
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let asset = release["assets"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|asset| {
+            asset["name"].as_str().is_some_and(|name| {
+                name.starts_with("MinGit-")
+                    && name.ends_with("-64-bit.zip")
+                    && !name.contains("busybox")
+            })
+        })
+        .context("No MinGit package found in the latest Git for Windows release")?;
+    let url = asset["browser_download_url"]
+        .as_str()
+        .context("MinGit download URL missing")?;
+    let checksum = asset["digest"]
+        .as_str()
+        .and_then(|digest| digest.strip_prefix("sha256:"))
+        .map(str::to_string);
+
+    let dir = portable_git_dir();
+    let parent = dir
+        .parent()
+        .context("Invalid data directory")?
+        .to_path_buf();
+    tokio::fs::create_dir_all(&parent).await?;
+    let archive = parent.join("mingit.zip");
+    println!("[BuildTools] Downloading {}", url);
+    crate::download::download_file(&client, &[url], &archive, "portable-git", None)
+        .await
+        .context("Failed to download portable Git")?;
+
+    tokio::task::spawn_blocking(move || {
+        let staging = parent.join("git.tmp");
+        let _ = std::fs::remove_dir_all(&staging);
+        let extracted = match &checksum {
+            Some(checksum) => crate::java_manager::verify_sha256(&archive, checksum),
+            None => Ok(()),
+        }
+        .and_then(|()| extract_zip(&archive, &staging));
+        let _ = std::fs::remove_file(&archive);
+        if let Err(e) = extracted {
+            let _ = std::fs::remove_dir_all(&staging);
+            return Err(e.context("Failed to extract portable Git"));
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::rename(&staging, &dir)?;
+        Ok(())
+    })
+    .await??;
+
+    let (version, _) = find_git().context("Portable Git was installed but does not run")?;
+    println!("[BuildTools] Portable Git {} installed", version);
+    Ok(version)
+}
+
+/// MinGit has no top-level directory, entries are extracted as they are
+fn extract_zip(archive: &Path, dest: &Path) -> Result<()> {
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(archive)?)?;
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let Some(path) = file.enclosed_name().map(|name| dest.join(name)) else {
+            continue;
+        };
+        if file.is_dir() {
+            std::fs::create_dir_all(&path)?;
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::io::copy(&mut file, &mut std::fs::File::create(&path)?)?;
+    }
+    Ok(())
+}
+
+/// Known BuildTools failures, matched against its output
+const FAILURES: &[(&str, &str)] = &[
+    ("Could not successfully run git", "Git could not be run. Check that git is installed and on PATH"),
+    ("Cannot run program \"git\"", "Git is not installed or not on PATH"),
+    ("requires Java versions between", "The selected Java version cannot build this Spigot version"),
+    ("Could not get version", "BuildTools does not know this version, Spigot may not support it"),
+    ("FileNotFoundException: https://hub.spigotmc.org/versions/", "BuildTools does not know this version, Spigot may not support it"),
+    ("Please do not run BuildTools in a Dropbox", "BuildTools refuses to run inside a cloud-synced folder (OneDrive, Dropbox). Move the servers folder elsewhere"),
+    ("OutOfMemoryError", "BuildTools ran out of memory"),
+    ("No space left on device", "The disk is full"),
+    ("There is not enough space on the disk", "The disk is full"),
+    ("Filename too long", "A path is too long for Windows. Move the servers folder closer to the drive root"),
+    ("UnknownHostException", "Could not reach hub.spigotmc.org. Check the internet connection"),
+    ("Connection timed out", "The connection to hub.spigotmc.org timed out"),
+    ("SSLHandshakeException", "A secure connection to hub.spigotmc.org failed. A proxy or antivirus may be intercepting HTTPS"),
+];
+
+/// Readable cause of a failed build, with the last lines of the output
+pub fn explain_failure(output: &str) -> String {
+    let cause = FAILURES
+        .iter()
+        .find(|(pattern, _)| output.contains(pattern))
+        .map(|(_, message)| *message)
+        .unwrap_or("BuildTools failed");
+    let lines: Vec<&str> = output
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    let tail = lines[lines.len().saturating_sub(10)..].join("\n");
+    format!("{}\n{}", cause, tail)
+}
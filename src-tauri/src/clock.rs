@@ -0,0 +1,9 @@
+// Clock
+// Wall-clock time as Unix seconds, the form timestamps are saved in.
+
+pub fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
@@ -1,52 +1,82 @@
-use crate::server_manager::ServerInfo;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tokio::fs;
 
+/// Application wide settings (servers themselves are stored in config.json)
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[allow(dead_code)]
-pub struct AppConfig {
-    pub servers: Vec<ServerInfo>,
-    pub base_path: PathBuf,
-    pub auto_open_ports: bool,
+pub struct AppSettings {
+    /// Where new servers are created; already registered servers keep their path
+    #[serde(default = "default_servers_dir")]
+    pub servers_dir: PathBuf,
+    /// What closing the window does while servers are running
+    #[serde(default)]
+    pub exit_policy: ExitPolicy,
+    /// Skip everything that needs the internet (LAN play without a connection)
+    #[serde(default)]
+    pub offline_mode: bool,
+    #[serde(default)]
+    pub limits: crate::limits::ConcurrencyLimits,
+    /// Data, cache and log folders, applied on the next start (see paths.rs)
+    #[serde(default)]
+    pub paths: crate::paths::PathOverrides,
+    #[serde(default)]
+    pub rest_api: crate::rest_api::RestApiSettings,
+    /// Pause before each server started at launch (see `ServerInfo::autostart`)
+    #[serde(default = "default_autostart_delay")]
+    pub autostart_delay_secs: u64,
 }
 
-impl AppConfig {
-    #[allow(dead_code)]
-    pub fn new(base_path: PathBuf) -> Self {
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ExitPolicy {
+    #[default]
+    Ask,
+    Stop,   // Stop every running server, then exit
+    Detach, // Leave them running, the next launch reattaches them
+}
+
+fn default_autostart_delay() -> u64 {
+    10
+}
+
+pub fn default_servers_dir() -> PathBuf {
+    // Before settings are read, so from the portable folder and environment only
+    crate::paths::AppPaths::from_env().default_servers_dir()
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
         Self {
-            servers: Vec::new(),
-            base_path,
-            auto_open_ports: true,
+            servers_dir: default_servers_dir(),
+            exit_policy: ExitPolicy::default(),
+            offline_mode: false,
+            limits: Default::default(),
+            paths: Default::default(),
+            rest_api: Default::default(),
+            autostart_delay_secs: default_autostart_delay(),
         }
     }
+}
 
-    #[allow(dead_code)]
-    pub async fn load(config_path: &PathBuf) -> Result<Self> {
-        if config_path.exists() {
-            let content = fs::read_to_string(config_path).await?;
-            let config: AppConfig = serde_json::from_str(&content)?;
-            Ok(config)
+impl AppSettings {
+    pub async fn load(settings_path: &Path) -> Result<Self> {
+        if settings_path.exists() {
+            let content = fs::read_to_string(settings_path).await?;
+            let settings: AppSettings = serde_json::from_str(&content)?;
+            Ok(settings)
         } else {
-            let default_path = dirs::data_local_dir()
-                .unwrap_or_else(|| PathBuf::from("."))
-                .join("MinecraftServerManager")
-                .join("servers");
-
-            Ok(Self::new(default_path))
+            Ok(Self::default())
         }
     }
 
-    #[allow(dead_code)]
-    pub async fn save(&self, config_path: &PathBuf) -> Result<()> {
+    pub async fn save(&self, settings_path: &Path) -> Result<()> {
         let content = serde_json::to_string_pretty(self)?;
 
-        if let Some(parent) = config_path.parent() {
+        if let Some(parent) = settings_path.parent() {
             fs::create_dir_all(parent).await?;
         }
 
-        fs::write(config_path, content).await?;
+        fs::write(settings_path, content).await?;
         Ok(())
     }
 }
@@ -0,0 +1,244 @@
+// Console bridge
+// Opt-in RCON-compatible TCP listener per server, bound to localhost and
+// proxied to the manager's console channel. Third-party tools (mcrcon scripts,
+// Discord bots) can send commands without the server's real RCON being exposed.
+
+use crate::error::OrNotFound;
+use crate::monitor::Monitor;
+use crate::server_manager::{ConsoleBridgeConfig, ServerManager};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+// RCON packet types
+const PACKET_RESPONSE: i32 = 0;
+const PACKET_COMMAND: i32 = 2;
+const PACKET_AUTH_RESPONSE: i32 = 2;
+const PACKET_LOGIN: i32 = 3;
+
+/// Largest packet accepted from clients (id + type + body + 2 null bytes)
+const MAX_PACKET_SIZE: i32 = 4096 + 10;
+/// Largest body per response packet, longer output is split
+const MAX_RESPONSE_BODY: usize = 4096;
+/// Time the server gets to write the command output to its log
+const OUTPUT_WAIT_MS: u64 = 300;
+/// Pause after a failed accept (out of file handles...), doubled up to the max
+const ACCEPT_BACKOFF_MIN: Duration = Duration::from_millis(50);
+const ACCEPT_BACKOFF_MAX: Duration = Duration::from_secs(5);
+
+pub struct ConsoleBridge {
+    listeners: Mutex<HashMap<String, JoinHandle<()>>>,
+}
+
+impl ConsoleBridge {
+    pub fn new() -> Self {
+        Self {
+            listeners: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// (Re)start the listener of a server, or stop it when the config is disabled
+    pub async fn apply(
+        &self,
+        manager: ServerManager,
+        server_id: &str,
+        config: &ConsoleBridgeConfig,
+    ) -> Result<()> {
+        self.stop(server_id);
+        if !config.enabled {
+            return Ok(());
+        }
+        if config.password.is_empty() {
+            anyhow::bail!("No console bridge password is stored, set one again");
+        }
+
+        let listener = TcpListener::bind(("127.0.0.1", config.port))
+            .await
+            .with_context(|| format!("Failed to listen on 127.0.0.1:{}", config.port))?;
+        println!(
+            "[ConsoleBridge] Listening on 127.0.0.1:{} for {}",
+            config.port, server_id
+        );
+
+        let id = server_id.to_string();
+        let password = config.password.clone();
+        let handle = tokio::spawn(async move {
+            let mut backoff = ACCEPT_BACKOFF_MIN;
+            loop {
+                let (stream, peer) = match listener.accept().await {
+                    Ok(accepted) => {
+                        backoff = ACCEPT_BACKOFF_MIN;
+                        accepted
+                    }
+                    Err(e) => {
+                        println!(
+                            "[ConsoleBridge] Accept failed, retrying in {:?}: {}",
+                            backoff, e
+                        );
+                        tokio::time::sleep(backoff).await;
+                        backoff = (backoff * 2).min(ACCEPT_BACKOFF_MAX);
+                        continue;
+                    }
+                };
+                let manager = manager.clone();
+                let id = id.clone();
+                let password = password.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_client(stream, &manager, &id, &password).await {
+                        println!("[ConsoleBridge] Connection from {} closed: {}", peer, e);
+                    }
+                });
+            }
+        });
+
+        self.listeners
+            .lock()
+            .unwrap()
+            .insert(server_id.to_string(), handle);
+        Ok(())
+    }
+
+    pub fn stop(&self, server_id: &str) {
+        if let Some(handle) = self.listeners.lock().unwrap().remove(server_id) {
+            handle.abort();
+            println!("[ConsoleBridge] Stopped listener for {}", server_id);
+        }
+    }
+}
+
+async fn handle_client(
+    mut stream: TcpStream,
+    manager: &ServerManager,
+    server_id: &str,
+    password: &str,
+) -> Result<()> {
+    let mut authenticated = false;
+
+    while let Some((request_id, packet_type, body)) = read_packet(&mut stream).await? {
+        match packet_type {
+            PACKET_LOGIN => {
+                if body == password {
+                    authenticated = true;
+                    write_packet(&mut stream, request_id, PACKET_AUTH_RESPONSE, "").await?;
+                } else {
+                    write_packet(&mut stream, -1, PACKET_AUTH_RESPONSE, "").await?;
+                    anyhow::bail!("Authentication failed");
+                }
+            }
+            PACKET_COMMAND if authenticated => {
+                let output = match run_command(manager, server_id, &body).await {
+                    Ok(output) => output,
+                    Err(e) => e.to_string(),
+                };
+                for chunk in split_body(&output) {
+                    write_packet(&mut stream, request_id, PACKET_RESPONSE, chunk).await?;
+                }
+            }
+            _ => anyhow::bail!("Unexpected packet type {}", packet_type),
+        }
+    }
+
+    Ok(())
+}
+
+/// Send a command through the console and collect the lines it wrote to the log
+async fn run_command(manager: &ServerManager, server_id: &str, command: &str) -> Result<String> {
+    let server = manager
+        .get_server(server_id)
+        .await
+        .or_not_found("Server not found")?;
+    let log_start = Monitor::log_position(&server.path).await;
+
+    // Console safety mode applies; external tools cannot confirm dangerous commands
+    manager
+        .send_console_command(server_id, command, false)
+        .await?;
+    tokio::time::sleep(Duration::from_millis(OUTPUT_WAIT_MS)).await;
+
+    Ok(Monitor::read_log_since(&server.path, log_start)
+        .await
+        .join("\n"))
+}
+
+/// Run one command over a server's own RCON and return its reply
+pub async fn rcon_command(port: u16, password: &str, command: &str) -> Result<String> {
+    let mut stream = tokio::time::timeout(
+        Duration::from_secs(5),
+        TcpStream::connect(("127.0.0.1", port)),
+    )
+    .await
+    .context("RCON connection timed out")?
+    .with_context(|| format!("Failed to connect to RCON on port {}", port))?;
+
+    write_packet(&mut stream, 1, PACKET_LOGIN, password).await?;
+    match tokio::time::timeout(Duration::from_secs(5), read_packet(&mut stream)).await?? {
+        Some((1, PACKET_AUTH_RESPONSE, _)) => {}
+        Some(_) => anyhow::bail!("RCON authentication failed"),
+        None => anyhow::bail!("RCON connection closed"),
+    }
+
+    write_packet(&mut stream, 2, PACKET_COMMAND, command).await?;
+    match tokio::time::timeout(Duration::from_secs(5), read_packet(&mut stream)).await?? {
+        Some((_, PACKET_RESPONSE, body)) => Ok(body),
+        Some((_, packet_type, _)) => anyhow::bail!("Unexpected RCON packet type {}", packet_type),
+        None => anyhow::bail!("RCON connection closed"),
+    }
+}
+
+/// Split a response body on char boundaries into RCON sized chunks
+fn split_body(body: &str) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut rest = body;
+    while rest.len() > MAX_RESPONSE_BODY {
+        let mut end = MAX_RESPONSE_BODY;
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        chunks.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+    chunks.push(rest);
+    chunks
+}
+
+/// Read one packet, or None when the client closed the connection
+async fn read_packet(stream: &mut TcpStream) -> Result<Option<(i32, i32, String)>> {
+    let length = match stream.read_i32_le().await {
+        Ok(length) => length,
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    if !(10..=MAX_PACKET_SIZE).contains(&length) {
+        anyhow::bail!("Invalid packet length {}", length);
+    }
+
+    let mut payload = vec![0u8; length as usize];
+    stream.read_exact(&mut payload).await?;
+
+    let request_id = i32::from_le_bytes(payload[0..4].try_into()?);
+    let packet_type = i32::from_le_bytes(payload[4..8].try_into()?);
+    // Body is followed by two null bytes
+    let body = String::from_utf8_lossy(&payload[8..payload.len() - 2]).to_string();
+
+    Ok(Some((request_id, packet_type, body)))
+}
+
+async fn write_packet(
+    stream: &mut TcpStream,
+    request_id: i32,
+    packet_type: i32,
+    body: &str,
+) -> Result<()> {
+    let mut packet = Vec::with_capacity(body.len() + 14);
+    packet.extend_from_slice(&(body.len() as i32 + 10).to_le_bytes());
+    packet.extend_from_slice(&request_id.to_le_bytes());
+    packet.extend_from_slice(&packet_type.to_le_bytes());
+    packet.extend_from_slice(body.as_bytes());
+    packet.extend_from_slice(&[0, 0]);
+    stream.write_all(&packet).await?;
+    Ok(())
+}
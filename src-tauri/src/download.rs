@@ -0,0 +1,380 @@
+// Streaming downloads
+// Writes HTTP responses to disk chunk by chunk instead of buffering them in
+// memory, and reports progress so the UI can show progress bars.
+// Failed transfers are retried with backoff and resumed with HTTP range
+// requests, then fall back to mirror URLs. A ".part.json" next to the partial
+// file remembers its URL and validators, so the next run continues it with
+// If-Range instead of starting over.
+
+use crate::error::PrismarineError;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+/// Minimum bytes between two progress reports for the same download
+const PROGRESS_STEP: u64 = 256 * 1024;
+/// Attempts per URL before moving on to the next mirror
+const MAX_ATTEMPTS: u32 = 4;
+/// Delay before the first retry, doubled for every further attempt
+const RETRY_BASE_DELAY_MS: u64 = 1000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadProgress {
+    pub task_id: String, // Stable for the whole download, e.g. "server-jar:1.21.1"
+    pub downloaded: u64,
+    pub total: Option<u64>, // None when the server sends no Content-Length
+    pub done: bool,
+}
+
+type ProgressHandler = Box<dyn Fn(DownloadProgress) + Send + Sync>;
+
+static PROGRESS_HANDLER: OnceLock<ProgressHandler> = OnceLock::new();
+
+/// Register the receiver of progress reports (the app forwards them as Tauri events)
+pub fn set_progress_handler<F>(handler: F)
+where
+    F: Fn(DownloadProgress) + Send + Sync + 'static,
+{
+    let _ = PROGRESS_HANDLER.set(Box::new(handler));
+}
+
+fn report(progress: DownloadProgress) {
+    if let Some(handler) = PROGRESS_HANDLER.get() {
+        handler(progress);
+    }
+}
+
+/// Files every plugin or mod jar contains one of (Bukkit/Paper, BungeeCord,
+/// Velocity, Forge/NeoForge, legacy Forge, Fabric, Quilt)
+const PLUGIN_DESCRIPTORS: &[&str] = &[
+    "plugin.yml",
+    "paper-plugin.yml",
+    "bungee.yml",
+    "velocity-plugin.json",
+    "META-INF/mods.toml",
+    "META-INF/neoforge.mods.toml",
+    "mcmod.info",
+    "fabric.mod.json",
+    "quilt.mod.json",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JarKind {
+    /// Any runnable jar (server jars, installers, tools)
+    Executable,
+    /// A plugin or mod that must carry a descriptor
+    Plugin,
+}
+
+/// Reject files that are not the expected jar, such as HTML error or
+/// "external download" pages served with a 200 status.
+pub fn validate_jar(path: &Path, kind: JarKind) -> Result<()> {
+    let mut file = std::fs::File::open(path)?;
+    let mut magic = [0u8; 4];
+    let read = std::io::Read::read(&mut file, &mut magic)?;
+    if read < 4 || magic != *b"PK\x03\x04" {
+        if magic.first() == Some(&b'<') {
+            anyhow::bail!("Received a web page instead of a jar file");
+        }
+        anyhow::bail!("Downloaded file is not a jar");
+    }
+
+    let archive =
+        zip::ZipArchive::new(std::fs::File::open(path)?).context("Downloaded jar is corrupted")?;
+    let has = |name: &str| archive.index_for_name(name).is_some();
+
+    match kind {
+        JarKind::Executable if !has("META-INF/MANIFEST.MF") => {
+            anyhow::bail!("Downloaded jar has no manifest")
+        }
+        JarKind::Plugin if !PLUGIN_DESCRIPTORS.iter().any(|d| has(d)) => {
+            anyhow::bail!("Downloaded jar is not a plugin or mod (no plugin.yml, mods.toml or fabric.mod.json)")
+        }
+        _ => Ok(()),
+    }
+}
+
+/// SHA-1 of a file as lowercase hex
+pub fn sha1_hex(path: &Path) -> Result<String> {
+    use sha1::{Digest, Sha1};
+
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha1::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+/// Where a partial download came from, stored next to it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PartInfo {
+    url: String,
+    #[serde(default)]
+    etag: Option<String>,
+    #[serde(default)]
+    last_modified: Option<String>,
+}
+
+impl PartInfo {
+    /// If-Range value; weak ETags cannot be used for ranges
+    fn validator(&self) -> Option<&str> {
+        self.etag
+            .as_deref()
+            .filter(|etag| !etag.starts_with("W/"))
+            .or(self.last_modified.as_deref())
+    }
+}
+
+async fn read_part_info(part_path: &Path) -> Option<PartInfo> {
+    let content = tokio::fs::read_to_string(part_info_path(part_path))
+        .await
+        .ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+async fn discard_part(part_path: &Path) {
+    let _ = tokio::fs::remove_file(part_path).await;
+    let _ = tokio::fs::remove_file(part_info_path(part_path)).await;
+}
+
+/// Outcome of a failed attempt
+enum AttemptError {
+    /// Worth retrying (network error, 5xx, truncated body)
+    Retry(anyhow::Error),
+    /// Retrying the same URL will not help (404 and other client errors)
+    GiveUp(anyhow::Error),
+}
+
+impl From<anyhow::Error> for AttemptError {
+    fn from(e: anyhow::Error) -> Self {
+        AttemptError::Retry(e)
+    }
+}
+
+impl From<std::io::Error> for AttemptError {
+    fn from(e: std::io::Error) -> Self {
+        AttemptError::Retry(e.into())
+    }
+}
+
+/// Download into `dest`, trying each URL in order (the first is the primary
+/// source, the rest are mirrors). Data goes to a ".part" file first, so an
+/// interrupted download never leaves a truncated file behind; a ".part" left
+/// by an earlier run is continued, starting with the URL it came from.
+/// With `jar_kind` set, a response that is not a valid jar counts as a failure of that URL.
+pub async fn download_file(
+    client: &reqwest::Client,
+    urls: &[&str],
+    dest: &Path,
+    task_id: &str,
+    jar_kind: Option<JarKind>,
+) -> Result<()> {
+    crate::offline::ensure_online("Downloading")?;
+    let _slot = crate::limits::DOWNLOADS.acquire().await;
+    let part_path = part_path(dest);
+    let mut urls = urls.to_vec();
+    if let Some(info) = read_part_info(&part_path).await {
+        if let Some(i) = urls.iter().position(|url| *url == info.url) {
+            let url = urls.remove(i);
+            urls.insert(0, url);
+        }
+    }
+
+    let mut last_error = anyhow::anyhow!("No download URL given");
+    for url in urls {
+        for attempt in 1..=MAX_ATTEMPTS {
+            if attempt > 1 {
+                let delay = RETRY_BASE_DELAY_MS * 2u64.pow(attempt - 2);
+                println!(
+                    "[Download] Retrying {} in {}ms (attempt {}/{})",
+                    url, delay, attempt, MAX_ATTEMPTS
+                );
+                tokio::time::sleep(Duration::from_millis(delay)).await;
+            }
+
+            match fetch(client, url, &part_path, task_id).await {
+                Ok(downloaded) => {
+                    if let Some(kind) = jar_kind {
+                        if let Err(e) = validate_jar(&part_path, kind) {
+                            println!("[Download] {} returned an invalid jar: {}", url, e);
+                            discard_part(&part_path).await;
+                            last_error = e;
+                            break;
+                        }
+                    }
+                    tokio::fs::rename(&part_path, dest).await?;
+                    let _ = tokio::fs::remove_file(part_info_path(&part_path)).await;
+                    report(DownloadProgress {
+                        task_id: task_id.to_string(),
+                        downloaded,
+                        total: Some(downloaded),
+                        done: true,
+                    });
+                    return Ok(());
+                }
+                Err(AttemptError::Retry(e)) => {
+                    println!("[Download] {} failed: {}", url, e);
+                    last_error = e;
+                }
+                Err(AttemptError::GiveUp(e)) => {
+                    println!("[Download] {} failed: {}", url, e);
+                    last_error = e;
+                    break;
+                }
+            }
+        }
+    }
+
+    let name = dest.file_name().unwrap_or_default().to_string_lossy();
+    Err(last_error.context(PrismarineError::download(format!(
+        "Failed to download {}",
+        name
+    ))))
+}
+
+/// One attempt: continue `part_path` from its current size when it came from
+/// `url` and the server still has the same file. Returns the final size of the file.
+async fn fetch(
+    client: &reqwest::Client,
+    url: &str,
+    part_path: &Path,
+    task_id: &str,
+) -> std::result::Result<u64, AttemptError> {
+    let mut resume_from = tokio::fs::metadata(part_path)
+        .await
+        .map(|m| m.len())
+        .unwrap_or(0);
+    let info = read_part_info(part_path)
+        .await
+        .filter(|info| info.url == url);
+    let validator = info.as_ref().and_then(|info| info.validator());
+    if resume_from > 0 && validator.is_none() {
+        // From another URL, or nothing to tell whether the file changed since
+        discard_part(part_path).await;
+        resume_from = 0;
+    }
+
+    let mut request = client.get(url);
+    if let Some(validator) = validator.filter(|_| resume_from > 0) {
+        request = request
+            .header("Range", format!("bytes={}-", resume_from))
+            .header("If-Range", validator);
+    }
+    let mut response = request.send().await.map_err(anyhow::Error::from)?;
+
+    let status = response.status();
+    let resuming = resume_from > 0 && status == reqwest::StatusCode::PARTIAL_CONTENT;
+    if !status.is_success() {
+        let error = anyhow::anyhow!("HTTP {}", status);
+        if status == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+            // Stale partial file, start over on the next attempt
+            discard_part(part_path).await;
+            return Err(AttemptError::Retry(error));
+        }
+        let retryable = status.is_server_error()
+            || status == reqwest::StatusCode::REQUEST_TIMEOUT
+            || status == reqwest::StatusCode::TOO_MANY_REQUESTS;
+        return Err(if retryable {
+            AttemptError::Retry(error)
+        } else {
+            AttemptError::GiveUp(error)
+        });
+    }
+
+    // A plain 200 means the server ignored the range, so the file restarts from zero
+    let mut downloaded = if resuming { resume_from } else { 0 };
+    let total = response.content_length().map(|len| len + downloaded);
+    if resuming {
+        println!("[Download] Resuming {} at {} bytes", url, resume_from);
+    } else {
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let info = PartInfo {
+            url: url.to_string(),
+            etag: header("etag"),
+            last_modified: header("last-modified"),
+        };
+        let _ = tokio::fs::write(
+            part_info_path(part_path),
+            serde_json::to_string(&info).unwrap_or_default(),
+        )
+        .await;
+    }
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resuming)
+        .truncate(!resuming)
+        .open(part_path)
+        .await
+        .with_context(|| format!("Failed to create {}", part_path.display()))
+        .map_err(AttemptError::GiveUp)?;
+
+    let mut last_report = downloaded;
+    report(DownloadProgress {
+        task_id: task_id.to_string(),
+        downloaded,
+        total,
+        done: false,
+    });
+
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .context("Download interrupted")
+        .map_err(AttemptError::Retry)?
+    {
+        file.write_all(&chunk).await?;
+        downloaded += chunk.len() as u64;
+
+        if downloaded - last_report >= PROGRESS_STEP {
+            last_report = downloaded;
+            report(DownloadProgress {
+                task_id: task_id.to_string(),
+                downloaded,
+                total,
+                done: false,
+            });
+        }
+    }
+    file.flush().await?;
+
+    if let Some(total) = total {
+        if downloaded < total {
+            return Err(AttemptError::Retry(anyhow::anyhow!(
+                "Connection closed after {} of {} bytes",
+                downloaded,
+                total
+            )));
+        }
+    }
+
+    Ok(downloaded)
+}
+
+/// "server.jar.part" -> "server.jar.part.json"
+fn part_info_path(part_path: &Path) -> PathBuf {
+    let mut path = part_path.as_os_str().to_owned();
+    path.push(".json");
+    PathBuf::from(path)
+}
+
+/// "server.jar" -> "server.jar.part"
+fn part_path(dest: &Path) -> PathBuf {
+    dest.with_extension(match dest.extension() {
+        Some(ext) => format!("{}.part", ext.to_string_lossy()),
+        None => "part".to_string(),
+    })
+}
@@ -0,0 +1,190 @@
+// Command errors
+// Tauri commands return PrismarineError instead of a plain string so the
+// frontend can react to the kind of failure (missing server, port in use,
+// failed download, ...) rather than parsing messages.
+
+use serde::Serialize;
+use std::fmt;
+
+/// Serialized as `{ "code": "PORT_IN_USE", "message": "...", ...context }`
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "code", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PrismarineError {
+    /// A server, world, template, ... that does not exist
+    NotFound { message: String },
+    /// Rejected arguments from the caller
+    InvalidInput { message: String },
+    /// The server is in the wrong state (e.g. must be stopped first)
+    InvalidState { message: String },
+    /// Needs the internet while offline mode is on
+    OfflineMode { message: String },
+    PortInUse {
+        message: String,
+        port: Option<u16>,
+        suggested_port: Option<u16>, // A free port to offer instead
+    },
+    /// The heap would not fit in the machine's RAM next to the running servers
+    InsufficientMemory {
+        message: String,
+        requested_mb: u64,
+        available_mb: u64,         // RAM left for this server
+        suggested_mb: Option<u64>, // A heap size that fits
+    },
+    Download {
+        message: String,
+        causes: Vec<String>,
+    },
+    Network {
+        message: String,
+        causes: Vec<String>,
+    },
+    Io {
+        message: String,
+        causes: Vec<String>,
+    },
+    Internal {
+        message: String,
+        causes: Vec<String>,
+    },
+}
+
+impl PrismarineError {
+    pub fn not_found(message: impl Into<String>) -> Self {
+        PrismarineError::NotFound {
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        PrismarineError::InvalidInput {
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_state(message: impl Into<String>) -> Self {
+        PrismarineError::InvalidState {
+            message: message.into(),
+        }
+    }
+
+    pub fn download(message: impl Into<String>) -> Self {
+        PrismarineError::Download {
+            message: message.into(),
+            causes: Vec::new(),
+        }
+    }
+
+    pub fn port_in_use(port: u16, suggested_port: Option<u16>, message: impl Into<String>) -> Self {
+        PrismarineError::PortInUse {
+            message: message.into(),
+            port: Some(port),
+            suggested_port,
+        }
+    }
+
+    pub fn insufficient_memory(
+        requested_mb: u64,
+        available_mb: u64,
+        suggested_mb: Option<u64>,
+        message: impl Into<String>,
+    ) -> Self {
+        PrismarineError::InsufficientMemory {
+            message: message.into(),
+            requested_mb,
+            available_mb,
+            suggested_mb,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            PrismarineError::NotFound { message }
+            | PrismarineError::InvalidInput { message }
+            | PrismarineError::InvalidState { message }
+            | PrismarineError::OfflineMode { message }
+            | PrismarineError::PortInUse { message, .. }
+            | PrismarineError::InsufficientMemory { message, .. }
+            | PrismarineError::Download { message, .. }
+            | PrismarineError::Network { message, .. }
+            | PrismarineError::Io { message, .. }
+            | PrismarineError::Internal { message, .. } => message,
+        }
+    }
+}
+
+impl fmt::Display for PrismarineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for PrismarineError {}
+
+/// Categorize manager errors: a PrismarineError raised or attached as context
+/// anywhere in the chain wins, then known error types. Everything else is Internal.
+impl From<anyhow::Error> for PrismarineError {
+    fn from(error: anyhow::Error) -> Self {
+        let message = error.to_string();
+        let causes: Vec<String> = error.chain().skip(1).map(|c| c.to_string()).collect();
+
+        let typed = error.downcast_ref::<PrismarineError>().or_else(|| {
+            error
+                .chain()
+                .find_map(|cause| cause.downcast_ref::<PrismarineError>())
+        });
+        if let Some(typed) = typed {
+            // Keep the outer context as the message
+            return match typed.clone() {
+                PrismarineError::PortInUse {
+                    port,
+                    suggested_port,
+                    ..
+                } => PrismarineError::PortInUse {
+                    message,
+                    port,
+                    suggested_port,
+                },
+                PrismarineError::Download { .. } => PrismarineError::Download { message, causes },
+                other => other,
+            };
+        }
+
+        let has = |ty: fn(&(dyn std::error::Error + 'static)) -> bool| error.chain().any(ty);
+        let addr_in_use = error.chain().any(|cause| {
+            cause
+                .downcast_ref::<std::io::Error>()
+                .is_some_and(|io| io.kind() == std::io::ErrorKind::AddrInUse)
+        });
+
+        if addr_in_use {
+            PrismarineError::PortInUse {
+                message,
+                port: None,
+                suggested_port: None,
+            }
+        } else if has(|e| e.is::<reqwest::Error>()) {
+            PrismarineError::Network { message, causes }
+        } else if has(|e| e.is::<std::io::Error>()) {
+            PrismarineError::Io { message, causes }
+        } else {
+            PrismarineError::Internal { message, causes }
+        }
+    }
+}
+
+/// `context` for lookups where a miss is a NotFound error
+pub trait OrNotFound<T> {
+    fn or_not_found(self, message: &str) -> anyhow::Result<T>;
+}
+
+impl<T> OrNotFound<T> for Option<T> {
+    fn or_not_found(self, message: &str) -> anyhow::Result<T> {
+        self.ok_or_else(|| PrismarineError::not_found(message).into())
+    }
+}
+
+impl From<std::io::Error> for PrismarineError {
+    fn from(error: std::io::Error) -> Self {
+        anyhow::Error::from(error).into()
+    }
+}
@@ -0,0 +1,224 @@
+// Network failover
+// Watches external reachability of running servers with players online.
+// When a server stops being reachable (ISP renumbering, router reboot), it first
+// tries to re-create the UPnP mapping, then falls back to the bridge tunnel and
+// announces the new address through the notification channels. Servers that are unhealthy
+// locally (hung, crashed) are left alone, a new route would not help them.
+
+use crate::bridge::{BridgeStatus, PrismarineBridge};
+use crate::health::HealthLight;
+use crate::monitor::Monitor;
+use crate::notifications::NotifyEvent;
+use crate::paths::AppPaths;
+use crate::port_manager::PortManager;
+use crate::secrets;
+use crate::server_manager::{ServerManager, ServerStatus};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::Mutex as TokioMutex;
+
+/// Consecutive failed checks before failover kicks in
+const FAILURE_THRESHOLD: u32 = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct FailoverConfig {
+    pub enabled: bool,
+    /// Discord/Slack compatible webhook that receives the new address.
+    /// Kept in the secret store, failover.json only has it from older versions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
+}
+
+pub struct FailoverWatchdog {
+    config_path: PathBuf,
+    failures: Mutex<HashMap<String, u32>>,
+}
+
+impl FailoverWatchdog {
+    pub fn new(paths: &AppPaths) -> Self {
+        let config_path = paths.config_file("failover.json");
+
+        Self {
+            config_path,
+            failures: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get_config(&self) -> FailoverConfig {
+        let mut config: FailoverConfig = std::fs::read_to_string(&self.config_path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        if config.webhook_url.is_none() {
+            config.webhook_url = secrets::get(secrets::FAILOVER_WEBHOOK).ok().flatten();
+        }
+        config
+    }
+
+    pub fn set_config(&self, config: &FailoverConfig) -> Result<()> {
+        match config.webhook_url.as_deref().filter(|url| !url.is_empty()) {
+            Some(url) => secrets::set(secrets::FAILOVER_WEBHOOK, url)?,
+            None => secrets::delete(secrets::FAILOVER_WEBHOOK)?,
+        }
+
+        if let Some(parent) = self.config_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let on_disk = FailoverConfig {
+            webhook_url: None,
+            ..config.clone()
+        };
+        std::fs::write(&self.config_path, serde_json::to_string_pretty(&on_disk)?)?;
+        Ok(())
+    }
+
+    /// Move a webhook URL saved in plaintext by older versions into the secret store
+    pub fn migrate_secrets(&self) -> Result<()> {
+        let Some(data) = std::fs::read_to_string(&self.config_path).ok() else {
+            return Ok(());
+        };
+        let config: FailoverConfig = serde_json::from_str(&data).unwrap_or_default();
+        if config.webhook_url.is_some() {
+            self.set_config(&config)?;
+            println!("[Failover] Moved the webhook URL into the secret store");
+        }
+        Ok(())
+    }
+
+    /// Run one reachability check over all running servers with players online
+    pub async fn check(
+        &self,
+        server_manager: &TokioMutex<ServerManager>,
+        monitor: &Mutex<Monitor>,
+        port_manager: &PortManager,
+        bridge: &PrismarineBridge,
+    ) {
+        let config = self.get_config();
+        if !config.enabled {
+            return;
+        }
+
+        let manager = server_manager.lock().await.clone();
+        let servers = manager.get_servers().await;
+        for server in servers.iter().filter(|s| s.status == ServerStatus::Running) {
+            let light = crate::health::check(&manager, monitor, &server.id)
+                .await
+                .map(|h| h.light)
+                .unwrap_or(HealthLight::Red);
+            if light == HealthLight::Red {
+                self.failures.lock().unwrap().remove(&server.id);
+                continue;
+            }
+
+            let online = Monitor::get_online_players(&server.path)
+                .await
+                .map(|p| p.len())
+                .unwrap_or(0);
+            if online == 0 {
+                self.failures.lock().unwrap().remove(&server.id);
+                continue;
+            }
+
+            // Unknown (checker down, no hairpin) neither counts nor resets
+            let Some(reachable) = port_manager.probe_external_tcp(server.port).await else {
+                continue;
+            };
+
+            let failures = {
+                let mut failures = self.failures.lock().unwrap();
+                let count = failures.entry(server.id.clone()).or_insert(0);
+                *count = if reachable { 0 } else { *count + 1 };
+                *count
+            };
+
+            if failures < FAILURE_THRESHOLD {
+                continue;
+            }
+
+            println!(
+                "[Failover] {} is unreachable from outside ({} checks), recovering",
+                server.name, failures
+            );
+
+            // 1. Re-create the router mapping
+            let description = format!("Prismarine {}", server.name);
+            if port_manager
+                .map_port(server.port, "TCP", &description)
+                .await
+                .is_ok()
+                && port_manager.probe_external_tcp(server.port).await == Some(true)
+            {
+                println!("[Failover] UPnP re-mapping restored {}", server.name);
+                self.failures.lock().unwrap().remove(&server.id);
+                continue;
+            }
+
+            // 2. Fall back to the bridge tunnel (only one tunnel at a time)
+            if bridge.is_running() {
+                continue;
+            }
+
+            match start_bridge_and_wait(bridge, server.port).await {
+                Ok(address) => {
+                    println!("[Failover] Bridge tunnel up at {}", address);
+                    self.failures.lock().unwrap().remove(&server.id);
+                    let message = format!(
+                        "Server \"{}\" is now reachable at {} (network failover)",
+                        server.name, address
+                    );
+                    crate::notifications::dispatch(
+                        NotifyEvent::Failover,
+                        "Network failover",
+                        &message,
+                    )
+                    .await;
+                }
+                Err(e) => println!("[Failover] Bridge fallback failed: {}", e),
+            }
+        }
+    }
+}
+
+async fn start_bridge_and_wait(bridge: &PrismarineBridge, port: u16) -> Result<String> {
+    bridge.ensure_installed().await?;
+    bridge.start(port, None, None)?;
+
+    for _ in 0..30 {
+        match bridge.get_status(None) {
+            BridgeStatus::Connected(address) => return Ok(address),
+            BridgeStatus::Error(e) => anyhow::bail!(e),
+            BridgeStatus::AccessRequired(host) => anyhow::bail!(
+                "{} is a cloudflared quick tunnel, players cannot join it directly",
+                host
+            ),
+            _ => tokio::time::sleep(Duration::from_millis(500)).await,
+        }
+    }
+
+    anyhow::bail!("Timed out waiting for the bridge address")
+}
+
+pub async fn post_webhook(url: &str, message: &str) -> Result<()> {
+    crate::offline::ensure_online("Sending the webhook")?;
+    let client = reqwest::Client::builder()
+        .user_agent("MinecraftServerManager/0.1.0")
+        .timeout(Duration::from_secs(10))
+        .build()?;
+
+    // "content" is read by Discord, "text" by Slack
+    let response = client
+        .post(url)
+        .json(&serde_json::json!({ "content": message, "text": message }))
+        .send()
+        .await
+        .map_err(reqwest::Error::without_url)?;
+
+    if !response.status().is_success() {
+        anyhow::bail!("Webhook returned HTTP {}", response.status());
+    }
+    Ok(())
+}
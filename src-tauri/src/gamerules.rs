@@ -0,0 +1,342 @@
+// Game rules
+// A few quality-of-life game rules as typed values. A running server gets them
+// through `/gamerule` (RCON or the console), a stopped one through level.dat:
+// only the Data.GameRules compound is rewritten, every other byte is copied.
+// World settings pair them with the gameplay keys of server.properties.
+
+use crate::nbt::{self, Nbt, NbtReader};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+
+pub const PLAYERS_SLEEPING_PERCENTAGE: &str = "playersSleepingPercentage";
+pub const KEEP_INVENTORY: &str = "keepInventory";
+pub const MOB_GRIEFING: &str = "mobGriefing";
+pub const DO_DAYLIGHT_CYCLE: &str = "doDaylightCycle";
+
+/// Rules read back by `get_game_rules`
+pub const RULES: [&str; 4] = [
+    PLAYERS_SLEEPING_PERCENTAGE,
+    KEEP_INVENTORY,
+    MOB_GRIEFING,
+    DO_DAYLIGHT_CYCLE,
+];
+
+/// None means unknown when reading and unchanged when writing
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GameRules {
+    #[serde(default)]
+    pub players_sleeping_percentage: Option<u32>, // 0-100, above 100 sleeping never skips the night
+    #[serde(default)]
+    pub keep_inventory: Option<bool>,
+    #[serde(default)]
+    pub mob_griefing: Option<bool>,
+    #[serde(default)]
+    pub do_daylight_cycle: Option<bool>,
+}
+
+impl GameRules {
+    /// (rule, value) pairs in `/gamerule` syntax
+    pub fn to_pairs(&self) -> Vec<(&'static str, String)> {
+        let mut pairs = Vec::new();
+        if let Some(percentage) = self.players_sleeping_percentage {
+            pairs.push((PLAYERS_SLEEPING_PERCENTAGE, percentage.to_string()));
+        }
+        if let Some(keep) = self.keep_inventory {
+            pairs.push((KEEP_INVENTORY, keep.to_string()));
+        }
+        if let Some(griefing) = self.mob_griefing {
+            pairs.push((MOB_GRIEFING, griefing.to_string()));
+        }
+        if let Some(cycle) = self.do_daylight_cycle {
+            pairs.push((DO_DAYLIGHT_CYCLE, cycle.to_string()));
+        }
+        pairs
+    }
+
+    pub fn from_values(values: &HashMap<String, String>) -> Self {
+        Self {
+            players_sleeping_percentage: values
+                .get(PLAYERS_SLEEPING_PERCENTAGE)
+                .and_then(|v| v.parse().ok()),
+            keep_inventory: values.get(KEEP_INVENTORY).and_then(|v| v.parse().ok()),
+            mob_griefing: values.get(MOB_GRIEFING).and_then(|v| v.parse().ok()),
+            do_daylight_cycle: values.get(DO_DAYLIGHT_CYCLE).and_then(|v| v.parse().ok()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GameRulesStatus {
+    pub rules: GameRules,
+    pub source: String, // "rcon" or "level.dat" (last save of a running server)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Peaceful,
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Difficulty::Peaceful => "peaceful",
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+        }
+    }
+
+    /// Names and the numbers written by servers before 1.14
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "peaceful" | "0" => Some(Difficulty::Peaceful),
+            "easy" | "1" => Some(Difficulty::Easy),
+            "normal" | "2" => Some(Difficulty::Normal),
+            "hard" | "3" => Some(Difficulty::Hard),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GameMode {
+    Survival,
+    Creative,
+    Adventure,
+    Spectator,
+}
+
+impl GameMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            GameMode::Survival => "survival",
+            GameMode::Creative => "creative",
+            GameMode::Adventure => "adventure",
+            GameMode::Spectator => "spectator",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "survival" | "0" => Some(GameMode::Survival),
+            "creative" | "1" => Some(GameMode::Creative),
+            "adventure" | "2" => Some(GameMode::Adventure),
+            "spectator" | "3" => Some(GameMode::Spectator),
+            _ => None,
+        }
+    }
+}
+
+/// Gameplay keys of server.properties plus the game rules, None means unknown
+/// when reading and unchanged when writing
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorldSettings {
+    #[serde(default)]
+    pub difficulty: Option<Difficulty>,
+    #[serde(default)]
+    pub gamemode: Option<GameMode>,
+    #[serde(default)]
+    pub hardcore: Option<bool>,
+    #[serde(default)]
+    pub pvp: Option<bool>,
+    #[serde(default)]
+    pub spawn_protection: Option<u32>, // Radius in blocks, 0 turns it off
+    #[serde(default)]
+    pub view_distance: Option<u32>,
+    #[serde(default)]
+    pub simulation_distance: Option<u32>,
+    #[serde(default)]
+    pub game_rules: GameRules,
+}
+
+impl WorldSettings {
+    /// server.properties (key, value) pairs to write
+    pub fn property_pairs(&self) -> Result<Vec<(&'static str, String)>> {
+        for (name, distance) in [
+            ("View distance", self.view_distance),
+            ("Simulation distance", self.simulation_distance),
+        ] {
+            if distance.is_some_and(|d| !(3..=32).contains(&d)) {
+                anyhow::bail!("{} must be between 3 and 32", name);
+            }
+        }
+
+        let mut pairs = Vec::new();
+        if let Some(difficulty) = self.difficulty {
+            pairs.push(("difficulty", difficulty.as_str().to_string()));
+        }
+        if let Some(gamemode) = self.gamemode {
+            pairs.push(("gamemode", gamemode.as_str().to_string()));
+        }
+        if let Some(hardcore) = self.hardcore {
+            pairs.push(("hardcore", hardcore.to_string()));
+        }
+        if let Some(pvp) = self.pvp {
+            pairs.push(("pvp", pvp.to_string()));
+        }
+        if let Some(radius) = self.spawn_protection {
+            pairs.push(("spawn-protection", radius.to_string()));
+        }
+        if let Some(distance) = self.view_distance {
+            pairs.push(("view-distance", distance.to_string()));
+        }
+        if let Some(distance) = self.simulation_distance {
+            pairs.push(("simulation-distance", distance.to_string()));
+        }
+        Ok(pairs)
+    }
+
+    /// Console commands that apply the difficulty and default game mode live,
+    /// the other properties are only read at startup
+    pub fn live_commands(&self) -> Vec<String> {
+        let mut commands = Vec::new();
+        if let Some(difficulty) = self.difficulty {
+            commands.push(format!("difficulty {}", difficulty.as_str()));
+        }
+        if let Some(gamemode) = self.gamemode {
+            commands.push(format!("defaultgamemode {}", gamemode.as_str()));
+        }
+        commands
+    }
+
+    pub fn from_properties(properties: &HashMap<String, String>, game_rules: GameRules) -> Self {
+        let get = |key: &str| properties.get(key).map(|v| v.trim());
+        Self {
+            difficulty: get("difficulty").and_then(Difficulty::parse),
+            gamemode: get("gamemode").and_then(GameMode::parse),
+            hardcore: get("hardcore").and_then(|v| v.parse().ok()),
+            pvp: get("pvp").and_then(|v| v.parse().ok()),
+            spawn_protection: get("spawn-protection").and_then(|v| v.parse().ok()),
+            view_distance: get("view-distance").and_then(|v| v.parse().ok()),
+            simulation_distance: get("simulation-distance").and_then(|v| v.parse().ok()),
+            game_rules,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WorldSettingsStatus {
+    pub settings: WorldSettings,
+    pub game_rules_source: String, // See GameRulesStatus::source
+}
+
+/// What `set_world_settings` did
+#[derive(Debug, Clone, Serialize)]
+pub struct WorldSettingsResult {
+    pub applied_live: bool,
+    pub restart_required: Vec<String>, // server.properties keys read only at startup
+}
+
+/// Value from a "Gamerule keepInventory is currently set to: false" reply
+pub fn parse_query_reply(reply: &str) -> Option<String> {
+    let (_, value) = reply.trim().rsplit_once(": ")?;
+    Some(value.trim().to_string())
+}
+
+/// All string game rules of a world
+pub fn read_level_dat(world_dir: &Path) -> Result<HashMap<String, String>> {
+    let data = nbt::read_gzip(&world_dir.join("level.dat"))?;
+    let (start, end) = find_game_rules(&data).context("level.dat has no GameRules")?;
+    Ok(rule_entries(&data[start..end])
+        .context("Invalid GameRules in level.dat")?
+        .into_iter()
+        .filter_map(|entry| Some((entry.name, entry.value?)))
+        .collect())
+}
+
+/// Set game rules of a stopped world; the previous file is kept as level.dat_old
+pub fn write_level_dat(world_dir: &Path, updates: &[(&str, String)]) -> Result<()> {
+    let path = world_dir.join("level.dat");
+    let data = nbt::read_gzip(&path)?;
+    let (start, end) = find_game_rules(&data).context("level.dat has no GameRules")?;
+    let entries = rule_entries(&data[start..end]).context("Invalid GameRules in level.dat")?;
+
+    let mut rules = Vec::with_capacity(end - start + 64);
+    for entry in &entries {
+        match updates.iter().find(|(rule, _)| *rule == entry.name) {
+            Some((rule, value)) => {
+                if entry.value.is_none() {
+                    anyhow::bail!("Unsupported format of game rule {} in level.dat", rule);
+                }
+                write_string_tag(&mut rules, rule, value);
+            }
+            None => rules.extend_from_slice(entry.raw),
+        }
+    }
+    for (rule, value) in updates {
+        if !entries.iter().any(|entry| entry.name == *rule) {
+            write_string_tag(&mut rules, rule, value);
+        }
+    }
+    rules.push(0); // TAG_End
+
+    let mut patched = Vec::with_capacity(data.len() + rules.len());
+    patched.extend_from_slice(&data[..start]);
+    patched.extend_from_slice(&rules);
+    patched.extend_from_slice(&data[end..]);
+
+    std::fs::copy(&path, world_dir.join("level.dat_old")).context("Failed to back up level.dat")?;
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&patched)?;
+    // Renamed over level.dat so a crash mid-write never leaves a truncated world
+    let tmp = world_dir.join("level.dat.tmp");
+    std::fs::write(&tmp, encoder.finish()?).context("Failed to write level.dat")?;
+    std::fs::rename(&tmp, &path).context("Failed to replace level.dat")?;
+    Ok(())
+}
+
+fn write_string_tag(out: &mut Vec<u8>, name: &str, value: &str) {
+    out.push(8); // TAG_String
+    for text in [name, value] {
+        out.extend_from_slice(&(text.len() as u16).to_be_bytes());
+        out.extend_from_slice(text.as_bytes());
+    }
+}
+
+/// Byte range of the entries of Data.GameRules, without its TAG_End
+fn find_game_rules(data: &[u8]) -> Option<(usize, usize)> {
+    let mut reader = NbtReader::new(data);
+    if reader.u8()? != 10 {
+        return None;
+    }
+    reader.string()?;
+    reader.enter_compound("Data")?;
+    reader.enter_compound("GameRules")?;
+    let start = reader.pos();
+    reader.payload(10)?;
+    Some((start, reader.pos() - 1))
+}
+
+struct RuleEntry<'a> {
+    name: String,
+    value: Option<String>, // None for rules that are not stored as strings
+    raw: &'a [u8],         // The whole tag, copied as is when not updated
+}
+
+fn rule_entries(data: &[u8]) -> Option<Vec<RuleEntry<'_>>> {
+    let mut entries = Vec::new();
+    let mut reader = NbtReader::new(data);
+    while reader.pos() < data.len() {
+        let start = reader.pos();
+        let tag = reader.u8()?;
+        let name = reader.string()?;
+        let value = match reader.payload(tag)? {
+            Nbt::String(value) => Some(value),
+            _ => None,
+        };
+        entries.push(RuleEntry {
+            name,
+            value,
+            raw: &data[start..reader.pos()],
+        });
+    }
+    Some(entries)
+}
@@ -0,0 +1,189 @@
+// Server health
+// Folds process liveness, a server list ping, TPS, memory pressure and log
+// activity into one traffic light with the reasons behind it. A server can be
+// "Running" while hung or lagging; this is what the dashboard and the network
+// watchdog look at instead of the plain status.
+
+use crate::error::OrNotFound;
+use crate::monitor::Monitor;
+use crate::server_manager::{parse_memory_mb, ServerManager, ServerStatus};
+use anyhow::Result;
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+const PING_TIMEOUT: Duration = Duration::from_secs(3);
+/// Pings slower than this are answered by a struggling server
+const SLOW_PING_MS: u64 = 1000;
+const TPS_WARNING: f64 = 18.0;
+const TPS_CRITICAL: f64 = 10.0;
+/// System memory in use, percent
+const MEMORY_WARNING: f32 = 90.0;
+const MEMORY_CRITICAL: f32 = 97.0;
+/// A starting server that writes nothing for this long is probably stuck
+const STARTUP_SILENCE_SECS: u64 = 180;
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Serialize)]
+pub enum HealthLight {
+    Off, // Stopped
+    Green,
+    Yellow,
+    Red,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerHealth {
+    pub server_id: String,
+    pub light: HealthLight,
+    pub reasons: Vec<String>, // Why the light is not green
+    pub process_alive: bool,
+    pub ping_ms: Option<u64>, // None when the ping failed or was not tried
+    pub tps: Option<f64>,     // None without RCON
+    pub memory_used: Option<u64>, // Bytes, the server process
+    pub system_memory_percent: f32,
+    pub last_log_secs: Option<u64>, // Seconds since latest.log was written
+}
+
+impl ServerHealth {
+    fn raise(&mut self, light: HealthLight, reason: String) {
+        if light > self.light {
+            self.light = light;
+        }
+        self.reasons.push(reason);
+    }
+}
+
+/// Check one server
+pub async fn check(
+    manager: &ServerManager,
+    monitor: &Mutex<Monitor>,
+    server_id: &str,
+) -> Result<ServerHealth> {
+    let server = manager
+        .get_server(server_id)
+        .await
+        .or_not_found("Server not found")?;
+    let system_memory_percent = monitor.lock().unwrap().get_system_stats().memory_percent;
+    let last_log_secs = std::fs::metadata(server.path.join("logs").join("latest.log"))
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .map(|idle| idle.as_secs());
+
+    let mut health = ServerHealth {
+        server_id: server.id.clone(),
+        light: HealthLight::Green,
+        reasons: Vec::new(),
+        process_alive: false,
+        ping_ms: None,
+        tps: None,
+        memory_used: None,
+        system_memory_percent,
+        last_log_secs,
+    };
+
+    if server.remote.is_some() {
+        // Hosted elsewhere: only the panel's status is known
+        if server.status != ServerStatus::Running {
+            health.light = HealthLight::Off;
+        }
+        return Ok(health);
+    }
+
+    match server.status {
+        ServerStatus::Stopped => {
+            health.light = HealthLight::Off;
+            return Ok(health);
+        }
+        ServerStatus::Stopping => {
+            health.raise(HealthLight::Yellow, "The server is stopping".to_string());
+            return Ok(health);
+        }
+        _ => {}
+    }
+
+    health.process_alive = manager.is_process_alive(server_id);
+    if !health.process_alive {
+        health.raise(
+            HealthLight::Red,
+            "The server process has exited".to_string(),
+        );
+        return Ok(health);
+    }
+
+    if let Some(pid) = server.pid {
+        health.memory_used = monitor
+            .lock()
+            .unwrap()
+            .get_server_stats(pid)
+            .map(|stats| stats.memory_used);
+    }
+    if system_memory_percent >= MEMORY_CRITICAL {
+        health.raise(
+            HealthLight::Red,
+            format!("System memory is {:.0}% used", system_memory_percent),
+        );
+    } else if system_memory_percent >= MEMORY_WARNING {
+        health.raise(
+            HealthLight::Yellow,
+            format!("System memory is {:.0}% used", system_memory_percent),
+        );
+    }
+    // The JVM needs some room beyond the heap, far more hints at a leak
+    let heap_mb = parse_memory_mb(&server.max_memory);
+    if let (Some(used), Some(heap_mb)) = (health.memory_used, heap_mb) {
+        if used > heap_mb * 1024 * 1024 * 3 / 2 {
+            health.raise(
+                HealthLight::Yellow,
+                format!(
+                    "The process uses {} MB, well above its {} heap",
+                    used / 1024 / 1024,
+                    server.max_memory
+                ),
+            );
+        }
+    }
+
+    if server.status == ServerStatus::Starting {
+        health.raise(HealthLight::Yellow, "The server is starting".to_string());
+        if let Some(idle) = last_log_secs.filter(|idle| *idle >= STARTUP_SILENCE_SECS) {
+            health.raise(
+                HealthLight::Red,
+                format!("No log output for {} s while starting", idle),
+            );
+        }
+        return Ok(health);
+    }
+
+    match crate::query::status("127.0.0.1", server.port, PING_TIMEOUT).await {
+        Ok(status) => {
+            health.ping_ms = Some(status.latency_ms);
+            if status.latency_ms > SLOW_PING_MS {
+                health.raise(
+                    HealthLight::Yellow,
+                    format!("Server list ping took {} ms", status.latency_ms),
+                );
+            }
+        }
+        Err(_) => {
+            let mut reason = "Does not answer server list pings".to_string();
+            if let Some(idle) = last_log_secs.filter(|idle| *idle >= 60) {
+                reason.push_str(&format!(", no log output for {} s", idle));
+            }
+            health.raise(HealthLight::Red, reason);
+        }
+    }
+
+    health.tps = manager.query_tps(&server).await;
+    match health.tps {
+        Some(tps) if tps < TPS_CRITICAL => {
+            health.raise(HealthLight::Red, format!("TPS is {:.1}", tps))
+        }
+        Some(tps) if tps < TPS_WARNING => {
+            health.raise(HealthLight::Yellow, format!("TPS is {:.1}", tps))
+        }
+        _ => {}
+    }
+
+    Ok(health)
+}
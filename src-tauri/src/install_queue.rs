@@ -0,0 +1,67 @@
+// Plugin install queue
+// Plugin and mod downloads run in parallel, limited per download host so
+// installing many mods at once neither floods a single API nor serializes
+// everything behind the ServerManager lock. The overall cap is the download
+// limit from the settings, taken by download_file.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OwnedMutexGuard, OwnedSemaphorePermit, Semaphore};
+
+/// Concurrent downloads per host (Modrinth CDN, Spiget, GeyserMC, ...)
+const MAX_DOWNLOADS_PER_HOST: usize = 3;
+
+pub struct InstallQueue {
+    hosts: Mutex<HashMap<String, Arc<Semaphore>>>,
+    // Installs into the same plugins folder update a shared sources file
+    folders: Mutex<HashMap<PathBuf, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+/// Held for the duration of one download
+pub struct DownloadSlot {
+    _host: OwnedSemaphorePermit,
+}
+
+impl InstallQueue {
+    pub fn new() -> Self {
+        Self {
+            hosts: Mutex::new(HashMap::new()),
+            folders: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Wait until a download from `url` may start
+    pub async fn acquire(&self, url: &str) -> DownloadSlot {
+        let host = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(|h| h.to_string()))
+            .unwrap_or_default();
+        let host_limit = self
+            .hosts
+            .lock()
+            .unwrap()
+            .entry(host.clone())
+            .or_insert_with(|| Arc::new(Semaphore::new(MAX_DOWNLOADS_PER_HOST)))
+            .clone();
+
+        if host_limit.available_permits() == 0 {
+            println!("[InstallQueue] Waiting for a free slot on {}", host);
+        }
+        // Taken before the global download slot so a busy host does not hold global slots
+        let host_permit = host_limit.acquire_owned().await.expect("semaphore closed");
+        DownloadSlot { _host: host_permit }
+    }
+
+    /// Exclusive access to the bookkeeping files of a plugins folder
+    pub async fn lock_folder(&self, plugins_path: &Path) -> OwnedMutexGuard<()> {
+        let lock = self
+            .folders
+            .lock()
+            .unwrap()
+            .entry(plugins_path.to_path_buf())
+            .or_default()
+            .clone();
+        lock.lock_owned().await
+    }
+}
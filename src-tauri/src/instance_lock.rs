@@ -0,0 +1,122 @@
+// Instance lock
+// Only one Prismarine process may manage a data folder at a time, otherwise two
+// instances overwrite each other's config and start the same servers twice.
+// A second GUI launch is handed to the first one by the single-instance plugin;
+// this lock also covers headless runs against the same data folder.
+// The lock is an OS file lock on instance.lock, released by the OS when the
+// process ends however it ends. Who holds it is written to instance.json, a
+// separate file because Windows file locks also block reading.
+
+use crate::paths::AppPaths;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, TryLockError};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockHolder {
+    pub pid: u32,
+    pub kind: String, // "gui" or "headless"
+    pub started_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InstanceStatus {
+    pub owner: bool,                // This process manages the data folder
+    pub holder: Option<LockHolder>, // The other process when `owner` is false
+}
+
+pub struct InstanceLock {
+    _file: Option<File>, // Holds the OS lock for the lifetime of the process
+    owner: bool,
+    holder: Option<LockHolder>,
+}
+
+impl InstanceLock {
+    /// Take the lock for this process. Never fails: when another process holds
+    /// it, the returned lock is not the owner and the app runs read-only.
+    pub fn acquire(kind: &str, paths: &AppPaths) -> Self {
+        let lock_path = paths.config_file("instance.lock");
+        let info_path = paths.config_file("instance.json");
+        if let Some(parent) = lock_path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+
+        let file = match std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&lock_path)
+        {
+            Ok(file) => file,
+            Err(e) => {
+                // The lock file cannot be created at all (read-only config folder):
+                // behave like before rather than refusing to work
+                println!("[Instance] Cannot open the instance lock: {}", e);
+                return Self::unlocked();
+            }
+        };
+
+        match file.try_lock() {
+            Ok(()) => {
+                let me = LockHolder {
+                    pid: std::process::id(),
+                    kind: kind.to_string(),
+                    started_at: std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .unwrap()
+                        .as_secs(),
+                };
+                let _ = std::fs::write(&info_path, serde_json::to_string(&me).unwrap_or_default());
+                Self {
+                    _file: Some(file),
+                    owner: true,
+                    holder: None,
+                }
+            }
+            Err(TryLockError::WouldBlock) => {
+                // Written right after the holder took the lock, unknown for a moment
+                let holder = std::fs::read_to_string(&info_path)
+                    .ok()
+                    .and_then(|data| serde_json::from_str(&data).ok())
+                    .unwrap_or(LockHolder {
+                        pid: 0,
+                        kind: "unknown".to_string(),
+                        started_at: 0,
+                    });
+                println!(
+                    "[Instance] Data folder is managed by {} instance (pid {}), running read-only",
+                    holder.kind, holder.pid
+                );
+                Self {
+                    _file: None,
+                    owner: false,
+                    holder: Some(holder),
+                }
+            }
+            Err(TryLockError::Error(e)) => {
+                // File system without lock support (some network shares)
+                println!("[Instance] Cannot lock the data folder: {}", e);
+                Self::unlocked()
+            }
+        }
+    }
+
+    fn unlocked() -> Self {
+        Self {
+            _file: None,
+            owner: true,
+            holder: None,
+        }
+    }
+
+    pub fn is_owner(&self) -> bool {
+        self.owner
+    }
+
+    pub fn status(&self) -> InstanceStatus {
+        InstanceStatus {
+            owner: self.owner,
+            holder: self.holder.clone(),
+        }
+    }
+}
@@ -0,0 +1,131 @@
+// Jar cache
+// Keeps downloaded Paper/Purpur builds by file name, so reinstalling, rolling
+// back an upgrade or creating another server of the same build copies a local
+// file instead of downloading it again. Neither API offers binary diffs, so
+// this is what saves bandwidth on slow connections. Files are checked against
+// the SHA-256 the API publishes when there is one.
+
+use crate::download::JarKind;
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Builds kept; the least recently used are removed first
+const MAX_ENTRIES: usize = 8;
+
+/// A build that never changes once published
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CachedBuild {
+    pub file_name: String,      // e.g. "paper-1.21.4-232.jar"
+    pub sha256: Option<String>, // Published by the PaperMC API
+}
+
+fn cache_dir() -> PathBuf {
+    crate::paths::get().cache_dir.join("jars")
+}
+
+/// Older versions kept the cache in the config folder
+pub fn remove_legacy_dir() {
+    let legacy = crate::paths::get().config_file("jar-cache");
+    if legacy.exists() && std::fs::remove_dir_all(&legacy).is_ok() {
+        println!(
+            "[JarCache] Removed the old cache folder {}",
+            legacy.display()
+        );
+    }
+}
+
+fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Whether `path` holds the build: same hash, or a valid jar when no hash is known
+fn is_intact(path: &Path, build: &CachedBuild) -> bool {
+    if !path.exists() {
+        return false;
+    }
+    match &build.sha256 {
+        Some(expected) => {
+            sha256_file(path).is_ok_and(|actual| actual.eq_ignore_ascii_case(expected))
+        }
+        None => crate::download::validate_jar(path, JarKind::Executable).is_ok(),
+    }
+}
+
+/// Put the build at `dest`, downloading it from `url` only when neither `dest`
+/// nor the cache already has it
+pub async fn fetch(
+    client: &reqwest::Client,
+    url: &str,
+    build: &CachedBuild,
+    dest: &Path,
+    task_id: &str,
+) -> Result<()> {
+    let cached = cache_dir().join(&build.file_name);
+
+    let (dest_check, cached_check) = (dest.to_path_buf(), cached.clone());
+    let expected = build.clone();
+    let (dest_intact, cached_intact) = tokio::task::spawn_blocking(move || {
+        // Without a hash a jar at `dest` may be any build, only the cache is trusted
+        let dest_intact = expected.sha256.is_some() && is_intact(&dest_check, &expected);
+        (dest_intact, is_intact(&cached_check, &expected))
+    })
+    .await?;
+
+    if dest_intact {
+        println!("[JarCache] {} is already in place", build.file_name);
+        return Ok(());
+    }
+    if !cached_intact {
+        tokio::fs::create_dir_all(cache_dir()).await?;
+        crate::download::download_file(client, &[url], &cached, task_id, Some(JarKind::Executable))
+            .await?;
+        if let Some(expected) = &build.sha256 {
+            let path = cached.clone();
+            let actual = tokio::task::spawn_blocking(move || sha256_file(&path)).await??;
+            if !actual.eq_ignore_ascii_case(expected) {
+                let _ = tokio::fs::remove_file(&cached).await;
+                anyhow::bail!("Checksum mismatch of the downloaded {}", build.file_name);
+            }
+        }
+    } else {
+        println!("[JarCache] Reusing cached {}", build.file_name);
+    }
+
+    tokio::fs::copy(&cached, dest)
+        .await
+        .with_context(|| format!("Failed to copy {} from the jar cache", build.file_name))?;
+    touch(&cached);
+    prune();
+    Ok(())
+}
+
+/// Mark an entry as recently used
+fn touch(path: &Path) {
+    if let Ok(file) = std::fs::File::options().append(true).open(path) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+}
+
+/// Remove the least recently used entries beyond MAX_ENTRIES
+fn prune() {
+    let mut entries: Vec<(SystemTime, PathBuf)> = std::fs::read_dir(cache_dir())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "jar"))
+        .filter_map(|e| Some((e.metadata().ok()?.modified().ok()?, e.path())))
+        .collect();
+    entries.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    for (_, path) in entries.into_iter().skip(MAX_ENTRIES) {
+        println!(
+            "[JarCache] Removing {:?}",
+            path.file_name().unwrap_or_default()
+        );
+        let _ = std::fs::remove_file(path);
+    }
+}
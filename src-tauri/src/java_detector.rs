@@ -86,6 +86,14 @@ pub fn find_java_installations() -> Vec<JavaInstallation> {
         }
     }
 
+    // Runtimes installed by Prismarine
+    for runtime in crate::java_manager::JavaManager::new().managed_runtimes() {
+        installations.push(JavaInstallation {
+            path: runtime.path,
+            version: runtime.version,
+        });
+    }
+
     // Fallback: try system java
     if let Some(version) = get_java_version("java") {
         installations.push(JavaInstallation {
@@ -109,13 +117,7 @@ pub fn get_java_version(java_path: &str) -> Option<u8> {
     for line in stderr.lines() {
         if line.contains("version") {
             if let Some(version_str) = line.split('"').nth(1) {
-                // Handle "21.0.1" format
-                if let Some(major) = version_str.split('.').next() {
-                    if let Ok(ver) = major.parse::<u8>() {
-                        return Some(ver);
-                    }
-                }
-                // Handle "1.8.0_292" format (Java 8)
+                // Handle "1.8.0_292" format (Java 8) before "1" is taken as the major
                 if version_str.starts_with("1.") {
                     if let Some(minor) = version_str.split('.').nth(1) {
                         if let Ok(ver) = minor.parse::<u8>() {
@@ -123,6 +125,12 @@ pub fn get_java_version(java_path: &str) -> Option<u8> {
                         }
                     }
                 }
+                // Handle "21.0.1" format
+                if let Some(major) = version_str.split(['.', '-', '+']).next() {
+                    if let Ok(ver) = major.parse::<u8>() {
+                        return Some(ver);
+                    }
+                }
             }
         }
     }
@@ -132,43 +140,30 @@ pub fn get_java_version(java_path: &str) -> Option<u8> {
 
 /// Get required Java version for Minecraft version
 pub fn get_required_java_version(mc_version: &str) -> u8 {
-    // Parse version like "1.21.1" -> [1, 21, 1]
-    let parts: Vec<&str> = mc_version.split('.').collect();
-
-    if parts.len() < 2 {
-        return 8; // Default to Java 8
-    }
-
-    let major = parts[0].parse::<u16>().unwrap_or(1);
-    let minor = parts[1].parse::<u16>().unwrap_or(0);
-    let patch = parts
-        .get(2)
-        .and_then(|p| p.parse::<u16>().ok())
-        .unwrap_or(0);
-
-    // Minecraft version logic
-    if major == 1 {
-        if minor >= 21 || (minor == 20 && patch >= 5) {
-            21 // 1.20.5+
-        } else if minor >= 17 {
-            17 // 1.17 - 1.20.4
-        } else {
-            8 // 1.16.5 and below
-        }
-    } else {
-        21 // Future versions
-    }
+    crate::versions::requirements(mc_version).min_java
 }
 
-/// Select best Java for Minecraft version
-pub fn select_java_for_minecraft(mc_version: &str) -> Option<String> {
-    let required = get_required_java_version(mc_version);
+/// Select best Java for a server; `modded` applies the stricter limits of Forge
+pub fn select_java_for_server(mc_version: &str, modded: bool) -> Option<String> {
+    let requirements = crate::versions::requirements(mc_version);
+    let required = requirements.min_java;
+    let max = if modded {
+        requirements.max_java_modded
+    } else {
+        requirements.max_java
+    };
     let installations = find_java_installations();
 
-    println!(
-        "[Java Selector] Minecraft {} requires Java {}",
-        mc_version, required
-    );
+    match max {
+        Some(max) => println!(
+            "[Java Selector] Minecraft {} requires Java {} - {}",
+            mc_version, required, max
+        ),
+        None => println!(
+            "[Java Selector] Minecraft {} requires Java {}",
+            mc_version, required
+        ),
+    }
     println!(
         "[Java Selector] Found {} Java installations",
         installations.len()
@@ -184,7 +179,7 @@ pub fn select_java_for_minecraft(mc_version: &str) -> Option<String> {
     // Find Java that meets requirements (prefer closest version)
     installations
         .iter()
-        .filter(|j| j.version >= required)
+        .filter(|j| j.version >= required && max.is_none_or(|max| j.version <= max))
         .min_by_key(|j| j.version)
         .map(|j| {
             println!("[Java Selector] Selected: Java {} at {}", j.version, j.path);
@@ -0,0 +1,464 @@
+// Java runtimes
+// Eclipse Temurin JREs downloaded from Adoptium into the app data directory,
+// one directory per major version. Servers use a suitable Java found on the
+// system first and only get a runtime installed when there is none. Packages
+// are checked against the sha256 Adoptium publishes and kept in the cache.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::{Component, Path, PathBuf};
+
+/// Major versions Adoptium publishes long-term JRE builds for
+const LTS_VERSIONS: &[u8] = &[8, 11, 17, 21, 25];
+
+/// Serializes installs, so two servers starting at once download a runtime only once
+static INSTALL_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JavaRuntime {
+    pub version: u8,
+    pub path: String,
+    pub managed: bool, // Installed by Prismarine and removable
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JavaRecommendation {
+    pub mc_version: String,
+    pub required: u8,
+    pub max: Option<u8>,
+    pub recommended: u8, // Version installed when nothing suitable is found
+    pub installed_path: Option<String>, // Java a server would start with right now
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JavaVerification {
+    pub version: u8,
+    pub path: String,
+    pub repaired: bool, // The runtime did not run and was reinstalled
+}
+
+/// Directory holding one runtime per major version
+pub fn runtimes_dir() -> PathBuf {
+    crate::paths::get().data_dir.join("java")
+}
+
+/// Downloaded archives, kept so reinstalls and repairs do not download again;
+/// one directory per major version holding only the newest package
+fn archive_cache_dir(version: u8) -> PathBuf {
+    crate::paths::get()
+        .cache_dir
+        .join("java")
+        .join(version.to_string())
+}
+
+/// Java executable inside an extracted runtime (macOS builds are app bundles)
+fn executable_in(dir: &Path) -> Option<PathBuf> {
+    let name = if cfg!(target_os = "windows") {
+        "java.exe"
+    } else {
+        "java"
+    };
+    [
+        dir.join("bin").join(name),
+        dir.join("Contents").join("Home").join("bin").join(name),
+    ]
+    .into_iter()
+    .find(|path| path.exists())
+}
+
+pub struct JavaManager {
+    runtimes_dir: PathBuf,
+}
+
+impl JavaManager {
+    pub fn new() -> Self {
+        Self {
+            runtimes_dir: runtimes_dir(),
+        }
+    }
+
+    /// Runtimes installed by Prismarine, by major version
+    pub fn managed_runtimes(&self) -> Vec<JavaRuntime> {
+        let mut runtimes: Vec<JavaRuntime> = std::fs::read_dir(&self.runtimes_dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let version = entry.file_name().to_str()?.parse().ok()?;
+                let path = executable_in(&entry.path())?;
+                Some(JavaRuntime {
+                    version,
+                    path: path.to_string_lossy().to_string(),
+                    managed: true,
+                })
+            })
+            .collect();
+        runtimes.sort_by_key(|runtime| runtime.version);
+        runtimes
+    }
+
+    /// Managed runtimes and every Java found on the system
+    pub fn list_installed(&self) -> Vec<JavaRuntime> {
+        let managed_dir = self.runtimes_dir.to_string_lossy().to_string();
+        let mut runtimes = Vec::new();
+        for install in crate::java_detector::find_java_installations() {
+            if runtimes
+                .iter()
+                .any(|runtime: &JavaRuntime| runtime.path == install.path)
+            {
+                continue;
+            }
+            runtimes.push(JavaRuntime {
+                managed: install.path.starts_with(&managed_dir),
+                version: install.version,
+                path: install.path,
+            });
+        }
+        runtimes.sort_by_key(|runtime| (runtime.version, !runtime.managed));
+        runtimes
+    }
+
+    /// Java requirements of a Minecraft version and the runtime that fits them
+    pub fn recommended(&self, mc_version: &str, modded: bool) -> JavaRecommendation {
+        let requirements = crate::versions::requirements(mc_version);
+        let max = if modded {
+            requirements.max_java_modded
+        } else {
+            requirements.max_java
+        };
+        let recommended = LTS_VERSIONS
+            .iter()
+            .copied()
+            .find(|v| *v >= requirements.min_java && max.is_none_or(|max| *v <= max))
+            .unwrap_or(requirements.min_java);
+        JavaRecommendation {
+            mc_version: mc_version.to_string(),
+            required: requirements.min_java,
+            max,
+            recommended,
+            installed_path: crate::java_detector::select_java_for_server(mc_version, modded),
+        }
+    }
+
+    /// Java to run a server with, installing the recommended runtime when none fits
+    pub async fn get_java_executable(&self, mc_version: &str, modded: bool) -> Result<String> {
+        let version = mc_version.to_string();
+        let recommendation =
+            tokio::task::spawn_blocking(move || JavaManager::new().recommended(&version, modded))
+                .await?;
+        if let Some(path) = recommendation.installed_path {
+            return Ok(path);
+        }
+
+        println!(
+            "[Java Manager] No Java for Minecraft {} found, installing Java {}",
+            mc_version, recommendation.recommended
+        );
+        let runtime = self.install(recommendation.recommended).await?;
+        Ok(runtime.path)
+    }
+
+    /// Download and extract a Temurin JRE; reports progress as "java:<version>"
+    pub async fn install(&self, version: u8) -> Result<JavaRuntime> {
+        let _guard = INSTALL_LOCK.lock().await;
+        if let Some(runtime) = self
+            .managed_runtimes()
+            .into_iter()
+            .find(|runtime| runtime.version == version)
+        {
+            return Ok(runtime);
+        }
+
+        let (url, checksum, archive_name) = find_package(version).await?;
+        tokio::fs::create_dir_all(&self.runtimes_dir).await?;
+        let cache_dir = archive_cache_dir(version);
+        let archive_path = cache_dir.join(&archive_name);
+
+        let (cached, expected) = (archive_path.clone(), checksum.clone());
+        let reusable = tokio::task::spawn_blocking(move || {
+            cached.exists() && verify_sha256(&cached, &expected).is_ok()
+        })
+        .await?;
+        if reusable {
+            println!("[Java Manager] Using cached {}", archive_name);
+        } else {
+            // Older packages of this version are replaced by the new one
+            let _ = tokio::fs::remove_dir_all(&cache_dir).await;
+            tokio::fs::create_dir_all(&cache_dir).await?;
+            let client = reqwest::Client::builder()
+                .user_agent("MinecraftServerManager/0.1.0")
+                .build()?;
+            println!("[Java Manager] Downloading {}", archive_name);
+            crate::download::download_file(
+                &client,
+                &[&url],
+                &archive_path,
+                &format!("java:{}", version),
+                None,
+            )
+            .await
+            .with_context(|| format!("Failed to download Java {}", version))?;
+        }
+
+        let runtimes_dir = self.runtimes_dir.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            let staging = runtimes_dir.join(format!("{}.tmp", version));
+            let _ = std::fs::remove_dir_all(&staging);
+            let extracted = verify_sha256(&archive_path, &checksum).and_then(|()| {
+                if archive_name.ends_with(".zip") {
+                    extract_zip(&archive_path, &staging)
+                } else {
+                    extract_tar_gz(&archive_path, &staging)
+                }
+            });
+            if let Err(e) = extracted {
+                let _ = std::fs::remove_file(&archive_path);
+                let _ = std::fs::remove_dir_all(&staging);
+                return Err(e.context(format!("Failed to install Java {}", version)));
+            }
+            // A directory without an executable is left over from an interrupted install
+            let target = runtimes_dir.join(version.to_string());
+            let _ = std::fs::remove_dir_all(&target);
+            std::fs::rename(&staging, &target)?;
+            executable_in(&target).context("The Java archive contains no java executable")
+        })
+        .await??;
+
+        println!(
+            "[Java Manager] Java {} installed at {}",
+            version,
+            result.display()
+        );
+        Ok(JavaRuntime {
+            version,
+            path: result.to_string_lossy().to_string(),
+            managed: true,
+        })
+    }
+
+    /// Run `java -version` on a managed runtime and reinstall it when it does not
+    /// run or reports another version
+    pub async fn verify(&self, version: u8) -> Result<JavaVerification> {
+        let dir = self.runtimes_dir.join(version.to_string());
+        if !dir.exists() {
+            anyhow::bail!("Java {} was not installed by Prismarine", version);
+        }
+        if let Some(path) = executable_in(&dir) {
+            let java = path.to_string_lossy().to_string();
+            let probe = java.clone();
+            let detected =
+                tokio::task::spawn_blocking(move || crate::java_detector::get_java_version(&probe))
+                    .await?;
+            if detected == Some(version) {
+                return Ok(JavaVerification {
+                    version,
+                    path: java,
+                    repaired: false,
+                });
+            }
+        }
+
+        println!("[Java Manager] Java {} is broken, reinstalling", version);
+        self.remove(version)?;
+        let runtime = self.install(version).await?;
+        let probe = runtime.path.clone();
+        let detected =
+            tokio::task::spawn_blocking(move || crate::java_detector::get_java_version(&probe))
+                .await?;
+        if detected != Some(version) {
+            anyhow::bail!("Java {} still does not run after reinstalling it", version);
+        }
+        Ok(JavaVerification {
+            version,
+            path: runtime.path,
+            repaired: true,
+        })
+    }
+
+    /// Delete a runtime installed by Prismarine
+    pub fn remove(&self, version: u8) -> Result<()> {
+        let dir = self.runtimes_dir.join(version.to_string());
+        if !dir.exists() {
+            anyhow::bail!("Java {} was not installed by Prismarine", version);
+        }
+        std::fs::remove_dir_all(&dir).with_context(|| {
+            format!("Failed to remove Java {} (is a server using it?)", version)
+        })?;
+        println!("[Java Manager] Removed Java {}", version);
+        Ok(())
+    }
+}
+
+/// Download link, sha256 and file name of the latest JRE for this platform
+async fn find_package(version: u8) -> Result<(String, String, String)> {
+    crate::offline::ensure_online("Looking up Java packages")?;
+    let os = match std::env::consts::OS {
+        "macos" => "mac",
+        os => os,
+    };
+    let arch = match std::env::consts::ARCH {
+        "x86_64" => "x64",
+        "x86" => "x32",
+        arch => arch,
+    };
+    let url = format!(
+        "https://api.adoptium.net/v3/assets/latest/{}/hotspot?os={}&architecture={}&image_type=jre&vendor=eclipse",
+        version, os, arch
+    );
+    let client = reqwest::Client::builder()
+        .user_agent("MinecraftServerManager/0.1.0")
+        .build()?;
+    let assets: serde_json::Value = client
+        .get(&url)
+        .send()
+        .await?
+        .error_for_status()
+        .with_context(|| format!("Adoptium has no Java {} runtime", version))?
+        .json()
+        .await?;
+
+    let package = assets
+        .as_array()
+        .and_then(|assets| assets.first())
+        .map(|asset| &asset["binary"]["package"])
+        .with_context(|| format!("No Java {} runtime for {}/{}", version, os, arch))?;
+    let field = |name: &str| {
+        package[name]
+            .as_str()
+            .map(str::to_string)
+            .with_context(|| format!("Adoptium package has no {}", name))
+    };
+    Ok((field("link")?, field("checksum")?, field("name")?))
+}
+
+pub(crate) fn verify_sha256(path: &Path, expected: &str) -> Result<()> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    let actual: String = hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    if !actual.eq_ignore_ascii_case(expected) {
+        anyhow::bail!("Checksum mismatch of the downloaded archive");
+    }
+    Ok(())
+}
+
+/// Target of an archive entry with its top-level directory stripped;
+/// None for the top-level directory itself and for entries escaping `dest`
+fn entry_path(dest: &Path, name: &str) -> Option<PathBuf> {
+    let (_, rest) = name.trim_start_matches("./").split_once('/')?;
+    let rest = Path::new(rest);
+    if rest.as_os_str().is_empty()
+        || !rest
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return None;
+    }
+    Some(dest.join(rest))
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode & 0o777))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> Result<()> {
+    Ok(())
+}
+
+fn extract_zip(archive: &Path, dest: &Path) -> Result<()> {
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(archive)?)?;
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let Some(path) = entry_path(dest, file.name()) else {
+            continue;
+        };
+        if file.is_dir() {
+            std::fs::create_dir_all(&path)?;
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::io::copy(&mut file, &mut std::fs::File::create(&path)?)?;
+        if let Some(mode) = file.unix_mode() {
+            set_mode(&path, mode)?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether a symlink at `link` pointing to `target` resolves inside `dest`
+fn link_stays_inside(dest: &Path, link: &Path, target: &Path) -> bool {
+    let Some(parent) = link
+        .parent()
+        .and_then(|parent| parent.strip_prefix(dest).ok())
+    else {
+        return false;
+    };
+    let mut depth = parent.components().count();
+    for component in target.components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir if depth > 0 => depth -= 1,
+            _ => return false,
+        }
+    }
+    true
+}
+
+fn extract_tar_gz(archive: &Path, dest: &Path) -> Result<()> {
+    let mut archive =
+        tar::Archive::new(flate2::read::GzDecoder::new(std::fs::File::open(archive)?));
+    std::fs::create_dir_all(dest)?;
+
+    for entry in archive.entries()? {
+        let mut entry = entry.context("Invalid tar archive")?;
+        let name = entry.path()?.to_string_lossy().into_owned();
+        let Some(path) = entry_path(dest, &name) else {
+            continue;
+        };
+        let kind = entry.header().entry_type();
+        if kind.is_dir() {
+            std::fs::create_dir_all(&path)?;
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        if kind.is_symlink() {
+            let target = entry
+                .link_name()?
+                .with_context(|| format!("Symlink {} has no target", name))?
+                .into_owned();
+            if !link_stays_inside(dest, &path, &target) {
+                anyhow::bail!("Symlink {} points outside the Java archive", name);
+            }
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(&target, &path)?;
+        } else if kind.is_hard_link() {
+            let target = entry
+                .link_name()?
+                .with_context(|| format!("Hard link {} has no target", name))?
+                .to_string_lossy()
+                .into_owned();
+            if let Some(source) = entry_path(dest, &target) {
+                std::fs::copy(source, &path)?;
+            }
+        } else if kind.is_file() || kind == tar::EntryType::Continuous {
+            std::io::copy(&mut entry, &mut std::fs::File::create(&path)?)?;
+            set_mode(&path, entry.header().mode().unwrap_or(0o644))?;
+        }
+    }
+    Ok(())
+}
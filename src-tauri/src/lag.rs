@@ -0,0 +1,305 @@
+// Lag analysis
+// Ranks chunks by what usually eats tick time: crowds of villagers and mobs,
+// piles of dropped items, hoppers and spawners. Counts come from the saved
+// region files (the most recently written ones, where players are active);
+// TPS and tick durations come from spark or Paper's /tps over RCON.
+
+use crate::nbt::Nbt;
+use crate::world_preview::read_chunk;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Region files read per dimension and folder, newest first
+const MAX_REGIONS: usize = 64;
+/// Chunks listed in a report
+const MAX_SOURCES: usize = 25;
+/// Chunks scoring below this are normal gameplay, not lag sources
+const MIN_SCORE: f64 = 40.0;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EntityCount {
+    pub id: String,
+    pub count: usize,
+}
+
+/// One chunk worth a visit, coordinates are the block at its center
+#[derive(Debug, Clone, Serialize)]
+pub struct LagSource {
+    pub dimension: String,
+    pub x: i32,
+    pub z: i32,
+    pub score: f64,
+    pub entities: usize,
+    pub block_entities: usize,
+    pub top: Vec<EntityCount>, // Biggest contributors, entities and block entities mixed
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LagReport {
+    pub tps: Option<f64>,
+    pub mspt: Option<f64>,           // Median tick duration (spark only)
+    pub tick_source: Option<String>, // "spark" or "paper"
+    pub sources: Vec<LagSource>,     // Highest score first
+    pub chunks_scanned: usize,
+    pub total_entities: usize,
+    pub total_block_entities: usize,
+    pub notes: Vec<String>,
+}
+
+/// Rough tick cost of an entity relative to an ordinary mob
+fn entity_weight(id: &str) -> f64 {
+    match id.trim_start_matches("minecraft:") {
+        "villager" => 5.0, // Pathfinding and gossip make villagers the classic lag source
+        "hopper_minecart" | "chest_minecart" | "minecart" => 1.5,
+        "item" | "armor_stand" => 0.5,
+        "experience_orb" => 0.25,
+        "item_frame" | "glow_item_frame" | "painting" => 0.2,
+        _ => 1.0,
+    }
+}
+
+/// Rough tick cost of a block entity; storage blocks barely tick
+fn block_entity_weight(id: &str) -> f64 {
+    match id.trim_start_matches("minecraft:") {
+        "hopper" => 3.0,
+        "mob_spawner" | "spawner" | "trial_spawner" => 2.0,
+        "furnace" | "blast_furnace" | "smoker" | "brewing_stand" | "beacon" => 1.0,
+        _ => 0.1,
+    }
+}
+
+#[derive(Default)]
+struct ChunkStats {
+    entities: HashMap<String, usize>,
+    block_entities: HashMap<String, usize>,
+}
+
+impl ChunkStats {
+    fn score(&self) -> f64 {
+        let entities: f64 = self
+            .entities
+            .iter()
+            .map(|(id, count)| entity_weight(id) * *count as f64)
+            .sum();
+        let block_entities: f64 = self
+            .block_entities
+            .iter()
+            .map(|(id, count)| block_entity_weight(id) * *count as f64)
+            .sum();
+        entities + block_entities
+    }
+}
+
+/// Dimension folders of a world; Bukkit servers keep the nether and the end
+/// in sibling worlds ("world_nether/DIM-1")
+fn dimensions(world_dir: &Path) -> Vec<(&'static str, PathBuf)> {
+    let sibling = |suffix: &str, dim: &str| {
+        let inside = world_dir.join(dim);
+        if inside.exists() {
+            return inside;
+        }
+        let name = world_dir
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        world_dir
+            .with_file_name(format!("{}{}", name, suffix))
+            .join(dim)
+    };
+    vec![
+        ("overworld", world_dir.to_path_buf()),
+        ("the_nether", sibling("_nether", "DIM-1")),
+        ("the_end", sibling("_the_end", "DIM1")),
+    ]
+}
+
+/// Newest region files of a folder with their region coordinates
+fn newest_regions(dir: &Path) -> Vec<(i32, i32, PathBuf)> {
+    let mut regions: Vec<(std::time::SystemTime, i32, i32, PathBuf)> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let mut parts = name.strip_prefix("r.")?.strip_suffix(".mca")?.split('.');
+            let rx = parts.next()?.parse().ok()?;
+            let rz = parts.next()?.parse().ok()?;
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            Some((modified, rx, rz, entry.path()))
+        })
+        .collect();
+    regions.sort_by_key(|region| std::cmp::Reverse(region.0));
+    regions
+        .into_iter()
+        .take(MAX_REGIONS)
+        .map(|(_, rx, rz, path)| (rx, rz, path))
+        .collect()
+}
+
+fn count_chunk(chunk: &Nbt, stats: &mut ChunkStats) {
+    // 1.18+ keeps everything at the root, older versions inside "Level"
+    let level = chunk.get("Level").unwrap_or(chunk);
+    for entity in level.get("Entities").map(Nbt::as_list).unwrap_or(&[]) {
+        if let Some(id) = entity.get("id").and_then(Nbt::as_str) {
+            *stats.entities.entry(id.to_string()).or_default() += 1;
+        }
+    }
+    for key in ["block_entities", "TileEntities"] {
+        for block_entity in level.get(key).map(Nbt::as_list).unwrap_or(&[]) {
+            if let Some(id) = block_entity.get("id").and_then(Nbt::as_str) {
+                *stats.block_entities.entry(id.to_string()).or_default() += 1;
+            }
+        }
+    }
+}
+
+/// Scan the saved world; live numbers are filled in by the caller
+pub fn scan_world(world_dir: &Path) -> Result<LagReport> {
+    if !world_dir.join("level.dat").exists() {
+        anyhow::bail!("The world has not been generated yet");
+    }
+
+    let mut report = LagReport {
+        tps: None,
+        mspt: None,
+        tick_source: None,
+        sources: Vec::new(),
+        chunks_scanned: 0,
+        total_entities: 0,
+        total_block_entities: 0,
+        notes: Vec::new(),
+    };
+    let mut limited = false;
+
+    for (dimension, dir) in dimensions(world_dir) {
+        let mut chunks: HashMap<(i32, i32), ChunkStats> = HashMap::new();
+        // Entities live in their own folder since 1.17, block entities stay in region/
+        for folder in ["region", "entities"] {
+            let regions = newest_regions(&dir.join(folder));
+            limited |= regions.len() == MAX_REGIONS;
+            for (rx, rz, path) in regions {
+                let Ok(region) = std::fs::read(&path) else {
+                    continue;
+                };
+                for i in 0..1024 {
+                    let (cx, cz) = (rx * 32 + i % 32, rz * 32 + i / 32);
+                    if let Some(chunk) = read_chunk(&region, cx, cz) {
+                        count_chunk(&chunk, chunks.entry((cx, cz)).or_default());
+                    }
+                }
+            }
+        }
+
+        report.chunks_scanned += chunks.len();
+        for ((cx, cz), stats) in chunks {
+            let entities: usize = stats.entities.values().sum();
+            let block_entities: usize = stats.block_entities.values().sum();
+            report.total_entities += entities;
+            report.total_block_entities += block_entities;
+
+            let score = stats.score();
+            if score < MIN_SCORE {
+                continue;
+            }
+            let mut top: Vec<EntityCount> = stats
+                .entities
+                .iter()
+                .chain(stats.block_entities.iter())
+                .map(|(id, count)| EntityCount {
+                    id: id.clone(),
+                    count: *count,
+                })
+                .collect();
+            top.sort_by_key(|entry| std::cmp::Reverse(entry.count));
+            top.truncate(3);
+            report.sources.push(LagSource {
+                dimension: dimension.to_string(),
+                x: cx * 16 + 8,
+                z: cz * 16 + 8,
+                score: (score * 10.0).round() / 10.0,
+                entities,
+                block_entities,
+                top,
+            });
+        }
+    }
+
+    report.sources.sort_by(|a, b| b.score.total_cmp(&a.score));
+    report.sources.truncate(MAX_SOURCES);
+    if limited {
+        report.notes.push(format!(
+            "Only the {} most recently saved region files per dimension were scanned",
+            MAX_REGIONS
+        ));
+    }
+    Ok(report)
+}
+
+/// Reply text without § formatting codes
+fn strip_formatting(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '§' {
+            chars.next();
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Numbers following a header line, on the same line after ':' (Paper) or on
+/// the next line (spark)
+fn values_after(reply: &str, header: &str) -> Option<String> {
+    let reply = strip_formatting(reply);
+    let mut lines = reply.lines();
+    let line = lines.find(|line| line.contains(header))?;
+    let same_line = line
+        .split_once(':')
+        .map(|(_, rest)| rest.trim())
+        .unwrap_or("");
+    if !same_line.is_empty() {
+        return Some(same_line.to_string());
+    }
+    lines.next().map(|next| next.trim().to_string())
+}
+
+/// Shortest-window TPS from a spark or Paper "TPS from last ..." reply
+pub fn parse_tps(reply: &str) -> Option<f64> {
+    values_after(reply, "TPS from last")?
+        .split(',')
+        .next()?
+        .trim()
+        .trim_start_matches('*') // Paper marks values capped at 20
+        .parse()
+        .ok()
+}
+
+/// Median tick duration from spark's "min/med/95%ile/max" tick durations
+pub fn parse_mspt(reply: &str) -> Option<f64> {
+    values_after(reply, "Tick durations")?
+        .split(';')
+        .next()?
+        .split('/')
+        .nth(1)?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Whether the spark profiler is installed as a plugin or mod
+pub fn has_spark(server_dir: &Path) -> bool {
+    ["plugins", "mods"].iter().any(|folder| {
+        std::fs::read_dir(server_dir.join(folder))
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .any(|entry| {
+                let name = entry.file_name().to_string_lossy().to_lowercase();
+                name.starts_with("spark") && name.ends_with(".jar")
+            })
+    })
+}
@@ -0,0 +1,154 @@
+// Latency probe
+// Estimates the ping players in different regions will see by timing TCP
+// handshakes from this machine to reference endpoints in those regions
+// (and to the bridge tunnel relay), to guide the choice of bridge endpoint.
+
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+
+/// Connections per endpoint; the fastest one is the best estimate of the round trip
+const SAMPLES: u32 = 3;
+const CONNECT_TIMEOUT_SECS: u64 = 3;
+
+/// (id, display name, host, port)
+const REFERENCE_ENDPOINTS: &[(&str, &str, &str, u16)] = &[
+    (
+        "us-east",
+        "US East (Virginia)",
+        "ec2.us-east-1.amazonaws.com",
+        443,
+    ),
+    (
+        "us-west",
+        "US West (Oregon)",
+        "ec2.us-west-2.amazonaws.com",
+        443,
+    ),
+    (
+        "sa-east",
+        "South America (São Paulo)",
+        "ec2.sa-east-1.amazonaws.com",
+        443,
+    ),
+    (
+        "eu-west",
+        "Europe (Ireland)",
+        "ec2.eu-west-1.amazonaws.com",
+        443,
+    ),
+    (
+        "eu-central",
+        "Europe (Frankfurt)",
+        "ec2.eu-central-1.amazonaws.com",
+        443,
+    ),
+    (
+        "ap-northeast",
+        "Asia Pacific (Tokyo)",
+        "ec2.ap-northeast-1.amazonaws.com",
+        443,
+    ),
+    (
+        "ap-southeast",
+        "Asia Pacific (Singapore)",
+        "ec2.ap-southeast-1.amazonaws.com",
+        443,
+    ),
+    (
+        "oceania",
+        "Oceania (Sydney)",
+        "ec2.ap-southeast-2.amazonaws.com",
+        443,
+    ),
+    (
+        "bridge",
+        "Prismarine Bridge relay (bore.pub)",
+        "bore.pub",
+        7835,
+    ),
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencyResult {
+    pub region: String,
+    pub name: String,
+    pub host: String,
+    pub min_ms: Option<f64>,
+    pub avg_ms: Option<f64>,
+    pub samples: u32, // Successful connections
+    pub error: Option<String>,
+}
+
+/// Region ids accepted by `measure`
+pub fn available_regions() -> Vec<String> {
+    REFERENCE_ENDPOINTS
+        .iter()
+        .map(|(id, ..)| id.to_string())
+        .collect()
+}
+
+/// Probe the given regions (all when empty) in parallel
+pub async fn measure(regions: &[String]) -> Vec<LatencyResult> {
+    let endpoints: Vec<_> = REFERENCE_ENDPOINTS
+        .iter()
+        .filter(|(id, ..)| regions.is_empty() || regions.iter().any(|r| r == id))
+        .collect();
+
+    let handles: Vec<_> = endpoints
+        .into_iter()
+        .map(|&(id, name, host, port)| {
+            tokio::spawn(async move {
+                let mut timings = Vec::new();
+                let mut error = None;
+                for _ in 0..SAMPLES {
+                    match probe(host, port).await {
+                        Ok(elapsed) => timings.push(elapsed.as_secs_f64() * 1000.0),
+                        Err(e) => error = Some(e),
+                    }
+                }
+
+                LatencyResult {
+                    region: id.to_string(),
+                    name: name.to_string(),
+                    host: format!("{}:{}", host, port),
+                    min_ms: timings.iter().copied().reduce(f64::min),
+                    avg_ms: (!timings.is_empty())
+                        .then(|| timings.iter().sum::<f64>() / timings.len() as f64),
+                    samples: timings.len() as u32,
+                    error: if timings.is_empty() { error } else { None },
+                }
+            })
+        })
+        .collect();
+
+    let mut results = Vec::new();
+    for handle in handles {
+        if let Ok(result) = handle.await {
+            results.push(result);
+        }
+    }
+    results
+}
+
+/// Time a TCP handshake (one round trip) to host:port
+async fn probe(host: &str, port: u16) -> Result<Duration, String> {
+    // Resolve first so DNS lookup time is not counted
+    let addr = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| format!("DNS lookup failed: {}", e))?
+        .next()
+        .ok_or_else(|| "DNS lookup returned no address".to_string())?;
+
+    let start = Instant::now();
+    match tokio::time::timeout(
+        Duration::from_secs(CONNECT_TIMEOUT_SECS),
+        TcpStream::connect(addr),
+    )
+    .await
+    {
+        Ok(Ok(_)) => Ok(start.elapsed()),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err("Timed out".to_string()),
+    }
+}
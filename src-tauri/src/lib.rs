@@ -1,31 +1,225 @@
+mod address_book;
+mod app_log;
 mod bridge;
+mod buildtools;
+mod clock;
 mod config;
+mod console_bridge;
+mod download;
+mod error;
+mod failover;
+mod gamerules;
+mod health;
+mod install_queue;
+mod instance_lock;
+mod jar_cache;
 mod java_detector;
+mod java_manager;
+mod lag;
+mod latency;
+mod limits;
+mod malware_scan;
+mod modpack;
 mod monitor;
+mod nbt;
+mod notifications;
+mod offline;
+mod paths;
+mod plugin_compat;
 mod port_manager;
+mod power;
+mod protocol_codec;
+mod protocol_versions;
+mod query;
+mod region_check;
+mod remote;
+mod reports;
+mod resource_pack;
+mod rest_api;
+mod restore_points;
+mod search_hints;
+mod secrets;
 mod server_manager;
-
+mod stress_test;
+mod templates;
+mod transfer;
+mod tunnels;
+mod udp_relay;
+mod uninstall;
+mod versions;
+mod world_preview;
+
+use address_book::{AddressBook, AddressKind};
+use anyhow::Context;
 use bridge::{BridgeStatus, PrismarineBridge};
+use config::{AppSettings, ExitPolicy};
+use console_bridge::ConsoleBridge;
+use error::PrismarineError;
+use failover::FailoverWatchdog;
+use instance_lock::InstanceLock;
+use java_manager::{JavaManager, JavaRecommendation, JavaRuntime, JavaVerification};
 use monitor::Monitor;
+use paths::AppPaths;
 use port_manager::PortManager;
+use power::PowerEvent;
+use resource_pack::PackHost;
+use rest_api::{RestApi, RestApiSettings};
 use server_manager::{RestartType, ServerManager, ServerType};
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
-use tauri::State;
+use tauri::{Emitter, Manager, State};
+use templates::TemplateStore;
 use tokio::sync::Mutex as TokioMutex;
+use transfer::TransferReceiver;
+use udp_relay::UdpRelay;
 
 // App state
+#[derive(Clone)]
 pub struct AppState {
     server_manager: Arc<TokioMutex<ServerManager>>,
     port_manager: Arc<PortManager>,
     monitor: Arc<Mutex<Monitor>>,
     bridge: Arc<PrismarineBridge>,
+    failover: Arc<FailoverWatchdog>,
+    address_book: Arc<AddressBook>,
+    console_bridge: Arc<ConsoleBridge>,
+    pack_host: Arc<PackHost>,
+    udp_relay: Arc<UdpRelay>,
+    templates: Arc<TemplateStore>,
+    transfer: Arc<TransferReceiver>,
+    instance_lock: Arc<InstanceLock>,
+    rest_api: Arc<RestApi>,
     #[allow(dead_code)]
     config_path: PathBuf,
+    settings_path: PathBuf,
+    paths: AppPaths,
+}
+
+// Shared by the Tauri commands and the REST API, which has no Tauri `State`
+// when the core runs headless
+impl AppState {
+    async fn start_server(&self, server_id: &str) -> Result<(), PrismarineError> {
+        let manager = self.server_manager.lock().await.clone();
+        // Two Geyser servers on the same Bedrock port would fail to bind it
+        resolve_geyser_ports(&manager, &self.port_manager).await;
+        manager
+            .start_server(server_id)
+            .await
+            .map_err(PrismarineError::from)?;
+        // The pid lets a later session find the server if the app exits unexpectedly
+        let _ = manager.save_servers(&self.config_path).await;
+
+        sync_server_tunnels(&manager, &self.bridge, &self.config_path).await;
+        self.udp_relay.sync(&manager.relay_targets().await).await;
+        Ok(())
+    }
+
+    async fn stop_server(&self, server_id: &str) -> Result<(), PrismarineError> {
+        let manager = self.server_manager.lock().await.clone();
+        manager
+            .stop_server(server_id)
+            .await
+            .map_err(PrismarineError::from)?;
+        let _ = manager.save_servers(&self.config_path).await;
+
+        sync_server_tunnels(&manager, &self.bridge, &self.config_path).await;
+        self.udp_relay.sync(&manager.relay_targets().await).await;
+        Ok(())
+    }
+
+    /// Start a server group: the backends one after another, each waited for
+    /// until it finished starting, then the proxies. Returns the started ids.
+    async fn start_group(&self, group: &str) -> Result<Vec<String>, PrismarineError> {
+        let manager = self.server_manager.lock().await.clone();
+        let members = manager
+            .group_members(group)
+            .await
+            .map_err(PrismarineError::from)?;
+        let has_proxy = members
+            .iter()
+            .any(|s| server_manager::is_proxy(&s.server_type));
+
+        let mut started = Vec::new();
+        for server in members {
+            if server.status != server_manager::ServerStatus::Stopped {
+                continue;
+            }
+            println!("[Group] Starting {} ({})", server.name, group);
+            let since = std::time::SystemTime::now();
+            self.start_server(&server.id).await?;
+            started.push(server.id.clone());
+
+            // A proxy started before its backends sends the first players nowhere
+            let backend = !server_manager::is_proxy(&server.server_type);
+            if backend && has_proxy && server.remote.is_none() {
+                manager
+                    .wait_for_boot(&server.id, &server.path, since)
+                    .await
+                    .map_err(|e| {
+                        PrismarineError::from(e.context(format!("{} did not start", server.name)))
+                    })?;
+            }
+        }
+        Ok(started)
+    }
+
+    /// Stop a server group in reverse order: proxies first, then the backends
+    async fn stop_group(&self, group: &str) -> Result<Vec<String>, PrismarineError> {
+        let manager = self.server_manager.lock().await.clone();
+        let members = manager
+            .group_members(group)
+            .await
+            .map_err(PrismarineError::from)?;
+
+        let mut stopped = Vec::new();
+        for server in members.into_iter().rev() {
+            if server.status == server_manager::ServerStatus::Stopped {
+                continue;
+            }
+            println!("[Group] Stopping {} ({})", server.name, group);
+            self.stop_server(&server.id).await?;
+            stopped.push(server.id);
+        }
+        Ok(stopped)
+    }
+
+    async fn server_logs(
+        &self,
+        server_id: &str,
+        lines: usize,
+    ) -> Result<Vec<String>, PrismarineError> {
+        let manager = self.server_manager.lock().await.clone();
+        let server_path = if let Some(server) = manager.get_server(server_id).await {
+            server.path.clone()
+        } else {
+            return Err(PrismarineError::not_found("Server not found"));
+        };
+        if let Err(e) = manager.refresh_remote_log(server_id).await {
+            // Show the last downloaded log rather than nothing
+            println!("[Remote] Failed to refresh the log: {}", e);
+        }
+
+        // The launch banner heads the console; latest.log is rolled over on start
+        let mut logs = manager.launch_banner(server_id);
+        let tail = lines.saturating_sub(logs.len());
+        logs.extend(
+            Monitor::get_server_logs(&server_path, tail)
+                .await
+                .map_err(PrismarineError::from)?,
+        );
+        Ok(logs)
+    }
 }
 
 // Tauri commands
 
+#[derive(serde::Serialize)]
+struct CreatedServer {
+    #[serde(flatten)]
+    server: server_manager::ServerInfo,
+    readiness: server_manager::ServerReadiness, // Blockers to fix before the first start
+}
+
 #[tauri::command]
 async fn create_server(
     name: String,
@@ -33,8 +227,9 @@ async fn create_server(
     server_type: String,
     port: u16,
     max_memory: String,
+    eula_accepted: Option<bool>, // Owner agreed to the Minecraft EULA
     state: State<'_, AppState>,
-) -> Result<server_manager::ServerInfo, String> {
+) -> Result<CreatedServer, PrismarineError> {
     let st = match server_type.as_str() {
         "vanilla" => ServerType::Vanilla,
         "paper" => ServerType::Paper,
@@ -48,136 +243,1770 @@ async fn create_server(
         "velocity" => ServerType::Velocity,
         "waterfall" => ServerType::Waterfall,
         "bungeecord" => ServerType::BungeeCord,
-        _ => return Err("Invalid server type".to_string()),
+        _ => return Err(PrismarineError::invalid_input("Invalid server type")),
+    };
+
+    let manager = state.server_manager.lock().await;
+    let result = manager
+        .create_server(
+            name,
+            version,
+            st,
+            port,
+            max_memory,
+            eula_accepted.unwrap_or(false),
+        )
+        .await
+        .map_err(PrismarineError::from)?;
+
+    // Save servers after creation
+    let _ = manager.save_servers(&state.config_path).await;
+
+    let readiness = manager
+        .check_readiness(&result.id)
+        .await
+        .map_err(PrismarineError::from)?;
+    Ok(CreatedServer {
+        server: result,
+        readiness,
+    })
+}
+
+#[tauri::command]
+async fn check_server_readiness(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<server_manager::ServerReadiness, PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    manager
+        .check_readiness(&server_id)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+fn get_version_requirements(version: String) -> versions::VersionRequirements {
+    versions::requirements(&version)
+}
+
+#[tauri::command]
+async fn repair_server(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<server_manager::RepairReport, PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    manager
+        .repair_server(&server_id)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn change_server_version(
+    server_id: String,
+    new_version: String,
+    state: State<'_, AppState>,
+) -> Result<server_manager::ServerInfo, PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    let result = manager
+        .change_server_version(&server_id, &new_version)
+        .await
+        .map_err(PrismarineError::from)?;
+
+    let _ = manager.save_servers(&state.config_path).await;
+
+    Ok(result)
+}
+
+#[tauri::command]
+async fn change_server_type(
+    server_id: String,
+    new_type: ServerType,
+    state: State<'_, AppState>,
+) -> Result<server_manager::ServerInfo, PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    let result = manager
+        .change_server_type(&server_id, new_type)
+        .await
+        .map_err(PrismarineError::from)?;
+
+    let _ = manager.save_servers(&state.config_path).await;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn check_plugin_compatibility(
+    server_id: String,
+    target_version: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<plugin_compat::PluginCompatibility>, PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    manager
+        .check_plugin_compatibility(&server_id, &target_version)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn upgrade_server(
+    server_id: String,
+    new_version: String,
+    update_plugins: Option<bool>,
+    verify_boot: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<server_manager::UpgradeReport, PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    let report = manager
+        .upgrade_server(
+            &server_id,
+            &new_version,
+            update_plugins.unwrap_or(false),
+            verify_boot.unwrap_or(false),
+        )
+        .await
+        .map_err(PrismarineError::from)?;
+
+    let _ = manager.save_servers(&state.config_path).await;
+    Ok(report)
+}
+
+#[tauri::command]
+async fn list_restore_points(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<restore_points::RestorePoint>, PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    manager
+        .list_restore_points(&server_id)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn get_last_rollback(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<Option<restore_points::RollbackReport>, PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    Ok(manager.get_last_rollback(&server_id).await)
+}
+
+#[tauri::command]
+async fn start_server(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    state.start_server(&server_id).await
+}
+
+/// Clear the damaged-regions warning left by a forced kill
+#[tauri::command]
+async fn dismiss_region_warning(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .dismiss_region_warning(&server_id)
+        .await
+        .map_err(PrismarineError::from)?;
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn is_server_adopted(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<bool, PrismarineError> {
+    Ok(state.server_manager.lock().await.is_adopted(&server_id))
+}
+
+#[tauri::command]
+async fn import_remote_server(
+    name: String,
+    server_type: ServerType,
+    version: String,
+    remote: remote::RemoteHost,
+    panel_api_key: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<server_manager::ServerInfo, PrismarineError> {
+    if name.trim().is_empty() || remote.host.trim().is_empty() || remote.user.trim().is_empty() {
+        return Err(PrismarineError::invalid_input(
+            "Name, host and user are required",
+        ));
+    }
+    let manager = state.server_manager.lock().await.clone();
+    let server = manager
+        .import_remote_server(name, server_type, version, remote, panel_api_key)
+        .await
+        .map_err(PrismarineError::from)?;
+    let _ = manager.save_servers(&state.config_path).await;
+    Ok(server)
+}
+
+#[tauri::command]
+async fn sync_remote_server(
+    server_id: String,
+    push: bool,
+    state: State<'_, AppState>,
+) -> Result<remote::SyncReport, PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    manager
+        .sync_remote_server(&server_id, push)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn stop_server(server_id: String, state: State<'_, AppState>) -> Result<(), PrismarineError> {
+    state.stop_server(&server_id).await
+}
+
+/// Send a PlayerJoin notification for players not online at the last check.
+/// The first check of a server only learns who is there.
+async fn notify_player_joins(
+    manager: &ServerManager,
+    known: &mut std::collections::HashMap<String, Vec<String>>,
+) {
+    let running: Vec<server_manager::ServerInfo> = manager
+        .get_servers()
+        .await
+        .into_iter()
+        .filter(|s| s.status == server_manager::ServerStatus::Running && s.remote.is_none())
+        .collect();
+    known.retain(|id, _| running.iter().any(|s| &s.id == id));
+    if !notifications::has_route(notifications::NotifyEvent::PlayerJoin).await {
+        known.clear();
+        return;
+    }
+
+    for server in running {
+        let Ok(players) = manager.online_players(&server.id).await else {
+            continue;
+        };
+        if let Some(previous) = known.get(&server.id) {
+            for player in players.iter().filter(|p| !previous.contains(p)) {
+                notifications::dispatch(
+                    notifications::NotifyEvent::PlayerJoin,
+                    "Player joined",
+                    &format!("{} joined \"{}\"", player, server.name),
+                )
+                .await;
+            }
+        }
+        known.insert(server.id, players);
+    }
+}
+
+/// Bring server tunnels in line with running servers and store their addresses
+async fn sync_server_tunnels(
+    manager: &ServerManager,
+    bridge: &PrismarineBridge,
+    config_path: &std::path::Path,
+) {
+    let targets = manager.tunnel_targets().await;
+    bridge.sync_server_tunnels(&targets).await;
+
+    let mut changed = false;
+    for server in manager.get_servers().await {
+        for (bedrock, key, enabled) in [
+            (false, server.id.clone(), server.tunnel.is_some()),
+            (
+                true,
+                bridge::bedrock_key(&server.id),
+                server.bedrock_tunnel.is_some(),
+            ),
+        ] {
+            if !enabled {
+                continue;
+            }
+            let address = match bridge.get_status(Some(&key)) {
+                BridgeStatus::Connected(address) => Some(address),
+                _ => None,
+            };
+            changed |= manager
+                .set_tunnel_address(&server.id, bedrock, address)
+                .await;
+        }
+    }
+    if changed {
+        let _ = manager.save_servers(config_path).await;
+    }
+}
+
+#[tauri::command]
+async fn start_bridge_for_server(
+    server_id: String,
+    provider: Option<tunnels::TunnelKind>,
+    remote_server: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    let tunnel = server_manager::ServerTunnel {
+        provider: provider.unwrap_or_default(),
+        remote_server: remote_server.filter(|s| !s.is_empty()),
+        address: None,
+    };
+    manager
+        .set_server_tunnel(&server_id, Some(tunnel))
+        .await
+        .map_err(PrismarineError::from)?;
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)?;
+
+    // Starts right away when the server is running, otherwise with the server
+    sync_server_tunnels(&manager, &state.bridge, &state.config_path).await;
+    Ok(())
+}
+
+#[tauri::command]
+async fn stop_bridge_for_server(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    manager
+        .set_server_tunnel(&server_id, None)
+        .await
+        .map_err(PrismarineError::from)?;
+    state.bridge.stop_for_server(&server_id);
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+/// Take Bedrock players on the Java port number (UDP) and relay them to Geyser,
+/// so a single forwarded port number serves both editions
+#[tauri::command]
+async fn set_bedrock_relay(
+    server_id: String,
+    enabled: bool,
+    state: State<'_, AppState>,
+) -> Result<bool, PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    let restart_required = manager
+        .set_bedrock_relay(&server_id, enabled)
+        .await
+        .map_err(PrismarineError::from)?;
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)?;
+    state.udp_relay.sync(&manager.relay_targets().await).await;
+    Ok(restart_required)
+}
+
+/// Tunnel Geyser's Bedrock port (UDP) next to the Java port. The Java tunnel is
+/// set up with the same provider when the server has none yet.
+#[tauri::command]
+async fn start_bedrock_bridge(
+    server_id: String,
+    provider: Option<tunnels::TunnelKind>,
+    remote_server: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    let tunnel = server_manager::ServerTunnel {
+        provider: provider.unwrap_or(tunnels::TunnelKind::Frp),
+        remote_server: remote_server.filter(|s| !s.is_empty()),
+        address: None,
     };
+    manager
+        .set_bedrock_tunnel(&server_id, Some(tunnel.clone()))
+        .await
+        .map_err(PrismarineError::from)?;
+    let has_java_tunnel = manager
+        .get_server(&server_id)
+        .await
+        .is_some_and(|s| s.tunnel.is_some());
+    if !has_java_tunnel {
+        manager
+            .set_server_tunnel(&server_id, Some(tunnel))
+            .await
+            .map_err(PrismarineError::from)?;
+    }
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)?;
+
+    sync_server_tunnels(&manager, &state.bridge, &state.config_path).await;
+    Ok(())
+}
+
+#[tauri::command]
+async fn stop_bedrock_bridge(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    manager
+        .set_bedrock_tunnel(&server_id, None)
+        .await
+        .map_err(PrismarineError::from)?;
+    state
+        .bridge
+        .stop_for_server(&bridge::bedrock_key(&server_id));
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn set_server_autostart(
+    server_id: String,
+    enabled: bool,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .set_autostart(&server_id, enabled)
+        .await
+        .map_err(PrismarineError::from)?;
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn set_auto_open_port(
+    server_id: String,
+    enabled: bool,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .set_auto_open_port(&server_id, enabled)
+        .await
+        .map_err(PrismarineError::from)?;
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn delete_server(
+    server_id: String,
+    mode: Option<server_manager::DeleteMode>,
+    confirm_token: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .delete_server(
+            &server_id,
+            mode.unwrap_or(server_manager::DeleteMode::Archive),
+            confirm_token.as_deref(),
+        )
+        .await
+        .map_err(PrismarineError::from)?;
+
+    // Save servers after deletion
+    let _ = manager.save_servers(&state.config_path).await;
+    let _ = state.address_book.remove_server(&server_id);
+    state.console_bridge.stop(&server_id);
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn request_delete_confirmation(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<server_manager::DeleteConfirmation, PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .request_delete_confirmation(&server_id)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn list_deleted_servers(
+    state: State<'_, AppState>,
+) -> Result<Vec<server_manager::DeletedServer>, PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    Ok(manager.list_deleted_servers().await)
+}
+
+#[tauri::command]
+async fn restore_deleted_server(
+    trash_id: String,
+    state: State<'_, AppState>,
+) -> Result<server_manager::ServerInfo, PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    let server = manager
+        .restore_deleted_server(&trash_id)
+        .await
+        .map_err(PrismarineError::from)?;
+
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)?;
+    Ok(server)
+}
+
+#[tauri::command]
+async fn purge_deleted_server(
+    trash_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .purge_deleted_server(&trash_id)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn accept_eula(server_id: String, state: State<'_, AppState>) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .accept_eula(&server_id)
+        .await
+        .map_err(PrismarineError::from)?;
+
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn send_server_command(
+    server_id: String,
+    command: String,
+    confirm: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .send_console_command(&server_id, &command, confirm.unwrap_or(false))
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn get_pinned_commands(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<server_manager::PinnedCommand>, PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .get_pinned_commands(&server_id)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn get_recent_commands(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .get_recent_commands(&server_id)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn pin_command(
+    server_id: String,
+    label: String,
+    command: String,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .pin_command(&server_id, &label, &command)
+        .await
+        .map_err(PrismarineError::from)?;
+
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn unpin_command(
+    server_id: String,
+    label: String,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .unpin_command(&server_id, &label)
+        .await
+        .map_err(PrismarineError::from)?;
+
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn run_pinned_command(
+    server_id: String,
+    label: String,
+    confirm: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .run_pinned_command(&server_id, &label, confirm.unwrap_or(false))
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn list_worlds(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<server_manager::WorldInfo>, PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .list_worlds(&server_id)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn set_active_world(
+    server_id: String,
+    world_name: String,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .set_active_world(&server_id, &world_name)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn delete_world(
+    server_id: String,
+    world_name: String,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .delete_world(&server_id, &world_name)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn reset_world(
+    server_id: String,
+    world_name: String,
+    seed: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .reset_world(&server_id, &world_name, seed)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+fn list_templates(state: State<'_, AppState>) -> Vec<server_manager::ServerTemplate> {
+    state.templates.list()
+}
+
+/// Save an existing server's configuration as a named template
+#[tauri::command]
+async fn save_server_as_template(
+    server_id: String,
+    template_name: String,
+    state: State<'_, AppState>,
+) -> Result<server_manager::ServerTemplate, PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    let template = manager
+        .capture_template(&server_id, &template_name)
+        .await
+        .map_err(PrismarineError::from)?;
+    state.templates.add(template).map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+fn delete_template(template_id: String, state: State<'_, AppState>) -> Result<(), PrismarineError> {
+    state
+        .templates
+        .remove(&template_id)
+        .map_err(PrismarineError::from)
+}
+
+#[derive(serde::Serialize)]
+struct TemplateServerResult {
+    server: server_manager::ServerInfo,
+    skipped_plugins: Vec<String>, // Plugins that could not be downloaded again
+    readiness: server_manager::ServerReadiness,
+}
+
+#[tauri::command]
+async fn create_server_from_template(
+    template_id: String,
+    name: String,
+    port: u16,
+    eula_accepted: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<TemplateServerResult, PrismarineError> {
+    let template = state
+        .templates
+        .get(&template_id)
+        .map_err(PrismarineError::from)?;
+
+    let manager = state.server_manager.lock().await;
+    let (server, skipped_plugins) = manager
+        .create_server_from_template(&template, name, port, eula_accepted.unwrap_or(false))
+        .await
+        .map_err(PrismarineError::from)?;
+
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)?;
+
+    let readiness = manager
+        .check_readiness(&server.id)
+        .await
+        .map_err(PrismarineError::from)?;
+    Ok(TemplateServerResult {
+        server,
+        skipped_plugins,
+        readiness,
+    })
+}
+
+#[tauri::command]
+fn get_latency_regions() -> Vec<String> {
+    latency::available_regions()
+}
+
+/// Estimate player ping per region (all regions when none are given)
+#[tauri::command]
+async fn measure_latency(regions: Option<Vec<String>>) -> Vec<latency::LatencyResult> {
+    latency::measure(&regions.unwrap_or_default()).await
+}
+
+/// Connect simulated players to a running server and report the TPS/CPU impact
+#[tauri::command]
+async fn stress_test(
+    server_id: String,
+    bots: u32,
+    duration: u64, // seconds
+    state: State<'_, AppState>,
+) -> Result<stress_test::StressTestReport, PrismarineError> {
+    // Run on a handle so the manager stays usable during the test
+    let manager = state.server_manager.lock().await.clone();
+    stress_test::run(&manager, &state.monitor, &server_id, bots, duration)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn get_rcon_status(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<server_manager::RconStatus, PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .get_rcon_status(&server_id)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn set_rcon_enabled(
+    server_id: String,
+    enabled: bool,
+    port: Option<u16>,
+    localhost_only: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<server_manager::RconStatus, PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    let status = if enabled {
+        manager
+            .enable_rcon(&server_id, port, localhost_only.unwrap_or(true))
+            .await
+    } else {
+        manager.disable_rcon(&server_id).await
+    }
+    .map_err(PrismarineError::from)?;
+
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)?;
+    Ok(status)
+}
+
+#[tauri::command]
+async fn rotate_rcon_password(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<server_manager::RconStatus, PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .rotate_rcon_password(&server_id)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn issue_server_api_token(
+    server_id: String,
+    scopes: Vec<server_manager::ApiScope>,
+    state: State<'_, AppState>,
+) -> Result<server_manager::IssuedApiToken, PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    let issued = manager
+        .issue_api_token(&server_id, scopes)
+        .await
+        .map_err(PrismarineError::from)?;
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)?;
+    Ok(issued)
+}
+
+#[tauri::command]
+async fn revoke_server_api_token(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .revoke_api_token(&server_id)
+        .await
+        .map_err(PrismarineError::from)?;
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn get_console_bridge(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<server_manager::ConsoleBridgeConfig, PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .get_console_bridge(&server_id)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn set_console_bridge(
+    server_id: String,
+    config: server_manager::ConsoleBridgeConfig,
+    state: State<'_, AppState>,
+) -> Result<server_manager::ConsoleBridgeConfig, PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    let config = manager
+        .set_console_bridge(&server_id, config)
+        .await
+        .map_err(PrismarineError::from)?;
+
+    state
+        .console_bridge
+        .apply(manager.clone(), &server_id, &config)
+        .await
+        .map_err(PrismarineError::from)?;
+
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)?;
+    Ok(config)
+}
+
+#[tauri::command]
+async fn get_dynamic_view_distance(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<server_manager::DynamicViewDistance, PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .get_dynamic_view_distance(&server_id)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn set_dynamic_view_distance(
+    server_id: String,
+    config: server_manager::DynamicViewDistance,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .set_dynamic_view_distance(&server_id, config)
+        .await
+        .map_err(PrismarineError::from)?;
+
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn import_world(
+    server_id: String,
+    zip_path: String,
+    world_name: String,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .import_world(&server_id, &PathBuf::from(zip_path), &world_name)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn export_world(
+    server_id: String,
+    world_name: String,
+    dest_path: String,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    // Waits for a backup slot, keep the manager usable meanwhile
+    let manager = state.server_manager.lock().await.clone();
+    manager
+        .export_world(&server_id, &world_name, &PathBuf::from(dest_path))
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn set_server_java(
+    server_id: String,
+    path: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Option<u8>, PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    let version = manager
+        .set_server_java(&server_id, path)
+        .await
+        .map_err(PrismarineError::from)?;
+
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)?;
+    Ok(version)
+}
+
+#[tauri::command]
+async fn set_jvm_args(
+    server_id: String,
+    args: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .set_jvm_args(&server_id, args)
+        .await
+        .map_err(PrismarineError::from)?;
+
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn analyze_lag(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<lag::LagReport, PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    manager
+        .analyze_lag(&server_id)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+fn get_jvm_profiles() -> Vec<server_manager::JvmProfileInfo> {
+    server_manager::JvmProfile::ALL
+        .iter()
+        .map(|profile| server_manager::JvmProfileInfo {
+            id: *profile,
+            label: profile.label(),
+            min_java: profile.min_java(),
+        })
+        .collect()
+}
+
+#[tauri::command]
+async fn set_jvm_profile(
+    server_id: String,
+    profile: server_manager::JvmProfile,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .set_jvm_profile(&server_id, profile)
+        .await
+        .map_err(PrismarineError::from)?;
+
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn get_announcements(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<Option<server_manager::AnnouncementConfig>, PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .get_announcements(&server_id)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn set_announcements(
+    server_id: String,
+    config: server_manager::AnnouncementConfig,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .set_announcements(&server_id, config)
+        .await
+        .map_err(PrismarineError::from)?;
+
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn get_game_rules(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<gamerules::GameRulesStatus, PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    manager
+        .get_game_rules(&server_id)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn set_game_rules(
+    server_id: String,
+    rules: gamerules::GameRules,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    manager
+        .set_game_rules(&server_id, &rules)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn get_world_settings(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<gamerules::WorldSettingsStatus, PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    manager
+        .get_world_settings(&server_id)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn set_world_settings(
+    server_id: String,
+    settings: gamerules::WorldSettings,
+    state: State<'_, AppState>,
+) -> Result<gamerules::WorldSettingsResult, PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    manager
+        .set_world_settings(&server_id, &settings)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn get_scheduled_tasks(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<server_manager::ScheduledTask>, PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .get_scheduled_tasks(&server_id)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn save_scheduled_task(
+    server_id: String,
+    task: server_manager::ScheduledTask,
+    state: State<'_, AppState>,
+) -> Result<server_manager::ScheduledTask, PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    let task = manager
+        .save_scheduled_task(&server_id, task)
+        .await
+        .map_err(PrismarineError::from)?;
+
+    let _ = manager.save_servers(&state.config_path).await;
+
+    Ok(task)
+}
+
+#[tauri::command]
+async fn remove_scheduled_task(
+    server_id: String,
+    task_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .remove_scheduled_task(&server_id, &task_id)
+        .await
+        .map_err(PrismarineError::from)?;
+
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+/// Folders in use this session; path settings apply on the next start
+#[tauri::command]
+fn get_app_paths(state: State<'_, AppState>) -> AppPaths {
+    state.paths.clone()
+}
+
+#[tauri::command]
+async fn get_app_settings(state: State<'_, AppState>) -> Result<AppSettings, PrismarineError> {
+    AppSettings::load(&state.settings_path)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn set_app_settings(
+    settings: AppSettings,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    if !settings.servers_dir.is_absolute() {
+        return Err(PrismarineError::invalid_input(
+            "Servers directory must be an absolute path",
+        ));
+    }
+    std::fs::create_dir_all(&settings.servers_dir).map_err(|e| {
+        PrismarineError::invalid_input(format!(
+            "Cannot use {}: {}",
+            settings.servers_dir.display(),
+            e
+        ))
+    })?;
+    let overrides = &settings.paths;
+    for dir in [
+        &overrides.data_dir,
+        &overrides.cache_dir,
+        &overrides.log_dir,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        if !dir.is_absolute() {
+            return Err(PrismarineError::invalid_input(
+                "Folder overrides must be absolute paths",
+            ));
+        }
+    }
+
+    settings
+        .save(&state.settings_path)
+        .await
+        .map_err(PrismarineError::from)?;
+    offline::set(settings.offline_mode);
+    limits::apply(&settings.limits);
+    if state.instance_lock.is_owner() {
+        state
+            .rest_api
+            .apply(&settings.rest_api, app)
+            .await
+            .map_err(PrismarineError::from)?;
+    }
+    state
+        .server_manager
+        .lock()
+        .await
+        .set_base_path(settings.servers_dir);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_rest_api_status(state: State<'_, AppState>) -> rest_api::RestApiStatus {
+    state.rest_api.status()
+}
+
+/// New app token for the REST API, returned once for the user to copy
+#[tauri::command]
+fn regenerate_rest_api_token() -> Result<String, PrismarineError> {
+    rest_api::regenerate_token().map_err(PrismarineError::from)
+}
+
+/// Window close: follow the exit policy, or ask the frontend while servers are running
+async fn handle_close_request(app: tauri::AppHandle) {
+    let state = app.state::<AppState>();
+    let manager = state.server_manager.lock().await.clone();
+    let running = manager.running_local_servers().await;
+    if running.is_empty() {
+        app.exit(0);
+        return;
+    }
+    let policy = AppSettings::load(&state.settings_path)
+        .await
+        .map(|settings| settings.exit_policy)
+        .unwrap_or_default();
+    match policy {
+        ExitPolicy::Ask => {
+            let _ = app.emit("exit-requested", running);
+        }
+        policy => exit_with_policy(&app, &manager, policy).await,
+    }
+}
+
+async fn exit_with_policy(app: &tauri::AppHandle, manager: &ServerManager, policy: ExitPolicy) {
+    let state = app.state::<AppState>();
+    if policy == ExitPolicy::Stop {
+        let stopped = manager.stop_all_servers().await;
+        println!("[Exit] Stopped {} servers", stopped.len());
+    } else {
+        // Their pids are saved, recover_processes reattaches them on the next launch
+        println!("[Exit] Leaving running servers detached");
+    }
+    let _ = manager.save_servers(&state.config_path).await;
+    app.exit(0);
+}
+
+#[tauri::command]
+async fn exit_app(
+    policy: ExitPolicy,
+    remember: bool,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    if policy == ExitPolicy::Ask {
+        return Err(PrismarineError::invalid_input(
+            "Choose whether to stop or detach the servers",
+        ));
+    }
+    if remember {
+        let mut settings = AppSettings::load(&state.settings_path)
+            .await
+            .map_err(PrismarineError::from)?;
+        settings.exit_policy = policy;
+        settings
+            .save(&state.settings_path)
+            .await
+            .map_err(PrismarineError::from)?;
+    }
+    let manager = state.server_manager.lock().await.clone();
+    exit_with_policy(&app, &manager, policy).await;
+    Ok(())
+}
+
+#[tauri::command]
+async fn rename_server(
+    server_id: String,
+    new_name: String,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .rename_server(&server_id, &new_name)
+        .await
+        .map_err(PrismarineError::from)?;
+
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn start_transfer_receiver(
+    port: Option<u16>,
+    state: State<'_, AppState>,
+) -> Result<transfer::TransferPairing, PrismarineError> {
+    if !state.instance_lock.is_owner() {
+        return Err(PrismarineError::invalid_input(
+            "This instance is read-only and cannot receive servers",
+        ));
+    }
+    let manager = state.server_manager.lock().await.clone();
+    state
+        .transfer
+        .start(manager, state.config_path.clone(), port)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+fn stop_transfer_receiver(state: State<'_, AppState>) {
+    state.transfer.stop();
+}
+
+#[tauri::command]
+fn get_transfer_receiver(state: State<'_, AppState>) -> Option<transfer::TransferPairing> {
+    state.transfer.status()
+}
+
+/// Remember the pairing code shown by the receiving instance at `target_host`
+#[tauri::command]
+fn pair_transfer_target(target_host: String, code: String) -> Result<(), PrismarineError> {
+    if target_host.trim().is_empty() || code.trim().is_empty() {
+        return Err(PrismarineError::invalid_input(
+            "Enter the address and the pairing code of the receiving instance",
+        ));
+    }
+    secrets::set(&secrets::transfer_key(target_host.trim()), code.trim())
+        .map_err(PrismarineError::from)
+}
+
+/// Send a stopped server to a paired instance; returns its id over there
+#[tauri::command]
+async fn transfer_server(
+    server_id: String,
+    target_host: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<String, PrismarineError> {
+    let target_host = target_host.trim().to_string();
+    let code = secrets::get(&secrets::transfer_key(&target_host))
+        .map_err(PrismarineError::from)?
+        .ok_or_else(|| {
+            PrismarineError::invalid_input(format!(
+                "{} is not paired, enter its pairing code first",
+                target_host
+            ))
+        })?;
+
+    // Zipping and sending take a while, keep the manager usable meanwhile
+    let manager = state.server_manager.lock().await.clone();
+    let server = manager
+        .get_server(&server_id)
+        .await
+        .ok_or_else(|| PrismarineError::not_found("Server not found"))?;
+    let bundle_path = manager
+        .build_transfer_bundle(&server_id)
+        .await
+        .map_err(PrismarineError::from)?;
+
+    let progress_id = server_id.clone();
+    let remote_id = transfer::send_bundle(
+        &bundle_path,
+        &server.name,
+        &target_host,
+        &code,
+        move |sent, total| {
+            let _ = app.emit(
+                "server-transfer-progress",
+                transfer::TransferProgress {
+                    server_id: progress_id.clone(),
+                    sent,
+                    total,
+                    done: sent == total,
+                },
+            );
+        },
+    )
+    .await
+    .map_err(PrismarineError::from)?;
+
+    println!("[Transfer] Sent {} to {}", server.name, target_host);
+    let _ = tokio::fs::remove_file(&bundle_path).await;
+    Ok(remote_id)
+}
+
+#[tauri::command]
+async fn move_server(
+    server_id: String,
+    new_base_path: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<server_manager::ServerInfo, PrismarineError> {
+    // Copying a large server can take minutes, keep the manager usable meanwhile
+    let manager = state.server_manager.lock().await.clone();
+    let server = manager
+        .move_server(&server_id, &PathBuf::from(new_base_path), move |progress| {
+            let _ = app.emit("server-move-progress", progress);
+        })
+        .await
+        .map_err(PrismarineError::from)?;
+
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)?;
+    Ok(server)
+}
+
+#[tauri::command]
+async fn set_console_safety(
+    server_id: String,
+    enabled: bool,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .set_console_safety(&server_id, enabled)
+        .await
+        .map_err(PrismarineError::from)?;
+
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn install_geyser_support(
+    server_id: String,
+    optional_pack: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    manager
+        .install_geyser(&server_id, optional_pack.unwrap_or(false))
+        .await
+        .map_err(PrismarineError::from)?;
+    resolve_geyser_ports(&manager, &state.port_manager).await;
+    Ok(())
+}
+
+/// Give each Geyser server a unique Bedrock port. When a moved server's Java port
+/// is a managed port, its new Bedrock port is registered as a managed UDP port.
+async fn resolve_geyser_ports(
+    manager: &ServerManager,
+    port_manager: &PortManager,
+) -> Vec<server_manager::GeyserPortChange> {
+    let changes = match manager.resolve_geyser_port_conflicts().await {
+        Ok(changes) => changes,
+        Err(e) => {
+            println!("[Geyser] Failed to resolve Bedrock port conflicts: {}", e);
+            return Vec::new();
+        }
+    };
+    for change in &changes {
+        register_bedrock_port(
+            port_manager,
+            change.java_port,
+            change.new_port,
+            &change.server_name,
+        )
+        .await;
+    }
+    changes
+}
+
+async fn register_bedrock_port(port_manager: &PortManager, java_port: u16, port: u16, name: &str) {
+    let Some(java) = port_manager
+        .get_managed_ports()
+        .into_iter()
+        .find(|p| p.port == java_port)
+    else {
+        return;
+    };
+    match port_manager
+        .ensure_managed_udp_port(port, &format!("{} (Bedrock)", name), java.family)
+        .await
+    {
+        Ok(Some(slot)) => println!("[Geyser] Registered UDP {} in slot {}", port, slot),
+        Ok(None) => {}
+        Err(e) => println!("[Geyser] Failed to register UDP {}: {}", port, e),
+    }
+}
+
+#[tauri::command]
+async fn resolve_geyser_port_conflicts(
+    state: State<'_, AppState>,
+) -> Result<Vec<server_manager::GeyserPortChange>, PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    Ok(resolve_geyser_ports(&manager, &state.port_manager).await)
+}
+
+#[tauri::command]
+async fn get_geyser_port(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<Option<u16>, PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    manager
+        .get_geyser_port(&server_id)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn set_geyser_port(
+    server_id: String,
+    port: u16,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    manager
+        .set_geyser_port(&server_id, port)
+        .await
+        .map_err(PrismarineError::from)?;
+    if let Some(server) = manager.get_server(&server_id).await {
+        register_bedrock_port(&state.port_manager, server.port, port, &server.name).await;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_geyser_config(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<server_manager::GeyserConfig, PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    manager
+        .get_geyser_config(&server_id)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn set_geyser_config(
+    server_id: String,
+    config: server_manager::GeyserConfig,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    manager
+        .set_geyser_config(&server_id, &config)
+        .await
+        .map_err(PrismarineError::from)?;
+    if let Some(server) = manager.get_server(&server_id).await {
+        // With clone-remote-port Bedrock shares the Java port number over UDP
+        let port = if config.clone_remote_port {
+            server.port
+        } else {
+            config.bedrock_port
+        };
+        register_bedrock_port(&state.port_manager, server.port, port, &server.name).await;
+    }
+    Ok(())
+}
 
-    let manager = state.server_manager.lock().await;
-    let result = manager
-        .create_server(name, version, st, port, max_memory)
+#[tauri::command]
+async fn list_installed_javas() -> Result<Vec<JavaRuntime>, PrismarineError> {
+    tokio::task::spawn_blocking(|| JavaManager::new().list_installed())
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| PrismarineError::from(anyhow::Error::from(e)))
+}
 
-    // Save servers after creation
-    let _ = manager.save_servers(&state.config_path).await;
+#[tauri::command]
+async fn install_java(version: u8) -> Result<JavaRuntime, PrismarineError> {
+    if version < 8 {
+        return Err(PrismarineError::invalid_input(format!(
+            "Java {} is not supported",
+            version
+        )));
+    }
+    JavaManager::new()
+        .install(version)
+        .await
+        .map_err(PrismarineError::from)
+}
 
-    Ok(result)
+#[tauri::command]
+async fn verify_java_installation(version: u8) -> Result<JavaVerification, PrismarineError> {
+    JavaManager::new()
+        .verify(version)
+        .await
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
-async fn start_server(server_id: String, state: State<'_, AppState>) -> Result<(), String> {
-    let manager = state.server_manager.lock().await;
-    manager
-        .start_server(&server_id)
+fn remove_java(version: u8) -> Result<(), PrismarineError> {
+    JavaManager::new()
+        .remove(version)
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn get_recommended_java(
+    mc_version: String,
+    modded: Option<bool>,
+) -> Result<JavaRecommendation, PrismarineError> {
+    tokio::task::spawn_blocking(move || {
+        JavaManager::new().recommended(&mc_version, modded.unwrap_or(false))
+    })
+    .await
+    .map_err(|e| PrismarineError::from(anyhow::Error::from(e)))
+}
+
+#[tauri::command]
+async fn check_buildtools(
+    mc_version: String,
+) -> Result<buildtools::BuildToolsCheck, PrismarineError> {
+    tokio::task::spawn_blocking(move || buildtools::check(&mc_version))
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| PrismarineError::from(anyhow::Error::from(e)))
 }
 
 #[tauri::command]
-async fn stop_server(server_id: String, state: State<'_, AppState>) -> Result<(), String> {
-    let manager = state.server_manager.lock().await;
-    manager
-        .stop_server(&server_id)
+async fn install_portable_git() -> Result<String, PrismarineError> {
+    buildtools::install_portable_git()
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
-async fn delete_server(server_id: String, state: State<'_, AppState>) -> Result<(), String> {
-    let manager = state.server_manager.lock().await;
+async fn apply_security_preset(
+    server_id: String,
+    install_plugin: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<server_manager::SecurityPresetReport, PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
     manager
-        .delete_server(&server_id)
+        .apply_security_preset(&server_id, install_plugin.unwrap_or(false))
         .await
-        .map_err(|e| e.to_string())?;
-
-    // Save servers after deletion
-    let _ = manager.save_servers(&state.config_path).await;
+        .map_err(PrismarineError::from)
+}
 
-    Ok(())
+#[tauri::command]
+async fn install_viaversion_support(
+    server_id: String,
+    backwards: Option<bool>,
+    rewind: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    manager
+        .install_viaversion(
+            &server_id,
+            backwards.unwrap_or(false),
+            rewind.unwrap_or(false),
+        )
+        .await
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
-async fn send_server_command(
+async fn is_geyser_installed(
     server_id: String,
-    command: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<bool, PrismarineError> {
     let manager = state.server_manager.lock().await;
     manager
-        .send_command(&server_id, &command)
+        .check_geyser_installed(&server_id)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
-async fn install_geyser_support(
+async fn is_viaversion_installed(
     server_id: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<protocol_versions::ViaPlugins, PrismarineError> {
     let manager = state.server_manager.lock().await;
     manager
-        .install_geyser(&server_id)
+        .check_viaversion_installed(&server_id)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
-async fn install_viaversion_support(
+async fn get_plugin_reload_method(
     server_id: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
-    let manager = state.server_manager.lock().await;
+) -> Result<Option<server_manager::ReloadMethod>, PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
     manager
-        .install_viaversion(&server_id)
+        .plugin_reload_method(&server_id)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
-async fn is_geyser_installed(
+async fn reload_plugin(
     server_id: String,
+    plugin_name: String,
+    allow_full_reload: Option<bool>,
     state: State<'_, AppState>,
-) -> Result<bool, String> {
-    let manager = state.server_manager.lock().await;
+) -> Result<server_manager::ReloadMethod, PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
     manager
-        .check_geyser_installed(&server_id)
+        .reload_plugin(&server_id, &plugin_name, allow_full_reload.unwrap_or(false))
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
-async fn is_viaversion_installed(
+async fn get_client_versions(
     server_id: String,
     state: State<'_, AppState>,
-) -> Result<bool, String> {
-    let manager = state.server_manager.lock().await;
+) -> Result<protocol_versions::ClientVersions, PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
     manager
-        .check_viaversion_installed(&server_id)
+        .client_versions(&server_id)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
 async fn uninstall_geyser_support(
     server_id: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), PrismarineError> {
     let manager = state.server_manager.lock().await;
     manager
         .uninstall_geyser(&server_id)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
 async fn uninstall_viaversion_support(
     server_id: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), PrismarineError> {
     let manager = state.server_manager.lock().await;
     manager
         .uninstall_viaversion(&server_id)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
@@ -186,12 +2015,12 @@ async fn search_plugins(
     query: String,
     source: String,
     state: State<'_, AppState>,
-) -> Result<Vec<server_manager::PluginSearchResult>, String> {
+) -> Result<Vec<server_manager::PluginSearchResult>, PrismarineError> {
     let manager = state.server_manager.lock().await;
     manager
         .search_plugins(&server_id, &query, &source)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
@@ -199,13 +2028,20 @@ async fn install_plugin(
     server_id: String,
     download_url: String,
     filename: Option<String>,
+    allow_flagged: Option<bool>,
     state: State<'_, AppState>,
-) -> Result<(), String> {
-    let manager = state.server_manager.lock().await;
+) -> Result<(), PrismarineError> {
+    // Downloads run on a handle so other commands are not blocked meanwhile
+    let manager = state.server_manager.lock().await.clone();
     manager
-        .install_plugin_by_url(&server_id, &download_url, filename)
+        .install_plugin_by_url(
+            &server_id,
+            &download_url,
+            filename,
+            allow_flagged.unwrap_or(false),
+        )
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
@@ -214,12 +2050,25 @@ async fn install_modrinth_plugin(
     project_id: String,
     plugin_name: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
-    let manager = state.server_manager.lock().await;
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
     manager
         .install_modrinth_plugin(&server_id, &project_id, &plugin_name)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn install_modpack(
+    server_id: String,
+    source: String,
+    state: State<'_, AppState>,
+) -> Result<modpack::ModpackInstallResult, PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    manager
+        .install_modpack(&server_id, &source)
+        .await
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
@@ -228,12 +2077,49 @@ async fn install_spigot_plugin(
     resource_id: String,
     plugin_name: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
-    let manager = state.server_manager.lock().await;
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
     manager
         .install_spigot_plugin(&server_id, &resource_id, &plugin_name)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn get_malware_scan_config(
+    state: State<'_, AppState>,
+) -> Result<malware_scan::ScanConfig, PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    Ok(manager.malware_scanner().get_config())
+}
+
+#[tauri::command]
+async fn set_malware_scan_config(
+    config: malware_scan::ScanConfig,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .malware_scanner()
+        .set_config(&config)
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn list_quarantine(
+    state: State<'_, AppState>,
+) -> Result<Vec<malware_scan::QuarantinedFile>, PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    Ok(manager.malware_scanner().list_quarantine())
+}
+
+#[tauri::command]
+async fn delete_quarantined(id: String, state: State<'_, AppState>) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .malware_scanner()
+        .delete_quarantined(&id)
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
@@ -241,12 +2127,12 @@ async fn uninstall_plugin(
     server_id: String,
     plugin_name: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), PrismarineError> {
     let manager = state.server_manager.lock().await;
     manager
         .uninstall_plugin(&server_id, &plugin_name)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
@@ -254,27 +2140,82 @@ async fn is_plugin_installed(
     server_id: String,
     plugin_name: String,
     state: State<'_, AppState>,
-) -> Result<bool, String> {
+) -> Result<bool, PrismarineError> {
     let manager = state.server_manager.lock().await;
     manager
         .is_plugin_installed(&server_id, &plugin_name)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
 async fn get_servers(
+    query: Option<server_manager::ServerQuery>,
+    state: State<'_, AppState>,
+) -> Result<server_manager::ServerListing, PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    Ok(manager.list_servers(&query.unwrap_or_default()).await)
+}
+
+#[tauri::command]
+async fn set_server_tags(
+    server_id: String,
+    tags: Vec<String>,
     state: State<'_, AppState>,
-) -> Result<Vec<server_manager::ServerInfo>, String> {
+) -> Result<Vec<String>, PrismarineError> {
     let manager = state.server_manager.lock().await;
-    Ok(manager.get_servers().await)
+    let tags = manager
+        .set_server_tags(&server_id, tags)
+        .await
+        .map_err(PrismarineError::from)?;
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)?;
+    Ok(tags)
+}
+
+#[tauri::command]
+async fn set_server_group(
+    server_id: String,
+    group: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Option<String>, PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    let group = manager
+        .set_server_group(&server_id, group)
+        .await
+        .map_err(PrismarineError::from)?;
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)?;
+    Ok(group)
+}
+
+/// Start the servers of a group, backends before the proxy
+#[tauri::command]
+async fn start_group(
+    group: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, PrismarineError> {
+    state.start_group(&group).await
+}
+
+/// Stop the servers of a group, the proxy before the backends
+#[tauri::command]
+async fn stop_group(
+    group: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, PrismarineError> {
+    state.stop_group(&group).await
 }
 
 #[tauri::command]
 async fn get_server(
     server_id: String,
     state: State<'_, AppState>,
-) -> Result<Option<server_manager::ServerInfo>, String> {
+) -> Result<Option<server_manager::ServerInfo>, PrismarineError> {
     let manager = state.server_manager.lock().await;
     Ok(manager.get_server(&server_id).await)
 }
@@ -285,31 +2226,32 @@ async fn open_managed_port(
     protocol: String,
     name: String,
     slot: u8,
+    family: Option<port_manager::AddressFamily>,
     state: State<'_, AppState>,
-) -> Result<String, String> {
+) -> Result<String, PrismarineError> {
     state
         .port_manager
-        .open_managed_port(port, &protocol, &name, slot)
+        .open_managed_port(port, &protocol, &name, slot, family.unwrap_or_default())
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
-async fn close_managed_port(slot: u8, state: State<'_, AppState>) -> Result<(), String> {
+async fn close_managed_port(slot: u8, state: State<'_, AppState>) -> Result<(), PrismarineError> {
     state
         .port_manager
         .close_managed_port(slot)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
-async fn delete_managed_port(slot: u8, state: State<'_, AppState>) -> Result<(), String> {
+async fn delete_managed_port(slot: u8, state: State<'_, AppState>) -> Result<(), PrismarineError> {
     state
         .port_manager
         .delete_managed_port(slot)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
@@ -317,37 +2259,84 @@ async fn set_managed_port_active(
     slot: u8,
     active: bool,
     state: State<'_, AppState>,
-) -> Result<String, String> {
+) -> Result<String, PrismarineError> {
     state
         .port_manager
         .set_managed_port_active(slot, active)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
+}
+
+/// Free port for the create-server flow (defaults to 25565-25665)
+#[tauri::command]
+async fn find_free_port(
+    start: Option<u16>,
+    end: Option<u16>,
+    state: State<'_, AppState>,
+) -> Result<Option<u16>, PrismarineError> {
+    let start = start.unwrap_or(25565);
+    let end = end.unwrap_or(start.saturating_add(100));
+    if end < start {
+        return Err(PrismarineError::invalid_input("Invalid port range"));
+    }
+    let manager = state.server_manager.lock().await;
+    Ok(manager.find_free_port(start, end).await)
 }
 
 #[tauri::command]
 async fn get_managed_ports(
     state: State<'_, AppState>,
-) -> Result<Vec<port_manager::ManagedPort>, String> {
-    Ok(state.port_manager.get_managed_ports())
+) -> Result<Vec<port_manager::ManagedPortStatus>, PrismarineError> {
+    let servers = state.server_manager.lock().await.get_servers().await;
+    Ok(state.port_manager.get_managed_ports_status(&servers).await)
+}
+
+#[tauri::command]
+async fn get_external_ip(
+    state: State<'_, AppState>,
+) -> Result<port_manager::ExternalAddresses, PrismarineError> {
+    Ok(state.port_manager.get_external_addresses().await)
 }
 
 #[tauri::command]
-async fn get_external_ip(state: State<'_, AppState>) -> Result<String, String> {
+async fn get_upnp_diagnostics(
+    state: State<'_, AppState>,
+) -> Result<port_manager::UpnpDiagnostics, PrismarineError> {
+    Ok(state.port_manager.get_upnp_diagnostics().await)
+}
+
+#[tauri::command]
+async fn test_port_reachability(
+    port: u16,
+    protocol: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<port_manager::ReachabilityReport, PrismarineError> {
     state
         .port_manager
-        .get_external_ip()
+        .test_port_reachability(port, protocol.as_deref().unwrap_or("TCP"))
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
-async fn is_upnp_available(state: State<'_, AppState>) -> Result<bool, String> {
+async fn is_upnp_available(state: State<'_, AppState>) -> Result<bool, PrismarineError> {
     Ok(state.port_manager.is_upnp_available().await)
 }
 
+/// Traffic-light health of a server with the reasons behind it
+#[tauri::command]
+async fn get_server_health(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<health::ServerHealth, PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    health::check(&manager, &state.monitor, &server_id)
+        .await
+        .map_err(PrismarineError::from)
+}
+
 #[tauri::command]
-fn get_system_stats(state: State<'_, AppState>) -> Result<monitor::SystemStats, String> {
+fn get_system_stats(state: State<'_, AppState>) -> Result<monitor::SystemStats, PrismarineError> {
     let mut monitor = state.monitor.lock().unwrap();
     Ok(monitor.get_system_stats())
 }
@@ -357,29 +2346,18 @@ async fn get_server_logs(
     server_id: String,
     lines: usize,
     state: State<'_, AppState>,
-) -> Result<Vec<String>, String> {
-    let server_path = {
-        let manager = state.server_manager.lock().await;
-        if let Some(server) = manager.get_server(&server_id).await {
-            server.path.clone()
-        } else {
-            return Err("Server not found".to_string());
-        }
-    };
-
-    Monitor::get_server_logs(&server_path, lines)
-        .await
-        .map_err(|e| e.to_string())
+) -> Result<Vec<String>, PrismarineError> {
+    state.server_logs(&server_id, lines).await
 }
 
 #[tauri::command]
-async fn open_folder(path: String) -> Result<(), String> {
+async fn open_folder(path: String) -> Result<(), PrismarineError> {
     #[cfg(target_os = "windows")]
     {
         std::process::Command::new("explorer")
             .arg(&path)
             .spawn()
-            .map_err(|e| format!("Failed to open folder: {}", e))?;
+            .context("Failed to open folder")?;
     }
 
     #[cfg(target_os = "macos")]
@@ -387,7 +2365,7 @@ async fn open_folder(path: String) -> Result<(), String> {
         std::process::Command::new("open")
             .arg(&path)
             .spawn()
-            .map_err(|e| e.to_string())?;
+            .context("Failed to open folder")?;
     }
 
     #[cfg(target_os = "linux")]
@@ -395,19 +2373,22 @@ async fn open_folder(path: String) -> Result<(), String> {
         std::process::Command::new("xdg-open")
             .arg(&path)
             .spawn()
-            .map_err(|e| e.to_string())?;
+            .context("Failed to open folder")?;
     }
 
     Ok(())
 }
 
 #[tauri::command]
-async fn get_motd(server_id: String, state: State<'_, AppState>) -> Result<String, String> {
+async fn get_motd(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<String, PrismarineError> {
     let manager = state.server_manager.lock().await;
     manager
         .get_server_motd(&server_id)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
@@ -415,21 +2396,24 @@ async fn set_motd(
     server_id: String,
     motd: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), PrismarineError> {
     let manager = state.server_manager.lock().await;
     manager
         .set_server_motd(&server_id, &motd)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
-async fn get_max_players(server_id: String, state: State<'_, AppState>) -> Result<u32, String> {
+async fn get_max_players(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<u32, PrismarineError> {
     let manager = state.server_manager.lock().await;
     manager
         .get_server_max_players(&server_id)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
@@ -437,22 +2421,25 @@ async fn set_max_players(
     server_id: String,
     max_players: u32,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), PrismarineError> {
     let manager = state.server_manager.lock().await;
     manager
         .set_server_max_players(&server_id, max_players)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
-async fn open_server_folder(server_id: String, state: State<'_, AppState>) -> Result<(), String> {
+async fn open_server_folder(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
     let server_path = {
         let manager = state.server_manager.lock().await;
         if let Some(server) = manager.get_server(&server_id).await {
             server.path.clone()
         } else {
-            return Err("Server not found".to_string());
+            return Err(PrismarineError::not_found("Server not found"));
         }
     };
 
@@ -460,13 +2447,16 @@ async fn open_server_folder(server_id: String, state: State<'_, AppState>) -> Re
 }
 
 #[tauri::command]
-async fn open_plugins_folder(server_id: String, state: State<'_, AppState>) -> Result<(), String> {
+async fn open_plugins_folder(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
     let server_path = {
         let manager = state.server_manager.lock().await;
         manager
             .get_plugins_path(&server_id)
             .await
-            .map_err(|e| e.to_string())?
+            .map_err(PrismarineError::from)?
     };
 
     // Ensure plugins folder exists
@@ -478,21 +2468,27 @@ async fn open_plugins_folder(server_id: String, state: State<'_, AppState>) -> R
 }
 
 #[tauri::command]
-async fn restart_server(server_id: String, state: State<'_, AppState>) -> Result<(), String> {
+async fn restart_server(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
     let manager = state.server_manager.lock().await;
     manager
         .restart_server(&server_id)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
 async fn get_ops(
     server_id: String,
     state: State<'_, AppState>,
-) -> Result<Vec<server_manager::OpEntry>, String> {
+) -> Result<Vec<server_manager::OpEntry>, PrismarineError> {
     let manager = state.server_manager.lock().await;
-    manager.get_ops(&server_id).await.map_err(|e| e.to_string())
+    manager
+        .get_ops(&server_id)
+        .await
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
@@ -500,12 +2496,12 @@ async fn grant_op(
     server_id: String,
     player: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), PrismarineError> {
     let manager = state.server_manager.lock().await;
     manager
         .grant_op(&server_id, &player)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
@@ -513,34 +2509,155 @@ async fn revoke_op(
     server_id: String,
     player: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), PrismarineError> {
     let manager = state.server_manager.lock().await;
     manager
         .revoke_op(&server_id, &player)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn get_floodgate_settings(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<server_manager::FloodgateSettings, PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .get_floodgate_settings(&server_id)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn get_whitelist(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<server_manager::WhitelistEntry>, PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .get_whitelist(&server_id)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn add_to_whitelist(
+    server_id: String,
+    player: String,
+    bedrock: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .add_to_whitelist(&server_id, &player, bedrock.unwrap_or(false))
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn remove_from_whitelist(
+    server_id: String,
+    player: String,
+    bedrock: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .remove_from_whitelist(&server_id, &player, bedrock.unwrap_or(false))
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn get_banned_players(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<server_manager::BanEntry>, PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .get_banned_players(&server_id)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn ban_player(
+    server_id: String,
+    player: String,
+    reason: Option<String>,
+    bedrock: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .ban_player(
+            &server_id,
+            &player,
+            reason.as_deref(),
+            bedrock.unwrap_or(false),
+        )
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn pardon_player(
+    server_id: String,
+    player: String,
+    bedrock: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .pardon_player(&server_id, &player, bedrock.unwrap_or(false))
+        .await
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
 async fn get_online_players(
     server_id: String,
     state: State<'_, AppState>,
-) -> Result<Vec<String>, String> {
-    let server_path = {
-        let manager = state.server_manager.lock().await;
-        if let Some(server) = manager.get_server(&server_id).await {
-            server.path.clone()
-        } else {
-            return Err("Server not found".to_string());
-        }
-    };
+) -> Result<Vec<String>, PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    if manager.get_server(&server_id).await.is_none() {
+        return Err(PrismarineError::not_found("Server not found"));
+    }
+    manager
+        .online_players(&server_id)
+        .await
+        .map_err(PrismarineError::from)
+}
 
-    Monitor::get_online_players(&server_path)
+/// Server list ping: online count, player sample, version and favicon
+#[tauri::command]
+async fn query_server_status(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<query::StatusResponse, PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    manager
+        .query_status(&server_id)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+/// UDP query: every online player and the plugin list
+#[tauri::command]
+async fn query_server_details(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<query::QueryResponse, PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    manager
+        .query_full_stat(&server_id)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn set_auto_restart(
     server_id: String,
     enabled: bool,
@@ -548,8 +2665,9 @@ async fn set_auto_restart(
     interval: u64,
     schedule: Option<String>,
     time_zone: Option<String>,
+    restart_warnings: Option<Vec<u64>>, // seconds before restart, e.g. [300, 60, 10]
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), PrismarineError> {
     let r_type = match restart_type.as_str() {
         "Schedule" => RestartType::Schedule,
         _ => RestartType::Interval,
@@ -557,9 +2675,29 @@ async fn set_auto_restart(
 
     let manager = state.server_manager.lock().await;
     manager
-        .set_auto_restart(&server_id, enabled, r_type, interval, schedule, time_zone)
+        .set_auto_restart(&server_id, enabled, r_type, interval, schedule, time_zone)
+        .await
+        .map_err(PrismarineError::from)?;
+
+    if let Some(warnings) = restart_warnings {
+        manager
+            .set_restart_warnings(&server_id, warnings)
+            .await
+            .map_err(PrismarineError::from)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn suggest_memory(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<server_manager::MemorySuggestion, PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    manager
+        .suggest_memory(&server_id)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
@@ -568,71 +2706,78 @@ async fn set_server_memory(
     memory: String, // This will be max_memory from frontend
     min_memory: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), PrismarineError> {
     let manager = state.server_manager.lock().await;
     manager
         .set_server_memory(&server_id, &memory, &min_memory)
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(PrismarineError::from)?;
 
     manager
         .save_servers(&state.config_path)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
 async fn fetch_versions(
     server_type: String,
+    include_snapshots: Option<bool>,
     state: State<'_, AppState>,
-) -> Result<Vec<String>, String> {
+) -> Result<Vec<String>, PrismarineError> {
+    offline::ensure_online("Fetching versions").map_err(PrismarineError::from)?;
+    let include_snapshots = include_snapshots.unwrap_or(false);
     let manager = state.server_manager.lock().await;
     match server_type.as_str() {
         "vanilla" => manager
-            .fetch_vanilla_versions()
+            .fetch_vanilla_versions(include_snapshots)
             .await
-            .map_err(|e| e.to_string()),
+            .map_err(PrismarineError::from),
         "paper" => manager
-            .fetch_paper_versions()
+            .fetch_paper_versions(include_snapshots)
             .await
-            .map_err(|e| e.to_string()),
+            .map_err(PrismarineError::from),
         "fabric" => manager
-            .fetch_fabric_versions()
+            .fetch_fabric_versions(include_snapshots)
             .await
-            .map_err(|e| e.to_string()),
+            .map_err(PrismarineError::from),
         "mohist" => manager
             .fetch_mohist_versions()
             .await
-            .map_err(|e| e.to_string()),
+            .map_err(PrismarineError::from),
         "taiyitist" => manager
             .fetch_taiyitist_versions()
             .await
-            .map_err(|e| e.to_string()),
+            .map_err(PrismarineError::from),
         "purpur" => manager
-            .fetch_purpur_versions()
+            .fetch_purpur_versions(include_snapshots)
             .await
-            .map_err(|e| e.to_string()),
+            .map_err(PrismarineError::from),
         "banner" => manager
             .fetch_banner_versions()
             .await
-            .map_err(|e| e.to_string()),
+            .map_err(PrismarineError::from),
         "spigot" => manager
             .fetch_spigot_versions()
             .await
-            .map_err(|e| e.to_string()),
+            .map_err(PrismarineError::from),
         "velocity" => manager
-            .fetch_velocity_versions()
+            .fetch_velocity_versions(include_snapshots)
             .await
-            .map_err(|e| e.to_string()),
+            .map_err(PrismarineError::from),
         "waterfall" => manager
-            .fetch_waterfall_versions()
+            .fetch_waterfall_versions(include_snapshots)
             .await
-            .map_err(|e| e.to_string()),
+            .map_err(PrismarineError::from),
         "bungeecord" => manager
             .fetch_bungeecord_versions()
             .await
-            .map_err(|e| e.to_string()),
-        _ => Err("Unsupported server type".to_string()),
+            .map_err(PrismarineError::from),
+        "forge" => manager
+            .fetch_forge_versions()
+            .await
+            .map_err(PrismarineError::from),
+        _ => Err(PrismarineError::invalid_input("Unsupported server type")),
     }
 }
 
@@ -640,12 +2785,12 @@ async fn fetch_versions(
 async fn get_proxy_servers(
     proxy_id: String,
     state: State<'_, AppState>,
-) -> Result<Vec<server_manager::ProxyServerEntry>, String> {
+) -> Result<Vec<server_manager::ProxyServerEntry>, PrismarineError> {
     let manager = state.server_manager.lock().await;
     manager
         .get_proxy_registered_servers(&proxy_id)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
@@ -655,12 +2800,12 @@ async fn add_proxy_server(
     address: String,
     add_to_try: Option<bool>,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), PrismarineError> {
     let manager = state.server_manager.lock().await;
     manager
         .add_server_to_proxy(&proxy_id, &name, &address, add_to_try.unwrap_or(true))
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
@@ -668,12 +2813,12 @@ async fn remove_proxy_server(
     proxy_id: String,
     name: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), PrismarineError> {
     let manager = state.server_manager.lock().await;
     manager
         .remove_server_from_proxy(&proxy_id, &name)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
@@ -681,12 +2826,12 @@ async fn configure_backend_for_proxy(
     backend_id: String,
     proxy_id: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), PrismarineError> {
     let manager = state.server_manager.lock().await;
     manager
         .configure_backend_for_proxy(&backend_id, &proxy_id)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
@@ -694,29 +2839,57 @@ async fn start_bridge(
     port: u16,
     remote_server: Option<String>,
     secret: Option<String>,
+    provider: Option<tunnels::TunnelKind>,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), PrismarineError> {
+    if let Some(provider) = provider {
+        state.bridge.set_provider(provider);
+    }
     // First ensure binary is installed
     state
         .bridge
         .ensure_installed()
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(PrismarineError::from)?;
     // Then start the bridge
     state
         .bridge
         .start(port, remote_server, secret)
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+fn list_tunnel_providers(state: State<'_, AppState>) -> Vec<bridge::TunnelProviderInfo> {
+    state.bridge.list_providers()
+}
+
+#[tauri::command]
+fn set_tunnel_provider(provider: tunnels::TunnelKind, state: State<'_, AppState>) {
+    state.bridge.set_provider(provider);
+}
+
+#[tauri::command]
+fn get_tunnel_provider(state: State<'_, AppState>) -> tunnels::TunnelKind {
+    state.bridge.provider()
 }
 
 #[tauri::command]
-fn stop_bridge(state: State<'_, AppState>) -> Result<(), String> {
-    state.bridge.stop().map_err(|e| e.to_string())
+fn stop_bridge(state: State<'_, AppState>) -> Result<(), PrismarineError> {
+    state.bridge.stop().map_err(PrismarineError::from)
 }
 
 #[tauri::command]
-fn get_bridge_status(state: State<'_, AppState>) -> BridgeStatus {
-    state.bridge.get_status()
+fn get_bridge_status(
+    server_id: Option<String>,
+    bedrock: Option<bool>,
+    state: State<'_, AppState>,
+) -> BridgeStatus {
+    match server_id {
+        Some(id) if bedrock.unwrap_or(false) => {
+            state.bridge.get_status(Some(&bridge::bedrock_key(&id)))
+        }
+        id => state.bridge.get_status(id.as_deref()),
+    }
 }
 
 #[tauri::command]
@@ -730,11 +2903,11 @@ fn is_bridge_running(state: State<'_, AppState>) -> bool {
 }
 
 #[tauri::command]
-fn set_bridge_authtoken(token: String, state: State<'_, AppState>) -> Result<(), String> {
+fn set_bridge_authtoken(token: String, state: State<'_, AppState>) -> Result<(), PrismarineError> {
     state
         .bridge
         .set_authtoken(&token)
-        .map_err(|e| e.to_string())
+        .map_err(PrismarineError::from)
 }
 
 #[tauri::command]
@@ -742,52 +2915,798 @@ fn has_bridge_authtoken(state: State<'_, AppState>) -> bool {
     state.bridge.has_authtoken()
 }
 
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    // Initialize app state
-    let config_path = dirs::config_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join("MinecraftServerManager")
-        .join("config.json");
-
-    let base_path = dirs::data_local_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join("MinecraftServerManager")
-        .join("servers");
-
-    let server_manager = Arc::new(TokioMutex::new(ServerManager::new(base_path)));
-    let port_manager = Arc::new(PortManager::new());
+#[tauri::command]
+fn get_failover_config(state: State<'_, AppState>) -> failover::FailoverConfig {
+    state.failover.get_config()
+}
+
+#[tauri::command]
+fn set_failover_config(
+    config: failover::FailoverConfig,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    state
+        .failover
+        .set_config(&config)
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn get_report(
+    server_id: String,
+    period: reports::ReportPeriod,
+    state: State<'_, AppState>,
+) -> Result<reports::StatsReport, PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .get_report(&server_id, period)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+/// Choose which statistics reports are sent through the notification channels
+#[tauri::command]
+async fn set_stats_reports(
+    server_id: String,
+    schedule: reports::ReportSchedule,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .set_stats_reports(&server_id, schedule)
+        .await
+        .map_err(PrismarineError::from)?;
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+fn get_notification_config() -> notifications::NotificationConfig {
+    notifications::load_config()
+}
+
+/// Save channels and routes; `credentials` sets (or clears with "") channel secrets
+#[tauri::command]
+fn set_notification_config(
+    config: notifications::NotificationConfig,
+    credentials: std::collections::HashMap<String, String>,
+) -> Result<(), PrismarineError> {
+    notifications::save_config(&config, &credentials).map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn test_notification_channel(channel_id: String) -> Result<(), PrismarineError> {
+    notifications::test_channel(&channel_id)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+fn list_secrets() -> Vec<secrets::StoredSecret> {
+    secrets::list_user_secrets()
+}
+
+/// Store (or clear with an empty value) one of the user-managed secrets
+#[tauri::command]
+fn set_secret(name: String, value: Option<String>) -> Result<(), PrismarineError> {
+    if !secrets::USER_SECRETS.contains(&name.as_str()) {
+        return Err(PrismarineError::invalid_input(format!(
+            "Unknown secret: {}",
+            name
+        )));
+    }
+    match value.filter(|v| !v.is_empty()) {
+        Some(value) => secrets::set(&name, &value),
+        None => secrets::delete(&name),
+    }
+    .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+fn get_address_book(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Vec<address_book::PublishedAddress> {
+    state.address_book.get_addresses(&server_id)
+}
+
+#[tauri::command]
+fn add_published_address(
+    server_id: String,
+    address: String,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let address = address_book::parse_address(&address).map_err(PrismarineError::from)?;
+    state
+        .address_book
+        .record(&server_id, AddressKind::Ddns, &address)
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+fn remove_published_address(
+    server_id: String,
+    address: String,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    state
+        .address_book
+        .remove(&server_id, &address)
+        .map_err(PrismarineError::from)
+}
+
+/// Record the server's current LAN, external and bridge addresses, then probe all known addresses
+#[tauri::command]
+async fn refresh_address_book(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<address_book::PublishedAddress>, PrismarineError> {
+    let port = {
+        let manager = state.server_manager.lock().await;
+        manager
+            .get_server(&server_id)
+            .await
+            .ok_or_else(|| PrismarineError::not_found("Server not found"))?
+            .port
+    };
+
+    if let Ok(local_ip) = port_manager::get_local_ip() {
+        let _ = state.address_book.record(
+            &server_id,
+            AddressKind::Lan,
+            &format!("{}:{}", local_ip, port),
+        );
+    }
+
+    let external = state.port_manager.get_external_addresses().await;
+    if let Some(ipv4) = external.ipv4 {
+        let _ = state.address_book.record(
+            &server_id,
+            AddressKind::External,
+            &format!("{}:{}", ipv4, port),
+        );
+    }
+    if let Some(ipv6) = external.ipv6 {
+        let _ = state.address_book.record(
+            &server_id,
+            AddressKind::External,
+            &format!("[{}]:{}", ipv6, port),
+        );
+    }
+
+    if let BridgeStatus::Connected(address) = state.bridge.get_status(None) {
+        if state.bridge.local_port() == Some(port) {
+            let _ = state
+                .address_book
+                .record(&server_id, AddressKind::Bridge, &address);
+        }
+    }
+
+    state
+        .address_book
+        .check_server(&server_id)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+/// Ports currently mapped for running servers with auto_open_port
+async fn auto_opened_ports(manager: &ServerManager) -> Vec<(u16, String)> {
+    let mut ports = Vec::new();
+    for server in manager.get_servers().await {
+        if server.auto_open_port && server.status == server_manager::ServerStatus::Running {
+            ports.extend(manager.public_ports(&server.id).await.unwrap_or_default());
+        }
+    }
+    ports
+}
+
+#[tauri::command]
+async fn list_router_mappings(
+    state: State<'_, AppState>,
+) -> Result<Vec<port_manager::RouterMapping>, PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    let in_use = auto_opened_ports(&manager).await;
+    state
+        .port_manager
+        .list_router_mappings(&in_use)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn find_port_conflicts(
+    port: u16,
+    protocol: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<port_manager::RouterMapping>, PrismarineError> {
+    state
+        .port_manager
+        .find_mapping_conflicts(port, &protocol)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn remove_stale_router_mappings(
+    state: State<'_, AppState>,
+) -> Result<Vec<port_manager::RouterMapping>, PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    let in_use = auto_opened_ports(&manager).await;
+    state
+        .port_manager
+        .remove_stale_mappings(&in_use)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+/// Stop everything and remove Prismarine's router mappings and firewall rules
+/// before uninstalling; `archive_path` zips the server folders there first
+#[tauri::command]
+async fn uninstall_cleanup(
+    archive_path: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<uninstall::CleanupReport, PrismarineError> {
+    if !state.instance_lock.is_owner() {
+        return Err(PrismarineError::InvalidState {
+            message: "Another Prismarine instance manages the servers".to_string(),
+        });
+    }
+    let archive = archive_path.map(PathBuf::from);
+    if archive.as_ref().is_some_and(|path| !path.is_absolute()) {
+        return Err(PrismarineError::invalid_input(
+            "The archive path must be absolute",
+        ));
+    }
+    let manager = state.server_manager.lock().await.clone();
+    let report = uninstall::run(
+        &manager,
+        &state.port_manager,
+        &state.bridge,
+        archive.as_deref(),
+    )
+    .await;
+    let _ = manager.save_servers(&state.config_path).await;
+    Ok(report)
+}
+
+#[tauri::command]
+async fn render_world_preview(
+    server_id: String,
+    force: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Option<String>, PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    manager
+        .render_world_preview(&server_id, force.unwrap_or(false))
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+async fn install_web_map(
+    server_id: String,
+    plugin: server_manager::WebMapPlugin,
+    port: Option<u16>,
+    public: bool,
+    accept_download: bool,
+    state: State<'_, AppState>,
+) -> Result<server_manager::WebMapSettings, PrismarineError> {
+    let manager = state.server_manager.lock().await.clone();
+    let settings = manager
+        .install_web_map(&server_id, plugin, port, public, accept_download)
+        .await
+        .map_err(PrismarineError::from)?;
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)?;
+    Ok(settings)
+}
+
+#[tauri::command]
+async fn uninstall_web_map(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let manager = state.server_manager.lock().await;
+    manager
+        .uninstall_web_map(&server_id)
+        .await
+        .map_err(PrismarineError::from)?;
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+/// Open the server's web map in the browser
+#[tauri::command]
+async fn open_web_map(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    let map = {
+        let manager = state.server_manager.lock().await;
+        manager
+            .get_server(&server_id)
+            .await
+            .ok_or_else(|| PrismarineError::not_found("Server not found"))?
+            .web_map
+    };
+    let map = map.ok_or_else(|| PrismarineError::invalid_input("No web map installed"))?;
+    open_folder(format!("http://localhost:{}", map.port)).await
+}
+
+/// Use a local zip as the server's resource pack. Built-in hosting serves it
+/// from this machine at `public_host` (the external IPv4 when omitted).
+#[tauri::command]
+async fn set_resource_pack(
+    server_id: String,
+    path: String,
+    hosting: resource_pack::PackHosting,
+    required: bool,
+    public_host: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<resource_pack::ResourcePackSettings, PrismarineError> {
+    let public_host = match (&hosting, public_host) {
+        (resource_pack::PackHosting::BuiltIn { .. }, None) => {
+            state.port_manager.get_external_addresses().await.ipv4
+        }
+        (_, host) => host,
+    };
+
+    let manager = state.server_manager.lock().await.clone();
+    let settings = manager
+        .set_resource_pack(
+            &server_id,
+            &PathBuf::from(&path),
+            hosting,
+            required,
+            public_host.as_deref(),
+        )
+        .await
+        .map_err(PrismarineError::from)?;
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)?;
+
+    let server_path = manager
+        .get_server(&server_id)
+        .await
+        .ok_or_else(|| PrismarineError::not_found("Server not found"))?
+        .path;
+    state
+        .pack_host
+        .apply(&server_id, &server_path, Some(&settings))
+        .await
+        .map_err(PrismarineError::from)?;
+    Ok(settings)
+}
+
+#[tauri::command]
+async fn remove_resource_pack(
+    server_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), PrismarineError> {
+    state.pack_host.stop(&server_id);
+    let manager = state.server_manager.lock().await;
+    manager
+        .remove_resource_pack(&server_id)
+        .await
+        .map_err(PrismarineError::from)?;
+    manager
+        .save_servers(&state.config_path)
+        .await
+        .map_err(PrismarineError::from)
+}
+
+#[tauri::command]
+fn get_instance_status(state: State<'_, AppState>) -> instance_lock::InstanceStatus {
+    state.instance_lock.status()
+}
+
+/// Paths, settings and the state shared by the GUI and the headless core.
+/// `kind` is recorded in the instance lock ("gui" or "headless").
+fn init_state(kind: &str) -> (AppState, AppSettings) {
+    // Initialize app state. settings.json is in the config folder, so the
+    // folders it overrides are resolved after reading it.
+    let env_paths = AppPaths::from_env();
+    let config_path = env_paths.config_file("config.json");
+    let settings_path = env_paths.config_file("settings.json");
+    let settings = tauri::async_runtime::block_on(AppSettings::load(&settings_path))
+        .unwrap_or_else(|e| {
+            println!("[Settings] Failed to load settings, using defaults: {}", e);
+            AppSettings::default()
+        });
+    let paths = env_paths.with_overrides(&settings.paths);
+    paths::init(paths.clone());
+    // Release GUI builds have no console, their output goes to the log folder
+    if kind == "gui" && !cfg!(debug_assertions) {
+        if let Err(e) = app_log::init(&paths.log_dir) {
+            println!("[Log] Failed to open the app log: {}", e);
+        }
+    }
+    if paths.portable {
+        println!("[Paths] Portable install, data in {:?}", paths.data_dir);
+    }
+    offline::set(settings.offline_mode);
+    limits::apply(&settings.limits);
+
+    // A second GUI launch is handed over by the single-instance plugin before
+    // setup runs; this lock catches headless runs on the same data folder
+    let instance_lock = Arc::new(InstanceLock::acquire(kind, &paths));
+
+    let manager = ServerManager::new(settings.servers_dir.clone(), &paths);
+    manager.set_read_only(instance_lock.status().holder);
+    let port_manager = manager.port_manager();
+    let server_manager = Arc::new(TokioMutex::new(manager));
     let monitor = Arc::new(Mutex::new(Monitor::new()));
-    let bridge = Arc::new(PrismarineBridge::new());
+    let bridge = Arc::new(PrismarineBridge::new(&paths));
+    let failover = Arc::new(FailoverWatchdog::new(&paths));
+    if instance_lock.is_owner() {
+        jar_cache::remove_legacy_dir();
+    }
+    if let Err(e) = failover.migrate_secrets() {
+        println!("[Failover] Failed to migrate the webhook URL: {}", e);
+    }
 
+    let console_bridge = Arc::new(ConsoleBridge::new());
+    let pack_host = Arc::new(PackHost::new());
+    let udp_relay = Arc::new(UdpRelay::new());
+    let rest_api = Arc::new(RestApi::new());
     let app_state = AppState {
         server_manager: Arc::clone(&server_manager),
-        port_manager,
-        monitor,
-        bridge,
+        port_manager: Arc::clone(&port_manager),
+        monitor: Arc::clone(&monitor),
+        bridge: Arc::clone(&bridge),
+        failover: Arc::clone(&failover),
+        address_book: Arc::new(AddressBook::new(&paths)),
+        console_bridge: Arc::clone(&console_bridge),
+        pack_host: Arc::clone(&pack_host),
+        udp_relay: Arc::clone(&udp_relay),
+        templates: Arc::new(TemplateStore::new(&paths)),
+        transfer: Arc::new(TransferReceiver::new()),
+        instance_lock,
+        rest_api: Arc::clone(&rest_api),
         config_path: config_path.clone(),
+        settings_path,
+        paths,
     };
+    (app_state, settings)
+}
+
+/// Payload of the `autostart-progress` event, sent before and after each start
+#[derive(Debug, Clone, serde::Serialize)]
+struct AutostartProgress {
+    server_id: String,
+    name: String,
+    index: usize, // 1-based
+    total: usize,
+    started: bool,         // false while waiting for the delay
+    error: Option<String>, // The start failed
+}
+
+/// Start the servers flagged `autostart` one after another, `delay` before each
+/// so the network is up and the servers do not compete for the disk
+async fn autostart_servers(
+    state: &AppState,
+    delay: std::time::Duration,
+    on_progress: &(dyn Fn(AutostartProgress) + Send + Sync),
+) {
+    let manager = state.server_manager.lock().await.clone();
+    let servers: Vec<_> = manager
+        .get_servers()
+        .await
+        .into_iter()
+        .filter(|s| s.autostart && s.status == server_manager::ServerStatus::Stopped)
+        .collect();
+    let total = servers.len();
+    for (i, server) in servers.into_iter().enumerate() {
+        let progress = |started, error| AutostartProgress {
+            server_id: server.id.clone(),
+            name: server.name.clone(),
+            index: i + 1,
+            total,
+            started,
+            error,
+        };
+        on_progress(progress(false, None));
+        tokio::time::sleep(delay).await;
+        println!("[Autostart] Starting {} ({}/{})", server.name, i + 1, total);
+        let error = state.start_server(&server.id).await.err().map(|e| {
+            println!("[Autostart] Failed to start {}: {}", server.name, e);
+            e.to_string()
+        });
+        on_progress(progress(true, error));
+    }
+}
+
+/// Background work of the managing instance: REST API, failover, power
+/// events, the 30 second monitor loop, loading the saved servers and starting
+/// the autostart ones. `on_shutdown` ends the process after servers were
+/// stopped for an OS shutdown.
+fn start_services(
+    state: &AppState,
+    rest_api_settings: RestApiSettings,
+    autostart_delay: std::time::Duration,
+    on_autostart: impl Fn(AutostartProgress) + Send + Sync + 'static,
+    on_shutdown: impl Fn() + Send + Sync + 'static,
+) {
+    let server_manager = Arc::clone(&state.server_manager);
+    let port_manager = Arc::clone(&state.port_manager);
+    let monitor = Arc::clone(&state.monitor);
+    let bridge = Arc::clone(&state.bridge);
+    let failover = Arc::clone(&state.failover);
+    let console_bridge = Arc::clone(&state.console_bridge);
+    let pack_host = Arc::clone(&state.pack_host);
+    let udp_relay = Arc::clone(&state.udp_relay);
+    let rest_api = Arc::clone(&state.rest_api);
+    let config_path = state.config_path.clone();
+
+    let api_state = state.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = rest_api.apply(&rest_api_settings, api_state).await {
+            println!("[RestApi] {}", e);
+        }
+    });
+
+    // Spawn background task for network failover (UPnP re-mapping, bridge fallback)
+    let failover_manager = Arc::clone(&server_manager);
+    let failover_ports = Arc::clone(&port_manager);
+    let failover_bridge = Arc::clone(&bridge);
+    let failover_monitor = Arc::clone(&monitor);
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(tokio::time::Duration::from_secs(60)).await;
+            failover
+                .check(
+                    &failover_manager,
+                    &failover_monitor,
+                    &failover_ports,
+                    &failover_bridge,
+                )
+                .await;
+        }
+    });
+
+    // Renew IPv6 pinholes hourly (one day lease) and IPv4 mappings daily (one week lease)
+    let pinhole_ports = Arc::clone(&port_manager);
+    tauri::async_runtime::spawn(async move {
+        for hour in 1u64.. {
+            tokio::time::sleep(tokio::time::Duration::from_secs(3600)).await;
+            if hour % 24 == 0 {
+                // Also renews the pinholes
+                match pinhole_ports.reapply_active_ports().await {
+                    Ok(applied) => println!("[PortManager] Renewed {} port mappings", applied),
+                    Err(e) => println!("[PortManager] Failed to renew port mappings: {}", e),
+                }
+            } else {
+                pinhole_ports.refresh_pinholes().await;
+            }
+        }
+    });
+
+    let tunnel_bridge = Arc::clone(&bridge);
+    let monitor_relay = Arc::clone(&udp_relay);
+    let tunnel_config_path = config_path.clone();
+    let boot_config_path = config_path.clone();
+
+    // Stop servers cleanly before OS shutdown or sleep, recover after resume
+    let power_manager = Arc::clone(&server_manager);
+    let on_shutdown: Arc<dyn Fn() + Send + Sync> = Arc::new(on_shutdown);
+    let suspended_servers: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+    tauri::async_runtime::spawn(async move {
+        power::listen(move |event| {
+            let manager = Arc::clone(&power_manager);
+            let port_manager = Arc::clone(&port_manager);
+            let bridge = Arc::clone(&bridge);
+            let suspended_servers = Arc::clone(&suspended_servers);
+            let on_shutdown = Arc::clone(&on_shutdown);
+            async move {
+                match event {
+                    PowerEvent::Shutdown | PowerEvent::Suspend => {
+                        println!("[Power] {:?}: stopping running servers", event);
+                        let manager = manager.lock().await.clone();
+                        if event == PowerEvent::Suspend {
+                            manager.begin_suspend();
+                        }
+                        let stopped = manager.stop_all_servers().await;
+                        if event == PowerEvent::Shutdown {
+                            on_shutdown();
+                        } else {
+                            *suspended_servers.lock().unwrap() = stopped;
+                        }
+                    }
+                    PowerEvent::Resume => {
+                        // Other platforms only notice the sleep once it is over
+                        manager.lock().await.begin_suspend();
+                        // Give the network stack time to come back up
+                        tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
+
+                        let to_restart: Vec<String> =
+                            std::mem::take(&mut *suspended_servers.lock().unwrap());
+                        let manager = manager.lock().await;
+                        let exited = manager.finish_resume().await;
+                        for id in exited.iter().chain(to_restart.iter()) {
+                            if let Err(e) = manager.start_server(id).await {
+                                println!("[Power] Failed to restart server {}: {}", id, e);
+                            }
+                        }
+                        drop(manager);
+
+                        match port_manager.reapply_active_ports().await {
+                            Ok(count) => {
+                                println!("[Power] Re-applied {} port mappings", count)
+                            }
+                            Err(e) => {
+                                println!("[Power] Failed to re-apply port mappings: {}", e)
+                            }
+                        }
+
+                        if let Err(e) = bridge.reconnect() {
+                            println!("[Power] Failed to reconnect bridge: {}", e);
+                        }
+                    }
+                }
+            }
+        });
+    });
+
+    // Spawn background task for auto-restart monitor
+    let monitor_manager = Arc::clone(&server_manager);
+    tauri::async_runtime::spawn(async move {
+        let mut known_players = std::collections::HashMap::new();
+        loop {
+            tokio::time::sleep(tokio::time::Duration::from_secs(30)).await;
+            let manager = monitor_manager.lock().await;
+            // Crashed servers are marked stopped (and reported) before auto-restart looks at them
+            let crashed = manager.verify_processes().await;
+            manager.check_pending_boots(&boot_config_path).await;
+            manager.check_and_restart_servers().await;
+            manager.check_dynamic_view_distance().await;
+            manager.check_scheduled_tasks().await;
+            manager.check_announcements().await;
+            let snapshot = manager.clone();
+            drop(manager);
+            for id in &crashed {
+                if let Some(server) = snapshot.get_server(id).await {
+                    notifications::dispatch(
+                        notifications::NotifyEvent::Crash,
+                        "Server crashed",
+                        &format!("\"{}\" stopped unexpectedly", server.name),
+                    )
+                    .await;
+                }
+            }
+            snapshot.refresh_remote_statuses().await;
+
+            // Restarted servers get their tunnel back, crashed tunnels are restarted
+            sync_server_tunnels(&snapshot, &tunnel_bridge, &tunnel_config_path).await;
+            monitor_relay.sync(&snapshot.relay_targets().await).await;
+
+            snapshot.refresh_player_counts().await;
+            notify_player_joins(&snapshot, &mut known_players).await;
+            snapshot.record_stats_samples().await;
+            let reports_enabled = snapshot
+                .get_servers()
+                .await
+                .iter()
+                .any(|s| s.stats_reports.weekly || s.stats_reports.monthly);
+            if reports_enabled {
+                snapshot.send_due_reports().await;
+            }
+        }
+    });
+
+    // Load saved servers (inside Tauri's async runtime)
+    let autostart_state = state.clone();
+    tauri::async_runtime::spawn(async move {
+        let manager = server_manager.lock().await;
+        let _ = manager.load_servers(&config_path).await;
+
+        // Servers still running after a crash are reattached, stale statuses reset
+        let adopted = manager.recover_processes().await;
+        if !adopted.is_empty() {
+            println!("[Startup] Reattached {} running servers", adopted.len());
+        }
+        let _ = manager.save_servers(&config_path).await;
+
+        // Move plaintext secrets of older versions into the secret store
+        manager.migrate_secrets().await;
+
+        // Bring up console bridges enabled in a previous session
+        for server in manager.get_servers().await {
+            if let Some(config) = server.console_bridge.filter(|c| c.enabled) {
+                if let Err(e) = console_bridge
+                    .apply(manager.clone(), &server.id, &config)
+                    .await
+                {
+                    println!("[ConsoleBridge] {}: {}", server.name, e);
+                }
+            }
+        }
+
+        // Serve resource packs hosted by the app
+        for server in manager.get_servers().await {
+            if let Err(e) = pack_host
+                .apply(&server.id, &server.path, server.resource_pack.as_ref())
+                .await
+            {
+                println!("[ResourcePack] {}: {}", server.name, e);
+            }
+        }
+        drop(manager);
+
+        autostart_servers(&autostart_state, autostart_delay, &on_autostart).await;
+    });
+}
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    let (app_state, settings) = init_state("gui");
+    let read_only = !app_state.instance_lock.is_owner();
+    let rest_api_settings = settings.rest_api;
+    let autostart_delay = std::time::Duration::from_secs(settings.autostart_delay_secs);
 
     tauri::Builder::default()
+        // Must be registered first: a second launch focuses this window and exits
+        .plugin(tauri_plugin_single_instance::init(|app, _args, _cwd| {
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.unminimize();
+                let _ = window.show();
+                let _ = window.set_focus();
+            }
+        }))
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_shell::init())
         .manage(app_state)
-        .setup(move |_app| {
-            // Spawn background task for auto-restart monitor
-            let monitor_manager = Arc::clone(&server_manager);
-            tauri::async_runtime::spawn(async move {
-                loop {
-                    tokio::time::sleep(tokio::time::Duration::from_secs(30)).await;
-                    let manager = monitor_manager.lock().await;
-                    manager.check_and_restart_servers().await;
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+                // A read-only instance owns no servers
+                if window.label() != "main" || !window.state::<AppState>().instance_lock.is_owner()
+                {
+                    return;
                 }
+                api.prevent_close();
+                tauri::async_runtime::spawn(handle_close_request(window.app_handle().clone()));
+            }
+        })
+        .setup(move |app| {
+            // Forward download progress to the frontend
+            let progress_handle = app.handle().clone();
+            download::set_progress_handler(move |progress| {
+                let _ = progress_handle.emit("download-progress", progress);
             });
-
-            // Load saved servers in setup hook (inside Tauri's async runtime)
-            tauri::async_runtime::spawn(async move {
-                let manager = server_manager.lock().await;
-                let _ = manager.load_servers(&config_path).await;
+            let status_handle = app.handle().clone();
+            server_manager::set_status_handler(move |change| {
+                let _ = status_handle.emit("server-status-changed", change);
             });
+
+            // Servers, ports and tunnels belong to the other instance: only show them
+            if read_only {
+                let state = app.state::<AppState>();
+                let (server_manager, config_path) =
+                    (Arc::clone(&state.server_manager), state.config_path.clone());
+                tauri::async_runtime::spawn(async move {
+                    let _ = server_manager.lock().await.load_servers(&config_path).await;
+                });
+                return Ok(());
+            }
+
+            let autostart_handle = app.handle().clone();
+            let app_handle = app.handle().clone();
+            start_services(
+                app.state::<AppState>().inner(),
+                rest_api_settings,
+                autostart_delay,
+                move |progress| {
+                    let _ = autostart_handle.emit("autostart-progress", progress);
+                },
+                move || app_handle.exit(0),
+            );
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -805,6 +3724,7 @@ pub fn run() {
             get_external_ip,
             is_upnp_available,
             get_system_stats,
+            get_server_health,
             get_server_logs,
             send_server_command,
             open_folder,
@@ -824,11 +3744,15 @@ pub fn run() {
             install_viaversion_support,
             is_geyser_installed,
             is_viaversion_installed,
+            get_client_versions,
+            get_plugin_reload_method,
+            reload_plugin,
             uninstall_geyser_support,
             uninstall_viaversion_support,
             search_plugins,
             install_plugin,
             install_modrinth_plugin,
+            install_modpack,
             install_spigot_plugin,
             uninstall_plugin,
             is_plugin_installed,
@@ -836,6 +3760,9 @@ pub fn run() {
             open_plugins_folder,
             restart_server,
             get_online_players,
+            dismiss_region_warning,
+            query_server_status,
+            query_server_details,
             set_auto_restart,
             set_server_memory,
             get_proxy_servers,
@@ -845,7 +3772,237 @@ pub fn run() {
             get_ops,
             grant_op,
             revoke_op,
+            change_server_version,
+            set_console_safety,
+            get_pinned_commands,
+            get_recent_commands,
+            pin_command,
+            unpin_command,
+            run_pinned_command,
+            list_worlds,
+            set_active_world,
+            delete_world,
+            reset_world,
+            get_dynamic_view_distance,
+            set_dynamic_view_distance,
+            import_world,
+            export_world,
+            get_scheduled_tasks,
+            save_scheduled_task,
+            remove_scheduled_task,
+            get_failover_config,
+            set_failover_config,
+            get_report,
+            set_stats_reports,
+            get_notification_config,
+            set_notification_config,
+            test_notification_channel,
+            get_address_book,
+            add_published_address,
+            remove_published_address,
+            refresh_address_book,
+            get_console_bridge,
+            set_console_bridge,
+            stress_test,
+            get_latency_regions,
+            measure_latency,
+            list_templates,
+            save_server_as_template,
+            delete_template,
+            create_server_from_template,
+            accept_eula,
+            get_malware_scan_config,
+            set_malware_scan_config,
+            list_quarantine,
+            delete_quarantined,
+            request_delete_confirmation,
+            list_deleted_servers,
+            restore_deleted_server,
+            purge_deleted_server,
+            rename_server,
+            move_server,
+            get_app_paths,
+            get_rest_api_status,
+            regenerate_rest_api_token,
+            get_app_settings,
+            set_app_settings,
+            get_floodgate_settings,
+            get_whitelist,
+            add_to_whitelist,
+            remove_from_whitelist,
+            get_banned_players,
+            ban_player,
+            pardon_player,
+            find_free_port,
+            get_upnp_diagnostics,
+            get_rcon_status,
+            set_rcon_enabled,
+            rotate_rcon_password,
+            issue_server_api_token,
+            revoke_server_api_token,
+            list_secrets,
+            set_secret,
+            test_port_reachability,
+            check_server_readiness,
+            repair_server,
+            get_version_requirements,
+            set_auto_open_port,
+            set_server_autostart,
+            get_instance_status,
+            set_server_tags,
+            set_server_group,
+            start_group,
+            stop_group,
+            list_router_mappings,
+            find_port_conflicts,
+            remove_stale_router_mappings,
+            uninstall_cleanup,
+            list_tunnel_providers,
+            set_tunnel_provider,
+            get_tunnel_provider,
+            render_world_preview,
+            install_web_map,
+            uninstall_web_map,
+            open_web_map,
+            set_resource_pack,
+            remove_resource_pack,
+            start_bridge_for_server,
+            stop_bridge_for_server,
+            get_announcements,
+            set_announcements,
+            get_game_rules,
+            set_game_rules,
+            get_world_settings,
+            set_world_settings,
+            start_transfer_receiver,
+            stop_transfer_receiver,
+            get_transfer_receiver,
+            pair_transfer_target,
+            transfer_server,
+            start_bedrock_bridge,
+            stop_bedrock_bridge,
+            set_bedrock_relay,
+            resolve_geyser_port_conflicts,
+            get_geyser_port,
+            set_geyser_port,
+            list_installed_javas,
+            install_java,
+            remove_java,
+            get_recommended_java,
+            apply_security_preset,
+            set_server_java,
+            set_jvm_args,
+            analyze_lag,
+            check_plugin_compatibility,
+            upgrade_server,
+            get_jvm_profiles,
+            set_jvm_profile,
+            list_restore_points,
+            get_last_rollback,
+            check_buildtools,
+            install_portable_git,
+            verify_java_installation,
+            change_server_type,
+            is_server_adopted,
+            import_remote_server,
+            sync_remote_server,
+            exit_app,
+            suggest_memory,
+            get_geyser_config,
+            set_geyser_config,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+/// Give the headless process a console on Windows: release builds use the
+/// GUI subsystem, so without one the token and Ctrl+C would go nowhere.
+#[cfg(windows)]
+fn attach_console() {
+    use windows::Win32::System::Console::{AllocConsole, AttachConsole, ATTACH_PARENT_PROCESS};
+
+    unsafe {
+        if AttachConsole(ATTACH_PARENT_PROCESS).is_err() {
+            let _ = AllocConsole();
+        }
+    }
+}
+
+/// Run the core without a window (`prismarine --headless`), for a home server
+/// without a desktop session. Saved servers are loaded, schedules and crash
+/// restarts run as usual, and the REST API is the way to control it. Ctrl+C
+/// stops the running servers and exits. With `--regenerate-token` a new REST
+/// API token replaces the old one and is printed.
+pub fn run_headless(regenerate_token: bool) {
+    #[cfg(windows)]
+    attach_console();
+    let (state, settings) = init_state("headless");
+    if !state.instance_lock.is_owner() {
+        println!("[Headless] The data folder is managed by another instance, exiting");
+        return;
+    }
+    if !settings.rest_api.enabled {
+        println!("[Headless] Enabling the REST API, it is the only way to control this instance");
+    }
+    let rest_api_settings = RestApiSettings {
+        enabled: true,
+        ..settings.rest_api
+    };
+    let autostart_delay = std::time::Duration::from_secs(settings.autostart_delay_secs);
+    if regenerate_token || secrets::get(rest_api::APP_TOKEN).ok().flatten().is_none() {
+        match rest_api::regenerate_token() {
+            Ok(token) => println!(
+                "[Headless] REST API token (shown once, run with --headless --regenerate-token if lost): {}",
+                token
+            ),
+            Err(e) => println!("[Headless] Failed to create the REST API token: {}", e),
+        }
+    }
+
+    tauri::async_runtime::block_on(async {
+        start_services(
+            &state,
+            rest_api_settings,
+            autostart_delay,
+            |_| {}, // Logged by autostart_servers
+            || std::process::exit(0),
+        );
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            println!("[Headless] Cannot listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+        println!("[Headless] Stopping running servers");
+        let manager = state.server_manager.lock().await.clone();
+        manager.stop_all_servers().await;
+        let _ = manager.save_servers(&state.config_path).await;
+        state.rest_api.stop();
+    });
+}
+
+/// `prismarine --uninstall-cleanup [--archive <zip>]`, run by the uninstaller.
+/// Returns false when a step failed.
+pub fn run_uninstall_cleanup(archive: Option<PathBuf>) -> bool {
+    let (state, _) = init_state("uninstall");
+    if !state.instance_lock.is_owner() {
+        println!("[Uninstall] Another Prismarine instance is running, close it first");
+        return false;
+    }
+    tauri::async_runtime::block_on(async {
+        let manager = state.server_manager.lock().await.clone();
+        let _ = manager.load_servers(&state.config_path).await;
+        // Servers left running by a detached exit are stopped too
+        manager.recover_processes().await;
+        let report = uninstall::run(
+            &manager,
+            &state.port_manager,
+            &state.bridge,
+            archive.as_deref(),
+        )
+        .await;
+        let _ = manager.save_servers(&state.config_path).await;
+        for error in &report.errors {
+            println!("[Uninstall] {}", error);
+        }
+        report.errors.is_empty()
+    })
+}
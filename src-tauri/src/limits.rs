@@ -0,0 +1,124 @@
+// Concurrency limits
+// Caps on what runs at once so a modest machine is not overwhelmed: starts
+// beyond the running-server cap are refused, downloads and backup-like jobs
+// (world exports, transfers, moves) wait in line for a free slot. The caps
+// come from the app settings and can change while jobs are running.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+fn default_max_downloads() -> usize {
+    4
+}
+
+fn default_max_backup_jobs() -> usize {
+    1
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConcurrencyLimits {
+    #[serde(default)]
+    pub max_running_servers: Option<usize>, // None: as many as the memory check allows
+    #[serde(default = "default_max_downloads")]
+    pub max_downloads: usize,
+    #[serde(default = "default_max_backup_jobs")]
+    pub max_backup_jobs: usize,
+}
+
+impl Default for ConcurrencyLimits {
+    fn default() -> Self {
+        Self {
+            max_running_servers: None,
+            max_downloads: default_max_downloads(),
+            max_backup_jobs: default_max_backup_jobs(),
+        }
+    }
+}
+
+/// Semaphore whose limit can be changed at any time
+pub struct Limiter {
+    name: &'static str,
+    permits: Semaphore,
+    state: Mutex<LimitState>,
+}
+
+struct LimitState {
+    limit: usize,
+    // Permits still out after the limit was lowered, forgotten when returned
+    owed: usize,
+}
+
+/// Held while the job runs, frees the slot when dropped
+pub struct Slot {
+    limiter: &'static Limiter,
+    permit: Option<SemaphorePermit<'static>>,
+}
+
+impl Limiter {
+    const fn new(name: &'static str, limit: usize) -> Self {
+        Self {
+            name,
+            permits: Semaphore::const_new(limit),
+            state: Mutex::new(LimitState { limit, owed: 0 }),
+        }
+    }
+
+    fn set_limit(&self, limit: usize) {
+        let limit = limit.max(1);
+        let mut state = self.state.lock().unwrap();
+        if limit > state.limit {
+            let raised = limit - state.limit;
+            let repaid = raised.min(state.owed);
+            state.owed -= repaid;
+            self.permits.add_permits(raised - repaid);
+        } else {
+            let lowered = state.limit - limit;
+            state.owed += lowered - self.permits.forget_permits(lowered);
+        }
+        state.limit = limit;
+    }
+
+    /// Wait for a free slot
+    pub async fn acquire(&'static self) -> Slot {
+        if self.permits.available_permits() == 0 {
+            println!("[Limits] Waiting for a free {} slot", self.name);
+        }
+        let permit = self.permits.acquire().await.expect("semaphore closed");
+        Slot {
+            limiter: self,
+            permit: Some(permit),
+        }
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        let mut state = self.limiter.state.lock().unwrap();
+        if state.owed > 0 {
+            state.owed -= 1;
+            if let Some(permit) = self.permit.take() {
+                permit.forget();
+            }
+        }
+    }
+}
+
+pub static DOWNLOADS: Limiter = Limiter::new("download", 4);
+pub static BACKUP_JOBS: Limiter = Limiter::new("backup", 1);
+/// 0: no cap
+static MAX_RUNNING_SERVERS: AtomicUsize = AtomicUsize::new(0);
+
+pub fn apply(limits: &ConcurrencyLimits) {
+    DOWNLOADS.set_limit(limits.max_downloads);
+    BACKUP_JOBS.set_limit(limits.max_backup_jobs);
+    MAX_RUNNING_SERVERS.store(limits.max_running_servers.unwrap_or(0), Ordering::Relaxed);
+}
+
+pub fn max_running_servers() -> Option<usize> {
+    match MAX_RUNNING_SERVERS.load(Ordering::Relaxed) {
+        0 => None,
+        max => Some(max),
+    }
+}
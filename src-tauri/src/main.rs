@@ -2,5 +2,19 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
-    minecraft_server_manager_lib::run()
+    let args: Vec<String> = std::env::args().collect();
+    if args.iter().any(|arg| arg == "--uninstall-cleanup") {
+        let archive = args
+            .iter()
+            .position(|arg| arg == "--archive")
+            .and_then(|i| args.get(i + 1))
+            .map(std::path::PathBuf::from);
+        let ok = minecraft_server_manager_lib::run_uninstall_cleanup(archive);
+        std::process::exit(if ok { 0 } else { 1 });
+    } else if args.iter().any(|arg| arg == "--headless") {
+        let regenerate_token = args.iter().any(|arg| arg == "--regenerate-token");
+        minecraft_server_manager_lib::run_headless(regenerate_token)
+    } else {
+        minecraft_server_manager_lib::run()
+    }
 }
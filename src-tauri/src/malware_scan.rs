@@ -0,0 +1,297 @@
+// Malware scan
+// Checks downloaded plugin/mod jars against a hash blocklist (local hashes and
+// configurable feeds) before they are installed. No hashes ship with the app,
+// so nothing is flagged until the user adds hashes or a feed such as the
+// fractureiser IOC list. Jars are downloaded to a staging folder and only moved
+// into the server once the scan passed; flagged jars go to a quarantine folder
+// instead.
+
+use crate::clock::now_secs;
+use crate::error::OrNotFound;
+use crate::paths::AppPaths;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Feeds are downloaded again when the cache is older than this
+const FEED_REFRESH_SECS: u64 = 24 * 60 * 60;
+/// Former default feed; it never listed any hashes and is dropped from old configs
+const LEGACY_FEED_URL: &str =
+    "https://raw.githubusercontent.com/FCD-ABH/Prismarine-Core-Project/main/blocklists/fractureiser.txt";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanConfig {
+    pub enabled: bool,
+    /// Plain text feeds: one SHA-1 or SHA-256 hash per line, "#" starts a comment
+    #[serde(default)]
+    pub feed_urls: Vec<String>,
+    /// Extra hashes blocked locally
+    #[serde(default)]
+    pub blocked_hashes: Vec<String>,
+}
+
+impl Default for ScanConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            feed_urls: Vec::new(),
+            blocked_hashes: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+struct FeedCache {
+    fetched_at: u64,
+    feed_urls: Vec<String>, // Feeds the cache was built from
+    hashes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuarantinedFile {
+    pub id: String, // File name inside the quarantine folder
+    pub filename: String,
+    pub source_url: String,
+    pub hash: String,
+    pub quarantined_at: u64,
+}
+
+pub struct MalwareScanner {
+    data_dir: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl MalwareScanner {
+    pub fn new(paths: &AppPaths) -> Self {
+        let data_dir = paths.config_dir.clone();
+
+        Self {
+            data_dir,
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn config_path(&self) -> PathBuf {
+        self.data_dir.join("malware_scan.json")
+    }
+
+    fn cache_path(&self) -> PathBuf {
+        self.data_dir.join("malware_feed_cache.json")
+    }
+
+    fn quarantine_dir(&self) -> PathBuf {
+        self.data_dir.join("quarantine")
+    }
+
+    /// Where a download waits for its scan, outside every server folder
+    pub fn staging_path(&self, filename: &str) -> Result<PathBuf> {
+        let dir = self
+            .quarantine_dir()
+            .join("staging")
+            .join(uuid::Uuid::new_v4().to_string());
+        std::fs::create_dir_all(&dir)?;
+        Ok(dir.join(filename))
+    }
+
+    pub fn get_config(&self) -> ScanConfig {
+        let mut config: ScanConfig = read_json(&self.config_path()).unwrap_or_default();
+        config.feed_urls.retain(|url| url != LEGACY_FEED_URL);
+        config
+    }
+
+    pub fn set_config(&self, config: &ScanConfig) -> Result<()> {
+        write_json(&self.config_path(), config)
+    }
+
+    /// Returns the matching hash when the file is on the blocklist
+    pub async fn check_file(&self, path: &Path) -> Result<Option<String>> {
+        let config = self.get_config();
+        if !config.enabled {
+            return Ok(None);
+        }
+
+        let mut blocked: HashSet<String> = self.feed_hashes(&config).await;
+        blocked.extend(
+            config
+                .blocked_hashes
+                .iter()
+                .map(|h| h.trim().to_lowercase()),
+        );
+        if blocked.is_empty() {
+            return Ok(None);
+        }
+
+        let path = path.to_path_buf();
+        let (sha1, sha256) = tokio::task::spawn_blocking(move || hash_file(&path)).await??;
+
+        Ok([sha1, sha256].into_iter().find(|h| blocked.contains(h)))
+    }
+
+    /// Hashes from all feeds, refreshed once a day (stale cache is used when offline)
+    async fn feed_hashes(&self, config: &ScanConfig) -> HashSet<String> {
+        if config.feed_urls.is_empty() {
+            return HashSet::new();
+        }
+
+        let cache: FeedCache = read_json(&self.cache_path()).unwrap_or_default();
+        let now = now_secs();
+        if cache.feed_urls == config.feed_urls && now < cache.fetched_at + FEED_REFRESH_SECS {
+            return cache.hashes.into_iter().collect();
+        }
+
+        let mut hashes = HashSet::new();
+        for url in &config.feed_urls {
+            match fetch_feed(url).await {
+                Ok(feed) => hashes.extend(feed),
+                Err(e) => {
+                    println!("[MalwareScan] Failed to fetch feed {}: {}", url, e);
+                    // Keep the previous hashes rather than silently scanning with none
+                    return cache.hashes.into_iter().chain(hashes).collect();
+                }
+            }
+        }
+
+        let _ = write_json(
+            &self.cache_path(),
+            &FeedCache {
+                fetched_at: now,
+                feed_urls: config.feed_urls.clone(),
+                hashes: hashes.iter().cloned().collect(),
+            },
+        );
+        hashes
+    }
+
+    /// Move a flagged file out of the server into the quarantine folder
+    pub fn quarantine(&self, path: &Path, source_url: &str, hash: &str) -> Result<QuarantinedFile> {
+        let _guard = self.lock.lock().unwrap();
+        let dir = self.quarantine_dir();
+        std::fs::create_dir_all(&dir)?;
+
+        let filename = path
+            .file_name()
+            .context("Invalid file name")?
+            .to_string_lossy()
+            .to_string();
+        let entry = QuarantinedFile {
+            id: format!("{}_{}", now_secs(), filename),
+            filename,
+            source_url: source_url.to_string(),
+            hash: hash.to_string(),
+            quarantined_at: now_secs(),
+        };
+
+        // rename fails across drives, fall back to copy + delete
+        let target = dir.join(&entry.id);
+        if std::fs::rename(path, &target).is_err() {
+            std::fs::copy(path, &target)?;
+            std::fs::remove_file(path)?;
+        }
+
+        let mut index = self.list_quarantine();
+        index.push(entry.clone());
+        write_json(&dir.join("index.json"), &index)?;
+        Ok(entry)
+    }
+
+    pub fn list_quarantine(&self) -> Vec<QuarantinedFile> {
+        read_json(&self.quarantine_dir().join("index.json")).unwrap_or_default()
+    }
+
+    pub fn delete_quarantined(&self, id: &str) -> Result<()> {
+        let _guard = self.lock.lock().unwrap();
+        let dir = self.quarantine_dir();
+        let mut index = self.list_quarantine();
+        let entry = index
+            .iter()
+            .position(|q| q.id == id)
+            .or_not_found("Quarantined file not found")?;
+        let _ = std::fs::remove_file(dir.join(&index[entry].id));
+        index.remove(entry);
+        write_json(&dir.join("index.json"), &index)
+    }
+}
+
+async fn fetch_feed(url: &str) -> Result<Vec<String>> {
+    crate::offline::ensure_online("Updating the malware feed")?;
+    let client = reqwest::Client::builder()
+        .user_agent("MinecraftServerManager/0.1.0")
+        .timeout(Duration::from_secs(30))
+        .build()?;
+    let response = client.get(url).send().await?;
+    if !response.status().is_success() {
+        anyhow::bail!("HTTP {}", response.status());
+    }
+
+    Ok(parse_hashes(&response.text().await?))
+}
+
+/// One SHA-1 or SHA-256 hash per line, "#" starts a comment
+fn parse_hashes(text: &str) -> Vec<String> {
+    text.lines()
+        .filter_map(|line| line.split('#').next())
+        .filter_map(|line| line.split_whitespace().next())
+        .map(|hash| hash.to_lowercase())
+        .filter(|hash| {
+            (hash.len() == 40 || hash.len() == 64) && hash.chars().all(|c| c.is_ascii_hexdigit())
+        })
+        .collect()
+}
+
+/// Move a scanned jar from its staging folder into place and drop the folder
+pub fn install_staged(staged: &Path, dest: &Path) -> Result<()> {
+    // rename fails across drives, fall back to copy + delete
+    if std::fs::rename(staged, dest).is_err() {
+        std::fs::copy(staged, dest)?;
+        std::fs::remove_file(staged)?;
+    }
+    discard_staged(staged);
+    Ok(())
+}
+
+/// Delete a staged download and its staging folder
+pub fn discard_staged(staged: &Path) {
+    let _ = std::fs::remove_file(staged);
+    if let Some(dir) = staged.parent() {
+        let _ = std::fs::remove_dir(dir);
+    }
+}
+
+/// (SHA-1, SHA-256) as lowercase hex
+fn hash_file(path: &Path) -> Result<(String, String)> {
+    let mut file = std::fs::File::open(path)?;
+    let mut sha1 = Sha1::new();
+    let mut sha256 = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        sha1.update(&buffer[..read]);
+        sha256.update(&buffer[..read]);
+    }
+
+    let hex = |bytes: &[u8]| bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    Ok((hex(&sha1.finalize()), hex(&sha256.finalize())))
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Option<T> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(value)?)?;
+    Ok(())
+}
@@ -0,0 +1,290 @@
+// Modpack installer
+// Installs a Modrinth modpack (.mrpack) into a server folder. The pack's
+// server-side files are downloaded a few at a time, each with its own retries
+// and range resume (download_file) and checked against the pack's SHA-1.
+// Finished files are recorded in ".modpack-install.json" in the server folder,
+// so running the install again after a failure only fetches what is missing
+// instead of starting a multi-gigabyte pack over. Overrides are extracted last.
+
+use crate::download;
+use crate::malware_scan::MalwareScanner;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+/// Install progress, kept in the server folder
+const STATE_FILE: &str = ".modpack-install.json";
+/// Where a downloaded pack is kept until the install finished
+pub const PACK_FILE: &str = ".modpack.mrpack";
+/// Pack files downloaded at once (download_file also takes a global slot)
+const MAX_PARALLEL_FILES: usize = 4;
+/// Downloads of one file whose content does not match the pack's hash
+const FILE_ATTEMPTS: u32 = 2;
+
+#[derive(Debug, Deserialize)]
+struct PackIndex {
+    name: String,
+    #[serde(rename = "versionId", default)]
+    version_id: String,
+    files: Vec<PackFile>,
+    #[serde(default)]
+    dependencies: HashMap<String, String>, // "minecraft", "fabric-loader", "forge", ...
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct PackFile {
+    path: String,
+    hashes: PackHashes,
+    #[serde(default)]
+    env: Option<PackEnv>,
+    downloads: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct PackHashes {
+    sha1: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct PackEnv {
+    #[serde(default)]
+    server: String, // "required", "optional" or "unsupported"
+}
+
+/// Progress of an install, rewritten after every finished file
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+struct InstallState {
+    pack_sha1: String, // Started over when a different pack is installed
+    done: HashSet<String>,
+    overrides_done: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModpackInstallResult {
+    pub name: String,
+    pub version: String,
+    pub downloaded: usize,
+    pub skipped: usize, // Finished by an earlier run
+    pub dependencies: HashMap<String, String>,
+}
+
+/// Install `pack_path` into `server_dir`, continuing an earlier attempt at the same pack
+pub async fn install_mrpack(
+    client: &reqwest::Client,
+    scanner: Arc<MalwareScanner>,
+    pack_path: &Path,
+    server_dir: &Path,
+) -> Result<ModpackInstallResult> {
+    let pack = pack_path.to_path_buf();
+    let (index, pack_sha1) = tokio::task::spawn_blocking(move || -> Result<_> {
+        Ok((read_index(&pack)?, download::sha1_hex(&pack)?))
+    })
+    .await??;
+
+    let state_path = server_dir.join(STATE_FILE);
+    let mut state: InstallState = std::fs::read_to_string(&state_path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .filter(|state: &InstallState| state.pack_sha1 == pack_sha1)
+        .unwrap_or_else(|| InstallState {
+            pack_sha1: pack_sha1.clone(),
+            ..Default::default()
+        });
+    // A finished file deleted since then is downloaded again
+    state
+        .done
+        .retain(|path| safe_path(path).is_some_and(|p| server_dir.join(p).is_file()));
+
+    let files: Vec<PackFile> = index
+        .files
+        .iter()
+        .filter(|f| {
+            f.env
+                .as_ref()
+                .map_or(true, |env| env.server != "unsupported")
+        })
+        .cloned()
+        .collect();
+    let pending: Vec<PackFile> = files
+        .iter()
+        .filter(|f| !state.done.contains(&f.path))
+        .cloned()
+        .collect();
+    let skipped = files.len() - pending.len();
+    println!(
+        "[Modpack] Installing {} {}: {} files, {} already done",
+        index.name,
+        index.version_id,
+        files.len(),
+        skipped
+    );
+
+    let state = Arc::new(Mutex::new(state));
+    save_state(&state_path, &state.lock().unwrap())?;
+
+    let limit = Arc::new(Semaphore::new(MAX_PARALLEL_FILES));
+    let mut tasks = JoinSet::new();
+    for file in pending {
+        let client = client.clone();
+        let scanner = Arc::clone(&scanner);
+        let limit = Arc::clone(&limit);
+        let state = Arc::clone(&state);
+        let state_path = state_path.clone();
+        let server_dir = server_dir.to_path_buf();
+        tasks.spawn(async move {
+            let _permit = limit.acquire_owned().await.expect("semaphore closed");
+            install_file(&client, &scanner, &server_dir, &file)
+                .await
+                .with_context(|| format!("Failed to install {}", file.path))?;
+            let mut state = state.lock().unwrap();
+            state.done.insert(file.path.clone());
+            save_state(&state_path, &state)
+        });
+    }
+
+    let mut downloaded = 0;
+    let mut failures = Vec::new();
+    while let Some(result) = tasks.join_next().await {
+        match result? {
+            Ok(()) => downloaded += 1,
+            Err(e) => {
+                println!("[Modpack] {:#}", e);
+                failures.push(e);
+            }
+        }
+    }
+    if let Some(first) = failures.into_iter().next() {
+        return Err(first.context(format!(
+            "Some modpack files could not be installed; installing again continues from {} of {} files",
+            skipped + downloaded,
+            files.len()
+        )));
+    }
+
+    let overrides_done = state.lock().unwrap().overrides_done;
+    if !overrides_done {
+        let pack = pack_path.to_path_buf();
+        let server_dir = server_dir.to_path_buf();
+        tokio::task::spawn_blocking(move || extract_overrides(&pack, &server_dir)).await??;
+        let mut state = state.lock().unwrap();
+        state.overrides_done = true;
+        save_state(&state_path, &state)?;
+    }
+
+    println!("[Modpack] Installed {} {}", index.name, index.version_id);
+    Ok(ModpackInstallResult {
+        name: index.name,
+        version: index.version_id,
+        downloaded,
+        skipped,
+        dependencies: index.dependencies,
+    })
+}
+
+/// Download one pack file, verify it and put it in place
+async fn install_file(
+    client: &reqwest::Client,
+    scanner: &MalwareScanner,
+    server_dir: &Path,
+    file: &PackFile,
+) -> Result<()> {
+    let relative = safe_path(&file.path).context("Unsafe path in the modpack")?;
+    let dest = server_dir.join(relative);
+    if let Some(parent) = dest.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let urls: Vec<&str> = file.downloads.iter().map(String::as_str).collect();
+    let task_id = format!("modpack:{}", file.path);
+
+    let mut attempt = 1;
+    loop {
+        download::download_file(client, &urls, &dest, &task_id, None).await?;
+        let path = dest.clone();
+        let sha1 = tokio::task::spawn_blocking(move || download::sha1_hex(&path)).await??;
+        if sha1.eq_ignore_ascii_case(&file.hashes.sha1) {
+            break;
+        }
+        let _ = tokio::fs::remove_file(&dest).await;
+        if attempt >= FILE_ATTEMPTS {
+            anyhow::bail!("Downloaded file does not match the modpack's hash");
+        }
+        println!(
+            "[Modpack] {} has the wrong hash, downloading again",
+            file.path
+        );
+        attempt += 1;
+    }
+
+    if let Some(hash) = scanner.check_file(&dest).await? {
+        let entry = scanner.quarantine(&dest, file.downloads[0].as_str(), &hash)?;
+        anyhow::bail!(
+            "Matches a blocklisted jar ({}) and was moved to quarantine as {}",
+            hash,
+            entry.id
+        );
+    }
+    Ok(())
+}
+
+fn read_index(pack_path: &Path) -> Result<PackIndex> {
+    let file = std::fs::File::open(pack_path).context("Failed to open the modpack")?;
+    let mut archive = zip::ZipArchive::new(file).context("The modpack is not a zip file")?;
+    let mut content = String::new();
+    archive
+        .by_name("modrinth.index.json")
+        .context("modrinth.index.json not found - is this a Modrinth modpack?")?
+        .read_to_string(&mut content)?;
+    serde_json::from_str(&content).context("Invalid modrinth.index.json")
+}
+
+/// Copy "overrides/" and then "server-overrides/" into the server folder
+fn extract_overrides(pack_path: &Path, server_dir: &Path) -> Result<()> {
+    let file = std::fs::File::open(pack_path).context("Failed to open the modpack")?;
+    let mut archive = zip::ZipArchive::new(file).context("The modpack is not a zip file")?;
+
+    for prefix in ["overrides", "server-overrides"] {
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i)?;
+            let Some(path) = entry.enclosed_name() else {
+                continue;
+            };
+            let Ok(relative) = path.strip_prefix(prefix) else {
+                continue;
+            };
+            if relative.as_os_str().is_empty() {
+                continue;
+            }
+
+            let out_path = server_dir.join(relative);
+            if entry.is_dir() {
+                std::fs::create_dir_all(&out_path)?;
+            } else {
+                if let Some(parent) = out_path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                let mut out_file = std::fs::File::create(&out_path)?;
+                std::io::copy(&mut entry, &mut out_file)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A relative path made of plain names only ("mods/a.jar", never "../a" or "/a")
+fn safe_path(path: &str) -> Option<PathBuf> {
+    let path = Path::new(path);
+    let plain = path.components().all(|c| matches!(c, Component::Normal(_)));
+    (plain && path.components().next().is_some()).then(|| path.to_path_buf())
+}
+
+fn save_state(path: &Path, state: &InstallState) -> Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_string_pretty(state)?)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
@@ -57,7 +57,6 @@ impl Monitor {
     }
 
     /// Get statistics for a specific server process
-    #[allow(dead_code)]
     pub fn get_server_stats(&mut self, pid: u32) -> Option<ServerStats> {
         self.system.refresh_all();
 
@@ -95,6 +94,30 @@ impl Monitor {
         Ok(all_lines[start..].to_vec())
     }
 
+    /// Current end of latest.log, to be passed to `read_log_since` later
+    pub async fn log_position(server_path: &Path) -> u64 {
+        fs::metadata(server_path.join("logs").join("latest.log"))
+            .await
+            .map(|m| m.len())
+            .unwrap_or(0)
+    }
+
+    /// Log messages appended after `position`, without the "[time] [thread/LEVEL]: " prefix
+    pub async fn read_log_since(server_path: &Path, position: u64) -> Vec<String> {
+        let log = fs::read(server_path.join("logs").join("latest.log"))
+            .await
+            .unwrap_or_default();
+        let appended = log.get(position as usize..).unwrap_or_default();
+
+        String::from_utf8_lossy(appended)
+            .lines()
+            .map(|line| match line.find("]: ") {
+                Some(pos) if line.starts_with('[') => line[pos + 3..].to_string(),
+                _ => line.to_string(),
+            })
+            .collect()
+    }
+
     /// Parse server.properties to get max players
     #[allow(dead_code)]
     pub async fn get_max_players(server_path: &Path) -> Result<u32> {
@@ -119,7 +142,10 @@ impl Monitor {
         Ok(20) // Default if not found
     }
 
-    /// Parse server logs to find online players
+    /// Players online according to latest.log. A crashed server never logs
+    /// "left the game", so the set starts over at each server start and stop.
+    /// Vanish plugins hide joins and fake quits, prefer `parse_player_list`
+    /// over RCON when it is available.
     pub async fn get_online_players(server_path: &Path) -> Result<Vec<String>> {
         let log_path = server_path.join("logs").join("latest.log");
         if !log_path.exists() {
@@ -131,25 +157,64 @@ impl Monitor {
 
         // 1.20+ format: [16:32:04] [Server thread/INFO]: PlayerName joined the game
         for line in content.lines() {
-            if line.contains(": ") {
-                let parts: Vec<&str> = line.split(": ").collect();
-                if parts.len() < 2 {
-                    continue;
-                }
-                let message = parts[1];
-
-                if message.contains(" joined the game") {
-                    if let Some(name) = message.split(" joined").next() {
-                        players.insert(name.trim().to_string());
-                    }
-                } else if message.contains(" left the game") {
-                    if let Some(name) = message.split(" left").next() {
-                        players.remove(name.trim());
-                    }
-                }
+            let Some((_, message)) = line.split_once("]: ") else {
+                continue;
+            };
+            let lower = message.to_lowercase();
+            if lower.starts_with("starting minecraft server")
+                || lower.starts_with("stopping server")
+            {
+                players.clear();
+            } else if let Some(name) = message.strip_suffix(" joined the game") {
+                players.insert(name.trim().to_string());
+            } else if let Some(name) = message.strip_suffix(" left the game") {
+                players.remove(name.trim());
             }
         }
 
         Ok(players.into_iter().collect())
     }
 }
+
+/// Names from the reply to `list`: vanilla puts them after the colon of its
+/// one line, Bukkit and Essentials list them per group on further lines with
+/// color codes and markers such as "[HIDDEN]" or "[AFK]" (vanished players
+/// are still listed to the console). Essentials shows nicknames as "~Nick"
+/// instead of the account name, those entries are skipped.
+pub fn parse_player_list(reply: &str) -> Vec<String> {
+    let mut plain = String::new();
+    let mut chars = reply.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{a7}' {
+            chars.next(); // Color code
+        } else {
+            plain.push(c);
+        }
+    }
+
+    let mut players = Vec::new();
+    for line in plain.lines() {
+        let Some((_, names)) = line.split_once(':') else {
+            continue;
+        };
+        for entry in names.split(',') {
+            let mut entry = entry.trim();
+            while let Some((_, rest)) = entry.strip_prefix('[').and_then(|r| r.split_once(']')) {
+                entry = rest.trim_start();
+            }
+            let name = entry.split_whitespace().last().unwrap_or_default();
+            if name.starts_with('~') {
+                continue;
+            }
+            let valid = !name.is_empty()
+                && name.len() <= 17 // 16 plus Floodgate's prefix
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '*');
+            if valid && !players.iter().any(|p| p == name) {
+                players.push(name.to_string());
+            }
+        }
+    }
+    players
+}
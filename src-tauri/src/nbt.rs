@@ -0,0 +1,183 @@
+// NBT
+// Minimal reader for Minecraft's NBT format (big endian, uncompressed), used
+// for level.dat and region file chunks. Arrays other than longs are skipped.
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+
+/// Decompressed content of a gzipped NBT file such as level.dat
+pub(crate) fn read_gzip(path: &Path) -> Result<Vec<u8>> {
+    let file =
+        std::fs::File::open(path).with_context(|| format!("{} not found", path.display()))?;
+    let mut data = Vec::new();
+    flate2::read::GzDecoder::new(file).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+#[derive(Debug)]
+pub(crate) enum Nbt {
+    Int(i64), // Byte, Short, Int and Long
+    Float(f64),
+    ByteArray,
+    String(String),
+    List(Vec<Nbt>),
+    Compound(HashMap<String, Nbt>),
+    IntArray,
+    LongArray(Vec<i64>),
+}
+
+impl Nbt {
+    pub(crate) fn get(&self, key: &str) -> Option<&Nbt> {
+        match self {
+            Nbt::Compound(map) => map.get(key),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_i64(&self) -> Option<i64> {
+        match self {
+            Nbt::Int(value) => Some(*value),
+            Nbt::Float(value) => Some(*value as i64),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            Nbt::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_list(&self) -> &[Nbt] {
+        match self {
+            Nbt::List(items) => items,
+            _ => &[],
+        }
+    }
+}
+
+pub(crate) struct NbtReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> NbtReader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        NbtReader { data, pos: 0 }
+    }
+
+    /// Offset of the next unread byte
+    pub(crate) fn pos(&self) -> usize {
+        self.pos
+    }
+
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(n)?)?;
+        self.pos += n;
+        Some(bytes)
+    }
+
+    pub(crate) fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    fn i16(&mut self) -> Option<i16> {
+        Some(i16::from_be_bytes(self.take(2)?.try_into().ok()?))
+    }
+
+    fn i32(&mut self) -> Option<i32> {
+        Some(i32::from_be_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn i64(&mut self) -> Option<i64> {
+        Some(i64::from_be_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn len(&mut self) -> Option<usize> {
+        usize::try_from(self.i32()?).ok()
+    }
+
+    pub(crate) fn string(&mut self) -> Option<String> {
+        let len = self.i16()? as u16 as usize;
+        Some(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+
+    pub(crate) fn payload(&mut self, tag: u8) -> Option<Nbt> {
+        Some(match tag {
+            1 => Nbt::Int(self.u8()? as i8 as i64),
+            2 => Nbt::Int(self.i16()? as i64),
+            3 => Nbt::Int(self.i32()? as i64),
+            4 => Nbt::Int(self.i64()?),
+            5 => Nbt::Float(f32::from_bits(self.i32()? as u32) as f64),
+            6 => Nbt::Float(f64::from_bits(self.i64()? as u64)),
+            7 => {
+                let len = self.len()?;
+                self.take(len)?;
+                Nbt::ByteArray
+            }
+            8 => Nbt::String(self.string()?),
+            9 => {
+                let item_tag = self.u8()?;
+                let len = self.len()?;
+                let mut items = Vec::with_capacity(len.min(4096));
+                for _ in 0..len {
+                    items.push(self.payload(item_tag)?);
+                }
+                Nbt::List(items)
+            }
+            10 => {
+                let mut map = HashMap::new();
+                loop {
+                    let tag = self.u8()?;
+                    if tag == 0 {
+                        break;
+                    }
+                    let name = self.string()?;
+                    map.insert(name, self.payload(tag)?);
+                }
+                Nbt::Compound(map)
+            }
+            11 => {
+                let len = self.len()?;
+                self.take(len.checked_mul(4)?)?;
+                Nbt::IntArray
+            }
+            12 => {
+                let len = self.len()?;
+                let mut values = Vec::with_capacity(len.min(4096));
+                for _ in 0..len {
+                    values.push(self.i64()?);
+                }
+                Nbt::LongArray(values)
+            }
+            _ => return None,
+        })
+    }
+    /// Move to the payload of the compound `name` inside the compound being read,
+    /// skipping the tags before it
+    pub(crate) fn enter_compound(&mut self, name: &str) -> Option<()> {
+        loop {
+            let tag = self.u8()?;
+            if tag == 0 {
+                return None;
+            }
+            if self.string()? == name && tag == 10 {
+                return Some(());
+            }
+            self.payload(tag)?;
+        }
+    }
+}
+
+/// Root compound of an uncompressed NBT document
+pub(crate) fn parse(data: &[u8]) -> Option<Nbt> {
+    let mut reader = NbtReader::new(data);
+    if reader.u8()? != 10 {
+        return None;
+    }
+    reader.string()?;
+    reader.payload(10)
+}
@@ -0,0 +1,426 @@
+// Notifications
+// Events (crashes, player joins, failed backups, failover, reports) are routed
+// to channels: Discord or Slack webhooks, a Telegram bot, SMTP email or an
+// ntfy topic. The routing table in notifications.json picks the channels per
+// event; their credentials (webhook URLs, bot tokens, SMTP passwords) live in
+// the secret store. Events without a route still go to the failover webhook
+// where they did before channels existed (failover and reports).
+
+use crate::error::OrNotFound;
+use crate::secrets;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::time::Duration;
+
+const DEFAULT_NTFY_SERVER: &str = "https://ntfy.sh";
+
+// Serializes read-modify-write of notifications.json
+static CONFIG_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum NotifyEvent {
+    Crash,
+    PlayerJoin,
+    BackupFailed,
+    Failover, // A server moved to the bridge tunnel
+    Report,   // Weekly and monthly statistics
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ChannelKind {
+    Discord,
+    Slack,
+    Telegram {
+        chat_id: String,
+    },
+    Email {
+        smtp_host: String,
+        smtp_port: u16, // 465 for implicit TLS, STARTTLS otherwise
+        username: String,
+        from: String,
+        to: String,
+    },
+    Ntfy {
+        #[serde(default)]
+        server: Option<String>, // https://ntfy.sh when empty
+        topic: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Channel {
+    pub id: String,
+    pub name: String,
+    pub kind: ChannelKind,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NotificationConfig {
+    #[serde(default)]
+    pub channels: Vec<Channel>,
+    /// Event -> ids of the channels it goes to
+    #[serde(default)]
+    pub routes: HashMap<NotifyEvent, Vec<String>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub event: NotifyEvent,
+    pub title: String,
+    pub message: String,
+}
+
+type SendFuture<'a> = Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>>;
+
+/// A channel notifications can be delivered through
+pub trait Notifier: Send + Sync {
+    fn send<'a>(&'a self, notification: &'a Notification) -> SendFuture<'a>;
+}
+
+struct DiscordNotifier {
+    webhook_url: String,
+}
+
+struct SlackNotifier {
+    webhook_url: String,
+}
+
+struct TelegramNotifier {
+    bot_token: String,
+    chat_id: String,
+}
+
+struct EmailNotifier {
+    smtp_host: String,
+    smtp_port: u16,
+    username: String,
+    password: String,
+    from: String,
+    to: String,
+}
+
+struct NtfyNotifier {
+    server: String,
+    topic: String,
+    access_token: Option<String>,
+}
+
+fn http_client() -> Result<reqwest::Client> {
+    Ok(reqwest::Client::builder()
+        .user_agent("MinecraftServerManager/0.1.0")
+        .timeout(Duration::from_secs(10))
+        .build()?)
+}
+
+async fn check_response(response: reqwest::Response, service: &str) -> Result<()> {
+    if !response.status().is_success() {
+        anyhow::bail!("{} returned HTTP {}", service, response.status());
+    }
+    Ok(())
+}
+
+impl Notifier for DiscordNotifier {
+    fn send<'a>(&'a self, notification: &'a Notification) -> SendFuture<'a> {
+        Box::pin(async move {
+            let content = format!("**{}**\n{}", notification.title, notification.message);
+            // The webhook URL is the credential, keep it out of error messages
+            let response = http_client()?
+                .post(&self.webhook_url)
+                .json(&serde_json::json!({ "content": content }))
+                .send()
+                .await
+                .map_err(reqwest::Error::without_url)?;
+            check_response(response, "Discord").await
+        })
+    }
+}
+
+impl Notifier for SlackNotifier {
+    fn send<'a>(&'a self, notification: &'a Notification) -> SendFuture<'a> {
+        Box::pin(async move {
+            let text = format!("*{}*\n{}", notification.title, notification.message);
+            let response = http_client()?
+                .post(&self.webhook_url)
+                .json(&serde_json::json!({ "text": text }))
+                .send()
+                .await
+                .map_err(reqwest::Error::without_url)?;
+            check_response(response, "Slack").await
+        })
+    }
+}
+
+impl Notifier for TelegramNotifier {
+    fn send<'a>(&'a self, notification: &'a Notification) -> SendFuture<'a> {
+        Box::pin(async move {
+            let url = format!("https://api.telegram.org/bot{}/sendMessage", self.bot_token);
+            let response = http_client()?
+                .post(url)
+                .json(&serde_json::json!({
+                    "chat_id": self.chat_id,
+                    "text": format!("{}\n{}", notification.title, notification.message),
+                }))
+                .send()
+                .await
+                .map_err(reqwest::Error::without_url)?;
+            check_response(response, "Telegram").await
+        })
+    }
+}
+
+impl Notifier for EmailNotifier {
+    fn send<'a>(&'a self, notification: &'a Notification) -> SendFuture<'a> {
+        Box::pin(async move {
+            use lettre::transport::smtp::authentication::Credentials;
+            use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+            let email = Message::builder()
+                .from(self.from.parse().context("Invalid sender address")?)
+                .to(self.to.parse().context("Invalid recipient address")?)
+                .subject(&notification.title)
+                .body(notification.message.clone())?;
+            let transport = if self.smtp_port == 465 {
+                AsyncSmtpTransport::<Tokio1Executor>::relay(&self.smtp_host)?
+            } else {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&self.smtp_host)?
+            };
+            transport
+                .port(self.smtp_port)
+                .credentials(Credentials::new(
+                    self.username.clone(),
+                    self.password.clone(),
+                ))
+                .timeout(Some(Duration::from_secs(15)))
+                .build()
+                .send(email)
+                .await
+                .context("SMTP delivery failed")?;
+            Ok(())
+        })
+    }
+}
+
+impl Notifier for NtfyNotifier {
+    fn send<'a>(&'a self, notification: &'a Notification) -> SendFuture<'a> {
+        Box::pin(async move {
+            let url = format!("{}/{}", self.server.trim_end_matches('/'), self.topic);
+            let mut request = http_client()?
+                .post(url)
+                // ntfy reads headers as Latin-1, the title goes in the body for other scripts
+                .body(format!("{}\n{}", notification.title, notification.message));
+            if notification.event == NotifyEvent::Crash {
+                request = request.header("Priority", "high");
+            }
+            if let Some(token) = &self.access_token {
+                request = request.bearer_auth(token);
+            }
+            let response = request.send().await.map_err(reqwest::Error::without_url)?;
+            check_response(response, "ntfy").await
+        })
+    }
+}
+
+/// `notifier` off the async runtime, the keyring can block
+async fn load_notifier(channel: &Channel) -> Result<Box<dyn Notifier>> {
+    let channel = channel.clone();
+    tokio::task::spawn_blocking(move || notifier(&channel)).await?
+}
+
+/// The notifier for a channel, with its credential from the secret store
+fn notifier(channel: &Channel) -> Result<Box<dyn Notifier>> {
+    let secret = secrets::get(&secrets::notify_key(&channel.id))?;
+    let required = || {
+        secret
+            .clone()
+            .filter(|s| !s.is_empty())
+            .with_context(|| format!("No credential is stored for \"{}\"", channel.name))
+    };
+    Ok(match &channel.kind {
+        ChannelKind::Discord => Box::new(DiscordNotifier {
+            webhook_url: required()?,
+        }),
+        ChannelKind::Slack => Box::new(SlackNotifier {
+            webhook_url: required()?,
+        }),
+        ChannelKind::Telegram { chat_id } => Box::new(TelegramNotifier {
+            bot_token: required()?,
+            chat_id: chat_id.clone(),
+        }),
+        ChannelKind::Email {
+            smtp_host,
+            smtp_port,
+            username,
+            from,
+            to,
+        } => Box::new(EmailNotifier {
+            smtp_host: smtp_host.clone(),
+            smtp_port: *smtp_port,
+            username: username.clone(),
+            password: required()?,
+            from: from.clone(),
+            to: to.clone(),
+        }),
+        ChannelKind::Ntfy { server, topic } => Box::new(NtfyNotifier {
+            server: server
+                .clone()
+                .filter(|s| !s.is_empty())
+                .unwrap_or_else(|| DEFAULT_NTFY_SERVER.to_string()),
+            topic: topic.clone(),
+            access_token: secret.filter(|s| !s.is_empty()), // Public topics need none
+        }),
+    })
+}
+
+fn config_path() -> PathBuf {
+    crate::paths::get().config_file("notifications.json")
+}
+
+pub fn load_config() -> NotificationConfig {
+    std::fs::read_to_string(config_path())
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+/// Save the channels and routes. `credentials` holds new secrets by channel id;
+/// channels without an entry keep theirs, removed channels lose theirs.
+pub fn save_config(
+    config: &NotificationConfig,
+    credentials: &HashMap<String, String>,
+) -> Result<()> {
+    let _guard = CONFIG_LOCK.lock().unwrap();
+    for channel in &config.channels {
+        if channel.id.is_empty() || channel.name.trim().is_empty() {
+            anyhow::bail!("Every channel needs an id and a name");
+        }
+    }
+    for ids in config.routes.values() {
+        if let Some(unknown) = ids
+            .iter()
+            .find(|id| !config.channels.iter().any(|c| &c.id == *id))
+        {
+            anyhow::bail!("A route points to the unknown channel {}", unknown);
+        }
+    }
+
+    for removed in load_config()
+        .channels
+        .iter()
+        .filter(|old| !config.channels.iter().any(|c| c.id == old.id))
+    {
+        secrets::delete(&secrets::notify_key(&removed.id))?;
+    }
+    for (id, value) in credentials {
+        if value.is_empty() {
+            secrets::delete(&secrets::notify_key(id))?;
+        } else {
+            secrets::set(&secrets::notify_key(id), value)?;
+        }
+    }
+
+    let path = config_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, serde_json::to_string_pretty(config)?)?;
+    Ok(())
+}
+
+/// The failover webhook, for events that went there before channels existed
+fn legacy_webhook(event: NotifyEvent) -> Option<String> {
+    matches!(event, NotifyEvent::Failover | NotifyEvent::Report)
+        .then(|| secrets::get(secrets::FAILOVER_WEBHOOK).ok().flatten())
+        .flatten()
+        .filter(|url| !url.is_empty())
+}
+
+/// Whether an event would be delivered anywhere
+pub async fn has_route(event: NotifyEvent) -> bool {
+    tokio::task::spawn_blocking(move || {
+        load_config()
+            .routes
+            .get(&event)
+            .is_some_and(|ids| !ids.is_empty())
+            || legacy_webhook(event).is_some()
+    })
+    .await
+    .unwrap_or(false)
+}
+
+/// Send an event to every channel routed for it. Failures are logged, one
+/// broken channel does not keep the others from getting the event. Returns
+/// whether it reached at least one channel.
+pub async fn dispatch(event: NotifyEvent, title: &str, message: &str) -> bool {
+    if crate::offline::is_offline() {
+        return false;
+    }
+    let config = tokio::task::spawn_blocking(load_config)
+        .await
+        .unwrap_or_default();
+    let notification = Notification {
+        event,
+        title: title.to_string(),
+        message: message.to_string(),
+    };
+
+    let channels: Vec<&Channel> = config
+        .routes
+        .get(&event)
+        .into_iter()
+        .flatten()
+        .filter_map(|id| config.channels.iter().find(|c| &c.id == id))
+        .collect();
+    if channels.is_empty() {
+        let legacy = tokio::task::spawn_blocking(move || legacy_webhook(event))
+            .await
+            .ok()
+            .flatten();
+        let Some(url) = legacy else {
+            return false;
+        };
+        let text = format!("{}\n{}", title, message);
+        return match crate::failover::post_webhook(&url, &text).await {
+            Ok(()) => true,
+            Err(e) => {
+                println!("[Notify] Webhook failed for {:?}: {}", event, e);
+                false
+            }
+        };
+    }
+
+    let mut delivered = false;
+    for channel in channels {
+        let sent = match load_notifier(channel).await {
+            Ok(notifier) => notifier.send(&notification).await,
+            Err(e) => Err(e),
+        };
+        match sent {
+            Ok(()) => delivered = true,
+            Err(e) => println!("[Notify] {} failed for {:?}: {}", channel.name, event, e),
+        }
+    }
+    delivered
+}
+
+/// Send a test message through one channel
+pub async fn test_channel(channel_id: &str) -> Result<()> {
+    crate::offline::ensure_online("Sending a notification")?;
+    let config = tokio::task::spawn_blocking(load_config).await?;
+    let channel = config
+        .channels
+        .iter()
+        .find(|c| c.id == channel_id)
+        .or_not_found("Channel not found")?;
+    let notification = Notification {
+        event: NotifyEvent::Report,
+        title: "Prismarine test notification".to_string(),
+        message: format!("\"{}\" is set up correctly.", channel.name),
+    };
+    load_notifier(channel).await?.send(&notification).await
+}
@@ -0,0 +1,38 @@
+// Offline mode
+// For LAN parties without an internet connection: network calls check
+// `ensure_online` first and fail at once with an OfflineMode error instead
+// of hanging until their timeouts. LAN features (UPnP, transfers between
+// instances, RCON) keep working.
+
+use crate::error::PrismarineError;
+use anyhow::Result;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+pub fn set(offline: bool) {
+    if OFFLINE.swap(offline, Ordering::Relaxed) != offline {
+        println!(
+            "[Offline] Offline mode {}",
+            if offline { "enabled" } else { "disabled" }
+        );
+    }
+}
+
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+}
+
+/// Fail with OfflineMode when `action` would need the internet
+pub fn ensure_online(action: &str) -> Result<()> {
+    if is_offline() {
+        return Err(PrismarineError::OfflineMode {
+            message: format!(
+                "{} needs an internet connection, offline mode is on",
+                action
+            ),
+        }
+        .into());
+    }
+    Ok(())
+}
@@ -0,0 +1,154 @@
+// App paths
+// Every folder the app keeps its own files in, resolved once at startup and
+// handed to the subsystems. Defaults are the per-user OS folders; a portable
+// install keeps everything next to the executable instead.
+//
+// Resolution, later wins:
+//  1. OS folders (config: .../MinecraftServerManager, data/cache: .../Prismarine)
+//  2. Portable: a `portable` file next to the executable, or PRISMARINE_HOME,
+//     puts all four under one folder
+//  3. `paths` in settings.json (data, cache and log only, settings.json itself
+//     lives in the config folder)
+//  4. PRISMARINE_CONFIG_DIR, PRISMARINE_DATA_DIR, PRISMARINE_CACHE_DIR,
+//     PRISMARINE_LOG_DIR
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// Marker file next to the executable that makes the install portable
+const PORTABLE_MARKER: &str = "portable";
+/// Folder next to the executable a portable install keeps its files in
+const PORTABLE_DIR: &str = "PrismarineData";
+
+static PATHS: OnceLock<AppPaths> = OnceLock::new();
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AppPaths {
+    pub config_dir: PathBuf, // Config files, secrets fallback, instance lock
+    pub data_dir: PathBuf,   // Java runtimes, stats, restore points, tunnel binaries
+    pub cache_dir: PathBuf,  // Downloads that can be fetched again (jar cache)
+    pub log_dir: PathBuf,    // The app's own log (prismarine.log)
+    pub portable: bool,
+}
+
+/// Overrides kept in settings.json, applied on the next start
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PathOverrides {
+    #[serde(default)]
+    pub data_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub cache_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub log_dir: Option<PathBuf>,
+}
+
+fn env_dir(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Folder of a portable install, None for a regular one
+fn portable_home() -> Option<PathBuf> {
+    if let Some(home) = env_dir("PRISMARINE_HOME") {
+        return Some(home);
+    }
+    let exe_dir = std::env::current_exe().ok()?.parent()?.to_path_buf();
+    exe_dir
+        .join(PORTABLE_MARKER)
+        .exists()
+        .then(|| exe_dir.join(PORTABLE_DIR))
+}
+
+impl AppPaths {
+    /// Paths from the OS folders, a portable install and the environment;
+    /// settings overrides come on top with `with_overrides`
+    pub fn from_env() -> Self {
+        let mut paths = match portable_home() {
+            Some(home) => Self {
+                config_dir: home.join("config"),
+                data_dir: home.join("data"),
+                cache_dir: home.join("cache"),
+                log_dir: home.join("logs"),
+                portable: true,
+            },
+            None => {
+                let data_dir = dirs::data_local_dir()
+                    .unwrap_or_else(|| PathBuf::from("."))
+                    .join("Prismarine");
+                Self {
+                    config_dir: dirs::config_dir()
+                        .unwrap_or_else(|| PathBuf::from("."))
+                        .join("MinecraftServerManager"),
+                    cache_dir: dirs::cache_dir()
+                        .unwrap_or_else(|| PathBuf::from("."))
+                        .join("Prismarine"),
+                    log_dir: data_dir.join("logs"),
+                    data_dir,
+                    portable: false,
+                }
+            }
+        };
+        if let Some(dir) = env_dir("PRISMARINE_CONFIG_DIR") {
+            paths.config_dir = dir;
+        }
+        paths.apply_env();
+        paths
+    }
+
+    fn apply_env(&mut self) {
+        if let Some(dir) = env_dir("PRISMARINE_DATA_DIR") {
+            self.data_dir = dir;
+        }
+        if let Some(dir) = env_dir("PRISMARINE_CACHE_DIR") {
+            self.cache_dir = dir;
+        }
+        if let Some(dir) = env_dir("PRISMARINE_LOG_DIR") {
+            self.log_dir = dir;
+        }
+    }
+
+    /// Settings overrides; the environment still wins over them
+    pub fn with_overrides(mut self, overrides: &PathOverrides) -> Self {
+        if let Some(dir) = &overrides.data_dir {
+            self.data_dir = dir.clone();
+        }
+        if let Some(dir) = &overrides.cache_dir {
+            self.cache_dir = dir.clone();
+        }
+        if let Some(dir) = &overrides.log_dir {
+            self.log_dir = dir.clone();
+        }
+        self.apply_env();
+        self
+    }
+
+    pub fn config_file(&self, name: &str) -> PathBuf {
+        self.config_dir.join(name)
+    }
+
+    /// Where new servers go unless settings say otherwise. Regular installs keep
+    /// the folder they have always used.
+    pub fn default_servers_dir(&self) -> PathBuf {
+        if self.portable || env_dir("PRISMARINE_DATA_DIR").is_some() {
+            return self.data_dir.join("servers");
+        }
+        dirs::data_local_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("MinecraftServerManager")
+            .join("servers")
+    }
+}
+
+/// Make `paths` the ones `get` returns; only the first call has an effect
+pub fn init(paths: AppPaths) {
+    if PATHS.set(paths).is_err() {
+        println!("[Paths] Already initialized, keeping the first paths");
+    }
+}
+
+/// Paths for code without a handle to them (free functions in the storage modules)
+pub fn get() -> &'static AppPaths {
+    PATHS.get_or_init(AppPaths::from_env)
+}
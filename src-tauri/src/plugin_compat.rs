@@ -0,0 +1,301 @@
+// Plugin compatibility
+// Checks the installed plugin and mod jars against a Minecraft version before
+// an upgrade. Jars are identified on Modrinth by their SHA-1, the rest on
+// Hangar by the name in plugin.yml; jars found nowhere are reported as unknown.
+
+use anyhow::Result;
+use serde::Serialize;
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginUpdate {
+    pub version: String,
+    pub url: String,
+    pub filename: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginCompatibility {
+    pub file: String,
+    pub name: String, // Name from plugin.yml, else the file name
+    pub installed_version: Option<String>,
+    pub source: Option<String>, // "modrinth" or "hangar", None when not found
+    pub compatible: Option<bool>, // None when it could not be determined
+    pub update: Option<PluginUpdate>, // Newest release supporting the target version
+}
+
+/// "1.21.4" -> "1.21"
+pub fn major_line(version: &str) -> String {
+    version.split('.').take(2).collect::<Vec<_>>().join(".")
+}
+
+pub fn crosses_major(from: &str, to: &str) -> bool {
+    major_line(from) != major_line(to)
+}
+
+struct Jar {
+    path: PathBuf,
+    sha1: String,
+    name: Option<String>,
+    version: Option<String>,
+}
+
+/// Name and version from plugin.yml or paper-plugin.yml
+fn read_descriptor(path: &Path) -> (Option<String>, Option<String>) {
+    let Ok(file) = std::fs::File::open(path) else {
+        return (None, None);
+    };
+    let Ok(mut archive) = zip::ZipArchive::new(file) else {
+        return (None, None);
+    };
+    for descriptor in ["paper-plugin.yml", "plugin.yml"] {
+        let Ok(mut entry) = archive.by_name(descriptor) else {
+            continue;
+        };
+        let mut content = String::new();
+        if entry.read_to_string(&mut content).is_err() {
+            continue;
+        }
+        let Ok(yaml) = serde_yaml::from_str::<serde_yaml::Value>(&content) else {
+            continue;
+        };
+        let field = |key: &str| match yaml.get(key)? {
+            serde_yaml::Value::String(s) => Some(s.clone()),
+            serde_yaml::Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        };
+        return (field("name"), field("version"));
+    }
+    (None, None)
+}
+
+/// (file stem, plugin.yml name) of the plugin jars in `dir`
+pub fn plugin_names(dir: &Path) -> Vec<(String, String)> {
+    std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "jar"))
+        .filter_map(|path| {
+            let stem = path.file_stem()?.to_string_lossy().to_string();
+            Some((stem, read_descriptor(&path).0?))
+        })
+        .collect()
+}
+
+fn scan_jars(dir: &Path) -> Vec<Jar> {
+    let mut jars: Vec<Jar> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "jar"))
+        .filter_map(|path| {
+            let mut hasher = Sha1::new();
+            std::io::copy(&mut std::fs::File::open(&path).ok()?, &mut hasher).ok()?;
+            let sha1 = hasher
+                .finalize()
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect();
+            let (name, version) = read_descriptor(&path);
+            Some(Jar {
+                path,
+                sha1,
+                name,
+                version,
+            })
+        })
+        .collect();
+    jars.sort_by(|a, b| a.path.cmp(&b.path));
+    jars
+}
+
+fn primary_file(version: &serde_json::Value) -> Option<(String, String)> {
+    let files = version["files"].as_array()?;
+    let file = files
+        .iter()
+        .find(|f| f["primary"].as_bool().unwrap_or(false))
+        .or(files.first())?;
+    Some((
+        file["url"].as_str()?.to_string(),
+        file["filename"].as_str()?.to_string(),
+    ))
+}
+
+async fn modrinth_lookup(
+    client: &reqwest::Client,
+    path: &str,
+    body: serde_json::Value,
+) -> HashMap<String, serde_json::Value> {
+    let response = client
+        .post(format!("https://api.modrinth.com/v2/{}", path))
+        .json(&body)
+        .send()
+        .await;
+    match response {
+        Ok(response) if response.status().is_success() => response.json().await.unwrap_or_default(),
+        Ok(response) => {
+            println!(
+                "[PluginCompat] Modrinth {} failed: {}",
+                path,
+                response.status()
+            );
+            HashMap::new()
+        }
+        Err(e) => {
+            println!("[PluginCompat] Modrinth {} failed: {}", path, e);
+            HashMap::new()
+        }
+    }
+}
+
+async fn hangar_version(client: &reqwest::Client, url: &str) -> Option<serde_json::Value> {
+    let response = client.get(url).send().await.ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    response.json().await.ok()
+}
+
+/// Whether a Hangar version lists `target` for Paper
+fn hangar_supports(version: &serde_json::Value, target: &str) -> bool {
+    version["platformDependencies"]["PAPER"]
+        .as_array()
+        .is_some_and(|versions| versions.iter().any(|v| v.as_str() == Some(target)))
+}
+
+async fn check_hangar(
+    client: &reqwest::Client,
+    jar: &Jar,
+    name: &str,
+    target: &str,
+    result: &mut PluginCompatibility,
+) {
+    let latest = hangar_version(
+        client,
+        &format!(
+            "https://hangar.papermc.io/api/v1/projects/{}/versions?platform=PAPER&platformVersion={}&limit=1",
+            name, target
+        ),
+    )
+    .await;
+    let Some(latest) = latest.and_then(|page| page["result"].as_array()?.first().cloned()) else {
+        return;
+    };
+    result.source = Some("hangar".to_string());
+
+    let latest_name = latest["name"].as_str().unwrap_or_default().to_string();
+    if jar.version.as_deref() == Some(latest_name.as_str()) {
+        result.compatible = Some(true);
+        return;
+    }
+    if let Some(installed) = &jar.version {
+        if let Some(version) = hangar_version(
+            client,
+            &format!(
+                "https://hangar.papermc.io/api/v1/projects/{}/versions/{}",
+                name, installed
+            ),
+        )
+        .await
+        {
+            result.compatible = Some(hangar_supports(&version, target));
+        }
+    }
+
+    let download = &latest["downloads"]["PAPER"];
+    if let (Some(url), Some(filename)) = (
+        download["downloadUrl"].as_str(),
+        download["fileInfo"]["name"].as_str(),
+    ) {
+        result.update = Some(PluginUpdate {
+            version: latest_name,
+            url: url.to_string(),
+            filename: filename.to_string(),
+        });
+    }
+}
+
+/// Compatibility of every jar in `dir` with Minecraft `target`
+pub async fn check(dir: &Path, target: &str, loaders: &[&str]) -> Result<Vec<PluginCompatibility>> {
+    crate::offline::ensure_online("Checking plugin compatibility")?;
+    let dir = dir.to_path_buf();
+    let jars = tokio::task::spawn_blocking(move || scan_jars(&dir)).await?;
+    if jars.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let client = reqwest::Client::builder()
+        .user_agent("MinecraftServerManager/0.1.0")
+        .build()?;
+    let hashes: Vec<&str> = jars.iter().map(|jar| jar.sha1.as_str()).collect();
+    let installed = modrinth_lookup(
+        &client,
+        "version_files",
+        serde_json::json!({ "hashes": hashes, "algorithm": "sha1" }),
+    )
+    .await;
+    let updates = modrinth_lookup(
+        &client,
+        "version_files/update",
+        serde_json::json!({
+            "hashes": hashes,
+            "algorithm": "sha1",
+            "loaders": loaders,
+            "game_versions": [target],
+        }),
+    )
+    .await;
+
+    let mut results = Vec::new();
+    for jar in &jars {
+        let file = jar
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let mut result = PluginCompatibility {
+            name: jar.name.clone().unwrap_or_else(|| file.clone()),
+            file,
+            installed_version: jar.version.clone(),
+            source: None,
+            compatible: None,
+            update: None,
+        };
+
+        if let Some(current) = installed.get(&jar.sha1) {
+            result.source = Some("modrinth".to_string());
+            result.installed_version = current["version_number"]
+                .as_str()
+                .map(str::to_string)
+                .or(result.installed_version);
+            let supports = current["game_versions"]
+                .as_array()
+                .is_some_and(|versions| versions.iter().any(|v| v.as_str() == Some(target)));
+            result.compatible = Some(supports);
+            if let Some(update) = updates.get(&jar.sha1) {
+                if update["id"] != current["id"] {
+                    if let Some((url, filename)) = primary_file(update) {
+                        result.update = Some(PluginUpdate {
+                            version: update["version_number"]
+                                .as_str()
+                                .unwrap_or_default()
+                                .to_string(),
+                            url,
+                            filename,
+                        });
+                    }
+                }
+            }
+        } else if let Some(name) = &jar.name {
+            check_hangar(&client, jar, name, target, &mut result).await;
+        }
+        results.push(result);
+    }
+    Ok(results)
+}
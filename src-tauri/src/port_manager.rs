@@ -1,3 +1,5 @@
+use crate::paths::AppPaths;
+use crate::server_manager::ServerInfo;
 use anyhow::{Context, Result};
 use reqwest::{Client, Url};
 use serde::{Deserialize, Serialize};
@@ -13,7 +15,7 @@ pub struct PortMapping {
     pub enabled: bool,
 }
 
-// use std::collections::HashMap; // Unused
+use std::collections::HashMap;
 use std::fs;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,12 +26,207 @@ pub struct ManagedPort {
     pub name: String,
     #[serde(default = "default_active")]
     pub active: bool,
+    #[serde(default)]
+    pub family: AddressFamily,
+}
+
+/// Which IP versions a managed port is opened for. IPv4 uses a UPnP port
+/// mapping, IPv6 a firewall pinhole (there is no NAT to map through).
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum AddressFamily {
+    #[default]
+    Ipv4,
+    Ipv6,
+    Both,
+}
+
+impl AddressFamily {
+    pub fn ipv4(self) -> bool {
+        self != AddressFamily::Ipv6
+    }
+
+    pub fn ipv6(self) -> bool {
+        self != AddressFamily::Ipv4
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExternalAddresses {
+    pub ipv4: Option<String>,
+    pub ipv6: Option<String>, // Global address of this machine, None without IPv6
 }
 
 fn default_active() -> bool {
     true
 }
 
+/// A managed port together with what is actually happening on the host and router
+#[derive(Debug, Clone, Serialize)]
+pub struct ManagedPortStatus {
+    #[serde(flatten)]
+    pub port: ManagedPort,
+    pub bound_locally: bool, // Some process is listening on the port
+    pub owner_server_id: Option<String>, // Managed server configured for this port
+    pub owner_server_name: Option<String>,
+    pub upnp_mapped: Option<bool>, // None when the router could not be asked
+    pub verified_at: u64,          // Unix time of this check
+}
+
+/// Identity of the router from its UPnP device description
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct RouterInfo {
+    pub friendly_name: Option<String>,
+    pub manufacturer: Option<String>,
+    pub model_name: Option<String>,
+}
+
+/// Why a UPnP operation failed, so the UI can show matching guidance
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum UpnpFailure {
+    /// No answer to SSDP discovery (UPnP disabled or not supported)
+    RouterNotFound,
+    /// The router answered but offers no WANIPConnection/WANPPPConnection service
+    NoWanService,
+    /// Router refused the change (errors 606/401, UPnP in read-only mode)
+    NotAuthorized,
+    /// Port already mapped to another device (error 718)
+    ConflictingMapping,
+    /// Router only accepts permanent leases (error 725) or rejected the arguments
+    InvalidArguments,
+    /// Request timed out or the connection failed
+    Unreachable,
+    Other,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpnpFailureRecord {
+    pub failure: UpnpFailure,
+    pub detail: String,
+    pub at: u64, // Unix time
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpnpDiagnostics {
+    pub available: bool,
+    pub router: Option<RouterInfo>,
+    pub last_failure: Option<UpnpFailureRecord>,
+}
+
+/// Outcome of an inbound connection test from outside the network
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReachabilityVerdict {
+    Reachable,
+    /// Nothing is listening on the port on this machine
+    NotListening,
+    /// Router has no mapping for the port
+    NotMapped,
+    /// Mapped on the router, but the router's WAN address is not public (CGNAT)
+    IspBlocked,
+    /// Mapped with a public WAN address, still unreachable (firewall or ISP filter)
+    Blocked,
+    /// Could not be tested (UDP, or the check service failed)
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReachabilityReport {
+    pub port: u16,
+    pub protocol: String,
+    pub verdict: ReachabilityVerdict,
+    pub reachable: Option<bool>, // None when no inbound test was possible
+    pub bound_locally: bool,
+    pub upnp_mapped: Option<bool>,
+    pub public_ip: Option<String>,     // As seen by the internet
+    pub router_wan_ip: Option<String>, // As reported by the router over UPnP
+    pub cgnat: bool,
+    pub checked_by: String, // "portchecker.io", "loopback" or "none"
+}
+
+/// WAN connection service types we can drive, most preferred first
+const WAN_SERVICE_TYPES: [&str; 4] = [
+    "urn:schemas-upnp-org:service:WANIPConnection:2",
+    "urn:schemas-upnp-org:service:WANIPConnection:1",
+    "urn:schemas-upnp-org:service:WANPPPConnection:1",
+    "urn:schemas-upnp-org:service:WANPPPConnection:2",
+];
+
+const SSDP_SEARCH_TARGETS: [&str; 4] = [
+    "urn:schemas-upnp-org:device:InternetGatewayDevice:2",
+    "urn:schemas-upnp-org:device:InternetGatewayDevice:1",
+    "urn:schemas-upnp-org:service:WANIPConnection:1",
+    "urn:schemas-upnp-org:service:WANPPPConnection:1",
+];
+
+/// Longest lease IGDv2 allows (one week); mappings are re-applied daily and on resume
+const IGD2_LEASE_SECS: u32 = 604800;
+
+const PINHOLE_SERVICE_TYPE: &str = "urn:schemas-upnp-org:service:WANIPv6FirewallControl:1";
+/// Longest pinhole lease (one day); refreshed by `refresh_pinholes`
+const PINHOLE_LEASE_SECS: u32 = 86400;
+
+/// The router's WAN connection service found during discovery. Every SOAP call
+/// must use its exact service type, routers reject a mismatched namespace.
+#[derive(Debug, Clone)]
+struct WanService {
+    control_url: String,
+    service_type: String,
+}
+
+impl WanService {
+    /// Version suffix of the service type ("...:WANIPConnection:2" -> 2)
+    fn version(&self) -> u8 {
+        self.service_type
+            .rsplit(':')
+            .next()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(1)
+    }
+
+    fn soap_action(&self, action: &str) -> String {
+        format!("\"{}#{}\"", self.service_type, action)
+    }
+
+    fn envelope(&self, action: &str, args: &str) -> String {
+        format!(
+            r#"<?xml version="1.0"?>
+            <SOAP-ENV:Envelope xmlns:SOAP-ENV="http://schemas.xmlsoap.org/soap/envelope/" SOAP-ENV:encodingStyle="http://schemas.xmlsoap.org/soap/encoding/">
+            <SOAP-ENV:Body>
+                <m:{action} xmlns:m="{service_type}">
+                    {args}
+                </m:{action}>
+            </SOAP-ENV:Body>
+            </SOAP-ENV:Envelope>"#,
+            action = action,
+            service_type = self.service_type,
+            args = args
+        )
+    }
+}
+
+/// A mapping found on the router (GetGenericPortMappingEntry)
+#[derive(Debug, Clone, Serialize)]
+pub struct RouterMapping {
+    pub external_port: u16,
+    pub protocol: String,
+    pub internal_client: String,
+    pub internal_port: u16,
+    pub description: String,
+    pub enabled: bool,
+    pub lease_duration: u32, // Seconds left, 0 = permanent
+    pub prismarine: bool,    // Created by us (description starts with "Prismarine")
+    pub this_computer: bool, // Forwards to this computer's LAN address
+    pub stale: bool,         // Ours, but no managed port or running server needs it
+}
+
+/// Routers list at most this many entries; stops runaway enumeration on buggy firmware
+const MAX_ROUTER_MAPPINGS: u32 = 256;
+
+/// Slots of managed ports (1..=5), as shown in the ports view
+const MAX_MANAGED_PORTS: u8 = 5;
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 struct PortConfig {
     ports: Vec<ManagedPort>,
@@ -38,14 +235,17 @@ struct PortConfig {
 pub struct PortManager {
     http_client: Client,
     config_path: std::path::PathBuf,
+    router: std::sync::Mutex<Option<RouterInfo>>, // From the last successful discovery
+    last_failure: std::sync::Mutex<Option<UpnpFailureRecord>>,
+    /// UniqueID of each open IPv6 pinhole by (port, protocol)
+    pinholes: std::sync::Mutex<HashMap<(u16, String), String>>,
+    /// Description of each mapping opened for a running server by (port, protocol)
+    server_mappings: std::sync::Mutex<HashMap<(u16, String), String>>,
 }
 
 impl PortManager {
-    pub fn new() -> Self {
-        let config_path = dirs::config_dir()
-            .unwrap_or_else(|| std::path::PathBuf::from("."))
-            .join("MinecraftServerManager")
-            .join("managed_ports.json");
+    pub fn new(paths: &AppPaths) -> Self {
+        let config_path = paths.config_file("managed_ports.json");
 
         Self {
             http_client: Client::builder()
@@ -53,6 +253,32 @@ impl PortManager {
                 .build()
                 .unwrap_or_default(),
             config_path,
+            router: std::sync::Mutex::new(None),
+            last_failure: std::sync::Mutex::new(None),
+            pinholes: std::sync::Mutex::new(HashMap::new()),
+            server_mappings: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn record_failure(&self, failure: UpnpFailure, detail: &str) {
+        println!("[PortManager] UPnP failure {:?}: {}", failure, detail);
+        *self.last_failure.lock().unwrap() = Some(UpnpFailureRecord {
+            failure,
+            detail: detail.to_string(),
+            at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+        });
+    }
+
+    /// Discover the router again and report its identity and the last UPnP failure
+    pub async fn get_upnp_diagnostics(&self) -> UpnpDiagnostics {
+        let available = self.find_wan_service().await.is_ok();
+        UpnpDiagnostics {
+            available,
+            router: self.router.lock().unwrap().clone(),
+            last_failure: self.last_failure.lock().unwrap().clone(),
         }
     }
 
@@ -62,6 +288,60 @@ impl PortManager {
         self.load_config().unwrap_or_default().ports
     }
 
+    /// Managed ports with their live state. `servers` is used to find the owning server.
+    pub async fn get_managed_ports_status(&self, servers: &[ServerInfo]) -> Vec<ManagedPortStatus> {
+        let ports = self.get_managed_ports();
+        let service = if ports.is_empty() {
+            None
+        } else {
+            self.find_wan_service().await.ok()
+        };
+        let verified_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let mut statuses = Vec::new();
+        for port in ports {
+            let protocols: &[&str] = match port.protocol.as_str() {
+                "BOTH" => &["TCP", "UDP"],
+                "UDP" => &["UDP"],
+                _ => &["TCP"],
+            };
+
+            let bound_locally = protocols.iter().any(|p| is_port_bound(port.port, p));
+
+            let mut upnp_mapped = None;
+            if let Some(service) = &service {
+                let mut mapped = true;
+                for proto in protocols {
+                    match self.get_port_mapping_proto(service, port.port, proto).await {
+                        Ok(exists) => mapped &= exists,
+                        Err(e) => {
+                            println!(
+                                "[PortManager] Failed to query mapping for {}: {}",
+                                port.port, e
+                            );
+                            mapped = false;
+                        }
+                    }
+                }
+                upnp_mapped = Some(mapped);
+            }
+
+            let owner = servers.iter().find(|s| s.port == port.port);
+            statuses.push(ManagedPortStatus {
+                owner_server_id: owner.map(|s| s.id.clone()),
+                owner_server_name: owner.map(|s| s.name.clone()),
+                port,
+                bound_locally,
+                upnp_mapped,
+                verified_at,
+            });
+        }
+        statuses
+    }
+
     fn load_config(&self) -> Result<PortConfig> {
         if !self.config_path.exists() {
             return Ok(PortConfig::default());
@@ -86,6 +366,7 @@ impl PortManager {
         protocol: &str,
         name: &str,
         slot: u8,
+        family: AddressFamily,
     ) -> Result<String> {
         let mut config = self.load_config().unwrap_or_default();
 
@@ -104,16 +385,20 @@ impl PortManager {
         let description = format!("Prismarine Port {}", slot);
 
         // --- Actual UPnP / Firewall Call ---
-        let local_ip = get_local_ip()?;
-
         // Try to open ports (ignore errors if router not found, will rely on FW or error later)
-        if let Ok(control_url) = self.find_control_url().await {
-            for proto in &protocols_to_open {
-                let _ = self
-                    .add_port_mapping_proto(&control_url, port, &local_ip, &description, proto)
-                    .await;
+        if family.ipv4() {
+            let local_ip = get_local_ip()?;
+            if let Ok(service) = self.find_wan_service().await {
+                for proto in &protocols_to_open {
+                    let _ = self
+                        .add_port_mapping_proto(&service, port, &local_ip, &description, proto)
+                        .await;
+                }
             }
         }
+        if family.ipv6() {
+            self.open_pinholes(port, &protocols_to_open).await;
+        }
 
         #[cfg(target_os = "windows")]
         for proto in &protocols_to_open {
@@ -127,6 +412,7 @@ impl PortManager {
             protocol: protocol.to_string(),
             name: name.to_string(),
             active: true,
+            family,
         });
         self.save_config(&config)?;
 
@@ -148,6 +434,7 @@ impl PortManager {
         config.ports[port_idx].active = active;
         let port = config.ports[port_idx].port;
         let protocol = config.ports[port_idx].protocol.clone();
+        let family = config.ports[port_idx].family;
         let description = format!("Prismarine Port {}", slot);
 
         self.save_config(&config)?;
@@ -161,14 +448,19 @@ impl PortManager {
 
         if active {
             // OPEN
-            let local_ip = get_local_ip()?;
-            if let Ok(control_url) = self.find_control_url().await {
-                for proto in &protocols {
-                    let _ = self
-                        .add_port_mapping_proto(&control_url, port, &local_ip, &description, proto)
-                        .await;
+            if family.ipv4() {
+                let local_ip = get_local_ip()?;
+                if let Ok(service) = self.find_wan_service().await {
+                    for proto in &protocols {
+                        let _ = self
+                            .add_port_mapping_proto(&service, port, &local_ip, &description, proto)
+                            .await;
+                    }
                 }
             }
+            if family.ipv6() {
+                self.open_pinholes(port, &protocols).await;
+            }
             #[cfg(target_os = "windows")]
             for proto in &protocols {
                 let _ = add_windows_firewall_rule_proto(port, proto);
@@ -176,13 +468,16 @@ impl PortManager {
             Ok("ポートを再開しました".to_string())
         } else {
             // CLOSE
-            if let Ok(control_url) = self.find_control_url().await {
-                for proto in &protocols {
-                    let _ = self
-                        .delete_port_mapping_proto(&control_url, port, proto)
-                        .await;
+            if family.ipv4() {
+                if let Ok(service) = self.find_wan_service().await {
+                    for proto in &protocols {
+                        let _ = self.delete_port_mapping_proto(&service, port, proto).await;
+                    }
                 }
             }
+            if family.ipv6() {
+                self.close_pinholes(port, &protocols).await;
+            }
             #[cfg(target_os = "windows")]
             for proto in &protocols {
                 let _ = remove_windows_firewall_rule_proto(port, proto);
@@ -191,6 +486,101 @@ impl PortManager {
         }
     }
 
+    /// Re-add UPnP mappings for every active managed port and running server.
+    /// Routers often drop mappings while the machine sleeps or the lease runs out.
+    pub async fn reapply_active_ports(&self) -> Result<usize> {
+        let config = self.load_config().unwrap_or_default();
+        let local_ip = get_local_ip()?;
+        let service = self.find_wan_service().await?;
+
+        let mut applied = 0;
+        for managed_port in config.ports.iter().filter(|p| p.active && p.family.ipv4()) {
+            let description = format!("Prismarine Port {}", managed_port.slot);
+            let protocols = if managed_port.protocol == "BOTH" {
+                vec!["TCP", "UDP"]
+            } else {
+                vec![managed_port.protocol.as_str()]
+            };
+
+            for proto in &protocols {
+                if self
+                    .add_port_mapping_proto(
+                        &service,
+                        managed_port.port,
+                        &local_ip,
+                        &description,
+                        proto,
+                    )
+                    .await
+                    .is_ok()
+                {
+                    applied += 1;
+                }
+            }
+        }
+
+        let server_mappings: Vec<((u16, String), String)> = self
+            .server_mappings
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(key, description)| (key.clone(), description.clone()))
+            .collect();
+        for ((port, proto), description) in server_mappings {
+            if self
+                .add_port_mapping_proto(&service, port, &local_ip, &description, &proto)
+                .await
+                .is_ok()
+            {
+                applied += 1;
+            }
+        }
+
+        self.refresh_pinholes().await;
+        Ok(applied)
+    }
+
+    /// Renew the pinholes of active IPv6 managed ports before their lease runs out
+    pub async fn refresh_pinholes(&self) {
+        let ports: Vec<ManagedPort> = self
+            .get_managed_ports()
+            .into_iter()
+            .filter(|p| p.active && p.family.ipv6())
+            .collect();
+        for managed_port in ports {
+            let protocols = if managed_port.protocol == "BOTH" {
+                vec!["TCP", "UDP"]
+            } else {
+                vec![managed_port.protocol.as_str()]
+            };
+            self.open_pinholes(managed_port.port, &protocols).await;
+        }
+    }
+
+    /// Add a managed UDP port for `port` in the first free slot, unless a managed
+    /// port already covers it. Returns the slot used, None when nothing was added.
+    pub async fn ensure_managed_udp_port(
+        &self,
+        port: u16,
+        name: &str,
+        family: AddressFamily,
+    ) -> Result<Option<u8>> {
+        let config = self.load_config().unwrap_or_default();
+        if config
+            .ports
+            .iter()
+            .any(|p| p.port == port && p.protocol != "TCP")
+        {
+            return Ok(None);
+        }
+        let slot = (1..=MAX_MANAGED_PORTS)
+            .find(|slot| !config.ports.iter().any(|p| p.slot == *slot))
+            .context("All managed port slots are in use")?;
+        self.open_managed_port(port, "UDP", name, slot, family)
+            .await?;
+        Ok(Some(slot))
+    }
+
     pub async fn delete_managed_port(&self, slot: u8) -> Result<()> {
         self.close_managed_port(slot).await
     }
@@ -209,13 +599,19 @@ impl PortManager {
                 vec![managed_port.protocol.as_str()]
             };
 
-            if let Ok(control_url) = self.find_control_url().await {
-                for proto in &protocols_to_close {
-                    let _ = self
-                        .delete_port_mapping_proto(&control_url, managed_port.port, proto)
-                        .await;
+            if managed_port.family.ipv4() {
+                if let Ok(service) = self.find_wan_service().await {
+                    for proto in &protocols_to_close {
+                        let _ = self
+                            .delete_port_mapping_proto(&service, managed_port.port, proto)
+                            .await;
+                    }
                 }
             }
+            if managed_port.family.ipv6() {
+                self.close_pinholes(managed_port.port, &protocols_to_close)
+                    .await;
+            }
 
             #[cfg(target_os = "windows")]
             for proto in &protocols_to_close {
@@ -234,11 +630,14 @@ impl PortManager {
         println!("[PortManager] Local IP: {}", local_ip);
 
         // 1. Discover Router via SSDP
-        let control_url = self.find_control_url().await?;
-        println!("[PortManager] Control URL: {}", control_url);
+        let service = self.find_wan_service().await?;
+        println!(
+            "[PortManager] Control URL: {} ({})",
+            service.control_url, service.service_type
+        );
 
         // 2. Send AddPortMapping SOAP Request
-        self.add_port_mapping_proto(&control_url, port, &local_ip, description, "TCP")
+        self.add_port_mapping_proto(&service, port, &local_ip, description, "TCP")
             .await?;
         println!("[PortManager] Port mapping added via UPnP (TCP)");
 
@@ -262,10 +661,8 @@ impl PortManager {
     /// Legacy wrapper - defaults to TCP
     #[allow(dead_code)]
     pub async fn close_port(&self, port: u16) -> Result<()> {
-        if let Ok(control_url) = self.find_control_url().await {
-            let _ = self
-                .delete_port_mapping_proto(&control_url, port, "TCP")
-                .await;
+        if let Ok(service) = self.find_wan_service().await {
+            let _ = self.delete_port_mapping_proto(&service, port, "TCP").await;
         }
 
         #[cfg(target_os = "windows")]
@@ -278,56 +675,489 @@ impl PortManager {
 
     /// Get external IP address
     pub async fn get_external_ip(&self) -> Result<String> {
-        if let Ok(control_url) = self.find_control_url().await {
-            if let Ok(ip) = self.get_external_ip_upnp(&control_url).await {
+        if let Ok(service) = self.find_wan_service().await {
+            if let Ok(ip) = self.get_external_ip_upnp(&service).await {
                 return Ok(ip);
             }
         }
 
+        crate::offline::ensure_online("Looking up the external IP")?;
         let ip = reqwest::get("https://api.ipify.org").await?.text().await?;
         Ok(ip)
     }
 
+    /// External IPv4 address and the global IPv6 address of this machine
+    pub async fn get_external_addresses(&self) -> ExternalAddresses {
+        let ipv4 = self
+            .get_external_ip()
+            .await
+            .ok()
+            .map(|ip| ip.trim().to_string());
+
+        // IPv6 has no NAT, the local global address is the external one
+        let ipv6 = match get_global_ipv6() {
+            Some(ip) => Some(ip.to_string()),
+            None if crate::offline::is_offline() => None,
+            None => match self.http_client.get("https://api6.ipify.org").send().await {
+                Ok(response) => response.text().await.ok().map(|ip| ip.trim().to_string()),
+                Err(_) => None,
+            },
+        };
+
+        ExternalAddresses { ipv4, ipv6 }
+    }
+
+    /// Open (or renew) IPv6 pinholes for the port. Best effort like the IPv4
+    /// mapping: routers without WANIPv6FirewallControl are skipped.
+    async fn open_pinholes(&self, port: u16, protocols: &[&str]) {
+        let Some(local_ipv6) = get_global_ipv6() else {
+            println!("[PortManager] No global IPv6 address, skipping pinholes");
+            return;
+        };
+        let Some(service) = self.find_pinhole_service().await else {
+            println!("[PortManager] Router has no IPv6 firewall control");
+            return;
+        };
+
+        for proto in protocols {
+            let key = (port, proto.to_string());
+            let existing = self.pinholes.lock().unwrap().get(&key).cloned();
+            if let Some(id) = existing {
+                let args = format!(
+                    "<UniqueID>{}</UniqueID><NewLeaseTime>{}</NewLeaseTime>",
+                    id, PINHOLE_LEASE_SECS
+                );
+                if self
+                    .send_soap_request(&service, "UpdatePinhole", &args)
+                    .await
+                    .is_ok()
+                {
+                    continue;
+                }
+            }
+
+            let args = format!(
+                r#"<RemoteHost></RemoteHost>
+                    <RemotePort>0</RemotePort>
+                    <InternalClient>{}</InternalClient>
+                    <InternalPort>{}</InternalPort>
+                    <Protocol>{}</Protocol>
+                    <LeaseTime>{}</LeaseTime>"#,
+                local_ipv6,
+                port,
+                if proto.eq_ignore_ascii_case("UDP") {
+                    17
+                } else {
+                    6
+                },
+                PINHOLE_LEASE_SECS
+            );
+            match self.send_soap_request(&service, "AddPinhole", &args).await {
+                Ok(response) => {
+                    let id = roxmltree::Document::parse(&response).ok().and_then(|doc| {
+                        doc.descendants()
+                            .find(|n| n.tag_name().name() == "UniqueID")
+                            .and_then(|n| n.text())
+                            .map(|t| t.trim().to_string())
+                    });
+                    if let Some(id) = id {
+                        println!("[PortManager] Opened IPv6 pinhole {}/{}", port, proto);
+                        self.pinholes.lock().unwrap().insert(key, id);
+                    }
+                }
+                Err(e) => println!(
+                    "[PortManager] Failed to open IPv6 pinhole {}/{}: {}",
+                    port, proto, e
+                ),
+            }
+        }
+    }
+
+    async fn close_pinholes(&self, port: u16, protocols: &[&str]) {
+        let ids: Vec<String> = {
+            let mut pinholes = self.pinholes.lock().unwrap();
+            protocols
+                .iter()
+                .filter_map(|proto| pinholes.remove(&(port, proto.to_string())))
+                .collect()
+        };
+        if ids.is_empty() {
+            return;
+        }
+        if let Some(service) = self.find_pinhole_service().await {
+            for id in ids {
+                let args = format!("<UniqueID>{}</UniqueID>", id);
+                let _ = self
+                    .send_soap_request(&service, "DeletePinhole", &args)
+                    .await;
+            }
+        }
+    }
+
+    /// Check whether a TCP port answers on the external IP address.
+    /// Relies on the router supporting NAT loopback (hairpinning).
+    pub async fn check_external_reachability(&self, port: u16) -> Result<bool> {
+        let ip = self.get_external_ip().await?;
+        let addr = format!("{}:{}", ip.trim(), port);
+
+        let connect = tokio::net::TcpStream::connect(&addr);
+        Ok(matches!(
+            tokio::time::timeout(Duration::from_secs(5), connect).await,
+            Ok(Ok(_))
+        ))
+    }
+
+    /// Whether a TCP port answers from the internet, asked of the external port
+    /// checker. NAT loopback is the fallback but only counts when it connects:
+    /// many routers do not hairpin, so a failed attempt says nothing.
+    /// None when neither could tell.
+    pub async fn probe_external_tcp(&self, port: u16) -> Option<bool> {
+        if crate::offline::is_offline() {
+            return None;
+        }
+        if let Ok(response) = self.http_client.get("https://api.ipify.org").send().await {
+            if let Ok(ip) = response.text().await {
+                match check_with_portchecker(ip.trim(), port).await {
+                    Ok(open) => return Some(open),
+                    Err(e) => println!("[PortManager] External port check failed: {}", e),
+                }
+            }
+        }
+        self.check_external_reachability(port)
+            .await
+            .ok()
+            .filter(|open| *open)
+    }
+
+    /// Verify from outside that inbound connections reach this machine.
+    /// TCP is checked by an external port checker (NAT loopback as fallback);
+    /// UDP has no generic echo service, so only the mapping and CGNAT are reported.
+    pub async fn test_port_reachability(
+        &self,
+        port: u16,
+        protocol: &str,
+    ) -> Result<ReachabilityReport> {
+        let protocol = protocol.to_uppercase();
+        if protocol != "TCP" && protocol != "UDP" {
+            anyhow::bail!("Protocol must be TCP or UDP");
+        }
+
+        let bound_locally = is_port_bound(port, &protocol);
+        let service = self.find_wan_service().await.ok();
+        let mut upnp_mapped = None;
+        let mut router_wan_ip = None;
+        if let Some(service) = &service {
+            upnp_mapped = self
+                .get_port_mapping_proto(service, port, &protocol)
+                .await
+                .ok();
+            router_wan_ip = self.get_external_ip_upnp(service).await.ok();
+        }
+
+        let public_ip = match self.http_client.get("https://api.ipify.org").send().await {
+            Ok(response) => response.text().await.ok().map(|ip| ip.trim().to_string()),
+            Err(_) => None,
+        };
+
+        // A router WAN address that is private/shared, or differs from the address
+        // the internet sees, means another NAT sits in front of the router
+        let cgnat = match (&router_wan_ip, &public_ip) {
+            (Some(wan), Some(public)) => is_non_public_ipv4(wan) || wan != public,
+            (Some(wan), None) => is_non_public_ipv4(wan),
+            _ => false,
+        };
+
+        let (reachable, checked_by) = match (&protocol[..], &public_ip) {
+            ("TCP", Some(ip)) if bound_locally => match check_with_portchecker(ip, port).await {
+                Ok(open) => (Some(open), "portchecker.io"),
+                Err(e) => {
+                    println!("[PortManager] External port check failed: {}", e);
+                    // Without hairpin support a failed loopback proves nothing
+                    let open = self
+                        .check_external_reachability(port)
+                        .await
+                        .ok()
+                        .filter(|open| *open);
+                    (open, if open.is_some() { "loopback" } else { "none" })
+                }
+            },
+            _ => (None, "none"),
+        };
+
+        let verdict = match reachable {
+            Some(true) => ReachabilityVerdict::Reachable,
+            _ if !bound_locally => ReachabilityVerdict::NotListening,
+            _ if upnp_mapped == Some(false) => ReachabilityVerdict::NotMapped,
+            _ if cgnat => ReachabilityVerdict::IspBlocked,
+            Some(false) => ReachabilityVerdict::Blocked,
+            None => ReachabilityVerdict::Unknown,
+        };
+        println!(
+            "[PortManager] Reachability of {}/{}: {:?}",
+            port, protocol, verdict
+        );
+
+        Ok(ReachabilityReport {
+            port,
+            protocol,
+            verdict,
+            reachable,
+            bound_locally,
+            upnp_mapped,
+            public_ip,
+            router_wan_ip,
+            cgnat,
+            checked_by: checked_by.to_string(),
+        })
+    }
+
+    /// Add a UPnP mapping for a single port without touching the managed port list
+    pub async fn map_port(&self, port: u16, protocol: &str, description: &str) -> Result<()> {
+        let local_ip = get_local_ip()?;
+        let service = self.find_wan_service().await?;
+        self.add_port_mapping_proto(&service, port, &local_ip, description, protocol)
+            .await
+    }
+
+    /// Map a running server's ports on the router and firewall (auto_open_port)
+    pub async fn open_server_ports(&self, server_name: &str, ports: &[(u16, String)]) {
+        let description = format!("Prismarine {}", server_name);
+        for (port, protocol) in ports {
+            match self.map_port(*port, protocol, &description).await {
+                Ok(()) => {
+                    self.server_mappings
+                        .lock()
+                        .unwrap()
+                        .insert((*port, protocol.clone()), description.clone());
+                    println!(
+                        "[PortManager] Opened {}/{} for {}",
+                        port, protocol, server_name
+                    )
+                }
+                Err(e) => println!(
+                    "[PortManager] Failed to open {}/{} for {}: {}",
+                    port, protocol, server_name, e
+                ),
+            }
+            #[cfg(target_os = "windows")]
+            let _ = add_windows_firewall_rule_proto(*port, protocol);
+        }
+    }
+
+    /// Undo `open_server_ports`, keeping ports an active managed slot still needs
+    pub async fn close_server_ports(&self, ports: &[(u16, String)]) {
+        let managed = self.get_managed_ports();
+        let still_needed = |port: u16, protocol: &str| {
+            managed.iter().any(|m| {
+                m.active
+                    && m.port == port
+                    && m.family.ipv4()
+                    && (m.protocol == "BOTH" || m.protocol == protocol)
+            })
+        };
+
+        let service = self.find_wan_service().await.ok();
+        for (port, protocol) in ports {
+            self.server_mappings
+                .lock()
+                .unwrap()
+                .remove(&(*port, protocol.clone()));
+            if still_needed(*port, protocol) {
+                continue;
+            }
+            if let Some(service) = &service {
+                let _ = self
+                    .delete_port_mapping_proto(service, *port, protocol)
+                    .await;
+            }
+            #[cfg(target_os = "windows")]
+            let _ = remove_windows_firewall_rule_proto(*port, protocol);
+        }
+    }
+
+    /// Every port mapping on the router. `in_use` are the ports of running
+    /// servers with auto_open_port; other Prismarine mappings to this computer
+    /// that no active managed port covers are marked stale.
+    pub async fn list_router_mappings(
+        &self,
+        in_use: &[(u16, String)],
+    ) -> Result<Vec<RouterMapping>> {
+        let service = self.find_wan_service().await?;
+        let local_ip = get_local_ip().unwrap_or_default();
+        let managed = self.get_managed_ports();
+
+        let mut mappings = Vec::new();
+        for index in 0..MAX_ROUTER_MAPPINGS {
+            let Some(entry) = self.get_generic_mapping_entry(&service, index).await? else {
+                break;
+            };
+            let prismarine = entry.description.starts_with("Prismarine");
+            let this_computer = entry.internal_client == local_ip;
+            let needed = managed.iter().any(|m| {
+                m.active
+                    && m.port == entry.external_port
+                    && m.family.ipv4()
+                    && (m.protocol == "BOTH" || m.protocol == entry.protocol)
+            }) || in_use.iter().any(|(port, protocol)| {
+                *port == entry.external_port && *protocol == entry.protocol
+            });
+            mappings.push(RouterMapping {
+                prismarine,
+                this_computer,
+                stale: prismarine && this_computer && !needed,
+                ..entry
+            });
+        }
+        Ok(mappings)
+    }
+
+    /// Mappings that would collide with opening `port`: the same external port
+    /// already forwarded somewhere else (another computer or another internal port)
+    pub async fn find_mapping_conflicts(
+        &self,
+        port: u16,
+        protocol: &str,
+    ) -> Result<Vec<RouterMapping>> {
+        Ok(self
+            .list_router_mappings(&[])
+            .await?
+            .into_iter()
+            .filter(|m| {
+                m.external_port == port
+                    && (protocol == "BOTH" || m.protocol == protocol)
+                    && !(m.this_computer && m.internal_port == port)
+            })
+            .collect())
+    }
+
+    /// Delete the stale mappings (left behind when Prismarine crashed while a port was open)
+    pub async fn remove_stale_mappings(
+        &self,
+        in_use: &[(u16, String)],
+    ) -> Result<Vec<RouterMapping>> {
+        let stale = self
+            .list_router_mappings(in_use)
+            .await?
+            .into_iter()
+            .filter(|m| m.stale)
+            .collect();
+        self.delete_mappings(stale).await
+    }
+
+    /// Delete every Prismarine mapping to this computer, needed or not, and the
+    /// IPv6 pinholes opened in this session (uninstall cleanup)
+    pub async fn remove_all_mappings(&self) -> Result<Vec<RouterMapping>> {
+        let ours = self
+            .list_router_mappings(&[])
+            .await?
+            .into_iter()
+            .filter(|m| m.prismarine && m.this_computer)
+            .collect();
+        let removed = self.delete_mappings(ours).await?;
+
+        let pinholes: Vec<(u16, String)> = self.pinholes.lock().unwrap().keys().cloned().collect();
+        for (port, protocol) in pinholes {
+            self.close_pinholes(port, &[protocol.as_str()]).await;
+        }
+        Ok(removed)
+    }
+
+    async fn delete_mappings(&self, mappings: Vec<RouterMapping>) -> Result<Vec<RouterMapping>> {
+        let service = self.find_wan_service().await?;
+        let mut removed = Vec::new();
+        for mapping in mappings {
+            match self
+                .delete_port_mapping_proto(&service, mapping.external_port, &mapping.protocol)
+                .await
+            {
+                Ok(()) => {
+                    println!(
+                        "[PortManager] Removed mapping {}/{} ({})",
+                        mapping.external_port, mapping.protocol, mapping.description
+                    );
+                    removed.push(mapping);
+                }
+                Err(e) => println!(
+                    "[PortManager] Failed to remove mapping {}/{}: {}",
+                    mapping.external_port, mapping.protocol, e
+                ),
+            }
+        }
+        Ok(removed)
+    }
+
     /// Check if UPnP is available
     pub async fn is_upnp_available(&self) -> bool {
-        self.find_control_url().await.is_ok()
+        self.find_wan_service().await.is_ok()
     }
 
     // --- Private UPnP Methods ---
 
-    async fn find_control_url(&self) -> Result<String> {
+    /// SSDP discovery plus the router's description: every service it offers
+    async fn router_services(&self) -> Result<Vec<WanService>> {
+        let location = self.discover_ssdp().await?;
+        println!("[PortManager] SSDP Location: {}", location);
+        // Fetch Description XML
+        let xml = self.http_client.get(&location).send().await?.text().await?;
+        let doc =
+            roxmltree::Document::parse(&xml).context("Failed to parse router description XML")?;
+
+        // The root device comes first in the description
+        let text_of = |tag: &str| {
+            doc.descendants()
+                .find(|n| n.tag_name().name() == tag)
+                .and_then(|n| n.text())
+                .map(|t| t.trim().to_string())
+        };
+        *self.router.lock().unwrap() = Some(RouterInfo {
+            friendly_name: text_of("friendlyName"),
+            manufacturer: text_of("manufacturer"),
+            model_name: text_of("modelName"),
+        });
+
+        let base_url = Url::parse(&location)?;
+        Ok(doc
+            .descendants()
+            .filter(|n| n.tag_name().name() == "service")
+            .filter_map(|n| {
+                let child = |tag: &str| {
+                    n.children()
+                        .find(|c| c.tag_name().name() == tag)
+                        .and_then(|c| c.text())
+                        .map(|t| t.trim().to_string())
+                };
+                let control_url = base_url.join(&child("controlURL")?).ok()?;
+                Some(WanService {
+                    control_url: control_url.to_string(),
+                    service_type: child("serviceType")?,
+                })
+            })
+            .collect())
+    }
+
+    /// Find the router's WAN connection service. IGDv2 WANIPConnection is preferred,
+    /// then WANIPConnection:1, then WANPPPConnection:1.
+    async fn find_wan_service(&self) -> Result<WanService> {
         // 1. Try SSDP Discovery
-        match self.discover_ssdp().await {
-            Ok(location) => {
-                println!("[PortManager] SSDP Location: {}", location);
-                // Fetch Description XML
-                let xml = self.http_client.get(&location).send().await?.text().await?;
-                let doc = roxmltree::Document::parse(&xml)
-                    .context("Failed to parse router description XML")?;
-
-                let control_path = doc
-                    .descendants()
-                    .find(|n| {
-                        let tag = n.tag_name().name();
-                        if tag == "serviceType" {
-                            if let Some(text) = n.text() {
-                                return text.contains("WANPPPConnection")
-                                    || text.contains("WANIPConnection");
-                            }
-                        }
-                        false
+        match self.router_services().await {
+            Ok(services) => {
+                let service = services
+                    .into_iter()
+                    .filter_map(|s| {
+                        let rank = WAN_SERVICE_TYPES
+                            .iter()
+                            .position(|t| *t == s.service_type)?;
+                        Some((rank, s))
                     })
-                    .and_then(|n| {
-                        n.parent()?
-                            .children()
-                            .find(|c| c.tag_name().name() == "controlURL")
-                    })
-                    .and_then(|n| n.text())
-                    .context("Could not find WANPPPConnection/WANIPConnection controlURL")?;
-
-                let base_url = Url::parse(&location)?;
-                let control_url = base_url.join(control_path)?;
-                Ok(control_url.to_string())
+                    .min_by_key(|(rank, _)| *rank);
+                let Some((_, service)) = service else {
+                    self.record_failure(
+                        UpnpFailure::NoWanService,
+                        "Router has no WANPPPConnection/WANIPConnection service",
+                    );
+                    anyhow::bail!("Could not find WANPPPConnection/WANIPConnection controlURL");
+                };
+                Ok(service)
             }
             Err(e) => {
                 println!("[PortManager] SSDP failed: {}. Trying fallback...", e);
@@ -335,14 +1165,27 @@ impl PortManager {
                 let fallback_url = "http://192.168.0.1:2869/upnp/control/WANPPPConn1";
                 if self.http_client.get(fallback_url).send().await.is_ok() {
                     println!("[PortManager] Using NEC Fallback URL");
-                    Ok(fallback_url.to_string())
+                    Ok(WanService {
+                        control_url: fallback_url.to_string(),
+                        service_type: "urn:schemas-upnp-org:service:WANPPPConnection:1".to_string(),
+                    })
                 } else {
+                    self.record_failure(UpnpFailure::RouterNotFound, &e.to_string());
                     Err(anyhow::anyhow!("Router not found via SSDP or Fallback"))
                 }
             }
         }
     }
 
+    /// IGDv2 WANIPv6FirewallControl, if the router offers it
+    async fn find_pinhole_service(&self) -> Option<WanService> {
+        self.router_services()
+            .await
+            .ok()?
+            .into_iter()
+            .find(|s| s.service_type == PINHOLE_SERVICE_TYPE)
+    }
+
     async fn discover_ssdp(&self) -> Result<String> {
         // Bind to the specific local IP to ensure we use the correct interface
         let local_ip = get_local_ip()?;
@@ -351,15 +1194,20 @@ impl PortManager {
 
         socket.set_read_timeout(Some(Duration::from_secs(4)))?;
 
-        let msg = "M-SEARCH * HTTP/1.1\r\n\
-                   HOST: 239.255.255.250:1900\r\n\
-                   MAN: \"ssdp:discover\"\r\n\
-                   MX: 3\r\n\
-                   ST: urn:schemas-upnp-org:service:WANPPPConnection:1\r\n\
-                   \r\n";
-
+        // Routers only answer searches for what they offer, so ask for every variant
         println!("[PortManager] Sending SSDP M-SEARCH from {}...", local_ip);
-        socket.send_to(msg.as_bytes(), "239.255.255.250:1900")?;
+        for target in SSDP_SEARCH_TARGETS {
+            let msg = format!(
+                "M-SEARCH * HTTP/1.1\r\n\
+                 HOST: 239.255.255.250:1900\r\n\
+                 MAN: \"ssdp:discover\"\r\n\
+                 MX: 3\r\n\
+                 ST: {}\r\n\
+                 \r\n",
+                target
+            );
+            socket.send_to(msg.as_bytes(), "239.255.255.250:1900")?;
+        }
 
         let mut buf = [0u8; 2048];
         let end_time = std::time::Instant::now() + Duration::from_secs(4);
@@ -380,80 +1228,174 @@ impl PortManager {
         Err(anyhow::anyhow!("Timed out"))
     }
 
+    /// IGDv2 routers get AddAnyPortMapping (with a lease, as v2 forbids infinite
+    /// ones); the mapping is rejected if the router picks a different port.
     async fn add_port_mapping_proto(
         &self,
-        control_url: &str,
+        service: &WanService,
         port: u16,
         local_ip: &str,
         description: &str,
         protocol: &str,
     ) -> Result<()> {
-        let soap_action = "\"urn:schemas-upnp-org:service:WANPPPConnection:1#AddPortMapping\"";
-        let body = format!(
-            r#"<?xml version="1.0"?>
-            <SOAP-ENV:Envelope xmlns:SOAP-ENV="http://schemas.xmlsoap.org/soap/envelope/" SOAP-ENV:encodingStyle="http://schemas.xmlsoap.org/soap/encoding/">
-            <SOAP-ENV:Body>
-                <m:AddPortMapping xmlns:m="urn:schemas-upnp-org:service:WANPPPConnection:1">
-                    <NewRemoteHost></NewRemoteHost>
+        let (action, lease) = if service.version() >= 2 {
+            ("AddAnyPortMapping", IGD2_LEASE_SECS)
+        } else {
+            ("AddPortMapping", 0)
+        };
+        let args = format!(
+            r#"<NewRemoteHost></NewRemoteHost>
                     <NewExternalPort>{}</NewExternalPort>
                     <NewProtocol>{}</NewProtocol>
                     <NewInternalPort>{}</NewInternalPort>
                     <NewInternalClient>{}</NewInternalClient>
                     <NewEnabled>1</NewEnabled>
                     <NewPortMappingDescription>{}</NewPortMappingDescription>
-                    <NewLeaseDuration>0</NewLeaseDuration>
-                </m:AddPortMapping>
-            </SOAP-ENV:Body>
-            </SOAP-ENV:Envelope>"#,
-            port, protocol, port, local_ip, description
+                    <NewLeaseDuration>{}</NewLeaseDuration>"#,
+            port,
+            protocol,
+            port,
+            local_ip,
+            xml_escape(description), // Server names are user input
+            lease
         );
 
-        self.send_soap_request(control_url, soap_action, &body)
-            .await
+        let response = self.send_soap_request(service, action, &args).await?;
+        if action == "AddAnyPortMapping" {
+            let reserved = roxmltree::Document::parse(&response).ok().and_then(|doc| {
+                doc.descendants()
+                    .find(|n| n.tag_name().name() == "NewReservedPort")
+                    .and_then(|n| n.text())
+                    .and_then(|t| t.trim().parse::<u16>().ok())
+            });
+            if let Some(reserved) = reserved.filter(|r| *r != port) {
+                let _ = self
+                    .delete_port_mapping_proto(service, reserved, protocol)
+                    .await;
+                let detail = format!(
+                    "Router reserved port {} instead of {} (already mapped elsewhere)",
+                    reserved, port
+                );
+                self.record_failure(UpnpFailure::ConflictingMapping, &detail);
+                anyhow::bail!(detail);
+            }
+        }
+        Ok(())
     }
 
     async fn delete_port_mapping_proto(
         &self,
-        control_url: &str,
+        service: &WanService,
         port: u16,
         protocol: &str,
     ) -> Result<()> {
-        let soap_action = "\"urn:schemas-upnp-org:service:WANPPPConnection:1#DeletePortMapping\"";
-        let body = format!(
-            r#"<?xml version="1.0"?>
-            <SOAP-ENV:Envelope xmlns:SOAP-ENV="http://schemas.xmlsoap.org/soap/envelope/" SOAP-ENV:encodingStyle="http://schemas.xmlsoap.org/soap/encoding/">
-            <SOAP-ENV:Body>
-                <m:DeletePortMapping xmlns:m="urn:schemas-upnp-org:service:WANPPPConnection:1">
-                    <NewRemoteHost></NewRemoteHost>
+        let args = format!(
+            r#"<NewRemoteHost></NewRemoteHost>
                     <NewExternalPort>{}</NewExternalPort>
-                    <NewProtocol>{}</NewProtocol>
-                </m:DeletePortMapping>
-            </SOAP-ENV:Body>
-            </SOAP-ENV:Envelope>"#,
+                    <NewProtocol>{}</NewProtocol>"#,
             port, protocol
         );
+        self.send_soap_request(service, "DeletePortMapping", &args)
+            .await?;
+        Ok(())
+    }
 
-        self.send_soap_request(control_url, soap_action, &body)
-            .await
+    /// Whether the router currently has a mapping for the port (GetSpecificPortMappingEntry)
+    async fn get_port_mapping_proto(
+        &self,
+        service: &WanService,
+        port: u16,
+        protocol: &str,
+    ) -> Result<bool> {
+        let args = format!(
+            r#"<NewRemoteHost></NewRemoteHost>
+                    <NewExternalPort>{}</NewExternalPort>
+                    <NewProtocol>{}</NewProtocol>"#,
+            port, protocol
+        );
+
+        let response = self
+            .http_client
+            .post(&service.control_url)
+            .header(
+                "SOAPAction",
+                service.soap_action("GetSpecificPortMappingEntry"),
+            )
+            .header("Content-Type", "text/xml; charset=\"utf-8\"")
+            .body(service.envelope("GetSpecificPortMappingEntry", &args))
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            return Ok(true);
+        }
+        let error_text = response.text().await?;
+        // 714 NoSuchEntryInArray: the mapping does not exist
+        if error_text.contains("714") || error_text.contains("NoSuchEntryInArray") {
+            return Ok(false);
+        }
+        Err(anyhow::anyhow!("SOAP Error: {}", error_text))
     }
 
-    async fn get_external_ip_upnp(&self, control_url: &str) -> Result<String> {
-        let soap_action =
-            "\"urn:schemas-upnp-org:service:WANPPPConnection:1#GetExternalIPAddress\"";
-        let body = r#"<?xml version="1.0"?>
-            <SOAP-ENV:Envelope xmlns:SOAP-ENV="http://schemas.xmlsoap.org/soap/envelope/" SOAP-ENV:encodingStyle="http://schemas.xmlsoap.org/soap/encoding/">
-            <SOAP-ENV:Body>
-                <m:GetExternalIPAddress xmlns:m="urn:schemas-upnp-org:service:WANPPPConnection:1">
-                </m:GetExternalIPAddress>
-            </SOAP-ENV:Body>
-            </SOAP-ENV:Envelope>"#;
+    /// Mapping number `index` on the router, None past the last one (713 SpecifiedArrayIndexInvalid)
+    async fn get_generic_mapping_entry(
+        &self,
+        service: &WanService,
+        index: u32,
+    ) -> Result<Option<RouterMapping>> {
+        let args = format!("<NewPortMappingIndex>{}</NewPortMappingIndex>", index);
 
         let response = self
             .http_client
-            .post(control_url)
-            .header("SOAPAction", soap_action)
+            .post(&service.control_url)
+            .header(
+                "SOAPAction",
+                service.soap_action("GetGenericPortMappingEntry"),
+            )
             .header("Content-Type", "text/xml; charset=\"utf-8\"")
-            .body(body)
+            .body(service.envelope("GetGenericPortMappingEntry", &args))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            if error_text.contains("713") || error_text.contains("SpecifiedArrayIndexInvalid") {
+                return Ok(None);
+            }
+            return Err(anyhow::anyhow!("SOAP Error: {}", error_text));
+        }
+
+        let body = response.text().await?;
+        let doc = roxmltree::Document::parse(&body)?;
+        let field = |name: &str| {
+            doc.descendants()
+                .find(|n| n.tag_name().name() == name)
+                .and_then(|n| n.text())
+                .map(|t| t.trim().to_string())
+                .unwrap_or_default()
+        };
+
+        Ok(Some(RouterMapping {
+            external_port: field("NewExternalPort").parse().unwrap_or(0),
+            protocol: field("NewProtocol").to_uppercase(),
+            internal_client: field("NewInternalClient"),
+            internal_port: field("NewInternalPort").parse().unwrap_or(0),
+            description: field("NewPortMappingDescription"),
+            enabled: field("NewEnabled") != "0",
+            lease_duration: field("NewLeaseDuration").parse().unwrap_or(0),
+            prismarine: false,
+            this_computer: false,
+            stale: false,
+        }))
+    }
+
+    async fn get_external_ip_upnp(&self, service: &WanService) -> Result<String> {
+        let response = self
+            .http_client
+            .post(&service.control_url)
+            .header("SOAPAction", service.soap_action("GetExternalIPAddress"))
+            .header("Content-Type", "text/xml; charset=\"utf-8\"")
+            .body(service.envelope("GetExternalIPAddress", ""))
             .send()
             .await?
             .text()
@@ -469,78 +1411,263 @@ impl PortManager {
         Ok(ip.to_string())
     }
 
-    async fn send_soap_request(&self, url: &str, action: &str, body: &str) -> Result<()> {
-        let response = self
+    /// Invoke `action` on the WAN service, returning the response body
+    async fn send_soap_request(
+        &self,
+        service: &WanService,
+        action: &str,
+        args: &str,
+    ) -> Result<String> {
+        let response = match self
             .http_client
-            .post(url)
-            .header("SOAPAction", action)
+            .post(&service.control_url)
+            .header("SOAPAction", service.soap_action(action))
             .header("Content-Type", "text/xml; charset=\"utf-8\"")
-            .body(body.to_string())
+            .body(service.envelope(action, args))
             .send()
-            .await?;
+            .await
+        {
+            Ok(response) => response,
+            Err(e) => {
+                self.record_failure(UpnpFailure::Unreachable, &e.to_string());
+                return Err(e.into());
+            }
+        };
 
         if !response.status().is_success() {
             let error_text = response.text().await?;
+            let failure = classify_soap_error(&error_text);
+            self.record_failure(failure, &error_text);
             return Err(anyhow::anyhow!("SOAP Error: {}", error_text));
         }
 
-        Ok(())
+        Ok(response.text().await.unwrap_or_default())
+    }
+}
+
+/// Map the UPnP errorCode of a SOAP fault to a failure category
+/// Escape text for an XML element
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn classify_soap_error(body: &str) -> UpnpFailure {
+    let code = roxmltree::Document::parse(body).ok().and_then(|doc| {
+        doc.descendants()
+            .find(|n| n.tag_name().name() == "errorCode")
+            .and_then(|n| n.text())
+            .and_then(|t| t.trim().parse::<u32>().ok())
+    });
+
+    match code {
+        Some(401) | Some(606) => UpnpFailure::NotAuthorized,
+        Some(718) => UpnpFailure::ConflictingMapping,
+        Some(402) | Some(716) | Some(724) | Some(725) | Some(726) | Some(727) => {
+            UpnpFailure::InvalidArguments
+        }
+        _ => UpnpFailure::Other,
+    }
+}
+
+/// Ask portchecker.io to open a TCP connection to ip:port from the internet
+async fn check_with_portchecker(ip: &str, port: u16) -> Result<bool> {
+    crate::offline::ensure_online("Checking the port from outside")?;
+    #[derive(Deserialize)]
+    struct Check {
+        port: u16,
+        status: bool,
+    }
+    #[derive(Deserialize)]
+    struct Response {
+        #[serde(default)]
+        error: bool,
+        #[serde(default)]
+        check: Vec<Check>,
+    }
+
+    let response: Response = Client::builder()
+        .timeout(Duration::from_secs(15))
+        .build()?
+        .post("https://portchecker.io/api/v1/query")
+        .json(&serde_json::json!({ "host": ip, "ports": [port] }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    if response.error {
+        anyhow::bail!("Port checker rejected the request");
+    }
+    response
+        .check
+        .iter()
+        .find(|c| c.port == port)
+        .map(|c| c.status)
+        .context("Port checker returned no result")
+}
+
+/// Private (RFC 1918), shared CGNAT (100.64.0.0/10) or otherwise non-routable
+fn is_non_public_ipv4(ip: &str) -> bool {
+    match ip.trim().parse::<std::net::Ipv4Addr>() {
+        Ok(ip) => {
+            let [a, b, ..] = ip.octets();
+            ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || (a == 100 && (64..128).contains(&b))
+        }
+        Err(_) => false,
+    }
+}
+
+/// Whether another process is already using the port (binding it fails)
+pub fn is_port_bound(port: u16, protocol: &str) -> bool {
+    if protocol.eq_ignore_ascii_case("UDP") {
+        UdpSocket::bind(("0.0.0.0", port)).is_err()
+    } else {
+        std::net::TcpListener::bind(("0.0.0.0", port)).is_err()
+    }
+}
+
+/// Global unicast IPv6 address used for outgoing connections (None without IPv6)
+pub fn get_global_ipv6() -> Option<std::net::Ipv6Addr> {
+    let socket = UdpSocket::bind("[::]:0").ok()?;
+    socket.connect("[2001:4860:4860::8888]:80").ok()?;
+    match socket.local_addr().ok()?.ip() {
+        std::net::IpAddr::V6(ip) => {
+            let first = ip.segments()[0];
+            let link_local = first & 0xffc0 == 0xfe80;
+            let unique_local = first & 0xfe00 == 0xfc00;
+            (!ip.is_loopback() && !ip.is_unspecified() && !link_local && !unique_local)
+                .then_some(ip)
+        }
+        _ => None,
     }
 }
 
 /// Get local IP address
-fn get_local_ip() -> Result<String> {
+pub fn get_local_ip() -> Result<String> {
     let socket = UdpSocket::bind("0.0.0.0:0")?;
     socket.connect("8.8.8.8:80")?;
     let local_addr = socket.local_addr()?;
     Ok(local_addr.ip().to_string())
 }
 
+/// Every firewall rule Prismarine adds is named "<prefix> <port> (<protocol>)"
+#[cfg(target_os = "windows")]
+const FIREWALL_RULE_PREFIX: &str = "Minecraft Server Port";
+/// Firewall group of every rule Prismarine adds, so they can be told apart
+/// from the user's own rules
+#[cfg(target_os = "windows")]
+const FIREWALL_GROUP: &str = "Prismarine";
+
 #[cfg(target_os = "windows")]
 fn add_windows_firewall_rule_proto(port: u16, protocol: &str) -> Result<()> {
     // Protocol must be TCP or UDP
-    let cmd = format!(
-        "advfirewall firewall add rule name=\"Minecraft Server Port {} ({})\" dir=in action=allow protocol={} localport={}",
-        port, protocol, protocol, port
-    );
-
-    std::process::Command::new("powershell")
-        .args([
-            "-Command",
-            "Start-Process",
-            "netsh",
-            "-ArgumentList",
-            &format!("'{}'", cmd),
-            "-Verb",
-            "RunAs",
-            "-WindowStyle",
-            "Hidden",
-        ])
-        .output()?;
+    elevated_powershell(&format!(
+        "New-NetFirewallRule -DisplayName '{} {} ({})' -Group '{}' -Direction Inbound -Action Allow -Protocol {} -LocalPort {}",
+        FIREWALL_RULE_PREFIX, port, protocol, FIREWALL_GROUP, protocol, port
+    ))
+    .output()?;
 
     Ok(())
 }
 
 #[cfg(target_os = "windows")]
 fn remove_windows_firewall_rule_proto(port: u16, protocol: &str) -> Result<()> {
-    let cmd = format!(
-        "advfirewall firewall delete rule name=\"Minecraft Server Port {} ({})\" protocol={} localport={}",
-        port, protocol, protocol, port
-    );
-
-    std::process::Command::new("powershell")
-        .args([
-            "-Command",
-            "Start-Process",
-            "netsh",
-            "-ArgumentList",
-            &format!("'{}'", cmd),
-            "-Verb",
-            "RunAs",
-            "-WindowStyle",
-            "Hidden",
-        ])
-        .output()?;
+    elevated_powershell(&remove_rule_script(&format!(
+        "{} {} ({})",
+        FIREWALL_RULE_PREFIX, port, protocol
+    )))
+    .output()?;
+
+    Ok(())
+}
+
+/// Block connections from other machines to a TCP port that should only be
+/// used locally. Windows Firewall does not filter loopback traffic, so local
+/// clients still connect.
+pub fn block_remote_tcp(port: u16) -> Result<()> {
+    #[cfg(target_os = "windows")]
+    {
+        run_elevated(&format!(
+            "New-NetFirewallRule -DisplayName '{} {} (local only)' -Group '{}' -Direction Inbound -Action Block -Protocol TCP -LocalPort {}",
+            FIREWALL_RULE_PREFIX, port, FIREWALL_GROUP, port
+        ))
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        let _ = port;
+        anyhow::bail!("Blocking remote access to a port is only supported on Windows")
+    }
+}
+
+/// Undo `block_remote_tcp`
+pub fn unblock_remote_tcp(port: u16) -> Result<()> {
+    #[cfg(target_os = "windows")]
+    {
+        run_elevated(&remove_rule_script(&format!(
+            "{} {} (local only)",
+            FIREWALL_RULE_PREFIX, port
+        )))
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        let _ = port;
+        Ok(())
+    }
+}
+
+/// Remove our rule with this name, leaving same-named rules of other groups alone
+#[cfg(target_os = "windows")]
+fn remove_rule_script(display_name: &str) -> String {
+    format!(
+        "Get-NetFirewallRule -Group '{}' | Where-Object DisplayName -eq '{}' | Remove-NetFirewallRule",
+        FIREWALL_GROUP, display_name
+    )
+}
+
+/// A PowerShell that runs `script` elevated, behind a UAC prompt
+#[cfg(target_os = "windows")]
+fn elevated_powershell(script: &str) -> std::process::Command {
+    // Quotes are doubled inside the single-quoted -ArgumentList
+    let mut command = std::process::Command::new("powershell");
+    command.args([
+        "-Command",
+        "Start-Process",
+        "powershell",
+        "-ArgumentList",
+        &format!("'-Command {}'", script.replace('\'', "''")),
+        "-Verb",
+        "RunAs",
+        "-WindowStyle",
+        "Hidden",
+    ]);
+    command
+}
+
+/// Run `script` elevated and wait for it, failing when the UAC prompt is declined
+#[cfg(target_os = "windows")]
+fn run_elevated(script: &str) -> Result<()> {
+    let status = elevated_powershell(script).arg("-Wait").status()?;
+    if !status.success() {
+        anyhow::bail!("Changing the firewall rules was cancelled or failed");
+    }
+    Ok(())
+}
 
+/// Remove every firewall rule Prismarine added, found by their group.
+/// Rules are only added on Windows.
+pub fn remove_all_firewall_rules() -> Result<()> {
+    #[cfg(target_os = "windows")]
+    run_elevated(&format!(
+        "Remove-NetFirewallRule -Group '{}' -ErrorAction SilentlyContinue",
+        FIREWALL_GROUP
+    ))?;
     Ok(())
 }
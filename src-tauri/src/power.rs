@@ -0,0 +1,308 @@
+// Power management hooks
+// Lets the app react before the OS shuts down or suspends, so running
+// Minecraft servers get a chance to save their worlds and stop cleanly,
+// and recover processes, port mappings and tunnels after a resume.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PowerEvent {
+    /// OS shutdown, logoff or termination signal
+    Shutdown,
+    /// Machine is about to sleep/hibernate (only reported on Windows)
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    Suspend,
+    /// Machine woke up from sleep/hibernate
+    Resume,
+}
+
+/// Polling interval for the wall-clock resume detector
+const RESUME_POLL_SECS: u64 = 10;
+/// Clock jump beyond the poll interval that is treated as a sleep
+const RESUME_GAP_SECS: u64 = 30;
+
+type PowerHandler =
+    Arc<dyn Fn(PowerEvent) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
+
+/// Start listening for OS power events. Must be called from within a tokio runtime.
+pub fn listen<F, Fut>(on_event: F)
+where
+    F: Fn(PowerEvent) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let handler: PowerHandler = Arc::new(move |event| Box::pin(on_event(event)));
+
+    spawn_signal_listener(Arc::clone(&handler));
+
+    #[cfg(target_os = "windows")]
+    windows_session::register(Arc::clone(&handler));
+
+    #[cfg(target_os = "windows")]
+    windows_suspend::register(handler);
+
+    #[cfg(not(target_os = "windows"))]
+    spawn_resume_detector(handler);
+}
+
+/// Detect resume by comparing wall-clock time with the monotonic clock,
+/// which does not advance while the machine is asleep.
+#[cfg(not(target_os = "windows"))]
+fn spawn_resume_detector(handler: PowerHandler) {
+    tokio::spawn(async move {
+        loop {
+            let wall_before = std::time::SystemTime::now();
+            let mono_before = std::time::Instant::now();
+
+            tokio::time::sleep(std::time::Duration::from_secs(RESUME_POLL_SECS)).await;
+
+            let wall_elapsed = wall_before.elapsed().unwrap_or_default();
+            let mono_elapsed = mono_before.elapsed();
+
+            if wall_elapsed.saturating_sub(mono_elapsed).as_secs() > RESUME_GAP_SECS {
+                println!(
+                    "[Power] Resume detected (clock jumped {:?})",
+                    wall_elapsed - mono_elapsed
+                );
+                handler(PowerEvent::Resume).await;
+            }
+        }
+    });
+}
+
+#[cfg(unix)]
+fn spawn_signal_listener(handler: PowerHandler) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    tokio::spawn(async move {
+        let (mut term, mut hup, mut int) = match (
+            signal(SignalKind::terminate()),
+            signal(SignalKind::hangup()),
+            signal(SignalKind::interrupt()),
+        ) {
+            (Ok(t), Ok(h), Ok(i)) => (t, h, i),
+            _ => {
+                println!("[Power] Failed to register signal handlers");
+                return;
+            }
+        };
+
+        tokio::select! {
+            _ = term.recv() => println!("[Power] SIGTERM received"),
+            _ = hup.recv() => println!("[Power] SIGHUP received"),
+            _ = int.recv() => println!("[Power] SIGINT received"),
+        }
+
+        handler(PowerEvent::Shutdown).await;
+    });
+}
+
+/// Console control events, only delivered with a console attached (headless or
+/// debug builds). The GUI learns of a shutdown through `windows_session`.
+#[cfg(target_os = "windows")]
+fn spawn_signal_listener(handler: PowerHandler) {
+    use tokio::signal::windows::{ctrl_close, ctrl_logoff, ctrl_shutdown};
+
+    tokio::spawn(async move {
+        let (mut shutdown, mut logoff, mut close) =
+            match (ctrl_shutdown(), ctrl_logoff(), ctrl_close()) {
+                (Ok(s), Ok(l), Ok(c)) => (s, l, c),
+                _ => {
+                    println!("[Power] Failed to register console control handlers");
+                    return;
+                }
+            };
+
+        tokio::select! {
+            _ = shutdown.recv() => println!("[Power] Shutdown event received"),
+            _ = logoff.recv() => println!("[Power] Logoff event received"),
+            _ = close.recv() => println!("[Power] Close event received"),
+        }
+
+        handler(PowerEvent::Shutdown).await;
+    });
+}
+
+/// Shutdown and logoff for processes without a console: Windows sends
+/// WM_QUERYENDSESSION/WM_ENDSESSION to every top-level window, so a hidden one
+/// is created on its own thread to receive them.
+#[cfg(target_os = "windows")]
+mod windows_session {
+    use super::{PowerEvent, PowerHandler};
+    use std::sync::OnceLock;
+    use windows::core::w;
+    use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
+    use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+    use windows::Win32::System::Shutdown::{ShutdownBlockReasonCreate, ShutdownBlockReasonDestroy};
+    use windows::Win32::UI::WindowsAndMessaging::{
+        CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, RegisterClassW,
+        TranslateMessage, MSG, WINDOW_EX_STYLE, WINDOW_STYLE, WM_ENDSESSION, WM_QUERYENDSESSION,
+        WNDCLASSW,
+    };
+
+    /// Windows shows the block reason and waits while servers stop
+    const SHUTDOWN_WAIT_SECS: u64 = 60;
+
+    static STATE: OnceLock<(PowerHandler, tokio::runtime::Handle)> = OnceLock::new();
+
+    pub fn register(handler: PowerHandler) {
+        if STATE
+            .set((handler, tokio::runtime::Handle::current()))
+            .is_err()
+        {
+            return;
+        }
+
+        std::thread::spawn(|| unsafe {
+            let class_name = w!("PrismarineSessionWindow");
+            let instance = GetModuleHandleW(None).unwrap_or_default();
+            let class = WNDCLASSW {
+                lpfnWndProc: Some(window_proc),
+                hInstance: instance.into(),
+                lpszClassName: class_name,
+                ..Default::default()
+            };
+            if RegisterClassW(&class) == 0 {
+                println!("[Power] Failed to register the session window class");
+                return;
+            }
+            // Top-level but never shown: message-only windows miss the broadcast
+            let hwnd = CreateWindowExW(
+                WINDOW_EX_STYLE::default(),
+                class_name,
+                class_name,
+                WINDOW_STYLE::default(),
+                0,
+                0,
+                0,
+                0,
+                None,
+                None,
+                Some(instance.into()),
+                None,
+            );
+            if hwnd.is_err() {
+                println!("[Power] Failed to create the session window");
+                return;
+            }
+
+            let mut msg = MSG::default();
+            while GetMessageW(&mut msg, None, 0, 0).0 > 0 {
+                let _ = TranslateMessage(&msg);
+                DispatchMessageW(&msg);
+            }
+        });
+    }
+
+    unsafe extern "system" fn window_proc(
+        hwnd: HWND,
+        msg: u32,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> LRESULT {
+        match msg {
+            WM_QUERYENDSESSION => {
+                let _ = ShutdownBlockReasonCreate(hwnd, w!("Minecraftサーバーを停止しています"));
+                LRESULT(1) // Never veto the shutdown, only delay it
+            }
+            WM_ENDSESSION => {
+                if wparam.0 != 0 {
+                    if let Some((handler, runtime)) = STATE.get() {
+                        println!("[Power] Session is ending");
+                        let future = handler(PowerEvent::Shutdown);
+                        // The process is ended once this returns, servers must be stopped by then
+                        let _ = runtime.block_on(async {
+                            tokio::time::timeout(
+                                std::time::Duration::from_secs(SHUTDOWN_WAIT_SECS),
+                                future,
+                            )
+                            .await
+                        });
+                    }
+                }
+                let _ = ShutdownBlockReasonDestroy(hwnd);
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod windows_suspend {
+    use super::{PowerEvent, PowerHandler};
+    use std::ffi::c_void;
+    use std::sync::OnceLock;
+    use windows::Win32::Foundation::HANDLE;
+    use windows::Win32::System::Power::{
+        PowerRegisterSuspendResumeNotification, DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS,
+    };
+    use windows::Win32::UI::WindowsAndMessaging::{
+        DEVICE_NOTIFY_CALLBACK, PBT_APMRESUMEAUTOMATIC, PBT_APMSUSPEND,
+    };
+
+    /// Longer than the 30 s graceful stop, so a slow server is not cut off mid-save
+    const SUSPEND_WAIT_SECS: u64 = 45;
+
+    static STATE: OnceLock<(PowerHandler, tokio::runtime::Handle)> = OnceLock::new();
+
+    pub fn register(handler: PowerHandler) {
+        if STATE
+            .set((handler, tokio::runtime::Handle::current()))
+            .is_err()
+        {
+            return;
+        }
+
+        // Registration must outlive the process, so the parameters are leaked on purpose
+        let params = Box::leak(Box::new(DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS {
+            Callback: Some(on_power_event),
+            Context: std::ptr::null_mut(),
+        }));
+        let mut registration = std::ptr::null_mut();
+
+        let result = unsafe {
+            PowerRegisterSuspendResumeNotification(
+                DEVICE_NOTIFY_CALLBACK,
+                HANDLE(params as *mut DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS as *mut c_void),
+                &mut registration,
+            )
+        };
+
+        if result.is_err() {
+            println!(
+                "[Power] Failed to register suspend notification (error {})",
+                result.0
+            );
+        }
+    }
+
+    unsafe extern "system" fn on_power_event(
+        _context: *const c_void,
+        event_type: u32,
+        _setting: *const c_void,
+    ) -> u32 {
+        let Some((handler, runtime)) = STATE.get() else {
+            return 0;
+        };
+
+        if event_type == PBT_APMRESUMEAUTOMATIC {
+            println!("[Power] System resumed");
+            runtime.spawn(handler(PowerEvent::Resume));
+            return 0;
+        }
+
+        if event_type == PBT_APMSUSPEND {
+            println!("[Power] System is suspending");
+            let future = handler(PowerEvent::Suspend);
+            // Block the notification thread so servers stop before the machine sleeps
+            let _ = runtime.block_on(async {
+                tokio::time::timeout(std::time::Duration::from_secs(SUSPEND_WAIT_SECS), future)
+                    .await
+            });
+        }
+
+        0
+    }
+}
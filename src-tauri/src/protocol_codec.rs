@@ -0,0 +1,58 @@
+// Minecraft protocol codec
+// VarInts and length-prefixed strings as used by the Java edition protocol,
+// shared by the status ping and the stress test bots.
+
+use anyhow::{Context, Result};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+pub fn write_varint(buf: &mut Vec<u8>, value: i32) {
+    let mut value = value as u32;
+    loop {
+        if value & !0x7f == 0 {
+            buf.push(value as u8);
+            return;
+        }
+        buf.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+}
+
+pub fn write_string(buf: &mut Vec<u8>, value: &str) {
+    write_varint(buf, value.len() as i32);
+    buf.extend_from_slice(value.as_bytes());
+}
+
+pub fn read_varint(cursor: &mut &[u8]) -> Result<i32> {
+    let mut value = 0u32;
+    for shift in (0..35).step_by(7) {
+        let (&byte, rest) = cursor.split_first().context("Truncated packet")?;
+        *cursor = rest;
+        value |= ((byte & 0x7f) as u32) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value as i32);
+        }
+    }
+    anyhow::bail!("VarInt too long")
+}
+
+pub async fn read_varint_async<R: AsyncRead + Unpin>(reader: &mut R) -> Result<i32> {
+    let mut value = 0u32;
+    for shift in (0..35).step_by(7) {
+        let byte = reader.read_u8().await?;
+        value |= ((byte & 0x7f) as u32) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value as i32);
+        }
+    }
+    anyhow::bail!("VarInt too long")
+}
+
+pub fn read_string(cursor: &mut &[u8]) -> Result<String> {
+    let length = read_varint(cursor)? as usize;
+    if cursor.len() < length {
+        anyhow::bail!("Truncated string");
+    }
+    let (value, rest) = cursor.split_at(length);
+    *cursor = rest;
+    Ok(String::from_utf8_lossy(value).to_string())
+}
@@ -0,0 +1,154 @@
+// Protocol versions
+// Maps Java Edition protocol numbers to the Minecraft releases that speak them,
+// so a server list ping or a server's version can be turned into the exact
+// client versions that can join.
+
+use serde::Serialize;
+
+/// Release protocol numbers, oldest first. Releases sharing a protocol are
+/// interchangeable for joining (e.g. a 1.21 client joins a 1.21.1 server).
+const PROTOCOLS: &[(i32, &[&str])] = &[
+    (
+        47,
+        &[
+            "1.8", "1.8.1", "1.8.2", "1.8.3", "1.8.4", "1.8.5", "1.8.6", "1.8.7", "1.8.8", "1.8.9",
+        ],
+    ),
+    (107, &["1.9"]),
+    (108, &["1.9.1"]),
+    (109, &["1.9.2"]),
+    (110, &["1.9.3", "1.9.4"]),
+    (210, &["1.10", "1.10.1", "1.10.2"]),
+    (315, &["1.11"]),
+    (316, &["1.11.1", "1.11.2"]),
+    (335, &["1.12"]),
+    (338, &["1.12.1"]),
+    (340, &["1.12.2"]),
+    (393, &["1.13"]),
+    (401, &["1.13.1"]),
+    (404, &["1.13.2"]),
+    (477, &["1.14"]),
+    (480, &["1.14.1"]),
+    (485, &["1.14.2"]),
+    (490, &["1.14.3"]),
+    (498, &["1.14.4"]),
+    (573, &["1.15"]),
+    (575, &["1.15.1"]),
+    (578, &["1.15.2"]),
+    (735, &["1.16"]),
+    (736, &["1.16.1"]),
+    (751, &["1.16.2"]),
+    (753, &["1.16.3"]),
+    (754, &["1.16.4", "1.16.5"]),
+    (755, &["1.17"]),
+    (756, &["1.17.1"]),
+    (757, &["1.18", "1.18.1"]),
+    (758, &["1.18.2"]),
+    (759, &["1.19"]),
+    (760, &["1.19.1", "1.19.2"]),
+    (761, &["1.19.3"]),
+    (762, &["1.19.4"]),
+    (763, &["1.20", "1.20.1"]),
+    (764, &["1.20.2"]),
+    (765, &["1.20.3", "1.20.4"]),
+    (766, &["1.20.5", "1.20.6"]),
+    (767, &["1.21", "1.21.1"]),
+    (768, &["1.21.2", "1.21.3"]),
+    (769, &["1.21.4"]),
+    (770, &["1.21.5"]),
+    (771, &["1.21.6"]),
+    (772, &["1.21.7", "1.21.8"]),
+    (773, &["1.21.9", "1.21.10"]),
+];
+
+/// Oldest protocol ViaBackwards translates down to (1.10)
+const VIA_BACKWARDS_OLDEST: i32 = 210;
+
+/// Via plugins installed on a server
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct ViaPlugins {
+    pub via_version: bool,   // Newer clients
+    pub via_backwards: bool, // Older clients down to 1.10
+    pub via_rewind: bool,    // Older clients down to 1.8, needs ViaBackwards
+}
+
+/// Minecraft versions whose clients can join a server the way it is set up
+#[derive(Debug, Clone, Serialize)]
+pub struct ClientVersions {
+    pub protocol: Option<i32>, // None for versions newer than the table
+    pub oldest: String,
+    pub newest: String,
+    pub via: ViaPlugins,
+}
+
+/// Releases speaking `protocol`, empty when unknown
+pub fn versions_for(protocol: i32) -> &'static [&'static str] {
+    PROTOCOLS
+        .iter()
+        .find(|(p, _)| *p == protocol)
+        .map_or(&[], |(_, versions)| *versions)
+}
+
+/// Protocol number of a release, e.g. 767 for "1.21.1"
+pub fn protocol_for(version: &str) -> Option<i32> {
+    PROTOCOLS
+        .iter()
+        .find(|(_, versions)| versions.contains(&version))
+        .map(|(p, _)| *p)
+}
+
+/// "1.21 - 1.21.1" for a protocol, or "protocol N" when it is not in the table
+pub fn describe(protocol: i32) -> String {
+    match versions_for(protocol) {
+        [] => format!("protocol {}", protocol),
+        [only] => only.to_string(),
+        [first, .., last] => format!("{} - {}", first, last),
+    }
+}
+
+/// Newest release in the table
+pub fn latest_version() -> &'static str {
+    PROTOCOLS
+        .last()
+        .and_then(|(_, versions)| versions.last())
+        .copied()
+        .unwrap_or_default()
+}
+
+/// First release speaking `protocol` or a newer one
+fn first_version_from(protocol: i32) -> Option<&'static str> {
+    PROTOCOLS
+        .iter()
+        .find(|(p, _)| *p >= protocol)
+        .and_then(|(_, versions)| versions.first())
+        .copied()
+}
+
+/// Clients that can join a server running `version`. Without Via plugins only
+/// releases with the same protocol join; ViaVersion adds every newer release,
+/// ViaBackwards older ones down to 1.10 and ViaRewind down to 1.8.
+pub fn client_versions(version: &str, via: ViaPlugins) -> ClientVersions {
+    let protocol = protocol_for(version);
+    let same_protocol = protocol.map(versions_for).unwrap_or_default();
+    let oldest_protocol = match protocol {
+        Some(_) if via.via_version && via.via_backwards && via.via_rewind => PROTOCOLS[0].0,
+        Some(p) if via.via_version && via.via_backwards => p.min(VIA_BACKWARDS_OLDEST),
+        _ => protocol.unwrap_or_default(),
+    };
+    let oldest = first_version_from(oldest_protocol)
+        .filter(|_| protocol.is_some())
+        .or(same_protocol.first().copied())
+        .unwrap_or(version)
+        .to_string();
+    let newest = if via.via_version {
+        latest_version().to_string()
+    } else {
+        same_protocol.last().copied().unwrap_or(version).to_string()
+    };
+    ClientVersions {
+        protocol,
+        oldest,
+        newest,
+        via,
+    }
+}
@@ -0,0 +1,296 @@
+// Server list ping and GameSpy4 query
+// The status exchange a Minecraft client does for its server list: handshake
+// (next state 1), status request, then a ping/pong for the round trip. Gives the
+// real online count, a player sample, the protocol version and the favicon,
+// for any server that accepts Java connections, proxies included.
+// The UDP query (enable-query in server.properties) answers a full stat with
+// every online player's name and the plugin list, even where the ping hides
+// its sample.
+
+use crate::protocol_codec::{
+    read_string, read_varint, read_varint_async, write_string, write_varint,
+};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+
+/// Protocol sent in the handshake; servers answer status requests for any version
+const HANDSHAKE_PROTOCOL: i32 = -1;
+/// Status responses with a favicon are a few KB, anything past this is not a server
+const MAX_RESPONSE: i32 = 256 * 1024;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlayerSample {
+    pub name: String,
+    pub id: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusResponse {
+    pub online: u32,
+    pub max: u32,
+    /// Up to 12 players; servers with hide-online-players send none
+    pub sample: Vec<PlayerSample>,
+    pub version: String, // e.g. "Paper 1.21.1", proxies may send anything
+    pub protocol: i32,
+    pub motd: String,            // Plain text, formatting codes removed
+    pub favicon: Option<String>, // data:image/png;base64,...
+    pub latency_ms: u64,
+}
+
+impl StatusResponse {
+    /// Names of the online players when the sample lists all of them
+    pub fn complete_sample(&self) -> Option<Vec<String>> {
+        (self.sample.len() as u32 == self.online)
+            .then(|| self.sample.iter().map(|p| p.name.clone()).collect())
+    }
+}
+
+/// Full stat of the UDP query
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryResponse {
+    pub motd: String,
+    pub version: String,
+    pub software: String, // e.g. "Paper on 1.21.1", "Vanilla" without plugin support
+    pub plugins: Vec<String>, // "Name Version", empty on servers without plugins
+    pub map: String,
+    pub online: u32,
+    pub max: u32,
+    pub players: Vec<String>, // Every online player
+}
+
+const QUERY_MAGIC: [u8; 2] = [0xFE, 0xFD];
+const QUERY_HANDSHAKE: u8 = 0x09;
+const QUERY_STAT: u8 = 0x00;
+
+/// Ping host:port, giving up after `timeout`
+pub async fn status(host: &str, port: u16, timeout: Duration) -> Result<StatusResponse> {
+    tokio::time::timeout(timeout, exchange(host, port))
+        .await
+        .context("The server did not answer the status request in time")?
+}
+
+async fn exchange(host: &str, port: u16) -> Result<StatusResponse> {
+    let mut stream = TcpStream::connect((host, port))
+        .await
+        .context("Failed to connect to the server")?;
+    stream.set_nodelay(true)?;
+
+    let mut handshake = Vec::new();
+    write_varint(&mut handshake, HANDSHAKE_PROTOCOL);
+    write_string(&mut handshake, host);
+    handshake.extend_from_slice(&port.to_be_bytes());
+    write_varint(&mut handshake, 1); // Status
+    send(&mut stream, 0x00, &handshake).await?;
+    send(&mut stream, 0x00, &[]).await?;
+
+    let (id, data) = receive(&mut stream).await?;
+    if id != 0x00 {
+        anyhow::bail!("Unexpected packet 0x{:02x} instead of the status", id);
+    }
+    let mut cursor = data.as_slice();
+    let json: serde_json::Value = serde_json::from_str(&read_string(&mut cursor)?)
+        .context("The status response is not JSON")?;
+
+    // Ping/pong: the round trip as the server list shows it
+    let payload = rand::random::<i64>();
+    let started = Instant::now();
+    send(&mut stream, 0x01, &payload.to_be_bytes()).await?;
+    let latency_ms = match receive(&mut stream).await {
+        Ok((0x01, _)) => started.elapsed().as_millis() as u64,
+        _ => 0, // Some proxies close after the status, the status is still valid
+    };
+
+    let sample = json["players"]["sample"]
+        .as_array()
+        .map(|players| {
+            players
+                .iter()
+                .filter_map(|p| {
+                    Some(PlayerSample {
+                        name: p["name"].as_str()?.to_string(),
+                        id: p["id"].as_str().unwrap_or_default().to_string(),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(StatusResponse {
+        online: json["players"]["online"].as_u64().unwrap_or(0) as u32,
+        max: json["players"]["max"].as_u64().unwrap_or(0) as u32,
+        sample,
+        version: json["version"]["name"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+        protocol: json["version"]["protocol"]
+            .as_i64()
+            .context("The status has no protocol version")? as i32,
+        motd: strip_formatting(&description_text(&json["description"])),
+        favicon: json["favicon"].as_str().map(str::to_string),
+        latency_ms,
+    })
+}
+
+/// The description is a plain string or a chat component with "extra" parts
+fn description_text(description: &serde_json::Value) -> String {
+    match description {
+        serde_json::Value::String(text) => text.clone(),
+        serde_json::Value::Object(component) => {
+            let mut text = component
+                .get("text")
+                .and_then(|t| t.as_str())
+                .unwrap_or_default()
+                .to_string();
+            if let Some(extra) = component.get("extra").and_then(|e| e.as_array()) {
+                for part in extra {
+                    text.push_str(&description_text(part));
+                }
+            }
+            text
+        }
+        serde_json::Value::Array(parts) => parts.iter().map(description_text).collect(),
+        _ => String::new(),
+    }
+}
+
+/// Remove § formatting codes
+fn strip_formatting(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '§' {
+            chars.next();
+        } else {
+            plain.push(c);
+        }
+    }
+    plain
+}
+
+/// Full stat from the query port, giving up after `timeout`
+pub async fn full_stat(host: &str, port: u16, timeout: Duration) -> Result<QueryResponse> {
+    tokio::time::timeout(timeout, query_exchange(host, port))
+        .await
+        .context("The server did not answer the query in time")?
+}
+
+async fn query_exchange(host: &str, port: u16) -> Result<QueryResponse> {
+    let socket = UdpSocket::bind(("0.0.0.0", 0)).await?;
+    socket
+        .connect((host, port))
+        .await
+        .context("Failed to reach the query port")?;
+    // Only the low 4 bits of each byte are echoed back by the server
+    let session = rand::random::<u32>() & 0x0F0F_0F0F;
+
+    let mut packet = QUERY_MAGIC.to_vec();
+    packet.push(QUERY_HANDSHAKE);
+    packet.extend_from_slice(&session.to_be_bytes());
+    socket.send(&packet).await?;
+    let reply = query_reply(&socket, QUERY_HANDSHAKE, session).await?;
+    let token: i32 = read_cstring(&mut reply.as_slice())?
+        .trim()
+        .parse()
+        .context("Invalid query challenge token")?;
+
+    let mut packet = QUERY_MAGIC.to_vec();
+    packet.push(QUERY_STAT);
+    packet.extend_from_slice(&session.to_be_bytes());
+    packet.extend_from_slice(&token.to_be_bytes());
+    packet.extend_from_slice(&[0; 4]); // Padding asks for the full stat
+    socket.send(&packet).await?;
+    let reply = query_reply(&socket, QUERY_STAT, session).await?;
+
+    // "splitnum\0\x80\0", the key/value section, "\x01player_\0\0", the players
+    let mut cursor = reply.get(11..).context("Truncated query response")?;
+    let mut values = std::collections::HashMap::new();
+    loop {
+        let key = read_cstring(&mut cursor)?;
+        if key.is_empty() {
+            break;
+        }
+        values.insert(key, read_cstring(&mut cursor)?);
+    }
+    let mut cursor = cursor.get(10..).unwrap_or_default();
+    let mut players = Vec::new();
+    while let Ok(name) = read_cstring(&mut cursor) {
+        if name.is_empty() {
+            break;
+        }
+        players.push(name);
+    }
+
+    let value = |key: &str| values.get(key).cloned().unwrap_or_default();
+    // Bukkit sends "Paper on 1.21.1: WorldEdit 7.3.4; LuckPerms 5.4.131"
+    let (software, plugins) = match value("plugins").split_once(": ") {
+        Some((software, plugins)) => (
+            software.to_string(),
+            plugins
+                .split("; ")
+                .map(|p| p.trim().to_string())
+                .filter(|p| !p.is_empty())
+                .collect(),
+        ),
+        None => (value("plugins"), Vec::new()),
+    };
+
+    Ok(QueryResponse {
+        motd: strip_formatting(&value("hostname")),
+        version: value("version"),
+        software,
+        plugins,
+        map: value("map"),
+        online: value("numplayers").parse().unwrap_or(0),
+        max: value("maxplayers").parse().unwrap_or(0),
+        players,
+    })
+}
+
+/// Next datagram of `kind` for our session, without the 5-byte header
+async fn query_reply(socket: &UdpSocket, kind: u8, session: u32) -> Result<Vec<u8>> {
+    let mut buffer = vec![0u8; 16 * 1024];
+    loop {
+        let read = socket.recv(&mut buffer).await?;
+        if read >= 5 && buffer[0] == kind && buffer[1..5] == session.to_be_bytes() {
+            return Ok(buffer[5..read].to_vec());
+        }
+    }
+}
+
+/// Null-terminated string
+fn read_cstring(cursor: &mut &[u8]) -> Result<String> {
+    let end = cursor
+        .iter()
+        .position(|b| *b == 0)
+        .context("Truncated query response")?;
+    let value = String::from_utf8_lossy(&cursor[..end]).to_string();
+    *cursor = &cursor[end + 1..];
+    Ok(value)
+}
+
+async fn send(stream: &mut TcpStream, id: i32, data: &[u8]) -> Result<()> {
+    let mut body = Vec::with_capacity(data.len() + 5);
+    write_varint(&mut body, id);
+    body.extend_from_slice(data);
+    let mut packet = Vec::with_capacity(body.len() + 5);
+    write_varint(&mut packet, body.len() as i32);
+    packet.extend_from_slice(&body);
+    stream.write_all(&packet).await?;
+    Ok(())
+}
+
+async fn receive(stream: &mut TcpStream) -> Result<(i32, Vec<u8>)> {
+    let length = read_varint_async(stream).await?;
+    if !(1..=MAX_RESPONSE).contains(&length) {
+        anyhow::bail!("Invalid packet length {}", length);
+    }
+    let mut packet = vec![0u8; length as usize];
+    stream.read_exact(&mut packet).await?;
+    let mut cursor = packet.as_slice();
+    let id = read_varint(&mut cursor)?;
+    Ok((id, cursor.to_vec()))
+}
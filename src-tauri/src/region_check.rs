@@ -0,0 +1,135 @@
+// Region check
+// A server killed mid-save can leave region files half written. After a forced
+// kill the region files written during the session are checked: the header's
+// chunk locations must point inside the file, and each chunk must start with
+// a plausible length and compression type. Only the structure is checked, a
+// chunk with valid framing can still hold damaged data.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+const SECTOR: u64 = 4096;
+const HEADER: u64 = 2 * SECTOR; // Chunk locations, then timestamps
+/// Reported problems are capped, one broken file usually has many
+const MAX_PROBLEMS: usize = 20;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegionCheckReport {
+    pub checked_at: u64,
+    pub files_checked: usize,
+    pub problems: Vec<String>, // "world/region/r.0.-1.mca: chunk 12 points past the end"
+}
+
+/// Region folders of a world and its dimensions (vanilla and Bukkit layouts)
+fn region_dirs(server_path: &Path, level_name: &str) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    for world in [
+        level_name.to_string(),
+        format!("{}_nether", level_name),
+        format!("{}_the_end", level_name),
+    ] {
+        let world_dir = server_path.join(world);
+        for dimension in ["", "DIM-1", "DIM1"] {
+            let dir = world_dir.join(dimension).join("region");
+            if dir.is_dir() {
+                dirs.push(dir);
+            }
+        }
+    }
+    dirs
+}
+
+/// Check region files modified since `since` (all of them without)
+pub fn check(server_path: &Path, level_name: &str, since: Option<SystemTime>) -> RegionCheckReport {
+    let mut problems = Vec::new();
+    let mut files_checked = 0;
+    for dir in region_dirs(server_path, level_name) {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("mca") {
+                continue;
+            }
+            let modified = entry.metadata().and_then(|m| m.modified()).ok();
+            if let (Some(since), Some(modified)) = (since, modified) {
+                if modified < since {
+                    continue;
+                }
+            }
+            files_checked += 1;
+            let name = path
+                .strip_prefix(server_path)
+                .unwrap_or(&path)
+                .display()
+                .to_string();
+            match std::fs::read(&path) {
+                Ok(data) => problems.extend(
+                    check_region(&data)
+                        .into_iter()
+                        .map(|problem| format!("{}: {}", name, problem)),
+                ),
+                Err(e) => problems.push(format!("{}: unreadable ({})", name, e)),
+            }
+        }
+    }
+    problems.truncate(MAX_PROBLEMS);
+
+    RegionCheckReport {
+        checked_at: SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+        files_checked,
+        problems,
+    }
+}
+
+fn check_region(data: &[u8]) -> Vec<String> {
+    let len = data.len() as u64;
+    if len == 0 {
+        return Vec::new(); // Created but never written, the server fills it in
+    }
+    if len < HEADER {
+        return vec![format!("truncated header ({} bytes)", len)];
+    }
+
+    let mut problems = Vec::new();
+    for index in 0..1024 {
+        let location = &data[index * 4..index * 4 + 4];
+        let offset = u32::from_be_bytes([0, location[0], location[1], location[2]]) as u64;
+        let sectors = location[3] as u64;
+        if offset == 0 && sectors == 0 {
+            continue; // Chunk not generated
+        }
+        if offset < 2 {
+            problems.push(format!("chunk {} points into the header", index));
+            continue;
+        }
+        let start = offset * SECTOR;
+        if start + 5 > len || (offset + sectors) * SECTOR > len.div_ceil(SECTOR) * SECTOR {
+            problems.push(format!("chunk {} points past the end of the file", index));
+            continue;
+        }
+        let start = start as usize;
+        let length = u32::from_be_bytes([
+            data[start],
+            data[start + 1],
+            data[start + 2],
+            data[start + 3],
+        ]) as u64;
+        let compression = data[start + 4];
+        // 1 gzip, 2 zlib, 3 none, 4 LZ4; +128 when stored in an external .mcc file
+        if !matches!(compression & 0x7f, 1..=4) {
+            problems.push(format!(
+                "chunk {} has unknown compression {}",
+                index, compression
+            ));
+        } else if length == 0 || (compression & 0x80 == 0 && length + 4 > sectors * SECTOR) {
+            problems.push(format!("chunk {} has an invalid length {}", index, length));
+        }
+    }
+    problems
+}
@@ -0,0 +1,307 @@
+// Externally hosted servers
+// Servers rented from a host are reached over SFTP with the system's OpenSSH
+// client (key authentication only, BatchMode). Prismarine keeps a local copy
+// of the config files in the server's folder: pull downloads them, push
+// uploads the ones edited since. Start/stop and console commands go through
+// the host's Pterodactyl client API when a panel is configured; its API key
+// lives in the secret store.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// Modification times of the files as pulled, to find local edits to push
+const SYNC_STATE: &str = ".remote-sync.json";
+/// Top-level files pulled from the host
+const CONFIG_EXTENSIONS: &[&str] = &["yml", "yaml", "properties", "json", "toml"];
+
+fn default_sftp_port() -> u16 {
+    22
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteHost {
+    pub host: String,
+    #[serde(default = "default_sftp_port")]
+    pub port: u16,
+    pub user: String,
+    #[serde(default)]
+    pub key_path: Option<String>, // Private key, else the ssh agent/default keys
+    #[serde(default)]
+    pub root: String, // Server folder on the host, "" for the login directory
+    #[serde(default)]
+    pub panel: Option<PanelConfig>,
+}
+
+/// Pterodactyl-compatible panel (most Minecraft hosts)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PanelConfig {
+    pub url: String,       // e.g. https://panel.example.com
+    pub server_id: String, // Short id shown in the panel URL
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncReport {
+    pub files: Vec<String>,
+}
+
+/// Path on the host of `relative` (a '/' separated path inside the server folder)
+fn remote_path(host: &RemoteHost, relative: &str) -> String {
+    let root = host.root.trim_end_matches('/');
+    match (root.is_empty(), relative.is_empty()) {
+        (true, _) => relative.to_string(),
+        (false, true) => root.to_string(),
+        (false, false) => format!("{}/{}", root, relative),
+    }
+}
+
+/// Quote an argument of an sftp batch command
+fn quote(arg: &str) -> String {
+    format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Run sftp batch commands and return what they printed (without the echoed commands)
+async fn run_batch(host: &RemoteHost, commands: &[String]) -> Result<String> {
+    let mut command = Command::new("sftp");
+    command
+        .args(["-b", "-", "-P", &host.port.to_string()])
+        .args([
+            "-o",
+            "BatchMode=yes",
+            "-o",
+            "StrictHostKeyChecking=accept-new",
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(key) = host.key_path.as_ref().filter(|k| !k.is_empty()) {
+        command.args(["-i", key]);
+    }
+    command.arg(format!("{}@{}", host.user, host.host));
+
+    let mut child = command
+        .spawn()
+        .context("Failed to run sftp. Install the OpenSSH client")?;
+    let mut stdin = child.stdin.take().context("sftp stdin not available")?;
+    stdin.write_all(commands.join("\n").as_bytes()).await?;
+    drop(stdin);
+
+    let output = tokio::time::timeout(
+        std::time::Duration::from_secs(300),
+        child.wait_with_output(),
+    )
+    .await
+    .context("sftp timed out")??;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("SFTP failed: {}", stderr.trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.starts_with("sftp>"))
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// Check that the host accepts the login and the server folder exists
+pub async fn test(host: &RemoteHost) -> Result<()> {
+    run_batch(host, &[format!("ls -1 {}", quote(&remote_path(host, "")))])
+        .await
+        .map(|_| ())
+}
+
+/// Names in a folder of the server
+pub async fn list(host: &RemoteHost, relative: &str) -> Result<Vec<String>> {
+    let output = run_batch(
+        host,
+        &[format!("ls -1 {}", quote(&remote_path(host, relative)))],
+    )
+    .await?;
+    Ok(output
+        .lines()
+        .map(|line| line.trim().rsplit('/').next().unwrap_or("").to_string())
+        .filter(|name| !name.is_empty() && name != "." && name != "..")
+        .collect())
+}
+
+pub async fn download(host: &RemoteHost, relative: &str, local: &Path) -> Result<()> {
+    if let Some(parent) = local.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    run_batch(
+        host,
+        &[format!(
+            "get {} {}",
+            quote(&remote_path(host, relative)),
+            quote(&local.to_string_lossy())
+        )],
+    )
+    .await
+    .with_context(|| format!("Failed to download {}", relative))
+    .map(|_| ())
+}
+
+pub async fn upload(host: &RemoteHost, local: &Path, relative: &str) -> Result<()> {
+    run_batch(
+        host,
+        &[format!(
+            "put {} {}",
+            quote(&local.to_string_lossy()),
+            quote(&remote_path(host, relative))
+        )],
+    )
+    .await
+    .with_context(|| format!("Failed to upload {}", relative))
+    .map(|_| ())
+}
+
+pub async fn remove(host: &RemoteHost, relative: &str) -> Result<()> {
+    run_batch(
+        host,
+        &[format!("rm {}", quote(&remote_path(host, relative)))],
+    )
+    .await
+    .with_context(|| format!("Failed to delete {}", relative))
+    .map(|_| ())
+}
+
+fn is_config(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| CONFIG_EXTENSIONS.contains(&ext))
+}
+
+fn modified_secs(path: &Path) -> Option<u64> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    Some(
+        modified
+            .duration_since(std::time::UNIX_EPOCH)
+            .ok()?
+            .as_secs(),
+    )
+}
+
+fn read_sync_state(local_dir: &Path) -> HashMap<String, u64> {
+    std::fs::read_to_string(local_dir.join(SYNC_STATE))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn write_sync_state(local_dir: &Path, state: &HashMap<String, u64>) -> Result<()> {
+    std::fs::write(local_dir.join(SYNC_STATE), serde_json::to_string(state)?)?;
+    Ok(())
+}
+
+/// Download the config files (top level and config/) into `local_dir`
+pub async fn pull(host: &RemoteHost, local_dir: &Path) -> Result<SyncReport> {
+    let mut files: Vec<String> = list(host, "")
+        .await?
+        .into_iter()
+        .filter(|name| is_config(name))
+        .collect();
+    if let Ok(config) = list(host, "config").await {
+        files.extend(
+            config
+                .into_iter()
+                .filter(|name| is_config(name))
+                .map(|name| format!("config/{}", name)),
+        );
+    }
+
+    let mut state = read_sync_state(local_dir);
+    for file in &files {
+        let local = local_dir.join(file);
+        download(host, file, &local).await?;
+        if let Some(modified) = modified_secs(&local) {
+            state.insert(file.clone(), modified);
+        }
+    }
+    write_sync_state(local_dir, &state)?;
+    Ok(SyncReport { files })
+}
+
+/// Upload the pulled files that were edited locally since
+pub async fn push(host: &RemoteHost, local_dir: &Path) -> Result<SyncReport> {
+    let mut state = read_sync_state(local_dir);
+    let mut files = Vec::new();
+    for (file, pulled) in state.clone() {
+        let local = local_dir.join(&file);
+        let Some(modified) = modified_secs(&local) else {
+            continue;
+        };
+        if modified != pulled {
+            upload(host, &local, &file).await?;
+            state.insert(file.clone(), modified);
+            files.push(file);
+        }
+    }
+    write_sync_state(local_dir, &state)?;
+    files.sort();
+    Ok(SyncReport { files })
+}
+
+fn panel_request(
+    client: &reqwest::Client,
+    method: reqwest::Method,
+    panel: &PanelConfig,
+    api_key: &str,
+    path: &str,
+) -> reqwest::RequestBuilder {
+    client
+        .request(
+            method,
+            format!(
+                "{}/api/client/servers/{}/{}",
+                panel.url.trim_end_matches('/'),
+                panel.server_id,
+                path
+            ),
+        )
+        .bearer_auth(api_key)
+        .header("Accept", "application/json")
+}
+
+/// Send a power signal: "start", "stop", "restart" or "kill"
+pub async fn power(panel: &PanelConfig, api_key: &str, signal: &str) -> Result<()> {
+    let client = reqwest::Client::new();
+    panel_request(&client, reqwest::Method::POST, panel, api_key, "power")
+        .json(&serde_json::json!({ "signal": signal }))
+        .send()
+        .await?
+        .error_for_status()
+        .with_context(|| format!("The panel rejected the {} request", signal))?;
+    Ok(())
+}
+
+pub async fn send_command(panel: &PanelConfig, api_key: &str, command: &str) -> Result<()> {
+    let client = reqwest::Client::new();
+    panel_request(&client, reqwest::Method::POST, panel, api_key, "command")
+        .json(&serde_json::json!({ "command": command }))
+        .send()
+        .await?
+        .error_for_status()
+        .context("The panel rejected the command (is the server running?)")?;
+    Ok(())
+}
+
+/// Panel state: "running", "starting", "stopping" or "offline"
+pub async fn state(panel: &PanelConfig, api_key: &str) -> Result<String> {
+    let client = reqwest::Client::new();
+    let resources: serde_json::Value =
+        panel_request(&client, reqwest::Method::GET, panel, api_key, "resources")
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+    resources["attributes"]["current_state"]
+        .as_str()
+        .map(str::to_string)
+        .context("The panel did not report a state")
+}
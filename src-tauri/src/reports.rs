@@ -0,0 +1,254 @@
+// Statistics reports
+// Running servers are sampled for online players and TPS, and crashes and world
+// backups are recorded, in a per-server log in the app data directory. Weekly
+// and monthly summaries are built from the log on request and, when enabled,
+// posted to the notification webhook.
+
+use crate::clock::now_secs;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+/// Seconds between two samples of a running server
+pub const SAMPLE_INTERVAL: u64 = 300;
+/// Entries older than this are dropped, the longest period fits with room to spare
+const RETENTION: u64 = 40 * 86400;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReportPeriod {
+    Weekly,
+    Monthly,
+}
+
+impl ReportPeriod {
+    fn seconds(self) -> u64 {
+        match self {
+            ReportPeriod::Weekly => 7 * 86400,
+            ReportPeriod::Monthly => 30 * 86400,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            ReportPeriod::Weekly => "Weekly",
+            ReportPeriod::Monthly => "Monthly",
+        }
+    }
+}
+
+/// Which reports are posted to the notification webhook
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReportSchedule {
+    pub weekly: bool,
+    pub monthly: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Sample {
+    at: u64,
+    players: Vec<String>,
+    #[serde(default)]
+    tps: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BackupRecord {
+    at: u64,
+    success: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct StatsLog {
+    #[serde(default)]
+    samples: Vec<Sample>,
+    #[serde(default)]
+    crashes: Vec<u64>,
+    #[serde(default)]
+    backups: Vec<BackupRecord>,
+    #[serde(default)]
+    last_weekly: Option<u64>,
+    #[serde(default)]
+    last_monthly: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StatsReport {
+    pub server_id: String,
+    pub period: ReportPeriod,
+    pub from: u64,
+    pub to: u64,
+    pub unique_players: usize,
+    pub peak_players: usize,
+    pub average_tps: Option<f64>, // None without RCON (or a server without /tps)
+    pub crashes: usize,
+    pub backups: usize,
+    pub backup_success_rate: Option<f64>, // 0-100, None without backups
+    pub hours_online: f64,                // Time covered by samples
+}
+
+impl StatsReport {
+    /// Plain text for the webhook
+    pub fn to_message(&self, server_name: &str) -> String {
+        let tps = self
+            .average_tps
+            .map(|tps| format!("{:.1}", tps))
+            .unwrap_or_else(|| "n/a".to_string());
+        let backups = match self.backup_success_rate {
+            Some(rate) => format!("{} ({:.0}% succeeded)", self.backups, rate),
+            None => "none".to_string(),
+        };
+        format!(
+            "{} report for \"{}\"\nUnique players: {}\nPeak online: {}\nAverage TPS: {}\nCrashes: {}\nBackups: {}\nOnline for {:.1} h",
+            self.period.label(),
+            server_name,
+            self.unique_players,
+            self.peak_players,
+            tps,
+            self.crashes,
+            backups,
+            self.hours_online
+        )
+    }
+}
+
+fn log_path(server_id: &str) -> PathBuf {
+    crate::paths::get()
+        .data_dir
+        .join("stats")
+        .join(format!("{}.json", server_id))
+}
+
+fn load(server_id: &str) -> StatsLog {
+    std::fs::read_to_string(log_path(server_id))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save(server_id: &str, log: &mut StatsLog) -> Result<()> {
+    let cutoff = now_secs().saturating_sub(RETENTION);
+    log.samples.retain(|s| s.at >= cutoff);
+    log.crashes.retain(|at| *at >= cutoff);
+    log.backups.retain(|b| b.at >= cutoff);
+
+    let path = log_path(server_id);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string(log)?)?;
+    Ok(())
+}
+
+/// Whether the server's last sample is old enough for a new one
+pub fn sample_due(server_id: &str) -> bool {
+    load(server_id)
+        .samples
+        .last()
+        .is_none_or(|s| now_secs() >= s.at + SAMPLE_INTERVAL)
+}
+
+pub fn record_sample(server_id: &str, players: Vec<String>, tps: Option<f64>) -> Result<()> {
+    let mut log = load(server_id);
+    log.samples.push(Sample {
+        at: now_secs(),
+        players,
+        tps,
+    });
+    save(server_id, &mut log)
+}
+
+pub fn record_crash(server_id: &str) {
+    let mut log = load(server_id);
+    log.crashes.push(now_secs());
+    if let Err(e) = save(server_id, &mut log) {
+        println!("[Reports] Failed to record a crash of {}: {}", server_id, e);
+    }
+}
+
+pub fn record_backup(server_id: &str, success: bool) {
+    let mut log = load(server_id);
+    log.backups.push(BackupRecord {
+        at: now_secs(),
+        success,
+    });
+    if let Err(e) = save(server_id, &mut log) {
+        println!(
+            "[Reports] Failed to record a backup of {}: {}",
+            server_id, e
+        );
+    }
+}
+
+/// Summary of the period ending now
+pub fn build(server_id: &str, period: ReportPeriod) -> StatsReport {
+    let to = now_secs();
+    let from = to.saturating_sub(period.seconds());
+    let log = load(server_id);
+
+    let samples: Vec<&Sample> = log.samples.iter().filter(|s| s.at >= from).collect();
+    let unique_players = samples
+        .iter()
+        .flat_map(|s| s.players.iter())
+        .collect::<HashSet<_>>()
+        .len();
+    let peak_players = samples.iter().map(|s| s.players.len()).max().unwrap_or(0);
+    let tps: Vec<f64> = samples.iter().filter_map(|s| s.tps).collect();
+    let average_tps = (!tps.is_empty()).then(|| tps.iter().sum::<f64>() / tps.len() as f64);
+
+    let backups: Vec<&BackupRecord> = log.backups.iter().filter(|b| b.at >= from).collect();
+    let succeeded = backups.iter().filter(|b| b.success).count();
+    let backup_success_rate =
+        (!backups.is_empty()).then(|| succeeded as f64 * 100.0 / backups.len() as f64);
+
+    StatsReport {
+        server_id: server_id.to_string(),
+        period,
+        from,
+        to,
+        unique_players,
+        peak_players,
+        average_tps,
+        crashes: log.crashes.iter().filter(|at| **at >= from).count(),
+        backups: backups.len(),
+        backup_success_rate,
+        hours_online: (samples.len() as u64 * SAMPLE_INTERVAL) as f64 / 3600.0,
+    }
+}
+
+/// Scheduled periods whose report is due. A server's first report goes out
+/// one full period after its first sample.
+pub fn due(server_id: &str, schedule: &ReportSchedule) -> Vec<ReportPeriod> {
+    let log = load(server_id);
+    let Some(first) = log.samples.first().map(|s| s.at) else {
+        return Vec::new();
+    };
+    let now = now_secs();
+
+    [
+        (ReportPeriod::Weekly, schedule.weekly, log.last_weekly),
+        (ReportPeriod::Monthly, schedule.monthly, log.last_monthly),
+    ]
+    .into_iter()
+    .filter(|(period, enabled, last)| *enabled && now >= last.unwrap_or(first) + period.seconds())
+    .map(|(period, _, _)| period)
+    .collect()
+}
+
+/// Record a delivered report, the next one is due a period later
+pub fn mark_sent(server_id: &str, period: ReportPeriod) {
+    let mut log = load(server_id);
+    let last = match period {
+        ReportPeriod::Weekly => &mut log.last_weekly,
+        ReportPeriod::Monthly => &mut log.last_monthly,
+    };
+    *last = Some(now_secs());
+    if let Err(e) = save(server_id, &mut log) {
+        println!("[Reports] Failed to save {}: {}", server_id, e);
+    }
+}
+
+/// Remove the log of a deleted server
+pub fn remove_all(server_id: &str) {
+    let _ = std::fs::remove_file(log_path(server_id));
+}
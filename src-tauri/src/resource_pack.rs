@@ -0,0 +1,222 @@
+// Resource pack hosting
+// Serves a server's resource pack zip over a small built-in HTTP listener, or
+// uploads it (HTTP PUT) to a URL the owner provides, so players get the pack
+// without a third-party file host. The pack's SHA-1 lets clients cache it.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// The pack is kept in the server folder under this name
+pub const PACK_FILE: &str = "resource-pack.zip";
+/// Largest request head read from a client
+const MAX_REQUEST_SIZE: usize = 8192;
+/// Time a client gets to send its request head
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Pause after a failed accept (out of file handles...), doubled up to the max
+const ACCEPT_BACKOFF_MIN: Duration = Duration::from_millis(50);
+const ACCEPT_BACKOFF_MAX: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind")]
+pub enum PackHosting {
+    /// Served by the app; players must be able to reach the port
+    BuiltIn { port: u16 },
+    /// PUT to `upload_url` (WebDAV, a presigned S3 URL...), players download `public_url`
+    Upload {
+        upload_url: String,
+        public_url: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourcePackSettings {
+    pub hosting: PackHosting,
+    pub sha1: String,
+    pub url: String, // Written to resource-pack in server.properties
+    pub required: bool,
+}
+
+/// SHA-1 of a resource pack, after checking it is a zip with a pack.mcmeta
+pub fn inspect_pack(path: &Path) -> Result<String> {
+    let file = std::fs::File::open(path).context("Failed to open the resource pack")?;
+    let mut archive = zip::ZipArchive::new(file).context("The resource pack is not a zip file")?;
+    if archive.by_name("pack.mcmeta").is_err() {
+        anyhow::bail!("The zip has no pack.mcmeta at its root, it is not a resource pack");
+    }
+
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha1::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Upload the pack with an HTTP PUT
+pub async fn upload(path: &Path, upload_url: &str) -> Result<()> {
+    crate::offline::ensure_online("Uploading the resource pack")?;
+    let data = tokio::fs::read(path).await?;
+    let client = reqwest::Client::builder()
+        .user_agent("MinecraftServerManager/0.1.0")
+        .build()?;
+    let response = client
+        .put(upload_url)
+        .header("Content-Type", "application/zip")
+        .body(data)
+        .send()
+        .await
+        .context("Failed to upload the resource pack")?;
+    if !response.status().is_success() {
+        anyhow::bail!("Resource pack upload failed (HTTP {})", response.status());
+    }
+    Ok(())
+}
+
+pub struct PackHost {
+    listeners: Mutex<HashMap<String, JoinHandle<()>>>,
+}
+
+impl PackHost {
+    pub fn new() -> Self {
+        Self {
+            listeners: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// (Re)start serving the pack of a server, or stop when it is not built-in hosted
+    pub async fn apply(
+        &self,
+        server_id: &str,
+        server_path: &Path,
+        settings: Option<&ResourcePackSettings>,
+    ) -> Result<()> {
+        self.stop(server_id);
+        let Some(settings) = settings else {
+            return Ok(());
+        };
+        let PackHosting::BuiltIn { port } = settings.hosting else {
+            return Ok(());
+        };
+
+        let listener = TcpListener::bind(("0.0.0.0", port))
+            .await
+            .with_context(|| format!("Failed to listen on port {}", port))?;
+        println!(
+            "[ResourcePack] Serving the pack of {} on port {}",
+            server_id, port
+        );
+
+        let pack = server_path.join(PACK_FILE);
+        let request_path = format!("/{}.zip", settings.sha1);
+        let handle = tokio::spawn(async move {
+            let mut backoff = ACCEPT_BACKOFF_MIN;
+            loop {
+                let (stream, peer) = match listener.accept().await {
+                    Ok(accepted) => {
+                        backoff = ACCEPT_BACKOFF_MIN;
+                        accepted
+                    }
+                    Err(e) => {
+                        println!(
+                            "[ResourcePack] Accept failed, retrying in {:?}: {}",
+                            backoff, e
+                        );
+                        tokio::time::sleep(backoff).await;
+                        backoff = (backoff * 2).min(ACCEPT_BACKOFF_MAX);
+                        continue;
+                    }
+                };
+                let pack = pack.clone();
+                let request_path = request_path.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = serve(stream, &pack, &request_path).await {
+                        println!("[ResourcePack] Request from {} failed: {}", peer, e);
+                    }
+                });
+            }
+        });
+
+        self.listeners
+            .lock()
+            .unwrap()
+            .insert(server_id.to_string(), handle);
+        Ok(())
+    }
+
+    pub fn stop(&self, server_id: &str) {
+        if let Some(handle) = self.listeners.lock().unwrap().remove(server_id) {
+            handle.abort();
+            println!("[ResourcePack] Stopped serving the pack of {}", server_id);
+        }
+    }
+}
+
+/// Answer one GET request: the pack at `request_path`, 404 for anything else
+async fn serve(mut stream: TcpStream, pack: &Path, request_path: &str) -> Result<()> {
+    let head = tokio::time::timeout(REQUEST_TIMEOUT, read_head(&mut stream))
+        .await
+        .context("Timed out waiting for the request")??;
+    let Some(head) = head else {
+        return Ok(());
+    };
+
+    let head = String::from_utf8_lossy(&head);
+    let mut request_line = head.lines().next().unwrap_or_default().split(' ');
+    let (method, path) = (request_line.next(), request_line.next());
+
+    let file = match (method, path) {
+        (Some("GET"), Some(path)) if path == request_path => tokio::fs::File::open(pack).await.ok(),
+        _ => None,
+    };
+    let Some(mut file) = file else {
+        stream
+            .write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+            .await?;
+        return Ok(());
+    };
+
+    let length = file.metadata().await?.len();
+    stream
+        .write_all(
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/zip\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                length
+            )
+            .as_bytes(),
+        )
+        .await?;
+    tokio::io::copy(&mut file, &mut stream).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+/// Request head up to the blank line, None when the client hung up first
+async fn read_head(stream: &mut TcpStream) -> Result<Option<Vec<u8>>> {
+    let mut head = Vec::new();
+    let mut buffer = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            return Ok(None);
+        }
+        head.extend_from_slice(&buffer[..read]);
+        if head.len() > MAX_REQUEST_SIZE {
+            anyhow::bail!("Request too large");
+        }
+    }
+    Ok(Some(head))
+}
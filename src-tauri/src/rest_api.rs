@@ -0,0 +1,670 @@
+// REST API
+// Optional HTTP API for managing servers from another machine or a CI script
+// without the desktop UI, or to drive a headless core. Handlers call the same
+// functions as the Tauri commands, so a start over the API opens ports and
+// tunnels like the button does. Requests carry `Authorization: Bearer <token>`:
+// the app token (in the secret store) reaches every server, a server's own API
+// token with the RemoteApi scope only that server. Listening on every interface
+// requires TLS, and clients are locked out for a while after repeated failed
+// authentications.
+//
+//   GET  /api/v1/servers                 GET  /api/v1/servers/{id}
+//   POST /api/v1/servers/{id}/start      POST /api/v1/servers/{id}/stop
+//   POST /api/v1/servers/{id}/restart    POST /api/v1/servers/{id}/command
+//   GET  /api/v1/servers/{id}/logs       GET  /api/v1/servers/{id}/health
+//   GET  /api/v1/servers/{id}/players    GET  /api/v1/system
+//   GET  /api/v1/servers/{id}/events     (WebSocket)
+//
+// The events socket streams JSON messages for one server: recent and new
+// console lines, status changes and process metrics. Browsers cannot set
+// headers on a WebSocket, so it also takes the token as the `bearer.<token>`
+// subprotocol (a query string would leave it in proxy and access logs).
+
+use crate::error::PrismarineError;
+use crate::monitor::Monitor;
+use crate::server_manager::{ApiScope, ServerStatus, StatusChange};
+use crate::{secrets, AppState};
+use anyhow::{Context, Result};
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{ConnectInfo, Path, Query, Request, State};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+/// Secret store entry of the app-wide token
+pub const APP_TOKEN: &str = "rest_api:token";
+const DEFAULT_PORT: u16 = 25581;
+const DEFAULT_LOG_LINES: usize = 100;
+/// Console lines an event stream starts with
+const STREAM_BACKLOG: usize = 50;
+const STREAM_LOG_POLL: Duration = Duration::from_millis(500);
+/// Most log bytes read per poll, the rest follows on the next ones
+const STREAM_LOG_CHUNK: u64 = 256 * 1024;
+const STREAM_METRICS_INTERVAL: Duration = Duration::from_secs(5);
+/// Failed authentications before a client is locked out
+const MAX_AUTH_FAILURES: u32 = 5;
+/// How long a lockout lasts, and how long failures are remembered
+const AUTH_LOCKOUT: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestApiSettings {
+    pub enabled: bool,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    /// Listen on every interface instead of localhost only (needs TLS)
+    #[serde(default)]
+    pub listen_all: bool,
+    /// PEM certificate chain and private key; the API is served over HTTPS when both are set
+    #[serde(default)]
+    pub tls_cert: Option<PathBuf>,
+    #[serde(default)]
+    pub tls_key: Option<PathBuf>,
+}
+
+fn default_port() -> u16 {
+    DEFAULT_PORT
+}
+
+impl Default for RestApiSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: DEFAULT_PORT,
+            listen_all: false,
+            tls_cert: None,
+            tls_key: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RestApiStatus {
+    pub running: bool,
+    pub address: Option<String>, // e.g. "127.0.0.1:25581"
+    pub tls: bool,
+    pub token_stored: bool,
+}
+
+struct Listener {
+    address: String,
+    tls: bool,
+    handle: JoinHandle<()>,
+}
+
+pub struct RestApi {
+    listener: Mutex<Option<Listener>>,
+}
+
+/// Failed authentications of one client
+struct AuthFailures {
+    count: u32,
+    last: Instant,
+}
+
+/// Who a request acts as
+enum Access {
+    All,
+    Server(String),
+}
+
+struct ApiError(StatusCode, serde_json::Value);
+
+impl From<PrismarineError> for ApiError {
+    fn from(error: PrismarineError) -> Self {
+        let status = match &error {
+            PrismarineError::NotFound { .. } => StatusCode::NOT_FOUND,
+            PrismarineError::InvalidInput { .. } => StatusCode::BAD_REQUEST,
+            PrismarineError::InvalidState { .. }
+            | PrismarineError::PortInUse { .. }
+            | PrismarineError::InsufficientMemory { .. } => StatusCode::CONFLICT,
+            PrismarineError::OfflineMode { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        ApiError(status, serde_json::to_value(&error).unwrap_or_default())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(self.1)).into_response()
+    }
+}
+
+fn unauthorized() -> ApiError {
+    ApiError(
+        StatusCode::UNAUTHORIZED,
+        serde_json::json!({ "code": "UNAUTHORIZED", "message": "Missing or invalid API token" }),
+    )
+}
+
+fn forbidden() -> ApiError {
+    ApiError(
+        StatusCode::FORBIDDEN,
+        serde_json::json!({ "code": "FORBIDDEN", "message": "This token cannot access this resource" }),
+    )
+}
+
+type ApiResult<T> = std::result::Result<Json<T>, ApiError>;
+
+impl RestApi {
+    pub fn new() -> Self {
+        Self {
+            listener: Mutex::new(None),
+        }
+    }
+
+    /// Start, restart or stop the listener to match `settings`
+    pub async fn apply(&self, settings: &RestApiSettings, state: AppState) -> Result<()> {
+        self.stop();
+        if !settings.enabled {
+            return Ok(());
+        }
+        if secrets::get(APP_TOKEN)?.is_none() {
+            secrets::set(APP_TOKEN, &secrets::generate_password())?;
+        }
+
+        let tls = match (&settings.tls_cert, &settings.tls_key) {
+            (Some(cert), Some(key)) => {
+                let _ = rustls::crypto::ring::default_provider().install_default();
+                Some(
+                    axum_server::tls_rustls::RustlsConfig::from_pem_file(cert, key)
+                        .await
+                        .context("Failed to load the TLS certificate or key")?,
+                )
+            }
+            _ => None,
+        };
+        if settings.listen_all && tls.is_none() {
+            return Err(PrismarineError::invalid_input(
+                "Accepting connections from other PCs needs a TLS certificate and key",
+            )
+            .into());
+        }
+
+        let host = if settings.listen_all {
+            "0.0.0.0"
+        } else {
+            "127.0.0.1"
+        };
+        let address = format!("{}:{}", host, settings.port);
+        let listener = std::net::TcpListener::bind(&address)
+            .with_context(|| format!("Failed to listen on {}", address))?;
+        listener.set_nonblocking(true)?;
+        println!(
+            "[RestApi] Listening on {} ({})",
+            address,
+            if tls.is_some() { "HTTPS" } else { "HTTP" }
+        );
+
+        let router = Router::new()
+            .route("/api/v1/servers", get(list_servers))
+            .route("/api/v1/servers/{id}", get(get_server))
+            .route("/api/v1/servers/{id}/start", post(start_server))
+            .route("/api/v1/servers/{id}/stop", post(stop_server))
+            .route("/api/v1/servers/{id}/restart", post(restart_server))
+            .route("/api/v1/servers/{id}/command", post(send_command))
+            .route("/api/v1/servers/{id}/logs", get(server_logs))
+            .route("/api/v1/servers/{id}/health", get(server_health))
+            .route("/api/v1/servers/{id}/players", get(online_players))
+            .route("/api/v1/servers/{id}/events", get(event_stream))
+            .route("/api/v1/system", get(system_stats))
+            .layer(axum::middleware::from_fn(throttle_failed_auth))
+            .with_state(state);
+        let service = router.into_make_service_with_connect_info::<SocketAddr>();
+        let handle = match tls.clone() {
+            Some(config) => {
+                let server = axum_server::from_tcp_rustls(listener, config);
+                tokio::spawn(async move {
+                    if let Err(e) = server.serve(service).await {
+                        println!("[RestApi] Stopped: {}", e);
+                    }
+                })
+            }
+            None => {
+                let listener = tokio::net::TcpListener::from_std(listener)?;
+                tokio::spawn(async move {
+                    if let Err(e) = axum::serve(listener, service).await {
+                        println!("[RestApi] Stopped: {}", e);
+                    }
+                })
+            }
+        };
+        *self.listener.lock().unwrap() = Some(Listener {
+            address,
+            tls: tls.is_some(),
+            handle,
+        });
+        Ok(())
+    }
+
+    pub fn stop(&self) {
+        if let Some(listener) = self.listener.lock().unwrap().take() {
+            listener.handle.abort();
+            println!("[RestApi] Stopped listening on {}", listener.address);
+        }
+    }
+
+    pub fn status(&self) -> RestApiStatus {
+        let listener = self.listener.lock().unwrap();
+        RestApiStatus {
+            running: listener.as_ref().is_some_and(|l| !l.handle.is_finished()),
+            address: listener.as_ref().map(|l| l.address.clone()),
+            tls: listener.as_ref().is_some_and(|l| l.tls),
+            token_stored: secrets::get(APP_TOKEN).ok().flatten().is_some(),
+        }
+    }
+}
+
+/// Replace the app token; the new one is only shown here
+pub fn regenerate_token() -> Result<String> {
+    let token = secrets::generate_password();
+    secrets::set(APP_TOKEN, &token)?;
+    println!("[RestApi] Generated a new app token");
+    Ok(token)
+}
+
+fn auth_failures() -> &'static Mutex<HashMap<IpAddr, AuthFailures>> {
+    static FAILURES: OnceLock<Mutex<HashMap<IpAddr, AuthFailures>>> = OnceLock::new();
+    FAILURES.get_or_init(Default::default)
+}
+
+/// Turn away clients with too many recent 401s and count new ones. Only a
+/// successful request clears the count, so other errors do not reset it.
+async fn throttle_failed_auth(
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let ip = peer.ip();
+    let locked_out = auth_failures()
+        .lock()
+        .unwrap()
+        .get(&ip)
+        .is_some_and(|f| f.count >= MAX_AUTH_FAILURES && f.last.elapsed() < AUTH_LOCKOUT);
+    if locked_out {
+        return ApiError(
+            StatusCode::TOO_MANY_REQUESTS,
+            serde_json::json!({
+                "code": "TOO_MANY_REQUESTS",
+                "message": "Too many failed authentication attempts, try again later",
+            }),
+        )
+        .into_response();
+    }
+
+    let response = next.run(request).await;
+    let status = response.status();
+    let mut failures = auth_failures().lock().unwrap();
+    failures.retain(|_, f| f.last.elapsed() < AUTH_LOCKOUT);
+    if status == StatusCode::UNAUTHORIZED {
+        let entry = failures.entry(ip).or_insert(AuthFailures {
+            count: 0,
+            last: Instant::now(),
+        });
+        entry.count += 1;
+        entry.last = Instant::now();
+        if entry.count == MAX_AUTH_FAILURES {
+            println!(
+                "[RestApi] Locking out {} for {}s after {} failed authentications",
+                ip,
+                AUTH_LOCKOUT.as_secs(),
+                MAX_AUTH_FAILURES
+            );
+        }
+    } else if status.is_success() || status == StatusCode::SWITCHING_PROTOCOLS {
+        failures.remove(&ip);
+    }
+    response
+}
+
+async fn authorize(state: &AppState, headers: &HeaderMap) -> std::result::Result<Access, ApiError> {
+    let token = headers
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .ok_or_else(unauthorized)?;
+
+    let app_token = secrets::get(APP_TOKEN).ok().flatten();
+    if app_token
+        .is_some_and(|stored| secrets::constant_time_eq(stored.as_bytes(), token.as_bytes()))
+    {
+        return Ok(Access::All);
+    }
+    let manager = state.server_manager.lock().await.clone();
+    manager
+        .server_for_api_token(token, ApiScope::RemoteApi)
+        .await
+        .map(Access::Server)
+        .ok_or_else(unauthorized)
+}
+
+/// Authorize a request for one server; other servers look missing to a server token
+async fn authorize_server(
+    state: &AppState,
+    headers: &HeaderMap,
+    server_id: &str,
+) -> std::result::Result<(), ApiError> {
+    match authorize(state, headers).await? {
+        Access::Server(id) if id != server_id => {
+            Err(PrismarineError::not_found("Server not found").into())
+        }
+        _ => Ok(()),
+    }
+}
+
+async fn list_servers(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> ApiResult<Vec<crate::server_manager::ServerSummary>> {
+    let access = authorize(&state, &headers).await?;
+    let manager = state.server_manager.lock().await.clone();
+    let servers = manager.query_servers(&Default::default()).await;
+    Ok(Json(match access {
+        Access::All => servers,
+        Access::Server(id) => servers.into_iter().filter(|s| s.id == id).collect(),
+    }))
+}
+
+async fn get_server(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> ApiResult<crate::server_manager::ServerSummary> {
+    authorize_server(&state, &headers, &id).await?;
+    let manager = state.server_manager.lock().await.clone();
+    let server = manager
+        .get_server(&id)
+        .await
+        .ok_or_else(|| PrismarineError::not_found("Server not found"))?;
+    Ok(Json((&server).into()))
+}
+
+async fn start_server(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> ApiResult<()> {
+    authorize_server(&state, &headers, &id).await?;
+    state.start_server(&id).await?;
+    Ok(Json(()))
+}
+
+async fn stop_server(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> ApiResult<()> {
+    authorize_server(&state, &headers, &id).await?;
+    state.stop_server(&id).await?;
+    Ok(Json(()))
+}
+
+async fn restart_server(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> ApiResult<()> {
+    authorize_server(&state, &headers, &id).await?;
+    let manager = state.server_manager.lock().await.clone();
+    manager
+        .restart_server(&id)
+        .await
+        .map_err(PrismarineError::from)?;
+    Ok(Json(()))
+}
+
+#[derive(Deserialize)]
+struct CommandRequest {
+    command: String,
+    /// Run a command the console safety would otherwise block
+    #[serde(default)]
+    confirm: bool,
+}
+
+async fn send_command(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<CommandRequest>,
+) -> ApiResult<()> {
+    authorize_server(&state, &headers, &id).await?;
+    let manager = state.server_manager.lock().await.clone();
+    manager
+        .send_console_command(&id, &request.command, request.confirm)
+        .await
+        .map_err(PrismarineError::from)?;
+    Ok(Json(()))
+}
+
+#[derive(Deserialize)]
+struct LogQuery {
+    lines: Option<usize>,
+}
+
+async fn server_logs(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<LogQuery>,
+    headers: HeaderMap,
+) -> ApiResult<Vec<String>> {
+    authorize_server(&state, &headers, &id).await?;
+    let lines = query.lines.unwrap_or(DEFAULT_LOG_LINES).min(5000);
+    Ok(Json(state.server_logs(&id, lines).await?))
+}
+
+async fn server_health(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> ApiResult<crate::health::ServerHealth> {
+    authorize_server(&state, &headers, &id).await?;
+    let manager = state.server_manager.lock().await.clone();
+    Ok(Json(
+        crate::health::check(&manager, &state.monitor, &id)
+            .await
+            .map_err(PrismarineError::from)?,
+    ))
+}
+
+async fn online_players(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> ApiResult<Vec<String>> {
+    authorize_server(&state, &headers, &id).await?;
+    let manager = state.server_manager.lock().await.clone();
+    if manager.get_server(&id).await.is_none() {
+        return Err(PrismarineError::not_found("Server not found").into());
+    }
+    Ok(Json(
+        manager
+            .online_players(&id)
+            .await
+            .map_err(PrismarineError::from)?,
+    ))
+}
+
+async fn system_stats(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> ApiResult<crate::monitor::SystemStats> {
+    match authorize(&state, &headers).await? {
+        Access::All => Ok(Json(state.monitor.lock().unwrap().get_system_stats())),
+        Access::Server(_) => Err(forbidden()),
+    }
+}
+
+/// Message sent over the events socket
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamEvent {
+    Console {
+        line: String,
+    },
+    Status(StatusChange),
+    Metrics {
+        cpu_usage: f32,
+        memory_used: u64,
+        players: String,
+    },
+}
+
+async fn event_stream(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    mut headers: HeaderMap,
+    mut ws: WebSocketUpgrade,
+) -> std::result::Result<Response, ApiError> {
+    let protocol = headers
+        .get(header::SEC_WEBSOCKET_PROTOCOL)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .find(|p| p.starts_with("bearer."))
+        })
+        .map(str::to_string);
+    if let Some(protocol) = protocol.filter(|_| !headers.contains_key(header::AUTHORIZATION)) {
+        let token = &protocol["bearer.".len()..];
+        let value =
+            HeaderValue::from_str(&format!("Bearer {}", token)).map_err(|_| unauthorized())?;
+        headers.insert(header::AUTHORIZATION, value);
+        // The browser drops the connection unless the subprotocol is echoed
+        ws = ws.protocols([protocol]);
+    }
+    authorize_server(&state, &headers, &id).await?;
+    Ok(ws.on_upgrade(move |socket| stream_events(socket, state, id)))
+}
+
+async fn send_event(socket: &mut WebSocket, event: &StreamEvent) -> Result<()> {
+    let text = serde_json::to_string(event)?;
+    socket.send(Message::Text(text.into())).await?;
+    Ok(())
+}
+
+/// Complete lines appended to latest.log since `position`, which is moved past
+/// them. Only the new bytes are read. The server starts a new latest.log on
+/// every start, a shorter file is read from the beginning.
+async fn read_new_log_lines(server_path: &std::path::Path, position: &mut u64) -> Vec<String> {
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+    let Ok(mut file) = tokio::fs::File::open(server_path.join("logs").join("latest.log")).await
+    else {
+        return Vec::new();
+    };
+    let len = file.metadata().await.map(|m| m.len()).unwrap_or(0);
+    if len < *position {
+        *position = 0;
+    }
+    if len == *position
+        || file
+            .seek(std::io::SeekFrom::Start(*position))
+            .await
+            .is_err()
+    {
+        return Vec::new();
+    }
+    let mut appended = Vec::new();
+    if (&mut file)
+        .take(STREAM_LOG_CHUNK)
+        .read_to_end(&mut appended)
+        .await
+        .is_err()
+    {
+        return Vec::new();
+    }
+    let Some(end) = appended.iter().rposition(|&b| b == b'\n') else {
+        if appended.len() as u64 == STREAM_LOG_CHUNK {
+            *position += STREAM_LOG_CHUNK; // A single huge line, skip it
+        }
+        return Vec::new(); // Wait for the rest of a line being written
+    };
+    *position += end as u64 + 1;
+    String::from_utf8_lossy(&appended[..end])
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+async fn stream_events(mut socket: WebSocket, state: AppState, server_id: String) {
+    let manager = state.server_manager.lock().await.clone();
+    let Some(server) = manager.get_server(&server_id).await else {
+        return;
+    };
+    let mut status = crate::server_manager::subscribe_status();
+    let mut position = Monitor::log_position(&server.path).await;
+    let backlog = state
+        .server_logs(&server_id, STREAM_BACKLOG)
+        .await
+        .unwrap_or_default();
+    for line in backlog {
+        if send_event(&mut socket, &StreamEvent::Console { line })
+            .await
+            .is_err()
+        {
+            return;
+        }
+    }
+
+    let mut log_tick = tokio::time::interval(STREAM_LOG_POLL);
+    let mut metrics_tick = tokio::time::interval(STREAM_METRICS_INTERVAL);
+    loop {
+        let events = tokio::select! {
+            change = status.recv() => match change {
+                Ok(change) if change.server_id == server_id => vec![StreamEvent::Status(change)],
+                Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return,
+            },
+            _ = log_tick.tick() => read_new_log_lines(&server.path, &mut position)
+                .await
+                .into_iter()
+                .map(|line| StreamEvent::Console { line })
+                .collect(),
+            _ = metrics_tick.tick() => {
+                let Some(current) = manager.get_server(&server_id).await else {
+                    return; // Deleted
+                };
+                let stats = current
+                    .pid
+                    .filter(|_| current.status == ServerStatus::Running)
+                    .and_then(|pid| {
+                        state
+                            .monitor
+                            .lock()
+                            .unwrap()
+                            .get_server_stats(pid)
+                    });
+                stats
+                    .map(|stats| StreamEvent::Metrics {
+                        cpu_usage: stats.cpu_usage,
+                        memory_used: stats.memory_used,
+                        players: current.players.clone(),
+                    })
+                    .into_iter()
+                    .collect()
+            },
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                _ => continue, // The stream is one way, axum answers pings
+            },
+        };
+        for event in &events {
+            if send_event(&mut socket, event).await.is_err() {
+                return;
+            }
+        }
+    }
+}
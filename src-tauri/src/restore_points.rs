@@ -0,0 +1,240 @@
+// Restore points
+// Copies of a server's software (server.jar, plugin/mod jars) and config files
+// taken before the manager changes them. Worlds are not included. Stored per
+// server in the app data directory, newest MAX_RESTORE_POINTS kept.
+
+use crate::error::OrNotFound;
+use crate::server_manager::ServerType;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const MAX_RESTORE_POINTS: usize = 3;
+const MANIFEST: &str = "restore-point.json";
+/// Id of the restore point taken before changes that have not booted successfully yet
+const PENDING: &str = "pending";
+const LAST_ROLLBACK: &str = "last-rollback.json";
+/// Top-level server files that hold configuration
+const CONFIG_EXTENSIONS: &[&str] = &["yml", "yaml", "properties", "json", "toml", "txt"];
+/// Player lists the server keeps up to date itself; rolling them back would
+/// undo bans and whitelist changes made since
+const PLAYER_LISTS: &[&str] = &[
+    "whitelist.json",
+    "ops.json",
+    "banned-players.json",
+    "banned-ips.json",
+    "usercache.json",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestorePoint {
+    pub id: String,
+    pub created_at: u64,
+    pub reason: String,  // What was about to change, e.g. "Install ViaVersion"
+    pub version: String, // Minecraft version at the time
+    #[serde(default)]
+    pub server_type: Option<ServerType>,
+    pub files: Vec<String>, // Relative to the server folder, '/' separated
+}
+
+/// What an automatic rollback restored, shown on the server's detail page
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollbackReport {
+    pub at: u64,
+    pub restore_point: String,
+    pub reason: String,
+    pub version: String,        // Version restored
+    pub failed_version: String, // Version that failed to start
+    pub error: String,
+    pub removed: Vec<String>, // Plugin/mod jars added after the restore point
+    pub replaced: Vec<String>, // Files put back because they had changed
+    pub restarted: bool,
+}
+
+/// Files changed by `restore`
+pub struct Restored {
+    pub point: RestorePoint,
+    pub removed: Vec<String>,
+    pub replaced: Vec<String>,
+}
+
+fn server_dir(server_id: &str) -> PathBuf {
+    crate::paths::get()
+        .data_dir
+        .join("restore-points")
+        .join(server_id)
+}
+
+/// Files a restore point copies: server.jar, top-level config files, config/
+/// and the jars directly inside the plugin/mod folder
+fn collect_files(server_path: &Path, extensions_dir: &str) -> Vec<String> {
+    let mut files = Vec::new();
+    let list = |dir: &Path| -> Vec<PathBuf> {
+        std::fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .collect()
+    };
+    let has_extension = |path: &Path, extensions: &[&str]| {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| extensions.contains(&ext))
+    };
+
+    for path in list(server_path) {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if PLAYER_LISTS.contains(&&*name) {
+            continue;
+        }
+        if name == "server.jar" || has_extension(&path, CONFIG_EXTENSIONS) {
+            files.push(name.to_string());
+        }
+    }
+    for path in list(&server_path.join(extensions_dir)) {
+        if has_extension(&path, &["jar"]) {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            files.push(format!("{}/{}", extensions_dir, name));
+        }
+    }
+    let mut dirs = vec![server_path.join("config")];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir).into_iter().flatten().flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if let Ok(relative) = path.strip_prefix(server_path) {
+                files.push(relative.to_string_lossy().replace('\\', "/"));
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+pub fn list(server_id: &str) -> Vec<RestorePoint> {
+    let mut points: Vec<RestorePoint> = std::fs::read_dir(server_dir(server_id))
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let manifest = std::fs::read_to_string(entry.path().join(MANIFEST)).ok()?;
+            serde_json::from_str(&manifest).ok()
+        })
+        .collect();
+    points.sort_by_key(|point| std::cmp::Reverse(point.created_at));
+    points
+}
+
+pub fn create(
+    server_id: &str,
+    server_path: &Path,
+    extensions_dir: &str,
+    server_type: &ServerType,
+    version: &str,
+    reason: &str,
+) -> Result<RestorePoint> {
+    let created_at = chrono::Utc::now().timestamp() as u64;
+    let point = RestorePoint {
+        id: format!("{}-{}", created_at, &uuid::Uuid::new_v4().to_string()[..8]),
+        created_at,
+        reason: reason.to_string(),
+        version: version.to_string(),
+        server_type: Some(server_type.clone()),
+        files: collect_files(server_path, extensions_dir),
+    };
+
+    let dir = server_dir(server_id).join(&point.id);
+    for file in &point.files {
+        let dest = dir.join("files").join(file);
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(server_path.join(file), &dest)
+            .with_context(|| format!("Failed to copy {} into the restore point", file))?;
+    }
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join(MANIFEST), serde_json::to_string_pretty(&point)?)?;
+
+    for old in list(server_id).into_iter().skip(MAX_RESTORE_POINTS) {
+        let _ = std::fs::remove_dir_all(server_dir(server_id).join(&old.id));
+    }
+    Ok(point)
+}
+
+/// Put the files of a restore point back; plugin/mod jars added since are removed
+pub fn restore(
+    server_id: &str,
+    point_id: &str,
+    server_path: &Path,
+    extensions_dir: &str,
+) -> Result<Restored> {
+    let point = list(server_id)
+        .into_iter()
+        .find(|point| point.id == point_id)
+        .or_not_found("Restore point not found")?;
+    let dir = server_dir(server_id).join(&point.id).join("files");
+
+    let mut removed = Vec::new();
+    for file in collect_files(server_path, extensions_dir) {
+        if file.starts_with(&format!("{}/", extensions_dir)) && !point.files.contains(&file) {
+            std::fs::remove_file(server_path.join(&file))
+                .with_context(|| format!("Failed to remove {}", file))?;
+            removed.push(file);
+        }
+    }
+    let mut replaced = Vec::new();
+    for file in &point.files {
+        let (src, dest) = (dir.join(file), server_path.join(file));
+        if std::fs::read(&dest).ok() == Some(std::fs::read(&src)?) {
+            continue;
+        }
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(&src, &dest).with_context(|| format!("Failed to restore {}", file))?;
+        replaced.push(file.clone());
+    }
+    Ok(Restored {
+        point,
+        removed,
+        replaced,
+    })
+}
+
+pub fn pending(server_id: &str) -> Option<String> {
+    std::fs::read_to_string(server_dir(server_id).join(PENDING))
+        .ok()
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty())
+}
+
+pub fn set_pending(server_id: &str, point_id: &str) -> Result<()> {
+    std::fs::write(server_dir(server_id).join(PENDING), point_id)?;
+    Ok(())
+}
+
+pub fn clear_pending(server_id: &str) {
+    let _ = std::fs::remove_file(server_dir(server_id).join(PENDING));
+}
+
+pub fn last_rollback(server_id: &str) -> Option<RollbackReport> {
+    let json = std::fs::read_to_string(server_dir(server_id).join(LAST_ROLLBACK)).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+pub fn save_rollback(server_id: &str, report: &RollbackReport) -> Result<()> {
+    std::fs::create_dir_all(server_dir(server_id))?;
+    std::fs::write(
+        server_dir(server_id).join(LAST_ROLLBACK),
+        serde_json::to_string_pretty(report)?,
+    )?;
+    Ok(())
+}
+
+/// Remove everything kept for a deleted server
+pub fn remove_all(server_id: &str) {
+    let _ = std::fs::remove_dir_all(server_dir(server_id));
+}
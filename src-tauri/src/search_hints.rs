@@ -0,0 +1,151 @@
+// Search hints
+// Modrinth and Spigot index plugins in English, so a Japanese query finds
+// nothing. Known Japanese terms are turned into the English keywords plugin
+// authors use, and full-width letters into ASCII, to search with alongside
+// the original query.
+
+/// Japanese terms and the English keywords they are searched as. Longer terms
+/// are matched first, so compounds win over their parts.
+const TERMS: &[(&str, &str)] = &[
+    // Administration
+    ("権限", "permissions"),
+    ("パーミッション", "permissions"),
+    ("管理", "admin"),
+    ("荒らし対策", "anti grief"),
+    ("荒らし", "grief"),
+    ("保護", "protection"),
+    ("土地保護", "land claim"),
+    ("土地", "land"),
+    ("ログ", "logging"),
+    ("ロールバック", "rollback"),
+    ("バックアップ", "backup"),
+    ("アンチチート", "anticheat"),
+    ("チート対策", "anticheat"),
+    ("ホワイトリスト", "whitelist"),
+    ("ログイン", "login"),
+    ("認証", "auth"),
+    // Gameplay
+    ("経済", "economy"),
+    ("お金", "economy"),
+    ("ショップ", "shop"),
+    ("お店", "shop"),
+    ("オークション", "auction"),
+    ("テレポート", "teleport"),
+    ("ワープ", "warp"),
+    ("ホーム", "home"),
+    ("スポーン", "spawn"),
+    ("ミニゲーム", "minigame"),
+    ("スキル", "skills"),
+    ("クエスト", "quests"),
+    ("ジョブ", "jobs"),
+    ("職業", "jobs"),
+    ("ペット", "pets"),
+    ("乗り物", "vehicles"),
+    ("家具", "furniture"),
+    ("一括破壊", "tree feller"),
+    ("木こり", "timber"),
+    ("死亡時", "death"),
+    ("墓", "graves"),
+    // World
+    ("ワールド編集", "worldedit"),
+    ("ワールドエディット", "worldedit"),
+    ("ワールドガード", "worldguard"),
+    ("ワールド生成", "world generator"),
+    ("ワールド", "world"),
+    ("地図", "map"),
+    ("マップ", "map"),
+    ("建築", "building"),
+    // Communication
+    ("チャット", "chat"),
+    ("日本語化", "japanese"),
+    ("日本語", "japanese"),
+    ("ローマ字変換", "japanize"),
+    ("翻訳", "translate"),
+    ("ディスコード", "discord"),
+    ("音声", "voice chat"),
+    ("ボイスチャット", "voice chat"),
+    ("スコアボード", "scoreboard"),
+    ("タブリスト", "tab"),
+    ("看板", "signs"),
+    ("ホログラム", "holograms"),
+    // Server
+    ("軽量化", "performance"),
+    ("最適化", "optimization"),
+    ("ラグ", "lag"),
+    ("統計", "stats"),
+    ("プレースホルダー", "placeholderapi"),
+    ("統合版", "geyser"),
+    ("ゲイザー", "geyser"),
+    ("バージョン互換", "viaversion"),
+    ("プロキシ", "proxy"),
+    ("ロビー", "lobby"),
+    ("メニュー", "menu gui"),
+    ("ライブラリ", "library"),
+];
+
+/// Kana are only used by Japanese, unlike kanji which Chinese shares
+fn is_kana(c: char) -> bool {
+    matches!(c, '\u{3041}'..='\u{309F}' | '\u{30A0}'..='\u{30FF}' | '\u{31F0}'..='\u{31FF}')
+}
+
+fn is_japanese(c: char) -> bool {
+    is_kana(c) || matches!(c, '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}' | '々')
+}
+
+/// Full-width ASCII (ＷｏｒｌｄＥｄｉｔ, ＰＶＰ) and the ideographic space as plain ASCII
+fn to_half_width(c: char) -> char {
+    match c {
+        '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+        '\u{3000}' => ' ',
+        _ => c,
+    }
+}
+
+/// English query to search with next to `query`, None when the query has
+/// nothing to translate or no known term
+pub fn translate_query(query: &str) -> Option<String> {
+    let original = query.trim();
+    let query: String = original.chars().map(to_half_width).collect();
+    let query = query.trim();
+    if query == original && !query.chars().any(is_japanese) {
+        return None;
+    }
+
+    let mut terms: Vec<&(&str, &str)> = TERMS.iter().collect();
+    terms.sort_by_key(|(japanese, _)| std::cmp::Reverse(japanese.chars().count()));
+
+    let mut words: Vec<String> = Vec::new();
+    let mut latin = String::new();
+    let mut rest = query;
+    while let Some(c) = rest.chars().next() {
+        if let Some((japanese, english)) = terms.iter().find(|(j, _)| rest.starts_with(j)) {
+            words.push(english.to_string());
+            rest = &rest[japanese.len()..];
+            continue;
+        }
+        // Latin words typed between Japanese ones ("Discord 連携") are kept
+        if c.is_ascii_alphanumeric() {
+            latin.push(c);
+        } else if !latin.is_empty() {
+            words.push(std::mem::take(&mut latin));
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    if !latin.is_empty() {
+        words.push(latin);
+    }
+
+    let mut unique: Vec<String> = Vec::new();
+    for word in words {
+        if !unique.contains(&word) {
+            unique.push(word);
+        }
+    }
+    (!unique.is_empty()).then(|| unique.join(" "))
+}
+
+/// Language of a plugin description when it can be told from the script
+/// ("ja" for text with kana), None for everything else
+pub fn description_language(description: &str) -> Option<String> {
+    description.chars().any(is_kana).then(|| "ja".to_string())
+}
@@ -0,0 +1,220 @@
+// Secrets
+// Credentials (RCON passwords, Velocity forwarding secrets, webhook URLs, API
+// keys, tunnel secrets, notification channel credentials, console bridge
+// passwords) are kept in the OS keyring (Windows Credential Manager, macOS
+// Keychain, Secret Service) instead of our JSON config. When no keyring is
+// available they fall back to secrets.json, readable only by the user.
+// Values stored here must never be printed to the log.
+
+use anyhow::{Context, Result};
+use rand::distributions::{Alphanumeric, DistString};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+const SERVICE: &str = "Prismarine";
+
+/// Discord/Slack webhook used by network failover
+pub const FAILOVER_WEBHOOK: &str = "failover:webhook_url";
+/// bore `--secret` for self-hosted tunnel servers
+pub const BRIDGE_SECRET: &str = "bridge:secret";
+/// playit.gg agent secret (written by the agent after claiming, or set by the user)
+pub const PLAYIT_SECRET: &str = "bridge:playit_secret";
+/// frps `auth.token`
+pub const FRP_TOKEN: &str = "bridge:frp_token";
+/// Token of a named Cloudflare tunnel
+pub const CLOUDFLARED_TOKEN: &str = "bridge:cloudflared_token";
+pub const CURSEFORGE_API_KEY: &str = "curseforge:api_key";
+
+/// Secrets the user may set from the UI (the others are generated by us)
+pub const USER_SECRETS: [&str; 6] = [
+    FAILOVER_WEBHOOK,
+    BRIDGE_SECRET,
+    PLAYIT_SECRET,
+    FRP_TOKEN,
+    CLOUDFLARED_TOKEN,
+    CURSEFORGE_API_KEY,
+];
+
+// Serializes read-modify-write of the fallback file
+static FALLBACK_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StoredSecret {
+    pub name: String,
+    pub stored: bool,
+    pub in_keyring: bool, // false when kept in the fallback file
+}
+
+pub fn rcon_key(server_id: &str) -> String {
+    format!("rcon:{}", server_id)
+}
+
+/// Client API key of the panel hosting a remote server
+pub fn panel_key(server_id: &str) -> String {
+    format!("panel:{}", server_id)
+}
+
+/// Token external integrations use to refer to a server
+pub fn api_token_key(server_id: &str) -> String {
+    format!("api_token:{}", server_id)
+}
+
+/// Webhook URL, bot token or password of a notification channel
+pub fn notify_key(channel_id: &str) -> String {
+    format!("notify:{}", channel_id)
+}
+
+/// Password clients of a server's console bridge log in with
+pub fn console_bridge_key(server_id: &str) -> String {
+    format!("console_bridge:{}", server_id)
+}
+
+pub fn forwarding_key(proxy_id: &str) -> String {
+    format!("forwarding:{}", proxy_id)
+}
+
+/// Pairing code of another instance that receives server transfers
+pub fn transfer_key(target_host: &str) -> String {
+    format!("transfer:{}", target_host)
+}
+
+/// Compare secrets without leaking where they differ through timing
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Random alphanumeric password (32 chars, ~190 bits)
+pub fn generate_password() -> String {
+    Alphanumeric.sample_string(&mut rand::thread_rng(), 32)
+}
+
+pub fn get(name: &str) -> Result<Option<String>> {
+    match keyring::Entry::new(SERVICE, name).and_then(|entry| entry.get_password()) {
+        Ok(value) => Ok(Some(value)),
+        // Not in the keyring (or no keyring at all): check the fallback file
+        Err(_) => Ok(read_fallback()?.remove(name)),
+    }
+}
+
+pub fn set(name: &str, value: &str) -> Result<()> {
+    match keyring::Entry::new(SERVICE, name).and_then(|entry| entry.set_password(value)) {
+        Ok(()) => remove_fallback(name),
+        Err(e) => {
+            println!(
+                "[Secrets] OS keyring unavailable ({}), storing {} in the fallback file",
+                e, name
+            );
+            let _guard = FALLBACK_LOCK.lock().unwrap();
+            let mut secrets = read_fallback()?;
+            secrets.insert(name.to_string(), value.to_string());
+            write_fallback(&secrets)
+        }
+    }
+}
+
+pub fn delete(name: &str) -> Result<()> {
+    if let Ok(entry) = keyring::Entry::new(SERVICE, name) {
+        match entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => {}
+            Err(e) => println!(
+                "[Secrets] Failed to remove {} from the keyring: {}",
+                name, e
+            ),
+        }
+    }
+    remove_fallback(name)
+}
+
+/// Remove every secret generated for a server
+pub fn delete_server_secrets(server_id: &str) -> Result<()> {
+    delete(&rcon_key(server_id))?;
+    delete(&panel_key(server_id))?;
+    delete(&api_token_key(server_id))?;
+    delete(&console_bridge_key(server_id))?;
+    delete(&forwarding_key(server_id))
+}
+
+/// Which user secrets are stored and where (values are never returned)
+pub fn list_user_secrets() -> Vec<StoredSecret> {
+    let fallback = read_fallback().unwrap_or_default();
+    USER_SECRETS
+        .iter()
+        .map(|name| {
+            let in_keyring = keyring::Entry::new(SERVICE, name)
+                .and_then(|entry| entry.get_password())
+                .is_ok();
+            StoredSecret {
+                name: name.to_string(),
+                stored: in_keyring || fallback.contains_key(*name),
+                in_keyring,
+            }
+        })
+        .collect()
+}
+
+pub fn store_rcon_password(server_id: &str, password: &str) -> Result<()> {
+    set(&rcon_key(server_id), password).context("Failed to store the RCON password")
+}
+
+pub fn has_rcon_password(server_id: &str) -> bool {
+    get(&rcon_key(server_id)).ok().flatten().is_some()
+}
+
+pub fn delete_rcon_password(server_id: &str) -> Result<()> {
+    delete(&rcon_key(server_id)).context("Failed to remove the RCON password")
+}
+
+fn fallback_path() -> PathBuf {
+    crate::paths::get().config_file("secrets.json")
+}
+
+fn read_fallback() -> Result<HashMap<String, String>> {
+    let path = fallback_path();
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    let data = std::fs::read_to_string(&path).context("Failed to read secrets.json")?;
+    // Never treat a damaged file as empty, the next write would drop every secret
+    serde_json::from_str(&data).context("secrets.json is damaged")
+}
+
+fn write_fallback(secrets: &HashMap<String, String>) -> Result<()> {
+    let path = fallback_path();
+    if secrets.is_empty() {
+        let _ = std::fs::remove_file(&path);
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    // Written to a new file that is private from the start, then swapped in
+    let temp_path = path.with_extension("json.tmp");
+    let _ = std::fs::remove_file(&temp_path);
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(&temp_path)
+        .context("Failed to create secrets.json")?;
+    std::io::Write::write_all(&mut file, serde_json::to_string_pretty(secrets)?.as_bytes())?;
+    file.sync_all()?;
+    drop(file);
+    std::fs::rename(&temp_path, &path)?;
+    Ok(())
+}
+
+fn remove_fallback(name: &str) -> Result<()> {
+    let _guard = FALLBACK_LOCK.lock().unwrap();
+    let mut secrets = read_fallback()?;
+    if secrets.remove(name).is_some() {
+        write_fallback(&secrets)?;
+    }
+    Ok(())
+}
@@ -1,10 +1,25 @@
+use crate::download::JarKind;
+use crate::error::{OrNotFound, PrismarineError};
+use crate::gamerules::{
+    GameRules, GameRulesStatus, WorldSettings, WorldSettingsResult, WorldSettingsStatus,
+};
+use crate::install_queue::InstallQueue;
+use crate::instance_lock::LockHolder;
+use crate::malware_scan::MalwareScanner;
+use crate::paths::AppPaths;
+use crate::plugin_compat::PluginCompatibility;
+use crate::port_manager::PortManager;
+use crate::remote::{RemoteHost, SyncReport};
+use crate::reports::{ReportPeriod, ReportSchedule, StatsReport};
+use crate::resource_pack::{PackHosting, ResourcePackSettings};
+use crate::restore_points::{RestorePoint, RollbackReport};
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use serde_yaml;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
 use tokio::process::{Child, Command};
@@ -23,6 +38,120 @@ impl Default for RestartType {
     }
 }
 
+/// Garbage collector tuning a server starts with
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum JvmProfile {
+    /// Aikar's G1 flags, the long-standing recommendation for Minecraft
+    #[default]
+    Aikar,
+    /// Low-pause collector for large heaps
+    Zgc,
+    Shenandoah,
+    /// No GC flags, the JVM picks its own defaults
+    Vanilla,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JvmProfileInfo {
+    pub id: JvmProfile,
+    pub label: &'static str,
+    pub min_java: u8,
+}
+
+impl JvmProfile {
+    pub const ALL: [JvmProfile; 4] = [
+        JvmProfile::Aikar,
+        JvmProfile::Zgc,
+        JvmProfile::Shenandoah,
+        JvmProfile::Vanilla,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            JvmProfile::Aikar => "Aikar (G1)",
+            JvmProfile::Zgc => "ZGC (large heaps, Java 17+)",
+            JvmProfile::Shenandoah => "Shenandoah",
+            JvmProfile::Vanilla => "Vanilla defaults",
+        }
+    }
+
+    /// Oldest Java whose JVM accepts the profile's flags without experimental unlocks
+    pub fn min_java(self) -> u8 {
+        match self {
+            JvmProfile::Aikar | JvmProfile::Vanilla => 8,
+            JvmProfile::Zgc | JvmProfile::Shenandoah => 17,
+        }
+    }
+
+    /// GC flags of the profile, memory settings excluded
+    fn flags(self, max_memory: &str, java: Option<u8>) -> Vec<String> {
+        let flags: Vec<&str> = match self {
+            JvmProfile::Aikar => {
+                let mut flags = vec![
+                    // G1GC garbage collector (optimal for Minecraft)
+                    "-XX:+UseG1GC",
+                    "-XX:+ParallelRefProcEnabled",
+                    "-XX:MaxGCPauseMillis=200",
+                    "-XX:+UnlockExperimentalVMOptions",
+                    "-XX:+DisableExplicitGC",
+                ];
+                // G1NewSizePercent and G1ReservePercent depend on the heap size
+                if let Some(mem_mb) = parse_memory_mb(max_memory) {
+                    if mem_mb >= 12288 {
+                        // 12GB+
+                        flags.extend([
+                            "-XX:G1NewSizePercent=40",
+                            "-XX:G1MaxNewSizePercent=50",
+                            "-XX:G1ReservePercent=15",
+                            "-XX:InitiatingHeapOccupancyPercent=15",
+                        ]);
+                    } else {
+                        flags.extend([
+                            "-XX:G1NewSizePercent=30",
+                            "-XX:G1MaxNewSizePercent=40",
+                            "-XX:G1ReservePercent=20",
+                            "-XX:InitiatingHeapOccupancyPercent=20",
+                        ]);
+                    }
+                }
+                flags.extend([
+                    "-XX:+AlwaysPreTouch",
+                    "-XX:G1HeapWastePercent=5",
+                    "-XX:G1MixedGCCountTarget=4",
+                    "-XX:G1MixedGCLiveThresholdPercent=90",
+                    "-XX:G1RSetUpdatingPauseTimePercent=5",
+                    "-XX:SurvivorRatio=32",
+                    "-XX:+PerfDisableSharedMem",
+                    "-XX:MaxTenuringThreshold=1",
+                ]);
+                flags
+            }
+            JvmProfile::Zgc => {
+                let mut flags = vec![
+                    "-XX:+UseZGC",
+                    "-XX:+AlwaysPreTouch",
+                    "-XX:+DisableExplicitGC",
+                    "-XX:+PerfDisableSharedMem",
+                ];
+                // Generational mode is opt-in on 21-22 and the only mode afterwards
+                if matches!(java, Some(21..=22)) {
+                    flags.push("-XX:+ZGenerational");
+                }
+                flags
+            }
+            JvmProfile::Shenandoah => vec![
+                "-XX:+UseShenandoahGC",
+                "-XX:+AlwaysPreTouch",
+                "-XX:+DisableExplicitGC",
+                "-XX:+ParallelRefProcEnabled",
+                "-XX:+PerfDisableSharedMem",
+            ],
+            JvmProfile::Vanilla => Vec::new(),
+        };
+        flags.into_iter().map(str::to_string).collect()
+    }
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ServerInfo {
     pub id: String,
@@ -38,7 +167,9 @@ pub struct ServerInfo {
     #[serde(default = "default_min_memory")]
     pub min_memory: String,
     #[serde(default)]
-    pub players: String, // e.g. "0/20"
+    pub players: String, // e.g. "0/20", from the server list ping while running
+    #[serde(default = "default_eula_accepted")]
+    pub eula_accepted: bool, // Minecraft EULA agreed to by the owner
     #[serde(default)]
     pub auto_restart: bool,
     #[serde(default = "default_restart_interval")]
@@ -49,1959 +180,9528 @@ pub struct ServerInfo {
     pub restart_schedule: Option<String>, // "HH:MM:SS"
     #[serde(default)]
     pub time_zone: Option<String>, // e.g. "Asia/Tokyo"
+    #[serde(default = "default_restart_warnings")]
+    pub restart_warnings: Vec<u64>, // seconds before an auto-restart to warn players
     #[serde(default)]
     pub last_start_time: Option<u64>,
+    #[serde(default)]
+    pub console_safety: bool, // Block dangerous console commands unless confirmed
+    #[serde(default)]
+    pub pinned_commands: Vec<PinnedCommand>,
+    #[serde(default)]
+    pub recent_commands: Vec<String>, // Newest first
+    #[serde(default)]
+    pub dynamic_view_distance: Option<DynamicViewDistance>,
+    #[serde(default)]
+    pub scheduled_tasks: Vec<ScheduledTask>,
+    #[serde(default)]
+    pub console_bridge: Option<ConsoleBridgeConfig>,
+    #[serde(default)]
+    pub rcon: Option<RconSettings>,
+    /// Map the server's ports on the router while it runs (see `public_ports`)
+    #[serde(default)]
+    pub auto_open_port: bool,
+    /// Started when the app (or the headless core) launches
+    #[serde(default)]
+    pub autostart: bool,
+    #[serde(default)]
+    pub tags: Vec<String>, // User labels for filtering the server list
+    /// Server group started and stopped together, e.g. a proxy and its backends
+    #[serde(default)]
+    pub group: Option<String>,
+    #[serde(default)]
+    pub web_map: Option<WebMapSettings>,
+    /// Bridge tunnel kept up while the server runs
+    #[serde(default)]
+    pub tunnel: Option<ServerTunnel>,
+    /// UDP tunnel for Geyser's Bedrock port, next to `tunnel`
+    #[serde(default)]
+    pub bedrock_tunnel: Option<ServerTunnel>,
+    /// Relay Bedrock (UDP) on the Java port number, see udp_relay.rs
+    #[serde(default)]
+    pub bedrock_relay: bool,
+    #[serde(default)]
+    pub announcements: Option<AnnouncementConfig>,
+    /// Java executable used instead of the one picked for the Minecraft version
+    #[serde(default)]
+    pub java_path_override: Option<String>,
+    /// JVM flags replacing the profile's flags (-Xmx/-Xms still come from the memory settings)
+    #[serde(default)]
+    pub custom_jvm_args: Option<Vec<String>>,
+    #[serde(default)]
+    pub jvm_profile: JvmProfile,
+    /// Hosted elsewhere, files go over SFTP and power through the host's panel
+    #[serde(default)]
+    pub remote: Option<RemoteHost>,
+    #[serde(default)]
+    pub resource_pack: Option<ResourcePackSettings>,
+    /// Statistics reports posted to the notification webhook
+    #[serde(default)]
+    pub stats_reports: ReportSchedule,
+    #[serde(default)]
+    pub api_token: Option<ApiTokenInfo>,
+    /// Damaged region files found after a forced kill, until the user dismisses it
+    #[serde(default)]
+    pub region_warning: Option<crate::region_check::RegionCheckReport>,
+    /// Download the jar came from, what a repair fetches again. None for jars
+    /// built locally (Spigot, Forge) and servers installed before it was kept.
+    #[serde(default)]
+    pub installed_build: Option<InstalledBuild>,
 }
 
-fn default_restart_interval() -> u64 {
-    86400 // 24 hours
+/// The exact server jar a server runs, down to the build
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstalledBuild {
+    pub url: String,
+    #[serde(default)]
+    pub cached: Option<crate::jar_cache::CachedBuild>, // Paper and Purpur go through the jar cache
 }
 
-fn default_min_memory() -> String {
-    "1G".to_string()
+impl ServerInfo {
+    /// A stopped server with default settings
+    fn new(
+        id: String,
+        name: String,
+        path: PathBuf,
+        server_type: ServerType,
+        version: String,
+    ) -> Self {
+        ServerInfo {
+            id,
+            name,
+            version,
+            server_type,
+            port: 25565,
+            max_memory: "2G".to_string(),
+            min_memory: "2G".to_string(),
+            status: ServerStatus::Stopped,
+            path,
+            pid: None,
+            players: "0/20".to_string(),
+            eula_accepted: false,
+            auto_restart: false,
+            restart_interval: 86400,
+            restart_type: RestartType::Interval,
+            restart_schedule: None,
+            time_zone: None,
+            restart_warnings: default_restart_warnings(),
+            last_start_time: None,
+            console_safety: false,
+            pinned_commands: Vec::new(),
+            recent_commands: Vec::new(),
+            dynamic_view_distance: None,
+            scheduled_tasks: Vec::new(),
+            console_bridge: None,
+            rcon: None,
+            auto_open_port: false,
+            autostart: false,
+            tags: Vec::new(),
+            group: None,
+            web_map: None,
+            tunnel: None,
+            bedrock_tunnel: None,
+            bedrock_relay: false,
+            announcements: None,
+            java_path_override: None,
+            custom_jvm_args: None,
+            jvm_profile: JvmProfile::default(),
+            remote: None,
+            resource_pack: None,
+            stats_reports: ReportSchedule::default(),
+            api_token: None,
+            region_warning: None,
+            installed_build: None,
+        }
+    }
+
+    /// Copy for another machine. Settings tied to this one (Java path, JVM
+    /// flags, autostart, the remote host) and those whose secrets live in this
+    /// machine's store (API token, RCON, console bridge password) are reset.
+    pub fn portable(&self) -> ServerInfo {
+        ServerInfo {
+            status: ServerStatus::Stopped,
+            pid: None,
+            java_path_override: None,
+            custom_jvm_args: None,
+            autostart: false,
+            remote: None,
+            api_token: None,
+            rcon: None, // Enabling it again generates a new password
+            console_bridge: self
+                .console_bridge
+                .clone()
+                .map(|bridge| ConsoleBridgeConfig {
+                    password: String::new(),
+                    ..bridge
+                }),
+            region_warning: None,
+            ..self.clone()
+        }
+    }
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub struct OpEntry {
-    pub uuid: String,
-    pub name: String,
-    pub level: i32,
-    #[serde(rename = "bypassesPlayerLimit")]
-    pub bypasses_player_limit: bool,
+pub struct ServerTunnel {
+    pub provider: crate::tunnels::TunnelKind,
+    #[serde(default)]
+    pub remote_server: Option<String>,
+    /// Public address reported by the tunnel, None while it is down
+    #[serde(default)]
+    pub address: Option<String>,
 }
 
-/// Parse memory string (e.g., "4G", "2048M") to megabytes
-fn parse_memory_mb(memory: &str) -> Option<u64> {
-    let memory = memory.trim().to_uppercase();
-    if memory.ends_with('G') {
-        memory[..memory.len() - 1]
-            .parse::<u64>()
-            .ok()
-            .map(|g| g * 1024)
-    } else if memory.ends_with('M') {
-        memory[..memory.len() - 1].parse::<u64>().ok()
-    } else {
-        memory.parse::<u64>().ok()
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum WebMapPlugin {
+    BlueMap,
+    Dynmap,
+}
+
+impl WebMapPlugin {
+    fn default_port(self) -> u16 {
+        match self {
+            WebMapPlugin::BlueMap => 8100,
+            WebMapPlugin::Dynmap => 8123,
+        }
+    }
+
+    fn modrinth_id(self) -> &'static str {
+        match self {
+            WebMapPlugin::BlueMap => "bluemap",
+            WebMapPlugin::Dynmap => "dynmap",
+        }
     }
 }
 
-#[derive(Debug, Clone, serde::Serialize)]
-pub struct PluginSearchResult {
-    pub id: String,
-    pub name: String,
-    pub description: String,
-    pub author: String,
-    pub icon_url: Option<String>,
-    pub source: String, // "Modrinth" or "Spigot"
-    pub external_url: String,
-    pub download_url: Option<String>,
+/// Live web map installed through `install_web_map`
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct WebMapSettings {
+    pub plugin: WebMapPlugin,
+    pub port: u16,
+    pub public: bool, // Opened on the router together with the game port (auto_open_port)
 }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq)]
-pub enum ServerType {
-    Vanilla,
-    Paper,
-    Spigot,
-    Forge,
-    Fabric,
-    Mohist,
-    Taiyitist,
-    Purpur,
-    Banner,
-    BungeeCord,
-    Velocity,
-    Waterfall,
+/// The server's own RCON, enabled for the manager. The password lives in the
+/// OS keyring (see secrets.rs) and in server.properties, never in our config.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RconSettings {
+    pub enabled: bool,
+    pub port: u16,
+    #[serde(default)]
+    pub localhost_only: bool, // Other machines are blocked by a firewall rule
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ProxyServerEntry {
-    pub name: String,
-    pub address: String,
+#[derive(Debug, Clone, Serialize)]
+pub struct RconStatus {
+    pub enabled: bool,
+    pub port: u16,
+    pub password_stored: bool, // Present in the OS keyring
+    /// Vanilla RCON listens on server-ip (all interfaces when empty) and has no
+    /// bind setting of its own, so it is only local when server-ip is loopback
+    /// or a firewall rule blocks other machines
+    pub localhost_only: bool,
+    pub restart_required: bool, // Changes apply on the next start
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub enum ServerStatus {
-    Stopped,
-    Starting,
-    Running,
-    Stopping,
+const DEFAULT_RCON_PORT: u16 = 25575;
+
+/// server-ip values that make the server listen on this computer only
+fn is_loopback_ip(server_ip: &str) -> bool {
+    matches!(server_ip.trim(), "127.0.0.1" | "localhost" | "::1")
 }
 
-pub struct ServerManager {
-    servers: Arc<Mutex<HashMap<String, ServerInfo>>>,
-    processes: Arc<std::sync::Mutex<HashMap<String, Child>>>,
-    base_path: PathBuf,
+/// What an integration may do with a server's API token
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ApiScope {
+    Webhooks,  // Incoming webhooks (start, stop, announcements)
+    RemoteApi, // The remote control API
+    Agent,     // A host agent managing the server
 }
 
-impl ServerManager {
-    pub fn new(base_path: PathBuf) -> Self {
+/// A server's API token as kept in our config. The token itself is in the OS
+/// keyring (see secrets.rs) and only shown once, when it is issued.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ApiTokenInfo {
+    pub scopes: Vec<ApiScope>,
+    pub issued_at: u64,
+    pub hint: String, // Last 4 characters, to tell tokens apart
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IssuedApiToken {
+    pub token: String,
+    pub info: ApiTokenInfo,
+}
+
+/// Local RCON-compatible listener that forwards commands to the server console
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ConsoleBridgeConfig {
+    pub enabled: bool,
+    pub port: u16, // Bound on 127.0.0.1 only
+    #[serde(default)]
+    pub password: String, // Generated when left empty
+}
+
+impl Default for ConsoleBridgeConfig {
+    fn default() -> Self {
         Self {
-            servers: Arc::new(Mutex::new(HashMap::new())),
-            processes: Arc::new(std::sync::Mutex::new(HashMap::new())),
-            base_path,
+            enabled: false,
+            port: 25585,
+            password: String::new(),
         }
     }
+}
 
-    /// Save servers to JSON file
-    pub async fn save_servers(&self, config_path: &Path) -> Result<()> {
-        let servers = self.servers.lock().await;
-        let server_list: Vec<ServerInfo> = servers.values().cloned().collect();
+/// A console command run periodically (Interval) or daily at a fixed time (Schedule)
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ScheduledTask {
+    #[serde(default)]
+    pub id: String, // Empty for new tasks
+    pub name: String,
+    pub command: String, // One console command per line
+    pub enabled: bool,
+    pub schedule_type: RestartType,
+    #[serde(default)]
+    pub interval: u64, // seconds
+    #[serde(default)]
+    pub schedule: Option<String>, // "HH:MM"
+    #[serde(default)]
+    pub time_zone: Option<String>, // e.g. "Asia/Tokyo"
+    #[serde(default)]
+    pub last_run: Option<u64>,
+}
 
-        let content = serde_json::to_string_pretty(&server_list)?;
+/// Messages broadcast in turn while the server is running
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct AnnouncementConfig {
+    pub enabled: bool,
+    pub messages: Vec<String>,
+    pub interval: u64, // seconds between two announcements
+    #[serde(default)]
+    pub prefix: String, // e.g. "[お知らせ] "
+    #[serde(default)]
+    pub next_index: usize,
+    #[serde(default)]
+    pub last_sent: Option<u64>,
+}
 
-        if let Some(parent) = config_path.parent() {
-            fs::create_dir_all(parent).await?;
-        }
+/// Adjust view/simulation distance based on the number of online players
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DynamicViewDistance {
+    pub enabled: bool,
+    /// Highest `min_players` that is <= the online count wins
+    pub tiers: Vec<ViewDistanceTier>,
+    /// Console command of a view distance plugin that applies the distances live.
+    /// `{view}` and `{simulation}` are replaced with the tier values. Vanilla and
+    /// Paper have no such command: without one the tier is only written to
+    /// server.properties and takes effect on the next start.
+    #[serde(default)]
+    pub command: Option<String>,
+    #[serde(default)]
+    pub applied_tier: Option<u32>, // min_players of the tier currently applied
+    #[serde(default)]
+    pub pending_tier: Option<u32>, // Written to server.properties, applies after a restart
+}
 
-        fs::write(config_path, content).await?;
-        Ok(())
-    }
+/// Default of older versions, a command no server software provides
+const LEGACY_VIEW_DISTANCE_COMMAND: &str = "viewdistance {view} {simulation}";
 
-    /// Load servers from JSON file
-    pub async fn load_servers(&self, config_path: &Path) -> Result<()> {
-        if config_path.exists() {
-            let content = fs::read_to_string(config_path).await?;
-            let server_list: Vec<ServerInfo> = serde_json::from_str(&content)?;
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ViewDistanceTier {
+    pub min_players: u32,
+    pub view_distance: u32,
+    pub simulation_distance: u32,
+}
 
-            let mut servers = self.servers.lock().await;
-            for server in server_list {
-                servers.insert(server.id.clone(), server);
-            }
+impl Default for DynamicViewDistance {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            tiers: vec![
+                ViewDistanceTier {
+                    min_players: 0,
+                    view_distance: 12,
+                    simulation_distance: 10,
+                },
+                ViewDistanceTier {
+                    min_players: 5,
+                    view_distance: 10,
+                    simulation_distance: 8,
+                },
+                ViewDistanceTier {
+                    min_players: 15,
+                    view_distance: 8,
+                    simulation_distance: 6,
+                },
+            ],
+            command: None,
+            applied_tier: None,
+            pending_tier: None,
         }
-        Ok(())
     }
+}
 
-    pub async fn create_server(
-        &self,
-        name: String,
-        version: String,
-        server_type: ServerType,
-        port: u16,
-        max_memory: String,
-    ) -> Result<ServerInfo> {
-        let id = uuid::Uuid::new_v4().to_string();
-        let server_path = self.base_path.join(&id);
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq)]
+pub struct PinnedCommand {
+    pub label: String,
+    pub command: String, // One console command per line (multi-line = macro)
+}
 
-        // Create server directory
-        fs::create_dir_all(&server_path)
-            .await
-            .context("Failed to create server directory")?;
+const MAX_RECENT_COMMANDS: usize = 20;
 
-        // Download server JAR
-        self.download_server_jar(&server_path, &server_type, &version)
-            .await?;
+fn default_restart_interval() -> u64 {
+    86400 // 24 hours
+}
 
-        // Create default server.properties
-        self.create_default_properties(&server_path, port).await?;
+/// Servers saved before the consent flow had the EULA accepted at creation
+fn default_eula_accepted() -> bool {
+    true
+}
 
-        // Accept EULA
-        fs::write(server_path.join("eula.txt"), "eula=true").await?;
+/// Forge-based servers, whose loader limits the usable Java versions
+fn is_forge_based(server_type: &ServerType) -> bool {
+    matches!(server_type, ServerType::Forge | ServerType::Mohist)
+}
 
-        // Default min_memory to same as max for new servers, or 1G?
-        // Let's default to max_memory for simplicity/Aikar's recommendation,
-        // but user can change it. Actually user wants to decide.
-        // I'll initialize it to max_memory for now so it doesn't break.
-        let min_memory = max_memory.clone();
+/// Proxies do not run Mojang server software and have no eula.txt
+fn requires_eula(server_type: &ServerType) -> bool {
+    !is_proxy(server_type)
+}
 
-        let server_info = ServerInfo {
-            id: id.clone(),
-            name,
-            version,
-            server_type,
-            port,
-            max_memory,
-            min_memory,
-            status: ServerStatus::Stopped,
-            path: server_path,
-            pid: None,
-            players: "0/20".to_string(),
-            auto_restart: false,
-            restart_interval: 86400,
-            restart_type: RestartType::Interval,
-            restart_schedule: None,
-            time_zone: None,
-            last_start_time: None,
-        };
+pub fn is_proxy(server_type: &ServerType) -> bool {
+    matches!(
+        server_type,
+        ServerType::Velocity | ServerType::Waterfall | ServerType::BungeeCord
+    )
+}
 
-        self.servers.lock().await.insert(id, server_info.clone());
-        Ok(server_info)
-    }
+async fn write_eula(server_path: &Path, accepted: bool) -> Result<()> {
+    fs::write(
+        server_path.join("eula.txt"),
+        format!(
+            "#By changing the setting below to TRUE you are indicating your agreement to our EULA (https://aka.ms/MinecraftEULA).\neula={}\n",
+            accepted
+        ),
+    )
+    .await?;
+    Ok(())
+}
 
-    pub async fn set_auto_restart(
-        &self,
-        server_id: &str,
-        enabled: bool,
-        restart_type: RestartType,
-        interval: u64,
-        schedule: Option<String>,
-        time_zone: Option<String>,
-    ) -> Result<()> {
-        let mut servers = self.servers.lock().await;
+/// What eula.txt says, None when it is missing or has no eula line
+async fn read_eula(server_path: &Path) -> Option<bool> {
+    let content = fs::read_to_string(server_path.join("eula.txt"))
+        .await
+        .ok()?;
+    content
+        .lines()
+        .filter(|line| !line.trim().starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .find(|(k, _)| k.trim() == "eula")
+        .map(|(_, v)| v.trim().eq_ignore_ascii_case("true"))
+}
 
-        if let Some(server) = servers.get_mut(server_id) {
-            server.auto_restart = enabled;
-            server.restart_type = restart_type;
-            server.restart_interval = interval;
-            server.restart_schedule = schedule;
-            server.time_zone = time_zone;
-            Ok(())
-        } else {
-            anyhow::bail!("Server not found")
+/// Rewrite server.properties lines as plain `key=value` the way the server
+/// reads them (java.util.Properties): a leading BOM dropped, `key: value` and
+/// `key value` separators replaced, and of repeated keys only the last kept.
+/// Values continued over several lines (ending in `\`) are kept as written.
+/// Returns the lines and what was changed.
+fn normalize_properties(content: &str) -> (Vec<String>, Vec<String>) {
+    let mut fixes = Vec::new();
+    let content = match content.strip_prefix('\u{feff}') {
+        Some(rest) => {
+            fixes.push("removed the byte order mark".to_string());
+            rest
+        }
+        None => content,
+    };
+
+    // A line ending in an odd number of backslashes continues on the next one
+    let continues = |line: &str| line.chars().rev().take_while(|&c| c == '\\').count() % 2 == 1;
+
+    let mut entries: Vec<(Option<String>, Vec<String>)> = Vec::new();
+    let mut rewritten = 0;
+    let mut physical = content.lines();
+    while let Some(line) = physical.next() {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with('!') {
+            entries.push((None, vec![line.to_string()]));
+            continue;
         }
+        let split = trimmed
+            .find(|c: char| c == '=' || c == ':' || c.is_whitespace())
+            .unwrap_or(trimmed.len());
+        let key = &trimmed[..split];
+        if key.contains('\\') {
+            entries.push((None, vec![line.to_string()])); // Escaped keys are left alone
+            continue;
+        }
+        if continues(line) {
+            let mut lines = vec![line.to_string()];
+            for next in physical.by_ref() {
+                lines.push(next.to_string());
+                if !continues(next) {
+                    break;
+                }
+            }
+            entries.push((Some(key.to_string()), lines));
+            continue;
+        }
+        let rest = trimmed[split..].trim_start();
+        let value = rest
+            .strip_prefix(['=', ':'])
+            .map(str::trim_start)
+            .unwrap_or(rest);
+        // Trailing whitespace belongs to the value, it is kept and not a change
+        let normalized = format!("{}={}", key, value);
+        if normalized != line {
+            rewritten += 1;
+        }
+        entries.push((Some(key.to_string()), vec![normalized]));
+    }
+    if rewritten > 0 {
+        fixes.push(format!("rewrote {} lines as key=value", rewritten));
     }
 
-    pub async fn start_server(&self, server_id: &str) -> Result<()> {
-        let server_info = {
-            let mut servers = self.servers.lock().await;
-            let server = servers.get_mut(server_id).context("Server not found")?;
-
-            if server.status == ServerStatus::Running {
-                anyhow::bail!("Server is already running");
+    let mut lines = Vec::new();
+    for (i, (key, entry)) in entries.iter().enumerate() {
+        if let Some(key) = key {
+            if entries[i + 1..]
+                .iter()
+                .any(|(k, _)| k.as_ref() == Some(key))
+            {
+                fixes.push(format!("removed a duplicate {}", key));
+                continue;
             }
+        }
+        lines.extend(entry.iter().cloned());
+    }
+    (lines, fixes)
+}
 
-            server.status = ServerStatus::Starting;
-            server.last_start_time = Some(
-                std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs(),
-            );
-            server.clone()
-        };
+fn default_restart_warnings() -> Vec<u64> {
+    vec![300, 60, 10]
+}
 
-        let jar_path = server_info.path.join("server.jar");
+/// "5 minutes" / "1 minute" / "10 seconds"
+fn format_countdown(secs: u64) -> String {
+    let (value, unit) = if secs >= 60 && secs.is_multiple_of(60) {
+        (secs / 60, "minute")
+    } else {
+        (secs, "second")
+    };
+    if value == 1 {
+        format!("{} {}", value, unit)
+    } else {
+        format!("{} {}s", value, unit)
+    }
+}
 
-        // Auto-select Java based on Minecraft version
-        let java_cmd = crate::java_detector::select_java_for_minecraft(&server_info.version)
-            .unwrap_or_else(|| {
-                // Fallback: Try JAVA_HOME, then system java
-                std::env::var("JAVA_HOME")
-                    .ok()
-                    .map(|java_home| {
-                        #[cfg(target_os = "windows")]
-                        {
-                            format!("{}\\bin\\java.exe", java_home)
-                        }
-                        #[cfg(not(target_os = "windows"))]
-                        {
-                            format!("{}/bin/java", java_home)
-                        }
-                    })
-                    .unwrap_or_else(|| "java".to_string())
-            });
+/// Seconds until the next daily "HH:MM" in the given time zone (0 while inside that minute)
+fn seconds_until_schedule(schedule: &str, time_zone: &str) -> Option<u64> {
+    let tz: chrono_tz::Tz = time_zone.parse().ok()?;
+    let target = chrono::NaiveTime::parse_from_str(schedule, "%H:%M").ok()?;
+    let now = chrono::Utc::now().with_timezone(&tz);
 
-        // Build JVM arguments with performance optimizations
-        let mut jvm_args = vec![
-            format!("-Xmx{}", server_info.max_memory),
-            format!("-Xms{}", server_info.min_memory),
-            // G1GC garbage collector (optimal for Minecraft)
-            "-XX:+UseG1GC".to_string(),
-            "-XX:+ParallelRefProcEnabled".to_string(),
-            "-XX:MaxGCPauseMillis=200".to_string(),
-            "-XX:+UnlockExperimentalVMOptions".to_string(),
-            "-XX:+DisableExplicitGC".to_string(),
-            "-XX:+AlwaysPreTouch".to_string(),
-            "-XX:G1HeapWastePercent=5".to_string(),
-            "-XX:G1MixedGCCountTarget=4".to_string(),
-            "-XX:G1MixedGCLiveThresholdPercent=90".to_string(),
-            "-XX:G1RSetUpdatingPauseTimePercent=5".to_string(),
-            "-XX:SurvivorRatio=32".to_string(),
-            "-XX:+PerfDisableSharedMem".to_string(),
-            "-XX:MaxTenuringThreshold=1".to_string(),
-            // Server JAR arguments
-            "-jar".to_string(),
-            jar_path.to_string_lossy().to_string(),
-            "nogui".to_string(),
-        ];
-
-        // Add G1NewSizePercent and G1ReservePercent for larger heap sizes
-        if let Some(mem_mb) = parse_memory_mb(&server_info.max_memory) {
-            if mem_mb >= 12288 {
-                // 12GB+
-                jvm_args.insert(7, "-XX:G1NewSizePercent=40".to_string());
-                jvm_args.insert(8, "-XX:G1MaxNewSizePercent=50".to_string());
-                jvm_args.insert(9, "-XX:G1ReservePercent=15".to_string());
-                jvm_args.insert(10, "-XX:InitiatingHeapOccupancyPercent=15".to_string());
-            } else {
-                jvm_args.insert(7, "-XX:G1NewSizePercent=30".to_string());
-                jvm_args.insert(8, "-XX:G1MaxNewSizePercent=40".to_string());
-                jvm_args.insert(9, "-XX:G1ReservePercent=20".to_string());
-                jvm_args.insert(10, "-XX:InitiatingHeapOccupancyPercent=20".to_string());
-            }
-        }
+    let elapsed_in_minute = (now.time() - target).num_seconds();
+    if (0..60).contains(&elapsed_in_minute) {
+        return Some(0);
+    }
 
-        let child = Command::new(java_cmd)
-            .args(&jvm_args)
-            .current_dir(&server_info.path)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .stdin(Stdio::piped())
-            .spawn()
-            .context("Failed to start server process")?;
+    let mut next = now.date_naive().and_time(target);
+    if next <= now.naive_local() {
+        next += chrono::Duration::days(1);
+    }
+    u64::try_from((next - now.naive_local()).num_seconds()).ok()
+}
 
-        self.processes
-            .lock()
-            .unwrap()
-            .insert(server_id.to_string(), child);
+fn default_min_memory() -> String {
+    "1G".to_string()
+}
 
-        let mut servers = self.servers.lock().await;
-        if let Some(server) = servers.get_mut(server_id) {
-            server.status = ServerStatus::Running;
-        }
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct OpEntry {
+    pub uuid: String,
+    pub name: String,
+    pub level: i32,
+    #[serde(rename = "bypassesPlayerLimit")]
+    pub bypasses_player_limit: bool,
+}
 
-        Ok(())
-    }
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WhitelistEntry {
+    pub uuid: String,
+    pub name: String,
+}
 
-    pub async fn stop_server(&self, server_id: &str) -> Result<()> {
-        // Set status to Stopping first
-        {
-            let mut servers = self.servers.lock().await;
-            if let Some(server) = servers.get_mut(server_id) {
-                // Already stopped or stopping - skip
-                if server.status == ServerStatus::Stopped || server.status == ServerStatus::Stopping
-                {
-                    return Ok(());
-                }
-                server.status = ServerStatus::Stopping;
-            }
-        }
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BanEntry {
+    pub uuid: String,
+    pub name: String,
+    pub created: String,
+    pub source: String,
+    pub expires: String,
+    pub reason: String,
+}
 
-        // Try to send "stop" command for graceful shutdown
-        let graceful_attempt = self.send_command(server_id, "stop").await;
-        let start_time = std::time::Instant::now();
-        let timeout = std::time::Duration::from_secs(30);
+/// Floodgate's view of Bedrock players: their Java name is the gamertag with
+/// a prefix, and their UUID is derived from the Xbox user id.
+#[derive(Debug, Clone, Serialize)]
+pub struct FloodgateSettings {
+    pub installed: bool,
+    pub username_prefix: String,
+    pub replace_spaces: bool,
+}
 
-        if graceful_attempt.is_ok() {
-            // Wait for server to shut down gracefully using try_wait()
-            // Poll every 200ms for faster response
-            loop {
-                if start_time.elapsed() >= timeout {
-                    println!("[ServerManager] Graceful shutdown timeout reached");
-                    break;
-                }
+/// UUIDs Floodgate assigns start with 64 zero bits followed by the XUID
+const FLOODGATE_UUID_PREFIX: &str = "00000000-0000-0000-";
 
-                // Check if process has exited using try_wait()
-                let process_exited = {
-                    let mut processes = self.processes.lock().unwrap();
-                    if let Some(process) = processes.get_mut(server_id) {
-                        match process.try_wait() {
-                            Ok(Some(_exit_status)) => {
-                                // Process has exited
-                                println!("[ServerManager] Process exited gracefully");
-                                true
-                            }
-                            Ok(None) => {
-                                // Process still running
-                                false
-                            }
-                            Err(_) => {
-                                // Error checking status, assume still running
-                                false
-                            }
-                        }
-                    } else {
-                        // Process not in map, already removed
-                        true
-                    }
-                };
+/// Read username-prefix/replace-spaces from Floodgate's config (defaults when not generated yet)
+async fn floodgate_settings(server: &ServerInfo) -> FloodgateSettings {
+    let variant = geyser_variant(&server.server_type);
+    let installed = variant.is_some_and(|v| {
+        v.floodgate_jar_path(&server.path).exists() || server.path.join(v.floodgate_dir).exists()
+    });
 
-                if process_exited {
-                    // Remove from processes map
-                    self.processes.lock().unwrap().remove(server_id);
-                    break;
-                }
+    let config: Option<serde_yaml::Value> = match variant {
+        Some(v) => fs::read_to_string(server.path.join(v.floodgate_dir).join("config.yml"))
+            .await
+            .ok()
+            .and_then(|content| serde_yaml::from_str(&content).ok()),
+        None => None,
+    };
+
+    FloodgateSettings {
+        installed,
+        username_prefix: config
+            .as_ref()
+            .and_then(|c| c["username-prefix"].as_str().map(|p| p.to_string()))
+            .unwrap_or_else(|| ".".to_string()),
+        replace_spaces: config
+            .as_ref()
+            .and_then(|c| c["replace-spaces"].as_bool())
+            .unwrap_or(true),
+    }
+}
 
-                tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
-            }
+/// The Java name Floodgate gives a Bedrock gamertag (prefixed, max 16 chars)
+fn floodgate_name(settings: &FloodgateSettings, gamertag: &str) -> String {
+    let gamertag = if settings.replace_spaces {
+        gamertag.replace(' ', "_")
+    } else {
+        gamertag.to_string()
+    };
+    format!("{}{}", settings.username_prefix, gamertag)
+        .chars()
+        .take(16)
+        .collect()
+}
 
-            println!(
-                "[ServerManager] Graceful stop completed in {:?}",
-                start_time.elapsed()
+/// Resolve the UUID the server will see for a player: the Floodgate UUID
+/// (via the GeyserMC XUID API) for Bedrock players, the Mojang UUID otherwise
+async fn resolve_player_uuid(name: &str, bedrock: bool) -> Result<String> {
+    crate::offline::ensure_online("Looking up a player")?;
+    let client = reqwest::Client::builder()
+        .user_agent("MinecraftServerManager/0.1.0")
+        .build()?;
+
+    if bedrock {
+        let url = format!("https://api.geysermc.org/v2/xbox/xuid/{}", name);
+        let response: serde_json::Value = client
+            .get(&url)
+            .send()
+            .await?
+            .error_for_status()
+            .context("Bedrock player not found")?
+            .json()
+            .await?;
+        let xuid = response["xuid"]
+            .as_u64()
+            .or_else(|| response["xuid"].as_str().and_then(|x| x.parse().ok()))
+            .or_not_found("Bedrock player not found")?;
+        let hex = format!("{:016x}", xuid);
+        Ok(format!(
+            "{}{}-{}",
+            FLOODGATE_UUID_PREFIX,
+            &hex[..4],
+            &hex[4..]
+        ))
+    } else {
+        let url = format!("https://api.mojang.com/users/profiles/minecraft/{}", name);
+        let response = client.get(&url).send().await?;
+        if !response.status().is_success() {
+            return Err(
+                PrismarineError::not_found(format!("Java player {} not found", name)).into(),
             );
         }
-
-        // Force kill if still running (fallback)
-        {
-            let mut processes = self.processes.lock().unwrap();
-            if let Some(mut process) = processes.remove(server_id) {
-                // Try to kill if still running
-                if let Err(e) = process.start_kill() {
-                    println!("[ServerManager] Failed to kill process: {}", e);
-                } else {
-                    println!("[ServerManager] Process force killed after graceful attempt");
-                }
-            }
-        }
-
-        // Update server status
-        let mut servers = self.servers.lock().await;
-        if let Some(server) = servers.get_mut(server_id) {
-            server.status = ServerStatus::Stopped;
-            server.last_start_time = None;
+        let profile: serde_json::Value = response.json().await?;
+        let id = profile["id"]
+            .as_str()
+            .or_not_found("Java player not found")?;
+        if id.len() != 32 {
+            anyhow::bail!("Unexpected UUID format: {}", id);
         }
-
-        Ok(())
+        Ok(format!(
+            "{}-{}-{}-{}-{}",
+            &id[..8],
+            &id[8..12],
+            &id[12..16],
+            &id[16..20],
+            &id[20..]
+        ))
     }
+}
 
-    /// Send a command to a running server
-    pub async fn send_command(&self, server_id: &str, command: &str) -> Result<()> {
-        // Get stdin handle - we need to release the lock before await
-        let mut stdin_handle = {
-            let mut processes = self.processes.lock().unwrap();
-            let process = processes
-                .get_mut(server_id)
-                .context("Server not found or not running")?;
+async fn read_player_list<T: serde::de::DeserializeOwned>(path: &Path) -> Vec<T> {
+    fs::read_to_string(path)
+        .await
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
 
-            process.stdin.take().context("Server stdin not available")?
-        };
+async fn write_player_list<T: Serialize>(path: &Path, entries: &[T]) -> Result<()> {
+    fs::write(path, serde_json::to_string_pretty(entries)?).await?;
+    Ok(())
+}
 
-        // Write command followed by newline
-        let command_line = format!("{}\n", command.trim());
-        stdin_handle
-            .write_all(command_line.as_bytes())
-            .await
-            .context("Failed to write command to server")?;
-        stdin_handle
-            .flush()
-            .await
-            .context("Failed to flush command to server")?;
+/// Geyser's default Bedrock port, shared by every server until changed
+const DEFAULT_BEDROCK_PORT: u16 = 19132;
+
+/// Geyser and Floodgate builds for one platform and where they live
+struct GeyserVariant {
+    platform: &'static str,           // Geyser download name
+    floodgate_platform: &'static str, // Floodgate download name
+    jar_dir: &'static str,            // plugins/ or mods/
+    geyser_jar: &'static str,
+    floodgate_jar: &'static str,
+    data_dir: &'static str, // Geyser's config folder, relative to the server
+    floodgate_dir: &'static str,
+    proxy: bool, // No server.properties, Bedrock players join through the proxy
+}
 
-        // Put stdin back
-        {
-            let mut processes = self.processes.lock().unwrap();
-            if let Some(process) = processes.get_mut(server_id) {
-                process.stdin = Some(stdin_handle);
-            }
-        }
+impl GeyserVariant {
+    fn geyser_jar_path(&self, server_path: &Path) -> PathBuf {
+        server_path.join(self.jar_dir).join(self.geyser_jar)
+    }
 
-        println!("Sent command to server {}: {}", server_id, command);
-        Ok(())
+    fn floodgate_jar_path(&self, server_path: &Path) -> PathBuf {
+        server_path.join(self.jar_dir).join(self.floodgate_jar)
     }
 
-    pub async fn get_servers(&self) -> Vec<ServerInfo> {
-        self.servers.lock().await.values().cloned().collect()
+    fn config_path(&self, server_path: &Path) -> PathBuf {
+        server_path.join(self.data_dir).join("config.yml")
     }
+}
 
-    pub async fn get_server(&self, server_id: &str) -> Option<ServerInfo> {
-        self.servers.lock().await.get(server_id).cloned()
+const GEYSER_SPIGOT: GeyserVariant = GeyserVariant {
+    platform: "spigot",
+    floodgate_platform: "spigot",
+    jar_dir: "plugins",
+    geyser_jar: "Geyser-Spigot.jar",
+    floodgate_jar: "floodgate-spigot.jar",
+    data_dir: "plugins/Geyser-Spigot",
+    floodgate_dir: "plugins/floodgate",
+    proxy: false,
+};
+
+const GEYSER_FABRIC: GeyserVariant = GeyserVariant {
+    platform: "fabric",
+    floodgate_platform: "fabric",
+    jar_dir: "mods",
+    geyser_jar: "Geyser-Fabric.jar",
+    floodgate_jar: "floodgate-fabric.jar",
+    data_dir: "config/Geyser-Fabric",
+    floodgate_dir: "config/floodgate",
+    proxy: false,
+};
+
+const GEYSER_VELOCITY: GeyserVariant = GeyserVariant {
+    platform: "velocity",
+    floodgate_platform: "velocity",
+    jar_dir: "plugins",
+    geyser_jar: "Geyser-Velocity.jar",
+    floodgate_jar: "floodgate-velocity.jar",
+    data_dir: "plugins/geyser",
+    floodgate_dir: "plugins/floodgate",
+    proxy: true,
+};
+
+const GEYSER_BUNGEECORD: GeyserVariant = GeyserVariant {
+    platform: "bungeecord",
+    floodgate_platform: "bungee",
+    jar_dir: "plugins",
+    geyser_jar: "Geyser-BungeeCord.jar",
+    floodgate_jar: "floodgate-bungee.jar",
+    data_dir: "plugins/Geyser-BungeeCord",
+    floodgate_dir: "plugins/floodgate",
+    proxy: true,
+};
+
+/// Geyser build for a server type; Bukkit hybrids load the Spigot plugin.
+/// None for Vanilla and Forge, which Geyser has no build for.
+fn geyser_variant(server_type: &ServerType) -> Option<&'static GeyserVariant> {
+    match server_type {
+        ServerType::Paper
+        | ServerType::Spigot
+        | ServerType::Purpur
+        | ServerType::Taiyitist
+        | ServerType::Mohist
+        | ServerType::Banner => Some(&GEYSER_SPIGOT),
+        ServerType::Fabric => Some(&GEYSER_FABRIC),
+        ServerType::Velocity => Some(&GEYSER_VELOCITY),
+        ServerType::Waterfall | ServerType::BungeeCord => Some(&GEYSER_BUNGEECORD),
+        ServerType::Vanilla | ServerType::Forge => None,
     }
+}
 
-    /// Get list of operators from ops.json
-    pub async fn get_ops(&self, server_id: &str) -> Result<Vec<OpEntry>> {
-        let server = self
-            .get_server(server_id)
-            .await
-            .context("Server not found")?;
+/// Bedrock (UDP) port Geyser listens on, None when Geyser is not installed
+async fn geyser_bedrock_port(server: &ServerInfo) -> Option<u16> {
+    let variant = geyser_variant(&server.server_type)?;
+    if !variant.geyser_jar_path(&server.path).exists() {
+        return None;
+    }
+    let config: Option<serde_yaml::Value> = fs::read_to_string(variant.config_path(&server.path))
+        .await
+        .ok()
+        .and_then(|content| serde_yaml::from_str(&content).ok());
+    Some(
+        config
+            .and_then(|c| c["bedrock"]["port"].as_u64())
+            .and_then(|p| u16::try_from(p).ok())
+            .unwrap_or(DEFAULT_BEDROCK_PORT),
+    )
+}
 
-        let ops_path = server.path.join("ops.json");
-        if !ops_path.exists() {
-            return Ok(Vec::new());
-        }
+/// UDP port Geyser actually binds: the Java port number with
+/// bedrock.clone-remote-port, bedrock.port otherwise
+async fn geyser_listen_port(server: &ServerInfo) -> Option<u16> {
+    let port = geyser_bedrock_port(server).await?;
+    let variant = geyser_variant(&server.server_type)?;
+    let clone_remote_port = fs::read_to_string(variant.config_path(&server.path))
+        .await
+        .ok()
+        .and_then(|content| serde_yaml::from_str::<serde_yaml::Value>(&content).ok())
+        .and_then(|c| c["bedrock"]["clone-remote-port"].as_bool())
+        .unwrap_or(false);
+    Some(if clone_remote_port { server.port } else { port })
+}
 
-        let content = fs::read_to_string(&ops_path).await?;
-        let ops: Vec<OpEntry> = serde_json::from_str(&content).unwrap_or_default();
-        Ok(ops)
-    }
+/// Whether Geyser expects PROXY protocol headers on its Bedrock port
+async fn geyser_proxy_protocol(server: &ServerInfo) -> bool {
+    let Some(variant) = geyser_variant(&server.server_type) else {
+        return false;
+    };
+    fs::read_to_string(variant.config_path(&server.path))
+        .await
+        .ok()
+        .and_then(|content| serde_yaml::from_str::<serde_yaml::Value>(&content).ok())
+        .and_then(|c| c["bedrock"]["enable-proxy-protocol"].as_bool())
+        .unwrap_or(false)
+}
 
-    /// Grant OP status to a player
-    pub async fn grant_op(&self, server_id: &str, player: &str) -> Result<()> {
-        self.send_command(server_id, &format!("op {}", player))
-            .await
+/// Set bedrock.port in Geyser's config.yml. Before Geyser's first start there is
+/// no config yet; a file with only this value is completed with defaults by Geyser.
+async fn write_geyser_bedrock_port(server: &ServerInfo, port: u16) -> Result<()> {
+    let variant =
+        geyser_variant(&server.server_type).context("Geyser does not support this server type")?;
+    let config_path = variant.config_path(&server.path);
+    if let Some(geyser_dir) = config_path.parent() {
+        fs::create_dir_all(geyser_dir).await?;
     }
+    let content = fs::read_to_string(&config_path).await.unwrap_or_default();
+    fs::write(
+        &config_path,
+        set_yaml_section_value(&content, "bedrock", "port", &port.to_string()),
+    )
+    .await
+    .context("Failed to write Geyser's config.yml")?;
+    Ok(())
+}
 
-    /// Revoke OP status from a player
-    pub async fn revoke_op(&self, server_id: &str, player: &str) -> Result<()> {
-        self.send_command(server_id, &format!("deop {}", player))
-            .await
+/// Replace the top-level `key` of a YAML file line by line, keeping comments.
+/// A missing key is appended.
+fn set_yaml_top_value(content: &str, key: &str, value: &str) -> String {
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let prefix = format!("{}:", key);
+    match lines.iter().position(|l| l.starts_with(&prefix)) {
+        Some(i) => lines[i] = format!("{} {}", prefix, value),
+        None => lines.push(format!("{} {}", prefix, value)),
     }
+    lines.join("\n") + "\n"
+}
 
-    pub async fn get_plugins_path(&self, server_id: &str) -> Result<PathBuf> {
-        let server = self
-            .servers
-            .lock()
-            .await
-            .get(server_id)
-            .context("Server not found")?
-            .clone();
+/// Quote a string for a YAML scalar
+fn yaml_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
 
-        match server.server_type {
-            ServerType::Fabric
-            | ServerType::Mohist
-            | ServerType::Forge
-            | ServerType::Taiyitist
-            | ServerType::Banner => Ok(server.path.join("mods")),
-            _ => Ok(server.path.join("plugins")),
+/// Replace `key` inside the top-level `section` of a YAML file line by line,
+/// keeping comments and formatting. Missing keys and sections are added.
+fn set_yaml_section_value(content: &str, section: &str, key: &str, value: &str) -> String {
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let header = format!("{}:", section);
+    let Some(start) = lines.iter().position(|l| l.trim_end() == header) else {
+        lines.push(header);
+        lines.push(format!("  {}: {}", key, value));
+        return lines.join("\n") + "\n";
+    };
+
+    // Keys of the section share the indentation of its first key
+    let prefix = format!("{}:", key);
+    let mut indent: Option<String> = None;
+    for i in start + 1..lines.len() {
+        let trimmed = lines[i].trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let current = lines[i][..lines[i].len() - trimmed.len()].to_string();
+        if current.is_empty() {
+            break; // Next top-level key
+        }
+        let child_indent = indent.get_or_insert_with(|| current.clone());
+        if current == *child_indent && trimmed.starts_with(&prefix) {
+            lines[i] = format!("{}{} {}", current, prefix, value);
+            return lines.join("\n") + "\n";
         }
     }
+    let indent = indent.unwrap_or_else(|| "  ".to_string());
+    lines.insert(start + 1, format!("{}{} {}", indent, prefix, value));
+    lines.join("\n") + "\n"
+}
 
-    pub async fn delete_server(&self, server_id: &str) -> Result<()> {
-        // Stop server if running
-        let _ = self.stop_server(server_id).await;
-
-        let server_info = {
-            let mut servers = self.servers.lock().await;
-            servers.remove(server_id).context("Server not found")?
-        };
+fn process_alive(pid: u32) -> bool {
+    let pid = sysinfo::Pid::from_u32(pid);
+    let mut system = sysinfo::System::new();
+    system.refresh_processes(sysinfo::ProcessesToUpdate::Some(&[pid]), true);
+    system.process(pid).is_some()
+}
 
-        // Delete server directory
-        fs::remove_dir_all(&server_info.path)
-            .await
-            .context("Failed to delete server directory")?;
+/// Whether `pid` is a Java process running in `server_path`; pids are reused,
+/// so the saved one alone does not prove the server still runs
+fn is_server_process(pid: u32, server_path: &Path) -> bool {
+    let pid = sysinfo::Pid::from_u32(pid);
+    let mut system = sysinfo::System::new();
+    system.refresh_processes_specifics(
+        sysinfo::ProcessesToUpdate::Some(&[pid]),
+        true,
+        sysinfo::ProcessRefreshKind::new()
+            .with_cwd(sysinfo::UpdateKind::Always)
+            .with_cmd(sysinfo::UpdateKind::Always),
+    );
+    let Some(process) = system.process(pid) else {
+        return false;
+    };
+    let is_java = process
+        .name()
+        .to_string_lossy()
+        .to_lowercase()
+        .starts_with("java");
+    let in_folder = match (process.cwd(), server_path.canonicalize()) {
+        (Some(cwd), Ok(path)) => cwd.canonicalize().is_ok_and(|cwd| cwd == path),
+        _ => false,
+    };
+    is_java && in_folder
+}
 
-        Ok(())
+/// Ask a process to exit (SIGTERM where supported) or kill it
+fn terminate_process(pid: u32, force: bool) {
+    let pid = sysinfo::Pid::from_u32(pid);
+    let mut system = sysinfo::System::new();
+    system.refresh_processes(sysinfo::ProcessesToUpdate::Some(&[pid]), true);
+    if let Some(process) = system.process(pid) {
+        let terminated = !force && process.kill_with(sysinfo::Signal::Term).unwrap_or(false);
+        if !terminated {
+            process.kill();
+        }
     }
+}
 
-    async fn download_server_jar(
-        &self,
-        server_path: &Path,
-        server_type: &ServerType,
-        version: &str,
-    ) -> Result<()> {
-        let jar_path = server_path.join("server.jar");
+/// Remote path of a file in the plugin/mod folder
+fn remote_relative(plugins_path: &Path, file: &str) -> String {
+    let folder = plugins_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "plugins".to_string());
+    format!("{}/{}", folder, file)
+}
 
-        let url = match server_type {
-            ServerType::Vanilla => self.get_vanilla_url(version).await?,
-            ServerType::Paper => self.get_paper_url(version).await?,
-            ServerType::Fabric => self.get_fabric_url(version).await?,
-            ServerType::Mohist => self.get_mohist_url(version).await?,
-            ServerType::Taiyitist => self.get_taiyitist_url(version).await?,
-            ServerType::Velocity => self.get_velocity_url(version).await?,
-            ServerType::Waterfall => self.get_waterfall_url(version).await?,
-            ServerType::BungeeCord => self.get_bungeecord_url(version).await?,
-            ServerType::Purpur => self.get_purpur_url(version).await?,
-            ServerType::Banner => self.get_banner_url(version).await?,
-            ServerType::Spigot => {
-                // Spigot requires BuildTools - handle separately
-                return self.build_spigot(server_path, version).await;
-            }
-            ServerType::Forge => {
-                return Err(anyhow::anyhow!(
-                    "Automatic download not supported for {:?}",
-                    server_type
-                ))
-            }
+/// Server types sharing configs, plugins and world layout
+fn server_family(server_type: &ServerType) -> Option<&'static str> {
+    match server_type {
+        ServerType::Spigot | ServerType::Paper | ServerType::Purpur => Some("bukkit"),
+        ServerType::BungeeCord | ServerType::Waterfall => Some("bungee"),
+        _ => None,
+    }
+}
+
+/// Fill in console bridge passwords from the secret store. Passwords still in
+/// config.json (older versions) are moved there; the next save drops them.
+fn load_bridge_passwords(mut servers: Vec<ServerInfo>) -> Vec<ServerInfo> {
+    for server in &mut servers {
+        let Some(bridge) = server.console_bridge.as_mut() else {
+            continue;
         };
+        let key = crate::secrets::console_bridge_key(&server.id);
+        if bridge.password.is_empty() {
+            bridge.password = crate::secrets::get(&key).ok().flatten().unwrap_or_default();
+        } else {
+            match crate::secrets::set(&key, &bridge.password) {
+                Ok(()) => println!(
+                    "[Secrets] Moved the console bridge password of {}",
+                    server.name
+                ),
+                Err(e) => println!("[Secrets] Failed to migrate {}: {}", server.name, e),
+            }
+        }
+    }
+    servers
+}
 
-        println!("Downloading server JAR from: {}", url);
-        // Use client with UA
-        let client = reqwest::Client::builder()
-            .user_agent("MinecraftServerManager/0.1.0")
-            .build()?;
-        let response = client.get(&url).send().await?;
+/// A ".sha1" file of a maven repository, as lowercase hex
+async fn fetch_sha1(client: &reqwest::Client, url: &str) -> Result<String> {
+    let response = client
+        .get(url)
+        .timeout(std::time::Duration::from_secs(15))
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        anyhow::bail!("HTTP {}", response.status());
+    }
+    let text = response.text().await?;
+    let hash = text.split_whitespace().next().unwrap_or("").to_lowercase();
+    if hash.len() != 40 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        anyhow::bail!("Invalid checksum file");
+    }
+    Ok(hash)
+}
 
-        if !response.status().is_success() {
-            return Err(anyhow::anyhow!(
-                "Failed to download server JAR: Status {}",
-                response.status()
-            ));
+/// Log file and the lines that mark a finished startup. Proxies announce
+/// their listener instead of "Done (", BungeeCord logs to proxy.log.0.
+fn boot_markers(server_type: &ServerType) -> (&'static str, &'static [&'static str]) {
+    match server_type {
+        ServerType::BungeeCord => ("proxy.log.0", &["Listening on "]),
+        ServerType::Waterfall | ServerType::Velocity => {
+            ("logs/latest.log", &["Listening on ", "Done ("])
         }
+        _ => ("logs/latest.log", &["Done ("]),
+    }
+}
 
-        let content = response.bytes().await?;
-        fs::write(&jar_path, content).await?;
+/// Whether the log written since `since` shows a finished startup
+async fn boot_finished(
+    server_path: &Path,
+    server_type: &ServerType,
+    since: std::time::SystemTime,
+) -> bool {
+    let (log_file, markers) = boot_markers(server_type);
+    let log_path = server_path.join(log_file);
+    // The log of the previous run is only rotated once the new one starts logging
+    let fresh = fs::metadata(&log_path)
+        .await
+        .and_then(|m| m.modified())
+        .is_ok_and(|modified| modified >= since);
+    fresh
+        && fs::read_to_string(&log_path)
+            .await
+            .is_ok_and(|log| markers.iter().any(|marker| log.contains(marker)))
+}
 
-        Ok(())
+/// Put plugin jars replaced during an upgrade back (best effort)
+async fn restore_plugins(plugins_path: &Path, backup_dir: &Path, replaced: &[(String, String)]) {
+    for (old_file, new_file) in replaced {
+        let _ = fs::remove_file(plugins_path.join(new_file)).await;
+        let _ = fs::rename(
+            backup_dir.join("plugins").join(old_file),
+            plugins_path.join(old_file),
+        )
+        .await;
     }
+}
 
-    async fn get_vanilla_url(&self, version: &str) -> Result<String> {
-        let manifest_url = "https://launchermeta.mojang.com/mc/game/version_manifest.json";
-        let client = reqwest::Client::builder()
-            .user_agent("MinecraftServerManager/0.1.0")
-            .build()?;
-        let manifest: serde_json::Value = client.get(manifest_url).send().await?.json().await?;
-
-        let versions = manifest["versions"]
-            .as_array()
-            .context("Invalid manifest format")?;
-        let version_info = versions
-            .iter()
-            .find(|v| v["id"].as_str() == Some(version))
-            .context(format!("Version {} not found", version))?;
+/// Modrinth loaders whose plugins/mods a server type can run
+fn modrinth_loaders(server_type: &ServerType) -> &'static [&'static str] {
+    match server_type {
+        // Paper keys can include "paper", "spigot", "bukkit"
+        ServerType::Paper | ServerType::Purpur => &["bukkit", "paper", "spigot"],
+        ServerType::Spigot => &["bukkit", "spigot"],
+        ServerType::Forge => &["forge"],
+        ServerType::Vanilla => &["bukkit"], // Fallback
+        ServerType::Fabric | ServerType::Banner => &["fabric"],
+        ServerType::Mohist | ServerType::Taiyitist => &[], // No plugin support or different system
+        ServerType::Velocity => &["velocity"],
+        ServerType::BungeeCord => &["bungeecord"],
+        ServerType::Waterfall => &["bungeecord", "waterfall"],
+    }
+}
 
-        let url = version_info["url"]
-            .as_str()
-            .context("Invalid version URL")?;
-        let packet: serde_json::Value = client.get(url).send().await?.json().await?;
+/// Memory settings plus the flags of `profile`; a profile the Java cannot run
+/// falls back to Aikar's flags so an automatic restart never fails on it
+fn profile_jvm_args(
+    profile: JvmProfile,
+    max_memory: &str,
+    min_memory: &str,
+    java: Option<u8>,
+) -> Vec<String> {
+    let profile = match java {
+        Some(java) if java < profile.min_java() => {
+            println!(
+                "[ServerManager] {} needs Java {}+, found Java {}; using {}",
+                profile.label(),
+                profile.min_java(),
+                java,
+                JvmProfile::Aikar.label()
+            );
+            JvmProfile::Aikar
+        }
+        _ => profile,
+    };
 
-        let download_url = packet["downloads"]["server"]["url"]
-            .as_str()
-            .context("Server download URL not found")?
-            .to_string();
+    let mut jvm_args = vec![format!("-Xmx{}", max_memory), format!("-Xms{}", min_memory)];
+    jvm_args.extend(profile.flags(max_memory, java));
+    jvm_args
+}
 
-        Ok(download_url)
+/// Set a dotted key path in a YAML document, creating missing sections.
+/// Returns whether the value changed.
+fn set_yaml_path(config: &mut serde_yaml::Value, path: &str, value: serde_yaml::Value) -> bool {
+    let mut current = config;
+    for key in path.split('.') {
+        if !current.is_mapping() {
+            *current = serde_yaml::Value::Mapping(serde_yaml::Mapping::new());
+        }
+        current = current
+            .as_mapping_mut()
+            .unwrap()
+            .entry(serde_yaml::Value::String(key.to_string()))
+            .or_insert(serde_yaml::Value::Null);
     }
+    if *current == value {
+        return false;
+    }
+    *current = value;
+    true
+}
 
-    async fn get_paper_url(&self, version: &str) -> Result<String> {
-        let builds_url = format!(
-            "https://api.papermc.io/v2/projects/paper/versions/{}/builds",
-            version
-        );
-        let client = reqwest::Client::builder()
-            .user_agent("MinecraftServerManager/0.1.0")
-            .build()?;
-        let builds_resp: serde_json::Value = client.get(&builds_url).send().await?.json().await?;
-
-        let builds = builds_resp["builds"]
-            .as_array()
-            .context("No builds found")?;
-        let latest_build = builds.last().context("No builds found")?;
-        let build_number = latest_build["build"]
-            .as_u64()
-            .context("Invalid build number")?;
-        let default_name = format!("paper-{}-{}.jar", version, build_number);
-        let file_name = latest_build["downloads"]["application"]["name"]
-            .as_str()
-            .unwrap_or(&default_name);
+fn yaml_path<'a>(config: &'a serde_yaml::Value, path: &str) -> Option<&'a serde_yaml::Value> {
+    path.split('.')
+        .try_fold(config, |value, key| value.get(key))
+}
 
-        Ok(format!(
-            "https://api.papermc.io/v2/projects/paper/versions/{}/builds/{}/downloads/{}",
-            version, build_number, file_name
-        ))
+/// Config folder of a web map plugin (plugins/ on Bukkit, config/ on mod loaders)
+fn web_map_config_dir(server: &ServerInfo, plugin: WebMapPlugin) -> PathBuf {
+    let modded = matches!(
+        server.server_type,
+        ServerType::Fabric | ServerType::Forge | ServerType::Banner
+    );
+    match (plugin, modded) {
+        (WebMapPlugin::BlueMap, false) => server.path.join("plugins").join("BlueMap"),
+        (WebMapPlugin::BlueMap, true) => server.path.join("config").join("bluemap"),
+        (WebMapPlugin::Dynmap, false) => server.path.join("plugins").join("dynmap"),
+        (WebMapPlugin::Dynmap, true) => server.path.join("dynmap"),
     }
+}
 
-    async fn get_fabric_url(&self, version: &str) -> Result<String> {
-        // Step 1: Get latest loader version
-        let loader_api = "https://meta.fabricmc.net/v2/versions/loader";
-        let client = reqwest::Client::builder()
-            .user_agent("MinecraftServerManager/0.1.0")
-            .build()?;
+/// Set `key: value` in a HOCON/YAML style config, appending it if missing
+async fn set_conf_value(path: &Path, key: &str, value: &str) -> Result<()> {
+    let content = fs::read_to_string(path).await.unwrap_or_default();
+    let prefix = format!("{}:", key);
+    let mut found = false;
+    let mut lines: Vec<String> = content
+        .lines()
+        .map(|line| {
+            if !found && line.trim_start().starts_with(&prefix) {
+                found = true;
+                let indent = &line[..line.len() - line.trim_start().len()];
+                format!("{}{}: {}", indent, key, value)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect();
+    if !found {
+        lines.push(format!("{}: {}", key, value));
+    }
+    fs::write(path, lines.join("\n") + "\n").await?;
+    Ok(())
+}
 
-        let loader_data: serde_json::Value = client.get(loader_api).send().await?.json().await?;
-        let latest_loader = loader_data
-            .as_array()
-            .and_then(|arr| arr.first())
-            .and_then(|v| v["version"].as_str())
-            .context("Failed to get latest Fabric loader version")?;
+/// What the process of a server with a `heap_mb` heap takes in RAM
+fn process_memory_mb(heap_mb: u64) -> u64 {
+    heap_mb + JVM_OVERHEAD_MB.max(heap_mb / 10)
+}
 
-        // Step 2: Get latest installer version
-        let installer_api = "https://meta.fabricmc.net/v2/versions/installer";
-        let installer_data: serde_json::Value =
-            client.get(installer_api).send().await?.json().await?;
-        let latest_installer = installer_data
-            .as_array()
-            .and_then(|arr| arr.first())
-            .and_then(|v| v["version"].as_str())
-            .context("Failed to get latest Fabric installer version")?;
+/// "4G" for whole gigabytes, "1536M" otherwise
+fn format_memory_mb(mb: u64) -> String {
+    if mb.is_multiple_of(1024) {
+        format!("{}G", mb / 1024)
+    } else {
+        format!("{}M", mb)
+    }
+}
 
-        // Step 3: Build download URL
-        Ok(format!(
-            "https://meta.fabricmc.net/v2/versions/loader/{}/{}/{}/server/jar",
-            version, latest_loader, latest_installer
-        ))
+/// Parse memory string (e.g., "4G", "2048M") to megabytes
+pub fn parse_memory_mb(memory: &str) -> Option<u64> {
+    let memory = memory.trim().to_uppercase();
+    if memory.ends_with('G') {
+        memory[..memory.len() - 1]
+            .parse::<u64>()
+            .ok()
+            .map(|g| g * 1024)
+    } else if memory.ends_with('M') {
+        memory[..memory.len() - 1].parse::<u64>().ok()
+    } else {
+        memory.parse::<u64>().ok()
     }
+}
 
-    async fn get_mohist_url(&self, version: &str) -> Result<String> {
-        // Mohist API: Get latest build info first
-        let builds_url = format!(
-            "https://api.mohistmc.com/project/mohist/{}/builds/latest",
-            version
-        );
+/// Check if the current time (in the given time zone, UTC if unset) matches "HH:MM"
+fn schedule_matches_now(schedule: &Option<String>, time_zone: &Option<String>) -> bool {
+    use chrono::Timelike;
 
-        let client = reqwest::Client::builder()
-            .user_agent("MinecraftServerManager/0.1.0")
-            .build()?;
+    let Some(target) = schedule
+        .as_deref()
+        .and_then(|s| chrono::NaiveTime::parse_from_str(s, "%H:%M").ok())
+    else {
+        return false;
+    };
 
-        let build_info: serde_json::Value = client.get(&builds_url).send().await?.json().await?;
+    let tz: chrono_tz::Tz = time_zone
+        .as_deref()
+        .and_then(|tz| tz.parse().ok())
+        .unwrap_or(chrono_tz::UTC);
+    let now = chrono::Utc::now().with_timezone(&tz);
 
-        let build_id = build_info["id"]
-            .as_i64()
-            .context("Failed to get Mohist build ID")?;
+    now.hour() == target.hour() && now.minute() == target.minute()
+}
 
-        // Construct download URL with build ID
-        let download_url = format!(
-            "https://api.mohistmc.com/project/mohist/{}/builds/{}/download",
-            version, build_id
+/// Reject world names that could escape the server directory: only a single
+/// plain folder name is allowed (no ".", "..", separators or drive prefixes)
+fn validate_world_name(world_name: &str) -> Result<()> {
+    let mut components = Path::new(world_name).components();
+    let single_folder = matches!(
+        (components.next(), components.next()),
+        (Some(std::path::Component::Normal(name)), None) if name == world_name
+    );
+    if world_name.trim().is_empty() || !single_folder || world_name.contains(['/', '\\']) {
+        return Err(
+            PrismarineError::invalid_input(format!("Invalid world name: {}", world_name)).into(),
         );
-
-        Ok(download_url)
     }
+    Ok(())
+}
 
-    async fn get_taiyitist_url(&self, version: &str) -> Result<String> {
-        // Taiyitist uses GitHub releases: https://github.com/Teneted/Taiyitist/releases
-        // Tag format is "{version}-release" (e.g., "1.20.1-release")
-        let tag = format!("{}-release", version);
-        let releases_url = format!(
-            "https://api.github.com/repos/Teneted/Taiyitist/releases/tags/{}",
-            tag
-        );
-
-        let client = reqwest::Client::builder()
-            .user_agent("MinecraftServerManager/0.1.0")
-            .build()?;
+/// Extract the world inside a zip into `target`, stripping any folders above level.dat
+fn extract_world_zip(zip_path: &Path, target: &Path) -> Result<()> {
+    let file = std::fs::File::open(zip_path).context("Failed to open world zip")?;
+    let mut archive = zip::ZipArchive::new(file).context("Invalid zip file")?;
+
+    // The shallowest level.dat marks the world root
+    let root = (0..archive.len())
+        .filter_map(|i| archive.by_index(i).ok()?.enclosed_name())
+        .filter(|p| p.file_name().map(|n| n == "level.dat").unwrap_or(false))
+        .min_by_key(|p| p.components().count())
+        .and_then(|p| p.parent().map(|parent| parent.to_path_buf()))
+        .context("level.dat not found in zip - is this a Minecraft world?")?;
+
+    std::fs::create_dir_all(target)?;
+
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        let Some(path) = entry.enclosed_name() else {
+            continue;
+        };
+        let Ok(relative) = path.strip_prefix(&root) else {
+            continue;
+        };
+        if relative.as_os_str().is_empty() {
+            continue;
+        }
 
-        let release_info: serde_json::Value =
-            client.get(&releases_url).send().await?.json().await?;
+        let out_path = target.join(relative);
+        if entry.is_dir() {
+            std::fs::create_dir_all(&out_path)?;
+        } else {
+            if let Some(parent) = out_path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let mut out_file = std::fs::File::create(&out_path)?;
+            std::io::copy(&mut entry, &mut out_file)?;
+        }
+    }
 
-        // Find the first .jar asset
-        let assets = release_info["assets"]
-            .as_array()
-            .context("Failed to get release assets")?;
+    Ok(())
+}
 
-        for asset in assets {
-            let name = asset["name"].as_str().unwrap_or("");
-            if name.ends_with(".jar") {
-                let download_url = asset["browser_download_url"]
-                    .as_str()
-                    .context("Failed to get download URL")?;
-                return Ok(download_url.to_string());
+/// Zip the given folders of a server directory, keeping their names as top-level folders
+fn write_world_zip(server_path: &Path, folders: &[String], dest_path: &Path) -> Result<()> {
+    use std::io::Write;
+
+    let file = std::fs::File::create(dest_path).context("Failed to create zip file")?;
+    let mut writer = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+
+    let mut stack: Vec<PathBuf> = folders.iter().map(|f| server_path.join(f)).collect();
+    while let Some(dir) = stack.pop() {
+        for entry in std::fs::read_dir(&dir)?.flatten() {
+            let path = entry.path();
+            let name = path
+                .strip_prefix(server_path)?
+                .to_string_lossy()
+                .replace('\\', "/");
+
+            if path.is_dir() {
+                writer.add_directory(format!("{}/", name), options)?;
+                stack.push(path);
+            } else {
+                // session.lock is held open by a running server and is useless elsewhere
+                if entry.file_name() == "session.lock" {
+                    continue;
+                }
+                writer.start_file(name, options)?;
+                writer.write_all(&std::fs::read(&path)?)?;
             }
         }
-
-        Err(anyhow::anyhow!(
-            "No JAR file found in Taiyitist release {}",
-            version
-        ))
     }
 
-    async fn get_velocity_url(&self, version: &str) -> Result<String> {
-        // Papermc API for Velocity
-        let base_url = "https://api.papermc.io/v2/projects/velocity";
-        let client = reqwest::Client::builder()
-            .user_agent("MinecraftServerManager/0.1.0")
-            .build()?;
+    writer.finish()?;
+    Ok(())
+}
 
-        // Get latest build for the version
-        let builds_url = format!("{}/versions/{}/builds", base_url, version);
-        let resp: serde_json::Value = client.get(&builds_url).send().await?.json().await?;
+/// Total size of all files below a directory
+fn dir_size(path: &Path) -> u64 {
+    std::fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| match entry.metadata() {
+                    Ok(meta) if meta.is_dir() => dir_size(&entry.path()),
+                    Ok(meta) => meta.len(),
+                    Err(_) => 0,
+                })
+                .sum()
+        })
+        .unwrap_or(0)
+}
 
-        let builds = resp["builds"].as_array().context("No builds found")?;
-        let latest_build = builds.last().context("No builds found")?;
-        let build_number = latest_build["build"]
-            .as_i64()
-            .context("Invalid build number")?;
-        let name = latest_build["downloads"]["application"]["name"]
-            .as_str()
-            .context("Invalid download name")?;
+/// Modern forwarding secret of a Velocity proxy. A forwarding.secret file left
+/// by older versions is moved into the secret store on first use.
+async fn forwarding_secret(proxy: &ServerInfo) -> Result<String> {
+    let key = crate::secrets::forwarding_key(&proxy.id);
+    let secret_path = proxy.path.join("forwarding.secret");
 
-        Ok(format!(
-            "{}/versions/{}/builds/{}/downloads/{}",
-            base_url, version, build_number, name
-        ))
-    }
+    tokio::task::spawn_blocking(move || {
+        if let Some(secret) = crate::secrets::get(&key)? {
+            return Ok(secret);
+        }
 
-    async fn get_waterfall_url(&self, version: &str) -> Result<String> {
-        // Papermc API for Waterfall
-        let base_url = "https://api.papermc.io/v2/projects/waterfall";
-        let client = reqwest::Client::builder()
-            .user_agent("MinecraftServerManager/0.1.0")
-            .build()?;
-
-        // Get latest build for the version
-        let builds_url = format!("{}/versions/{}/builds", base_url, version);
-        let resp: serde_json::Value = client.get(&builds_url).send().await?.json().await?;
-
-        let builds = resp["builds"].as_array().context("No builds found")?;
-        let latest_build = builds.last().context("No builds found")?;
-        let build_number = latest_build["build"]
-            .as_i64()
-            .context("Invalid build number")?;
-        let name = latest_build["downloads"]["application"]["name"]
-            .as_str()
-            .context("Invalid download name")?;
+        let legacy = std::fs::read_to_string(&secret_path)
+            .map(|s| s.trim().to_string())
+            .unwrap_or_default();
+        let secret = if legacy.is_empty() {
+            crate::secrets::generate_password()
+        } else {
+            legacy
+        };
+        crate::secrets::set(&key, &secret)?;
+        let _ = std::fs::remove_file(&secret_path);
+        Ok(secret)
+    })
+    .await?
+}
 
-        Ok(format!(
-            "{}/versions/{}/builds/{}/downloads/{}",
-            base_url, version, build_number, name
-        ))
+/// Copy a directory tree (used when a rename across drives is not possible).
+/// `on_file` receives the size of every copied file.
+fn copy_dir_all(src: &Path, dest: &Path, on_file: &mut dyn FnMut(u64)) -> Result<()> {
+    std::fs::create_dir_all(dest)?;
+    for entry in std::fs::read_dir(src)?.flatten() {
+        let target = dest.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir_all(&entry.path(), &target, on_file)?;
+        } else {
+            on_file(std::fs::copy(entry.path(), &target)?);
+        }
     }
+    Ok(())
+}
 
-    async fn get_bungeecord_url(&self, _version: &str) -> Result<String> {
-        // BungeeCord (Jenkins) - For now just return latest stable
-        // The version string might be ignored or used if we support specific builds
-        // Official CI: https://ci.md-5.net/job/BungeeCord/
-        Ok("https://ci.md-5.net/job/BungeeCord/lastSuccessfulBuild/artifact/bootstrap/target/BungeeCord.jar".to_string())
+/// Move a directory, falling back to copy + delete across drives.
+/// `on_progress` gets (bytes copied, total bytes) while copying.
+async fn move_dir<F>(src: &Path, dest: &Path, on_progress: F) -> Result<()>
+where
+    F: Fn(u64, u64) + Send + 'static,
+{
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    if fs::rename(src, dest).await.is_ok() {
+        return Ok(());
     }
 
-    async fn get_purpur_url(&self, version: &str) -> Result<String> {
-        // Purpur API: https://api.purpurmc.org/v2/purpur/{version}
-        let url = format!("https://api.purpurmc.org/v2/purpur/{}", version);
-        let client = reqwest::Client::builder()
-            .user_agent("MinecraftServerManager/0.1.0")
-            .build()?;
-        let resp: serde_json::Value = client.get(&url).send().await?.json().await?;
-
-        let latest_build = resp["builds"]["latest"]
-            .as_str()
-            .context("No latest build found for Purpur")?;
+    let (src_owned, dest_owned) = (src.to_path_buf(), dest.to_path_buf());
+    let copied = tokio::task::spawn_blocking(move || {
+        let total = dir_size(&src_owned);
+        let mut copied = 0;
+        let result = copy_dir_all(&src_owned, &dest_owned, &mut |size| {
+            copied += size;
+            on_progress(copied, total);
+        });
+        if result.is_err() {
+            // Do not leave a half copied directory behind
+            let _ = std::fs::remove_dir_all(&dest_owned);
+        }
+        result
+    })
+    .await?;
+    copied?;
+
+    fs::remove_dir_all(src)
+        .await
+        .context("Copied the directory but failed to remove the original")?;
+    Ok(())
+}
 
-        Ok(format!(
-            "https://api.purpurmc.org/v2/purpur/{}/{}/download",
-            version, latest_build
-        ))
+/// Names of players that have joined the server before (usercache.json + whitelist.json)
+async fn read_known_players(server_path: &Path) -> Vec<String> {
+    let mut names = Vec::new();
+    for file in ["usercache.json", "whitelist.json"] {
+        if let Ok(content) = fs::read_to_string(server_path.join(file)).await {
+            if let Ok(entries) = serde_json::from_str::<Vec<serde_json::Value>>(&content) {
+                names.extend(
+                    entries
+                        .iter()
+                        .filter_map(|e| e["name"].as_str().map(|n| n.to_lowercase())),
+                );
+            }
+        }
     }
+    names
+}
 
-    async fn get_banner_url(&self, version: &str) -> Result<String> {
-        // Banner is available on mohistmc.com builds-raw
-        // Filenames use git hashes: Banner-1.20.1-{hash}.jar
-        let client = reqwest::Client::builder()
-            .user_agent("MinecraftServerManager/0.1.0")
-            .build()?;
+/// Explain why a console command is considered dangerous, if it is
+fn dangerous_command_reason(command: &str, known_players: &[String]) -> Option<String> {
+    let command = command.trim().trim_start_matches('/');
+    let mut parts = command.split_whitespace();
+    let name = parts.next()?.to_lowercase();
+    let args: Vec<&str> = parts.collect();
+
+    match name.as_str() {
+        "stop" => Some("this stops the server".to_string()),
+        "kill"
+            if args
+                .iter()
+                .any(|a| a.starts_with("@e") || a.starts_with("@a")) =>
+        {
+            Some("this kills every matching entity or player".to_string())
+        }
+        "op" => {
+            let target = args.first()?;
+            if target.starts_with('@') {
+                Some("this grants operator to a selector".to_string())
+            } else if !known_players.contains(&target.to_lowercase()) {
+                Some(format!("{} has never joined this server", target))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
 
-        // Get directory listing from builds-raw
-        let dir_url = format!("https://mohistmc.com/builds-raw/Banner-{}/", version);
-        println!("Fetching Banner builds from: {}", dir_url);
+/// Check if a version string denotes a snapshot / pre-release build
+/// (e.g. "24w14a", "1.21-pre1", "1.20.5-rc1", "3.4.0-SNAPSHOT")
+fn is_prerelease_version(version: &str) -> bool {
+    let lower = version.to_lowercase();
+    lower.contains("-pre")
+        || lower.contains("-rc")
+        || lower.contains("snapshot")
+        || (lower.len() >= 5
+            && lower.as_bytes()[2] == b'w'
+            && lower[..2].chars().all(|c| c.is_ascii_digit()))
+}
 
-        let resp = client.get(&dir_url).send().await?;
+/// Locate the Forge 1.17+ launch argument file (relative to the server directory).
+/// Prefers the install matching `mc_version` when several Forge versions are present.
+fn find_forge_args_file(server_path: &Path, mc_version: &str) -> Option<String> {
+    #[cfg(target_os = "windows")]
+    let args_name = "win_args.txt";
+    #[cfg(not(target_os = "windows"))]
+    let args_name = "unix_args.txt";
+
+    let forge_libs = server_path
+        .join("libraries")
+        .join("net")
+        .join("minecraftforge")
+        .join("forge");
+
+    let candidates: Vec<std::fs::DirEntry> = std::fs::read_dir(&forge_libs)
+        .ok()?
+        .flatten()
+        .filter(|entry| entry.path().join(args_name).exists())
+        .collect();
+
+    let version_prefix = format!("{}-", mc_version);
+    candidates
+        .iter()
+        .find(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with(&version_prefix)
+        })
+        .or(candidates.first())
+        .map(|entry| {
+            format!(
+                "libraries/net/minecraftforge/forge/{}/{}",
+                entry.file_name().to_string_lossy(),
+                args_name
+            )
+        })
+}
 
-        if !resp.status().is_success() {
-            anyhow::bail!(
-                "Banner {} のビルドディレクトリにアクセスできません (HTTP {})",
-                version,
-                resp.status()
-            );
+/// Build the arguments that follow the JVM flags (what to launch and how)
+fn server_launch_args(server_path: &Path, server_type: &ServerType, version: &str) -> Vec<String> {
+    if *server_type == ServerType::Forge {
+        if let Some(args_file) = find_forge_args_file(server_path, version) {
+            let mut args = Vec::new();
+            if server_path.join("user_jvm_args.txt").exists() {
+                args.push("@user_jvm_args.txt".to_string());
+            }
+            args.push(format!("@{}", args_file));
+            args.push("nogui".to_string());
+            return args;
         }
+    }
 
-        let html = resp.text().await?;
+    vec![
+        "-jar".to_string(),
+        server_path.join("server.jar").to_string_lossy().to_string(),
+        "nogui".to_string(),
+    ]
+}
 
-        // Parse HTML directory listing for JAR files
-        // Format: href="Banner-1.20.1-{hash}.jar"
-        let prefix = format!("Banner-{}-", version);
-        let mut latest_jar: Option<String> = None;
+/// Summary of what a server is launched with, shown at the top of its console
+fn launch_banner(
+    server: &ServerInfo,
+    java_cmd: &str,
+    java_major: Option<u8>,
+    args: &[String],
+) -> Vec<String> {
+    use sha2::{Digest, Sha256};
+
+    let java_version = java_major.map_or("unknown".to_string(), |v| v.to_string());
+    // The jar follows -jar; Forge launches through argument files instead
+    let jar = args
+        .iter()
+        .position(|arg| arg == "-jar")
+        .and_then(|i| args.get(i + 1))
+        .map(|jar| server.path.join(jar));
+    let jar_hash = match &jar {
+        Some(jar) => std::fs::read(jar)
+            .map(|data| format!("{:x}", Sha256::digest(data)))
+            .unwrap_or_else(|_| "missing".to_string()),
+        None => "none (argument file launch)".to_string(),
+    };
+    let (addon_dir, addon_label) = match server.server_type {
+        ServerType::Fabric
+        | ServerType::Mohist
+        | ServerType::Forge
+        | ServerType::Taiyitist
+        | ServerType::Banner => ("mods", "Mods"),
+        _ => ("plugins", "Plugins"),
+    };
+    let addons = std::fs::read_dir(server.path.join(addon_dir))
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|e| e.path().extension().is_some_and(|ext| ext == "jar"))
+                .count()
+        })
+        .unwrap_or(0);
+
+    vec![
+        format!(
+            "[Prismarine] Starting {} ({:?} {})",
+            server.name, server.server_type, server.version
+        ),
+        format!("[Prismarine] Java {}: {}", java_version, java_cmd),
+        format!("[Prismarine] Arguments: {}", args.join(" ")),
+        format!("[Prismarine] Jar SHA-256: {}", jar_hash),
+        format!("[Prismarine] Port: {}", server.port),
+        format!("[Prismarine] {}: {}", addon_label, addons),
+    ]
+}
 
-        for part in html.split("href=\"") {
-            if let Some(end_quote) = part.find('"') {
-                let href = &part[..end_quote];
-                if href.starts_with(&prefix) && href.ends_with(".jar") {
-                    // Keep track of the last JAR found (directory listings are usually sorted)
-                    latest_jar = Some(href.to_string());
-                }
-            }
-        }
+/// Release builds of a Hangar project for Paper, newest first
+async fn hangar_releases(project: &str) -> Result<Vec<serde_json::Value>> {
+    crate::offline::ensure_online("Fetching plugins from Hangar")?;
+    let api_url = format!(
+        "https://hangar.papermc.io/api/v1/projects/{}/versions?limit=25&platform=PAPER&channel=Release",
+        project
+    );
+    println!("Fetching {} info from: {}", project, api_url);
+
+    let client = reqwest::Client::builder()
+        .user_agent("MinecraftServerManager/0.1.0")
+        .build()?;
+    let resp: serde_json::Value = client.get(&api_url).send().await?.json().await?;
+    resp["result"]
+        .as_array()
+        .cloned()
+        .context("Invalid Hangar API response")
+}
 
-        let jar_name =
-            latest_jar.context(format!("Banner {} のビルドが見つかりません。", version))?;
+fn hangar_download_url(version: &serde_json::Value) -> Result<&str> {
+    version["downloads"]["PAPER"]["downloadUrl"]
+        .as_str()
+        .context("Download URL not found in Hangar response")
+}
 
-        let download_url = format!(
-            "https://mohistmc.com/builds-raw/Banner-{}/{}",
-            version, jar_name
-        );
-        println!("Banner direct download: {}", download_url);
-        Ok(download_url)
+/// How a single plugin can be reloaded without restarting the server
+#[derive(Debug, Clone, Copy, serde::Serialize, PartialEq)]
+pub enum ReloadMethod {
+    PlugMan,     // PlugManX (or the original PlugMan): "plugman reload <name>"
+    ServerUtils, // "serverutils reloadplugin <name>"
+    FullReload,  // "reload confirm": reloads every plugin, which many plugins don't survive
+}
+
+impl ReloadMethod {
+    fn command(&self, plugin: &str) -> String {
+        match self {
+            ReloadMethod::PlugMan => format!("plugman reload {}", plugin),
+            ReloadMethod::ServerUtils => format!("serverutils reloadplugin {}", plugin),
+            ReloadMethod::FullReload => "reload confirm".to_string(),
+        }
     }
+}
 
-    async fn build_spigot(&self, server_path: &Path, version: &str) -> Result<()> {
-        // Spigot requires BuildTools to build
-        // 1. Download BuildTools.jar
-        // 2. Run BuildTools with specified version
-        // 3. Copy resulting spigot-*.jar to server.jar
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PluginSearchResult {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub author: String,
+    pub icon_url: Option<String>,
+    pub source: String, // "Modrinth" or "Spigot"
+    pub external_url: String,
+    pub download_url: Option<String>,
+    /// "ja" when the description is written in Japanese
+    #[serde(default)]
+    pub description_language: Option<String>,
+    /// English query this result was found with, when the typed one was translated
+    #[serde(default)]
+    pub matched_query: Option<String>,
+}
 
-        println!("[Spigot BuildTools] Starting build for version {}", version);
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq)]
+pub enum ServerType {
+    Vanilla,
+    Paper,
+    Spigot,
+    Forge,
+    Fabric,
+    Mohist,
+    Taiyitist,
+    Purpur,
+    Banner,
+    BungeeCord,
+    Velocity,
+    Waterfall,
+}
 
-        let buildtools_url = "https://hub.spigotmc.org/jenkins/job/BuildTools/lastSuccessfulBuild/artifact/target/BuildTools.jar";
-        let buildtools_path = server_path.join("BuildTools.jar");
-        let jar_path = server_path.join("server.jar");
+/// Reusable server configuration captured from an existing server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerTemplate {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub server_type: ServerType,
+    pub version: String,
+    pub max_memory: String,
+    pub min_memory: String,
+    #[serde(default)]
+    pub jvm_args: Option<String>, // Contents of user_jvm_args.txt
+    #[serde(default)]
+    pub properties: std::collections::BTreeMap<String, String>, // server.properties without ports
+    #[serde(default)]
+    pub plugins: Vec<TemplatePlugin>,
+    #[serde(default)]
+    pub created_at: u64,
+}
 
-        // Download BuildTools.jar
-        println!("[Spigot BuildTools] Downloading BuildTools.jar...");
-        let client = reqwest::Client::builder()
-            .user_agent("MinecraftServerManager/0.1.0")
-            .build()?;
-        let response = client.get(buildtools_url).send().await?;
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplatePlugin {
+    pub filename: String,
+    pub url: Option<String>, // None when the jar was added manually
+}
 
-        if !response.status().is_success() {
-            anyhow::bail!(
-                "Failed to download BuildTools.jar: HTTP {}",
-                response.status()
-            );
-        }
+/// Sidecar in the plugins/mods folder mapping jar file names to their download URLs
+const PLUGIN_SOURCES_FILE: &str = "prismarine-sources.json";
 
-        let content = response.bytes().await?;
-        fs::write(&buildtools_path, content).await?;
+/// server.properties keys that are tied to a single server and never copied
+const TEMPLATE_EXCLUDED_PROPERTIES: &[&str] =
+    &["server-port", "query.port", "rcon.port", "rcon.password"];
 
-        // Get appropriate Java version for building
-        let java_cmd = crate::java_detector::select_java_for_minecraft(version)
-            .unwrap_or_else(|| "java".to_string());
+async fn read_plugin_sources(plugins_path: &Path) -> HashMap<String, String> {
+    fs::read_to_string(plugins_path.join(PLUGIN_SOURCES_FILE))
+        .await
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
 
-        println!("[Spigot BuildTools] Using Java: {}", java_cmd);
-        println!(
-            "[Spigot BuildTools] Building Spigot {}... (this may take a while)",
-            version
-        );
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorldInfo {
+    pub name: String,
+    pub size_bytes: u64,
+    pub active: bool, // Part of the current level-name (incl. _nether/_the_end)
+}
 
-        // Run BuildTools
-        let output = Command::new(&java_cmd)
-            .args(&["-jar", "BuildTools.jar", "--rev", version])
-            .current_dir(server_path)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .output()
-            .await
-            .context("Failed to run BuildTools")?;
+/// Seconds a boot after an upgrade or plugin change may take before it counts as failed
+const BOOT_TIMEOUT_SECS: u64 = 300;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpgradeReport {
+    pub server: ServerInfo,
+    pub plugins: Vec<PluginCompatibility>, // Only checked when the major version changes
+    pub updated_plugins: Vec<String>,
+    pub failed_updates: Vec<String>,
+    pub booted: Option<bool>, // None when no test boot was requested
+    pub rolled_back: bool,
+    pub error: Option<String>, // Why the test boot failed
+}
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            anyhow::bail!("BuildTools failed: {}", stderr);
-        }
+#[derive(Debug, Clone, Serialize)]
+pub struct RepairReport {
+    pub server_id: String,
+    pub regenerated: Vec<String>, // Files recreated because they were missing
+    pub required_java: u8,
+    pub java_path: Option<String>, // None when no suitable Java is installed
+}
 
-        println!("[Spigot BuildTools] Build completed, locating JAR...");
+/// One item of the readiness checklist shown after creating a server
+#[derive(Debug, Clone, Serialize)]
+pub struct ReadinessCheck {
+    pub id: String, // "port", "java", "eula", "memory", "disk"
+    pub ok: bool,
+    pub blocker: bool, // The server will not start until this is fixed
+    pub message: String,
+}
 
-        // Find the built spigot JAR
-        let mut found_jar = false;
-        if let Ok(entries) = std::fs::read_dir(server_path) {
-            for entry in entries.flatten() {
-                let name = entry.file_name().to_string_lossy().to_string();
-                if name.starts_with("spigot-") && name.ends_with(".jar") {
-                    // Copy to server.jar
-                    std::fs::copy(entry.path(), &jar_path)?;
-                    found_jar = true;
-                    println!("[Spigot BuildTools] Found and copied: {}", name);
-                    break;
-                }
-            }
-        }
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerReadiness {
+    pub ready: bool, // No blockers
+    pub checks: Vec<ReadinessCheck>,
+}
 
-        if !found_jar {
-            anyhow::bail!("BuildTools completed but spigot-*.jar not found");
-        }
+/// Below this much free disk space the server cannot even unpack its libraries
+const MIN_FREE_DISK_BYTES: u64 = 512 * 1024 * 1024;
+/// Below this much the world will soon run out of room
+const LOW_FREE_DISK_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+/// Memory the JVM uses beyond the heap (metaspace, threads, native buffers)
+const JVM_OVERHEAD_MB: u64 = 512;
+/// Left for the OS and the app itself when suggesting a heap size
+const OS_RESERVED_MB: u64 = 2048;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MemorySuggestion {
+    pub suggested_mb: u64,
+    pub suggested: String, // As used for -Xmx, e.g. "4G"
+    pub total_mb: u64,
+    pub available_mb: u64,
+    pub other_servers_mb: u64, // Heaps of the other running servers
+    pub reason: String,
+}
 
-        // Cleanup BuildTools files (optional, keep for re-builds)
-        // let _ = std::fs::remove_file(&buildtools_path);
+#[derive(Debug, Clone, Serialize)]
+pub struct MoveProgress {
+    pub server_id: String,
+    pub copied: u64,
+    pub total: u64,
+    pub done: bool,
+}
 
-        println!("[Spigot BuildTools] Spigot server ready!");
-        Ok(())
-    }
+/// A Geyser server moved to a free Bedrock port by `resolve_geyser_port_conflicts`
+#[derive(Debug, Clone, Serialize)]
+pub struct GeyserPortChange {
+    pub server_id: String,
+    pub server_name: String,
+    pub java_port: u16,
+    pub old_port: u16,
+    pub new_port: u16,
+}
 
-    pub async fn fetch_vanilla_versions(&self) -> Result<Vec<String>> {
-        let manifest_url = "https://launchermeta.mojang.com/mc/game/version_manifest.json";
-        let client = reqwest::Client::builder()
-            .user_agent("MinecraftServerManager/0.1.0")
-            .build()?;
-        let manifest: serde_json::Value = client.get(manifest_url).send().await?.json().await?;
+/// Settings of Geyser's config.yml worth changing from the app
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeyserConfig {
+    pub bedrock_port: u16,
+    pub clone_remote_port: bool, // Listen on the Java port (UDP) instead of bedrock_port
+    pub auth_type: String,       // "online", "offline" or "floodgate"
+    pub passthrough_motd: bool,  // Show the Java server's MOTD to Bedrock players
+    pub passthrough_player_counts: bool,
+    pub motd1: String,
+    pub motd2: String,
+    #[serde(default)]
+    pub floodgate_installed: bool, // Read-only, auth-type "floodgate" needs it
+}
 
-        let versions = manifest["versions"]
-            .as_array()
-            .context("Invalid manifest format")?
-            .iter()
-            .filter(|v| v["type"].as_str() == Some("release"))
-            .filter_map(|v| v["id"].as_str().map(|s| s.to_string()))
-            .collect();
+const GEYSER_AUTH_TYPES: &[&str] = &["online", "offline", "floodgate"];
+
+/// Paper settings of the security preset: (file in config/, dotted key path, YAML value)
+const SECURITY_PRESET: &[(&str, &str, &str)] = &[
+    // Engine mode 2 hides ores and caves behind randomly placed fake ores
+    ("paper-world-defaults.yml", "anticheat.anti-xray.enabled", "true"),
+    ("paper-world-defaults.yml", "anticheat.anti-xray.engine-mode", "2"),
+    ("paper-world-defaults.yml", "anticheat.anti-xray.max-block-height", "64"),
+    ("paper-world-defaults.yml", "anticheat.anti-xray.update-radius", "2"),
+    ("paper-world-defaults.yml", "anticheat.anti-xray.lava-obscures", "false"),
+    ("paper-world-defaults.yml", "anticheat.anti-xray.use-permission", "false"),
+    (
+        "paper-world-defaults.yml",
+        "anticheat.anti-xray.hidden-blocks",
+        "[air, copper_ore, deepslate_copper_ore, raw_copper_block, diamond_ore, deepslate_diamond_ore, gold_ore, deepslate_gold_ore, iron_ore, deepslate_iron_ore, raw_iron_block, lapis_ore, deepslate_lapis_ore, redstone_ore, deepslate_redstone_ore]",
+    ),
+    (
+        "paper-world-defaults.yml",
+        "anticheat.anti-xray.replacement-blocks",
+        "[chest, amethyst_block, andesite, budding_amethyst, calcite, coal_ore, deepslate_coal_ore, deepslate, diorite, dirt, emerald_ore, deepslate_emerald_ore, granite, gravel, oak_planks, smooth_basalt, stone, tuff]",
+    ),
+    ("paper-world-defaults.yml", "fixes.disable-unloaded-chunk-enderpearl-exploit", "true"),
+    // Kick clients flooding packets, tab completions or recipe clicks
+    ("paper-global.yml", "packet-limiter.all-packets.action", "KICK"),
+    ("paper-global.yml", "packet-limiter.all-packets.interval", "7.0"),
+    ("paper-global.yml", "packet-limiter.all-packets.max-packet-rate", "500.0"),
+    ("paper-global.yml", "spam-limiter.incoming-packet-threshold", "300"),
+    ("paper-global.yml", "spam-limiter.tab-spam-increment", "1"),
+    ("paper-global.yml", "spam-limiter.tab-spam-limit", "500"),
+    ("paper-global.yml", "spam-limiter.recipe-spam-increment", "1"),
+    ("paper-global.yml", "spam-limiter.recipe-spam-limit", "20"),
+    // Oversized books are a classic way to crash servers and corrupt chunks
+    ("paper-global.yml", "item-validation.book-size.page-max", "2560"),
+    ("paper-global.yml", "item-validation.book-size.total-multiplier", "0.98"),
+    ("paper-global.yml", "unsupported-settings.allow-headless-pistons", "false"),
+    ("paper-global.yml", "unsupported-settings.allow-permanent-block-break-exploits", "false"),
+    ("paper-global.yml", "unsupported-settings.allow-piston-duplication", "false"),
+];
+
+/// Modrinth project installed by the security preset on request (anticheat
+/// that also drops malformed and exploit packets)
+const EXPLOIT_PATCH_PROJECT: &str = "grimac";
+
+/// What `apply_security_preset` changed and what it could not fix by itself
+#[derive(Debug, Clone, Serialize)]
+pub struct SecurityPresetReport {
+    pub changes: Vec<String>, // "paper-global.yml: packet-limiter.all-packets.action = KICK"
+    pub warnings: Vec<String>, // Issues that need a decision of the owner
+    pub plugin_installed: Option<String>,
+    pub restart_required: bool,
+}
 
-        Ok(versions)
-    }
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum DeleteMode {
+    /// Move to the trash, restorable with `restore_deleted_server`
+    Archive,
+    Permanent,
+}
 
-    pub async fn fetch_paper_versions(&self) -> Result<Vec<String>> {
-        let url = "https://api.papermc.io/v2/projects/paper";
-        let client = reqwest::Client::builder()
-            .user_agent("MinecraftServerManager/0.1.0")
-            .build()?;
-        let resp: serde_json::Value = client.get(url).send().await?.json().await?;
+/// Optional pack file inside Geyser's packs folder
+const GEYSER_OPTIONAL_PACK: &str = "GeyserOptionalPack.mcpack";
 
-        let mut versions: Vec<String> = resp["versions"]
-            .as_array()
-            .context("Invalid response format")?
-            .iter()
-            .filter_map(|v| v.as_str().map(|s| s.to_string()))
-            .collect();
+/// Permanently deleting worlds larger than this needs a confirmation token
+const LARGE_WORLD_BYTES: u64 = 1024 * 1024 * 1024;
+/// Trash folder next to the server directory, so archiving stays on the same drive
+const TRASH_DIR: &str = ".trash";
 
-        // Reverse to show newest first (Paper API returns oldest first usually)
-        versions.reverse();
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeletedServer {
+    pub trash_id: String,
+    pub trash_path: PathBuf,
+    pub server: ServerInfo,
+    pub deleted_at: u64,
+}
 
-        Ok(versions)
-    }
-
-    pub async fn fetch_fabric_versions(&self) -> Result<Vec<String>> {
-        let url = "https://meta.fabricmc.net/v2/versions/game";
-        let client = reqwest::Client::builder()
-            .user_agent("MinecraftServerManager/0.1.0")
-            .build()?;
-        let resp: serde_json::Value = client.get(url).send().await?.json().await?;
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteConfirmation {
+    pub world_size_bytes: u64,
+    /// Must be passed to `delete_server` for a permanent delete; None when not required
+    pub token: Option<String>,
+}
 
-        let versions: Vec<String> = resp
-            .as_array()
-            .context("Invalid response format")?
-            .iter()
-            .filter(|v| v["stable"].as_bool().unwrap_or(false))
-            .filter_map(|v| v["version"].as_str().map(|s| s.to_string()))
-            .collect();
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyServerEntry {
+    pub name: String,
+    pub address: String,
+}
 
-        Ok(versions)
-    }
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum ServerStatus {
+    Stopped,
+    Starting,
+    Running,
+    Stopping,
+}
 
-    pub async fn fetch_mohist_versions(&self) -> Result<Vec<String>> {
-        // Fetch versions from new Mohist API
-        let url = "https://api.mohistmc.com/project/mohist/versions";
-        let client = reqwest::Client::builder()
-            .user_agent("MinecraftServerManager/0.1.0")
-            .build()?;
+/// Payload of the `server-status-changed` event
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusChange {
+    pub server_id: String,
+    pub old_status: ServerStatus,
+    pub new_status: ServerStatus,
+    pub at: u64,
+}
 
-        let resp: serde_json::Value = client.get(url).send().await?.json().await?;
+type StatusHandler = Box<dyn Fn(StatusChange) + Send + Sync>;
 
-        let mut versions: Vec<String> = resp
-            .as_array()
-            .context("Invalid response format")?
-            .iter()
-            .filter_map(|v| v["name"].as_str().map(|s| s.to_string()))
-            .collect();
+static STATUS_HANDLER: OnceLock<StatusHandler> = OnceLock::new();
+static STATUS_EVENTS: OnceLock<tokio::sync::broadcast::Sender<StatusChange>> = OnceLock::new();
 
-        // Reverse to show newest first
-        versions.reverse();
+fn status_events() -> &'static tokio::sync::broadcast::Sender<StatusChange> {
+    STATUS_EVENTS.get_or_init(|| tokio::sync::broadcast::channel(64).0)
+}
 
-        Ok(versions)
-    }
+/// Status changes for listeners besides the handler (the REST API event stream)
+pub fn subscribe_status() -> tokio::sync::broadcast::Receiver<StatusChange> {
+    status_events().subscribe()
+}
 
-    pub async fn fetch_taiyitist_versions(&self) -> Result<Vec<String>> {
-        // Fetch releases from GitHub API
-        let url = "https://api.github.com/repos/Teneted/Taiyitist/releases";
-        let client = reqwest::Client::builder()
-            .user_agent("MinecraftServerManager/0.1.0")
-            .build()?;
+/// Register the receiver of status changes (the app forwards them as Tauri events)
+pub fn set_status_handler<F>(handler: F)
+where
+    F: Fn(StatusChange) + Send + Sync + 'static,
+{
+    let _ = STATUS_HANDLER.set(Box::new(handler));
+}
 
-        let resp: serde_json::Value = client.get(url).send().await?.json().await?;
+/// Every status change goes through here so the frontend hears about it
+fn set_status(server: &mut ServerInfo, status: ServerStatus) {
+    if server.status == status {
+        return;
+    }
+    let change = StatusChange {
+        server_id: server.id.clone(),
+        old_status: std::mem::replace(&mut server.status, status.clone()),
+        new_status: status,
+        at: chrono::Utc::now().timestamp() as u64,
+    };
+    let _ = status_events().send(change.clone()); // Err only means nobody listens
+    if let Some(handler) = STATUS_HANDLER.get() {
+        handler(change);
+    }
+}
 
-        // Tag format is "{version}-release", strip the "-release" suffix for UI display
-        let versions: Vec<String> = resp
-            .as_array()
-            .context("Invalid response format")?
-            .iter()
-            .filter_map(|v| {
-                v["tag_name"]
-                    .as_str()
-                    .map(|s| s.strip_suffix("-release").unwrap_or(s).to_string())
-            })
-            .collect();
+/// What the server list shows; the detail view fetches the full `ServerInfo`
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerSummary {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    pub server_type: ServerType,
+    pub status: ServerStatus,
+    pub port: u16,
+    pub players: String,
+    pub eula_accepted: bool,
+    pub tags: Vec<String>,
+    pub group: Option<String>,
+    pub last_start_time: Option<u64>,
+    pub web_map_port: Option<u16>,
+    pub tunnel_address: Option<String>,
+    pub bedrock_tunnel_address: Option<String>,
+}
 
-        Ok(versions)
+impl From<&ServerInfo> for ServerSummary {
+    fn from(server: &ServerInfo) -> Self {
+        Self {
+            id: server.id.clone(),
+            name: server.name.clone(),
+            version: server.version.clone(),
+            server_type: server.server_type.clone(),
+            status: server.status.clone(),
+            port: server.port,
+            players: server.players.clone(),
+            eula_accepted: server.eula_accepted,
+            tags: server.tags.clone(),
+            group: server.group.clone(),
+            last_start_time: server.last_start_time,
+            web_map_port: server.web_map.as_ref().map(|m| m.port),
+            tunnel_address: server.tunnel.as_ref().and_then(|t| t.address.clone()),
+            bedrock_tunnel_address: server
+                .bedrock_tunnel
+                .as_ref()
+                .and_then(|t| t.address.clone()),
+        }
     }
+}
 
-    pub async fn fetch_velocity_versions(&self) -> Result<Vec<String>> {
-        let url = "https://api.papermc.io/v2/projects/velocity";
-        let client = reqwest::Client::builder()
-            .user_agent("MinecraftServerManager/0.1.0")
-            .build()?;
-        let resp: serde_json::Value = client.get(url).send().await?.json().await?;
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub enum ServerSort {
+    #[default]
+    Name,
+    Status,
+    Type,
+    Version,
+    Port,
+    LastStarted, // Most recent first unless `descending` is set
+}
 
-        let mut versions: Vec<String> = resp["versions"]
-            .as_array()
-            .context("Invalid response format")?
-            .iter()
-            .filter_map(|v| v.as_str().map(|s| s.to_string()))
-            .collect();
+/// Filters for the server list; unset fields match every server
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerQuery {
+    pub status: Option<ServerStatus>,
+    pub server_type: Option<ServerType>,
+    pub tag: Option<String>,
+    pub search: Option<String>, // Case-insensitive match on name, version and tags
+    pub sort_by: ServerSort,
+    pub descending: bool,
+    pub offset: usize,
+    pub limit: Option<usize>, // Page size, every match when unset
+}
 
-        versions.reverse();
-        Ok(versions)
+/// Every server plus one page of a query, read in one go for the GUI
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerListing {
+    pub servers: Vec<ServerSummary>, // All servers, unfiltered
+    pub page: Vec<String>,           // Ids of the requested page of matches, in order
+    pub total: usize,                // Matches before paging
+}
+
+impl ServerQuery {
+    fn matches(&self, server: &ServerInfo) -> bool {
+        if self.status.as_ref().is_some_and(|s| *s != server.status) {
+            return false;
+        }
+        if self
+            .server_type
+            .as_ref()
+            .is_some_and(|t| *t != server.server_type)
+        {
+            return false;
+        }
+        if let Some(tag) = &self.tag {
+            if !server.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                return false;
+            }
+        }
+        match self.search.as_deref().map(str::trim) {
+            Some(search) if !search.is_empty() => {
+                let search = search.to_lowercase();
+                server.name.to_lowercase().contains(&search)
+                    || server.version.to_lowercase().contains(&search)
+                    || server
+                        .tags
+                        .iter()
+                        .any(|t| t.to_lowercase().contains(&search))
+            }
+            _ => true,
+        }
     }
 
-    pub async fn fetch_waterfall_versions(&self) -> Result<Vec<String>> {
-        let url = "https://api.papermc.io/v2/projects/waterfall";
-        let client = reqwest::Client::builder()
-            .user_agent("MinecraftServerManager/0.1.0")
-            .build()?;
-        let resp: serde_json::Value = client.get(url).send().await?.json().await?;
+    fn compare(&self, a: &ServerInfo, b: &ServerInfo) -> std::cmp::Ordering {
+        let by_name = || a.name.to_lowercase().cmp(&b.name.to_lowercase());
+        let ordering = match self.sort_by {
+            ServerSort::Name => by_name(),
+            ServerSort::Status => status_rank(&a.status).cmp(&status_rank(&b.status)),
+            ServerSort::Type => format!("{:?}", a.server_type).cmp(&format!("{:?}", b.server_type)),
+            ServerSort::Version => crate::versions::compare_versions(&a.version, &b.version),
+            ServerSort::Port => a.port.cmp(&b.port),
+            ServerSort::LastStarted => b.last_start_time.cmp(&a.last_start_time),
+        }
+        .then_with(by_name);
+        if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
 
-        let mut versions: Vec<String> = resp["versions"]
-            .as_array()
-            .context("Invalid response format")?
-            .iter()
-            .filter_map(|v| v.as_str().map(|s| s.to_string()))
+    /// Sorted matches among `servers` and how many there were before paging
+    fn page<'a>(
+        &self,
+        servers: impl Iterator<Item = &'a ServerInfo>,
+    ) -> (Vec<&'a ServerInfo>, usize) {
+        let mut matched: Vec<&ServerInfo> = servers.filter(|s| self.matches(s)).collect();
+        matched.sort_by(|a, b| self.compare(a, b));
+        let total = matched.len();
+        let page = matched
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
             .collect();
-
-        versions.reverse();
-        Ok(versions)
+        (page, total)
     }
+}
 
-    pub async fn fetch_bungeecord_versions(&self) -> Result<Vec<String>> {
-        // BungeeCord doesn't have a clean version list API easily accessible like Paper
-        // It's usually just "Latest" or build numbers.
-        // We'll return a single "latest" version for now.
-        Ok(vec!["latest".to_string()])
+/// Running servers first in the status sort
+fn status_rank(status: &ServerStatus) -> u8 {
+    match status {
+        ServerStatus::Running => 0,
+        ServerStatus::Starting => 1,
+        ServerStatus::Stopping => 2,
+        ServerStatus::Stopped => 3,
     }
+}
 
-    pub async fn fetch_purpur_versions(&self) -> Result<Vec<String>> {
-        let url = "https://api.purpurmc.org/v2/purpur";
-        let client = reqwest::Client::builder()
-            .user_agent("MinecraftServerManager/0.1.0")
-            .build()?;
-        let resp: serde_json::Value = client.get(url).send().await?.json().await?;
+/// Handle to the managed servers. Every field is shared (or never changes), so
+/// clones see the same state and may be used concurrently: operations guard
+/// what they touch themselves. AppState's outer mutex only hands out handles;
+/// holding it serializes nothing a clone could not bypass.
+#[derive(Clone)]
+pub struct ServerManager {
+    servers: Arc<Mutex<HashMap<String, ServerInfo>>>,
+    processes: Arc<std::sync::Mutex<HashMap<String, Child>>>,
+    pending_restarts: Arc<std::sync::Mutex<HashSet<String>>>, // Servers counting down to a restart
+    boot_watches: Arc<std::sync::Mutex<HashMap<String, u64>>>, // Servers starting after a change -> start time
+    adopted: Arc<std::sync::Mutex<HashMap<String, u32>>>, // Still running from a previous session -> pid
+    malware_scanner: Arc<MalwareScanner>,
+    port_manager: Arc<PortManager>, // Opens the ports of servers with auto_open_port
+    install_queue: Arc<InstallQueue>, // Limits parallel plugin downloads
+    delete_tokens: Arc<std::sync::Mutex<HashMap<String, String>>>, // Server id -> confirmation token
+    launch_banners: Arc<std::sync::Mutex<HashMap<String, Vec<String>>>>, // Banner of the last start
+    geyser_proxy_protocol: Arc<std::sync::Mutex<HashMap<String, bool>>>, // As Geyser loaded it at start
+    base_path: Arc<std::sync::RwLock<PathBuf>>, // Where new servers are created
+    paths: AppPaths,                            // Fixed for the lifetime of the process
+    read_only: Arc<std::sync::RwLock<Option<LockHolder>>>, // Another instance manages the data folder
+    suspended: Arc<std::sync::atomic::AtomicBool>, // From a suspend until the resume is handled
+    suspend_exits: Arc<std::sync::Mutex<Vec<String>>>, // Servers found dead while suspended
+}
 
-        let mut versions: Vec<String> = resp["versions"]
-            .as_array()
-            .context("Invalid Purpur response format")?
-            .iter()
-            .filter_map(|v| v.as_str().map(|s| s.to_string()))
-            .collect();
+/// How often `check_and_restart_servers` is polled
+const RESTART_CHECK_INTERVAL: u64 = 30;
+/// How long a "save-all flush" may take before a hung server is killed anyway
+const SAVE_BEFORE_KILL: std::time::Duration = std::time::Duration::from_secs(10);
 
-        versions.reverse();
-        Ok(versions)
+impl ServerManager {
+    pub fn new(base_path: PathBuf, paths: &AppPaths) -> Self {
+        Self {
+            servers: Arc::new(Mutex::new(HashMap::new())),
+            processes: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            pending_restarts: Arc::new(std::sync::Mutex::new(HashSet::new())),
+            boot_watches: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            adopted: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            malware_scanner: Arc::new(MalwareScanner::new(paths)),
+            port_manager: Arc::new(PortManager::new(paths)),
+            install_queue: Arc::new(InstallQueue::new()),
+            delete_tokens: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            launch_banners: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            geyser_proxy_protocol: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            base_path: Arc::new(std::sync::RwLock::new(base_path)),
+            paths: paths.clone(),
+            read_only: Arc::new(std::sync::RwLock::new(None)),
+            suspended: Arc::new(std::sync::atomic::AtomicBool::new(false)),
+            suspend_exits: Arc::new(std::sync::Mutex::new(Vec::new())),
+        }
     }
 
-    pub async fn fetch_banner_versions(&self) -> Result<Vec<String>> {
-        let client = reqwest::Client::builder()
-            .user_agent("MinecraftServerManager/0.1.0")
-            .build()?;
-
-        // Get Banner versions from builds-raw directory listing
-        let url = "https://mohistmc.com/builds-raw/";
-        let resp = client.get(url).send().await?;
-        let html = resp.text().await?;
+    /// Refuse to write the config or start processes while `holder` owns the data folder
+    pub fn set_read_only(&self, holder: Option<LockHolder>) {
+        *self.read_only.write().unwrap() = holder;
+    }
 
-        // Parse directory listing for Banner-X.Y.Z folders
-        let mut versions: Vec<String> = Vec::new();
-        for part in html.split("href=\"Banner-") {
-            if let Some(end) = part.find('/') {
-                let ver = &part[..end];
-                if !ver.is_empty()
-                    && ver
-                        .chars()
-                        .next()
-                        .map(|c| c.is_ascii_digit())
-                        .unwrap_or(false)
-                {
-                    versions.push(ver.to_string());
-                }
-            }
+    fn ensure_writable(&self) -> Result<()> {
+        if let Some(holder) = &*self.read_only.read().unwrap() {
+            anyhow::bail!(
+                "Another Prismarine instance ({}, pid {}) is managing this data folder",
+                holder.kind,
+                holder.pid
+            );
         }
+        Ok(())
+    }
 
-        // Remove duplicates
-        versions.sort();
-        versions.dedup();
-
-        // Sort by version (newest first)
-        versions.sort_by(|a, b| {
-            let a_parts: Vec<u32> = a.split('.').filter_map(|s| s.parse().ok()).collect();
-            let b_parts: Vec<u32> = b.split('.').filter_map(|s| s.parse().ok()).collect();
-            b_parts.cmp(&a_parts)
-        });
+    /// Router and firewall ports, shared with the port commands
+    pub fn port_manager(&self) -> Arc<PortManager> {
+        self.port_manager.clone()
+    }
 
-        Ok(versions)
+    /// Map the public ports of a server that opted in (best effort)
+    async fn open_auto_ports(&self, server_id: &str) {
+        let Some(server) = self
+            .get_server(server_id)
+            .await
+            .filter(|s| s.auto_open_port && s.remote.is_none())
+        else {
+            return;
+        };
+        if let Ok(ports) = self.public_ports(server_id).await {
+            self.port_manager
+                .open_server_ports(&server.name, &ports)
+                .await;
+        }
     }
 
-    pub async fn fetch_spigot_versions(&self) -> Result<Vec<String>> {
-        // Spigot versions typically mirror vanilla releases
-        // But only certain versions are supported by BuildTools
-        // We'll use vanilla versions for now, BuildTools will inform if unsupported
-        self.fetch_vanilla_versions().await
+    /// Undo `open_auto_ports` once the server is down
+    async fn close_auto_ports(&self, server_id: &str) {
+        let Some(server) = self
+            .get_server(server_id)
+            .await
+            .filter(|s| s.auto_open_port && s.remote.is_none())
+        else {
+            return;
+        };
+        if let Ok(ports) = self.public_ports(&server.id).await {
+            self.port_manager.close_server_ports(&ports).await;
+        }
     }
 
-    async fn create_default_properties(&self, server_path: &Path, port: u16) -> Result<()> {
-        let properties = format!(
-            "server-port={}\n\
-             enable-command-block=true\n\
-             gamemode=survival\n\
-             difficulty=normal\n\
-             max-players=20\n\
-             view-distance=10\n\
-             motd=A Minecraft Server managed by Prismarine\n",
-            port
-        );
+    /// Blocklist scanner used for plugin installs (config and quarantine are managed through it)
+    pub fn malware_scanner(&self) -> Arc<MalwareScanner> {
+        self.malware_scanner.clone()
+    }
 
-        fs::write(server_path.join("server.properties"), properties).await?;
-        Ok(())
+    /// Directory new servers are created in; existing servers keep their path
+    pub fn set_base_path(&self, base_path: PathBuf) {
+        *self.base_path.write().unwrap() = base_path;
     }
 
-    pub async fn set_server_motd(&self, server_id: &str, motd: &str) -> Result<()> {
-        let server = self
-            .servers
-            .lock()
-            .await
-            .get(server_id)
-            .context("Server not found")?
-            .clone();
+    fn base_path(&self) -> PathBuf {
+        self.base_path.read().unwrap().clone()
+    }
 
-        let props_path = server.path.join("server.properties");
-        if !props_path.exists() {
-            // If missing, create default? Or error? Error is safer but we initialized it.
-            // Just let it error or return.
-            return Ok(());
-        }
+    /// Save servers to JSON file
+    pub async fn save_servers(&self, config_path: &Path) -> Result<()> {
+        self.ensure_writable()?;
+        let servers = self.servers.lock().await;
+        let server_list: Vec<ServerInfo> = servers
+            .values()
+            .cloned()
+            .map(|mut server| {
+                // Kept in the secret store, see load_bridge_passwords
+                if let Some(bridge) = server.console_bridge.as_mut() {
+                    bridge.password.clear();
+                }
+                server
+            })
+            .collect();
 
-        let content = fs::read_to_string(&props_path).await?;
-        let mut new_lines = Vec::new();
-        let mut found = false;
+        let content = serde_json::to_string_pretty(&server_list)?;
 
-        for line in content.lines() {
-            if line.trim().starts_with("motd=") {
-                new_lines.push(format!("motd={}", motd));
-                found = true;
-            } else {
-                new_lines.push(line.to_string());
-            }
+        if let Some(parent) = config_path.parent() {
+            fs::create_dir_all(parent).await?;
         }
 
-        if !found {
-            new_lines.push(format!("motd={}", motd));
-        }
-
-        fs::write(&props_path, new_lines.join("\n")).await?;
+        fs::write(config_path, content).await?;
         Ok(())
     }
 
-    pub async fn get_server_motd(&self, server_id: &str) -> Result<String> {
-        let server = self
-            .servers
-            .lock()
-            .await
-            .get(server_id)
-            .context("Server not found")?
-            .clone();
-
-        let props_path = server.path.join("server.properties");
-        if !props_path.exists() {
-            return Ok("".to_string());
-        }
+    /// Load servers from JSON file
+    pub async fn load_servers(&self, config_path: &Path) -> Result<()> {
+        if config_path.exists() {
+            let content = fs::read_to_string(config_path).await?;
+            let server_list: Vec<ServerInfo> = serde_json::from_str(&content)?;
+            let server_list =
+                tokio::task::spawn_blocking(move || load_bridge_passwords(server_list)).await?;
 
-        let content = fs::read_to_string(&props_path).await?;
-        for line in content.lines() {
-            if let Some(val) = line.trim().strip_prefix("motd=") {
-                return Ok(val.to_string());
+            let mut servers = self.servers.lock().await;
+            for server in server_list {
+                servers.insert(server.id.clone(), server);
             }
         }
-        Ok("".to_string())
+        Ok(())
     }
 
-    pub async fn set_server_max_players(&self, server_id: &str, max_players: u32) -> Result<()> {
-        let server = self
-            .servers
-            .lock()
+    pub async fn create_server(
+        &self,
+        name: String,
+        version: String,
+        server_type: ServerType,
+        port: u16,
+        max_memory: String,
+        eula_accepted: bool,
+    ) -> Result<ServerInfo> {
+        let id = uuid::Uuid::new_v4().to_string();
+        let server_path = self.base_path().join(&id);
+
+        // Create server directory
+        fs::create_dir_all(&server_path)
             .await
-            .get(server_id)
-            .context("Server not found")?
-            .clone();
+            .context("Failed to create server directory")?;
 
-        let props_path = server.path.join("server.properties");
-        if !props_path.exists() {
-            return Ok(());
-        }
+        // Download server JAR
+        let installed_build = self
+            .download_server_jar(&server_path, &server_type, &version)
+            .await?;
 
-        let content = fs::read_to_string(&props_path).await?;
-        let mut new_lines = Vec::new();
-        let mut found = false;
+        // Create default server.properties
+        self.create_default_properties(&server_path, port).await?;
 
-        for line in content.lines() {
-            if line.trim().starts_with("max-players=") {
-                new_lines.push(format!("max-players={}", max_players));
-                found = true;
-            } else {
-                new_lines.push(line.to_string());
-            }
-        }
+        // Record the owner's EULA decision; unaccepted servers refuse to start
+        write_eula(&server_path, eula_accepted).await?;
 
-        if !found {
-            new_lines.push(format!("max-players={}", max_players));
+        // Default min_memory to same as max for new servers, or 1G?
+        // Let's default to max_memory for simplicity/Aikar's recommendation,
+        // but user can change it. Actually user wants to decide.
+        // I'll initialize it to max_memory for now so it doesn't break.
+        let min_memory = max_memory.clone();
+
+        let mut server_info = ServerInfo::new(id.clone(), name, server_path, server_type, version);
+        server_info.port = port;
+        server_info.max_memory = max_memory;
+        server_info.min_memory = min_memory;
+        server_info.eula_accepted = eula_accepted;
+        server_info.installed_build = installed_build;
+
+        self.servers.lock().await.insert(id, server_info.clone());
+        Ok(server_info)
+    }
+
+    pub async fn set_auto_restart(
+        &self,
+        server_id: &str,
+        enabled: bool,
+        restart_type: RestartType,
+        interval: u64,
+        schedule: Option<String>,
+        time_zone: Option<String>,
+    ) -> Result<()> {
+        let mut servers = self.servers.lock().await;
+
+        if let Some(server) = servers.get_mut(server_id) {
+            server.auto_restart = enabled;
+            server.restart_type = restart_type;
+            server.restart_interval = interval;
+            server.restart_schedule = schedule;
+            server.time_zone = time_zone;
+            Ok(())
+        } else {
+            Err(PrismarineError::not_found("Server not found").into())
         }
+    }
 
-        fs::write(&props_path, new_lines.join("\n")).await?;
+    /// Set the countdown warnings (seconds before restart) sent before an auto-restart
+    pub async fn set_restart_warnings(
+        &self,
+        server_id: &str,
+        mut warnings: Vec<u64>,
+    ) -> Result<()> {
+        warnings.retain(|&w| w > 0);
+        warnings.sort_unstable_by(|a, b| b.cmp(a));
+        warnings.dedup();
+
+        let mut servers = self.servers.lock().await;
+        let server = servers
+            .get_mut(server_id)
+            .or_not_found("Server not found")?;
+        server.restart_warnings = warnings;
         Ok(())
     }
 
-    pub async fn get_server_max_players(&self, server_id: &str) -> Result<u32> {
+    /// Record the owner's agreement to the Minecraft EULA and write eula.txt
+    pub async fn accept_eula(&self, server_id: &str) -> Result<()> {
+        let mut servers = self.servers.lock().await;
+        let server = servers
+            .get_mut(server_id)
+            .or_not_found("Server not found")?;
+        write_eula(&server.path, true).await?;
+        server.eula_accepted = true;
+        Ok(())
+    }
+
+    /// Pre-start pass over eula.txt and server.properties of imported or
+    /// hand-made servers, so a fixable file does not fail the boot. The EULA is
+    /// never accepted on the owner's behalf: eula.txt is only written when the
+    /// acceptance is on record, and an explicit eula=false withdraws it.
+    /// Returns what was repaired.
+    async fn repair_server_files(&self, server_id: &str) -> Result<Vec<String>> {
         let server = self
-            .servers
-            .lock()
+            .get_server(server_id)
             .await
-            .get(server_id)
-            .context("Server not found")?
-            .clone();
+            .or_not_found("Server not found")?;
+        if !requires_eula(&server.server_type) {
+            return Ok(Vec::new()); // Proxies have neither file
+        }
+        let mut repairs = Vec::new();
+
+        let eula_accepted = match read_eula(&server.path).await {
+            Some(true) if !server.eula_accepted => {
+                repairs.push("EULA acceptance taken over from eula.txt".to_string());
+                true
+            }
+            Some(false) if server.eula_accepted => {
+                repairs.push("eula.txt declines the EULA, asking again".to_string());
+                false
+            }
+            None if server.eula_accepted => {
+                write_eula(&server.path, true).await?;
+                repairs.push("recreated eula.txt".to_string());
+                true
+            }
+            _ => server.eula_accepted,
+        };
+        if eula_accepted != server.eula_accepted {
+            if let Some(server) = self.servers.lock().await.get_mut(server_id) {
+                server.eula_accepted = eula_accepted;
+            }
+        }
 
         let props_path = server.path.join("server.properties");
         if !props_path.exists() {
-            return Ok(20);
+            self.create_default_properties(&server.path, server.port)
+                .await?;
+            repairs.push("recreated server.properties".to_string());
+            return Ok(repairs);
         }
+        let content = fs::read(&props_path)
+            .await
+            .context("Failed to read server.properties")?;
+        let content = String::from_utf8_lossy(&content);
+        let (mut lines, fixes) = normalize_properties(&content);
+        let mut changed = !fixes.is_empty();
+        repairs.extend(fixes);
+
+        let mut set = |key: &str, value: String, lines: &mut Vec<String>| {
+            let line = format!("{}={}", key, value);
+            match lines
+                .iter_mut()
+                .find(|l| l.split_once('=').is_some_and(|(k, _)| k == key))
+            {
+                Some(existing) => *existing = line,
+                None => lines.push(line),
+            }
+            changed = true;
+        };
+        let property = |lines: &[String], key: &str| {
+            lines
+                .iter()
+                .filter_map(|l| l.split_once('='))
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| v.trim().to_string())
+        };
 
-        let content = fs::read_to_string(&props_path).await?;
-        for line in content.lines() {
-            if let Some(val) = line.trim().strip_prefix("max-players=") {
-                return Ok(val.parse().unwrap_or(20));
+        // The app's port is the one ports are checked and forwarded for
+        if property(&lines, "server-port").and_then(|p| p.parse::<u16>().ok()) != Some(server.port)
+        {
+            set("server-port", server.port.to_string(), &mut lines);
+            repairs.push(format!("set server-port to {}", server.port));
+        }
+        // A server-ip from another computer makes the server fail to bind
+        if let Some(ip) = property(&lines, "server-ip").filter(|ip| !ip.is_empty()) {
+            if tokio::net::TcpListener::bind((ip.as_str(), 0))
+                .await
+                .is_err()
+            {
+                set("server-ip", String::new(), &mut lines);
+                repairs.push(format!(
+                    "cleared server-ip {}, not an address of this computer",
+                    ip
+                ));
             }
         }
-        Ok(20)
+
+        if changed {
+            let mut content = lines.join("\n");
+            content.push('\n');
+            fs::write(&props_path, content).await?;
+        }
+        if !repairs.is_empty() {
+            println!(
+                "[ServerManager] Repaired files of {}: {}",
+                server.name,
+                repairs.join(", ")
+            );
+        }
+        Ok(repairs)
     }
 
-    pub async fn install_geyser(&self, server_id: &str) -> Result<()> {
-        let server = self
-            .servers
-            .lock()
-            .await
-            .get(server_id)
-            .context("Server not found")?
-            .clone();
+    /// Fail fast when the server's port (or Geyser's UDP port) is taken by
+    /// another managed server or any other program on this computer
+    async fn check_ports_available(&self, server_id: &str) -> Result<()> {
+        let (server, others) = {
+            let servers = self.servers.lock().await;
+            let server = servers
+                .get(server_id)
+                .or_not_found("Server not found")?
+                .clone();
+            let others: Vec<ServerInfo> = servers
+                .values()
+                .filter(|s| s.id != server_id && s.status != ServerStatus::Stopped)
+                .cloned()
+                .collect();
+            (server, others)
+        };
+        if server.status != ServerStatus::Stopped {
+            return Ok(());
+        }
 
-        match server.server_type {
-            ServerType::Vanilla | ServerType::Fabric | ServerType::Mohist => {
-                anyhow::bail!("このサーバータイプはBukkit/Spigotプラグインに対応していません。PaperまたはSpigotを使用してください。")
+        let port = server.port;
+        let owner = others.iter().find(|s| s.port == port);
+        if owner.is_some() || crate::port_manager::is_port_bound(port, "TCP") {
+            let suggested = self
+                .find_free_port(port.saturating_add(1), port.saturating_add(100))
+                .await;
+            let user = owner
+                .map(|s| format!("server \"{}\"", s.name))
+                .unwrap_or_else(|| "another program".to_string());
+            return Err(PrismarineError::port_in_use(
+                port,
+                suggested,
+                match suggested {
+                    Some(free) => format!(
+                        "Port {} is already in use by {}. Port {} is free",
+                        port, user, free
+                    ),
+                    None => format!("Port {} is already in use by {}", port, user),
+                },
+            )
+            .into());
+        }
+
+        if let Some(bedrock_port) = geyser_bedrock_port(&server).await {
+            if crate::port_manager::is_port_bound(bedrock_port, "UDP") {
+                let suggested = (bedrock_port.saturating_add(1)..=bedrock_port.saturating_add(100))
+                    .find(|p| !crate::port_manager::is_port_bound(*p, "UDP"));
+                return Err(PrismarineError::port_in_use(
+                    bedrock_port,
+                    suggested,
+                    format!(
+                        "Geyser's Bedrock port {} (UDP) is already in use. Change bedrock.port in Geyser's config.yml",
+                        bedrock_port
+                    ),
+                )
+                .into());
             }
-            _ => {}
         }
+        Ok(())
+    }
 
-        let plugins_path = server.path.join("plugins");
-        fs::create_dir_all(&plugins_path).await?;
+    /// First TCP port in `start..=end` that no managed server uses and nothing on this computer is bound to
+    pub async fn find_free_port(&self, start: u16, end: u16) -> Option<u16> {
+        let used: HashSet<u16> = self.servers.lock().await.values().map(|s| s.port).collect();
+        (start..=end).find(|p| !used.contains(p) && !crate::port_manager::is_port_bound(*p, "TCP"))
+    }
 
-        // Geyser for Spigot/Paper
-        self.install_plugin(
-            &plugins_path,
-            "https://download.geysermc.org/v2/projects/geyser/versions/latest/builds/latest/downloads/spigot",
-            "Geyser-Spigot.jar"
-        ).await.context("Failed to install Geyser")?;
+    pub async fn set_auto_open_port(&self, server_id: &str, enabled: bool) -> Result<()> {
+        let mut servers = self.servers.lock().await;
+        let server = servers
+            .get_mut(server_id)
+            .or_not_found("Server not found")?;
+        server.auto_open_port = enabled;
+        Ok(())
+    }
 
-        // Floodgate for Spigot/Paper
-        self.install_plugin(
-            &plugins_path,
-            "https://download.geysermc.org/v2/projects/floodgate/versions/latest/builds/latest/downloads/spigot",
-            "floodgate-spigot.jar"
-        ).await.context("Failed to install Floodgate")?;
+    pub async fn set_autostart(&self, server_id: &str, enabled: bool) -> Result<()> {
+        let mut servers = self.servers.lock().await;
+        let server = servers
+            .get_mut(server_id)
+            .or_not_found("Server not found")?;
+        server.autostart = enabled;
+        Ok(())
+    }
 
-        // Disable enforce-secure-profile in server.properties
-        self.update_server_property(&server.path, "enforce-secure-profile", "false")
-            .await?;
+    /// Give the server its own bridge tunnel (None removes it)
+    pub async fn set_server_tunnel(
+        &self,
+        server_id: &str,
+        tunnel: Option<ServerTunnel>,
+    ) -> Result<()> {
+        let mut servers = self.servers.lock().await;
+        let server = servers
+            .get_mut(server_id)
+            .or_not_found("Server not found")?;
+        server.tunnel = tunnel;
+        Ok(())
+    }
 
-        // "True" AutoGeyser: Install AutoUpdateGeyser plugin to keep them updated
-        // Slug: autoupdategeyser (NewAmazingPVP)
-        println!("Installing AutoUpdateGeyser...");
-        if let Err(e) = self
-            .install_modrinth_plugin(server_id, "autoupdategeyser", "AutoUpdateGeyser")
+    /// Give the server a UDP tunnel for Geyser's Bedrock port (None removes it)
+    pub async fn set_bedrock_tunnel(
+        &self,
+        server_id: &str,
+        tunnel: Option<ServerTunnel>,
+    ) -> Result<()> {
+        let server = self
+            .get_server(server_id)
             .await
-        {
-            println!("Failed to install AutoUpdateGeyser: {}", e);
-            // Don't fail the whole process, manual update is better than nothing
+            .or_not_found("Server not found")?;
+        if let Some(tunnel) = &tunnel {
+            if geyser_bedrock_port(&server).await.is_none() {
+                anyhow::bail!("Geyser is not installed on this server");
+            }
+            if !tunnel.provider.provider().supports_udp() {
+                anyhow::bail!(
+                    "{} cannot tunnel UDP, choose frp for Bedrock",
+                    tunnel.provider.provider().name()
+                );
+            }
         }
 
+        let mut servers = self.servers.lock().await;
+        let server = servers
+            .get_mut(server_id)
+            .or_not_found("Server not found")?;
+        server.bedrock_tunnel = tunnel;
         Ok(())
     }
 
-    pub async fn install_viaversion(&self, server_id: &str) -> Result<()> {
-        let server = self
+    /// Tunnels that should be up: Java and Bedrock tunnels of running servers
+    pub async fn tunnel_targets(&self) -> Vec<crate::bridge::TunnelTarget> {
+        let servers: Vec<ServerInfo> = self
             .servers
             .lock()
             .await
-            .get(server_id)
-            .context("Server not found")?
-            .clone();
+            .values()
+            .filter(|s| matches!(s.status, ServerStatus::Starting | ServerStatus::Running))
+            .cloned()
+            .collect();
 
-        match server.server_type {
-            ServerType::Vanilla => {
-                anyhow::bail!("Vanilla servers do not support plugins. Please use Paper or Spigot.")
+        let mut targets = Vec::new();
+        for server in servers {
+            if let Some(tunnel) = &server.tunnel {
+                targets.push(crate::bridge::TunnelTarget {
+                    key: server.id.clone(),
+                    kind: tunnel.provider,
+                    port: server.port,
+                    remote_server: tunnel.remote_server.clone(),
+                    udp: false,
+                });
+            }
+            if let Some(tunnel) = &server.bedrock_tunnel {
+                if let Some(port) = geyser_bedrock_port(&server).await {
+                    targets.push(crate::bridge::TunnelTarget {
+                        key: crate::bridge::bedrock_key(&server.id),
+                        kind: tunnel.provider,
+                        port,
+                        remote_server: tunnel.remote_server.clone(),
+                        udp: true,
+                    });
+                }
             }
-            _ => {}
         }
-
-        let plugins_path = server.path.join("plugins");
-        fs::create_dir_all(&plugins_path).await?;
-
-        // Fetch latest ViaVersion from Hangar API
-        let api_url =
-            "https://hangar.papermc.io/api/v1/projects/ViaVersion/versions?limit=1&platform=PAPER";
-        println!("Fetching ViaVersion info from: {}", api_url);
-
-        let client = reqwest::Client::builder()
-            .user_agent("MinecraftServerManager/0.1.0")
-            .build()?;
-
-        let resp: serde_json::Value = client.get(api_url).send().await?.json().await?;
-
-        let results = resp["result"]
-            .as_array()
-            .context("Invalid Hangar API response")?;
-
-        let latest_version = results.first().context("No ViaVersion versions found")?;
-
-        let download_url = latest_version["downloads"]["PAPER"]["downloadUrl"]
-            .as_str()
-            .context("Download URL not found in Hangar response")?;
-
-        println!("Found ViaVersion download URL: {}", download_url);
-
-        self.install_plugin(&plugins_path, download_url, "ViaVersion.jar")
-            .await
-            .context("Failed to install ViaVersion")?;
-
-        Ok(())
+        targets
     }
 
-    async fn install_plugin(&self, plugins_path: &Path, url: &str, filename: &str) -> Result<()> {
-        println!("Downloading plugin: {} from {}", filename, url);
-
-        let client = reqwest::Client::builder()
-            .user_agent("MinecraftServerManager/0.1.0")
-            .build()?;
+    /// Whether the running Geyser expects PROXY protocol headers. Servers
+    /// adopted from a previous session loaded the config file as it is now.
+    async fn loaded_proxy_protocol(&self, server: &ServerInfo) -> bool {
+        let recorded = self
+            .geyser_proxy_protocol
+            .lock()
+            .unwrap()
+            .get(&server.id)
+            .copied();
+        match recorded {
+            Some(loaded) => loaded,
+            None => geyser_proxy_protocol(server).await,
+        }
+    }
 
-        let response = client.get(url).send().await?;
+    /// Relay Bedrock players from the Java port number to Geyser. Returns
+    /// whether the server must restart before Geyser picks up the change.
+    pub async fn set_bedrock_relay(&self, server_id: &str, enabled: bool) -> Result<bool> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        if enabled {
+            let Some(bedrock_port) = geyser_listen_port(&server).await else {
+                anyhow::bail!("Geyser is not installed on this server");
+            };
+            if bedrock_port == server.port {
+                anyhow::bail!(
+                    "Geyser already listens on UDP {}, no relay is needed",
+                    server.port
+                );
+            }
+            if server.remote.is_some() {
+                anyhow::bail!("The relay only runs for servers on this machine");
+            }
+        }
 
-        if !response.status().is_success() {
-            return Err(anyhow::anyhow!(
-                "Failed to download plugin {}: Status {}",
-                filename,
-                response.status()
-            ));
+        let running = matches!(
+            server.status,
+            ServerStatus::Starting | ServerStatus::Running
+        );
+        let restart_required = running
+            && geyser_bedrock_port(&server).await.is_some()
+            && self.loaded_proxy_protocol(&server).await != enabled;
+
+        // The relay passes each player's address to Geyser with a PROXY protocol
+        // header, otherwise every player would come from localhost. Geyser reads
+        // the setting at startup; until then the relay sends no headers.
+        if geyser_bedrock_port(&server).await.is_some() {
+            let config_path = geyser_variant(&server.server_type)
+                .context("Geyser does not support this server type")?
+                .config_path(&server.path);
+            if let Some(geyser_dir) = config_path.parent() {
+                fs::create_dir_all(geyser_dir).await?;
+            }
+            let content = fs::read_to_string(&config_path).await.unwrap_or_default();
+            fs::write(
+                &config_path,
+                set_yaml_section_value(
+                    &content,
+                    "bedrock",
+                    "enable-proxy-protocol",
+                    &enabled.to_string(),
+                ),
+            )
+            .await
+            .context("Failed to write Geyser's config.yml")?;
         }
 
-        let content = response.bytes().await?;
-        fs::write(plugins_path.join(filename), content).await?;
-        Ok(())
+        let mut servers = self.servers.lock().await;
+        let server = servers
+            .get_mut(server_id)
+            .or_not_found("Server not found")?;
+        server.bedrock_relay = enabled;
+        Ok(restart_required)
     }
 
-    pub async fn uninstall_geyser(&self, server_id: &str) -> Result<()> {
-        let server = self
+    /// Relays wanted for running servers
+    pub async fn relay_targets(&self) -> Vec<crate::udp_relay::RelayTarget> {
+        let servers: Vec<ServerInfo> = self
             .servers
             .lock()
             .await
-            .get(server_id)
-            .context("Server not found")?
-            .clone();
-        let plugins_path = server.path.join("plugins");
+            .values()
+            .filter(|s| s.bedrock_relay && s.remote.is_none())
+            .filter(|s| matches!(s.status, ServerStatus::Starting | ServerStatus::Running))
+            .cloned()
+            .collect();
 
-        // Remove Geyser-Spigot.jar
-        let jar_path = plugins_path.join("Geyser-Spigot.jar");
-        if jar_path.exists() {
-            fs::remove_file(jar_path).await?;
-        }
-
-        // Remove floodgate-spigot.jar
-        let floodgate_path = plugins_path.join("floodgate-spigot.jar");
-        if floodgate_path.exists() {
-            fs::remove_file(floodgate_path).await?;
+        let mut targets = Vec::new();
+        for server in servers {
+            if let Some(bedrock_port) = geyser_listen_port(&server).await {
+                if bedrock_port != server.port {
+                    targets.push(crate::udp_relay::RelayTarget {
+                        server_id: server.id.clone(),
+                        listen_port: server.port,
+                        bedrock_port,
+                        proxy_protocol: self.loaded_proxy_protocol(&server).await,
+                    });
+                }
+            }
         }
+        targets
+    }
 
-        // Restore enforce-secure-profile in server.properties
-        self.update_server_property(&server.path, "enforce-secure-profile", "true")
-            .await?;
-
-        Ok(())
+    /// Store a tunnel's public address; returns true if it changed
+    pub async fn set_tunnel_address(
+        &self,
+        server_id: &str,
+        bedrock: bool,
+        address: Option<String>,
+    ) -> bool {
+        let mut servers = self.servers.lock().await;
+        let tunnel = servers.get_mut(server_id).and_then(|s| {
+            if bedrock {
+                s.bedrock_tunnel.as_mut()
+            } else {
+                s.tunnel.as_mut()
+            }
+        });
+        match tunnel {
+            Some(tunnel) if tunnel.address != address => {
+                tunnel.address = address;
+                true
+            }
+            _ => false,
+        }
     }
 
-    pub async fn uninstall_viaversion(&self, server_id: &str) -> Result<()> {
+    /// Ports players connect to: the server port (TCP), Geyser's Bedrock port (UDP),
+    /// a public web map (TCP) and a resource pack served by the app (TCP)
+    pub async fn public_ports(&self, server_id: &str) -> Result<Vec<(u16, String)>> {
         let server = self
-            .servers
-            .lock()
+            .get_server(server_id)
             .await
-            .get(server_id)
-            .context("Server not found")?
-            .clone();
-        let plugins_path = server.path.join("plugins");
-
-        // Remove ViaVersion.jar
-        let jar_path = plugins_path.join("ViaVersion.jar");
-        if jar_path.exists() {
-            fs::remove_file(jar_path).await?;
+            .or_not_found("Server not found")?;
+        let mut ports = vec![(server.port, "TCP".to_string())];
+        if let Some(bedrock_port) = geyser_listen_port(&server).await {
+            // The relay takes Bedrock on the Java port number, Geyser's own port stays local
+            let public = if server.bedrock_relay {
+                server.port
+            } else {
+                bedrock_port
+            };
+            ports.push((public, "UDP".to_string()));
         }
-
-        Ok(())
+        if let Some(map) = server.web_map.as_ref().filter(|m| m.public) {
+            ports.push((map.port, "TCP".to_string()));
+        }
+        if let Some(PackHosting::BuiltIn { port }) =
+            server.resource_pack.as_ref().map(|p| &p.hosting)
+        {
+            ports.push((*port, "TCP".to_string()));
+        }
+        Ok(ports)
     }
 
-    async fn update_server_property(
-        &self,
-        server_path: &Path,
-        key: &str,
-        value: &str,
-    ) -> Result<()> {
-        let props_path = server_path.join("server.properties");
-
-        // Read existing content or start empty
-        let content = if props_path.exists() {
-            fs::read_to_string(&props_path).await?
-        } else {
-            String::new()
-        };
-
-        let mut new_lines = Vec::new();
-        let mut found = false;
-
-        for line in content.lines() {
-            let mut matched = false;
-            // Ignore comments for keys
-            if !line.trim().starts_with('#') {
-                if let Some((k, _)) = line.split_once('=') {
-                    if k.trim() == key {
-                        new_lines.push(format!("{}={}", key, value));
-                        matched = true;
-                        found = true;
-                    }
-                }
-            }
-
-            if !matched {
-                new_lines.push(line.to_string());
+    /// Servers with Geyser and the Bedrock port each one listens on
+    async fn geyser_ports(&self) -> Vec<(ServerInfo, u16)> {
+        let servers = self.get_servers().await;
+        let mut ports = Vec::new();
+        for server in servers {
+            if let Some(port) = geyser_bedrock_port(&server).await {
+                ports.push((server, port));
             }
         }
+        ports
+    }
 
-        if !found {
-            new_lines.push(format!("{}={}", key, value));
-        }
+    /// Bedrock port of the server's Geyser, None without Geyser
+    pub async fn get_geyser_port(&self, server_id: &str) -> Result<Option<u16>> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        Ok(geyser_bedrock_port(&server).await)
+    }
 
-        if !found {
-            new_lines.push(format!("{}={}", key, value));
+    /// Move Geyser to another Bedrock port (UDP)
+    pub async fn set_geyser_port(&self, server_id: &str, port: u16) -> Result<()> {
+        let geyser_ports = self.geyser_ports().await;
+        let (server, _) = geyser_ports
+            .iter()
+            .find(|(s, _)| s.id == server_id)
+            .context("Geyser is not installed on this server")?;
+        if let Some((other, _)) = geyser_ports
+            .iter()
+            .find(|(s, p)| s.id != server_id && *p == port)
+        {
+            return Err(PrismarineError::port_in_use(
+                port,
+                None,
+                format!(
+                    "Bedrock port {} is already used by \"{}\"",
+                    port, other.name
+                ),
+            )
+            .into());
         }
-
-        fs::write(props_path, new_lines.join("\n")).await?;
-        Ok(())
+        write_geyser_bedrock_port(server, port).await
     }
 
-    pub async fn check_geyser_installed(&self, server_id: &str) -> Result<bool> {
+    /// Key settings of the server's Geyser config.yml (defaults before Geyser's first start)
+    pub async fn get_geyser_config(&self, server_id: &str) -> Result<GeyserConfig> {
         let server = self
-            .servers
-            .lock()
+            .get_server(server_id)
             .await
-            .get(server_id)
-            .context("Server not found")?
-            .clone();
-        let plugins_path = server.path.join("plugins");
+            .or_not_found("Server not found")?;
+        let bedrock_port = geyser_bedrock_port(&server)
+            .await
+            .context("Geyser is not installed on this server")?;
+        let variant = geyser_variant(&server.server_type)
+            .context("Geyser does not support this server type")?;
+        let config: serde_yaml::Value = fs::read_to_string(variant.config_path(&server.path))
+            .await
+            .ok()
+            .and_then(|content| serde_yaml::from_str(&content).ok())
+            .unwrap_or_default();
+        let flag = |path: &str, default: bool| {
+            yaml_path(&config, path)
+                .and_then(|v| v.as_bool())
+                .unwrap_or(default)
+        };
+        let text = |path: &str, default: &str| {
+            yaml_path(&config, path)
+                .and_then(|v| v.as_str())
+                .unwrap_or(default)
+                .to_string()
+        };
+        let floodgate_installed = variant.floodgate_jar_path(&server.path).exists();
+
+        Ok(GeyserConfig {
+            bedrock_port,
+            clone_remote_port: flag("bedrock.clone-remote-port", false),
+            auth_type: text(
+                "remote.auth-type",
+                if floodgate_installed {
+                    "floodgate"
+                } else {
+                    "online"
+                },
+            ),
+            passthrough_motd: flag("passthrough-motd", true),
+            passthrough_player_counts: flag("passthrough-player-counts", true),
+            motd1: text("bedrock.motd1", "Geyser"),
+            motd2: text("bedrock.motd2", "Another Geyser server."),
+            floodgate_installed,
+        })
+    }
 
-        let geyser_exists = plugins_path.join("Geyser-Spigot.jar").exists();
-        let floodgate_exists = plugins_path.join("floodgate-spigot.jar").exists();
+    /// Write the settings into Geyser's config.yml, keeping its comments.
+    /// Takes effect on the next start.
+    pub async fn set_geyser_config(&self, server_id: &str, config: &GeyserConfig) -> Result<()> {
+        self.ensure_writable()?;
+        if !GEYSER_AUTH_TYPES.contains(&config.auth_type.as_str()) {
+            anyhow::bail!("Unknown auth-type \"{}\"", config.auth_type);
+        }
+        let current = self.get_geyser_config(server_id).await?;
+        if config.auth_type == "floodgate" && !current.floodgate_installed {
+            anyhow::bail!("auth-type floodgate needs the Floodgate plugin, enable crossplay again to install it");
+        }
+        if config.bedrock_port != current.bedrock_port {
+            // Checks the port against the other Geyser servers
+            self.set_geyser_port(server_id, config.bedrock_port).await?;
+        }
 
-        println!(
-            "[Check] Server: {}, Geyser: {}, Floodgate: {}",
-            server_id, geyser_exists, floodgate_exists
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        let config_path = geyser_variant(&server.server_type)
+            .context("Geyser does not support this server type")?
+            .config_path(&server.path);
+        let mut content = fs::read_to_string(&config_path).await.unwrap_or_default();
+        for (key, value) in [
+            ("clone-remote-port", config.clone_remote_port.to_string()),
+            ("motd1", yaml_string(&config.motd1)),
+            ("motd2", yaml_string(&config.motd2)),
+        ] {
+            content = set_yaml_section_value(&content, "bedrock", key, &value);
+        }
+        content = set_yaml_section_value(&content, "remote", "auth-type", &config.auth_type);
+        content = set_yaml_top_value(
+            &content,
+            "passthrough-motd",
+            &config.passthrough_motd.to_string(),
         );
+        content = set_yaml_top_value(
+            &content,
+            "passthrough-player-counts",
+            &config.passthrough_player_counts.to_string(),
+        );
+        fs::write(&config_path, content)
+            .await
+            .context("Failed to write Geyser's config.yml")?;
+        println!("[Geyser] Updated the config of {}", server.name);
+        Ok(())
+    }
 
-        // Check server.properties for enforce-secure-profile=false
-        let props_path = server.path.join("server.properties");
-        let mut secure_profile_bg_check = false;
-
-        if props_path.exists() {
-            let content = fs::read_to_string(&props_path).await?;
-            for line in content.lines() {
-                let trimmed = line.trim();
-                // Ignore comments
-                if trimmed.starts_with('#') {
-                    continue;
-                }
-
-                if let Some((k, v)) = trimmed.split_once('=') {
-                    if k.trim() == "enforce-secure-profile" {
-                        println!("[Check] Found enforce-secure-profile value: '{}'", v.trim());
-                        if v.trim() == "false" {
-                            secure_profile_bg_check = true;
-                        }
-                        break;
-                    }
-                }
+    /// Give every Geyser server its own Bedrock port. Running servers keep theirs,
+    /// the others move to the next port that no server and no program uses.
+    pub async fn resolve_geyser_port_conflicts(&self) -> Result<Vec<GeyserPortChange>> {
+        let mut geyser_ports = self.geyser_ports().await;
+        geyser_ports.sort_by_key(|(s, _)| (s.status == ServerStatus::Stopped, s.name.clone()));
+
+        let mut taken: HashSet<u16> = HashSet::new();
+        let mut changes = Vec::new();
+        for (server, port) in &geyser_ports {
+            if taken.insert(*port) {
+                continue;
             }
-        } else {
-            println!("[Check] server.properties not found at {:?}", props_path);
-            secure_profile_bg_check = false;
+            let used: HashSet<u16> = geyser_ports.iter().map(|(_, p)| *p).collect();
+            let new_port = (DEFAULT_BEDROCK_PORT..=u16::MAX)
+                .find(|p| {
+                    !taken.contains(p)
+                        && !used.contains(p)
+                        && !crate::port_manager::is_port_bound(*p, "UDP")
+                })
+                .context("No free UDP port for Geyser")?;
+            write_geyser_bedrock_port(server, new_port).await?;
+            taken.insert(new_port);
+            println!(
+                "[Geyser] Bedrock port of {} moved from {} to {} (conflict)",
+                server.name, port, new_port
+            );
+            changes.push(GeyserPortChange {
+                server_id: server.id.clone(),
+                server_name: server.name.clone(),
+                java_port: server.port,
+                old_port: *port,
+                new_port,
+            });
         }
-
-        println!(
-            "[Check] Secure Profile Disabled: {}",
-            secure_profile_bg_check
-        );
-
-        // Treat as installed only if ALL conditions match.
-        Ok(geyser_exists && floodgate_exists && secure_profile_bg_check)
+        Ok(changes)
     }
 
-    pub async fn check_viaversion_installed(&self, server_id: &str) -> Result<bool> {
-        let server = self
+    /// Total RAM, available RAM and the heaps of the other running servers, in MB
+    async fn memory_budget(&self, server_id: &str) -> (u64, u64, u64) {
+        let other_servers_mb = self
             .servers
             .lock()
             .await
-            .get(server_id)
-            .context("Server not found")?
-            .clone();
-        let plugins_path = server.path.join("plugins");
-
-        Ok(plugins_path.join("ViaVersion.jar").exists())
+            .values()
+            .filter(|s| s.id != server_id && s.remote.is_none())
+            .filter(|s| s.status != ServerStatus::Stopped)
+            .filter_map(|s| parse_memory_mb(&s.max_memory))
+            .map(process_memory_mb)
+            .sum();
+        let mut system = sysinfo::System::new();
+        system.refresh_memory();
+        (
+            system.total_memory() / 1024 / 1024,
+            system.available_memory() / 1024 / 1024,
+            other_servers_mb,
+        )
     }
 
-    pub async fn search_plugins(
-        &self,
-        server_id: &str,
-        query: &str,
-        source: &str,
-    ) -> Result<Vec<PluginSearchResult>> {
-        let (version, server_type) = {
-            let servers = self.servers.lock().await;
-            let server = servers.get(server_id).context("Server not found")?;
-            (server.version.clone(), server.server_type.clone())
+    /// Refuse a start beyond the configured number of running servers
+    async fn check_running_limit(&self, server_id: &str) -> Result<()> {
+        let Some(max) = crate::limits::max_running_servers() else {
+            return Ok(());
         };
-
-        match source {
-            "Modrinth" => self.search_modrinth(query, &version, &server_type).await,
-            "Spigot" => self.search_spigot(query).await,
-            _ => Err(anyhow::anyhow!("Unknown source: {}", source)),
+        let running = self
+            .servers
+            .lock()
+            .await
+            .values()
+            .filter(|s| s.id != server_id && s.remote.is_none())
+            .filter(|s| s.status != ServerStatus::Stopped)
+            .count();
+        if running >= max {
+            return Err(PrismarineError::InvalidState {
+                message: format!(
+                    "{} servers are already running, the limit is {}. Stop one first or raise the limit in the settings",
+                    running, max
+                ),
+            }
+            .into());
         }
+        Ok(())
     }
 
-    pub async fn install_modrinth_plugin(
-        &self,
-        server_id: &str,
-        project_id: &str,
-        plugin_name: &str,
-    ) -> Result<()> {
-        let (version, server_type) = {
-            let servers = self.servers.lock().await;
-            let server = servers.get(server_id).context("Server not found")?;
-            (server.version.clone(), server.server_type.clone())
+    /// Refuse a start whose heap cannot fit in physical RAM next to the running servers
+    async fn check_memory(&self, server_id: &str) -> Result<()> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        let Some(max_mb) = parse_memory_mb(&server.max_memory) else {
+            return Ok(());
         };
+        let (total_mb, available_mb, other_servers_mb) = self.memory_budget(server_id).await;
+        let needed_mb = process_memory_mb(max_mb);
+        let room_mb = total_mb.saturating_sub(other_servers_mb);
+
+        if needed_mb > room_mb {
+            let suggestion = self.suggest_memory(server_id).await?;
+            return Err(PrismarineError::insufficient_memory(
+                max_mb,
+                room_mb,
+                Some(suggestion.suggested_mb),
+                format!(
+                    "{} needs about {} MB but this machine has {} MB of RAM and {} MB is taken by other running servers. Lower the memory to {} or stop another server",
+                    server.max_memory, needed_mb, total_mb, other_servers_mb, suggestion.suggested
+                ),
+            )
+            .into());
+        }
+        if needed_mb > available_mb {
+            // Fits in RAM, the OS will page out caches and other programs
+            println!(
+                "[ServerManager] {} needs {} MB, only {} MB is free right now",
+                server.name, needed_mb, available_mb
+            );
+        }
+        Ok(())
+    }
 
-        // Map ServerType to Modrinth loaders
-        // Paper keys can include "paper", "spigot", "bukkit"
-        // Spigot keys: "spigot", "bukkit"
-        // Vanilla: usually doesn't have plugins, but maybe "datapack"? Assuming plugin for now.
-        let loaders = match server_type {
-            ServerType::Paper | ServerType::Purpur => "[\"bukkit\", \"paper\", \"spigot\"]",
-            ServerType::Spigot => "[\"bukkit\", \"spigot\"]",
-            ServerType::Forge => "[\"forge\"]",
-            ServerType::Vanilla => "[\"bukkit\"]", // Fallback
-            ServerType::Fabric
+    /// Heap size that suits the server type and fits next to the running servers
+    pub async fn suggest_memory(&self, server_id: &str) -> Result<MemorySuggestion> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        let (total_mb, available_mb, other_servers_mb) = self.memory_budget(server_id).await;
+
+        let (wanted_mb, why) = match server.server_type {
+            ServerType::Velocity | ServerType::Waterfall | ServerType::BungeeCord => {
+                (512, "Proxies need little memory")
+            }
+            ServerType::Forge
+            | ServerType::Fabric
             | ServerType::Mohist
             | ServerType::Taiyitist
-            | ServerType::Banner => "[]", // No plugin support or different system
-            ServerType::Velocity => "[\"velocity\"]",
-            ServerType::BungeeCord => "[\"bungeecord\"]",
-            ServerType::Waterfall => "[\"bungeecord\",\"waterfall\"]",
+            | ServerType::Banner => (6144, "Modded servers need more memory"),
+            _ => (4096, "Plenty for a plugin server with a few dozen players"),
+        };
+        // Heap that still leaves the OS its share once the JVM overhead is added
+        let room_mb = total_mb
+            .saturating_sub(other_servers_mb)
+            .saturating_sub(OS_RESERVED_MB);
+        let fitting_mb = room_mb.saturating_sub(JVM_OVERHEAD_MB.max(room_mb / 11));
+        let step = if wanted_mb < 1024 { 256 } else { 512 };
+        let suggested_mb = (wanted_mb.min(fitting_mb) / step * step).max(512);
+        let reason = if suggested_mb < wanted_mb {
+            format!(
+                "{} MB would fit in the {} MB of RAM left for this server",
+                suggested_mb, room_mb
+            )
+        } else {
+            why.to_string()
         };
 
-        let game_versions = format!("[\"{}\"]", version);
-
-        let client = reqwest::Client::builder()
-            .user_agent("MinecraftServerManager/0.1.0 (antigravity)")
-            .build()?;
+        Ok(MemorySuggestion {
+            suggested_mb,
+            suggested: format_memory_mb(suggested_mb),
+            total_mb,
+            available_mb,
+            other_servers_mb,
+            reason,
+        })
+    }
 
-        // Fetch versions filtered by loader and game version
-        let url = format!(
-            "https://api.modrinth.com/v2/project/{}/version?loaders={}&game_versions={}",
-            project_id, loaders, game_versions
-        );
+    /// Checklist of everything a first start needs (port, Java, EULA, memory, disk)
+    pub async fn check_readiness(&self, server_id: &str) -> Result<ServerReadiness> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        let mut checks = Vec::new();
+        let mut check = |id: &str, ok: bool, blocker: bool, message: String| {
+            checks.push(ReadinessCheck {
+                id: id.to_string(),
+                ok,
+                blocker: !ok && blocker,
+                message,
+            });
+        };
 
-        let resp = client.get(&url).send().await?;
-        if !resp.status().is_success() {
-            let status = resp.status();
-            let text = resp.text().await.unwrap_or_default();
-            println!("Modrinth API Error: {} - Body: {}", status, text);
-            anyhow::bail!("Modrinth API failed with status {}: {}", status, text);
+        match self.check_ports_available(server_id).await {
+            Ok(()) => check("port", true, true, format!("Port {} is free", server.port)),
+            Err(e) => check("port", false, true, e.to_string()),
         }
 
-        let resp_text = resp.text().await?;
-        let versions: serde_json::Value =
-            serde_json::from_str(&resp_text).context("Failed to parse Modrinth JSON")?;
-
-        let versions = versions
-            .as_array()
-            .context("Invalid Modrinth version response")?;
+        let version = server.version.clone();
+        let modded = is_forge_based(&server.server_type);
+        let java_override = server.java_path_override.clone();
+        let java = tokio::task::spawn_blocking(move || match java_override {
+            Some(path) => crate::java_detector::get_java_version(&path).map(|_| path),
+            None => crate::java_detector::select_java_for_server(&version, modded),
+        })
+        .await?;
+        let required_java = crate::java_detector::get_required_java_version(&server.version);
+        check(
+            "java",
+            java.is_some(),
+            false,
+            match java {
+                Some(path) => format!("Java {}+ found at {}", required_java, path),
+                None => format!(
+                    "Java {} or newer was not found and will be downloaded on start",
+                    required_java
+                ),
+            },
+        );
 
-        if versions.is_empty() {
-            anyhow::bail!(
-                "No compatible version found for Minecraft {} ({:?})",
-                version,
-                server_type
+        if requires_eula(&server.server_type) {
+            check(
+                "eula",
+                server.eula_accepted,
+                true,
+                if server.eula_accepted {
+                    "Minecraft EULA accepted".to_string()
+                } else {
+                    "The Minecraft EULA must be accepted before the first start".to_string()
+                },
             );
         }
 
-        // Pick the first one (latest compatible)
-        let latest = &versions[0];
-        let files = latest["files"]
-            .as_array()
-            .context("No files found in version")?;
+        // -Xms is committed up front (AlwaysPreTouch), -Xmx only as the world grows
+        let mut system = sysinfo::System::new();
+        system.refresh_memory();
+        let available_mb = system.available_memory() / 1024 / 1024;
+        let min_mb = parse_memory_mb(&server.min_memory).unwrap_or(0);
+        let max_mb = parse_memory_mb(&server.max_memory).unwrap_or(0);
+        check(
+            "memory",
+            available_mb >= max_mb,
+            available_mb < min_mb,
+            format!(
+                "{} MB of memory free, the server is configured for {} - {}",
+                available_mb, server.min_memory, server.max_memory
+            ),
+        );
 
-        // Find the primary file or first .jar
-        let file = files
+        let disks = sysinfo::Disks::new_with_refreshed_list();
+        let free_disk = disks
+            .list()
             .iter()
-            .find(|f| {
-                f["primary"].as_bool().unwrap_or(false)
-                    || f["filename"].as_str().unwrap_or("").ends_with(".jar")
-            })
-            .or(files.first())
-            .context("No suitable file found")?;
+            .filter(|d| server.path.starts_with(d.mount_point()))
+            .max_by_key(|d| d.mount_point().as_os_str().len())
+            .map(|d| d.available_space());
+        match free_disk {
+            Some(free) => check(
+                "disk",
+                free >= LOW_FREE_DISK_BYTES,
+                free < MIN_FREE_DISK_BYTES,
+                format!("{} MB free on the server's drive", free / 1024 / 1024),
+            ),
+            None => check(
+                "disk",
+                true,
+                false,
+                "Free disk space could not be determined".to_string(),
+            ),
+        }
 
-        let download_url = file["url"].as_str().context("No download URL")?.to_string();
+        Ok(ServerReadiness {
+            ready: !checks.iter().any(|c| c.blocker),
+            checks,
+        })
+    }
 
-        // Sanitize plugin name for filename (remove invalid characters)
-        let safe_name: String = plugin_name
-            .chars()
+    pub async fn start_server(&self, server_id: &str) -> Result<()> {
+        self.ensure_writable()?;
+        if let Some(server) = self
+            .get_server(server_id)
+            .await
+            .filter(|s| s.remote.is_some())
+        {
+            return self.remote_power(&server, "start").await;
+        }
+        self.check_ports_available(server_id).await?;
+        self.check_running_limit(server_id).await?;
+        self.check_memory(server_id).await?;
+        let repairs = self.repair_server_files(server_id).await?;
+
+        let server_info = {
+            let mut servers = self.servers.lock().await;
+            let server = servers
+                .get_mut(server_id)
+                .or_not_found("Server not found")?;
+
+            if server.status == ServerStatus::Running {
+                return Err(PrismarineError::invalid_state("Server is already running").into());
+            }
+            if !server.eula_accepted && requires_eula(&server.server_type) {
+                anyhow::bail!(
+                    "The Minecraft EULA (https://aka.ms/MinecraftEULA) has not been accepted for this server"
+                );
+            }
+
+            set_status(server, ServerStatus::Starting);
+            server.last_start_time = Some(
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs(),
+            );
+            server.clone()
+        };
+
+        // Map plugins write their config on first start, keep our port in it
+        if let Err(e) = self.apply_web_map_config(&server_info).await {
+            println!("[WebMap] {}: {}", server_info.name, e);
+        }
+        if let Err(e) = self.apply_query_config(&server_info).await {
+            println!("[Query] {}: {}", server_info.name, e);
+        }
+        let proxy_protocol = geyser_proxy_protocol(&server_info).await;
+        self.geyser_proxy_protocol
+            .lock()
+            .unwrap()
+            .insert(server_id.to_string(), proxy_protocol);
+
+        // Auto-select Java based on Minecraft version, installing it when missing
+        let java_cmd = match &server_info.java_path_override {
+            Some(path) => Ok(path.clone()),
+            None => {
+                crate::java_manager::JavaManager::new()
+                    .get_java_executable(
+                        &server_info.version,
+                        is_forge_based(&server_info.server_type),
+                    )
+                    .await
+            }
+        };
+        let java_cmd = match java_cmd {
+            Ok(java_cmd) => java_cmd,
+            Err(e) => {
+                if let Some(server) = self.servers.lock().await.get_mut(server_id) {
+                    set_status(server, ServerStatus::Stopped);
+                }
+                return Err(e.context("No suitable Java runtime"));
+            }
+        };
+
+        let java = java_cmd.clone();
+        let java_major =
+            tokio::task::spawn_blocking(move || crate::java_detector::get_java_version(&java))
+                .await
+                .ok()
+                .flatten();
+
+        let mut jvm_args = match &server_info.custom_jvm_args {
+            Some(custom) => {
+                let mut args = Vec::new();
+                if !custom.iter().any(|arg| arg.starts_with("-Xmx")) {
+                    args.push(format!("-Xmx{}", server_info.max_memory));
+                }
+                if !custom.iter().any(|arg| arg.starts_with("-Xms")) {
+                    args.push(format!("-Xms{}", server_info.min_memory));
+                }
+                args.extend(custom.iter().cloned());
+                args
+            }
+            None => profile_jvm_args(
+                server_info.jvm_profile,
+                &server_info.max_memory,
+                &server_info.min_memory,
+                java_major,
+            ),
+        };
+
+        // Server launch arguments (jar or Forge argument files)
+        jvm_args.extend(server_launch_args(
+            &server_info.path,
+            &server_info.server_type,
+            &server_info.version,
+        ));
+
+        let mut banner = {
+            let (server, java, args) = (server_info.clone(), java_cmd.clone(), jvm_args.clone());
+            tokio::task::spawn_blocking(move || launch_banner(&server, &java, java_major, &args))
+                .await?
+        };
+        banner.extend(
+            repairs
+                .iter()
+                .map(|repair| format!("[Prismarine] Repaired: {}", repair)),
+        );
+        self.record_launch_banner(&server_info, banner).await;
+
+        let mut command = Command::new(java_cmd);
+        command
+            .args(&jvm_args)
+            .current_dir(&server_info.path)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .stdin(Stdio::piped());
+        if server_info.server_type == ServerType::Velocity {
+            // Velocity prefers this over forwarding-secret-file, so the secret
+            // stays in the secret store instead of a plaintext file
+            command.env(
+                "VELOCITY_FORWARDING_SECRET",
+                forwarding_secret(&server_info).await?,
+            );
+        }
+        let child = command.spawn().context("Failed to start server process")?;
+        let pid = child.id();
+
+        self.processes
+            .lock()
+            .unwrap()
+            .insert(server_id.to_string(), child);
+
+        let mut servers = self.servers.lock().await;
+        if let Some(server) = servers.get_mut(server_id) {
+            set_status(server, ServerStatus::Running);
+            server.pid = pid;
+            // The server reads the distances written while it was running
+            if let Some(dvd) = server.dynamic_view_distance.as_mut() {
+                if let Some(tier) = dvd.pending_tier.take() {
+                    dvd.applied_tier = Some(tier);
+                }
+            }
+            // First start since the manager changed the server: roll back if it never comes up
+            let id = server_id.to_string();
+            if tokio::task::spawn_blocking(move || crate::restore_points::pending(&id))
+                .await
+                .ok()
+                .flatten()
+                .is_some()
+            {
+                self.boot_watches
+                    .lock()
+                    .unwrap()
+                    .insert(server_id.to_string(), server.last_start_time.unwrap_or(0));
+            }
+        }
+        drop(servers);
+
+        self.open_auto_ports(server_id).await;
+        Ok(())
+    }
+
+    /// Keep the banner for the console and append it to logs/prismarine-launch.log.
+    /// latest.log is rolled over by the server at startup, so it can't hold the banner.
+    async fn record_launch_banner(&self, server: &ServerInfo, banner: Vec<String>) {
+        for line in &banner {
+            println!("{}", line);
+        }
+        let stamp = chrono::Local::now().format("%Y-%m-%d %H:%M:%S");
+        let entry: String = banner
+            .iter()
+            .map(|line| format!("[{}] {}\n", stamp, line))
+            .collect();
+        let log_dir = server.path.join("logs");
+        let written = async {
+            fs::create_dir_all(&log_dir).await?;
+            fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(log_dir.join("prismarine-launch.log"))
+                .await?
+                .write_all(entry.as_bytes())
+                .await
+        };
+        if let Err(e) = written.await {
+            println!("[ServerManager] Failed to write the launch log: {}", e);
+        }
+        self.launch_banners
+            .lock()
+            .unwrap()
+            .insert(server.id.clone(), banner);
+    }
+
+    /// Banner of the server's last start in this session
+    pub fn launch_banner(&self, server_id: &str) -> Vec<String> {
+        self.launch_banners
+            .lock()
+            .unwrap()
+            .get(server_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub async fn stop_server(&self, server_id: &str) -> Result<()> {
+        // Stopped on purpose, not a failed boot
+        self.boot_watches.lock().unwrap().remove(server_id);
+        if let Some(server) = self
+            .get_server(server_id)
+            .await
+            .filter(|s| s.remote.is_some())
+        {
+            return self.remote_power(&server, "stop").await;
+        }
+
+        // Set status to Stopping first
+        {
+            let mut servers = self.servers.lock().await;
+            if let Some(server) = servers.get_mut(server_id) {
+                // Already stopped or stopping - skip
+                if server.status == ServerStatus::Stopped || server.status == ServerStatus::Stopping
+                {
+                    return Ok(());
+                }
+                set_status(server, ServerStatus::Stopping);
+            }
+        }
+
+        let adopted_pid = self.adopted.lock().unwrap().get(server_id).copied();
+        if let Some(pid) = adopted_pid {
+            return self.stop_adopted(server_id, pid).await;
+        }
+
+        // Try to send "stop" command for graceful shutdown
+        let graceful_attempt = self.send_command(server_id, "stop").await;
+        let start_time = std::time::Instant::now();
+        let timeout = std::time::Duration::from_secs(30);
+
+        if graceful_attempt.is_ok() {
+            // Wait for server to shut down gracefully using try_wait()
+            // Poll every 200ms for faster response
+            loop {
+                if start_time.elapsed() >= timeout {
+                    println!("[ServerManager] Graceful shutdown timeout reached");
+                    break;
+                }
+
+                // Check if process has exited using try_wait()
+                let process_exited = {
+                    let mut processes = self.processes.lock().unwrap();
+                    if let Some(process) = processes.get_mut(server_id) {
+                        match process.try_wait() {
+                            Ok(Some(_exit_status)) => {
+                                // Process has exited
+                                println!("[ServerManager] Process exited gracefully");
+                                true
+                            }
+                            Ok(None) => {
+                                // Process still running
+                                false
+                            }
+                            Err(_) => {
+                                // Error checking status, assume still running
+                                false
+                            }
+                        }
+                    } else {
+                        // Process not in map, already removed
+                        true
+                    }
+                };
+
+                if process_exited {
+                    // Remove from processes map
+                    self.processes.lock().unwrap().remove(server_id);
+                    break;
+                }
+
+                tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+            }
+
+            println!(
+                "[ServerManager] Graceful stop completed in {:?}",
+                start_time.elapsed()
+            );
+        }
+
+        // Force kill if still running (fallback)
+        let still_running = self.processes.lock().unwrap().contains_key(server_id);
+        if still_running {
+            self.flush_before_kill(server_id).await;
+        }
+        let process = self.processes.lock().unwrap().remove(server_id);
+        if let Some(mut process) = process {
+            if matches!(process.try_wait(), Ok(Some(_))) {
+                println!("[ServerManager] Process exited after the flush");
+            } else if let Err(e) = process.start_kill() {
+                println!("[ServerManager] Failed to kill process: {}", e);
+            } else {
+                println!("[ServerManager] Process force killed after graceful attempt");
+                let _ =
+                    tokio::time::timeout(std::time::Duration::from_secs(5), process.wait()).await;
+                self.check_regions_after_kill(server_id).await;
+            }
+        }
+
+        // Update server status
+        let mut servers = self.servers.lock().await;
+        if let Some(server) = servers.get_mut(server_id) {
+            set_status(server, ServerStatus::Stopped);
+            server.last_start_time = None;
+            server.pid = None;
+        }
+        drop(servers);
+
+        self.close_auto_ports(server_id).await;
+        Ok(())
+    }
+
+    /// Last chance to get chunks on disk before a kill: "save-all flush", then
+    /// wait a little for the save to finish (or the process to exit after all)
+    async fn flush_before_kill(&self, server_id: &str) {
+        let Some(server) = self.get_server(server_id).await else {
+            return;
+        };
+        let log = server.path.join("logs").join("latest.log");
+        let log_len = fs::metadata(&log).await.map(|m| m.len()).unwrap_or(0);
+        if self
+            .send_command(server_id, "save-all flush")
+            .await
+            .is_err()
+        {
+            return;
+        }
+        println!("[ServerManager] Flushing {} before the kill", server.name);
+
+        let deadline = std::time::Instant::now() + SAVE_BEFORE_KILL;
+        while std::time::Instant::now() < deadline {
+            tokio::time::sleep(std::time::Duration::from_millis(250)).await;
+            let exited = match self.processes.lock().unwrap().get_mut(server_id) {
+                Some(process) => matches!(process.try_wait(), Ok(Some(_))),
+                None => true,
+            };
+            if exited {
+                return;
+            }
+            let saved = fs::read(&log).await.is_ok_and(|data| {
+                data.get(log_len as usize..)
+                    .is_some_and(|new| String::from_utf8_lossy(new).contains("Saved the game"))
+            });
+            if saved {
+                return;
+            }
+        }
+    }
+
+    /// Look for region files damaged by a forced kill and flag the server
+    async fn check_regions_after_kill(&self, server_id: &str) {
+        let Some(server) = self.get_server(server_id).await else {
+            return;
+        };
+        let level_name = self
+            .read_server_property(&server.path, "level-name")
+            .await
+            .unwrap_or_else(|| "world".to_string());
+        let since = server
+            .last_start_time
+            .map(|secs| std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs));
+        let path = server.path.clone();
+        let Ok(report) = tokio::task::spawn_blocking(move || {
+            crate::region_check::check(&path, &level_name, since)
+        })
+        .await
+        else {
+            return;
+        };
+
+        if report.problems.is_empty() {
+            println!(
+                "[RegionCheck] {} region files of {} look intact",
+                report.files_checked, server.name
+            );
+            return;
+        }
+        println!(
+            "[RegionCheck] {} may have damaged regions after the kill: {}",
+            server.name,
+            report.problems.join("; ")
+        );
+        if let Some(server) = self.servers.lock().await.get_mut(server_id) {
+            server.region_warning = Some(report);
+        }
+    }
+
+    pub async fn dismiss_region_warning(&self, server_id: &str) -> Result<()> {
+        let mut servers = self.servers.lock().await;
+        let server = servers
+            .get_mut(server_id)
+            .or_not_found("Server not found")?;
+        server.region_warning = None;
+        Ok(())
+    }
+
+    /// Stop a server adopted from a previous session: "stop" over RCON, else a
+    /// termination signal (the server saves on SIGTERM), killed after 30 seconds
+    async fn stop_adopted(&self, server_id: &str, pid: u32) -> Result<()> {
+        if let Err(e) = self.send_command(server_id, "stop").await {
+            println!("[ServerManager] {}, terminating process {}", e, pid);
+            tokio::task::spawn_blocking(move || terminate_process(pid, false)).await?;
+        }
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(30);
+        let mut killed = false;
+        while tokio::task::spawn_blocking(move || process_alive(pid)).await? {
+            if std::time::Instant::now() > deadline {
+                println!(
+                    "[ServerManager] Adopted process {} did not exit, killing it",
+                    pid
+                );
+                tokio::task::spawn_blocking(move || terminate_process(pid, true)).await?;
+                killed = true;
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        }
+
+        if killed {
+            self.check_regions_after_kill(server_id).await;
+        }
+
+        self.adopted.lock().unwrap().remove(server_id);
+        if let Some(server) = self.servers.lock().await.get_mut(server_id) {
+            set_status(server, ServerStatus::Stopped);
+            server.last_start_time = None;
+            server.pid = None;
+        }
+        self.close_auto_ports(server_id).await;
+        Ok(())
+    }
+
+    /// Reconcile statuses saved by a previous session that ended while servers
+    /// ran. A server whose saved pid is still a Java process in its folder is
+    /// adopted as Running: commands go over RCON and it is stopped through the
+    /// OS. Every other server is marked Stopped. Returns the adopted ids.
+    pub async fn recover_processes(&self) -> Vec<String> {
+        let candidates: Vec<(String, Option<u32>, PathBuf)> = self
+            .servers
+            .lock()
+            .await
+            .values()
+            .filter(|s| s.remote.is_none())
+            .filter(|s| s.status != ServerStatus::Stopped || s.pid.is_some())
+            .map(|s| (s.id.clone(), s.pid, s.path.clone()))
+            .collect();
+        if candidates.is_empty() {
+            return Vec::new();
+        }
+
+        let checked = tokio::task::spawn_blocking(move || {
+            candidates
+                .into_iter()
+                .map(|(id, pid, path)| {
+                    let running = pid.filter(|pid| is_server_process(*pid, &path));
+                    (id, running)
+                })
+                .collect::<Vec<_>>()
+        })
+        .await
+        .unwrap_or_default();
+
+        let mut adopted = Vec::new();
+        let mut servers = self.servers.lock().await;
+        for (id, running) in checked {
+            let Some(server) = servers.get_mut(&id) else {
+                continue;
+            };
+            match running {
+                Some(pid) => {
+                    println!(
+                        "[ServerManager] {} is still running (pid {}), reattached",
+                        server.name, pid
+                    );
+                    set_status(server, ServerStatus::Running);
+                    self.adopted.lock().unwrap().insert(id.clone(), pid);
+                    adopted.push(id);
+                }
+                None => {
+                    set_status(server, ServerStatus::Stopped);
+                    server.pid = None;
+                    server.last_start_time = None;
+                }
+            }
+        }
+        adopted
+    }
+
+    /// Names of the servers running on this machine (remote servers are not ours to stop)
+    pub async fn running_local_servers(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .servers
+            .lock()
+            .await
+            .values()
+            .filter(|s| s.status != ServerStatus::Stopped && s.remote.is_none())
+            .map(|s| s.name.clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Whether the server's own or adopted process is still running
+    pub fn is_process_alive(&self, server_id: &str) -> bool {
+        let mut processes = self.processes.lock().unwrap();
+        match processes.get_mut(server_id) {
+            Some(process) => matches!(process.try_wait(), Ok(None)),
+            None => {
+                let adopted = self.adopted.lock().unwrap().get(server_id).copied();
+                adopted.is_some_and(process_alive)
+            }
+        }
+    }
+
+    /// Whether the server runs in a process left by a previous session (no console stdin)
+    pub fn is_adopted(&self, server_id: &str) -> bool {
+        self.adopted.lock().unwrap().contains_key(server_id)
+    }
+
+    /// Gracefully stop every running server (save-all, then stop).
+    /// Returns the IDs of the servers that were running.
+    pub async fn stop_all_servers(&self) -> Vec<String> {
+        let running_ids: Vec<String> = {
+            let servers = self.servers.lock().await;
+            servers
+                .values()
+                .filter(|s| s.status == ServerStatus::Running || s.status == ServerStatus::Starting)
+                .filter(|s| s.remote.is_none())
+                .map(|s| s.id.clone())
+                .collect()
+        };
+
+        for id in &running_ids {
+            let _ = self.send_command(id, "save-all").await;
+        }
+
+        for id in &running_ids {
+            if let Err(e) = self.stop_server(id).await {
+                println!("[ServerManager] Failed to stop server {}: {}", id, e);
+            }
+        }
+
+        running_ids
+    }
+
+    /// Treat servers that exit from now on as stopped by the sleep, not crashed,
+    /// until `finish_resume`
+    pub fn begin_suspend(&self) {
+        self.suspended
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+    }
+
+    /// Servers that exited while the machine was asleep, to be started again
+    pub async fn finish_resume(&self) -> Vec<String> {
+        self.verify_processes().await;
+        self.suspended
+            .store(false, std::sync::atomic::Ordering::SeqCst);
+        std::mem::take(&mut *self.suspend_exits.lock().unwrap())
+    }
+
+    /// Re-check that servers marked as running still have a live process.
+    /// Servers whose process has exited are marked as stopped; their IDs are returned.
+    /// Exits while suspended are no crashes and are returned by `finish_resume` instead.
+    pub async fn verify_processes(&self) -> Vec<String> {
+        let suspended = self.suspended.load(std::sync::atomic::Ordering::SeqCst);
+        let mut dead_ids = Vec::new();
+        let mut exited = Vec::new();
+        let mut servers = self.servers.lock().await;
+
+        for (id, server) in servers.iter_mut() {
+            if server.status != ServerStatus::Running && server.status != ServerStatus::Starting {
+                continue;
+            }
+            if server.remote.is_some() {
+                continue;
+            }
+
+            if !self.is_process_alive(id) {
+                println!(
+                    "[ServerManager] Server {} is no longer running, marking as stopped",
+                    server.name
+                );
+                self.processes.lock().unwrap().remove(id);
+                self.adopted.lock().unwrap().remove(id);
+                set_status(server, ServerStatus::Stopped);
+                server.last_start_time = None;
+                server.pid = None;
+                if suspended {
+                    self.suspend_exits.lock().unwrap().push(id.clone());
+                } else {
+                    crate::reports::record_crash(id);
+                    dead_ids.push(id.clone());
+                }
+                exited.push(id.clone());
+            }
+        }
+        drop(servers);
+
+        for id in &exited {
+            self.close_auto_ports(id).await;
+        }
+        dead_ids
+    }
+
+    pub async fn set_console_safety(&self, server_id: &str, enabled: bool) -> Result<()> {
+        let mut servers = self.servers.lock().await;
+        let server = servers
+            .get_mut(server_id)
+            .or_not_found("Server not found")?;
+        server.console_safety = enabled;
+        Ok(())
+    }
+
+    pub async fn rename_server(&self, server_id: &str, new_name: &str) -> Result<()> {
+        let new_name = new_name.trim();
+        if new_name.is_empty() {
+            anyhow::bail!("Server name cannot be empty");
+        }
+
+        let mut servers = self.servers.lock().await;
+        let server = servers
+            .get_mut(server_id)
+            .or_not_found("Server not found")?;
+        server.name = new_name.to_string();
+        Ok(())
+    }
+
+    /// Move the server directory into `new_base_path` (e.g. another drive),
+    /// stopping the server first if it is running
+    pub async fn move_server<F>(
+        &self,
+        server_id: &str,
+        new_base_path: &Path,
+        on_progress: F,
+    ) -> Result<ServerInfo>
+    where
+        F: Fn(MoveProgress) + Send + Sync + 'static,
+    {
+        let _slot = crate::limits::BACKUP_JOBS.acquire().await;
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        let folder_name = server
+            .path
+            .file_name()
+            .context("Invalid server directory")?;
+        let new_path = new_base_path.join(folder_name);
+
+        if new_path == server.path {
+            return Ok(server);
+        }
+        if new_path.exists() {
+            anyhow::bail!("{} already exists", new_path.display());
+        }
+        if new_path.starts_with(&server.path) {
+            anyhow::bail!("Cannot move a server into its own directory");
+        }
+
+        if server.status != ServerStatus::Stopped {
+            println!("[ServerManager] Stopping {} before moving it", server.name);
+            self.stop_server(server_id).await?;
+        }
+
+        println!(
+            "[ServerManager] Moving {} to {}",
+            server.path.display(),
+            new_path.display()
+        );
+        let on_progress = Arc::new(on_progress);
+        let report = on_progress.clone();
+        let id = server_id.to_string();
+        move_dir(&server.path, &new_path, move |copied, total| {
+            report(MoveProgress {
+                server_id: id.clone(),
+                copied,
+                total,
+                done: false,
+            })
+        })
+        .await
+        .context("Failed to move server directory")?;
+
+        let updated = {
+            let mut servers = self.servers.lock().await;
+            let entry = servers
+                .get_mut(server_id)
+                .or_not_found("Server not found")?;
+            entry.path = new_path;
+            entry.clone()
+        };
+        on_progress(MoveProgress {
+            server_id: server_id.to_string(),
+            copied: 0,
+            total: 0,
+            done: true,
+        });
+        Ok(updated)
+    }
+
+    /// Send a command typed in the console UI.
+    /// With console safety enabled, dangerous commands are rejected unless `confirmed`.
+    pub async fn send_console_command(
+        &self,
+        server_id: &str,
+        command: &str,
+        confirmed: bool,
+    ) -> Result<()> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+
+        if server.console_safety && !confirmed {
+            let known_players = read_known_players(&server.path).await;
+            if let Some(reason) = dangerous_command_reason(command, &known_players) {
+                anyhow::bail!(
+                    "Blocked by console safety mode: {}. Send the command again with confirmation to run it.",
+                    reason
+                );
+            }
+        }
+
+        self.send_command(server_id, command).await?;
+
+        // Remember in the recent list (deduplicated, newest first)
+        let command = command.trim().to_string();
+        let mut servers = self.servers.lock().await;
+        if let Some(server) = servers.get_mut(server_id) {
+            server.recent_commands.retain(|c| c != &command);
+            server.recent_commands.insert(0, command);
+            server.recent_commands.truncate(MAX_RECENT_COMMANDS);
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_pinned_commands(&self, server_id: &str) -> Result<Vec<PinnedCommand>> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        Ok(server.pinned_commands)
+    }
+
+    pub async fn get_recent_commands(&self, server_id: &str) -> Result<Vec<String>> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        Ok(server.recent_commands)
+    }
+
+    /// Pin a command (or multi-line macro) under a label; replaces an existing pin with the same label
+    pub async fn pin_command(&self, server_id: &str, label: &str, command: &str) -> Result<()> {
+        if label.trim().is_empty() || command.trim().is_empty() {
+            anyhow::bail!("Label and command must not be empty");
+        }
+
+        let mut servers = self.servers.lock().await;
+        let server = servers
+            .get_mut(server_id)
+            .or_not_found("Server not found")?;
+        let pinned = PinnedCommand {
+            label: label.trim().to_string(),
+            command: command.trim().to_string(),
+        };
+
+        if let Some(existing) = server
+            .pinned_commands
+            .iter_mut()
+            .find(|p| p.label == pinned.label)
+        {
+            *existing = pinned;
+        } else {
+            server.pinned_commands.push(pinned);
+        }
+        Ok(())
+    }
+
+    pub async fn unpin_command(&self, server_id: &str, label: &str) -> Result<()> {
+        let mut servers = self.servers.lock().await;
+        let server = servers
+            .get_mut(server_id)
+            .or_not_found("Server not found")?;
+        server.pinned_commands.retain(|p| p.label != label);
+        Ok(())
+    }
+
+    /// Run a pinned command; each line of a macro is sent as its own console command
+    pub async fn run_pinned_command(
+        &self,
+        server_id: &str,
+        label: &str,
+        confirmed: bool,
+    ) -> Result<()> {
+        let pinned = self
+            .get_pinned_commands(server_id)
+            .await?
+            .into_iter()
+            .find(|p| p.label == label)
+            .or_not_found("Pinned command not found")?;
+
+        for line in pinned.command.lines().filter(|l| !l.trim().is_empty()) {
+            self.send_console_command(server_id, line, confirmed)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Send a command to a running server
+    pub async fn send_command(&self, server_id: &str, command: &str) -> Result<()> {
+        if let Some(server) = self
+            .get_server(server_id)
+            .await
+            .filter(|s| s.remote.is_some())
+        {
+            let (panel, api_key) = self.remote_panel(&server).await?;
+            return crate::remote::send_command(&panel, &api_key, command).await;
+        }
+        // Adopted servers have no stdin, RCON is the only way in
+        if !self.processes.lock().unwrap().contains_key(server_id) && self.is_adopted(server_id) {
+            let server = self
+                .get_server(server_id)
+                .await
+                .or_not_found("Server not found")?;
+            let (port, password) = self.rcon_credentials(&server).await.context(
+                "The server was started by an earlier session, enable RCON to send it commands",
+            )?;
+            crate::console_bridge::rcon_command(port, &password, command).await?;
+            println!(
+                "Sent command to server {} over RCON: {}",
+                server_id, command
+            );
+            return Ok(());
+        }
+
+        // Get stdin handle - we need to release the lock before await
+        let mut stdin_handle = {
+            let mut processes = self.processes.lock().unwrap();
+            let process = processes
+                .get_mut(server_id)
+                .or_not_found("Server not found or not running")?;
+
+            process.stdin.take().context("Server stdin not available")?
+        };
+
+        // Write command followed by newline
+        let command_line = format!("{}\n", command.trim());
+        stdin_handle
+            .write_all(command_line.as_bytes())
+            .await
+            .context("Failed to write command to server")?;
+        stdin_handle
+            .flush()
+            .await
+            .context("Failed to flush command to server")?;
+
+        // Put stdin back
+        {
+            let mut processes = self.processes.lock().unwrap();
+            if let Some(process) = processes.get_mut(server_id) {
+                process.stdin = Some(stdin_handle);
+            }
+        }
+
+        println!("Sent command to server {}: {}", server_id, command);
+        Ok(())
+    }
+
+    pub async fn get_servers(&self) -> Vec<ServerInfo> {
+        self.servers.lock().await.values().cloned().collect()
+    }
+
+    /// Servers matching `query`, in the order it asks for
+    pub async fn query_servers(&self, query: &ServerQuery) -> Vec<ServerSummary> {
+        let servers = self.servers.lock().await;
+        let (page, _) = query.page(servers.values());
+        page.into_iter().map(ServerSummary::from).collect()
+    }
+
+    /// All servers and a page of `query`, so the list view needs one call
+    pub async fn list_servers(&self, query: &ServerQuery) -> ServerListing {
+        let servers = self.servers.lock().await;
+        let (page, total) = query.page(servers.values());
+        ServerListing {
+            servers: servers.values().map(ServerSummary::from).collect(),
+            page: page.into_iter().map(|s| s.id.clone()).collect(),
+            total,
+        }
+    }
+
+    /// Replace the server's tags (trimmed, empty and duplicate tags dropped)
+    pub async fn set_server_tags(&self, server_id: &str, tags: Vec<String>) -> Result<Vec<String>> {
+        let mut servers = self.servers.lock().await;
+        let server = servers
+            .get_mut(server_id)
+            .or_not_found("Server not found")?;
+        let mut cleaned: Vec<String> = Vec::new();
+        for tag in tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+            if !cleaned.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                cleaned.push(tag.to_string());
+            }
+        }
+        server.tags = cleaned.clone();
+        Ok(cleaned)
+    }
+
+    /// Put the server in a group (None or an empty name takes it out)
+    pub async fn set_server_group(
+        &self,
+        server_id: &str,
+        group: Option<String>,
+    ) -> Result<Option<String>> {
+        let mut servers = self.servers.lock().await;
+        let group = group
+            .map(|g| g.trim().to_string())
+            .filter(|g| !g.is_empty());
+        // Reuse the spelling of an existing group so "Lobby" and "lobby" stay one
+        let group = group.map(|name| {
+            servers
+                .values()
+                .filter_map(|s| s.group.as_ref())
+                .find(|g| g.eq_ignore_ascii_case(&name))
+                .cloned()
+                .unwrap_or(name)
+        });
+        let server = servers
+            .get_mut(server_id)
+            .or_not_found("Server not found")?;
+        server.group = group.clone();
+        Ok(group)
+    }
+
+    /// Servers of a group in startup order: backends first, proxies last
+    pub async fn group_members(&self, group: &str) -> Result<Vec<ServerInfo>> {
+        let mut members: Vec<ServerInfo> = self
+            .servers
+            .lock()
+            .await
+            .values()
+            .filter(|s| {
+                s.group
+                    .as_deref()
+                    .is_some_and(|g| g.eq_ignore_ascii_case(group))
+            })
+            .cloned()
+            .collect();
+        if members.is_empty() {
+            anyhow::bail!("No servers are in the group \"{}\"", group);
+        }
+        members.sort_by(|a, b| {
+            (is_proxy(&a.server_type), &a.name).cmp(&(is_proxy(&b.server_type), &b.name))
+        });
+        Ok(members)
+    }
+
+    pub async fn get_server(&self, server_id: &str) -> Option<ServerInfo> {
+        self.servers.lock().await.get(server_id).cloned()
+    }
+
+    /// OS process id of a running server
+    pub fn get_process_id(&self, server_id: &str) -> Option<u32> {
+        let running = self
+            .processes
+            .lock()
+            .unwrap()
+            .get(server_id)
+            .and_then(|process| process.id());
+        running.or_else(|| self.adopted.lock().unwrap().get(server_id).copied())
+    }
+
+    /// Get list of operators from ops.json
+    pub async fn get_ops(&self, server_id: &str) -> Result<Vec<OpEntry>> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+
+        let ops_path = server.path.join("ops.json");
+        if !ops_path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(&ops_path).await?;
+        let ops: Vec<OpEntry> = serde_json::from_str(&content).unwrap_or_default();
+        Ok(ops)
+    }
+
+    /// Grant OP status to a player
+    pub async fn grant_op(&self, server_id: &str, player: &str) -> Result<()> {
+        self.send_command(server_id, &format!("op {}", player))
+            .await
+    }
+
+    /// Revoke OP status from a player
+    pub async fn revoke_op(&self, server_id: &str, player: &str) -> Result<()> {
+        self.send_command(server_id, &format!("deop {}", player))
+            .await
+    }
+
+    pub async fn get_floodgate_settings(&self, server_id: &str) -> Result<FloodgateSettings> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        Ok(floodgate_settings(&server).await)
+    }
+
+    /// The name the server matches a player by (Bedrock gamertags get Floodgate's
+    /// prefix) and the name to look the UUID up with
+    async fn player_list_name(
+        &self,
+        server: &ServerInfo,
+        player: &str,
+        bedrock: bool,
+    ) -> Result<(String, String)> {
+        if !bedrock {
+            return Ok((player.to_string(), player.to_string()));
+        }
+        let settings = floodgate_settings(server).await;
+        if !settings.installed {
+            anyhow::bail!("Bedrock players need Floodgate (install cross-play first)");
+        }
+        // Already prefixed names are accepted as well
+        let gamertag = player
+            .strip_prefix(&settings.username_prefix)
+            .unwrap_or(player);
+        Ok((floodgate_name(&settings, gamertag), gamertag.to_string()))
+    }
+
+    pub async fn get_whitelist(&self, server_id: &str) -> Result<Vec<WhitelistEntry>> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        Ok(read_player_list(&server.path.join("whitelist.json")).await)
+    }
+
+    /// Whitelist a Java player, or a Bedrock player by gamertag (`bedrock`).
+    /// Entries are written with the name and UUID the server actually matches,
+    /// since `whitelist add` cannot look up Floodgate players.
+    pub async fn add_to_whitelist(
+        &self,
+        server_id: &str,
+        player: &str,
+        bedrock: bool,
+    ) -> Result<()> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        let (name, lookup_name) = self.player_list_name(&server, player, bedrock).await?;
+
+        if !bedrock && server.status == ServerStatus::Running {
+            return self
+                .send_command(server_id, &format!("whitelist add {}", name))
+                .await;
+        }
+
+        let uuid = resolve_player_uuid(&lookup_name, bedrock).await?;
+
+        let path = server.path.join("whitelist.json");
+        let mut entries: Vec<WhitelistEntry> = read_player_list(&path).await;
+        entries.retain(|e| e.uuid != uuid);
+        entries.push(WhitelistEntry { uuid, name });
+        write_player_list(&path, &entries).await?;
+
+        if server.status == ServerStatus::Running {
+            self.send_command(server_id, "whitelist reload").await?;
+        }
+        Ok(())
+    }
+
+    pub async fn remove_from_whitelist(
+        &self,
+        server_id: &str,
+        player: &str,
+        bedrock: bool,
+    ) -> Result<()> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        let (name, _) = self.player_list_name(&server, player, bedrock).await?;
+
+        if server.status == ServerStatus::Running {
+            return self
+                .send_command(server_id, &format!("whitelist remove {}", name))
+                .await;
+        }
+
+        let path = server.path.join("whitelist.json");
+        let mut entries: Vec<WhitelistEntry> = read_player_list(&path).await;
+        entries.retain(|e| !e.name.eq_ignore_ascii_case(&name));
+        write_player_list(&path, &entries).await
+    }
+
+    pub async fn get_banned_players(&self, server_id: &str) -> Result<Vec<BanEntry>> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        Ok(read_player_list(&server.path.join("banned-players.json")).await)
+    }
+
+    /// Ban a player by name (Bedrock gamertags get Floodgate's prefix).
+    /// Running servers resolve Java names themselves; Bedrock players are matched
+    /// by their Floodgate UUID. Stopped servers get the entry written directly.
+    pub async fn ban_player(
+        &self,
+        server_id: &str,
+        player: &str,
+        reason: Option<&str>,
+        bedrock: bool,
+    ) -> Result<()> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        let (name, lookup_name) = self.player_list_name(&server, player, bedrock).await?;
+        let reason = reason
+            .filter(|r| !r.trim().is_empty())
+            .unwrap_or("Banned by an operator.");
+
+        if !bedrock && server.status == ServerStatus::Running {
+            return self
+                .send_command(server_id, &format!("ban {} {}", name, reason))
+                .await;
+        }
+
+        let uuid = resolve_player_uuid(&lookup_name, bedrock).await?;
+
+        if server.status == ServerStatus::Running {
+            // `ban` cannot look up Floodgate names, only profiles in the server's
+            // user cache, so ban the cached profile carrying the Floodgate UUID
+            let cache: Vec<WhitelistEntry> =
+                read_player_list(&server.path.join("usercache.json")).await;
+            let cached = cache
+                .into_iter()
+                .find(|e| e.uuid.eq_ignore_ascii_case(&uuid))
+                .ok_or_else(|| {
+                    PrismarineError::invalid_state(
+                        "This Bedrock player has not joined the server yet, stop the server to ban them",
+                    )
+                })?;
+            return self
+                .send_command(server_id, &format!("ban {} {}", cached.name, reason))
+                .await;
+        }
+
+        let path = server.path.join("banned-players.json");
+        let mut entries: Vec<BanEntry> = read_player_list(&path).await;
+        entries.retain(|e| e.uuid != uuid);
+        entries.push(BanEntry {
+            uuid,
+            name,
+            created: chrono::Local::now()
+                .format("%Y-%m-%d %H:%M:%S %z")
+                .to_string(),
+            source: "Server".to_string(),
+            expires: "forever".to_string(),
+            reason: reason.to_string(),
+        });
+        write_player_list(&path, &entries).await
+    }
+
+    pub async fn pardon_player(&self, server_id: &str, player: &str, bedrock: bool) -> Result<()> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        let (name, _) = self.player_list_name(&server, player, bedrock).await?;
+
+        if server.status == ServerStatus::Running {
+            return self
+                .send_command(server_id, &format!("pardon {}", name))
+                .await;
+        }
+
+        let path = server.path.join("banned-players.json");
+        let mut entries: Vec<BanEntry> = read_player_list(&path).await;
+        entries.retain(|e| !e.name.eq_ignore_ascii_case(&name));
+        write_player_list(&path, &entries).await
+    }
+
+    pub async fn get_plugins_path(&self, server_id: &str) -> Result<PathBuf> {
+        let server = self
+            .servers
+            .lock()
+            .await
+            .get(server_id)
+            .or_not_found("Server not found")?
+            .clone();
+
+        match server.server_type {
+            ServerType::Fabric
+            | ServerType::Mohist
+            | ServerType::Forge
+            | ServerType::Taiyitist
+            | ServerType::Banner => Ok(server.path.join("mods")),
+            _ => Ok(server.path.join("plugins")),
+        }
+    }
+
+    /// Total size of all worlds (folders with a level.dat) of a server
+    async fn world_size(&self, server_id: &str) -> Result<u64> {
+        Ok(self
+            .list_worlds(server_id)
+            .await?
+            .iter()
+            .map(|w| w.size_bytes)
+            .sum())
+    }
+
+    /// Check whether a permanent delete needs confirmation and issue the token for it
+    pub async fn request_delete_confirmation(&self, server_id: &str) -> Result<DeleteConfirmation> {
+        let world_size_bytes = self.world_size(server_id).await?;
+        let token = (world_size_bytes > LARGE_WORLD_BYTES).then(|| {
+            let token = format!("{:x}", rand::random::<u64>());
+            self.delete_tokens
+                .lock()
+                .unwrap()
+                .insert(server_id.to_string(), token.clone());
+            token
+        });
+
+        Ok(DeleteConfirmation {
+            world_size_bytes,
+            token,
+        })
+    }
+
+    pub async fn delete_server(
+        &self,
+        server_id: &str,
+        mode: DeleteMode,
+        confirm_token: Option<&str>,
+    ) -> Result<()> {
+        if mode == DeleteMode::Permanent && self.world_size(server_id).await? > LARGE_WORLD_BYTES {
+            let expected = self.delete_tokens.lock().unwrap().remove(server_id);
+            if expected.is_none() || expected.as_deref() != confirm_token {
+                anyhow::bail!(
+                    "This server has a large world. Request a confirmation token before deleting it permanently"
+                );
+            }
+        }
+
+        // Stop server if running
+        let _ = self.stop_server(server_id).await;
+
+        let server_info = {
+            let mut servers = self.servers.lock().await;
+            servers.remove(server_id).or_not_found("Server not found")?
+        };
+
+        match mode {
+            DeleteMode::Permanent => {
+                // Delete server directory
+                fs::remove_dir_all(&server_info.path)
+                    .await
+                    .context("Failed to delete server directory")?;
+                let id = server_info.id.clone();
+                let _ = tokio::task::spawn_blocking(move || {
+                    let _ = crate::secrets::delete_server_secrets(&id);
+                    crate::restore_points::remove_all(&id);
+                    crate::reports::remove_all(&id);
+                })
+                .await;
+            }
+            DeleteMode::Archive => {
+                let deleted_at = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs();
+                let trash_id = format!("{}_{}", deleted_at, server_info.id);
+                let base_path = self.base_path();
+                let trash_path = server_info
+                    .path
+                    .parent()
+                    .unwrap_or(&base_path)
+                    .join(TRASH_DIR)
+                    .join(&trash_id);
+
+                if let Err(e) = move_dir(&server_info.path, &trash_path, |_, _| {}).await {
+                    // Keep the server registered when it could not be moved
+                    self.servers
+                        .lock()
+                        .await
+                        .insert(server_info.id.clone(), server_info);
+                    return Err(e.context("Failed to move server to the trash"));
+                }
+
+                let mut trash = self.list_deleted_servers().await;
+                trash.push(DeletedServer {
+                    trash_id,
+                    trash_path,
+                    server: ServerInfo {
+                        status: ServerStatus::Stopped,
+                        pid: None,
+                        ..server_info
+                    },
+                    deleted_at,
+                });
+                self.save_trash_index(&trash).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Index of archived servers (the archives themselves live next to the servers)
+    fn trash_index_path(&self) -> PathBuf {
+        self.paths.config_file("trash.json")
+    }
+
+    pub async fn list_deleted_servers(&self) -> Vec<DeletedServer> {
+        fs::read_to_string(self.trash_index_path())
+            .await
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    async fn save_trash_index(&self, trash: &[DeletedServer]) -> Result<()> {
+        let path = self.trash_index_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::write(path, serde_json::to_string_pretty(trash)?).await?;
+        Ok(())
+    }
+
+    /// Move an archived server back to its original directory and register it again
+    pub async fn restore_deleted_server(&self, trash_id: &str) -> Result<ServerInfo> {
+        let mut trash = self.list_deleted_servers().await;
+        let index = trash
+            .iter()
+            .position(|d| d.trash_id == trash_id)
+            .or_not_found("Deleted server not found")?;
+        let DeletedServer {
+            server, trash_path, ..
+        } = trash[index].clone();
+
+        if self.servers.lock().await.contains_key(&server.id) {
+            anyhow::bail!("A server with the same id is already registered");
+        }
+        if server.path.exists() {
+            anyhow::bail!("Cannot restore: {} already exists", server.path.display());
+        }
+
+        move_dir(&trash_path, &server.path, |_, _| {})
+            .await
+            .context("Failed to restore server directory")?;
+
+        trash.remove(index);
+        self.save_trash_index(&trash).await?;
+        self.servers
+            .lock()
+            .await
+            .insert(server.id.clone(), server.clone());
+        Ok(server)
+    }
+
+    /// Permanently remove an archived server from the trash
+    pub async fn purge_deleted_server(&self, trash_id: &str) -> Result<()> {
+        let mut trash = self.list_deleted_servers().await;
+        let index = trash
+            .iter()
+            .position(|d| d.trash_id == trash_id)
+            .or_not_found("Deleted server not found")?;
+
+        let path = &trash[index].trash_path;
+        if path.exists() {
+            fs::remove_dir_all(path)
+                .await
+                .context("Failed to delete server directory")?;
+        }
+        trash.remove(index);
+        self.save_trash_index(&trash).await
+    }
+
+    /// Bundle of a stopped server for `transfer_server`. A bundle newer than every
+    /// file of the server is reused, so a retried transfer resumes where it stopped.
+    pub async fn build_transfer_bundle(&self, server_id: &str) -> Result<PathBuf> {
+        let _slot = crate::limits::BACKUP_JOBS.acquire().await;
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        if server.status != ServerStatus::Stopped {
+            anyhow::bail!("Stop the server before transferring it");
+        }
+
+        let bundle_path = crate::transfer::outgoing_bundle_path(server_id);
+        let dest = bundle_path.clone();
+        tokio::task::spawn_blocking(move || {
+            if !crate::transfer::is_bundle_current(&server.path, &dest) {
+                crate::transfer::write_bundle(&server, &dest)?;
+            }
+            anyhow::Ok(())
+        })
+        .await??;
+        Ok(bundle_path)
+    }
+
+    /// Register a server received from another instance under a new id
+    pub async fn import_server_bundle(&self, bundle_path: &Path) -> Result<ServerInfo> {
+        self.ensure_writable()?;
+        let bundle = bundle_path.to_path_buf();
+        // Bundles of older versions still carry the sender's local settings
+        let settings =
+            tokio::task::spawn_blocking(move || crate::transfer::read_bundle_settings(&bundle))
+                .await??
+                .portable();
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let server_path = self.base_path().join(&id);
+        let bundle = bundle_path.to_path_buf();
+        let target = server_path.clone();
+        if let Err(e) =
+            tokio::task::spawn_blocking(move || crate::transfer::extract_bundle(&bundle, &target))
+                .await?
+        {
+            let _ = fs::remove_dir_all(&server_path).await;
+            return Err(e.context("Failed to extract the server"));
+        }
+
+        // Keep the port unless another server here already uses it
+        let mut port = settings.port;
+        let taken = self.servers.lock().await.values().any(|s| s.port == port);
+        if taken {
+            port = self
+                .find_free_port(port, port.saturating_add(100))
+                .await
+                .context("No free port for the received server")?;
+            self.update_server_property(&server_path, "server-port", &port.to_string())
+                .await?;
+        }
+        // The RCON password stayed in the sender's secret store
+        if server_path.join("server.properties").exists() {
+            self.update_server_property(&server_path, "enable-rcon", "false")
+                .await?;
+            self.update_server_property(&server_path, "rcon.password", "")
+                .await?;
+        }
+
+        let server = ServerInfo {
+            id: id.clone(),
+            path: server_path,
+            port,
+            last_start_time: None,
+            tunnel: settings
+                .tunnel
+                .clone()
+                .map(|t| ServerTunnel { address: None, ..t }),
+            bedrock_tunnel: settings
+                .bedrock_tunnel
+                .clone()
+                .map(|t| ServerTunnel { address: None, ..t }),
+            ..settings
+        };
+        self.servers.lock().await.insert(id, server.clone());
+        Ok(server)
+    }
+
+    /// Upgrade/downgrade a server in place: back up the current jar, download the
+    /// jar for `new_version` and update the stored version. Java is re-selected
+    /// from the new version on the next start.
+    pub async fn change_server_version(
+        &self,
+        server_id: &str,
+        new_version: &str,
+    ) -> Result<ServerInfo> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+
+        if server.status != ServerStatus::Stopped {
+            return Err(PrismarineError::invalid_state(
+                "Server must be stopped before changing its version",
+            )
+            .into());
+        }
+
+        if server.version == new_version {
+            return Ok(server);
+        }
+        self.create_restore_point(server_id, &format!("Change version to {}", new_version))
+            .await?;
+
+        let jar_path = server.path.join("server.jar");
+        let backup_path = server
+            .path
+            .join(format!("server-{}.jar.bak", server.version));
+
+        if jar_path.exists() {
+            fs::rename(&jar_path, &backup_path)
+                .await
+                .context("Failed to back up current server jar")?;
+            println!(
+                "[ServerManager] Backed up {} jar to {:?}",
+                server.version, backup_path
+            );
+        }
+
+        let installed_build = match self
+            .download_server_jar(&server.path, &server.server_type, new_version)
+            .await
+        {
+            Ok(build) => build,
+            Err(e) => {
+                // Restore the previous jar so the server keeps working
+                if backup_path.exists() {
+                    let _ = fs::rename(&backup_path, &jar_path).await;
+                }
+                return Err(e.context(format!("Failed to download version {}", new_version)));
+            }
+        };
+
+        let required_java = crate::java_detector::get_required_java_version(new_version);
+        if crate::java_detector::select_java_for_server(
+            new_version,
+            is_forge_based(&server.server_type),
+        )
+        .is_none()
+        {
+            println!(
+                "[ServerManager] Warning: Minecraft {} requires Java {}, which was not found",
+                new_version, required_java
+            );
+        }
+
+        let mut servers = self.servers.lock().await;
+        let server = servers
+            .get_mut(server_id)
+            .or_not_found("Server not found")?;
+        server.version = new_version.to_string();
+        server.installed_build = installed_build;
+        Ok(server.clone())
+    }
+
+    /// Swap the server software for another of the same family, keeping configs,
+    /// plugins and worlds. The previous jar is kept as a .bak next to it and a
+    /// restore point is taken first.
+    pub async fn change_server_type(
+        &self,
+        server_id: &str,
+        new_type: ServerType,
+    ) -> Result<ServerInfo> {
+        self.ensure_writable()?;
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        if server.status != ServerStatus::Stopped {
+            return Err(PrismarineError::invalid_state(
+                "Server must be stopped before changing its type",
+            )
+            .into());
+        }
+        if server.server_type == new_type {
+            return Ok(server);
+        }
+        if new_type == ServerType::Spigot {
+            // Paper's configs and plugins using the Paper API would be lost
+            anyhow::bail!("Migrating back to Spigot is not supported");
+        }
+        let family = server_family(&server.server_type);
+        if family.is_none() || family != server_family(&new_type) {
+            anyhow::bail!(
+                "{:?} cannot be migrated to {:?}, only Paper/Purpur (also from Spigot) and BungeeCord/Waterfall are interchangeable",
+                server.server_type,
+                new_type
+            );
+        }
+
+        self.create_restore_point(server_id, &format!("Change type to {:?}", new_type))
+            .await?;
+        let jar_path = server.path.join("server.jar");
+        let backup_path = server.path.join(
+            format!("server-{:?}-{}.jar.bak", server.server_type, server.version).to_lowercase(),
+        );
+        if jar_path.exists() {
+            fs::rename(&jar_path, &backup_path)
+                .await
+                .context("Failed to back up current server jar")?;
+        }
+        let installed_build = match self
+            .download_server_jar(&server.path, &new_type, &server.version)
+            .await
+        {
+            Ok(build) => build,
+            Err(e) => {
+                if backup_path.exists() {
+                    let _ = fs::rename(&backup_path, &jar_path).await;
+                }
+                return Err(e.context(format!(
+                    "Failed to download {:?} {}",
+                    new_type, server.version
+                )));
+            }
+        };
+        println!(
+            "[ServerManager] {} migrated from {:?} to {:?}",
+            server.name, server.server_type, new_type
+        );
+
+        let mut servers = self.servers.lock().await;
+        let server = servers
+            .get_mut(server_id)
+            .or_not_found("Server not found")?;
+        server.server_type = new_type;
+        server.installed_build = installed_build;
+        Ok(server.clone())
+    }
+
+    /// Which installed plugins/mods support `target_version`, with available updates
+    pub async fn check_plugin_compatibility(
+        &self,
+        server_id: &str,
+        target_version: &str,
+    ) -> Result<Vec<PluginCompatibility>> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        let plugins_path = self.get_plugins_path(server_id).await?;
+        crate::plugin_compat::check(
+            &plugins_path,
+            target_version,
+            modrinth_loaders(&server.server_type),
+        )
+        .await
+    }
+
+    /// Change the version like `change_server_version`, checking plugins when the
+    /// major version changes and optionally updating them. With `verify_boot`
+    /// the server is started once; if it never finishes starting, the jar,
+    /// updated plugins and worlds are restored from upgrade-backup/.
+    pub async fn upgrade_server(
+        &self,
+        server_id: &str,
+        new_version: &str,
+        update_plugins: bool,
+        verify_boot: bool,
+    ) -> Result<UpgradeReport> {
+        self.ensure_writable()?;
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        if server.status != ServerStatus::Stopped {
+            return Err(PrismarineError::invalid_state(
+                "Server must be stopped before upgrading it",
+            )
+            .into());
+        }
+        if verify_boot && is_forge_based(&server.server_type) {
+            // The Forge installer rewrites libraries/, restoring server.jar is not enough
+            anyhow::bail!("Rollback after a failed boot is not supported for Forge-based servers");
+        }
+
+        self.create_restore_point(server_id, &format!("Upgrade to {}", new_version))
+            .await?;
+
+        let old_version = server.version.clone();
+        let plugins = if crate::plugin_compat::crosses_major(&old_version, new_version) {
+            self.check_plugin_compatibility(server_id, new_version)
+                .await?
+        } else {
+            Vec::new()
+        };
+        let mut report = UpgradeReport {
+            server: server.clone(),
+            plugins,
+            updated_plugins: Vec::new(),
+            failed_updates: Vec::new(),
+            booted: None,
+            rolled_back: false,
+            error: None,
+        };
+
+        let plugins_path = self.get_plugins_path(server_id).await?;
+        let backup_dir = server.path.join("upgrade-backup");
+        let _ = fs::remove_dir_all(&backup_dir).await;
+        fs::create_dir_all(backup_dir.join("plugins")).await?;
+        let mut worlds = Vec::new();
+        if verify_boot {
+            for world in self.list_worlds(server_id).await? {
+                let (src, dest) = (server.path.join(&world.name), backup_dir.join(&world.name));
+                tokio::task::spawn_blocking(move || copy_dir_all(&src, &dest, &mut |_| {}))
+                    .await?
+                    .with_context(|| format!("Failed to back up world {}", world.name))?;
+                worlds.push(world.name);
+            }
+        }
+
+        // (replaced jar, jar installed instead)
+        let mut replaced: Vec<(String, String)> = Vec::new();
+        if update_plugins {
+            for plugin in &report.plugins {
+                let Some(update) = &plugin.update else {
+                    continue;
+                };
+                let backup = backup_dir.join("plugins").join(&plugin.file);
+                fs::rename(plugins_path.join(&plugin.file), &backup).await?;
+                match self
+                    .install_plugin(&plugins_path, &update.url, &update.filename, false)
+                    .await
+                {
+                    Ok(()) => {
+                        report
+                            .updated_plugins
+                            .push(format!("{} {}", plugin.name, update.version));
+                        replaced.push((plugin.file.clone(), update.filename.clone()));
+                    }
+                    Err(e) => {
+                        fs::rename(&backup, plugins_path.join(&plugin.file)).await?;
+                        report
+                            .failed_updates
+                            .push(format!("{}: {}", plugin.name, e));
+                    }
+                }
+            }
+        }
+
+        if let Err(e) = self.change_server_version(server_id, new_version).await {
+            restore_plugins(&plugins_path, &backup_dir, &replaced).await;
+            return Err(e);
+        }
+
+        if verify_boot {
+            match self.test_boot(server_id, &server.path).await {
+                Ok(()) => {
+                    report.booted = Some(true);
+                    let _ = fs::remove_dir_all(&backup_dir).await;
+                }
+                Err(e) => {
+                    println!(
+                        "[Upgrade] {} failed to boot on {}, rolling back: {}",
+                        server.name, new_version, e
+                    );
+                    report.booted = Some(false);
+                    report.error = Some(e.to_string());
+                    self.rollback_upgrade(
+                        server_id,
+                        &server.path,
+                        &old_version,
+                        &plugins_path,
+                        &replaced,
+                        &worlds,
+                    )
+                    .await?;
+                    report.rolled_back = true;
+                }
+            }
+        }
+
+        // Only non-empty backups (replaced plugins without a test boot) are kept
+        let _ = fs::remove_dir(backup_dir.join("plugins")).await;
+        let _ = fs::remove_dir(&backup_dir).await;
+
+        report.server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        Ok(report)
+    }
+
+    /// Start the server and wait for the log line of a finished startup
+    async fn test_boot(&self, server_id: &str, server_path: &Path) -> Result<()> {
+        let started = std::time::SystemTime::now();
+        self.start_server(server_id).await?;
+        // Watched here, check_pending_boots must not roll back on its own
+        self.boot_watches.lock().unwrap().remove(server_id);
+
+        if let Err(e) = self.wait_for_boot(server_id, server_path, started).await {
+            let _ = self.stop_server(server_id).await;
+            return Err(e);
+        }
+        let id = server_id.to_string();
+        let _ =
+            tokio::task::spawn_blocking(move || crate::restore_points::clear_pending(&id)).await;
+        Ok(())
+    }
+
+    /// Wait for the log line of a finished startup begun at `started`
+    pub async fn wait_for_boot(
+        &self,
+        server_id: &str,
+        server_path: &Path,
+        started: std::time::SystemTime,
+    ) -> Result<()> {
+        let server_type = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?
+            .server_type;
+        let deadline =
+            std::time::Instant::now() + std::time::Duration::from_secs(BOOT_TIMEOUT_SECS);
+        loop {
+            tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+            if self
+                .verify_processes()
+                .await
+                .iter()
+                .any(|id| id == server_id)
+            {
+                anyhow::bail!("The server exited during startup");
+            }
+            if boot_finished(server_path, &server_type, started).await {
+                return Ok(());
+            }
+            if std::time::Instant::now() > deadline {
+                anyhow::bail!(
+                    "The server did not finish starting within {} seconds",
+                    BOOT_TIMEOUT_SECS
+                );
+            }
+        }
+    }
+
+    async fn rollback_upgrade(
+        &self,
+        server_id: &str,
+        server_path: &Path,
+        old_version: &str,
+        plugins_path: &Path,
+        replaced: &[(String, String)],
+        worlds: &[String],
+    ) -> Result<()> {
+        if self
+            .get_server(server_id)
+            .await
+            .is_some_and(|s| s.status != ServerStatus::Stopped)
+        {
+            self.stop_server(server_id).await?;
+        }
+
+        let backup_dir = server_path.join("upgrade-backup");
+        let jar_backup = server_path.join(format!("server-{}.jar.bak", old_version));
+        if jar_backup.exists() {
+            fs::rename(&jar_backup, server_path.join("server.jar")).await?;
+        }
+        restore_plugins(plugins_path, &backup_dir, replaced).await;
+        for world in worlds {
+            let _ = fs::remove_dir_all(server_path.join(world)).await;
+            fs::rename(backup_dir.join(world), server_path.join(world))
+                .await
+                .with_context(|| format!("Failed to restore world {}", world))?;
+        }
+        let _ = fs::remove_dir_all(&backup_dir).await;
+
+        if let Some(server) = self.servers.lock().await.get_mut(server_id) {
+            server.version = old_version.to_string();
+        }
+        // Back to the state the restore point was taken from
+        let id = server_id.to_string();
+        let _ =
+            tokio::task::spawn_blocking(move || crate::restore_points::clear_pending(&id)).await;
+        Ok(())
+    }
+
+    /// Take a restore point before the manager changes a server's software or
+    /// config. Skipped while the restore point of an earlier change is still
+    /// waiting for a successful boot, so rollbacks return to the last state
+    /// that is known to start.
+    async fn create_restore_point(&self, server_id: &str, reason: &str) -> Result<()> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        if server.remote.is_some() {
+            // The files live at the host, nothing local to roll back to
+            return Ok(());
+        }
+        let plugins_path = self.get_plugins_path(server_id).await?;
+        let extensions_dir = plugins_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "plugins".to_string());
+        let (id, reason) = (server_id.to_string(), reason.to_string());
+        tokio::task::spawn_blocking(move || {
+            if crate::restore_points::pending(&id).is_some() {
+                return Ok(());
+            }
+            let point = crate::restore_points::create(
+                &id,
+                &server.path,
+                &extensions_dir,
+                &server.server_type,
+                &server.version,
+                &reason,
+            )?;
+            println!(
+                "[RestorePoint] {}: created {} ({})",
+                server.name, point.id, reason
+            );
+            crate::restore_points::set_pending(&id, &point.id)
+        })
+        .await?
+        .context("Failed to create a restore point")
+    }
+
+    pub async fn list_restore_points(&self, server_id: &str) -> Result<Vec<RestorePoint>> {
+        self.get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        let id = server_id.to_string();
+        Ok(tokio::task::spawn_blocking(move || crate::restore_points::list(&id)).await?)
+    }
+
+    pub async fn get_last_rollback(&self, server_id: &str) -> Option<RollbackReport> {
+        let id = server_id.to_string();
+        tokio::task::spawn_blocking(move || crate::restore_points::last_rollback(&id))
+            .await
+            .ok()
+            .flatten()
+    }
+
+    /// Servers started for the first time after a change either finished
+    /// starting (the restore point is released) or exited/timed out, in which
+    /// case the restore point is put back and the server restarted
+    pub async fn check_pending_boots(&self, config_path: &Path) {
+        let watches: Vec<(String, u64)> = self
+            .boot_watches
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, started)| (id.clone(), *started))
+            .collect();
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        for (id, started) in watches {
+            let Some(server) = self.get_server(&id).await else {
+                self.boot_watches.lock().unwrap().remove(&id);
+                continue;
+            };
+            let since = std::time::UNIX_EPOCH + std::time::Duration::from_secs(started);
+            if boot_finished(&server.path, &server.server_type, since).await {
+                self.boot_watches.lock().unwrap().remove(&id);
+                let pending_id = id.clone();
+                let _ = tokio::task::spawn_blocking(move || {
+                    crate::restore_points::clear_pending(&pending_id)
+                })
+                .await;
+                println!(
+                    "[RestorePoint] {} started successfully after the change",
+                    server.name
+                );
+                continue;
+            }
+
+            let exited = match self.processes.lock().unwrap().get_mut(&id) {
+                Some(process) => !matches!(process.try_wait(), Ok(None)),
+                None => true,
+            };
+            let error = if exited {
+                "The server exited during startup".to_string()
+            } else if now >= started + BOOT_TIMEOUT_SECS {
+                format!(
+                    "The server did not finish starting within {} seconds",
+                    BOOT_TIMEOUT_SECS
+                )
+            } else {
+                continue;
+            };
+
+            self.boot_watches.lock().unwrap().remove(&id);
+            println!("[RestorePoint] {}: {}, rolling back", server.name, error);
+            let manager = self.clone();
+            let config_path = config_path.to_path_buf();
+            tokio::spawn(async move {
+                match manager.roll_back_failed_boot(&id, &error).await {
+                    Ok(report) => println!(
+                        "[RestorePoint] {}: restored {} (removed {}, replaced {}), restarted: {}",
+                        server.name,
+                        report.restore_point,
+                        report.removed.len(),
+                        report.replaced.len(),
+                        report.restarted
+                    ),
+                    Err(e) => println!("[RestorePoint] {}: rollback failed: {}", server.name, e),
+                }
+                let _ = manager.save_servers(&config_path).await;
+            });
+        }
+    }
+
+    /// Restore the pending restore point of a server that failed to start and start it again
+    async fn roll_back_failed_boot(&self, server_id: &str, error: &str) -> Result<RollbackReport> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        if server.status != ServerStatus::Stopped {
+            self.stop_server(server_id).await?;
+        }
+
+        let plugins_path = self.get_plugins_path(server_id).await?;
+        let extensions_dir = plugins_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "plugins".to_string());
+        let (id, server_path) = (server_id.to_string(), server.path.clone());
+        let restored = tokio::task::spawn_blocking(move || {
+            let point_id =
+                crate::restore_points::pending(&id).context("No restore point to roll back to")?;
+            let restored =
+                crate::restore_points::restore(&id, &point_id, &server_path, &extensions_dir)?;
+            crate::restore_points::clear_pending(&id);
+            anyhow::Ok(restored)
+        })
+        .await??;
+
+        if let Some(server) = self.servers.lock().await.get_mut(server_id) {
+            server.version = restored.point.version.clone();
+            if let Some(server_type) = restored.point.server_type.clone() {
+                server.server_type = server_type;
+            }
+        }
+
+        let mut report = RollbackReport {
+            at: chrono::Utc::now().timestamp() as u64,
+            restore_point: restored.point.id,
+            reason: restored.point.reason,
+            version: restored.point.version,
+            failed_version: server.version,
+            error: error.to_string(),
+            removed: restored.removed,
+            replaced: restored.replaced,
+            restarted: false,
+        };
+        match self.start_server(server_id).await {
+            Ok(()) => report.restarted = true,
+            Err(e) => println!(
+                "[RestorePoint] {}: failed to restart after the rollback: {}",
+                server.name, e
+            ),
+        }
+
+        let (id, saved) = (server_id.to_string(), report.clone());
+        tokio::task::spawn_blocking(move || crate::restore_points::save_rollback(&id, &saved))
+            .await??;
+        Ok(report)
+    }
+
+    /// Re-download the server software for the stored type/version and recreate
+    /// missing eula.txt/server.properties. Builds are not recorded, so the latest
+    /// build of the version is fetched.
+    pub async fn repair_server(&self, server_id: &str) -> Result<RepairReport> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        if server.status != ServerStatus::Stopped {
+            return Err(PrismarineError::invalid_state(
+                "Server must be stopped before repairing it",
+            )
+            .into());
+        }
+
+        // Keep the old jar until the new one is in place
+        let jar_path = server.path.join("server.jar");
+        let broken_path = server.path.join("server.jar.broken");
+        if jar_path.exists() {
+            fs::rename(&jar_path, &broken_path)
+                .await
+                .context("Failed to move the current server jar aside")?;
+        }
+        // The installed build when it is known, a newer one could behave differently
+        let result = match &server.installed_build {
+            Some(build) => self
+                .fetch_build(&jar_path, build, &format!("server-jar:{}", server.version))
+                .await
+                .map(|()| Some(build.clone())),
+            None => {
+                self.download_server_jar(&server.path, &server.server_type, &server.version)
+                    .await
+            }
+        };
+        let installed_build = match result {
+            Ok(build) => build,
+            Err(e) => {
+                if broken_path.exists() && !jar_path.exists() {
+                    let _ = fs::rename(&broken_path, &jar_path).await;
+                }
+                return Err(e.context(format!("Failed to re-download {}", server.version)));
+            }
+        };
+        let _ = fs::remove_file(&broken_path).await;
+        println!(
+            "[ServerManager] Re-downloaded {:?} {} for {}",
+            server.server_type, server.version, server.name
+        );
+        if let Some(server) = self.servers.lock().await.get_mut(server_id) {
+            server.installed_build = installed_build;
+        }
+
+        let mut regenerated = Vec::new();
+        if requires_eula(&server.server_type) {
+            if !server.path.join("eula.txt").exists() {
+                write_eula(&server.path, server.eula_accepted).await?;
+                regenerated.push("eula.txt".to_string());
+            }
+            if !server.path.join("server.properties").exists() {
+                self.create_default_properties(&server.path, server.port)
+                    .await?;
+                regenerated.push("server.properties".to_string());
+            }
+        }
+
+        let version = server.version.clone();
+        let modded = is_forge_based(&server.server_type);
+        let java_path = tokio::task::spawn_blocking(move || {
+            crate::java_detector::select_java_for_server(&version, modded)
+        })
+        .await?;
+
+        Ok(RepairReport {
+            server_id: server.id,
+            regenerated,
+            required_java: crate::java_detector::get_required_java_version(&server.version),
+            java_path,
+        })
+    }
+
+    /// Install the latest suitable build of `version`; returns what was installed
+    /// when it came from a download
+    async fn download_server_jar(
+        &self,
+        server_path: &Path,
+        server_type: &ServerType,
+        version: &str,
+    ) -> Result<Option<InstalledBuild>> {
+        crate::offline::ensure_online("Downloading the server software")?;
+        let jar_path = server_path.join("server.jar");
+        let task_id = format!("server-jar:{}", version);
+
+        let url = match server_type {
+            ServerType::Vanilla => self.get_vanilla_url(version).await?,
+            ServerType::Paper | ServerType::Purpur => {
+                // These builds never change once published, reuse them from the jar cache
+                let (url, cached) = if *server_type == ServerType::Paper {
+                    self.get_paper_build(version).await?
+                } else {
+                    self.get_purpur_build(version).await?
+                };
+                let build = InstalledBuild {
+                    url,
+                    cached: Some(cached),
+                };
+                self.fetch_build(&jar_path, &build, &task_id).await?;
+                return Ok(Some(build));
+            }
+            ServerType::Fabric => self.get_fabric_url(version).await?,
+            ServerType::Mohist => self.get_mohist_url(version).await?,
+            ServerType::Taiyitist => self.get_taiyitist_url(version).await?,
+            ServerType::Velocity => self.get_velocity_url(version).await?,
+            ServerType::Waterfall => self.get_waterfall_url(version).await?,
+            ServerType::BungeeCord => self.get_bungeecord_url(version).await?,
+            ServerType::Banner => self.get_banner_url(version).await?,
+            ServerType::Spigot => {
+                // Spigot requires BuildTools - handle separately
+                self.build_spigot(server_path, version).await?;
+                return Ok(None);
+            }
+            ServerType::Forge => {
+                // Forge ships an installer that lays out libraries next to the server
+                self.install_forge(server_path, version).await?;
+                return Ok(None);
+            }
+        };
+
+        let build = InstalledBuild { url, cached: None };
+        self.fetch_build(&jar_path, &build, &task_id).await?;
+        Ok(Some(build))
+    }
+
+    /// Download exactly `build` to `jar_path`
+    async fn fetch_build(
+        &self,
+        jar_path: &Path,
+        build: &InstalledBuild,
+        task_id: &str,
+    ) -> Result<()> {
+        crate::offline::ensure_online("Downloading the server software")?;
+        let client = reqwest::Client::builder()
+            .user_agent("MinecraftServerManager/0.1.0")
+            .build()?;
+        match &build.cached {
+            Some(cached) => {
+                println!(
+                    "Fetching server JAR {} from: {}",
+                    cached.file_name, build.url
+                );
+                crate::jar_cache::fetch(&client, &build.url, cached, jar_path, task_id).await
+            }
+            None => {
+                println!("Downloading server JAR from: {}", build.url);
+                crate::download::download_file(
+                    &client,
+                    &[&build.url],
+                    jar_path,
+                    task_id,
+                    Some(JarKind::Executable),
+                )
+                .await
+            }
+        }
+        .context("Failed to download server JAR")
+    }
+
+    async fn get_vanilla_url(&self, version: &str) -> Result<String> {
+        let manifest_url = "https://launchermeta.mojang.com/mc/game/version_manifest.json";
+        let client = reqwest::Client::builder()
+            .user_agent("MinecraftServerManager/0.1.0")
+            .build()?;
+        let manifest: serde_json::Value = client.get(manifest_url).send().await?.json().await?;
+
+        let versions = manifest["versions"]
+            .as_array()
+            .context("Invalid manifest format")?;
+        let version_info = versions
+            .iter()
+            .find(|v| v["id"].as_str() == Some(version))
+            .context(format!("Version {} not found", version))?;
+
+        let url = version_info["url"]
+            .as_str()
+            .context("Invalid version URL")?;
+        let packet: serde_json::Value = client.get(url).send().await?.json().await?;
+
+        let download_url = packet["downloads"]["server"]["url"]
+            .as_str()
+            .context("Server download URL not found")?
+            .to_string();
+
+        Ok(download_url)
+    }
+
+    async fn get_paper_build(
+        &self,
+        version: &str,
+    ) -> Result<(String, crate::jar_cache::CachedBuild)> {
+        let builds_url = format!(
+            "https://api.papermc.io/v2/projects/paper/versions/{}/builds",
+            version
+        );
+        let client = reqwest::Client::builder()
+            .user_agent("MinecraftServerManager/0.1.0")
+            .build()?;
+        let builds_resp: serde_json::Value = client.get(&builds_url).send().await?.json().await?;
+
+        let builds = builds_resp["builds"]
+            .as_array()
+            .context("No builds found")?;
+        // Prefer the known-good build, then the latest stable build, then
+        // experimental builds (pre-release versions only have experimental ones)
+        let known_good = crate::versions::requirements(version).paper_build;
+        let latest_build = builds
+            .iter()
+            .find(|b| known_good.is_some() && b["build"].as_u64() == known_good)
+            .or_else(|| {
+                builds
+                    .iter()
+                    .rev()
+                    .find(|b| b["channel"].as_str() == Some("default"))
+            })
+            .or(builds.last())
+            .context("No builds found")?;
+        let build_number = latest_build["build"]
+            .as_u64()
+            .context("Invalid build number")?;
+        let default_name = format!("paper-{}-{}.jar", version, build_number);
+        let file_name = latest_build["downloads"]["application"]["name"]
+            .as_str()
+            .unwrap_or(&default_name);
+        let sha256 = latest_build["downloads"]["application"]["sha256"]
+            .as_str()
+            .map(str::to_string);
+
+        Ok((
+            format!(
+                "https://api.papermc.io/v2/projects/paper/versions/{}/builds/{}/downloads/{}",
+                version, build_number, file_name
+            ),
+            crate::jar_cache::CachedBuild {
+                file_name: file_name.to_string(),
+                sha256,
+            },
+        ))
+    }
+
+    async fn get_fabric_url(&self, version: &str) -> Result<String> {
+        // Step 1: Get latest loader version
+        let loader_api = "https://meta.fabricmc.net/v2/versions/loader";
+        let client = reqwest::Client::builder()
+            .user_agent("MinecraftServerManager/0.1.0")
+            .build()?;
+
+        let loader_data: serde_json::Value = client.get(loader_api).send().await?.json().await?;
+        let latest_loader = loader_data
+            .as_array()
+            .and_then(|arr| arr.first())
+            .and_then(|v| v["version"].as_str())
+            .context("Failed to get latest Fabric loader version")?;
+
+        // Step 2: Get latest installer version
+        let installer_api = "https://meta.fabricmc.net/v2/versions/installer";
+        let installer_data: serde_json::Value =
+            client.get(installer_api).send().await?.json().await?;
+        let latest_installer = installer_data
+            .as_array()
+            .and_then(|arr| arr.first())
+            .and_then(|v| v["version"].as_str())
+            .context("Failed to get latest Fabric installer version")?;
+
+        // Step 3: Build download URL
+        Ok(format!(
+            "https://meta.fabricmc.net/v2/versions/loader/{}/{}/{}/server/jar",
+            version, latest_loader, latest_installer
+        ))
+    }
+
+    async fn get_mohist_url(&self, version: &str) -> Result<String> {
+        // Mohist API: Get latest build info first
+        let builds_url = format!(
+            "https://api.mohistmc.com/project/mohist/{}/builds/latest",
+            version
+        );
+
+        let client = reqwest::Client::builder()
+            .user_agent("MinecraftServerManager/0.1.0")
+            .build()?;
+
+        let build_info: serde_json::Value = client.get(&builds_url).send().await?.json().await?;
+
+        let build_id = build_info["id"]
+            .as_i64()
+            .context("Failed to get Mohist build ID")?;
+
+        // Construct download URL with build ID
+        let download_url = format!(
+            "https://api.mohistmc.com/project/mohist/{}/builds/{}/download",
+            version, build_id
+        );
+
+        Ok(download_url)
+    }
+
+    async fn get_taiyitist_url(&self, version: &str) -> Result<String> {
+        // Taiyitist uses GitHub releases: https://github.com/Teneted/Taiyitist/releases
+        // Tag format is "{version}-release" (e.g., "1.20.1-release")
+        let tag = format!("{}-release", version);
+        let releases_url = format!(
+            "https://api.github.com/repos/Teneted/Taiyitist/releases/tags/{}",
+            tag
+        );
+
+        let client = reqwest::Client::builder()
+            .user_agent("MinecraftServerManager/0.1.0")
+            .build()?;
+
+        let release_info: serde_json::Value =
+            client.get(&releases_url).send().await?.json().await?;
+
+        // Find the first .jar asset
+        let assets = release_info["assets"]
+            .as_array()
+            .context("Failed to get release assets")?;
+
+        for asset in assets {
+            let name = asset["name"].as_str().unwrap_or("");
+            if name.ends_with(".jar") {
+                let download_url = asset["browser_download_url"]
+                    .as_str()
+                    .context("Failed to get download URL")?;
+                return Ok(download_url.to_string());
+            }
+        }
+
+        Err(anyhow::anyhow!(
+            "No JAR file found in Taiyitist release {}",
+            version
+        ))
+    }
+
+    async fn get_velocity_url(&self, version: &str) -> Result<String> {
+        // Papermc API for Velocity
+        let base_url = "https://api.papermc.io/v2/projects/velocity";
+        let client = reqwest::Client::builder()
+            .user_agent("MinecraftServerManager/0.1.0")
+            .build()?;
+
+        // Get latest build for the version
+        let builds_url = format!("{}/versions/{}/builds", base_url, version);
+        let resp: serde_json::Value = client.get(&builds_url).send().await?.json().await?;
+
+        let builds = resp["builds"].as_array().context("No builds found")?;
+        let latest_build = builds.last().context("No builds found")?;
+        let build_number = latest_build["build"]
+            .as_i64()
+            .context("Invalid build number")?;
+        let name = latest_build["downloads"]["application"]["name"]
+            .as_str()
+            .context("Invalid download name")?;
+
+        Ok(format!(
+            "{}/versions/{}/builds/{}/downloads/{}",
+            base_url, version, build_number, name
+        ))
+    }
+
+    async fn get_waterfall_url(&self, version: &str) -> Result<String> {
+        // Papermc API for Waterfall
+        let base_url = "https://api.papermc.io/v2/projects/waterfall";
+        let client = reqwest::Client::builder()
+            .user_agent("MinecraftServerManager/0.1.0")
+            .build()?;
+
+        // Get latest build for the version
+        let builds_url = format!("{}/versions/{}/builds", base_url, version);
+        let resp: serde_json::Value = client.get(&builds_url).send().await?.json().await?;
+
+        let builds = resp["builds"].as_array().context("No builds found")?;
+        let latest_build = builds.last().context("No builds found")?;
+        let build_number = latest_build["build"]
+            .as_i64()
+            .context("Invalid build number")?;
+        let name = latest_build["downloads"]["application"]["name"]
+            .as_str()
+            .context("Invalid download name")?;
+
+        Ok(format!(
+            "{}/versions/{}/builds/{}/downloads/{}",
+            base_url, version, build_number, name
+        ))
+    }
+
+    async fn get_bungeecord_url(&self, _version: &str) -> Result<String> {
+        // BungeeCord (Jenkins) - For now just return latest stable
+        // The version string might be ignored or used if we support specific builds
+        // Official CI: https://ci.md-5.net/job/BungeeCord/
+        Ok("https://ci.md-5.net/job/BungeeCord/lastSuccessfulBuild/artifact/bootstrap/target/BungeeCord.jar".to_string())
+    }
+
+    /// Purpur only publishes MD5 sums, cached builds are checked as jars instead
+    async fn get_purpur_build(
+        &self,
+        version: &str,
+    ) -> Result<(String, crate::jar_cache::CachedBuild)> {
+        // Purpur API: https://api.purpurmc.org/v2/purpur/{version}
+        let url = format!("https://api.purpurmc.org/v2/purpur/{}", version);
+        let client = reqwest::Client::builder()
+            .user_agent("MinecraftServerManager/0.1.0")
+            .build()?;
+        let resp: serde_json::Value = client.get(&url).send().await?.json().await?;
+
+        let latest_build = resp["builds"]["latest"]
+            .as_str()
+            .context("No latest build found for Purpur")?;
+
+        Ok((
+            format!(
+                "https://api.purpurmc.org/v2/purpur/{}/{}/download",
+                version, latest_build
+            ),
+            crate::jar_cache::CachedBuild {
+                file_name: format!("purpur-{}-{}.jar", version, latest_build),
+                sha256: None,
+            },
+        ))
+    }
+
+    async fn get_banner_url(&self, version: &str) -> Result<String> {
+        // Banner is available on mohistmc.com builds-raw
+        // Filenames use git hashes: Banner-1.20.1-{hash}.jar
+        let client = reqwest::Client::builder()
+            .user_agent("MinecraftServerManager/0.1.0")
+            .build()?;
+
+        // Get directory listing from builds-raw
+        let dir_url = format!("https://mohistmc.com/builds-raw/Banner-{}/", version);
+        println!("Fetching Banner builds from: {}", dir_url);
+
+        let resp = client.get(&dir_url).send().await?;
+
+        if !resp.status().is_success() {
+            anyhow::bail!(
+                "Banner {} のビルドディレクトリにアクセスできません (HTTP {})",
+                version,
+                resp.status()
+            );
+        }
+
+        let html = resp.text().await?;
+
+        // Parse HTML directory listing for JAR files
+        // Format: href="Banner-1.20.1-{hash}.jar"
+        let prefix = format!("Banner-{}-", version);
+        let mut latest_jar: Option<String> = None;
+
+        for part in html.split("href=\"") {
+            if let Some(end_quote) = part.find('"') {
+                let href = &part[..end_quote];
+                if href.starts_with(&prefix) && href.ends_with(".jar") {
+                    // Keep track of the last JAR found (directory listings are usually sorted)
+                    latest_jar = Some(href.to_string());
+                }
+            }
+        }
+
+        let jar_name =
+            latest_jar.context(format!("Banner {} のビルドが見つかりません。", version))?;
+
+        let download_url = format!(
+            "https://mohistmc.com/builds-raw/Banner-{}/{}",
+            version, jar_name
+        );
+        println!("Banner direct download: {}", download_url);
+        Ok(download_url)
+    }
+
+    async fn build_spigot(&self, server_path: &Path, version: &str) -> Result<()> {
+        crate::offline::ensure_online("Building Spigot")?;
+        // Spigot requires BuildTools to build
+        // 1. Download BuildTools.jar
+        // 2. Run BuildTools with specified version
+        // 3. Copy resulting spigot-*.jar to server.jar
+
+        println!("[Spigot BuildTools] Starting build for version {}", version);
+
+        let mc_version = version.to_string();
+        let check =
+            tokio::task::spawn_blocking(move || crate::buildtools::check(&mc_version)).await?;
+        if check.git_version.is_none() {
+            anyhow::bail!("{}", check.problems.join("\n"));
+        }
+
+        let buildtools_url = "https://hub.spigotmc.org/jenkins/job/BuildTools/lastSuccessfulBuild/artifact/target/BuildTools.jar";
+        let buildtools_path = server_path.join("BuildTools.jar");
+        let jar_path = server_path.join("server.jar");
+
+        // Download BuildTools.jar
+        println!("[Spigot BuildTools] Downloading BuildTools.jar...");
+        let client = reqwest::Client::builder()
+            .user_agent("MinecraftServerManager/0.1.0")
+            .build()?;
+        crate::download::download_file(
+            &client,
+            &[buildtools_url],
+            &buildtools_path,
+            "spigot-buildtools",
+            Some(JarKind::Executable),
+        )
+        .await
+        .context("Failed to download BuildTools.jar")?;
+
+        // Get appropriate Java version for building
+        let java_cmd = crate::java_manager::JavaManager::new()
+            .get_java_executable(version, false)
+            .await?;
+        let java = java_cmd.clone();
+        let java_version =
+            tokio::task::spawn_blocking(move || crate::java_detector::get_java_version(&java))
+                .await?
+                .with_context(|| format!("Java at {} could not be run", java_cmd))?;
+        crate::buildtools::java_supported(version, java_version)?;
+
+        println!("[Spigot BuildTools] Using Java: {}", java_cmd);
+        println!(
+            "[Spigot BuildTools] Building Spigot {}... (this may take a while)",
+            version
+        );
+
+        // Run BuildTools
+        let mut command = Command::new(&java_cmd);
+        command
+            .args(&["-jar", "BuildTools.jar", "--rev", version])
+            .current_dir(server_path)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if check.git_portable {
+            if let Some(path) = crate::buildtools::path_with_portable_git() {
+                command.env("PATH", path);
+            }
+        }
+        let output = command.output().await.context("Failed to run BuildTools")?;
+
+        if !output.status.success() {
+            // BuildTools reports most problems on stdout
+            let log = format!(
+                "{}\n{}",
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            );
+            anyhow::bail!("{}", crate::buildtools::explain_failure(&log));
+        }
+
+        println!("[Spigot BuildTools] Build completed, locating JAR...");
+
+        // Find the built spigot JAR
+        let mut found_jar = false;
+        if let Ok(entries) = std::fs::read_dir(server_path) {
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.starts_with("spigot-") && name.ends_with(".jar") {
+                    // Copy to server.jar
+                    std::fs::copy(entry.path(), &jar_path)?;
+                    found_jar = true;
+                    println!("[Spigot BuildTools] Found and copied: {}", name);
+                    break;
+                }
+            }
+        }
+
+        if !found_jar {
+            anyhow::bail!("BuildTools completed but spigot-*.jar not found");
+        }
+
+        // Cleanup BuildTools files (optional, keep for re-builds)
+        // let _ = std::fs::remove_file(&buildtools_path);
+
+        println!("[Spigot BuildTools] Spigot server ready!");
+        Ok(())
+    }
+
+    async fn install_forge(&self, server_path: &Path, version: &str) -> Result<()> {
+        crate::offline::ensure_online("Installing Forge")?;
+        // Forge no longer publishes a runnable server jar:
+        // 1. Resolve the recommended (or latest) Forge build for the MC version
+        // 2. Download and run the installer with --installServer
+        // 3. Modern Forge (1.17+) leaves libraries/.../unix_args.txt, older Forge a runnable jar
+
+        println!("[Forge Installer] Starting install for version {}", version);
+
+        let client = reqwest::Client::builder()
+            .user_agent("MinecraftServerManager/0.1.0")
+            .build()?;
+
+        let promotions: serde_json::Value = client
+            .get("https://files.minecraftforge.net/net/minecraftforge/forge/promotions_slim.json")
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        let forge_version = promotions["promos"][format!("{}-recommended", version)]
+            .as_str()
+            .or_else(|| promotions["promos"][format!("{}-latest", version)].as_str())
+            .context(format!("No Forge build found for Minecraft {}", version))?
+            .to_string();
+
+        let full_version = format!("{}-{}", version, forge_version);
+        let installer_url = format!(
+            "https://maven.minecraftforge.net/net/minecraftforge/forge/{}/forge-{}-installer.jar",
+            full_version, full_version
+        );
+        // BMCLAPI mirrors the Forge maven when the official host is unreachable
+        let installer_mirror = format!(
+            "https://bmclapi2.bangbang93.com/maven/net/minecraftforge/forge/{}/forge-{}-installer.jar",
+            full_version, full_version
+        );
+        let installer_path = server_path.join("forge-installer.jar");
+
+        // The installer gets executed: a jar from the mirror is only used when it
+        // matches the checksum published on the official maven
+        let expected_sha1 = fetch_sha1(&client, &format!("{}.sha1", installer_url)).await;
+        let mut urls = vec![installer_url.as_str()];
+        match &expected_sha1 {
+            Ok(_) => urls.push(&installer_mirror),
+            Err(e) => println!(
+                "[Forge Installer] No checksum from the official maven ({}), not using the mirror",
+                e
+            ),
+        }
+
+        println!(
+            "[Forge Installer] Downloading installer from: {}",
+            installer_url
+        );
+        let task_id = format!("forge-installer:{}", full_version);
+        crate::download::download_file(
+            &client,
+            &urls,
+            &installer_path,
+            &task_id,
+            Some(JarKind::Executable),
+        )
+        .await
+        .context("Failed to download Forge installer")?;
+
+        if let Ok(expected) = &expected_sha1 {
+            let path = installer_path.clone();
+            let actual =
+                tokio::task::spawn_blocking(move || crate::download::sha1_hex(&path)).await??;
+            if &actual != expected {
+                let _ = fs::remove_file(&installer_path).await;
+                anyhow::bail!(
+                    "The Forge installer does not match its published checksum (expected {}, got {})",
+                    expected,
+                    actual
+                );
+            }
+        }
+
+        let java_cmd = crate::java_manager::JavaManager::new()
+            .get_java_executable(version, true)
+            .await?;
+
+        println!("[Forge Installer] Using Java: {}", java_cmd);
+        println!(
+            "[Forge Installer] Installing Forge {}... (this may take a while)",
+            full_version
+        );
+
+        let output = Command::new(&java_cmd)
+            .args(["-jar", "forge-installer.jar", "--installServer"])
+            .current_dir(server_path)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output()
+            .await
+            .context("Failed to run Forge installer")?;
+
+        let _ = fs::remove_file(&installer_path).await;
+
+        if !output.status.success() {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("Forge installer failed: {}{}", stdout, stderr);
+        }
+
+        // Modern layout: started via argument files, nothing else to do
+        if let Some(args_file) = find_forge_args_file(server_path, version) {
+            println!("[Forge Installer] Found argument file: {}", args_file);
+            println!("[Forge Installer] Forge server ready!");
+            return Ok(());
+        }
+
+        // Legacy layout: copy the runnable forge jar to server.jar
+        let mut found_jar = false;
+        if let Ok(entries) = std::fs::read_dir(server_path) {
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.starts_with("forge-")
+                    && name.ends_with(".jar")
+                    && !name.contains("installer")
+                {
+                    std::fs::copy(entry.path(), server_path.join("server.jar"))?;
+                    found_jar = true;
+                    println!("[Forge Installer] Found and copied: {}", name);
+                    break;
+                }
+            }
+        }
+
+        if !found_jar {
+            anyhow::bail!("Forge installer completed but no server jar or argument file was found");
+        }
+
+        println!("[Forge Installer] Forge server ready!");
+        Ok(())
+    }
+
+    pub async fn fetch_forge_versions(&self) -> Result<Vec<String>> {
+        let url = "https://files.minecraftforge.net/net/minecraftforge/forge/promotions_slim.json";
+        let client = reqwest::Client::builder()
+            .user_agent("MinecraftServerManager/0.1.0")
+            .build()?;
+        let resp: serde_json::Value = client.get(url).send().await?.json().await?;
+
+        // Keys look like "1.20.1-recommended" / "1.20.1-latest"
+        let mut versions: Vec<String> = resp["promos"]
+            .as_object()
+            .context("Invalid Forge promotions format")?
+            .keys()
+            .filter_map(|k| k.rsplit_once('-').map(|(mc, _)| mc.to_string()))
+            .collect();
+
+        versions.sort();
+        versions.dedup();
+
+        // Sort by version (newest first)
+        versions.sort_by(|a, b| {
+            let a_parts: Vec<u32> = a.split('.').filter_map(|s| s.parse().ok()).collect();
+            let b_parts: Vec<u32> = b.split('.').filter_map(|s| s.parse().ok()).collect();
+            b_parts.cmp(&a_parts)
+        });
+
+        Ok(versions)
+    }
+
+    pub async fn fetch_vanilla_versions(&self, include_snapshots: bool) -> Result<Vec<String>> {
+        let manifest_url = "https://launchermeta.mojang.com/mc/game/version_manifest.json";
+        let client = reqwest::Client::builder()
+            .user_agent("MinecraftServerManager/0.1.0")
+            .build()?;
+        let manifest: serde_json::Value = client.get(manifest_url).send().await?.json().await?;
+
+        let versions = manifest["versions"]
+            .as_array()
+            .context("Invalid manifest format")?
+            .iter()
+            .filter(|v| match v["type"].as_str() {
+                Some("release") => true,
+                Some("snapshot") => include_snapshots,
+                _ => false,
+            })
+            .filter_map(|v| v["id"].as_str().map(|s| s.to_string()))
+            .collect();
+
+        Ok(versions)
+    }
+
+    pub async fn fetch_paper_versions(&self, include_snapshots: bool) -> Result<Vec<String>> {
+        let url = "https://api.papermc.io/v2/projects/paper";
+        let client = reqwest::Client::builder()
+            .user_agent("MinecraftServerManager/0.1.0")
+            .build()?;
+        let resp: serde_json::Value = client.get(url).send().await?.json().await?;
+
+        let mut versions: Vec<String> = resp["versions"]
+            .as_array()
+            .context("Invalid response format")?
+            .iter()
+            .filter_map(|v| v.as_str().map(|s| s.to_string()))
+            .filter(|v| include_snapshots || !is_prerelease_version(v))
+            .collect();
+
+        // Reverse to show newest first (Paper API returns oldest first usually)
+        versions.reverse();
+
+        Ok(versions)
+    }
+
+    pub async fn fetch_fabric_versions(&self, include_snapshots: bool) -> Result<Vec<String>> {
+        let url = "https://meta.fabricmc.net/v2/versions/game";
+        let client = reqwest::Client::builder()
+            .user_agent("MinecraftServerManager/0.1.0")
+            .build()?;
+        let resp: serde_json::Value = client.get(url).send().await?.json().await?;
+
+        let versions: Vec<String> = resp
+            .as_array()
+            .context("Invalid response format")?
+            .iter()
+            .filter(|v| include_snapshots || v["stable"].as_bool().unwrap_or(false))
+            .filter_map(|v| v["version"].as_str().map(|s| s.to_string()))
+            .collect();
+
+        Ok(versions)
+    }
+
+    pub async fn fetch_mohist_versions(&self) -> Result<Vec<String>> {
+        // Fetch versions from new Mohist API
+        let url = "https://api.mohistmc.com/project/mohist/versions";
+        let client = reqwest::Client::builder()
+            .user_agent("MinecraftServerManager/0.1.0")
+            .build()?;
+
+        let resp: serde_json::Value = client.get(url).send().await?.json().await?;
+
+        let mut versions: Vec<String> = resp
+            .as_array()
+            .context("Invalid response format")?
+            .iter()
+            .filter_map(|v| v["name"].as_str().map(|s| s.to_string()))
+            .collect();
+
+        // Reverse to show newest first
+        versions.reverse();
+
+        Ok(versions)
+    }
+
+    pub async fn fetch_taiyitist_versions(&self) -> Result<Vec<String>> {
+        // Fetch releases from GitHub API
+        let url = "https://api.github.com/repos/Teneted/Taiyitist/releases";
+        let client = reqwest::Client::builder()
+            .user_agent("MinecraftServerManager/0.1.0")
+            .build()?;
+
+        let resp: serde_json::Value = client.get(url).send().await?.json().await?;
+
+        // Tag format is "{version}-release", strip the "-release" suffix for UI display
+        let versions: Vec<String> = resp
+            .as_array()
+            .context("Invalid response format")?
+            .iter()
+            .filter_map(|v| {
+                v["tag_name"]
+                    .as_str()
+                    .map(|s| s.strip_suffix("-release").unwrap_or(s).to_string())
+            })
+            .collect();
+
+        Ok(versions)
+    }
+
+    pub async fn fetch_velocity_versions(&self, include_snapshots: bool) -> Result<Vec<String>> {
+        let url = "https://api.papermc.io/v2/projects/velocity";
+        let client = reqwest::Client::builder()
+            .user_agent("MinecraftServerManager/0.1.0")
+            .build()?;
+        let resp: serde_json::Value = client.get(url).send().await?.json().await?;
+
+        let mut versions: Vec<String> = resp["versions"]
+            .as_array()
+            .context("Invalid response format")?
+            .iter()
+            .filter_map(|v| v.as_str().map(|s| s.to_string()))
+            .filter(|v| include_snapshots || !is_prerelease_version(v))
+            .collect();
+
+        versions.reverse();
+        Ok(versions)
+    }
+
+    pub async fn fetch_waterfall_versions(&self, include_snapshots: bool) -> Result<Vec<String>> {
+        let url = "https://api.papermc.io/v2/projects/waterfall";
+        let client = reqwest::Client::builder()
+            .user_agent("MinecraftServerManager/0.1.0")
+            .build()?;
+        let resp: serde_json::Value = client.get(url).send().await?.json().await?;
+
+        let mut versions: Vec<String> = resp["versions"]
+            .as_array()
+            .context("Invalid response format")?
+            .iter()
+            .filter_map(|v| v.as_str().map(|s| s.to_string()))
+            .filter(|v| include_snapshots || !is_prerelease_version(v))
+            .collect();
+
+        versions.reverse();
+        Ok(versions)
+    }
+
+    pub async fn fetch_bungeecord_versions(&self) -> Result<Vec<String>> {
+        // BungeeCord doesn't have a clean version list API easily accessible like Paper
+        // It's usually just "Latest" or build numbers.
+        // We'll return a single "latest" version for now.
+        Ok(vec!["latest".to_string()])
+    }
+
+    pub async fn fetch_purpur_versions(&self, include_snapshots: bool) -> Result<Vec<String>> {
+        let url = "https://api.purpurmc.org/v2/purpur";
+        let client = reqwest::Client::builder()
+            .user_agent("MinecraftServerManager/0.1.0")
+            .build()?;
+        let resp: serde_json::Value = client.get(url).send().await?.json().await?;
+
+        let mut versions: Vec<String> = resp["versions"]
+            .as_array()
+            .context("Invalid Purpur response format")?
+            .iter()
+            .filter_map(|v| v.as_str().map(|s| s.to_string()))
+            .filter(|v| include_snapshots || !is_prerelease_version(v))
+            .collect();
+
+        versions.reverse();
+        Ok(versions)
+    }
+
+    pub async fn fetch_banner_versions(&self) -> Result<Vec<String>> {
+        let client = reqwest::Client::builder()
+            .user_agent("MinecraftServerManager/0.1.0")
+            .build()?;
+
+        // Get Banner versions from builds-raw directory listing
+        let url = "https://mohistmc.com/builds-raw/";
+        let resp = client.get(url).send().await?;
+        let html = resp.text().await?;
+
+        // Parse directory listing for Banner-X.Y.Z folders
+        let mut versions: Vec<String> = Vec::new();
+        for part in html.split("href=\"Banner-") {
+            if let Some(end) = part.find('/') {
+                let ver = &part[..end];
+                if !ver.is_empty()
+                    && ver
+                        .chars()
+                        .next()
+                        .map(|c| c.is_ascii_digit())
+                        .unwrap_or(false)
+                {
+                    versions.push(ver.to_string());
+                }
+            }
+        }
+
+        // Remove duplicates
+        versions.sort();
+        versions.dedup();
+
+        // Sort by version (newest first)
+        versions.sort_by(|a, b| {
+            let a_parts: Vec<u32> = a.split('.').filter_map(|s| s.parse().ok()).collect();
+            let b_parts: Vec<u32> = b.split('.').filter_map(|s| s.parse().ok()).collect();
+            b_parts.cmp(&a_parts)
+        });
+
+        Ok(versions)
+    }
+
+    pub async fn fetch_spigot_versions(&self) -> Result<Vec<String>> {
+        // Spigot versions typically mirror vanilla releases
+        // But only certain versions are supported by BuildTools
+        // We'll use vanilla versions for now, BuildTools will inform if unsupported
+        self.fetch_vanilla_versions(false).await
+    }
+
+    async fn create_default_properties(&self, server_path: &Path, port: u16) -> Result<()> {
+        let properties = format!(
+            "server-port={}\n\
+             enable-command-block=true\n\
+             gamemode=survival\n\
+             difficulty=normal\n\
+             max-players=20\n\
+             view-distance=10\n\
+             motd=A Minecraft Server managed by Prismarine\n",
+            port
+        );
+
+        fs::write(server_path.join("server.properties"), properties).await?;
+        Ok(())
+    }
+
+    pub async fn set_server_motd(&self, server_id: &str, motd: &str) -> Result<()> {
+        let server = self
+            .servers
+            .lock()
+            .await
+            .get(server_id)
+            .or_not_found("Server not found")?
+            .clone();
+
+        let props_path = server.path.join("server.properties");
+        if !props_path.exists() {
+            // If missing, create default? Or error? Error is safer but we initialized it.
+            // Just let it error or return.
+            return Ok(());
+        }
+
+        let content = fs::read_to_string(&props_path).await?;
+        let mut new_lines = Vec::new();
+        let mut found = false;
+
+        for line in content.lines() {
+            if line.trim().starts_with("motd=") {
+                new_lines.push(format!("motd={}", motd));
+                found = true;
+            } else {
+                new_lines.push(line.to_string());
+            }
+        }
+
+        if !found {
+            new_lines.push(format!("motd={}", motd));
+        }
+
+        fs::write(&props_path, new_lines.join("\n")).await?;
+        Ok(())
+    }
+
+    pub async fn get_server_motd(&self, server_id: &str) -> Result<String> {
+        let server = self
+            .servers
+            .lock()
+            .await
+            .get(server_id)
+            .or_not_found("Server not found")?
+            .clone();
+
+        let props_path = server.path.join("server.properties");
+        if !props_path.exists() {
+            return Ok("".to_string());
+        }
+
+        let content = fs::read_to_string(&props_path).await?;
+        for line in content.lines() {
+            if let Some(val) = line.trim().strip_prefix("motd=") {
+                return Ok(val.to_string());
+            }
+        }
+        Ok("".to_string())
+    }
+
+    pub async fn set_server_max_players(&self, server_id: &str, max_players: u32) -> Result<()> {
+        let server = self
+            .servers
+            .lock()
+            .await
+            .get(server_id)
+            .or_not_found("Server not found")?
+            .clone();
+
+        let props_path = server.path.join("server.properties");
+        if !props_path.exists() {
+            return Ok(());
+        }
+
+        let content = fs::read_to_string(&props_path).await?;
+        let mut new_lines = Vec::new();
+        let mut found = false;
+
+        for line in content.lines() {
+            if line.trim().starts_with("max-players=") {
+                new_lines.push(format!("max-players={}", max_players));
+                found = true;
+            } else {
+                new_lines.push(line.to_string());
+            }
+        }
+
+        if !found {
+            new_lines.push(format!("max-players={}", max_players));
+        }
+
+        fs::write(&props_path, new_lines.join("\n")).await?;
+        Ok(())
+    }
+
+    pub async fn get_server_max_players(&self, server_id: &str) -> Result<u32> {
+        let server = self
+            .servers
+            .lock()
+            .await
+            .get(server_id)
+            .or_not_found("Server not found")?
+            .clone();
+
+        let props_path = server.path.join("server.properties");
+        if !props_path.exists() {
+            return Ok(20);
+        }
+
+        let content = fs::read_to_string(&props_path).await?;
+        for line in content.lines() {
+            if let Some(val) = line.trim().strip_prefix("max-players=") {
+                return Ok(val.parse().unwrap_or(20));
+            }
+        }
+        Ok(20)
+    }
+
+    /// With `optional_pack`, GeyserOptionalPack is deployed and the custom
+    /// item/block mapping folders are prepared for servers with custom models.
+    pub async fn install_geyser(&self, server_id: &str, optional_pack: bool) -> Result<()> {
+        let server = self
+            .servers
+            .lock()
+            .await
+            .get(server_id)
+            .or_not_found("Server not found")?
+            .clone();
+
+        let Some(variant) = geyser_variant(&server.server_type) else {
+            anyhow::bail!("Geyserはこのサーバータイプに対応していません。Paper・Fabric・Velocityなどを使用してください。")
+        };
+        self.create_restore_point(server_id, "Install Geyser")
+            .await?;
+
+        let jar_dir = server.path.join(variant.jar_dir);
+        fs::create_dir_all(&jar_dir).await?;
+
+        self.install_plugin(
+            &jar_dir,
+            &format!(
+                "https://download.geysermc.org/v2/projects/geyser/versions/latest/builds/latest/downloads/{}",
+                variant.platform
+            ),
+            variant.geyser_jar,
+            false,
+        ).await.context("Failed to install Geyser")?;
+
+        self.install_plugin(
+            &jar_dir,
+            &format!(
+                "https://download.geysermc.org/v2/projects/floodgate/versions/latest/builds/latest/downloads/{}",
+                variant.floodgate_platform
+            ),
+            variant.floodgate_jar,
+            false,
+        ).await.context("Failed to install Floodgate")?;
+
+        // Proxies have no server.properties, the backends keep their own setting
+        if variant.proxy {
+            self.link_floodgate_backends(&server).await?;
+        } else {
+            self.update_server_property(&server.path, "enforce-secure-profile", "false")
+                .await?;
+        }
+
+        if optional_pack {
+            self.install_geyser_optional_pack(&server.path.join(variant.data_dir))
+                .await
+                .context("Failed to install GeyserOptionalPack")?;
+        }
+
+        if server.server_type == ServerType::Fabric {
+            // Geyser-Fabric and Floodgate need the Fabric API mod
+            if let Err(e) = self
+                .install_modrinth_plugin(server_id, "fabric-api", "Fabric API")
+                .await
+            {
+                println!("[Geyser] Failed to install Fabric API: {}", e);
+            }
+        } else {
+            // "True" AutoGeyser: Install AutoUpdateGeyser plugin to keep them updated
+            // Slug: autoupdategeyser (NewAmazingPVP)
+            println!("Installing AutoUpdateGeyser...");
+            if let Err(e) = self
+                .install_modrinth_plugin(server_id, "autoupdategeyser", "AutoUpdateGeyser")
+                .await
+            {
+                println!("Failed to install AutoUpdateGeyser: {}", e);
+                // Don't fail the whole process, manual update is better than nothing
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Deploy GeyserOptionalPack into Geyser's packs folder and create the
+    /// custom_mappings folder Geyser loads item/block mappings from
+    async fn install_geyser_optional_pack(&self, geyser_dir: &Path) -> Result<()> {
+        let packs_dir = geyser_dir.join("packs");
+        fs::create_dir_all(&packs_dir).await?;
+        fs::create_dir_all(geyser_dir.join("custom_mappings")).await?;
+
+        let client = reqwest::Client::builder()
+            .user_agent("MinecraftServerManager/0.1.0")
+            .build()?;
+        let url = "https://download.geysermc.org/v2/projects/geyseroptionalpack/versions/latest/builds/latest/downloads/geyseroptionalpack";
+        // Resource packs are zips without a plugin descriptor, so no jar validation
+        crate::download::download_file(
+            &client,
+            &[url],
+            &packs_dir.join(GEYSER_OPTIONAL_PACK),
+            "plugin:GeyserOptionalPack",
+            None,
+        )
+        .await?;
+
+        // Custom items are only sent to Bedrock clients with this enabled.
+        // The config only exists after Geyser's first start; otherwise the
+        // default written by Geyser is used.
+        let config_path = geyser_dir.join("config.yml");
+        if let Ok(content) = fs::read_to_string(&config_path).await {
+            let updated: Vec<String> = content
+                .lines()
+                .map(|line| {
+                    if line.trim_start().starts_with("add-non-bedrock-items:") {
+                        "add-non-bedrock-items: true".to_string()
+                    } else {
+                        line.to_string()
+                    }
+                })
+                .collect();
+            fs::write(&config_path, updated.join("\n") + "\n").await?;
+        }
+
+        println!("[Geyser] Installed GeyserOptionalPack and custom_mappings folder");
+        Ok(())
+    }
+
+    /// ViaBackwards (older clients down to 1.10) and ViaRewind (down to 1.8)
+    /// are optional companions; ViaRewind needs ViaBackwards and installs it.
+    pub async fn install_viaversion(
+        &self,
+        server_id: &str,
+        backwards: bool,
+        rewind: bool,
+    ) -> Result<()> {
+        let server = self
+            .servers
+            .lock()
+            .await
+            .get(server_id)
+            .or_not_found("Server not found")?
+            .clone();
+
+        match server.server_type {
+            ServerType::Vanilla => {
+                anyhow::bail!("Vanilla servers do not support plugins. Please use Paper or Spigot.")
+            }
+            _ => {}
+        }
+        self.create_restore_point(server_id, "Install ViaVersion")
+            .await?;
+
+        let plugins_path = server.path.join("plugins");
+        fs::create_dir_all(&plugins_path).await?;
+
+        // Fetch latest ViaVersion from Hangar API
+        let via_versions = hangar_releases("ViaVersion").await?;
+        let latest_version = via_versions
+            .first()
+            .context("No ViaVersion versions found")?;
+        let via_version = latest_version["name"].as_str().unwrap_or_default();
+
+        let download_url = hangar_download_url(latest_version)?;
+
+        println!("Found ViaVersion download URL: {}", download_url);
+
+        self.install_plugin(&plugins_path, download_url, "ViaVersion.jar", false)
+            .await
+            .context("Failed to install ViaVersion")?;
+
+        if backwards || rewind {
+            // ViaBackwards is released in lockstep with ViaVersion: take the same
+            // version, else the newest of the same major version
+            let releases = hangar_releases("ViaBackwards").await?;
+            let major = via_version.split('.').next().unwrap_or_default();
+            let release = releases
+                .iter()
+                .find(|v| v["name"].as_str() == Some(via_version))
+                .or_else(|| {
+                    releases.iter().find(|v| {
+                        v["name"].as_str().and_then(|n| n.split('.').next()) == Some(major)
+                    })
+                })
+                .with_context(|| {
+                    format!("No ViaBackwards release for ViaVersion {}", via_version)
+                })?;
+            self.install_plugin(
+                &plugins_path,
+                hangar_download_url(release)?,
+                "ViaBackwards.jar",
+                false,
+            )
+            .await
+            .context("Failed to install ViaBackwards")?;
+        }
+
+        if rewind {
+            let releases = hangar_releases("ViaRewind").await?;
+            let release = releases.first().context("No ViaRewind versions found")?;
+            self.install_plugin(
+                &plugins_path,
+                hangar_download_url(release)?,
+                "ViaRewind.jar",
+                false,
+            )
+            .await
+            .context("Failed to install ViaRewind")?;
+        }
+
+        Ok(())
+    }
+
+    /// Harden a public Paper server: anti-xray engine mode 2, packet and spam
+    /// limits and exploit fixes. Proxy forwarding is checked but only reported,
+    /// since fixing it needs the proxy. Settings apply on the next start.
+    pub async fn apply_security_preset(
+        &self,
+        server_id: &str,
+        install_plugin: bool,
+    ) -> Result<SecurityPresetReport> {
+        self.ensure_writable()?;
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        if !matches!(server.server_type, ServerType::Paper | ServerType::Purpur) {
+            anyhow::bail!("The security preset needs a Paper or Purpur server");
+        }
+
+        let config_dir = server.path.join("config");
+        if server.path.join("paper.yml").exists() && !config_dir.join("paper-global.yml").exists() {
+            anyhow::bail!(
+                "This Paper version uses the old paper.yml layout, update to 1.19 or newer"
+            );
+        }
+        fs::create_dir_all(&config_dir).await?;
+        self.create_restore_point(server_id, "Security preset")
+            .await?;
+
+        let mut report = SecurityPresetReport {
+            changes: Vec::new(),
+            warnings: Vec::new(),
+            plugin_installed: None,
+            restart_required: server.status != ServerStatus::Stopped,
+        };
+
+        // Paper fills in everything missing from its config files on start
+        let mut configs: Vec<(&str, serde_yaml::Value)> = Vec::new();
+        for file in ["paper-global.yml", "paper-world-defaults.yml"] {
+            let content = fs::read_to_string(config_dir.join(file))
+                .await
+                .unwrap_or_default();
+            let config = if content.trim().is_empty() {
+                serde_yaml::Value::Mapping(serde_yaml::Mapping::new())
+            } else {
+                serde_yaml::from_str(&content).with_context(|| format!("Invalid {}", file))?
+            };
+            configs.push((file, config));
+        }
+
+        for (file, path, value) in SECURITY_PRESET {
+            let value: serde_yaml::Value = serde_yaml::from_str(value)?;
+            let (_, config) = configs
+                .iter_mut()
+                .find(|(name, _)| name == file)
+                .context("Unknown config file")?;
+            if set_yaml_path(config, path, value.clone()) {
+                let shown = serde_yaml::to_string(&value)?;
+                report.changes.push(format!(
+                    "{}: {} = {}",
+                    file,
+                    path,
+                    shown.trim().replace('\n', " ")
+                ));
+            }
+        }
+
+        // Without online mode, only a proxy with modern forwarding proves player identities
+        let online_mode = self
+            .read_server_property(&server.path, "online-mode")
+            .await
+            .is_none_or(|v| v != "false");
+        let (_, global) = &mut configs[0];
+        let velocity = yaml_path(global, "proxies.velocity.enabled")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        if velocity {
+            if yaml_path(global, "proxies.velocity.secret")
+                .and_then(|v| v.as_str())
+                .is_none_or(|secret| secret.is_empty())
+            {
+                report.warnings.push(
+                    "Velocity forwarding is enabled without a secret, configure the server from its proxy"
+                        .to_string(),
+                );
+            }
+            if set_yaml_path(
+                global,
+                "proxies.velocity.online-mode",
+                serde_yaml::Value::Bool(true),
+            ) {
+                report
+                    .changes
+                    .push("paper-global.yml: proxies.velocity.online-mode = true".to_string());
+            }
+        }
+        if !online_mode && !velocity {
+            let bungeecord = fs::read_to_string(server.path.join("spigot.yml"))
+                .await
+                .ok()
+                .and_then(|content| serde_yaml::from_str::<serde_yaml::Value>(&content).ok())
+                .and_then(|config| yaml_path(&config, "settings.bungeecord")?.as_bool())
+                .unwrap_or(false);
+            report.warnings.push(if bungeecord {
+                "BungeeCord forwarding can be spoofed by anyone reaching this port, use a Velocity proxy with modern forwarding".to_string()
+            } else {
+                "online-mode is off without proxy forwarding: anyone can join under any name".to_string()
+            });
+        }
+        if !online_mode
+            && self
+                .read_server_property(&server.path, "server-ip")
+                .await
+                .is_none_or(|ip| ip.is_empty() || ip == "0.0.0.0")
+        {
+            report.warnings.push(
+                "The server accepts connections on all interfaces, set server-ip=127.0.0.1 if only the proxy should reach it"
+                    .to_string(),
+            );
+        }
+
+        for (file, config) in &configs {
+            fs::write(config_dir.join(file), serde_yaml::to_string(config)?)
+                .await
+                .with_context(|| format!("Failed to write {}", file))?;
+        }
+
+        if install_plugin {
+            match self
+                .install_modrinth_plugin(server_id, EXPLOIT_PATCH_PROJECT, "GrimAC")
+                .await
+            {
+                Ok(()) => report.plugin_installed = Some("GrimAC".to_string()),
+                Err(e) => report
+                    .warnings
+                    .push(format!("Failed to install GrimAC: {}", e)),
+            }
+        }
+
+        println!(
+            "[Security] {}: {} settings changed, {} warnings",
+            server.name,
+            report.changes.len(),
+            report.warnings.len()
+        );
+        Ok(report)
+    }
+
+    /// Download a plugin jar to the staging folder and scan it against the
+    /// malware blocklist before it goes into `plugins_path`. Flagged jars are
+    /// quarantined unless `allow_flagged` is set.
+    async fn install_plugin(
+        &self,
+        plugins_path: &Path,
+        url: &str,
+        filename: &str,
+        allow_flagged: bool,
+    ) -> Result<()> {
+        println!("Downloading plugin: {} from {}", filename, url);
+        fs::create_dir_all(plugins_path).await?;
+
+        let client = reqwest::Client::builder()
+            .user_agent("MinecraftServerManager/0.1.0")
+            .build()?;
+
+        let task_id = format!("plugin:{}", filename);
+        let staged = self.malware_scanner.staging_path(filename)?;
+        {
+            let _slot = self.install_queue.acquire(url).await;
+            let downloaded = crate::download::download_file(
+                &client,
+                &[url],
+                &staged,
+                &task_id,
+                Some(JarKind::Plugin),
+            )
+            .await;
+            if let Err(e) = downloaded {
+                crate::malware_scan::discard_staged(&staged);
+                return Err(e.context(format!("Failed to download plugin {}", filename)));
+            }
+        }
+
+        // An unscanned jar must never reach the plugins folder
+        let flagged = match self.malware_scanner.check_file(&staged).await {
+            Ok(flagged) => flagged,
+            Err(e) => {
+                crate::malware_scan::discard_staged(&staged);
+                return Err(e.context("Failed to scan plugin"));
+            }
+        };
+        if let Some(hash) = flagged {
+            if !allow_flagged {
+                let quarantined = self.malware_scanner.quarantine(&staged, url, &hash);
+                crate::malware_scan::discard_staged(&staged);
+                let entry = quarantined?;
+                println!(
+                    "[MalwareScan] Quarantined {} (hash {} is blocklisted)",
+                    filename, hash
+                );
+                anyhow::bail!(
+                    "{} matches a known malicious jar ({}) and was moved to quarantine as {}",
+                    filename,
+                    hash,
+                    entry.id
+                );
+            }
+            println!(
+                "[MalwareScan] Installing blocklisted {} ({}) on user override",
+                filename, hash
+            );
+        }
+        crate::malware_scan::install_staged(&staged, &plugins_path.join(filename))
+            .with_context(|| format!("Failed to install plugin {}", filename))?;
+
+        // Remember where the jar came from so templates can download it again
+        let _folder = self.install_queue.lock_folder(plugins_path).await;
+        let mut sources = read_plugin_sources(plugins_path).await;
+        sources.insert(filename.to_string(), url.to_string());
+        fs::write(
+            plugins_path.join(PLUGIN_SOURCES_FILE),
+            serde_json::to_string_pretty(&sources)?,
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn uninstall_geyser(&self, server_id: &str) -> Result<()> {
+        let server = self
+            .servers
+            .lock()
+            .await
+            .get(server_id)
+            .or_not_found("Server not found")?
+            .clone();
+        let variant = geyser_variant(&server.server_type)
+            .context("Geyser is not installed on this server")?;
+        self.create_restore_point(server_id, "Uninstall Geyser")
+            .await?;
+
+        for jar_path in [
+            variant.geyser_jar_path(&server.path),
+            variant.floodgate_jar_path(&server.path),
+        ] {
+            if jar_path.exists() {
+                fs::remove_file(jar_path).await?;
+            }
+        }
+
+        let pack_path = server
+            .path
+            .join(variant.data_dir)
+            .join("packs")
+            .join(GEYSER_OPTIONAL_PACK);
+        if pack_path.exists() {
+            fs::remove_file(pack_path).await?;
+        }
+
+        // Restore enforce-secure-profile in server.properties
+        if !variant.proxy {
+            self.update_server_property(&server.path, "enforce-secure-profile", "true")
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn uninstall_viaversion(&self, server_id: &str) -> Result<()> {
+        let server = self
+            .servers
+            .lock()
+            .await
+            .get(server_id)
+            .or_not_found("Server not found")?
+            .clone();
+        let plugins_path = server.path.join("plugins");
+        self.create_restore_point(server_id, "Uninstall ViaVersion")
+            .await?;
+
+        // ViaBackwards and ViaRewind fail to load without ViaVersion, remove the set
+        for jar in ["ViaVersion.jar", "ViaBackwards.jar", "ViaRewind.jar"] {
+            let jar_path = plugins_path.join(jar);
+            if jar_path.exists() {
+                fs::remove_file(jar_path).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Install BlueMap or Dynmap for the server's platform and serve it on
+    /// `port` (default 8100 / 8123, or the next free port). BlueMap only renders
+    /// after the owner accepts its download of the Minecraft client (`accept_download`).
+    pub async fn install_web_map(
+        &self,
+        server_id: &str,
+        plugin: WebMapPlugin,
+        port: Option<u16>,
+        public: bool,
+        accept_download: bool,
+    ) -> Result<WebMapSettings> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+
+        match server.server_type {
+            ServerType::Vanilla
+            | ServerType::Mohist
+            | ServerType::Taiyitist
+            | ServerType::Velocity
+            | ServerType::Waterfall
+            | ServerType::BungeeCord => {
+                anyhow::bail!("このサーバータイプにはWebマップを導入できません。Paper/Spigot/Fabric/Forgeを使用してください。")
+            }
+            _ => {}
+        }
+
+        let port = match port {
+            Some(port) => port,
+            None => {
+                let default = plugin.default_port();
+                self.find_free_port(default, default.saturating_add(50))
+                    .await
+                    .context("No free port for the web map")?
+            }
+        };
+        if port == server.port {
+            anyhow::bail!("The web map cannot use the game port {}", port);
+        }
+
+        let name = match plugin {
+            WebMapPlugin::BlueMap => "BlueMap",
+            WebMapPlugin::Dynmap => "Dynmap",
+        };
+        self.install_modrinth_plugin(server_id, plugin.modrinth_id(), name)
+            .await
+            .with_context(|| format!("Failed to install {}", name))?;
+
+        let settings = WebMapSettings {
+            plugin,
+            port,
+            public,
+        };
+        let server = {
+            let mut servers = self.servers.lock().await;
+            let server = servers
+                .get_mut(server_id)
+                .or_not_found("Server not found")?;
+            server.web_map = Some(settings.clone());
+            server.clone()
+        };
+
+        if plugin == WebMapPlugin::BlueMap && accept_download {
+            let core_conf = web_map_config_dir(&server, plugin).join("core.conf");
+            if let Some(parent) = core_conf.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+            set_conf_value(&core_conf, "accept-download", "true").await?;
+        }
+        self.apply_web_map_config(&server).await?;
+
+        println!(
+            "[WebMap] Installed {} on port {} for {}",
+            name, port, server.name
+        );
+        Ok(settings)
+    }
+
+    /// Forget the web map and remove its plugin jar (rendered tiles are kept)
+    pub async fn uninstall_web_map(&self, server_id: &str) -> Result<()> {
+        let map = {
+            let mut servers = self.servers.lock().await;
+            let server = servers
+                .get_mut(server_id)
+                .or_not_found("Server not found")?;
+            server.web_map.take()
+        };
+        let Some(map) = map else {
+            return Ok(());
+        };
+        let name = match map.plugin {
+            WebMapPlugin::BlueMap => "BlueMap",
+            WebMapPlugin::Dynmap => "Dynmap",
+        };
+        let jar_path = self
+            .get_plugins_path(server_id)
+            .await?
+            .join(format!("{}.jar", name));
+        if jar_path.exists() {
+            fs::remove_file(jar_path).await?;
+        }
+        Ok(())
+    }
+
+    /// Write the web map port into the plugin config (created on the plugin's first start)
+    async fn apply_web_map_config(&self, server: &ServerInfo) -> Result<()> {
+        let Some(map) = &server.web_map else {
+            return Ok(());
+        };
+        let dir = web_map_config_dir(server, map.plugin);
+        match map.plugin {
+            WebMapPlugin::BlueMap => {
+                // BlueMap fills in defaults for everything missing from webserver.conf
+                fs::create_dir_all(&dir).await?;
+                set_conf_value(&dir.join("webserver.conf"), "port", &map.port.to_string()).await
+            }
+            WebMapPlugin::Dynmap => {
+                let config = dir.join("configuration.txt");
+                if !config.exists() {
+                    return Ok(()); // Dynmap writes it (default port 8123) on first start
+                }
+                set_conf_value(&config, "webserver-port", &map.port.to_string()).await
+            }
+        }
+    }
+
+    /// Turn on the UDP query for the full player and plugin lists. It shares the
+    /// Java port number unless the Bedrock relay listens there.
+    async fn apply_query_config(&self, server: &ServerInfo) -> Result<()> {
+        if !requires_eula(&server.server_type) {
+            return Ok(()); // Proxies have no server.properties
+        }
+        let current = self
+            .read_server_property(&server.path, "query.port")
+            .await
+            .and_then(|port| port.parse::<u16>().ok());
+        // Query shares the Java port number unless the relay or Geyser
+        // (bedrock.clone-remote-port) already listens there on UDP
+        let geyser_port = geyser_listen_port(server).await;
+        let taken = |port: u16| {
+            port == 0 || geyser_port == Some(port) || (server.bedrock_relay && port == server.port)
+        };
+        let port = match current {
+            _ if !taken(server.port) => server.port,
+            Some(port) if !taken(port) => port,
+            _ => {
+                // Any free UDP port, kept in server.properties for later starts
+                let socket = std::net::UdpSocket::bind(("0.0.0.0", 0))?;
+                socket.local_addr()?.port()
+            }
+        };
+        self.update_server_property(&server.path, "enable-query", "true")
+            .await?;
+        self.update_server_property(&server.path, "query.port", &port.to_string())
+            .await
+    }
+
+    /// Copy a resource pack zip into the server folder, upload it when it is not
+    /// hosted by the app, and point server.properties at it. `public_host` is the
+    /// address players reach the built-in host on.
+    pub async fn set_resource_pack(
+        &self,
+        server_id: &str,
+        source: &Path,
+        hosting: PackHosting,
+        required: bool,
+        public_host: Option<&str>,
+    ) -> Result<ResourcePackSettings> {
+        self.ensure_writable()?;
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        if !requires_eula(&server.server_type) {
+            anyhow::bail!("Proxies have no server.properties, set the pack on the backends");
+        }
+        if server.remote.is_some() {
+            anyhow::bail!("Resource packs can only be hosted for local servers");
+        }
+
+        let sha1 = crate::resource_pack::inspect_pack(source)?;
+        let pack = server.path.join(crate::resource_pack::PACK_FILE);
+        if source != pack {
+            fs::copy(source, &pack)
+                .await
+                .context("Failed to copy the resource pack")?;
+        }
+
+        let url = match &hosting {
+            PackHosting::BuiltIn { port } => {
+                if *port == server.port {
+                    anyhow::bail!("The resource pack cannot use the game port {}", port);
+                }
+                let host = public_host.context("No public address to host the resource pack on")?;
+                format!("http://{}:{}/{}.zip", host, port, sha1)
+            }
+            PackHosting::Upload {
+                upload_url,
+                public_url,
+            } => {
+                crate::resource_pack::upload(&pack, upload_url).await?;
+                public_url.clone()
+            }
+        };
+
+        // server.properties escapes ':' and '=' in values
+        let escaped = url.replace(':', "\\:").replace('=', "\\=");
+        self.update_server_property(&server.path, "resource-pack", &escaped)
+            .await?;
+        self.update_server_property(&server.path, "resource-pack-sha1", &sha1)
+            .await?;
+        self.update_server_property(&server.path, "require-resource-pack", &required.to_string())
+            .await?;
+
+        let settings = ResourcePackSettings {
+            hosting,
+            sha1,
+            url,
+            required,
+        };
+        let mut servers = self.servers.lock().await;
+        let server = servers
+            .get_mut(server_id)
+            .or_not_found("Server not found")?;
+        server.resource_pack = Some(settings.clone());
+        println!("[ResourcePack] {} now uses {}", server.name, settings.url);
+        Ok(settings)
+    }
+
+    /// Clear the pack from server.properties and delete the hosted copy
+    pub async fn remove_resource_pack(&self, server_id: &str) -> Result<()> {
+        self.ensure_writable()?;
+        let server = {
+            let mut servers = self.servers.lock().await;
+            let server = servers
+                .get_mut(server_id)
+                .or_not_found("Server not found")?;
+            if server.resource_pack.take().is_none() {
+                return Ok(());
+            }
+            server.clone()
+        };
+        self.update_server_property(&server.path, "resource-pack", "")
+            .await?;
+        self.update_server_property(&server.path, "resource-pack-sha1", "")
+            .await?;
+        self.update_server_property(&server.path, "require-resource-pack", "false")
+            .await?;
+        let pack = server.path.join(crate::resource_pack::PACK_FILE);
+        if pack.exists() {
+            fs::remove_file(pack).await?;
+        }
+        Ok(())
+    }
+
+    async fn update_server_property(
+        &self,
+        server_path: &Path,
+        key: &str,
+        value: &str,
+    ) -> Result<()> {
+        let props_path = server_path.join("server.properties");
+
+        // Read existing content or start empty
+        let content = if props_path.exists() {
+            fs::read_to_string(&props_path).await?
+        } else {
+            String::new()
+        };
+
+        let mut new_lines = Vec::new();
+        let mut found = false;
+
+        for line in content.lines() {
+            let mut matched = false;
+            // Ignore comments for keys
+            if !line.trim().starts_with('#') {
+                if let Some((k, _)) = line.split_once('=') {
+                    if k.trim() == key {
+                        new_lines.push(format!("{}={}", key, value));
+                        matched = true;
+                        found = true;
+                    }
+                }
+            }
+
+            if !matched {
+                new_lines.push(line.to_string());
+            }
+        }
+
+        if !found {
+            new_lines.push(format!("{}={}", key, value));
+        }
+
+        fs::write(props_path, new_lines.join("\n")).await?;
+        Ok(())
+    }
+
+    pub async fn read_server_property(&self, server_path: &Path, key: &str) -> Option<String> {
+        let content = fs::read_to_string(server_path.join("server.properties"))
+            .await
+            .ok()?;
+        content
+            .lines()
+            .filter(|line| !line.trim().starts_with('#'))
+            .filter_map(|line| line.split_once('='))
+            .find(|(k, _)| k.trim() == key)
+            .map(|(_, v)| v.trim().to_string())
+    }
+
+    /// Capture a server's type, version, memory, JVM flags, properties and plugin list
+    pub async fn capture_template(&self, server_id: &str, name: &str) -> Result<ServerTemplate> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+
+        let jvm_args = fs::read_to_string(server.path.join("user_jvm_args.txt"))
+            .await
+            .ok();
+
+        let properties = fs::read_to_string(server.path.join("server.properties"))
+            .await
+            .unwrap_or_default()
+            .lines()
+            .filter(|line| !line.trim().starts_with('#'))
+            .filter_map(|line| line.split_once('='))
+            .filter(|(k, _)| !TEMPLATE_EXCLUDED_PROPERTIES.contains(&k.trim()))
+            .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+            .collect();
+
+        let plugins_path = self.get_plugins_path(server_id).await?;
+        let sources = read_plugin_sources(&plugins_path).await;
+        let mut plugins = Vec::new();
+        if let Ok(mut entries) = fs::read_dir(&plugins_path).await {
+            while let Some(entry) = entries.next_entry().await? {
+                let filename = entry.file_name().to_string_lossy().to_string();
+                if filename.ends_with(".jar") {
+                    plugins.push(TemplatePlugin {
+                        url: sources.get(&filename).cloned(),
+                        filename,
+                    });
+                }
+            }
+        }
+        plugins.sort_by(|a, b| a.filename.cmp(&b.filename));
+
+        Ok(ServerTemplate {
+            id: String::new(),
+            name: name.to_string(),
+            server_type: server.server_type,
+            version: server.version,
+            max_memory: server.max_memory,
+            min_memory: server.min_memory,
+            jvm_args,
+            properties,
+            plugins,
+            created_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+        })
+    }
+
+    /// Provision a new server matching a template.
+    /// Returns the server and the plugins that could not be downloaded again.
+    pub async fn create_server_from_template(
+        &self,
+        template: &ServerTemplate,
+        name: String,
+        port: u16,
+        eula_accepted: bool,
+    ) -> Result<(ServerInfo, Vec<String>)> {
+        let server = self
+            .create_server(
+                name,
+                template.version.clone(),
+                template.server_type.clone(),
+                port,
+                template.max_memory.clone(),
+                eula_accepted,
+            )
+            .await?;
+        self.set_server_memory(&server.id, &template.max_memory, &template.min_memory)
+            .await?;
+
+        if let Some(jvm_args) = &template.jvm_args {
+            fs::write(server.path.join("user_jvm_args.txt"), jvm_args).await?;
+        }
+        for (key, value) in &template.properties {
+            self.update_server_property(&server.path, key, value)
+                .await?;
+        }
+
+        let mut skipped = Vec::new();
+        for plugin in &template.plugins {
+            let Some(url) = &plugin.url else {
+                skipped.push(format!("{} (no recorded source)", plugin.filename));
+                continue;
+            };
+            if let Err(e) = self
+                .install_plugin_by_url(&server.id, url, Some(plugin.filename.clone()), false)
+                .await
+            {
+                skipped.push(format!("{} ({})", plugin.filename, e));
+            }
+        }
+
+        let server = self
+            .get_server(&server.id)
+            .await
+            .or_not_found("Server not found")?;
+        Ok((server, skipped))
+    }
+
+    /// Top-down PNG of the active world's spawn area as a data URL for the server
+    /// card, None while the world has not been generated
+    pub async fn render_world_preview(
+        &self,
+        server_id: &str,
+        force: bool,
+    ) -> Result<Option<String>> {
+        use base64::Engine;
+
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        let level_name = self
+            .read_server_property(&server.path, "level-name")
+            .await
+            .unwrap_or_else(|| "world".to_string());
+        let world_dir = server.path.join(&level_name);
+        let cache_path = self
+            .paths
+            .cache_dir
+            .join("previews")
+            .join(&server.id)
+            .join(format!("{}.png", level_name));
+
+        let png = tokio::task::spawn_blocking(move || {
+            crate::world_preview::render(&world_dir, &cache_path, force)
+        })
+        .await??;
+        Ok(png.map(|png| {
+            format!(
+                "data:image/png;base64,{}",
+                base64::engine::general_purpose::STANDARD.encode(png)
+            )
+        }))
+    }
+
+    /// List world folders (any directory containing level.dat) with their size on disk
+    pub async fn list_worlds(&self, server_id: &str) -> Result<Vec<WorldInfo>> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        let level_name = self
+            .read_server_property(&server.path, "level-name")
+            .await
+            .unwrap_or_else(|| "world".to_string());
+
+        let mut worlds = Vec::new();
+        let mut entries = fs::read_dir(&server.path).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if !path.is_dir() || !path.join("level.dat").exists() {
+                continue;
+            }
+
+            let name = entry.file_name().to_string_lossy().to_string();
+            let active = name == level_name
+                || name == format!("{}_nether", level_name)
+                || name == format!("{}_the_end", level_name);
+            let size_bytes = tokio::task::spawn_blocking(move || dir_size(&path))
+                .await
+                .unwrap_or(0);
+
+            worlds.push(WorldInfo {
+                name,
+                size_bytes,
+                active,
+            });
+        }
+
+        worlds.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(worlds)
+    }
+
+    /// Switch the world loaded on next start (level-name in server.properties)
+    pub async fn set_active_world(&self, server_id: &str, world_name: &str) -> Result<()> {
+        validate_world_name(world_name)?;
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+
+        self.update_server_property(&server.path, "level-name", world_name)
+            .await
+    }
+
+    /// Delete a world together with its Bukkit-style _nether/_the_end folders
+    pub async fn delete_world(&self, server_id: &str, world_name: &str) -> Result<()> {
+        validate_world_name(world_name)?;
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+
+        if server.status != ServerStatus::Stopped {
+            return Err(PrismarineError::invalid_state(
+                "Server must be stopped before deleting a world",
+            )
+            .into());
+        }
+        if !server.path.join(world_name).join("level.dat").is_file() {
+            return Err(
+                PrismarineError::not_found(format!("World {} not found", world_name)).into(),
+            );
+        }
+
+        for folder in [
+            world_name.to_string(),
+            format!("{}_nether", world_name),
+            format!("{}_the_end", world_name),
+        ] {
+            let path = server.path.join(&folder);
+            if path.is_dir() {
+                fs::remove_dir_all(&path)
+                    .await
+                    .context(format!("Failed to delete world folder {}", folder))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Delete a world so it is regenerated on next start, optionally with a new seed
+    pub async fn reset_world(
+        &self,
+        server_id: &str,
+        world_name: &str,
+        seed: Option<String>,
+    ) -> Result<()> {
+        self.delete_world(server_id, world_name).await?;
+
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        self.update_server_property(&server.path, "level-seed", seed.as_deref().unwrap_or(""))
+            .await
+    }
+
+    /// Import a world from a zip file as `world_name` and make it the active world.
+    /// Handles zips where the world sits inside one or more nested folders.
+    pub async fn import_world(
+        &self,
+        server_id: &str,
+        zip_path: &Path,
+        world_name: &str,
+    ) -> Result<()> {
+        validate_world_name(world_name)?;
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+
+        let target = server.path.join(world_name);
+        if target.exists() {
+            anyhow::bail!("A world named {} already exists", world_name);
+        }
+
+        let zip_path = zip_path.to_path_buf();
+        let extract_target = target.clone();
+        let result =
+            tokio::task::spawn_blocking(move || extract_world_zip(&zip_path, &extract_target))
+                .await?;
+
+        if let Err(e) = result {
+            let _ = fs::remove_dir_all(&target).await;
+            return Err(e);
+        }
+
+        self.update_server_property(&server.path, "level-name", world_name)
+            .await?;
+        println!(
+            "[ServerManager] Imported world {} into {}",
+            world_name, server.name
+        );
+        Ok(())
+    }
+
+    /// Export a world (and its _nether/_the_end folders) to a zip file
+    pub async fn export_world(
+        &self,
+        server_id: &str,
+        world_name: &str,
+        dest_path: &Path,
+    ) -> Result<()> {
+        validate_world_name(world_name)?;
+        let _slot = crate::limits::BACKUP_JOBS.acquire().await;
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+
+        if !server.path.join(world_name).join("level.dat").exists() {
+            return Err(
+                PrismarineError::not_found(format!("World {} not found", world_name)).into(),
+            );
+        }
+
+        // Flush chunks to disk so the export is consistent
+        if server.status == ServerStatus::Running
+            && self.send_command(server_id, "save-all flush").await.is_ok()
+        {
+            tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
+        }
+
+        let folders: Vec<String> = [
+            world_name.to_string(),
+            format!("{}_nether", world_name),
+            format!("{}_the_end", world_name),
+        ]
+        .into_iter()
+        .filter(|f| server.path.join(f).is_dir())
+        .collect();
+
+        let server_path = server.path.clone();
+        let dest_path = dest_path.to_path_buf();
+        let result = tokio::task::spawn_blocking(move || {
+            write_world_zip(&server_path, &folders, &dest_path)
+        })
+        .await?;
+        crate::reports::record_backup(server_id, result.is_ok());
+        if let Err(e) = &result {
+            let message = format!("The world backup of \"{}\" failed: {}", server.name, e);
+            tokio::spawn(async move {
+                crate::notifications::dispatch(
+                    crate::notifications::NotifyEvent::BackupFailed,
+                    "Backup failed",
+                    &message,
+                )
+                .await;
+            });
+        }
+        result
+    }
+
+    pub async fn check_geyser_installed(&self, server_id: &str) -> Result<bool> {
+        let server = self
+            .servers
+            .lock()
+            .await
+            .get(server_id)
+            .or_not_found("Server not found")?
+            .clone();
+        let Some(variant) = geyser_variant(&server.server_type) else {
+            return Ok(false);
+        };
+
+        let geyser_exists = variant.geyser_jar_path(&server.path).exists();
+        let floodgate_exists = variant.floodgate_jar_path(&server.path).exists();
+
+        println!(
+            "[Check] Server: {}, Geyser: {}, Floodgate: {}",
+            server_id, geyser_exists, floodgate_exists
+        );
+        if variant.proxy {
+            return Ok(geyser_exists && floodgate_exists);
+        }
+
+        // Check server.properties for enforce-secure-profile=false
+        let props_path = server.path.join("server.properties");
+        let mut secure_profile_bg_check = false;
+
+        if props_path.exists() {
+            let content = fs::read_to_string(&props_path).await?;
+            for line in content.lines() {
+                let trimmed = line.trim();
+                // Ignore comments
+                if trimmed.starts_with('#') {
+                    continue;
+                }
+
+                if let Some((k, v)) = trimmed.split_once('=') {
+                    if k.trim() == "enforce-secure-profile" {
+                        println!("[Check] Found enforce-secure-profile value: '{}'", v.trim());
+                        if v.trim() == "false" {
+                            secure_profile_bg_check = true;
+                        }
+                        break;
+                    }
+                }
+            }
+        } else {
+            println!("[Check] server.properties not found at {:?}", props_path);
+            secure_profile_bg_check = false;
+        }
+
+        println!(
+            "[Check] Secure Profile Disabled: {}",
+            secure_profile_bg_check
+        );
+
+        // Treat as installed only if ALL conditions match.
+        Ok(geyser_exists && floodgate_exists && secure_profile_bg_check)
+    }
+
+    pub async fn check_viaversion_installed(
+        &self,
+        server_id: &str,
+    ) -> Result<crate::protocol_versions::ViaPlugins> {
+        let server = self
+            .servers
+            .lock()
+            .await
+            .get(server_id)
+            .or_not_found("Server not found")?
+            .clone();
+        let plugins_path = server.path.join("plugins");
+
+        Ok(crate::protocol_versions::ViaPlugins {
+            via_version: plugins_path.join("ViaVersion.jar").exists(),
+            via_backwards: plugins_path.join("ViaBackwards.jar").exists(),
+            via_rewind: plugins_path.join("ViaRewind.jar").exists(),
+        })
+    }
+
+    /// Client versions that can join the server, counting ViaVersion
+    pub async fn client_versions(
+        &self,
+        server_id: &str,
+    ) -> Result<crate::protocol_versions::ClientVersions> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        if matches!(
+            server.server_type,
+            ServerType::Velocity | ServerType::Waterfall | ServerType::BungeeCord
+        ) {
+            anyhow::bail!("A proxy accepts the client versions of its backend servers");
+        }
+        let via = self.check_viaversion_installed(server_id).await?;
+        Ok(crate::protocol_versions::client_versions(
+            &server.version,
+            via,
+        ))
+    }
+
+    pub async fn search_plugins(
+        &self,
+        server_id: &str,
+        query: &str,
+        source: &str,
+    ) -> Result<Vec<PluginSearchResult>> {
+        let (version, server_type) = {
+            let servers = self.servers.lock().await;
+            let server = servers.get(server_id).or_not_found("Server not found")?;
+            (server.version.clone(), server.server_type.clone())
+        };
+
+        let search = |query: String| {
+            let (version, server_type) = (version.clone(), server_type.clone());
+            async move {
+                match source {
+                    "Modrinth" => self.search_modrinth(&query, &version, &server_type).await,
+                    "Spigot" => self.search_spigot(&query).await,
+                    _ => Err(anyhow::anyhow!("Unknown source: {}", source)),
+                }
+            }
+        };
+
+        let mut results = search(query.to_string()).await?;
+
+        // Japanese queries rarely match English listings, search the known terms too
+        if let Some(translated) = crate::search_hints::translate_query(query) {
+            println!(
+                "[Plugins] Also searching \"{}\" for \"{}\"",
+                translated, query
+            );
+            for mut result in search(translated.clone()).await? {
+                if results.iter().any(|r| r.id == result.id) {
+                    continue;
+                }
+                result.matched_query = Some(translated.clone());
+                results.push(result);
+            }
+        }
+        Ok(results)
+    }
+
+    pub async fn install_modrinth_plugin(
+        &self,
+        server_id: &str,
+        project_id: &str,
+        plugin_name: &str,
+    ) -> Result<()> {
+        crate::offline::ensure_online("Installing a plugin")?;
+        let (version, server_type) = {
+            let servers = self.servers.lock().await;
+            let server = servers.get(server_id).or_not_found("Server not found")?;
+            (server.version.clone(), server.server_type.clone())
+        };
+
+        let loaders = serde_json::to_string(modrinth_loaders(&server_type))?;
+
+        let game_versions = format!("[\"{}\"]", version);
+
+        let client = reqwest::Client::builder()
+            .user_agent("MinecraftServerManager/0.1.0 (antigravity)")
+            .build()?;
+
+        // Fetch versions filtered by loader and game version
+        let url = format!(
+            "https://api.modrinth.com/v2/project/{}/version?loaders={}&game_versions={}",
+            project_id, loaders, game_versions
+        );
+
+        let resp = client.get(&url).send().await?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let text = resp.text().await.unwrap_or_default();
+            println!("Modrinth API Error: {} - Body: {}", status, text);
+            anyhow::bail!("Modrinth API failed with status {}: {}", status, text);
+        }
+
+        let resp_text = resp.text().await?;
+        let versions: serde_json::Value =
+            serde_json::from_str(&resp_text).context("Failed to parse Modrinth JSON")?;
+
+        let versions = versions
+            .as_array()
+            .context("Invalid Modrinth version response")?;
+
+        if versions.is_empty() {
+            anyhow::bail!(
+                "No compatible version found for Minecraft {} ({:?})",
+                version,
+                server_type
+            );
+        }
+
+        // Pick the first one (latest compatible)
+        let latest = &versions[0];
+        let files = latest["files"]
+            .as_array()
+            .context("No files found in version")?;
+
+        // Find the primary file or first .jar
+        let file = files
+            .iter()
+            .find(|f| {
+                f["primary"].as_bool().unwrap_or(false)
+                    || f["filename"].as_str().unwrap_or("").ends_with(".jar")
+            })
+            .or(files.first())
+            .context("No suitable file found")?;
+
+        let download_url = file["url"].as_str().context("No download URL")?.to_string();
+
+        // Sanitize plugin name for filename (remove invalid characters)
+        let safe_name: String = plugin_name
+            .chars()
+            .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_' || *c == ' ')
+            .collect();
+        let filename = format!("{}.jar", safe_name.trim());
+
+        self.install_plugin_by_url(server_id, &download_url, Some(filename), false)
+            .await?;
+        Ok(())
+    }
+
+    async fn search_modrinth(
+        &self,
+        query: &str,
+        version: &str,
+        server_type: &ServerType,
+    ) -> Result<Vec<PluginSearchResult>> {
+        crate::offline::ensure_online("Searching Modrinth")?;
+        let client = reqwest::Client::builder()
+            .user_agent("MinecraftServerManager/0.1.0 (antigravity)")
+            .build()?;
+
+        // Map ServerType to Modrinth categories (loaders)
+        let loaders_facet = match server_type {
+            ServerType::Paper | ServerType::Purpur => {
+                "[\"categories:paper\",\"categories:spigot\",\"categories:bukkit\"]"
+            }
+            ServerType::Spigot => "[\"categories:spigot\",\"categories:bukkit\"]",
+            ServerType::Forge => "[\"categories:forge\"]",
+            ServerType::Vanilla => "[\"categories:bukkit\"]", // Weak fallback
+            ServerType::Fabric | ServerType::Banner => "[\"categories:fabric\"]",
+            ServerType::Mohist => "[\"categories:forge\"]", // Mohist runs Forge mods
+            ServerType::Taiyitist => "[\"categories:forge\"]", // Taiyitist runs Forge mods
+            ServerType::Velocity => "[\"categories:velocity\"]",
+            ServerType::BungeeCord => "[\"categories:bungeecord\"]",
+            ServerType::Waterfall => "[\"categories:bungeecord\",\"categories:waterfall\"]",
+        };
+
+        let version_facet = format!("[\"versions:{}\"]", version);
+
+        let sort_param = if query.is_empty() {
+            "&sort=follows" // Better "Trending/Popular" indicator than total downloads
+        } else {
+            ""
+        };
+
+        let project_type_facet = match server_type {
+            ServerType::Fabric | ServerType::Forge | ServerType::Mohist | ServerType::Taiyitist => {
+                "[\"project_type:mod\"]"
+            }
+            _ => "[\"project_type:plugin\"]",
+        };
+
+        // Facets: ProjectType AND Version AND Loaders
+        let facets = format!(
+            "[{},{},{}]",
+            project_type_facet, version_facet, loaders_facet
+        );
+
+        let url = format!(
+            "https://api.modrinth.com/v2/search?query={}&facets={}&limit=20{}",
+            query, facets, sort_param
+        );
+
+        let resp: serde_json::Value = client.get(&url).send().await?.json().await?;
+        let hits = resp["hits"]
+            .as_array()
+            .context("Invalid Modrinth response")?;
+
+        let mut results = Vec::new();
+        for hit in hits {
+            let id = hit["project_id"].as_str().unwrap_or("").to_string();
+            let name = hit["title"].as_str().unwrap_or("").to_string();
+            let description = hit["description"].as_str().unwrap_or("").to_string();
+            let description_language = crate::search_hints::description_language(&description);
+            let author = hit["author"].as_str().unwrap_or("").to_string();
+            let icon_url = hit["icon_url"].as_str().map(|s| s.to_string());
+            let slug = hit["slug"].as_str().unwrap_or("");
+            let external_url = format!("https://modrinth.com/plugin/{}", slug);
+
+            results.push(PluginSearchResult {
+                id,
+                name,
+                description,
+                author,
+                icon_url,
+                source: "Modrinth".to_string(),
+                external_url,
+                download_url: None, // Modrinth needs version fetch
+                description_language,
+                matched_query: None,
+            });
+        }
+        Ok(results)
+    }
+
+    async fn search_spigot(&self, query: &str) -> Result<Vec<PluginSearchResult>> {
+        crate::offline::ensure_online("Searching SpigotMC")?;
+        let client = reqwest::Client::builder()
+            .user_agent("MinecraftServerManager/0.1.0")
+            .build()?;
+
+        let url = if query.is_empty() {
+            "https://api.spiget.org/v2/resources?limit=20&sort=-downloads".to_string()
+        } else {
+            format!(
+                "https://api.spiget.org/v2/search/resources/{}?limit=20&sort=-downloads",
+                query
+            )
+        };
+
+        // Spiget returns array directly or inside content? Usually array.
+        let resp: serde_json::Value = client.get(&url).send().await?.json().await?;
+
+        let mut results = Vec::new();
+        // Spiget behavior: if no results, might return empty array.
+        if let Some(items) = resp.as_array() {
+            for item in items {
+                let id = item["id"]
+                    .as_i64()
+                    .map(|i| i.to_string())
+                    .unwrap_or_default();
+                let name = item["name"].as_str().unwrap_or("").to_string();
+                let tag = item["tag"].as_str().unwrap_or("").to_string(); // Short desc
+                let description_language = crate::search_hints::description_language(&tag);
+                let author_id = item["author"]["id"].as_i64().unwrap_or(0);
+
+                // Icon handling in Spiget is weird, usually https://www.spigotmc.org/data/resource_icons/<id_prefix>/<id>.jpg
+                // But we can skip or try to construct.
+                let icon_url = if !item["icon"]["data"].as_str().unwrap_or("").is_empty() {
+                    Some(format!(
+                        "https://www.spigotmc.org/data/resource_icons/{}/{}.jpg",
+                        id.parse::<i64>().unwrap_or(0) / 1000,
+                        id
+                    ))
+                } else {
+                    None
+                };
+
+                let external_url = format!("https://www.spigotmc.org/resources/{}", id);
+
+                // Premium and externally hosted resources cannot be downloaded through Spiget
+                let downloadable = !item["premium"].as_bool().unwrap_or(false)
+                    && !item["external"].as_bool().unwrap_or(false)
+                    && item["file"]["type"].as_str().unwrap_or(".jar") == ".jar";
+
+                results.push(PluginSearchResult {
+                    id: id.clone(),
+                    name,
+                    description: tag,
+                    author: format!("User {}", author_id), // Fetching author name requires extra call, skip for now
+                    icon_url,
+                    source: "Spigot".to_string(),
+                    external_url,
+                    download_url: downloadable
+                        .then(|| format!("https://api.spiget.org/v2/resources/{}/download", id)),
+                    description_language,
+                    matched_query: None,
+                });
+            }
+        }
+        Ok(results)
+    }
+
+    pub async fn install_plugin_by_url(
+        &self,
+        server_id: &str,
+        download_url: &str,
+        filename: Option<String>,
+        allow_flagged: bool,
+    ) -> Result<()> {
+        let plugins_path = self.get_plugins_path(server_id).await?;
+        self.create_restore_point(server_id, "Install plugin")
+            .await?;
+
+        let fname = if let Some(n) = filename {
+            n
+        } else {
+            // Try to guess from URL or Content-Disposition?
+            // Simple fallback: "plugin.jar" or derive from end of URL.
+            // Spiget download urls don't have filename.
+            // Modrinth version urls might.
+            "unknown_plugin.jar".to_string()
+        };
+
+        self.install_plugin(&plugins_path, download_url, &fname, allow_flagged)
+            .await?;
+        if let Some(remote) = self.get_server(server_id).await.and_then(|s| s.remote) {
+            let local = plugins_path.join(&fname);
+            crate::remote::upload(&remote, &local, &remote_relative(&plugins_path, &fname)).await?;
+        }
+        Ok(())
+    }
+
+    /// Install a Modrinth modpack from a .mrpack URL or file. A failed install
+    /// continues where it stopped when run again with the same pack.
+    pub async fn install_modpack(
+        &self,
+        server_id: &str,
+        source: &str,
+    ) -> Result<crate::modpack::ModpackInstallResult> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        if server.status != ServerStatus::Stopped {
+            return Err(PrismarineError::invalid_state(
+                "Server must be stopped before installing a modpack",
+            )
+            .into());
+        }
+        if server.remote.is_some() {
+            return Err(PrismarineError::invalid_input(
+                "Modpacks can only be installed on local servers",
+            )
+            .into());
+        }
+        crate::offline::ensure_online("Installing a modpack")?;
+
+        let client = reqwest::Client::builder()
+            .user_agent("MinecraftServerManager/0.1.0 (antigravity)")
+            .build()?;
+        let downloaded = source.starts_with("https://") || source.starts_with("http://");
+        let pack_path = if downloaded {
+            let pack_path = server.path.join(crate::modpack::PACK_FILE);
+            crate::download::download_file(&client, &[source], &pack_path, "modpack", None).await?;
+            pack_path
+        } else {
+            let path = PathBuf::from(source);
+            if !path.is_file() {
+                return Err(PrismarineError::not_found("Modpack file not found").into());
+            }
+            path
+        };
+
+        self.create_restore_point(server_id, "Install modpack")
+            .await?;
+        let result = crate::modpack::install_mrpack(
+            &client,
+            self.malware_scanner.clone(),
+            &pack_path,
+            &server.path,
+        )
+        .await?;
+        if downloaded {
+            let _ = tokio::fs::remove_file(&pack_path).await;
+        }
+        Ok(result)
+    }
+
+    pub async fn install_spigot_plugin(
+        &self,
+        server_id: &str,
+        resource_id: &str,
+        plugin_name: &str,
+    ) -> Result<()> {
+        crate::offline::ensure_online("Installing a plugin")?;
+        // Spiget serves a web page instead of the jar for premium and external resources
+        let client = reqwest::Client::builder()
+            .user_agent("MinecraftServerManager/0.1.0")
+            .build()?;
+        let resource: serde_json::Value = client
+            .get(format!(
+                "https://api.spiget.org/v2/resources/{}",
+                resource_id
+            ))
+            .send()
+            .await?
+            .json()
+            .await?;
+        if resource["premium"].as_bool().unwrap_or(false) {
+            anyhow::bail!(
+                "{} is a premium plugin and must be bought on SpigotMC",
+                plugin_name
+            );
+        }
+        if resource["external"].as_bool().unwrap_or(false)
+            || resource["file"]["type"].as_str().unwrap_or(".jar") != ".jar"
+        {
+            anyhow::bail!(
+                "{} is hosted outside SpigotMC; download it from https://www.spigotmc.org/resources/{}",
+                plugin_name,
+                resource_id
+            );
+        }
+
+        let download_url = format!(
+            "https://api.spiget.org/v2/resources/{}/download",
+            resource_id
+        );
+        // Sanitize plugin name for filename (remove invalid characters)
+        let safe_name: String = plugin_name
+            .chars()
+            .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_' || *c == ' ')
+            .collect();
+        let filename = format!("{}.jar", safe_name.trim());
+        self.install_plugin_by_url(server_id, &download_url, Some(filename), false)
+            .await
+    }
+
+    pub async fn set_server_memory(
+        &self,
+        server_id: &str,
+        max_memory: &str,
+        min_memory: &str,
+    ) -> Result<()> {
+        let mut servers = self.servers.lock().await;
+        if let Some(server) = servers.get_mut(server_id) {
+            server.max_memory = max_memory.to_string();
+            server.min_memory = min_memory.to_string();
+            Ok(())
+        } else {
+            Err(PrismarineError::not_found("Server not found").into())
+        }
+    }
+
+    pub async fn is_plugin_installed(&self, server_id: &str, plugin_name: &str) -> Result<bool> {
+        let plugins_path = self.get_plugins_path(server_id).await?;
+
+        // Sanitize plugin name for filename (same logic as install)
+        let safe_name: String = plugin_name
+            .chars()
+            .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_' || *c == ' ')
+            .collect();
+        let filename = format!("{}.jar", safe_name.trim());
+
+        Ok(plugins_path.join(filename).exists())
+    }
+
+    pub async fn uninstall_plugin(&self, server_id: &str, plugin_name: &str) -> Result<()> {
+        let plugins_path = self.get_plugins_path(server_id).await?;
+        self.create_restore_point(server_id, &format!("Uninstall {}", plugin_name))
+            .await?;
+
+        // Sanitize plugin name for filename (same logic as install)
+        let safe_name: String = plugin_name
+            .chars()
             .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_' || *c == ' ')
             .collect();
-        let filename = format!("{}.jar", safe_name.trim());
+        let filename = format!("{}.jar", safe_name.trim());
+
+        if let Some(remote) = self.get_server(server_id).await.and_then(|s| s.remote) {
+            crate::remote::remove(&remote, &remote_relative(&plugins_path, &filename)).await?;
+        }
+        let file_path = plugins_path.join(filename);
+        if file_path.exists() {
+            fs::remove_file(file_path).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Best way to reload one plugin of the server, None where Bukkit's reload
+    /// is not available (mod loaders, hybrids and proxies)
+    pub async fn plugin_reload_method(&self, server_id: &str) -> Result<Option<ReloadMethod>> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        if !matches!(
+            server.server_type,
+            ServerType::Paper | ServerType::Spigot | ServerType::Purpur
+        ) {
+            return Ok(None);
+        }
+        let plugins_path = server.path.join("plugins");
+        let names =
+            tokio::task::spawn_blocking(move || crate::plugin_compat::plugin_names(&plugins_path))
+                .await?;
+        let has = |plugin: &str| names.iter().any(|(_, name)| name == plugin);
+        Ok(Some(if has("PlugManX") || has("PlugMan") {
+            ReloadMethod::PlugMan
+        } else if has("ServerUtils") {
+            ReloadMethod::ServerUtils
+        } else {
+            ReloadMethod::FullReload
+        }))
+    }
+
+    /// Reload one plugin through the console so config changes apply without a
+    /// restart. Without a plugin manager this falls back to "reload confirm",
+    /// which only runs with `allow_full_reload`.
+    pub async fn reload_plugin(
+        &self,
+        server_id: &str,
+        plugin_name: &str,
+        allow_full_reload: bool,
+    ) -> Result<ReloadMethod> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        if server.status != ServerStatus::Running {
+            return Err(PrismarineError::invalid_state("Server must be running").into());
+        }
+        let method = self
+            .plugin_reload_method(server_id)
+            .await?
+            .context("Plugins of this server type can't be reloaded, restart the server instead")?;
+        if method == ReloadMethod::FullReload && !allow_full_reload {
+            anyhow::bail!(
+                "No plugin manager (PlugManX or ServerUtils) is installed; /reload reloads every plugin and can break them, confirm to use it anyway"
+            );
+        }
+
+        // The console knows the plugin by its plugin.yml name, not the jar or project name
+        let plugins_path = server.path.join("plugins");
+        let names =
+            tokio::task::spawn_blocking(move || crate::plugin_compat::plugin_names(&plugins_path))
+                .await?;
+        let name = names
+            .iter()
+            .find(|(stem, _)| stem.eq_ignore_ascii_case(plugin_name))
+            .or_else(|| {
+                names
+                    .iter()
+                    .find(|(_, name)| name.eq_ignore_ascii_case(plugin_name))
+            })
+            .map(|(_, name)| name.clone())
+            .with_context(|| format!("Plugin {} is not installed", plugin_name))?;
+
+        println!("[Reload] {} on {} via {:?}", name, server.name, method);
+        self.send_command(server_id, &method.command(&name)).await?;
+        Ok(method)
+    }
+
+    /// Add a server running at a host. Its config files are pulled into a local
+    /// folder; the jar and worlds stay at the host.
+    pub async fn import_remote_server(
+        &self,
+        name: String,
+        server_type: ServerType,
+        version: String,
+        remote: RemoteHost,
+        panel_api_key: Option<String>,
+    ) -> Result<ServerInfo> {
+        self.ensure_writable()?;
+        crate::remote::test(&remote)
+            .await
+            .context("Could not connect to the host over SFTP")?;
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let server_path = self.base_path().join(&id);
+        fs::create_dir_all(&server_path).await?;
+        if let Err(e) = crate::remote::pull(&remote, &server_path).await {
+            let _ = fs::remove_dir_all(&server_path).await;
+            return Err(e);
+        }
+        if let Some(key) = panel_api_key.filter(|key| !key.is_empty()) {
+            let name = crate::secrets::panel_key(&id);
+            tokio::task::spawn_blocking(move || crate::secrets::set(&name, &key)).await??;
+        }
+        let port = self
+            .read_server_property(&server_path, "server-port")
+            .await
+            .and_then(|port| port.parse().ok())
+            .unwrap_or(25565);
+
+        let mut server_info = ServerInfo::new(id.clone(), name, server_path, server_type, version);
+        server_info.port = port;
+        // Memory and the EULA are handled by the host
+        server_info.max_memory = "-".to_string();
+        server_info.min_memory = "-".to_string();
+        server_info.eula_accepted = true;
+        server_info.remote = Some(remote);
+        println!(
+            "[Remote] Imported {} ({})",
+            server_info.name,
+            server_info
+                .remote
+                .as_ref()
+                .map(|r| r.host.as_str())
+                .unwrap_or("")
+        );
+
+        self.servers.lock().await.insert(id, server_info.clone());
+        Ok(server_info)
+    }
+
+    /// Pull the config files from the host, or push the ones edited locally
+    pub async fn sync_remote_server(&self, server_id: &str, push: bool) -> Result<SyncReport> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        let remote = server.remote.context("The server is not hosted remotely")?;
+        if push {
+            crate::remote::push(&remote, &server.path).await
+        } else {
+            crate::remote::pull(&remote, &server.path).await
+        }
+    }
+
+    /// Download latest.log of a remote server so the console shows current output
+    pub async fn refresh_remote_log(&self, server_id: &str) -> Result<()> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        let Some(remote) = server.remote else {
+            return Ok(());
+        };
+        let log = server.path.join("logs").join("latest.log");
+        // The console polls every few seconds, one SFTP session per 5 seconds is enough
+        let fresh = std::fs::metadata(&log)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age.as_secs() < 5);
+        if fresh {
+            return Ok(());
+        }
+        crate::remote::download(&remote, "logs/latest.log", &log).await
+    }
+
+    async fn remote_panel(
+        &self,
+        server: &ServerInfo,
+    ) -> Result<(crate::remote::PanelConfig, String)> {
+        let panel = server
+            .remote
+            .as_ref()
+            .and_then(|remote| remote.panel.clone())
+            .context("No host panel is configured. Use the host's control panel for this")?;
+        let name = crate::secrets::panel_key(&server.id);
+        let api_key = tokio::task::spawn_blocking(move || crate::secrets::get(&name))
+            .await??
+            .context("The panel API key is missing")?;
+        Ok((panel, api_key))
+    }
+
+    async fn remote_power(&self, server: &ServerInfo, signal: &str) -> Result<()> {
+        let (panel, api_key) = self.remote_panel(server).await?;
+        crate::remote::power(&panel, &api_key, signal).await?;
+        if let Some(server) = self.servers.lock().await.get_mut(&server.id) {
+            if signal == "start" {
+                set_status(server, ServerStatus::Starting);
+                server.last_start_time = Some(chrono::Utc::now().timestamp() as u64);
+            } else {
+                set_status(server, ServerStatus::Stopping);
+            }
+        }
+        Ok(())
+    }
+
+    /// Take over the state the host's panel reports for remote servers
+    pub async fn refresh_remote_statuses(&self) {
+        let remotes: Vec<ServerInfo> = self
+            .servers
+            .lock()
+            .await
+            .values()
+            .filter(|s| s.remote.as_ref().is_some_and(|r| r.panel.is_some()))
+            .cloned()
+            .collect();
+        for server in remotes {
+            let state = match self.remote_panel(&server).await {
+                Ok((panel, api_key)) => crate::remote::state(&panel, &api_key).await,
+                Err(e) => Err(e),
+            };
+            let status = match state.as_deref() {
+                Ok("running") => ServerStatus::Running,
+                Ok("starting") => ServerStatus::Starting,
+                Ok("stopping") => ServerStatus::Stopping,
+                Ok(_) => ServerStatus::Stopped,
+                Err(e) => {
+                    println!("[Remote] {}: {}", server.name, e);
+                    continue;
+                }
+            };
+            if let Some(server) = self.servers.lock().await.get_mut(&server.id) {
+                if status == ServerStatus::Stopped {
+                    server.last_start_time = None;
+                }
+                set_status(server, status);
+            }
+        }
+    }
+
+    pub async fn check_and_restart_servers(&self) {
+        // The clock jumped over the sleep, every interval would look due
+        if self.suspended.load(std::sync::atomic::Ordering::SeqCst) {
+            return;
+        }
+        let upcoming_restarts = {
+            let servers = self.servers.lock().await;
+            let mut upcoming = Vec::new();
+            let now_params = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+
+            for (id, server) in servers.iter() {
+                if !server.auto_restart || server.status != ServerStatus::Running {
+                    continue;
+                }
+                if self.pending_restarts.lock().unwrap().contains(id) {
+                    continue;
+                }
+
+                let remaining = match server.restart_type {
+                    RestartType::Interval => server.last_start_time.map(|last_start| {
+                        // Restart interval must be at least 60 seconds to prevent loops
+                        let interval = std::cmp::max(server.restart_interval, 60);
+                        (last_start + interval).saturating_sub(now_params)
+                    }),
+                    RestartType::Schedule => {
+                        // Prevent double restart: skip if the server was started recently (e.g. < 5 mins ago)
+                        if let Some(last_start) = server.last_start_time {
+                            if now_params < last_start + 300 {
+                                continue;
+                            }
+                        }
+                        match (&server.restart_schedule, &server.time_zone) {
+                            (Some(schedule), Some(tz_str)) => {
+                                seconds_until_schedule(schedule, tz_str)
+                            }
+                            _ => None,
+                        }
+                    }
+                };
+
+                // Start the countdown early enough that the first warning is sent on time
+                let lead_time = server.restart_warnings.iter().copied().max().unwrap_or(0)
+                    + RESTART_CHECK_INTERVAL;
+                if let Some(remaining) = remaining.filter(|r| *r <= lead_time) {
+                    upcoming.push((id.clone(), remaining, server.restart_warnings.clone()));
+                }
+            }
+            upcoming
+        };
+
+        for (id, remaining, warnings) in upcoming_restarts {
+            self.pending_restarts.lock().unwrap().insert(id.clone());
+            let manager = self.clone();
+            tokio::spawn(async move {
+                manager
+                    .run_restart_countdown(&id, remaining, warnings)
+                    .await;
+                manager.pending_restarts.lock().unwrap().remove(&id);
+            });
+        }
+    }
+
+    /// Warn players at each offset before the restart, then restart the server
+    async fn run_restart_countdown(&self, server_id: &str, remaining: u64, mut warnings: Vec<u64>) {
+        let restart_at = tokio::time::Instant::now() + std::time::Duration::from_secs(remaining);
+        warnings.sort_unstable_by(|a, b| b.cmp(a));
+
+        // Offsets that have already passed are skipped
+        for offset in warnings.into_iter().filter(|w| *w <= remaining) {
+            tokio::time::sleep_until(restart_at - std::time::Duration::from_secs(offset)).await;
+            let message = format!("say Server restarting in {}...", format_countdown(offset));
+            if let Err(e) = self.send_command(server_id, &message).await {
+                println!("[AutoRestart] Failed to warn players: {}", e);
+            }
+        }
+        tokio::time::sleep_until(restart_at).await;
+
+        // Settings may have changed or the server may have stopped during the countdown
+        let still_due = self
+            .get_server(server_id)
+            .await
+            .map(|s| s.auto_restart && s.status == ServerStatus::Running)
+            .unwrap_or(false);
+        if !still_due {
+            return;
+        }
+
+        println!("[AutoRestart] Restarting server {}", server_id);
+        if let Err(e) = self.restart_server(server_id).await {
+            println!("[AutoRestart] Restart failed: {}", e);
+        }
+    }
+
+    pub async fn get_scheduled_tasks(&self, server_id: &str) -> Result<Vec<ScheduledTask>> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        Ok(server.scheduled_tasks)
+    }
+
+    /// Add a scheduled task, or replace the task with the same id
+    pub async fn save_scheduled_task(
+        &self,
+        server_id: &str,
+        mut task: ScheduledTask,
+    ) -> Result<ScheduledTask> {
+        if task.command.trim().is_empty() {
+            anyhow::bail!("Command must not be empty");
+        }
+        match task.schedule_type {
+            RestartType::Interval if task.interval < 60 => {
+                anyhow::bail!("Interval must be at least 60 seconds")
+            }
+            RestartType::Schedule => {
+                let schedule = task.schedule.as_deref().unwrap_or("");
+                chrono::NaiveTime::parse_from_str(schedule, "%H:%M")
+                    .context("Schedule must be in HH:MM format")?;
+            }
+            _ => {}
+        }
+
+        if task.id.is_empty() {
+            task.id = uuid::Uuid::new_v4().to_string();
+        }
+
+        let mut servers = self.servers.lock().await;
+        let server = servers
+            .get_mut(server_id)
+            .or_not_found("Server not found")?;
+        if let Some(existing) = server.scheduled_tasks.iter_mut().find(|t| t.id == task.id) {
+            task.last_run = existing.last_run;
+            *existing = task.clone();
+        } else {
+            server.scheduled_tasks.push(task.clone());
+        }
+        Ok(task)
+    }
+
+    pub async fn remove_scheduled_task(&self, server_id: &str, task_id: &str) -> Result<()> {
+        let mut servers = self.servers.lock().await;
+        let server = servers
+            .get_mut(server_id)
+            .or_not_found("Server not found")?;
+        server.scheduled_tasks.retain(|t| t.id != task_id);
+        Ok(())
+    }
+
+    /// Run scheduled tasks that are due on running servers
+    pub async fn check_scheduled_tasks(&self) {
+        let now_secs = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let due_tasks: Vec<(String, ScheduledTask)> = {
+            let servers = self.servers.lock().await;
+            let mut due = Vec::new();
+
+            for server in servers.values() {
+                if server.status != ServerStatus::Running {
+                    continue;
+                }
+
+                for task in server.scheduled_tasks.iter().filter(|t| t.enabled) {
+                    let is_due = match task.schedule_type {
+                        RestartType::Interval => {
+                            // Count the first interval from the server start
+                            let baseline = task
+                                .last_run
+                                .max(server.last_start_time)
+                                .unwrap_or(now_secs);
+                            now_secs >= baseline + std::cmp::max(task.interval, 60)
+                        }
+                        RestartType::Schedule => {
+                            let recently_run = task
+                                .last_run
+                                .map(|last| now_secs < last + 120)
+                                .unwrap_or(false);
+                            !recently_run && schedule_matches_now(&task.schedule, &task.time_zone)
+                        }
+                    };
+
+                    if is_due {
+                        due.push((server.id.clone(), task.clone()));
+                    }
+                }
+            }
+            due
+        };
+
+        for (server_id, task) in due_tasks {
+            println!("[Scheduler] Running task '{}' on {}", task.name, server_id);
+            for line in task.command.lines().filter(|l| !l.trim().is_empty()) {
+                if let Err(e) = self.send_command(&server_id, line).await {
+                    println!("[Scheduler] Task '{}' failed: {}", task.name, e);
+                }
+            }
+
+            let mut servers = self.servers.lock().await;
+            if let Some(t) = servers
+                .get_mut(&server_id)
+                .and_then(|s| s.scheduled_tasks.iter_mut().find(|t| t.id == task.id))
+            {
+                t.last_run = Some(now_secs);
+            }
+        }
+    }
+
+    pub async fn get_announcements(&self, server_id: &str) -> Result<Option<AnnouncementConfig>> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        Ok(server.announcements)
+    }
+
+    /// Replace the announcement rotation; the rotation restarts at the first message
+    pub async fn set_announcements(
+        &self,
+        server_id: &str,
+        config: AnnouncementConfig,
+    ) -> Result<()> {
+        if config.interval < 60 {
+            anyhow::bail!("Interval must be at least 60 seconds");
+        }
+        let messages: Vec<String> = config
+            .messages
+            .iter()
+            .map(|m| m.trim().to_string())
+            .filter(|m| !m.is_empty())
+            .collect();
+        if config.enabled && messages.is_empty() {
+            anyhow::bail!("At least one announcement message is required");
+        }
+
+        let mut servers = self.servers.lock().await;
+        let server = servers
+            .get_mut(server_id)
+            .or_not_found("Server not found")?;
+        server.announcements = Some(AnnouncementConfig {
+            messages,
+            next_index: 0,
+            last_sent: None,
+            ..config
+        });
+        Ok(())
+    }
+
+    /// Pin a server to a Java executable (None returns to automatic selection).
+    /// Returns the major version of the given Java.
+    pub async fn set_server_java(
+        &self,
+        server_id: &str,
+        path: Option<String>,
+    ) -> Result<Option<u8>> {
+        let path = path.map(|p| p.trim().to_string()).filter(|p| !p.is_empty());
+        let version = match &path {
+            Some(path) => {
+                let java = path.clone();
+                let version = tokio::task::spawn_blocking(move || {
+                    crate::java_detector::get_java_version(&java)
+                })
+                .await?
+                .with_context(|| format!("{} is not a working Java executable", path))?;
+                Some(version)
+            }
+            None => None,
+        };
+
+        let mut servers = self.servers.lock().await;
+        let server = servers
+            .get_mut(server_id)
+            .or_not_found("Server not found")?;
+        if let Some(version) = version {
+            let required = crate::java_detector::get_required_java_version(&server.version);
+            if version < required {
+                println!(
+                    "[ServerManager] {} is pinned to Java {}, Minecraft {} normally needs Java {}",
+                    server.name, version, server.version, required
+                );
+            }
+        }
+        server.java_path_override = path;
+        Ok(version)
+    }
+
+    /// Replace the default G1 flags with `args` (whitespace separated, None or
+    /// empty restores the defaults). Launch arguments stay under our control.
+    pub async fn set_jvm_args(&self, server_id: &str, args: Option<String>) -> Result<()> {
+        let args: Vec<String> = args
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(str::to_string)
+            .collect();
+        for arg in &args {
+            if !arg.starts_with('-') {
+                anyhow::bail!("JVM arguments must start with '-': {}", arg);
+            }
+            if matches!(arg.as_str(), "-jar" | "-cp" | "-classpath" | "--class-path") {
+                anyhow::bail!("{} is set by Prismarine and cannot be overridden", arg);
+            }
+        }
 
-        self.install_plugin_by_url(server_id, &download_url, Some(filename))
-            .await?;
+        let mut servers = self.servers.lock().await;
+        let server = servers
+            .get_mut(server_id)
+            .or_not_found("Server not found")?;
+        server.custom_jvm_args = (!args.is_empty()).then_some(args);
         Ok(())
     }
 
-    async fn search_modrinth(
-        &self,
-        query: &str,
-        version: &str,
-        server_type: &ServerType,
-    ) -> Result<Vec<PluginSearchResult>> {
-        let client = reqwest::Client::builder()
-            .user_agent("MinecraftServerManager/0.1.0 (antigravity)")
-            .build()?;
+    /// Major version of the Java a server starts with; the version that would
+    /// be installed when none is found yet
+    async fn server_java_major(&self, server: &ServerInfo) -> Option<u8> {
+        let version = server.version.clone();
+        let modded = is_forge_based(&server.server_type);
+        let java_override = server.java_path_override.clone();
+        tokio::task::spawn_blocking(move || {
+            let path = java_override
+                .or_else(|| crate::java_detector::select_java_for_server(&version, modded));
+            match path {
+                Some(path) => crate::java_detector::get_java_version(&path),
+                None => Some(
+                    crate::java_manager::JavaManager::new()
+                        .recommended(&version, modded)
+                        .recommended,
+                ),
+            }
+        })
+        .await
+        .ok()
+        .flatten()
+    }
 
-        // Map ServerType to Modrinth categories (loaders)
-        let loaders_facet = match server_type {
-            ServerType::Paper | ServerType::Purpur => {
-                "[\"categories:paper\",\"categories:spigot\",\"categories:bukkit\"]"
+    pub async fn set_jvm_profile(&self, server_id: &str, profile: JvmProfile) -> Result<()> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        if let Some(java) = self.server_java_major(&server).await {
+            if java < profile.min_java() {
+                anyhow::bail!(
+                    "{} needs Java {} or newer, this server runs on Java {}",
+                    profile.label(),
+                    profile.min_java(),
+                    java
+                );
             }
-            ServerType::Spigot => "[\"categories:spigot\",\"categories:bukkit\"]",
-            ServerType::Forge => "[\"categories:forge\"]",
-            ServerType::Vanilla => "[\"categories:bukkit\"]", // Weak fallback
-            ServerType::Fabric | ServerType::Banner => "[\"categories:fabric\"]",
-            ServerType::Mohist => "[\"categories:forge\"]", // Mohist runs Forge mods
-            ServerType::Taiyitist => "[\"categories:forge\"]", // Taiyitist runs Forge mods
-            ServerType::Velocity => "[\"categories:velocity\"]",
-            ServerType::BungeeCord => "[\"categories:bungeecord\"]",
-            ServerType::Waterfall => "[\"categories:bungeecord\",\"categories:waterfall\"]",
-        };
+        }
 
-        let version_facet = format!("[\"versions:{}\"]", version);
+        let mut servers = self.servers.lock().await;
+        let server = servers
+            .get_mut(server_id)
+            .or_not_found("Server not found")?;
+        server.jvm_profile = profile;
+        Ok(())
+    }
 
-        let sort_param = if query.is_empty() {
-            "&sort=follows" // Better "Trending/Popular" indicator than total downloads
-        } else {
-            ""
-        };
+    /// Broadcast the next announcement on running servers whose interval has passed
+    pub async fn check_announcements(&self) {
+        let now_secs = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
 
-        let project_type_facet = match server_type {
-            ServerType::Fabric | ServerType::Forge | ServerType::Mohist | ServerType::Taiyitist => {
-                "[\"project_type:mod\"]"
+        let due: Vec<(String, String)> = {
+            let mut servers = self.servers.lock().await;
+            let mut due = Vec::new();
+
+            for server in servers.values_mut() {
+                if server.status != ServerStatus::Running {
+                    continue;
+                }
+                let last_start_time = server.last_start_time;
+                let Some(config) = server.announcements.as_mut() else {
+                    continue;
+                };
+                if !config.enabled || config.messages.is_empty() {
+                    continue;
+                }
+
+                // Count the first interval from the server start
+                let baseline = config.last_sent.max(last_start_time).unwrap_or(now_secs);
+                if now_secs < baseline + std::cmp::max(config.interval, 60) {
+                    continue;
+                }
+
+                let index = config.next_index % config.messages.len();
+                let text = format!("{}{}", config.prefix, config.messages[index]);
+                config.next_index = (index + 1) % config.messages.len();
+                config.last_sent = Some(now_secs);
+                due.push((server.id.clone(), text));
             }
-            _ => "[\"project_type:plugin\"]",
+            due
         };
 
-        // Facets: ProjectType AND Version AND Loaders
-        let facets = format!(
-            "[{},{},{}]",
-            project_type_facet, version_facet, loaders_facet
-        );
+        for (server_id, text) in due {
+            // tellraw instead of say: no "[Server]" tag in front of the prefix
+            let command = format!("tellraw @a {}", serde_json::json!({ "text": text }));
+            if let Err(e) = self.send_command(&server_id, &command).await {
+                println!("[Announce] Failed on {}: {}", server_id, e);
+            }
+        }
+    }
 
-        let url = format!(
-            "https://api.modrinth.com/v2/search?query={}&facets={}&limit=20{}",
-            query, facets, sort_param
-        );
+    /// Port and password of the server's RCON when it is enabled and the password is stored
+    async fn rcon_credentials(&self, server: &ServerInfo) -> Option<(u16, String)> {
+        let rcon = server.rcon.as_ref().filter(|r| r.enabled)?;
+        let key = crate::secrets::rcon_key(&server.id);
+        let password = tokio::task::spawn_blocking(move || crate::secrets::get(&key))
+            .await
+            .ok()?
+            .ok()??;
+        Some((rcon.port, password))
+    }
 
-        let resp: serde_json::Value = client.get(&url).send().await?.json().await?;
-        let hits = resp["hits"]
-            .as_array()
-            .context("Invalid Modrinth response")?;
+    async fn world_dir(&self, server: &ServerInfo) -> PathBuf {
+        let level_name = self
+            .read_server_property(&server.path, "level-name")
+            .await
+            .unwrap_or_else(|| "world".to_string());
+        server.path.join(level_name)
+    }
 
-        let mut results = Vec::new();
-        for hit in hits {
-            let id = hit["project_id"].as_str().unwrap_or("").to_string();
-            let name = hit["title"].as_str().unwrap_or("").to_string();
-            let description = hit["description"].as_str().unwrap_or("").to_string();
-            let author = hit["author"].as_str().unwrap_or("").to_string();
-            let icon_url = hit["icon_url"].as_str().map(|s| s.to_string());
-            let slug = hit["slug"].as_str().unwrap_or("");
-            let external_url = format!("https://modrinth.com/plugin/{}", slug);
+    /// Ranked list of likely lag sources from the saved world, with live TPS
+    /// from spark (or Paper's /tps) when the server runs with RCON
+    pub async fn analyze_lag(&self, server_id: &str) -> Result<crate::lag::LagReport> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        let world_dir = self.world_dir(&server).await;
+        let mut report =
+            tokio::task::spawn_blocking(move || crate::lag::scan_world(&world_dir)).await??;
+
+        if server.status != ServerStatus::Running {
+            report
+                .notes
+                .push("The server is stopped, so no TPS is available".to_string());
+            return Ok(report);
+        }
+        report
+            .notes
+            .push("Entity counts are from the last world save".to_string());
+        let Some((port, password)) = self.rcon_credentials(&server).await else {
+            report
+                .notes
+                .push("Enable RCON to include TPS and tick durations".to_string());
+            return Ok(report);
+        };
 
-            results.push(PluginSearchResult {
-                id,
-                name,
-                description,
-                author,
-                icon_url,
-                source: "Modrinth".to_string(),
-                external_url,
-                download_url: None, // Modrinth needs version fetch
-            });
+        if crate::lag::has_spark(&server.path) {
+            match crate::console_bridge::rcon_command(port, &password, "spark tps").await {
+                Ok(reply) => {
+                    report.tps = crate::lag::parse_tps(&reply);
+                    report.mspt = crate::lag::parse_mspt(&reply);
+                    report.tick_source = report.tps.map(|_| "spark".to_string());
+                }
+                Err(e) => println!("[Lag] spark tps failed on {}: {}", server.name, e),
+            }
+        } else {
+            report
+                .notes
+                .push("Install spark for tick durations".to_string());
         }
-        Ok(results)
+        if report.tps.is_none() {
+            if let Ok(reply) = crate::console_bridge::rcon_command(port, &password, "tps").await {
+                report.tps = crate::lag::parse_tps(&reply);
+                report.tick_source = report.tps.map(|_| "paper".to_string());
+            }
+        }
+        Ok(report)
     }
 
-    async fn search_spigot(&self, query: &str) -> Result<Vec<PluginSearchResult>> {
-        let client = reqwest::Client::builder()
-            .user_agent("MinecraftServerManager/0.1.0")
-            .build()?;
+    /// Sample players and TPS of running servers for the statistics reports
+    pub async fn record_stats_samples(&self) {
+        let running: Vec<ServerInfo> = self
+            .get_servers()
+            .await
+            .into_iter()
+            .filter(|s| s.status == ServerStatus::Running && s.remote.is_none())
+            .collect();
 
-        let url = if query.is_empty() {
-            "https://api.spiget.org/v2/resources?limit=20&sort=-downloads".to_string()
-        } else {
-            format!(
-                "https://api.spiget.org/v2/search/resources/{}?limit=20&sort=-downloads",
-                query
+        for server in running {
+            if !crate::reports::sample_due(&server.id) {
+                continue;
+            }
+            let players = self.online_players(&server.id).await.unwrap_or_default();
+            let tps = self.query_tps(&server).await;
+            if let Err(e) = crate::reports::record_sample(&server.id, players, tps) {
+                println!("[Reports] Failed to sample {}: {}", server.name, e);
+            }
+        }
+    }
+
+    /// Server list ping of a running server on this machine
+    pub async fn query_status(&self, server_id: &str) -> Result<crate::query::StatusResponse> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        if server.status != ServerStatus::Running {
+            return Err(PrismarineError::invalid_state("The server is not running").into());
+        }
+        let host = server
+            .remote
+            .as_ref()
+            .map_or("127.0.0.1", |r| r.host.as_str());
+        crate::query::status(host, server.port, std::time::Duration::from_secs(3)).await
+    }
+
+    /// UDP query full stat of a running server on this machine
+    pub async fn query_full_stat(&self, server_id: &str) -> Result<crate::query::QueryResponse> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        if server.status != ServerStatus::Running || server.remote.is_some() {
+            return Err(PrismarineError::invalid_state(
+                "The server is not running on this machine",
             )
-        };
+            .into());
+        }
+        let enabled = self
+            .read_server_property(&server.path, "enable-query")
+            .await
+            .is_some_and(|v| v == "true");
+        let port = self
+            .read_server_property(&server.path, "query.port")
+            .await
+            .and_then(|port| port.parse().ok())
+            .filter(|_| enabled)
+            .context("Query is not enabled, restart the server to turn it on")?;
+        crate::query::full_stat("127.0.0.1", port, std::time::Duration::from_secs(3)).await
+    }
 
-        // Spiget returns array directly or inside content? Usually array.
-        let resp: serde_json::Value = client.get(&url).send().await?.json().await?;
+    /// Online player names: RCON `list` when enabled, the ping's sample when it
+    /// lists everyone, then the query, then the log
+    pub async fn online_players(&self, server_id: &str) -> Result<Vec<String>> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        // RCON sees vanished players and is never stale, the log can be both
+        if let Some((port, password)) = self.rcon_credentials(&server).await {
+            if let Ok(reply) = crate::console_bridge::rcon_command(port, &password, "list").await {
+                return Ok(crate::monitor::parse_player_list(&reply));
+            }
+        }
+        if let Some(players) = self
+            .query_status(server_id)
+            .await
+            .ok()
+            .and_then(|status| status.complete_sample())
+        {
+            return Ok(players);
+        }
+        if let Ok(stat) = self.query_full_stat(server_id).await {
+            return Ok(stat.players);
+        }
+        crate::monitor::Monitor::get_online_players(&server.path).await
+    }
 
-        let mut results = Vec::new();
-        // Spiget behavior: if no results, might return empty array.
-        if let Some(items) = resp.as_array() {
-            for item in items {
-                let id = item["id"]
-                    .as_i64()
-                    .map(|i| i.to_string())
-                    .unwrap_or_default();
-                let name = item["name"].as_str().unwrap_or("").to_string();
-                let tag = item["tag"].as_str().unwrap_or("").to_string(); // Short desc
-                let author_id = item["author"]["id"].as_i64().unwrap_or(0);
+    /// Keep `players` of running servers in line with their server list ping
+    pub async fn refresh_player_counts(&self) {
+        let running: Vec<String> = self
+            .get_servers()
+            .await
+            .into_iter()
+            .filter(|s| s.status == ServerStatus::Running)
+            .map(|s| s.id)
+            .collect();
 
-                // Icon handling in Spiget is weird, usually https://www.spigotmc.org/data/resource_icons/<id_prefix>/<id>.jpg
-                // But we can skip or try to construct.
-                let icon_url = if !item["icon"]["data"].as_str().unwrap_or("").is_empty() {
-                    Some(format!(
-                        "https://www.spigotmc.org/data/resource_icons/{}/{}.jpg",
-                        id.parse::<i64>().unwrap_or(0) / 1000,
-                        id
-                    ))
-                } else {
-                    None
-                };
+        for id in running {
+            let Ok(status) = self.query_status(&id).await else {
+                continue;
+            };
+            let mut servers = self.servers.lock().await;
+            if let Some(server) = servers.get_mut(&id) {
+                server.players = format!("{}/{}", status.online, status.max);
+            }
+        }
+    }
 
-                let external_url = format!("https://www.spigotmc.org/resources/{}", id);
+    /// TPS from Paper's (or spark's) /tps over RCON, None without RCON
+    pub async fn query_tps(&self, server: &ServerInfo) -> Option<f64> {
+        let (port, password) = self.rcon_credentials(server).await?;
+        let reply = crate::console_bridge::rcon_command(port, &password, "tps")
+            .await
+            .ok()?;
+        crate::lag::parse_tps(&reply)
+    }
 
-                results.push(PluginSearchResult {
-                    id: id.clone(),
-                    name,
-                    description: tag,
-                    author: format!("User {}", author_id), // Fetching author name requires extra call, skip for now
-                    icon_url,
-                    source: "Spigot".to_string(),
-                    external_url,
-                    download_url: Some(format!(
-                        "https://api.spiget.org/v2/resources/{}/download",
-                        id
-                    )),
+    pub async fn set_stats_reports(&self, server_id: &str, schedule: ReportSchedule) -> Result<()> {
+        let mut servers = self.servers.lock().await;
+        let server = servers
+            .get_mut(server_id)
+            .or_not_found("Server not found")?;
+        server.stats_reports = schedule;
+        Ok(())
+    }
+
+    pub async fn get_report(&self, server_id: &str, period: ReportPeriod) -> Result<StatsReport> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        Ok(crate::reports::build(&server.id, period))
+    }
+
+    /// Send the scheduled reports that are due. Nothing is sent (or marked as
+    /// sent) while reports have no notification channel.
+    pub async fn send_due_reports(&self) {
+        if !crate::notifications::has_route(crate::notifications::NotifyEvent::Report).await {
+            return;
+        }
+        for server in self.get_servers().await {
+            for period in crate::reports::due(&server.id, &server.stats_reports) {
+                let report = crate::reports::build(&server.id, period);
+                // Not marked as sent when delivery failed, the next check tries again
+                if crate::notifications::dispatch(
+                    crate::notifications::NotifyEvent::Report,
+                    &format!("Statistics report: {}", server.name),
+                    &report.to_message(&server.name),
+                )
+                .await
+                {
+                    crate::reports::mark_sent(&server.id, period);
+                }
+            }
+        }
+    }
+
+    /// Game rules live over RCON while running, otherwise from level.dat
+    pub async fn get_game_rules(&self, server_id: &str) -> Result<GameRulesStatus> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+
+        if server.status == ServerStatus::Running {
+            if let Some((port, password)) = self.rcon_credentials(&server).await {
+                let mut values = HashMap::new();
+                for rule in crate::gamerules::RULES {
+                    let reply = crate::console_bridge::rcon_command(
+                        port,
+                        &password,
+                        &format!("gamerule {}", rule),
+                    )
+                    .await?;
+                    if let Some(value) = crate::gamerules::parse_query_reply(&reply) {
+                        values.insert(rule.to_string(), value);
+                    }
+                }
+                return Ok(GameRulesStatus {
+                    rules: GameRules::from_values(&values),
+                    source: "rcon".to_string(),
                 });
             }
         }
-        Ok(results)
-    }
-
-    pub async fn install_plugin_by_url(
-        &self,
-        server_id: &str,
-        download_url: &str,
-        filename: Option<String>,
-    ) -> Result<()> {
-        let plugins_path = self.get_plugins_path(server_id).await?;
 
-        let fname = if let Some(n) = filename {
-            n
+        let world_dir = self.world_dir(&server).await;
+        let values =
+            tokio::task::spawn_blocking(move || crate::gamerules::read_level_dat(&world_dir))
+                .await??;
+        Ok(GameRulesStatus {
+            rules: GameRules::from_values(&values),
+            source: "level.dat".to_string(),
+        })
+    }
+
+    /// Apply the given rules: `/gamerule` on a running server, level.dat edit when stopped
+    pub async fn set_game_rules(&self, server_id: &str, rules: &GameRules) -> Result<()> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        let updates = rules.to_pairs();
+        if updates.is_empty() {
+            return Ok(());
+        }
+        let count = updates.len();
+
+        match server.status {
+            ServerStatus::Running => {
+                let rcon = self.rcon_credentials(&server).await;
+                for (rule, value) in &updates {
+                    let command = format!("gamerule {} {}", rule, value);
+                    match &rcon {
+                        Some((port, password)) => {
+                            crate::console_bridge::rcon_command(*port, password, &command).await?;
+                        }
+                        None => self.send_command(server_id, &command).await?,
+                    }
+                }
+            }
+            ServerStatus::Stopped => {
+                let world_dir = self.world_dir(&server).await;
+                tokio::task::spawn_blocking(move || {
+                    crate::gamerules::write_level_dat(&world_dir, &updates)
+                })
+                .await??;
+            }
+            // level.dat would be overwritten by the server, commands not accepted yet
+            _ => anyhow::bail!("Wait until the server has started or stopped"),
+        }
+        println!("[GameRules] Updated {} rule(s) on {}", count, server_id);
+        Ok(())
+    }
+
+    /// Gameplay properties from server.properties with the current game rules
+    pub async fn get_world_settings(&self, server_id: &str) -> Result<WorldSettingsStatus> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        if !requires_eula(&server.server_type) {
+            anyhow::bail!("Proxies have no worlds, edit the settings of the backends");
+        }
+
+        let properties: HashMap<String, String> =
+            fs::read_to_string(server.path.join("server.properties"))
+                .await
+                .unwrap_or_default()
+                .lines()
+                .filter(|line| !line.trim().starts_with('#'))
+                .filter_map(|line| line.split_once('='))
+                .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+                .collect();
+
+        // A world that has never been generated has no level.dat yet
+        let (game_rules, source) = match self.get_game_rules(server_id).await {
+            Ok(status) => (status.rules, status.source),
+            Err(e) => {
+                println!("[WorldSettings] No game rules for {}: {}", server.name, e);
+                (GameRules::default(), "none".to_string())
+            }
+        };
+        Ok(WorldSettingsStatus {
+            settings: WorldSettings::from_properties(&properties, game_rules),
+            game_rules_source: source,
+        })
+    }
+
+    /// Write the properties and apply what can be applied live: difficulty and
+    /// default game mode through commands, game rules through `set_game_rules`
+    pub async fn set_world_settings(
+        &self,
+        server_id: &str,
+        settings: &WorldSettings,
+    ) -> Result<WorldSettingsResult> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        if !requires_eula(&server.server_type) {
+            anyhow::bail!("Proxies have no worlds, edit the settings of the backends");
+        }
+        if matches!(
+            server.status,
+            ServerStatus::Starting | ServerStatus::Stopping
+        ) {
+            anyhow::bail!("Wait until the server has started or stopped");
+        }
+
+        let pairs = settings.property_pairs()?;
+        for (key, value) in &pairs {
+            self.update_server_property(&server.path, key, value)
+                .await?;
+        }
+
+        let running = server.status == ServerStatus::Running;
+        if running {
+            let rcon = self.rcon_credentials(&server).await;
+            for command in settings.live_commands() {
+                match &rcon {
+                    Some((port, password)) => {
+                        crate::console_bridge::rcon_command(*port, password, &command).await?;
+                    }
+                    None => self.send_command(server_id, &command).await?,
+                }
+            }
+        }
+        if !settings.game_rules.to_pairs().is_empty() {
+            self.set_game_rules(server_id, &settings.game_rules).await?;
+        }
+
+        let restart_required = if running {
+            pairs
+                .iter()
+                .map(|(key, _)| *key)
+                .filter(|key| !matches!(*key, "difficulty" | "gamemode"))
+                .map(String::from)
+                .collect()
         } else {
-            // Try to guess from URL or Content-Disposition?
-            // Simple fallback: "plugin.jar" or derive from end of URL.
-            // Spiget download urls don't have filename.
-            // Modrinth version urls might.
-            "unknown_plugin.jar".to_string()
+            Vec::new()
         };
+        println!(
+            "[WorldSettings] Updated {} propert(ies) on {}",
+            pairs.len(),
+            server.name
+        );
+        Ok(WorldSettingsResult {
+            applied_live: running,
+            restart_required,
+        })
+    }
 
-        self.install_plugin(&plugins_path, download_url, &fname)
-            .await?;
-        Ok(())
+    /// Current dynamic view distance settings (defaults if never configured)
+    pub async fn get_dynamic_view_distance(&self, server_id: &str) -> Result<DynamicViewDistance> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        Ok(server.dynamic_view_distance.unwrap_or_default())
     }
 
-    pub async fn install_spigot_plugin(
+    pub async fn set_dynamic_view_distance(
         &self,
         server_id: &str,
-        resource_id: &str,
-        plugin_name: &str,
+        config: DynamicViewDistance,
     ) -> Result<()> {
-        let download_url = format!(
-            "https://api.spiget.org/v2/resources/{}/download",
-            resource_id
-        );
-        // Sanitize plugin name for filename (remove invalid characters)
-        let safe_name: String = plugin_name
-            .chars()
-            .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_' || *c == ' ')
-            .collect();
-        let filename = format!("{}.jar", safe_name.trim());
-        self.install_plugin_by_url(server_id, &download_url, Some(filename))
+        if config.tiers.is_empty() {
+            anyhow::bail!("At least one view distance tier is required");
+        }
+
+        let mut servers = self.servers.lock().await;
+        let server = servers
+            .get_mut(server_id)
+            .or_not_found("Server not found")?;
+        server.dynamic_view_distance = Some(DynamicViewDistance {
+            command: config
+                .command
+                .map(|c| c.trim().to_string())
+                .filter(|c| !c.is_empty()),
+            applied_tier: None,
+            pending_tier: None,
+            ..config
+        });
+        Ok(())
+    }
+
+    pub async fn get_console_bridge(&self, server_id: &str) -> Result<ConsoleBridgeConfig> {
+        let server = self
+            .get_server(server_id)
             .await
+            .or_not_found("Server not found")?;
+        Ok(server.console_bridge.unwrap_or_default())
     }
 
-    pub async fn set_server_memory(
+    pub async fn set_console_bridge(
         &self,
         server_id: &str,
-        max_memory: &str,
-        min_memory: &str,
-    ) -> Result<()> {
+        mut config: ConsoleBridgeConfig,
+    ) -> Result<ConsoleBridgeConfig> {
+        if config.password.trim().is_empty() {
+            config.password = format!("{:x}", rand::random::<u64>());
+        }
+
         let mut servers = self.servers.lock().await;
-        if let Some(server) = servers.get_mut(server_id) {
-            server.max_memory = max_memory.to_string();
-            server.min_memory = min_memory.to_string();
-            Ok(())
-        } else {
-            anyhow::bail!("Server not found")
+        if config.port == 0
+            || servers.values().any(|s| {
+                s.port == config.port
+                    || (s.id != server_id
+                        && s.console_bridge
+                            .as_ref()
+                            .is_some_and(|b| b.enabled && b.port == config.port))
+            })
+        {
+            return Err(PrismarineError::port_in_use(
+                config.port,
+                None,
+                format!("Port {} is already used by a server", config.port),
+            )
+            .into());
         }
-    }
 
-    pub async fn is_plugin_installed(&self, server_id: &str, plugin_name: &str) -> Result<bool> {
-        let plugins_path = self.get_plugins_path(server_id).await?;
+        let server = servers
+            .get_mut(server_id)
+            .or_not_found("Server not found")?;
+        crate::secrets::set(
+            &crate::secrets::console_bridge_key(server_id),
+            &config.password,
+        )
+        .context("Failed to store the console bridge password")?;
+        server.console_bridge = Some(config.clone());
+        Ok(config)
+    }
 
-        // Sanitize plugin name for filename (same logic as install)
-        let safe_name: String = plugin_name
-            .chars()
-            .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_' || *c == ' ')
+    /// Move forwarding.secret files of Velocity proxies into the secret store
+    pub async fn migrate_secrets(&self) {
+        let proxies: Vec<ServerInfo> = self
+            .servers
+            .lock()
+            .await
+            .values()
+            .filter(|s| s.server_type == ServerType::Velocity)
+            .filter(|s| s.path.join("forwarding.secret").exists())
+            .cloned()
             .collect();
-        let filename = format!("{}.jar", safe_name.trim());
+        for proxy in proxies {
+            match forwarding_secret(&proxy).await {
+                Ok(_) => println!("[Secrets] Moved the forwarding secret of {}", proxy.name),
+                Err(e) => println!("[Secrets] Failed to migrate {}: {}", proxy.name, e),
+            }
+        }
+    }
 
-        Ok(plugins_path.join(filename).exists())
+    pub async fn get_rcon_status(&self, server_id: &str) -> Result<RconStatus> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        self.rcon_status(&server, false).await
     }
 
-    pub async fn uninstall_plugin(&self, server_id: &str, plugin_name: &str) -> Result<()> {
-        let plugins_path = self.get_plugins_path(server_id).await?;
+    async fn rcon_status(&self, server: &ServerInfo, changed: bool) -> Result<RconStatus> {
+        let settings = server.rcon.clone().unwrap_or(RconSettings {
+            enabled: false,
+            port: DEFAULT_RCON_PORT,
+            localhost_only: false,
+        });
+        let server_ip = self
+            .read_server_property(&server.path, "server-ip")
+            .await
+            .unwrap_or_default();
+        let id = server.id.clone();
+        let password_stored =
+            tokio::task::spawn_blocking(move || crate::secrets::has_rcon_password(&id)).await?;
+
+        Ok(RconStatus {
+            enabled: settings.enabled,
+            port: settings.port,
+            password_stored,
+            localhost_only: is_loopback_ip(&server_ip) || settings.localhost_only,
+            restart_required: changed && server.status != ServerStatus::Stopped,
+        })
+    }
 
-        // Sanitize plugin name for filename (same logic as install)
-        let safe_name: String = plugin_name
-            .chars()
-            .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_' || *c == ' ')
-            .collect();
-        let filename = format!("{}.jar", safe_name.trim());
+    /// Enable RCON with a freshly generated password stored in the OS keyring.
+    /// With `localhost_only`, other machines are kept out by a firewall rule
+    /// unless server-ip already makes the server listen on loopback only.
+    pub async fn enable_rcon(
+        &self,
+        server_id: &str,
+        port: Option<u16>,
+        localhost_only: bool,
+    ) -> Result<RconStatus> {
+        let port = port.unwrap_or(DEFAULT_RCON_PORT);
+        {
+            let servers = self.servers.lock().await;
+            let taken = servers.values().any(|s| {
+                s.port == port
+                    || (s.id != server_id
+                        && s.rcon.as_ref().is_some_and(|r| r.enabled && r.port == port))
+            });
+            if port == 0 || taken {
+                return Err(PrismarineError::port_in_use(
+                    port,
+                    None,
+                    format!("Port {} is already used by a server", port),
+                )
+                .into());
+            }
+        }
 
-        let file_path = plugins_path.join(filename);
-        if file_path.exists() {
-            fs::remove_file(file_path).await?;
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        let server_ip = self
+            .read_server_property(&server.path, "server-ip")
+            .await
+            .unwrap_or_default();
+        let block = localhost_only && !is_loopback_ip(&server_ip);
+        if let Some(previous) = server.rcon.as_ref().filter(|r| r.localhost_only) {
+            if !block || previous.port != port {
+                let old_port = previous.port;
+                tokio::task::spawn_blocking(move || {
+                    crate::port_manager::unblock_remote_tcp(old_port)
+                })
+                .await??;
+            }
+        }
+        if block {
+            tokio::task::spawn_blocking(move || crate::port_manager::block_remote_tcp(port))
+                .await?
+                .map_err(|e| {
+                    PrismarineError::invalid_input(format!(
+                        "RCON could not be limited to this computer: {}",
+                        e
+                    ))
+                })?;
         }
 
-        Ok(())
+        self.write_rcon_password(&server).await?;
+        self.update_server_property(&server.path, "enable-rcon", "true")
+            .await?;
+        self.update_server_property(&server.path, "rcon.port", &port.to_string())
+            .await?;
+        // Do not echo RCON commands (and their output) to every operator
+        self.update_server_property(&server.path, "broadcast-rcon-to-ops", "false")
+            .await?;
+
+        let server = {
+            let mut servers = self.servers.lock().await;
+            let entry = servers
+                .get_mut(server_id)
+                .or_not_found("Server not found")?;
+            entry.rcon = Some(RconSettings {
+                enabled: true,
+                port,
+                localhost_only: block,
+            });
+            entry.clone()
+        };
+        println!("[RCON] Enabled for {} on port {}", server.name, port);
+        self.rcon_status(&server, true).await
     }
 
-    pub async fn check_and_restart_servers(&self) {
-        let servers_to_restart = {
+    pub async fn disable_rcon(&self, server_id: &str) -> Result<RconStatus> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        if let Some(rcon) = server.rcon.as_ref().filter(|r| r.localhost_only) {
+            let port = rcon.port;
+            tokio::task::spawn_blocking(move || crate::port_manager::unblock_remote_tcp(port))
+                .await??;
+        }
+        self.update_server_property(&server.path, "enable-rcon", "false")
+            .await?;
+        self.update_server_property(&server.path, "rcon.password", "")
+            .await?;
+        let id = server_id.to_string();
+        tokio::task::spawn_blocking(move || crate::secrets::delete_rcon_password(&id)).await??;
+
+        let server = {
             let mut servers = self.servers.lock().await;
-            let mut restart_ids = Vec::new();
-            let now_params = std::time::SystemTime::now()
+            let entry = servers
+                .get_mut(server_id)
+                .or_not_found("Server not found")?;
+            if let Some(rcon) = entry.rcon.as_mut() {
+                rcon.enabled = false;
+                rcon.localhost_only = false;
+            }
+            entry.clone()
+        };
+        println!("[RCON] Disabled for {}", server.name);
+        self.rcon_status(&server, true).await
+    }
+
+    /// Replace the RCON password (takes effect on the next server start)
+    pub async fn rotate_rcon_password(&self, server_id: &str) -> Result<RconStatus> {
+        let server = self
+            .get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+        if !server.rcon.as_ref().is_some_and(|r| r.enabled) {
+            anyhow::bail!("RCON is not enabled for this server");
+        }
+
+        self.write_rcon_password(&server).await?;
+        println!("[RCON] Rotated password for {}", server.name);
+        self.rcon_status(&server, true).await
+    }
+
+    /// Generate a password, keep it in the keyring and hand it to the server
+    async fn write_rcon_password(&self, server: &ServerInfo) -> Result<()> {
+        let password = crate::secrets::generate_password();
+        let id = server.id.clone();
+        let stored = password.clone();
+        tokio::task::spawn_blocking(move || crate::secrets::store_rcon_password(&id, &stored))
+            .await??;
+        self.update_server_property(&server.path, "rcon.password", &password)
+            .await
+    }
+
+    /// Issue a new API token for the server, replacing the previous one
+    pub async fn issue_api_token(
+        &self,
+        server_id: &str,
+        scopes: Vec<ApiScope>,
+    ) -> Result<IssuedApiToken> {
+        if scopes.is_empty() {
+            anyhow::bail!("Select at least one scope for the token");
+        }
+        self.get_server(server_id)
+            .await
+            .or_not_found("Server not found")?;
+
+        let token = format!("pmt_{}", crate::secrets::generate_password());
+        let id = server_id.to_string();
+        let stored = token.clone();
+        tokio::task::spawn_blocking(move || {
+            crate::secrets::set(&crate::secrets::api_token_key(&id), &stored)
+        })
+        .await?
+        .context("Failed to store the API token")?;
+
+        let info = ApiTokenInfo {
+            scopes,
+            issued_at: std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
-                .as_secs();
+                .as_secs(),
+            hint: token[token.len() - 4..].to_string(),
+        };
+        let mut servers = self.servers.lock().await;
+        let server = servers
+            .get_mut(server_id)
+            .or_not_found("Server not found")?;
+        server.api_token = Some(info.clone());
+        println!("[ApiToken] Issued a token for {}", server.name);
+        Ok(IssuedApiToken { token, info })
+    }
 
-            for (id, server) in servers.iter_mut() {
-                if !server.auto_restart || server.status != ServerStatus::Running {
-                    continue;
-                }
+    pub async fn revoke_api_token(&self, server_id: &str) -> Result<()> {
+        let id = server_id.to_string();
+        tokio::task::spawn_blocking(move || {
+            crate::secrets::delete(&crate::secrets::api_token_key(&id))
+        })
+        .await?
+        .context("Failed to remove the API token")?;
 
-                match server.restart_type {
-                    RestartType::Interval => {
-                        if let Some(last_start) = server.last_start_time {
-                            // Restart interval must be at least 60 seconds to prevent loops
-                            let interval = std::cmp::max(server.restart_interval, 60);
-                            if now_params >= last_start + interval {
-                                println!("Interval Trigger: Restarting server {}", server.name);
-                                restart_ids.push(id.clone());
-                            }
-                        }
-                    }
-                    RestartType::Schedule => {
-                        if let (Some(schedule), Some(tz_str)) =
-                            (&server.restart_schedule, &server.time_zone)
-                        {
-                            if let Ok(tz) = tz_str.parse::<chrono_tz::Tz>() {
-                                use chrono::Timelike;
-                                let now = chrono::Utc::now().with_timezone(&tz);
+        let mut servers = self.servers.lock().await;
+        let server = servers
+            .get_mut(server_id)
+            .or_not_found("Server not found")?;
+        server.api_token = None;
+        println!("[ApiToken] Revoked the token of {}", server.name);
+        Ok(())
+    }
 
-                                if let Ok(target_time) =
-                                    chrono::NaiveTime::parse_from_str(schedule, "%H:%M")
-                                {
-                                    // Check if current time matches target time (minute precision)
-                                    if now.hour() == target_time.hour()
-                                        && now.minute() == target_time.minute()
-                                    {
-                                        // Prevent double restart: check if last_start_time was recently (e.g. < 5 mins ago)
-                                        if let Some(last_start) = server.last_start_time {
-                                            if now_params < last_start + 300 {
-                                                continue;
-                                            }
-                                        }
-
-                                        println!(
-                                            "Schedule Trigger: Restarting server {}",
-                                            server.name
-                                        );
-                                        restart_ids.push(id.clone());
-                                    }
-                                }
-                            }
-                        }
+    /// The server a token belongs to, when the token grants `scope`
+    pub async fn server_for_api_token(&self, token: &str, scope: ApiScope) -> Option<String> {
+        let candidates: Vec<String> = self
+            .servers
+            .lock()
+            .await
+            .values()
+            .filter(|s| {
+                s.api_token
+                    .as_ref()
+                    .is_some_and(|t| t.scopes.contains(&scope))
+            })
+            .map(|s| s.id.clone())
+            .collect();
+
+        let token = token.to_string();
+        tokio::task::spawn_blocking(move || {
+            candidates.into_iter().find(|id| {
+                crate::secrets::get(&crate::secrets::api_token_key(id))
+                    .ok()
+                    .flatten()
+                    .is_some_and(|stored| {
+                        crate::secrets::constant_time_eq(stored.as_bytes(), token.as_bytes())
+                    })
+            })
+        })
+        .await
+        .ok()
+        .flatten()
+    }
+
+    /// Apply the view distance tier matching the current player count of each running server
+    pub async fn check_dynamic_view_distance(&self) {
+        let candidates: Vec<(String, PathBuf, DynamicViewDistance)> = {
+            let servers = self.servers.lock().await;
+            servers
+                .values()
+                .filter(|s| s.status == ServerStatus::Running)
+                .filter_map(|s| {
+                    s.dynamic_view_distance
+                        .clone()
+                        .filter(|c| c.enabled)
+                        .map(|c| (s.id.clone(), s.path.clone(), c))
+                })
+                .collect()
+        };
+
+        for (id, path, config) in candidates {
+            let online = crate::monitor::Monitor::get_online_players(&path)
+                .await
+                .map(|p| p.len() as u32)
+                .unwrap_or(0);
+
+            let Some(tier) = config
+                .tiers
+                .iter()
+                .filter(|t| t.min_players <= online)
+                .max_by_key(|t| t.min_players)
+            else {
+                continue;
+            };
+
+            let command = config
+                .command
+                .as_deref()
+                .filter(|c| *c != LEGACY_VIEW_DISTANCE_COMMAND);
+            let target = if command.is_some() {
+                config.applied_tier
+            } else {
+                config.pending_tier.or(config.applied_tier)
+            };
+            if target == Some(tier.min_players) {
+                continue;
+            }
+
+            println!(
+                "[ServerManager] {} players online on {}: {} view distance {} / simulation {}",
+                online,
+                id,
+                if command.is_some() {
+                    "applying"
+                } else {
+                    "writing"
+                },
+                tier.view_distance,
+                tier.simulation_distance
+            );
+
+            let live = match command {
+                Some(command) => {
+                    let command = command
+                        .replace("{view}", &tier.view_distance.to_string())
+                        .replace("{simulation}", &tier.simulation_distance.to_string());
+                    if self.send_command(&id, &command).await.is_err() {
+                        continue;
                     }
+                    true
                 }
-            }
-            restart_ids
-        };
+                None => false,
+            };
+
+            // Keep server.properties in sync so restarts use the same values
+            let _ = self
+                .update_server_property(&path, "view-distance", &tier.view_distance.to_string())
+                .await;
+            let _ = self
+                .update_server_property(
+                    &path,
+                    "simulation-distance",
+                    &tier.simulation_distance.to_string(),
+                )
+                .await;
 
-        for id in servers_to_restart {
-            let _ = self.restart_server(&id).await;
+            let mut servers = self.servers.lock().await;
+            if let Some(cfg) = servers
+                .get_mut(&id)
+                .and_then(|s| s.dynamic_view_distance.as_mut())
+            {
+                if live {
+                    cfg.applied_tier = Some(tier.min_players);
+                    cfg.pending_tier = None;
+                } else {
+                    cfg.pending_tier = Some(tier.min_players);
+                }
+            }
         }
     }
 
@@ -2011,7 +9711,7 @@ impl ServerManager {
             if let Some(server) = servers.get(server_id) {
                 server.status.clone()
             } else {
-                anyhow::bail!("Server not found");
+                return Err(PrismarineError::not_found("Server not found").into());
             }
         };
 
@@ -2032,7 +9732,7 @@ impl ServerManager {
         let server = self
             .get_server(proxy_id)
             .await
-            .context("Server not found")?;
+            .or_not_found("Server not found")?;
 
         match server.server_type {
             ServerType::Velocity => {
@@ -2099,7 +9799,7 @@ impl ServerManager {
         let server = self
             .get_server(proxy_id)
             .await
-            .context("Server not found")?;
+            .or_not_found("Server not found")?;
         match server.server_type {
             ServerType::Velocity => {
                 let config_path = server.path.join("velocity.toml");
@@ -2126,13 +9826,8 @@ try = ["{}"]
                         name, address, name
                     );
 
-                    // Also create forwarding.secret if it doesn't exist
-                    let secret_path = server.path.join("forwarding.secret");
-                    if !secret_path.exists() {
-                        let secret =
-                            format!("{:x}{:x}", rand::random::<u64>(), rand::random::<u64>());
-                        fs::write(&secret_path, &secret).await?;
-                    }
+                    // Also create the forwarding secret if it doesn't exist
+                    forwarding_secret(&server).await?;
 
                     fs::write(&config_path, &default_config).await?;
                     return Ok(());
@@ -2172,13 +9867,8 @@ try = ["{}"]
                         .entry("online-mode".to_string())
                         .or_insert(toml::Value::Boolean(true));
 
-                    // Also ensure forwarding.secret exists
-                    let secret_path = server.path.join("forwarding.secret");
-                    if !secret_path.exists() {
-                        let secret =
-                            format!("{:x}{:x}", rand::random::<u64>(), rand::random::<u64>());
-                        fs::write(&secret_path, &secret).await?;
-                    }
+                    // Also ensure the forwarding secret exists
+                    forwarding_secret(&server).await?;
                 }
 
                 if let Some(servers) = config.get_mut("servers").and_then(|v| v.as_table_mut()) {
@@ -2336,7 +10026,7 @@ listeners:
         let server = self
             .get_server(proxy_id)
             .await
-            .context("Server not found")?;
+            .or_not_found("Server not found")?;
         match server.server_type {
             ServerType::Velocity => {
                 let config_path = server.path.join("velocity.toml");
@@ -2383,11 +10073,11 @@ listeners:
         let backend = self
             .get_server(backend_id)
             .await
-            .context("Backend server not found")?;
+            .or_not_found("Backend server not found")?;
         let proxy = self
             .get_server(proxy_id)
             .await
-            .context("Proxy server not found")?;
+            .or_not_found("Proxy server not found")?;
 
         // Update server.properties
         let props_path = backend.path.join("server.properties");
@@ -2421,20 +10111,8 @@ listeners:
 
         // For Paper servers, configure velocity forwarding
         if matches!(backend.server_type, ServerType::Paper) {
-            // Read the forwarding secret from proxy
-            let secret_path = proxy.path.join("forwarding.secret");
-            let secret = if secret_path.exists() {
-                fs::read_to_string(&secret_path)
-                    .await
-                    .unwrap_or_default()
-                    .trim()
-                    .to_string()
-            } else {
-                // Generate a new secret if it doesn't exist
-                let new_secret = format!("{:x}", rand::random::<u64>());
-                fs::write(&secret_path, &new_secret).await?;
-                new_secret
-            };
+            // Read the forwarding secret of the proxy (generated if it doesn't exist)
+            let secret = forwarding_secret(&proxy).await?;
 
             // Ensure config directory exists
             let config_dir = backend.path.join("config");
@@ -2515,10 +10193,168 @@ listeners:
             }
         }
 
+        if let Err(e) = self.link_floodgate_backend(&backend, &proxy).await {
+            println!(
+                "[Geyser] Failed to share Floodgate with {}: {}",
+                backend.name, e
+            );
+        }
+
         println!(
             "Configured backend {} for proxy {}",
             backend.name, proxy.name
         );
         Ok(())
     }
+
+    /// With Floodgate on the proxy, backends need Floodgate too and the proxy's
+    /// key.pem, or Bedrock players are kicked with "invalid floodgate data".
+    /// Does nothing while the proxy has no Floodgate.
+    async fn link_floodgate_backend(&self, backend: &ServerInfo, proxy: &ServerInfo) -> Result<()> {
+        let Some(proxy_variant) = geyser_variant(&proxy.server_type) else {
+            return Ok(());
+        };
+        if !proxy_variant.floodgate_jar_path(&proxy.path).exists() {
+            return Ok(());
+        }
+        let Some(variant) = geyser_variant(&backend.server_type).filter(|v| !v.proxy) else {
+            println!(
+                "[Geyser] {} has no Floodgate build, Bedrock players can't join it",
+                backend.name
+            );
+            return Ok(());
+        };
+
+        // Floodgate keeps an existing key, so one written before the proxy's
+        // first start is the key it will use
+        let proxy_floodgate = proxy.path.join(proxy_variant.floodgate_dir);
+        let key_path = proxy_floodgate.join("key.pem");
+        let key = match fs::read(&key_path).await {
+            Ok(key) => key,
+            Err(_) => {
+                let key = rand::random::<[u8; 16]>().to_vec();
+                fs::create_dir_all(&proxy_floodgate).await?;
+                fs::write(&key_path, &key).await?;
+                key
+            }
+        };
+        let proxy_config = proxy_floodgate.join("config.yml");
+        let content = fs::read_to_string(&proxy_config).await.unwrap_or_default();
+        fs::write(
+            &proxy_config,
+            set_yaml_top_value(&content, "send-floodgate-data", "true"),
+        )
+        .await?;
+
+        if !variant.floodgate_jar_path(&backend.path).exists() {
+            self.install_plugin(
+                &backend.path.join(variant.jar_dir),
+                &format!(
+                    "https://download.geysermc.org/v2/projects/floodgate/versions/latest/builds/latest/downloads/{}",
+                    variant.floodgate_platform
+                ),
+                variant.floodgate_jar,
+                false,
+            )
+            .await
+            .context("Failed to install Floodgate")?;
+        }
+        let backend_floodgate = backend.path.join(variant.floodgate_dir);
+        fs::create_dir_all(&backend_floodgate).await?;
+        fs::write(backend_floodgate.join("key.pem"), &key).await?;
+
+        if variant.geyser_jar_path(&backend.path).exists() {
+            println!(
+                "[Geyser] {} runs its own Geyser as well, the proxy's Geyser is enough",
+                backend.name
+            );
+        }
+        Ok(())
+    }
+
+    /// Share Floodgate with every managed server registered in the proxy
+    async fn link_floodgate_backends(&self, proxy: &ServerInfo) -> Result<()> {
+        let entries = self.get_proxy_registered_servers(&proxy.id).await?;
+        let backends: Vec<ServerInfo> = self
+            .get_servers()
+            .await
+            .into_iter()
+            .filter(|s| {
+                s.remote.is_none()
+                    && entries
+                        .iter()
+                        .any(|e| e.address.rsplit(':').next() == Some(&s.port.to_string()))
+            })
+            .collect();
+        for backend in &backends {
+            if let Err(e) = self.link_floodgate_backend(backend, proxy).await {
+                println!(
+                    "[Geyser] Failed to share Floodgate with {}: {}",
+                    backend.name, e
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::normalize_properties;
+
+    #[test]
+    fn normalize_properties_keeps_clean_files() {
+        let content = "#Minecraft server properties\nmotd=A Minecraft Server\nserver-port=25565\n";
+        let (lines, fixes) = normalize_properties(content);
+        assert_eq!(lines, content.lines().collect::<Vec<_>>());
+        assert!(fixes.is_empty());
+    }
+
+    #[test]
+    fn normalize_properties_rewrites_separators_and_bom() {
+        let (lines, fixes) =
+            normalize_properties("\u{feff}motd: Hello\nserver-port 25566\n  pvp=true");
+        assert_eq!(lines, ["motd=Hello", "server-port=25566", "pvp=true"]);
+        assert_eq!(
+            fixes,
+            [
+                "removed the byte order mark",
+                "rewrote 3 lines as key=value"
+            ]
+        );
+    }
+
+    #[test]
+    fn normalize_properties_keeps_trailing_whitespace() {
+        let content = "motd=Hello   \nlevel-name=world ";
+        let (lines, fixes) = normalize_properties(content);
+        assert_eq!(lines, ["motd=Hello   ", "level-name=world "]);
+        assert!(fixes.is_empty());
+    }
+
+    #[test]
+    fn normalize_properties_keeps_the_last_duplicate() {
+        let (lines, fixes) = normalize_properties("pvp=true\nmotd=Hi\npvp=false");
+        assert_eq!(lines, ["motd=Hi", "pvp=false"]);
+        assert_eq!(fixes, ["removed a duplicate pvp"]);
+    }
+
+    #[test]
+    fn normalize_properties_keeps_continued_values() {
+        let content = "motd=first \\\n    second: part\nlevel-name=world";
+        let (lines, fixes) = normalize_properties(content);
+        assert_eq!(
+            lines,
+            ["motd=first \\", "    second: part", "level-name=world"]
+        );
+        assert!(fixes.is_empty());
+    }
+
+    #[test]
+    fn normalize_properties_drops_continued_duplicates_whole() {
+        let content = "motd=old \\\n  value\nmotd=new\nescaped=a\\\\";
+        let (lines, fixes) = normalize_properties(content);
+        assert_eq!(lines, ["motd=new", "escaped=a\\\\"]);
+        assert_eq!(fixes, ["removed a duplicate motd"]);
+    }
 }
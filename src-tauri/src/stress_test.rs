@@ -0,0 +1,491 @@
+// Stress test
+// Connects simulated players to a local offline-mode server, lets them walk
+// around and chat, and compares TPS/CPU before and during the load so owners
+// can check whether their hardware handles the planned player count.
+// The bots speak the Minecraft 1.21 / 1.21.1 protocol (767).
+
+use crate::error::{OrNotFound, PrismarineError};
+use crate::monitor::Monitor;
+use crate::protocol_codec::{
+    read_string, read_varint, read_varint_async, write_string, write_varint,
+};
+use crate::protocol_versions;
+use crate::server_manager::{ServerManager, ServerStatus};
+use anyhow::Result;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::TcpStream;
+use tokio::sync::Mutex as TokioMutex;
+
+const PROTOCOL_VERSION: i32 = 767;
+pub const MAX_BOTS: u32 = 100;
+pub const MAX_DURATION_SECS: u64 = 600;
+/// Seconds between TPS/CPU samples while the bots are connected
+const SAMPLE_INTERVAL_SECS: u64 = 5;
+/// Delay between bot joins, so the test does not look like a connection flood
+const JOIN_STAGGER_MS: u64 = 250;
+
+// Login state
+const LOGIN_DISCONNECT: i32 = 0x00;
+const LOGIN_ENCRYPTION_REQUEST: i32 = 0x01;
+const LOGIN_SUCCESS: i32 = 0x02;
+const LOGIN_SET_COMPRESSION: i32 = 0x03;
+const LOGIN_PLUGIN_REQUEST: i32 = 0x04;
+const LOGIN_PLUGIN_RESPONSE: i32 = 0x02;
+const LOGIN_ACKNOWLEDGED: i32 = 0x03;
+
+// Configuration state
+const CONFIG_CLIENT_INFORMATION: i32 = 0x00;
+const CONFIG_DISCONNECT: i32 = 0x02;
+const CONFIG_FINISH: i32 = 0x03;
+const CONFIG_ACK_FINISH: i32 = 0x03;
+const CONFIG_KEEP_ALIVE: i32 = 0x04;
+const CONFIG_PING: i32 = 0x05;
+const CONFIG_PONG: i32 = 0x05;
+const CONFIG_KNOWN_PACKS: i32 = 0x0E;
+const CONFIG_KNOWN_PACKS_RESPONSE: i32 = 0x07;
+
+// Play state
+const PLAY_CHUNK_BATCH_FINISHED: i32 = 0x0C;
+const PLAY_DISCONNECT: i32 = 0x1D;
+const PLAY_KEEP_ALIVE: i32 = 0x26;
+const PLAY_PING: i32 = 0x35;
+const PLAY_SYNC_POSITION: i32 = 0x40;
+const PLAY_CONFIRM_TELEPORT: i32 = 0x00;
+const PLAY_CHAT_MESSAGE: i32 = 0x06;
+const PLAY_CHUNK_BATCH_RECEIVED: i32 = 0x08;
+const PLAY_KEEP_ALIVE_RESPONSE: i32 = 0x18;
+const PLAY_SET_POSITION: i32 = 0x1A;
+const PLAY_PONG: i32 = 0x27;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StressTestReport {
+    pub bots_requested: u32,
+    pub bots_connected: u32,
+    pub duration_secs: u64,
+    pub baseline_tps: Option<f32>,
+    pub average_tps: Option<f32>,
+    pub min_tps: Option<f32>,
+    pub baseline_cpu: Option<f32>, // Percent of one core
+    pub average_cpu: Option<f32>,
+    pub peak_cpu: Option<f32>,
+    pub peak_memory: Option<u64>, // Bytes
+    pub errors: Vec<String>,      // Distinct reasons bots failed or were kicked
+}
+
+/// Connect `bots` simulated players to a running server for `duration_secs`
+pub async fn run(
+    manager: &ServerManager,
+    monitor: &Mutex<Monitor>,
+    server_id: &str,
+    bots: u32,
+    duration_secs: u64,
+) -> Result<StressTestReport> {
+    if bots == 0 || bots > MAX_BOTS {
+        anyhow::bail!("Bot count must be between 1 and {}", MAX_BOTS);
+    }
+    let duration_secs = duration_secs.clamp(10, MAX_DURATION_SECS);
+
+    let server = manager
+        .get_server(server_id)
+        .await
+        .or_not_found("Server not found")?;
+    if server.status != ServerStatus::Running {
+        return Err(PrismarineError::invalid_state("Server must be running").into());
+    }
+    if manager
+        .read_server_property(&server.path, "online-mode")
+        .await
+        .as_deref()
+        != Some("false")
+    {
+        anyhow::bail!("Bots cannot authenticate: set online-mode=false for the test");
+    }
+
+    let protocol = crate::query::status("127.0.0.1", server.port, Duration::from_secs(5))
+        .await?
+        .protocol;
+    if protocol != PROTOCOL_VERSION {
+        anyhow::bail!(
+            "Stress test bots support Minecraft {}, the server speaks {}",
+            protocol_versions::describe(PROTOCOL_VERSION),
+            protocol_versions::describe(protocol)
+        );
+    }
+
+    let pid = manager.get_process_id(server_id);
+    let sample_cpu = || {
+        pid.and_then(|pid| monitor.lock().unwrap().get_server_stats(pid))
+            .map(|s| (s.cpu_usage, s.memory_used))
+    };
+
+    println!(
+        "[StressTest] {} bots for {}s against {}",
+        bots, duration_secs, server.name
+    );
+    let baseline_tps = query_tps(manager, server_id).await;
+    let baseline_cpu = sample_cpu().map(|(cpu, _)| cpu);
+
+    let connected = Arc::new(AtomicU32::new(0));
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(duration_secs);
+    let mut handles = Vec::new();
+    for i in 0..bots {
+        let connected = Arc::clone(&connected);
+        let port = server.port;
+        handles.push(tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(JOIN_STAGGER_MS * i as u64)).await;
+            let name = format!("StressBot{:03}", i + 1);
+            match tokio::time::timeout_at(deadline, run_bot(port, &name, &connected)).await {
+                Ok(result) => result,
+                Err(_) => Ok(()), // Test finished while connected
+            }
+        }));
+    }
+
+    let mut tps_samples = Vec::new();
+    let mut cpu_samples = Vec::new();
+    let mut peak_memory = None;
+    while tokio::time::Instant::now() + Duration::from_secs(SAMPLE_INTERVAL_SECS) < deadline {
+        tokio::time::sleep(Duration::from_secs(SAMPLE_INTERVAL_SECS)).await;
+        if let Some(tps) = query_tps(manager, server_id).await {
+            tps_samples.push(tps);
+        }
+        if let Some((cpu, memory)) = sample_cpu() {
+            cpu_samples.push(cpu);
+            peak_memory = peak_memory.max(Some(memory));
+        }
+    }
+
+    let mut errors = Vec::new();
+    for handle in handles {
+        if let Ok(Err(e)) = handle.await {
+            let message = e.to_string();
+            if !errors.contains(&message) {
+                errors.push(message);
+            }
+        }
+    }
+
+    let average = |samples: &[f32]| {
+        (!samples.is_empty()).then(|| samples.iter().sum::<f32>() / samples.len() as f32)
+    };
+
+    Ok(StressTestReport {
+        bots_requested: bots,
+        bots_connected: connected.load(Ordering::SeqCst),
+        duration_secs,
+        baseline_tps,
+        average_tps: average(&tps_samples),
+        min_tps: tps_samples.iter().copied().reduce(f32::min),
+        baseline_cpu,
+        average_cpu: average(&cpu_samples),
+        peak_cpu: cpu_samples.iter().copied().reduce(f32::max),
+        peak_memory,
+        errors,
+    })
+}
+
+/// TPS derived from the vanilla `tick query` average tick time
+async fn query_tps(manager: &ServerManager, server_id: &str) -> Option<f32> {
+    let server = manager.get_server(server_id).await?;
+    let log_start = Monitor::log_position(&server.path).await;
+    manager.send_command(server_id, "tick query").await.ok()?;
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    // "Average time per tick: 3.2ms (Target: 50.0ms)"
+    Monitor::read_log_since(&server.path, log_start)
+        .await
+        .iter()
+        .find_map(|line| {
+            let rest = line.split("Average time per tick: ").nth(1)?;
+            let mspt: f32 = rest.split("ms").next()?.trim().parse().ok()?;
+            Some((1000.0 / mspt.max(0.001)).min(20.0))
+        })
+}
+
+/// Join, then walk around and chat until the task is cancelled
+async fn run_bot(port: u16, name: &str, connected: &AtomicU32) -> Result<()> {
+    let stream = TcpStream::connect(("127.0.0.1", port)).await?;
+    stream.set_nodelay(true)?;
+    let mut connection = Connection::new(stream);
+
+    // Handshake + login
+    connection.send(0x00, &handshake(port, 2)).await?;
+    let mut login_start = Vec::new();
+    write_string(&mut login_start, name);
+    login_start.extend_from_slice(uuid::Uuid::new_v4().as_bytes());
+    connection.send(0x00, &login_start).await?;
+
+    loop {
+        let Some((id, data)) = connection.receive().await? else {
+            continue;
+        };
+        let mut cursor = data.as_slice();
+        match id {
+            LOGIN_SET_COMPRESSION => connection.threshold = Some(read_varint(&mut cursor)?),
+            LOGIN_SUCCESS => {
+                connection.send(LOGIN_ACKNOWLEDGED, &[]).await?;
+                break;
+            }
+            LOGIN_PLUGIN_REQUEST => {
+                let mut response = Vec::new();
+                write_varint(&mut response, read_varint(&mut cursor)?);
+                response.push(0); // Not understood
+                connection.send(LOGIN_PLUGIN_RESPONSE, &response).await?;
+            }
+            LOGIN_ENCRYPTION_REQUEST => anyhow::bail!("Server requires authentication"),
+            LOGIN_DISCONNECT => anyhow::bail!("Login rejected: {}", read_string(&mut cursor)?),
+            _ => {}
+        }
+    }
+
+    // Configuration
+    let mut client_information = Vec::new();
+    write_string(&mut client_information, "en_us");
+    client_information.push(8); // View distance
+    write_varint(&mut client_information, 0); // Chat enabled
+    client_information.extend_from_slice(&[1, 0x7f]); // Chat colors, skin parts
+    write_varint(&mut client_information, 1); // Right hand
+    client_information.extend_from_slice(&[0, 1]); // No text filtering, listed
+    connection
+        .send(CONFIG_CLIENT_INFORMATION, &client_information)
+        .await?;
+
+    loop {
+        let Some((id, data)) = connection.receive().await? else {
+            continue;
+        };
+        match id {
+            CONFIG_FINISH => {
+                connection.send(CONFIG_ACK_FINISH, &[]).await?;
+                break;
+            }
+            CONFIG_KEEP_ALIVE => connection.send(CONFIG_KEEP_ALIVE, &data).await?,
+            CONFIG_PING => connection.send(CONFIG_PONG, &data).await?,
+            // No packs known locally, the server sends full registries instead
+            CONFIG_KNOWN_PACKS => connection.send(CONFIG_KNOWN_PACKS_RESPONSE, &[0]).await?,
+            CONFIG_DISCONNECT => anyhow::bail!("Disconnected during configuration"),
+            _ => {}
+        }
+    }
+
+    connected.fetch_add(1, Ordering::SeqCst);
+
+    // Play: one task walks and chats, this one answers the server
+    let Connection { stream, threshold } = connection;
+    let (mut reader, writer) = stream.into_split();
+    let writer = Arc::new(TokioMutex::new(writer));
+    let position = Arc::new(Mutex::new(None::<(f64, f64, f64)>));
+
+    let walker = tokio::spawn(walk_and_chat(
+        Arc::clone(&writer),
+        threshold,
+        Arc::clone(&position),
+    ));
+    let result = async {
+        loop {
+            let Some((id, data)) = receive_packet(&mut reader, threshold).await? else {
+                continue;
+            };
+            let mut cursor = data.as_slice();
+            match id {
+                PLAY_KEEP_ALIVE => {
+                    send_packet(
+                        &mut *writer.lock().await,
+                        threshold,
+                        PLAY_KEEP_ALIVE_RESPONSE,
+                        &data,
+                    )
+                    .await?
+                }
+                PLAY_PING => {
+                    send_packet(&mut *writer.lock().await, threshold, PLAY_PONG, &data).await?
+                }
+                PLAY_CHUNK_BATCH_FINISHED => {
+                    let chunks_per_tick = 20.0f32.to_be_bytes();
+                    send_packet(
+                        &mut *writer.lock().await,
+                        threshold,
+                        PLAY_CHUNK_BATCH_RECEIVED,
+                        &chunks_per_tick,
+                    )
+                    .await?
+                }
+                PLAY_SYNC_POSITION => {
+                    let x = cursor.read_f64().await?;
+                    let y = cursor.read_f64().await?;
+                    let z = cursor.read_f64().await?;
+                    let mut rotation = [0u8; 8];
+                    cursor.read_exact(&mut rotation).await?;
+                    let flags = cursor.read_u8().await?;
+                    let teleport_id = read_varint(&mut cursor)?;
+
+                    // Bits 0x01/0x02/0x04 mark x/y/z as relative
+                    {
+                        let mut pos = position.lock().unwrap();
+                        let (old_x, old_y, old_z) = pos.unwrap_or_default();
+                        *pos = Some((
+                            if flags & 0x01 != 0 { old_x + x } else { x },
+                            if flags & 0x02 != 0 { old_y + y } else { y },
+                            if flags & 0x04 != 0 { old_z + z } else { z },
+                        ));
+                    }
+
+                    let mut confirm = Vec::new();
+                    write_varint(&mut confirm, teleport_id);
+                    send_packet(
+                        &mut *writer.lock().await,
+                        threshold,
+                        PLAY_CONFIRM_TELEPORT,
+                        &confirm,
+                    )
+                    .await?;
+                }
+                PLAY_DISCONNECT => anyhow::bail!("Kicked from the server"),
+                _ => {}
+            }
+        }
+    }
+    .await;
+
+    walker.abort();
+    result
+}
+
+/// Random walk around the spawn point with an occasional chat message
+async fn walk_and_chat(
+    writer: Arc<TokioMutex<OwnedWriteHalf>>,
+    threshold: Option<i32>,
+    position: Arc<Mutex<Option<(f64, f64, f64)>>>,
+) -> Result<()> {
+    let mut tick = 0u64;
+    loop {
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        tick += 1;
+
+        let step = {
+            let mut pos = position.lock().unwrap();
+            pos.as_mut().map(|(x, y, z)| {
+                *x += rand::random::<f64>() * 0.4 - 0.2;
+                *z += rand::random::<f64>() * 0.4 - 0.2;
+                (*x, *y, *z)
+            })
+        };
+        let Some((x, y, z)) = step else {
+            continue; // Not spawned yet
+        };
+
+        let mut movement = Vec::new();
+        movement.extend_from_slice(&x.to_be_bytes());
+        movement.extend_from_slice(&y.to_be_bytes());
+        movement.extend_from_slice(&z.to_be_bytes());
+        movement.push(1); // On ground
+        send_packet(
+            &mut *writer.lock().await,
+            threshold,
+            PLAY_SET_POSITION,
+            &movement,
+        )
+        .await?;
+
+        // Chat roughly every 15 seconds
+        if tick.is_multiple_of(60) {
+            let mut chat = Vec::new();
+            write_string(&mut chat, &format!("Stress test message {}", tick / 60));
+            let timestamp = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as i64;
+            chat.extend_from_slice(&timestamp.to_be_bytes());
+            chat.extend_from_slice(&rand::random::<i64>().to_be_bytes()); // Salt
+            chat.push(0); // Unsigned
+            write_varint(&mut chat, 0); // Acknowledged message count
+            chat.extend_from_slice(&[0, 0, 0]); // Acknowledged bitset (20 bits)
+            send_packet(
+                &mut *writer.lock().await,
+                threshold,
+                PLAY_CHAT_MESSAGE,
+                &chat,
+            )
+            .await?;
+        }
+    }
+}
+
+fn handshake(port: u16, next_state: i32) -> Vec<u8> {
+    let mut data = Vec::new();
+    write_varint(&mut data, PROTOCOL_VERSION);
+    write_string(&mut data, "127.0.0.1");
+    data.extend_from_slice(&port.to_be_bytes());
+    write_varint(&mut data, next_state);
+    data
+}
+
+/// Connection used during login and configuration
+struct Connection {
+    stream: TcpStream,
+    threshold: Option<i32>, // Compression threshold once enabled
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> Self {
+        Self {
+            stream,
+            threshold: None,
+        }
+    }
+
+    async fn send(&mut self, id: i32, data: &[u8]) -> Result<()> {
+        send_packet(&mut self.stream, self.threshold, id, data).await
+    }
+
+    async fn receive(&mut self) -> Result<Option<(i32, Vec<u8>)>> {
+        receive_packet(&mut self.stream, self.threshold).await
+    }
+}
+
+/// Write a packet. Bots only send small packets, so they always go uncompressed.
+async fn send_packet<W: AsyncWriteExt + Unpin>(
+    writer: &mut W,
+    threshold: Option<i32>,
+    id: i32,
+    data: &[u8],
+) -> Result<()> {
+    let mut body = Vec::with_capacity(data.len() + 6);
+    if threshold.is_some() {
+        write_varint(&mut body, 0); // Uncompressed
+    }
+    write_varint(&mut body, id);
+    body.extend_from_slice(data);
+
+    let mut packet = Vec::with_capacity(body.len() + 5);
+    write_varint(&mut packet, body.len() as i32);
+    packet.extend_from_slice(&body);
+    writer.write_all(&packet).await?;
+    Ok(())
+}
+
+/// Read a packet. Compressed packets (large ones like chunks) are skipped and
+/// returned as None; everything the bots react to is below the threshold.
+async fn receive_packet<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    threshold: Option<i32>,
+) -> Result<Option<(i32, Vec<u8>)>> {
+    let length = read_varint_async(reader).await?;
+    if !(1..=2 * 1024 * 1024).contains(&length) {
+        anyhow::bail!("Invalid packet length {}", length);
+    }
+    let mut packet = vec![0u8; length as usize];
+    reader.read_exact(&mut packet).await?;
+
+    let mut cursor = packet.as_slice();
+    if threshold.is_some() && read_varint(&mut cursor)? != 0 {
+        return Ok(None);
+    }
+    let id = read_varint(&mut cursor)?;
+    Ok(Some((id, cursor.to_vec())))
+}
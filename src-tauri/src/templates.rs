@@ -0,0 +1,69 @@
+// Server templates
+// Named snapshots of a server's configuration (type, version, memory, JVM
+// flags, server.properties, plugin list) used to provision matching servers.
+
+use crate::error::OrNotFound;
+use crate::paths::AppPaths;
+use crate::server_manager::ServerTemplate;
+use anyhow::Result;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+pub struct TemplateStore {
+    config_path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl TemplateStore {
+    pub fn new(paths: &AppPaths) -> Self {
+        let config_path = paths.config_file("templates.json");
+
+        Self {
+            config_path,
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn list(&self) -> Vec<ServerTemplate> {
+        std::fs::read_to_string(&self.config_path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn get(&self, template_id: &str) -> Result<ServerTemplate> {
+        self.list()
+            .into_iter()
+            .find(|t| t.id == template_id)
+            .or_not_found("Template not found")
+    }
+
+    /// Store a template under a new id
+    pub fn add(&self, mut template: ServerTemplate) -> Result<ServerTemplate> {
+        let _guard = self.lock.lock().unwrap();
+        let mut templates = self.list();
+        if templates.iter().any(|t| t.name == template.name) {
+            anyhow::bail!("A template named \"{}\" already exists", template.name);
+        }
+
+        template.id = uuid::Uuid::new_v4().to_string();
+        templates.push(template.clone());
+        self.save(&templates)?;
+        Ok(template)
+    }
+
+    pub fn remove(&self, template_id: &str) -> Result<()> {
+        let _guard = self.lock.lock().unwrap();
+        let mut templates = self.list();
+        templates.retain(|t| t.id != template_id);
+        self.save(&templates)
+    }
+
+    fn save(&self, templates: &[ServerTemplate]) -> Result<()> {
+        if let Some(parent) = self.config_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.config_path, serde_json::to_string_pretty(templates)?)?;
+        Ok(())
+    }
+}
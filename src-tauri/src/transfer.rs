@@ -0,0 +1,721 @@
+// Server transfer
+// Moves a stopped server to another Prismarine instance. The sender zips the
+// server folder together with its settings into a bundle and streams it in
+// hashed chunks to the receiver's transfer listener, reachable directly or
+// through a bridge tunnel. An interrupted transfer resumes from the chunks the
+// receiver already verified. The pairing code shown by the receiver is never
+// sent: both sides prove it with an HMAC over the challenge and the offered
+// bundle (name, size, hash), so a relay in between cannot swap the bundle.
+// Everything after the handshake is encrypted with a key derived from the code.
+
+use crate::server_manager::{ServerInfo, ServerManager};
+use anyhow::{Context, Result};
+use ring::{aead, hkdf, hmac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+pub const DEFAULT_TRANSFER_PORT: u16 = 25590;
+/// Server settings stored next to the server files in the bundle
+pub const SETTINGS_ENTRY: &str = "prismarine-server.json";
+
+const CHUNK_SIZE: usize = 4 * 1024 * 1024;
+/// Largest control message, chunks are sent outside of messages
+const MAX_MESSAGE_SIZE: u32 = 64 * 1024;
+/// Reconnects after a dropped connection before giving up
+const MAX_ATTEMPTS: u32 = 5;
+/// Resends of a chunk that failed verification
+const MAX_CHUNK_RETRIES: u32 = 3;
+const IO_TIMEOUT: Duration = Duration::from_secs(60);
+/// Longest pause of the listener after failed pairing attempts
+const MAX_BACKOFF_SECS: u64 = 60;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferPairing {
+    pub port: u16,
+    pub code: String, // Entered on the sending machine with `pair_transfer_target`
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferProgress {
+    pub server_id: String,
+    pub sent: u64,
+    pub total: u64,
+    pub done: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Message {
+    Challenge {
+        nonce: String,
+    },
+    Offer {
+        proof: String,
+        name: String,
+        size: u64,
+        sha256: String,
+    },
+    Accept {
+        proof: String, // The receiver's proof of the pairing code
+        offset: u64,
+    },
+    Reject {
+        reason: String,
+    },
+    Chunk {
+        offset: u64,
+        len: u32,
+        sha256: String,
+    },
+    ChunkAck {
+        ok: bool,
+    },
+    Finish,
+    Done {
+        server_id: String,
+    },
+}
+
+pub struct TransferReceiver {
+    listener: Mutex<Option<(JoinHandle<()>, TransferPairing)>>,
+}
+
+impl TransferReceiver {
+    pub fn new() -> Self {
+        Self {
+            listener: Mutex::new(None),
+        }
+    }
+
+    /// Listen for incoming servers with a new pairing code. Received servers are
+    /// registered with `manager` and saved to `config_path`.
+    pub async fn start(
+        &self,
+        manager: ServerManager,
+        config_path: PathBuf,
+        port: Option<u16>,
+    ) -> Result<TransferPairing> {
+        self.stop();
+        let port = port.unwrap_or(DEFAULT_TRANSFER_PORT);
+        let listener = TcpListener::bind(("0.0.0.0", port))
+            .await
+            .with_context(|| format!("Failed to listen on port {}", port))?;
+        let pairing = TransferPairing {
+            port,
+            code: crate::secrets::generate_password()[..12].to_string(),
+        };
+        println!("[Transfer] Receiving servers on port {}", port);
+
+        let code = pairing.code.clone();
+        let failures = Arc::new(AtomicU32::new(0));
+        let handle = tokio::spawn(async move {
+            loop {
+                // Guessing the pairing code gets slower with every failed attempt
+                let failed = failures.load(Ordering::Relaxed);
+                if failed > 0 {
+                    let delay = 2u64.saturating_pow(failed.min(6)).min(MAX_BACKOFF_SECS);
+                    tokio::time::sleep(Duration::from_secs(delay)).await;
+                }
+                let (stream, peer) = match listener.accept().await {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        println!("[Transfer] Accept failed: {}", e);
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                        continue;
+                    }
+                };
+                let manager = manager.clone();
+                let config_path = config_path.clone();
+                let code = code.clone();
+                let failures = Arc::clone(&failures);
+                tokio::spawn(async move {
+                    match receive(stream, &manager, &code).await {
+                        Ok(Some(server)) => {
+                            failures.store(0, Ordering::Relaxed);
+                            println!("[Transfer] Received server {} from {}", server.name, peer);
+                            let _ = manager.save_servers(&config_path).await;
+                        }
+                        Ok(None) => {}
+                        Err(e) => {
+                            if e.is::<WrongCode>() {
+                                failures.fetch_add(1, Ordering::Relaxed);
+                            }
+                            println!("[Transfer] Connection from {} closed: {}", peer, e)
+                        }
+                    }
+                });
+            }
+        });
+
+        *self.listener.lock().unwrap() = Some((handle, pairing.clone()));
+        Ok(pairing)
+    }
+
+    pub fn stop(&self) {
+        if let Some((handle, pairing)) = self.listener.lock().unwrap().take() {
+            handle.abort();
+            println!("[Transfer] Stopped listening on port {}", pairing.port);
+        }
+    }
+
+    pub fn status(&self) -> Option<TransferPairing> {
+        self.listener
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|(_, pairing)| pairing.clone())
+    }
+}
+
+fn transfers_dir(direction: &str) -> PathBuf {
+    crate::paths::get()
+        .cache_dir
+        .join("transfers")
+        .join(direction)
+}
+
+/// Failed pairing, counted by the listener to slow down guessing
+#[derive(Debug)]
+struct WrongCode;
+
+impl std::fmt::Display for WrongCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Wrong pairing code")
+    }
+}
+
+impl std::error::Error for WrongCode {}
+
+/// HMAC with the pairing code over the challenge and the offered bundle.
+/// `role` keeps the sender's proof from being replayed as the receiver's.
+fn proof(code: &str, role: &str, nonce: &str, name: &str, size: u64, sha256: &str) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, code.as_bytes());
+    let data = serde_json::to_vec(&(role, nonce, name, size, sha256)).unwrap_or_default();
+    hmac::sign(&key, &data)
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Encryption of everything after the handshake
+struct Cipher {
+    key: aead::LessSafeKey,
+    outgoing: u8, // Direction byte of our nonces, the peer uses the other one
+    sent: u64,
+    received: u64,
+}
+
+impl Cipher {
+    /// Key derived from the pairing code with the challenge as salt, so every
+    /// connection gets its own key
+    fn new(code: &str, nonce: &str, sender: bool) -> Result<Self> {
+        let key: aead::UnboundKey = hkdf::Salt::new(hkdf::HKDF_SHA256, nonce.as_bytes())
+            .extract(code.as_bytes())
+            .expand(
+                &[b"prismarine-transfer".as_slice()],
+                &aead::CHACHA20_POLY1305,
+            )
+            .map_err(|_| anyhow::anyhow!("Failed to derive the transfer key"))?
+            .into();
+        Ok(Self {
+            key: aead::LessSafeKey::new(key),
+            outgoing: if sender { 0 } else { 1 },
+            sent: 0,
+            received: 0,
+        })
+    }
+
+    fn nonce(direction: u8, counter: u64) -> aead::Nonce {
+        let mut nonce = [0u8; aead::NONCE_LEN];
+        nonce[0] = direction;
+        nonce[4..].copy_from_slice(&counter.to_be_bytes());
+        aead::Nonce::assume_unique_for_key(nonce)
+    }
+
+    fn seal(&mut self, data: &[u8]) -> Result<Vec<u8>> {
+        let mut sealed = data.to_vec();
+        self.key
+            .seal_in_place_append_tag(
+                Self::nonce(self.outgoing, self.sent),
+                aead::Aad::empty(),
+                &mut sealed,
+            )
+            .map_err(|_| anyhow::anyhow!("Failed to encrypt"))?;
+        self.sent += 1;
+        Ok(sealed)
+    }
+
+    fn open(&mut self, mut sealed: Vec<u8>) -> Result<Vec<u8>> {
+        let len = self
+            .key
+            .open_in_place(
+                Self::nonce(1 - self.outgoing, self.received),
+                aead::Aad::empty(),
+                &mut sealed,
+            )
+            .map_err(|_| anyhow::anyhow!("Transfer data was tampered with"))?
+            .len();
+        self.received += 1;
+        sealed.truncate(len);
+        Ok(sealed)
+    }
+}
+
+/// Length-prefixed frames, encrypted once the handshake is done
+struct Connection {
+    stream: TcpStream,
+    cipher: Option<Cipher>,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> Self {
+        Self {
+            stream,
+            cipher: None,
+        }
+    }
+
+    async fn write_frame(&mut self, data: &[u8]) -> Result<()> {
+        let data = match self.cipher.as_mut() {
+            Some(cipher) => cipher.seal(data)?,
+            None => data.to_vec(),
+        };
+        let mut packet = Vec::with_capacity(data.len() + 4);
+        packet.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        packet.extend_from_slice(&data);
+        tokio::time::timeout(IO_TIMEOUT, self.stream.write_all(&packet)).await??;
+        Ok(())
+    }
+
+    async fn read_frame(&mut self, max_len: usize) -> Result<Vec<u8>> {
+        let length = tokio::time::timeout(IO_TIMEOUT, self.stream.read_u32()).await?? as usize;
+        if length > max_len + aead::MAX_TAG_LEN {
+            anyhow::bail!("Invalid frame length {}", length);
+        }
+        let mut data = vec![0u8; length];
+        tokio::time::timeout(IO_TIMEOUT, self.stream.read_exact(&mut data)).await??;
+        match self.cipher.as_mut() {
+            Some(cipher) => cipher.open(data),
+            None => Ok(data),
+        }
+    }
+
+    async fn write_message(&mut self, message: &Message) -> Result<()> {
+        self.write_frame(&serde_json::to_vec(message)?).await
+    }
+
+    async fn read_message(&mut self) -> Result<Message> {
+        let data = self.read_frame(MAX_MESSAGE_SIZE as usize).await?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+}
+
+/// Receiver side of one connection; None when the sender gave up midway
+async fn receive(
+    stream: TcpStream,
+    manager: &ServerManager,
+    code: &str,
+) -> Result<Option<ServerInfo>> {
+    let mut stream = Connection::new(stream);
+    let nonce = crate::secrets::generate_password();
+    stream
+        .write_message(&Message::Challenge {
+            nonce: nonce.clone(),
+        })
+        .await?;
+
+    let Message::Offer {
+        proof: answer,
+        name,
+        size,
+        sha256,
+    } = stream.read_message().await?
+    else {
+        anyhow::bail!("Expected a transfer offer");
+    };
+    let expected = proof(code, "sender", &nonce, &name, size, &sha256);
+    if !crate::secrets::constant_time_eq(answer.as_bytes(), expected.as_bytes()) {
+        stream
+            .write_message(&Message::Reject {
+                reason: "Wrong pairing code".to_string(),
+            })
+            .await?;
+        return Err(WrongCode.into());
+    }
+    if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+        anyhow::bail!("Invalid bundle hash");
+    }
+
+    // Named after the bundle hash so a reconnect finds the verified part
+    let dir = transfers_dir("incoming");
+    tokio::fs::create_dir_all(&dir).await?;
+    let part_path = dir.join(format!("{}.part", sha256));
+    let mut offset = tokio::fs::metadata(&part_path)
+        .await
+        .map(|m| m.len())
+        .unwrap_or(0);
+    if offset > size {
+        offset = 0;
+    }
+    println!(
+        "[Transfer] Receiving {} ({} bytes, resuming at {})",
+        name, size, offset
+    );
+    stream
+        .write_message(&Message::Accept {
+            proof: proof(code, "receiver", &nonce, &name, size, &sha256),
+            offset,
+        })
+        .await?;
+    stream.cipher = Some(Cipher::new(code, &nonce, false)?);
+
+    let mut part = tokio::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(&part_path)
+        .await?;
+    part.set_len(offset).await?;
+    part.seek(SeekFrom::Start(offset)).await?;
+
+    loop {
+        let message = match stream.read_message().await {
+            Ok(message) => message,
+            // Dropped connection: keep the part for the next attempt
+            Err(_) => return Ok(None),
+        };
+        match message {
+            Message::Chunk {
+                offset: chunk_offset,
+                len,
+                sha256: chunk_hash,
+            } => {
+                if len as usize > CHUNK_SIZE {
+                    anyhow::bail!("Chunk too large");
+                }
+                let data = stream.read_frame(CHUNK_SIZE).await?;
+                let ok = data.len() == len as usize
+                    && chunk_offset == offset
+                    && format!("{:x}", Sha256::digest(&data)) == chunk_hash;
+                if ok {
+                    part.write_all(&data).await?;
+                    part.flush().await?;
+                    offset += len as u64;
+                }
+                stream.write_message(&Message::ChunkAck { ok }).await?;
+            }
+            Message::Finish => break,
+            _ => anyhow::bail!("Unexpected message"),
+        }
+    }
+    drop(part);
+
+    let bundle_path = part_path.clone();
+    let actual = tokio::task::spawn_blocking(move || file_sha256(&bundle_path)).await??;
+    if actual != sha256 {
+        let _ = tokio::fs::remove_file(&part_path).await;
+        stream
+            .write_message(&Message::Reject {
+                reason: "Bundle hash mismatch, send it again".to_string(),
+            })
+            .await?;
+        anyhow::bail!("Bundle hash mismatch");
+    }
+
+    let result = manager.import_server_bundle(&part_path).await;
+    let _ = tokio::fs::remove_file(&part_path).await;
+    match result {
+        Ok(server) => {
+            stream
+                .write_message(&Message::Done {
+                    server_id: server.id.clone(),
+                })
+                .await?;
+            Ok(Some(server))
+        }
+        Err(e) => {
+            stream
+                .write_message(&Message::Reject {
+                    reason: e.to_string(),
+                })
+                .await?;
+            Err(e)
+        }
+    }
+}
+
+/// Send a bundle to `target_host` ("host:port"), reconnecting and resuming when the
+/// connection drops. Returns the id the server got on the receiving instance.
+pub async fn send_bundle<F>(
+    bundle_path: &Path,
+    name: &str,
+    target_host: &str,
+    code: &str,
+    on_progress: F,
+) -> Result<String>
+where
+    F: Fn(u64, u64),
+{
+    let size = tokio::fs::metadata(bundle_path).await?.len();
+    let path = bundle_path.to_path_buf();
+    let sha256 = tokio::task::spawn_blocking(move || file_sha256(&path)).await??;
+
+    let mut last_error = None;
+    for attempt in 1..=MAX_ATTEMPTS {
+        match send_attempt(
+            bundle_path,
+            name,
+            size,
+            &sha256,
+            target_host,
+            code,
+            &on_progress,
+        )
+        .await
+        {
+            Ok(server_id) => return Ok(server_id),
+            Err(e) if e.is::<Rejected>() => return Err(e),
+            Err(e) => {
+                println!("[Transfer] Attempt {} failed: {}", attempt, e);
+                last_error = Some(e);
+                tokio::time::sleep(Duration::from_secs(2 * attempt as u64)).await;
+            }
+        }
+    }
+    Err(last_error
+        .unwrap_or_else(|| anyhow::anyhow!("Transfer failed"))
+        .context(format!("Transfer failed after {} attempts", MAX_ATTEMPTS)))
+}
+
+/// Refusal by the receiver, retrying would not help
+#[derive(Debug)]
+struct Rejected(String);
+
+impl std::fmt::Display for Rejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Transfer rejected: {}", self.0)
+    }
+}
+
+impl std::error::Error for Rejected {}
+
+async fn send_attempt<F>(
+    bundle_path: &Path,
+    name: &str,
+    size: u64,
+    sha256: &str,
+    target_host: &str,
+    code: &str,
+    on_progress: &F,
+) -> Result<String>
+where
+    F: Fn(u64, u64),
+{
+    let stream = tokio::time::timeout(IO_TIMEOUT, TcpStream::connect(target_host))
+        .await
+        .context("Connection timed out")?
+        .with_context(|| format!("Failed to connect to {}", target_host))?;
+    let mut stream = Connection::new(stream);
+
+    let Message::Challenge { nonce } = stream.read_message().await? else {
+        anyhow::bail!("Not a Prismarine transfer listener");
+    };
+    stream
+        .write_message(&Message::Offer {
+            proof: proof(code, "sender", &nonce, name, size, sha256),
+            name: name.to_string(),
+            size,
+            sha256: sha256.to_string(),
+        })
+        .await?;
+    let mut offset = match stream.read_message().await? {
+        Message::Accept {
+            proof: answer,
+            offset,
+        } => {
+            let expected = proof(code, "receiver", &nonce, name, size, sha256);
+            if !crate::secrets::constant_time_eq(answer.as_bytes(), expected.as_bytes()) {
+                return Err(
+                    Rejected("the receiver does not know the pairing code".to_string()).into(),
+                );
+            }
+            offset
+        }
+        Message::Reject { reason } => return Err(Rejected(reason).into()),
+        _ => anyhow::bail!("Unexpected message"),
+    };
+    stream.cipher = Some(Cipher::new(code, &nonce, true)?);
+
+    let mut file = std::fs::File::open(bundle_path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    while offset < size {
+        let len = read_full(&mut file, &mut buffer)?;
+        if len == 0 {
+            anyhow::bail!("Bundle changed while sending");
+        }
+        let data = &buffer[..len];
+        let chunk = Message::Chunk {
+            offset,
+            len: len as u32,
+            sha256: format!("{:x}", Sha256::digest(data)),
+        };
+
+        let mut retries = 0;
+        loop {
+            stream.write_message(&chunk).await?;
+            stream.write_frame(data).await?;
+            match stream.read_message().await? {
+                Message::ChunkAck { ok: true } => break,
+                Message::ChunkAck { ok: false } if retries < MAX_CHUNK_RETRIES => retries += 1,
+                _ => anyhow::bail!("Chunk at {} was not accepted", offset),
+            }
+        }
+        offset += len as u64;
+        on_progress(offset, size);
+    }
+
+    stream.write_message(&Message::Finish).await?;
+    match stream.read_message().await? {
+        Message::Done { server_id } => Ok(server_id),
+        Message::Reject { reason } => Err(Rejected(reason).into()),
+        _ => anyhow::bail!("Unexpected message"),
+    }
+}
+
+/// Fill `buffer` unless the file ends first
+fn read_full(file: &mut std::fs::File, buffer: &mut [u8]) -> Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        let read = file.read(&mut buffer[filled..])?;
+        if read == 0 {
+            break;
+        }
+        filled += read;
+    }
+    Ok(filled)
+}
+
+fn file_sha256(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Zip the whole server folder plus its settings into `dest_path`
+pub fn write_bundle(server: &ServerInfo, dest_path: &Path) -> Result<()> {
+    if let Some(parent) = dest_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let file = std::fs::File::create(dest_path).context("Failed to create bundle")?;
+    let mut writer = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .large_file(true);
+
+    writer.start_file(SETTINGS_ENTRY, options)?;
+    writer.write_all(&serde_json::to_vec_pretty(&server.portable())?)?;
+
+    let mut stack = vec![server.path.clone()];
+    while let Some(dir) = stack.pop() {
+        for entry in std::fs::read_dir(&dir)?.flatten() {
+            let path = entry.path();
+            let name = path
+                .strip_prefix(&server.path)?
+                .to_string_lossy()
+                .replace('\\', "/");
+
+            if path.is_dir() {
+                writer.add_directory(format!("files/{}/", name), options)?;
+                stack.push(path);
+            } else {
+                if entry.file_name() == "session.lock" {
+                    continue;
+                }
+                writer.start_file(format!("files/{}", name), options)?;
+                std::io::copy(&mut std::fs::File::open(&path)?, &mut writer)?;
+            }
+        }
+    }
+
+    writer.finish()?;
+    Ok(())
+}
+
+/// Settings stored in a bundle
+pub fn read_bundle_settings(bundle_path: &Path) -> Result<ServerInfo> {
+    let file = std::fs::File::open(bundle_path)?;
+    let mut archive = zip::ZipArchive::new(file).context("Invalid bundle")?;
+    let entry = archive
+        .by_name(SETTINGS_ENTRY)
+        .context("Not a Prismarine server bundle")?;
+    Ok(serde_json::from_reader(entry)?)
+}
+
+/// Extract the server files of a bundle into `target`
+pub fn extract_bundle(bundle_path: &Path, target: &Path) -> Result<()> {
+    let file = std::fs::File::open(bundle_path)?;
+    let mut archive = zip::ZipArchive::new(file).context("Invalid bundle")?;
+    std::fs::create_dir_all(target)?;
+
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        let Some(path) = entry.enclosed_name() else {
+            continue;
+        };
+        let Ok(relative) = path.strip_prefix("files") else {
+            continue;
+        };
+        if relative.as_os_str().is_empty() {
+            continue;
+        }
+
+        let out_path = target.join(relative);
+        if entry.is_dir() {
+            std::fs::create_dir_all(&out_path)?;
+        } else {
+            if let Some(parent) = out_path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let mut out_file = std::fs::File::create(&out_path)?;
+            std::io::copy(&mut entry, &mut out_file)?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether the bundle is newer than every file of the server
+pub fn is_bundle_current(server_path: &Path, bundle_path: &Path) -> bool {
+    let Ok(built) = std::fs::metadata(bundle_path).and_then(|m| m.modified()) else {
+        return false;
+    };
+    let mut stack = vec![server_path.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            return false;
+        };
+        for entry in entries.flatten() {
+            match entry.metadata() {
+                Ok(meta) if meta.is_dir() => stack.push(entry.path()),
+                Ok(meta) if meta.modified().is_ok_and(|m| m < built) => {}
+                _ => return false,
+            }
+        }
+    }
+    true
+}
+
+/// Where the outgoing bundle of a server is built
+pub fn outgoing_bundle_path(server_id: &str) -> PathBuf {
+    transfers_dir("outgoing").join(format!("{}.zip", server_id))
+}
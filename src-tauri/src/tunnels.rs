@@ -0,0 +1,419 @@
+// Tunnel providers for Prismarine Bridge
+// Each provider knows where to download its client, how to launch it for a
+// local port and how to read the public address from its output.
+//  - bore: no account, random port on bore.pub (or a self-hosted bore server)
+//  - playit.gg: free account, the agent is linked once through a claim URL
+//  - frp: self-hosted frps server ("host:port" + token), also tunnels UDP (Bedrock)
+//  - cloudflared: TCP quick tunnel, or a named tunnel with a token
+
+use crate::bridge::BridgeStatus;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TunnelKind {
+    #[default]
+    Bore,
+    Playit,
+    Frp,
+    Cloudflared,
+}
+
+impl TunnelKind {
+    pub const ALL: [TunnelKind; 4] = [
+        TunnelKind::Bore,
+        TunnelKind::Playit,
+        TunnelKind::Frp,
+        TunnelKind::Cloudflared,
+    ];
+
+    pub fn provider(self) -> Box<dyn TunnelProvider> {
+        match self {
+            TunnelKind::Bore => Box::new(Bore),
+            TunnelKind::Playit => Box::new(Playit),
+            TunnelKind::Frp => Box::new(Frp),
+            TunnelKind::Cloudflared => Box::new(Cloudflared),
+        }
+    }
+}
+
+/// Where a provider's client comes from
+pub enum BinaryDownload {
+    /// The release asset is the executable itself
+    Raw(&'static str),
+    /// The executable is the zip entry whose name ends with `entry`
+    Zip {
+        url: &'static str,
+        entry: &'static str,
+    },
+}
+
+/// What the tunnel is started with
+#[derive(Debug, Clone)]
+pub struct TunnelParams {
+    pub port: u16,
+    pub remote_server: Option<String>, // Self-hosted server or the public hostname
+    pub secret: Option<String>,
+    pub udp: bool, // Forward UDP instead of TCP (Geyser's Bedrock port)
+}
+
+pub trait TunnelProvider: Send + Sync {
+    /// Shown in logs and the UI
+    fn name(&self) -> &'static str;
+
+    /// File name of the client in the bridge folder (without .exe)
+    fn binary_name(&self) -> &'static str;
+
+    /// None when there is no build for this platform
+    fn download(&self) -> Option<BinaryDownload>;
+
+    /// Secret store entry used when no secret is passed to `start`
+    fn secret_name(&self) -> &'static str;
+
+    /// Whether the tunnel cannot start without `remote_server`
+    fn needs_remote_server(&self) -> bool {
+        false
+    }
+
+    /// Whether `TunnelParams::udp` is supported
+    fn supports_udp(&self) -> bool {
+        false
+    }
+
+    /// Command line for the tunnel; `dir` is the bridge folder for config files
+    fn command(&self, binary: &Path, dir: &Path, params: &TunnelParams) -> Result<Command>;
+
+    /// Status change announced by an output line, if any
+    fn parse_output(&self, line: &str, params: &TunnelParams) -> Option<BridgeStatus>;
+}
+
+pub fn binary_path(dir: &Path, provider: &dyn TunnelProvider) -> PathBuf {
+    dir.join(format!(
+        "{}{}",
+        provider.binary_name(),
+        std::env::consts::EXE_SUFFIX
+    ))
+}
+
+/// Generic failure detection shared by all providers
+fn error_line(line: &str) -> Option<BridgeStatus> {
+    let lower = line.to_lowercase();
+    if lower.contains("error") || lower.contains("failed") {
+        return Some(BridgeStatus::Error(line.to_string()));
+    }
+    None
+}
+
+pub struct Bore;
+
+impl TunnelProvider for Bore {
+    fn name(&self) -> &'static str {
+        "bore"
+    }
+
+    fn binary_name(&self) -> &'static str {
+        "bore"
+    }
+
+    fn download(&self) -> Option<BinaryDownload> {
+        if cfg!(target_os = "windows") {
+            Some(BinaryDownload::Zip {
+                url: "https://github.com/ekzhang/bore/releases/download/v0.6.0/bore-v0.6.0-x86_64-pc-windows-msvc.zip",
+                entry: "bore.exe",
+            })
+        } else {
+            None // Only published as tar.gz, install bore with your package manager
+        }
+    }
+
+    fn secret_name(&self) -> &'static str {
+        crate::secrets::BRIDGE_SECRET
+    }
+
+    fn command(&self, binary: &Path, _dir: &Path, params: &TunnelParams) -> Result<Command> {
+        let server = params
+            .remote_server
+            .clone()
+            .unwrap_or_else(|| "bore.pub".to_string());
+        let mut command = Command::new(binary);
+        command
+            .arg("local")
+            .arg(params.port.to_string())
+            .arg("--to")
+            .arg(server);
+        if let Some(secret) = params.secret.as_ref().filter(|s| !s.is_empty()) {
+            command.arg("--secret").arg(secret);
+        }
+        Ok(command)
+    }
+
+    fn parse_output(&self, line: &str, params: &TunnelParams) -> Option<BridgeStatus> {
+        // "listening at bore.pub:XXXXX"
+        let server = params.remote_server.as_deref().unwrap_or("bore.pub");
+        let host = server.split(':').next().unwrap_or(server);
+        if line.to_lowercase().contains("listening") {
+            if let Some(addr) = line
+                .split_whitespace()
+                .find(|w| w.contains(&format!("{}:", host)))
+            {
+                let clean = addr.trim_matches(|c| c == '"' || c == '\'' || c == ',' || c == '.');
+                return Some(BridgeStatus::Connected(clean.to_string()));
+            }
+        }
+        error_line(line)
+    }
+}
+
+pub struct Playit;
+
+impl TunnelProvider for Playit {
+    fn name(&self) -> &'static str {
+        "playit.gg"
+    }
+
+    fn binary_name(&self) -> &'static str {
+        "playit"
+    }
+
+    fn download(&self) -> Option<BinaryDownload> {
+        if cfg!(target_os = "windows") {
+            Some(BinaryDownload::Raw(
+                "https://github.com/playit-cloud/playit-agent/releases/download/v0.15.26/playit-windows-x86_64-signed.exe",
+            ))
+        } else if cfg!(all(target_os = "linux", target_arch = "x86_64")) {
+            Some(BinaryDownload::Raw(
+                "https://github.com/playit-cloud/playit-agent/releases/download/v0.15.26/playit-linux-amd64",
+            ))
+        } else {
+            None
+        }
+    }
+
+    fn secret_name(&self) -> &'static str {
+        crate::secrets::PLAYIT_SECRET
+    }
+
+    fn command(&self, binary: &Path, dir: &Path, params: &TunnelParams) -> Result<Command> {
+        let mut command = Command::new(binary);
+        command
+            .arg("--stdout")
+            .arg("--secret_path")
+            .arg(dir.join("playit.toml"));
+        if let Some(secret) = params.secret.as_ref().filter(|s| !s.is_empty()) {
+            command.arg("--secret").arg(secret);
+        }
+        Ok(command)
+    }
+
+    fn parse_output(&self, line: &str, params: &TunnelParams) -> Option<BridgeStatus> {
+        // A new agent has to be linked to an account once
+        if let Some(url) = line
+            .split_whitespace()
+            .find(|w| w.contains("playit.gg/claim/"))
+        {
+            return Some(BridgeStatus::ActionRequired(url.to_string()));
+        }
+        // "abc-def.joinmc.link => 127.0.0.1:25565 (minecraft-java)"
+        if let Some((public, local)) = line.split_once("=>") {
+            let local_address = local.split_whitespace().next().unwrap_or_default();
+            if local_address.ends_with(&format!(":{}", params.port)) {
+                if let Some(address) = public.split_whitespace().last() {
+                    return Some(BridgeStatus::Connected(address.to_string()));
+                }
+            }
+        }
+        error_line(line)
+    }
+}
+
+pub struct Frp;
+
+impl TunnelProvider for Frp {
+    fn name(&self) -> &'static str {
+        "frp"
+    }
+
+    fn binary_name(&self) -> &'static str {
+        "frpc"
+    }
+
+    fn download(&self) -> Option<BinaryDownload> {
+        if cfg!(target_os = "windows") {
+            Some(BinaryDownload::Zip {
+                url: "https://github.com/fatedier/frp/releases/download/v0.61.0/frp_0.61.0_windows_amd64.zip",
+                entry: "frpc.exe",
+            })
+        } else {
+            None
+        }
+    }
+
+    fn secret_name(&self) -> &'static str {
+        crate::secrets::FRP_TOKEN
+    }
+
+    fn needs_remote_server(&self) -> bool {
+        true
+    }
+
+    fn supports_udp(&self) -> bool {
+        true
+    }
+
+    fn command(&self, binary: &Path, dir: &Path, params: &TunnelParams) -> Result<Command> {
+        let server = params
+            .remote_server
+            .as_deref()
+            .context("frp needs the address of your frps server (host:port)")?;
+        let (host, server_port) = match server.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse::<u16>().context("Invalid frps port")?),
+            None => (server, 7000),
+        };
+
+        let protocol = if params.udp { "udp" } else { "tcp" };
+        let config = FrpConfig {
+            server_addr: host,
+            server_port,
+            auth: params
+                .secret
+                .as_deref()
+                .filter(|s| !s.is_empty())
+                .map(|token| FrpAuth { token }),
+            proxies: vec![FrpProxy {
+                name: format!("prismarine-{}-{}", params.port, protocol),
+                kind: protocol,
+                local_ip: "127.0.0.1",
+                local_port: params.port,
+                remote_port: params.port,
+            }],
+        };
+
+        // Contains the token: readable only by the user
+        let config_path = dir.join(format!("frpc-{}-{}.toml", params.port, protocol));
+        std::fs::write(&config_path, toml::to_string(&config)?)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&config_path, std::fs::Permissions::from_mode(0o600))?;
+        }
+
+        let mut command = Command::new(binary);
+        command.arg("-c").arg(config_path);
+        Ok(command)
+    }
+
+    fn parse_output(&self, line: &str, params: &TunnelParams) -> Option<BridgeStatus> {
+        // "[prismarine-25565-tcp] start proxy success"
+        if line.contains("start proxy success") {
+            let host = params
+                .remote_server
+                .as_deref()
+                .and_then(|s| s.rsplit_once(':').map(|(h, _)| h).or(Some(s)))
+                .unwrap_or_default();
+            return Some(BridgeStatus::Connected(format!("{}:{}", host, params.port)));
+        }
+        error_line(line)
+    }
+}
+
+/// frpc.toml
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FrpConfig<'a> {
+    server_addr: &'a str,
+    server_port: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    auth: Option<FrpAuth<'a>>,
+    proxies: Vec<FrpProxy<'a>>,
+}
+
+#[derive(Serialize)]
+struct FrpAuth<'a> {
+    token: &'a str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FrpProxy<'a> {
+    name: String,
+    #[serde(rename = "type")]
+    kind: &'a str,
+    #[serde(rename = "localIP")]
+    local_ip: &'a str,
+    local_port: u16,
+    remote_port: u16,
+}
+
+pub struct Cloudflared;
+
+impl TunnelProvider for Cloudflared {
+    fn name(&self) -> &'static str {
+        "cloudflared"
+    }
+
+    fn binary_name(&self) -> &'static str {
+        "cloudflared"
+    }
+
+    fn download(&self) -> Option<BinaryDownload> {
+        if cfg!(target_os = "windows") {
+            Some(BinaryDownload::Raw(
+                "https://github.com/cloudflare/cloudflared/releases/latest/download/cloudflared-windows-amd64.exe",
+            ))
+        } else if cfg!(all(target_os = "linux", target_arch = "x86_64")) {
+            Some(BinaryDownload::Raw(
+                "https://github.com/cloudflare/cloudflared/releases/latest/download/cloudflared-linux-amd64",
+            ))
+        } else {
+            None
+        }
+    }
+
+    fn secret_name(&self) -> &'static str {
+        crate::secrets::CLOUDFLARED_TOKEN
+    }
+
+    fn command(&self, binary: &Path, _dir: &Path, params: &TunnelParams) -> Result<Command> {
+        let mut command = Command::new(binary);
+        command.arg("tunnel").arg("--no-autoupdate");
+        match params.secret.as_ref().filter(|s| !s.is_empty()) {
+            // Named tunnel, the hostname is routed in the Cloudflare dashboard
+            Some(token) => {
+                command.arg("run").arg("--token").arg(token);
+            }
+            // Quick tunnel on a random trycloudflare.com hostname
+            None => {
+                command
+                    .arg("--url")
+                    .arg(format!("tcp://localhost:{}", params.port));
+            }
+        }
+        Ok(command)
+    }
+
+    fn parse_output(&self, line: &str, params: &TunnelParams) -> Option<BridgeStatus> {
+        if let Some(url) = line
+            .split_whitespace()
+            .find(|w| w.contains(".trycloudflare.com"))
+        {
+            // A quick TCP tunnel is not a plain address: players reach it through
+            // `cloudflared access tcp` on their own machine
+            let host = url.trim_start_matches("https://").trim_end_matches('|');
+            return Some(BridgeStatus::AccessRequired(host.to_string()));
+        }
+        if line.contains("Registered tunnel connection") {
+            return Some(match &params.remote_server {
+                Some(hostname) => BridgeStatus::Connected(hostname.clone()),
+                None => BridgeStatus::Running,
+            });
+        }
+        // cloudflared logs everything with a level, only ERR lines are failures
+        if line.contains(" ERR ") {
+            return Some(BridgeStatus::Error(line.to_string()));
+        }
+        None
+    }
+}
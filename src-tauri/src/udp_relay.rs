@@ -0,0 +1,214 @@
+// Bedrock relay
+// Listens on UDP at the Java port number and forwards datagrams to Geyser's
+// Bedrock port, so one forwarded port number (TCP for Java, UDP for Bedrock)
+// serves both editions. Each client gets its own upstream socket. The first
+// datagram of a session carries a PROXY protocol v2 header with the player's
+// address, so Geyser (bedrock.enable-proxy-protocol) does not see everyone
+// coming from localhost.
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::task::{JoinHandle, JoinSet};
+
+/// Sessions without a reply from Geyser for this long are closed
+const SESSION_IDLE: Duration = Duration::from_secs(60);
+/// Clients relayed at once. Source addresses are easy to spoof, so new ones
+/// are dropped rather than each getting a socket and a task.
+const MAX_SESSIONS: usize = 256;
+/// Largest datagram RakNet sends (MTU probes stay below this)
+const MAX_DATAGRAM: usize = 2048;
+/// PROXY protocol v2 signature
+const PROXY_V2_SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+
+/// A relay that should be running: `listen_port` (UDP) to Geyser's `bedrock_port`
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelayTarget {
+    pub server_id: String,
+    pub listen_port: u16,
+    pub bedrock_port: u16,
+    pub proxy_protocol: bool, // Geyser expects PROXY protocol headers
+}
+
+struct Relay {
+    target: RelayTarget,
+    handle: JoinHandle<()>,
+}
+
+pub struct UdpRelay {
+    relays: Mutex<HashMap<String, Relay>>,
+}
+
+impl UdpRelay {
+    pub fn new() -> Self {
+        Self {
+            relays: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Start missing relays and stop the ones no longer wanted (or whose ports changed)
+    pub async fn sync(&self, targets: &[RelayTarget]) {
+        let stale: Vec<String> = self
+            .relays
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, relay)| !targets.contains(&relay.target))
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in stale {
+            self.stop(&id);
+        }
+
+        for target in targets {
+            if self.relays.lock().unwrap().contains_key(&target.server_id) {
+                continue;
+            }
+            match start(target).await {
+                Ok(handle) => {
+                    println!(
+                        "[BedrockRelay] UDP {} -> Geyser {} for {}",
+                        target.listen_port, target.bedrock_port, target.server_id
+                    );
+                    self.relays.lock().unwrap().insert(
+                        target.server_id.clone(),
+                        Relay {
+                            target: target.clone(),
+                            handle,
+                        },
+                    );
+                }
+                Err(e) => println!("[BedrockRelay] {}: {}", target.server_id, e),
+            }
+        }
+    }
+
+    pub fn stop(&self, server_id: &str) {
+        if let Some(relay) = self.relays.lock().unwrap().remove(server_id) {
+            relay.handle.abort();
+            println!("[BedrockRelay] Stopped the relay of {}", server_id);
+        }
+    }
+}
+
+async fn start(target: &RelayTarget) -> Result<JoinHandle<()>> {
+    let socket = UdpSocket::bind(("0.0.0.0", target.listen_port))
+        .await
+        .with_context(|| format!("Failed to listen on UDP port {}", target.listen_port))?;
+    let socket = Arc::new(socket);
+    let bedrock: SocketAddr = ([127, 0, 0, 1], target.bedrock_port).into();
+    Ok(tokio::spawn(relay(socket, bedrock, target.proxy_protocol)))
+}
+
+/// PROXY protocol v2 header for a UDP datagram from `client` to `local`
+fn proxy_header(client: SocketAddr, local: SocketAddr) -> Vec<u8> {
+    let mut header = PROXY_V2_SIGNATURE.to_vec();
+    header.push(0x21); // Version 2, PROXY command
+    match (client.ip(), local.ip()) {
+        (IpAddr::V4(source), IpAddr::V4(destination)) => {
+            header.push(0x12); // IPv4, datagram
+            header.extend_from_slice(&12u16.to_be_bytes());
+            header.extend_from_slice(&source.octets());
+            header.extend_from_slice(&destination.octets());
+        }
+        (source, destination) => {
+            let v6 = |ip: IpAddr| match ip {
+                IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+                IpAddr::V6(ip) => ip,
+            };
+            header.push(0x22); // IPv6, datagram
+            header.extend_from_slice(&36u16.to_be_bytes());
+            header.extend_from_slice(&v6(source).octets());
+            header.extend_from_slice(&v6(destination).octets());
+        }
+    }
+    header.extend_from_slice(&client.port().to_be_bytes());
+    header.extend_from_slice(&local.port().to_be_bytes());
+    header
+}
+
+async fn relay(socket: Arc<UdpSocket>, bedrock: SocketAddr, proxy_protocol: bool) {
+    let Ok(local) = socket.local_addr() else {
+        return;
+    };
+    let sessions: Arc<Mutex<HashMap<SocketAddr, Arc<UdpSocket>>>> =
+        Arc::new(Mutex::new(HashMap::new()));
+    // Owned here so aborting the relay also ends its sessions and frees the port
+    let mut session_tasks = JoinSet::new();
+    let mut buffer = [0u8; MAX_DATAGRAM];
+    let mut full = false;
+    loop {
+        let Ok((read, client)) = socket.recv_from(&mut buffer).await else {
+            continue;
+        };
+        while session_tasks.try_join_next().is_some() {}
+
+        let upstream = sessions.lock().unwrap().get(&client).cloned();
+        let upstream = match upstream {
+            Some(upstream) => upstream,
+            None => {
+                if sessions.lock().unwrap().len() >= MAX_SESSIONS {
+                    if !full {
+                        println!(
+                            "[BedrockRelay] {} sessions open, dropping new clients",
+                            MAX_SESSIONS
+                        );
+                        full = true;
+                    }
+                    continue;
+                }
+                full = false;
+                match open_session(&socket, &sessions, &mut session_tasks, client, bedrock).await {
+                    Ok(upstream) if proxy_protocol => {
+                        // Geyser remembers the address per upstream socket
+                        let mut datagram = proxy_header(client, local);
+                        datagram.extend_from_slice(&buffer[..read]);
+                        let _ = upstream.send(&datagram).await;
+                        continue;
+                    }
+                    Ok(upstream) => upstream,
+                    Err(e) => {
+                        println!("[BedrockRelay] Session for {} failed: {}", client, e);
+                        continue;
+                    }
+                }
+            }
+        };
+        let _ = upstream.send(&buffer[..read]).await;
+    }
+}
+
+/// Connect a socket to Geyser for a new client and pass its replies back
+async fn open_session(
+    socket: &Arc<UdpSocket>,
+    sessions: &Arc<Mutex<HashMap<SocketAddr, Arc<UdpSocket>>>>,
+    session_tasks: &mut JoinSet<()>,
+    client: SocketAddr,
+    bedrock: SocketAddr,
+) -> Result<Arc<UdpSocket>> {
+    let upstream = UdpSocket::bind(("127.0.0.1", 0)).await?;
+    upstream.connect(bedrock).await?;
+    let upstream = Arc::new(upstream);
+    sessions
+        .lock()
+        .unwrap()
+        .insert(client, Arc::clone(&upstream));
+
+    let socket = Arc::clone(socket);
+    let sessions = Arc::clone(sessions);
+    let replies = Arc::clone(&upstream);
+    session_tasks.spawn(async move {
+        let mut buffer = [0u8; MAX_DATAGRAM];
+        while let Ok(Ok(read)) = tokio::time::timeout(SESSION_IDLE, replies.recv(&mut buffer)).await
+        {
+            if socket.send_to(&buffer[..read], client).await.is_err() {
+                break;
+            }
+        }
+        sessions.lock().unwrap().remove(&client);
+    });
+    Ok(upstream)
+}
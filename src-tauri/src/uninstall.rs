@@ -0,0 +1,133 @@
+// Uninstall cleanup
+// Undo what Prismarine changed outside its own folders, so removing the app
+// leaves the network as it found it: running servers are stopped, tunnels
+// closed, and the router mappings and firewall rules carrying Prismarine's tag
+// removed (mappings described "Prismarine ...", rules in the "Prismarine"
+// firewall group). The installer runs it as `prismarine --uninstall-cleanup`, the
+// frontend through the `uninstall_cleanup` command. Server data is never
+// deleted here, it can be archived to a zip on request.
+
+use crate::bridge::PrismarineBridge;
+use crate::port_manager::PortManager;
+use crate::server_manager::{ServerInfo, ServerManager};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CleanupReport {
+    pub servers_stopped: usize,
+    pub tunnels_killed: usize, // Leftover tunnel clients of earlier sessions
+    pub mappings_removed: usize,
+    pub firewall_cleaned: bool,
+    pub archive: Option<PathBuf>,
+    pub errors: Vec<String>, // Steps that failed, the others still ran
+}
+
+pub async fn run(
+    manager: &ServerManager,
+    port_manager: &PortManager,
+    bridge: &PrismarineBridge,
+    archive_to: Option<&Path>,
+) -> CleanupReport {
+    let mut errors = Vec::new();
+
+    let servers_stopped = manager.stop_all_servers().await.len();
+    let tunnels_killed = bridge.stop_all();
+
+    let mappings_removed = match port_manager.remove_all_mappings().await {
+        Ok(removed) => removed.len(),
+        Err(e) => {
+            errors.push(format!("Router mappings: {}", e));
+            0
+        }
+    };
+    let firewall_cleaned =
+        match tokio::task::spawn_blocking(crate::port_manager::remove_all_firewall_rules).await {
+            Ok(Ok(())) => true,
+            Ok(Err(e)) => {
+                errors.push(format!("Firewall rules: {}", e));
+                false
+            }
+            Err(e) => {
+                errors.push(format!("Firewall rules: {}", e));
+                false
+            }
+        };
+
+    let mut archive = None;
+    if let Some(dest) = archive_to {
+        let servers = manager.get_servers().await;
+        let target = dest.to_path_buf();
+        match tokio::task::spawn_blocking(move || archive_servers(&servers, &target)).await {
+            Ok(Ok(())) => archive = Some(dest.to_path_buf()),
+            Ok(Err(e)) => errors.push(format!("Archive: {:#}", e)),
+            Err(e) => errors.push(format!("Archive: {}", e)),
+        }
+    }
+
+    println!(
+        "[Uninstall] Stopped {} servers, removed {} mappings, firewall {}, {} errors",
+        servers_stopped,
+        mappings_removed,
+        if firewall_cleaned {
+            "cleaned"
+        } else {
+            "not cleaned"
+        },
+        errors.len()
+    );
+    CleanupReport {
+        servers_stopped,
+        tunnels_killed,
+        mappings_removed,
+        firewall_cleaned,
+        archive,
+        errors,
+    }
+}
+
+/// Zip every local server folder, each under "<name> (<id>)/"
+fn archive_servers(servers: &[ServerInfo], dest: &Path) -> Result<()> {
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let file = std::fs::File::create(dest).context("Failed to create the archive")?;
+    let mut writer = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .large_file(true);
+
+    for server in servers.iter().filter(|s| s.remote.is_none()) {
+        let root = format!("{} ({})", server.name.replace(['/', '\\'], "_"), server.id);
+        let mut stack = vec![server.path.clone()];
+        while let Some(dir) = stack.pop() {
+            let Ok(entries) = std::fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let Ok(file_type) = entry.file_type() else {
+                    continue;
+                };
+                let path = entry.path();
+                let name = format!(
+                    "{}/{}",
+                    root,
+                    path.strip_prefix(&server.path)?
+                        .to_string_lossy()
+                        .replace('\\', "/")
+                );
+                // Symlinked folders are not followed, they may point outside the server
+                if file_type.is_dir() {
+                    writer.add_directory(format!("{}/", name), options)?;
+                    stack.push(path);
+                } else if path.is_file() {
+                    writer.start_file(name, options)?;
+                    std::io::copy(&mut std::fs::File::open(&path)?, &mut writer)?;
+                }
+            }
+        }
+    }
+    writer.finish()?;
+    Ok(())
+}
@@ -0,0 +1,146 @@
+// Version requirements
+// Java requirements, support status and known-good Paper builds per Minecraft
+// version. Replaces guessing from the version string, which got modded 1.8-1.12
+// servers (Forge needs exactly Java 8 there) and 1.17 (Java 16) wrong.
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VersionRequirements {
+    pub version: String,
+    pub min_java: u8,
+    pub max_java: Option<u8>, // Newest Java that still works (None = any)
+    pub max_java_modded: Option<u8>, // Same for Forge-based servers
+    pub recommended_java: u8,
+    pub paper_build: Option<u64>, // Known-good (final) Paper build
+    pub end_of_life: bool,        // No longer receives fixes from Mojang or Paper
+    pub known: bool,              // false when the version is not in the table
+}
+
+struct Range {
+    from: (u16, u16, u16), // Inclusive
+    to: (u16, u16, u16),   // Inclusive
+    min_java: u8,
+    max_java: Option<u8>,
+    max_java_modded: Option<u8>,
+    recommended_java: u8,
+    end_of_life: bool,
+}
+
+const RANGES: &[Range] = &[
+    // LaunchWrapper (Forge up to 1.12) casts the system class loader, Java 9+ breaks it
+    Range {
+        from: (1, 0, 0),
+        to: (1, 12, 2),
+        min_java: 8,
+        max_java: None,
+        max_java_modded: Some(8),
+        recommended_java: 8,
+        end_of_life: true,
+    },
+    // ModLauncher Forge for 1.13 - 1.16.5 only supports Java 8
+    Range {
+        from: (1, 13, 0),
+        to: (1, 16, 5),
+        min_java: 8,
+        max_java: None,
+        max_java_modded: Some(8),
+        recommended_java: 11,
+        end_of_life: true,
+    },
+    Range {
+        from: (1, 17, 0),
+        to: (1, 17, 1),
+        min_java: 16,
+        max_java: None,
+        max_java_modded: None,
+        recommended_java: 17,
+        end_of_life: true,
+    },
+    Range {
+        from: (1, 18, 0),
+        to: (1, 20, 4),
+        min_java: 17,
+        max_java: None,
+        max_java_modded: None,
+        recommended_java: 17,
+        end_of_life: true,
+    },
+    Range {
+        from: (1, 20, 5),
+        to: (1, 21, u16::MAX),
+        min_java: 21,
+        max_java: None,
+        max_java_modded: None,
+        recommended_java: 21,
+        end_of_life: false,
+    },
+];
+
+/// Final builds of versions Paper no longer updates
+const PAPER_BUILDS: &[(&str, u64)] = &[
+    ("1.8.8", 445),
+    ("1.12.2", 1620),
+    ("1.16.5", 794),
+    ("1.17.1", 411),
+    ("1.18.2", 388),
+    ("1.19.4", 550),
+    ("1.20.1", 196),
+];
+
+/// "1.20.4" -> (1, 20, 4); snapshots and pre-releases ("1.21-pre1") use their base version
+fn parse_version(version: &str) -> Option<(u16, u16, u16)> {
+    let base = version.split(['-', ' ', '+']).next()?;
+    let mut parts = base.split('.').map(|p| p.parse::<u16>());
+    let major = parts.next()?.ok()?;
+    let minor = parts.next()?.ok()?;
+    let patch = parts.next().and_then(|p| p.ok()).unwrap_or(0);
+    Some((major, minor, patch))
+}
+
+/// Order Minecraft versions numerically ("1.9" < "1.20"); unparsable versions sort last
+pub fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    match (parse_version(a), parse_version(b)) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => a.cmp(b),
+    }
+}
+
+pub fn requirements(version: &str) -> VersionRequirements {
+    let parsed = parse_version(version);
+    let range = parsed.and_then(|v| RANGES.iter().find(|r| r.from <= v && v <= r.to));
+    let paper_build = PAPER_BUILDS
+        .iter()
+        .find(|(v, _)| *v == version)
+        .map(|(_, build)| *build);
+
+    match range {
+        Some(r) => VersionRequirements {
+            version: version.to_string(),
+            min_java: r.min_java,
+            max_java: r.max_java,
+            max_java_modded: r.max_java_modded,
+            recommended_java: r.recommended_java,
+            paper_build,
+            end_of_life: r.end_of_life,
+            known: true,
+        },
+        // Newer than the table (or not a Minecraft version, e.g. proxies):
+        // assume the requirements of the newest known version
+        None => {
+            let latest = RANGES.last().unwrap();
+            VersionRequirements {
+                version: version.to_string(),
+                min_java: latest.min_java,
+                max_java: None,
+                max_java_modded: None,
+                recommended_java: latest.recommended_java,
+                paper_build,
+                end_of_life: false,
+                known: false,
+            }
+        }
+    }
+}
@@ -0,0 +1,276 @@
+// World preview
+// Renders a small top-down map of the spawn area straight from the region
+// files (one pixel per block, colored by the topmost block) so server cards
+// get a recognizable thumbnail. Supports the chunk format of 1.16 and newer.
+
+use crate::nbt::{self, Nbt};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Chunks rendered in each direction from the spawn chunk (8 -> 16x16 chunks, 256x256 px)
+const RADIUS_CHUNKS: i32 = 8;
+const SIZE: usize = (RADIUS_CHUNKS as usize * 2) * 16;
+
+/// PNG of the area around the world spawn, cached in `cache_path` until one of
+/// the rendered region files changes. None when the world was not generated yet.
+pub fn render(world_dir: &Path, cache_path: &Path, force: bool) -> Result<Option<Vec<u8>>> {
+    let level_dat = world_dir.join("level.dat");
+    if !level_dat.exists() {
+        return Ok(None);
+    }
+    let (spawn_x, spawn_z) = read_spawn(&level_dat)?;
+    let center_cx = spawn_x.div_euclid(16);
+    let center_cz = spawn_z.div_euclid(16);
+    let (min_cx, min_cz) = (center_cx - RADIUS_CHUNKS, center_cz - RADIUS_CHUNKS);
+    let (max_cx, max_cz) = (center_cx + RADIUS_CHUNKS - 1, center_cz + RADIUS_CHUNKS - 1);
+
+    let region_dir = world_dir.join("region");
+    let mut regions: Vec<PathBuf> = Vec::new();
+    for rx in min_cx.div_euclid(32)..=max_cx.div_euclid(32) {
+        for rz in min_cz.div_euclid(32)..=max_cz.div_euclid(32) {
+            let path = region_dir.join(format!("r.{}.{}.mca", rx, rz));
+            if path.exists() {
+                regions.push(path);
+            }
+        }
+    }
+    if regions.is_empty() {
+        return Ok(None);
+    }
+
+    if !force && is_fresh(cache_path, &regions) {
+        return Ok(Some(std::fs::read(cache_path)?));
+    }
+
+    let mut pixels = vec![0u8; SIZE * SIZE * 4];
+    let mut open_regions: HashMap<(i32, i32), Option<Vec<u8>>> = HashMap::new();
+    for cx in min_cx..=max_cx {
+        for cz in min_cz..=max_cz {
+            let key = (cx.div_euclid(32), cz.div_euclid(32));
+            let region = open_regions.entry(key).or_insert_with(|| {
+                std::fs::read(region_dir.join(format!("r.{}.{}.mca", key.0, key.1))).ok()
+            });
+            let Some(region) = region else { continue };
+            let Some(chunk) = read_chunk(region, cx, cz) else {
+                continue;
+            };
+            let px = ((cx - min_cx) * 16) as usize;
+            let pz = ((cz - min_cz) * 16) as usize;
+            draw_chunk(&chunk, &mut pixels, px, pz);
+        }
+    }
+
+    let png = encode_png(&pixels)?;
+    if let Some(parent) = cache_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(cache_path, &png)?;
+    Ok(Some(png))
+}
+
+fn is_fresh(cache_path: &Path, regions: &[PathBuf]) -> bool {
+    let Ok(cached) = std::fs::metadata(cache_path).and_then(|m| m.modified()) else {
+        return false;
+    };
+    regions.iter().all(|region| {
+        std::fs::metadata(region)
+            .and_then(|m| m.modified())
+            .map(|modified| modified <= cached)
+            .unwrap_or(false)
+    })
+}
+
+fn read_spawn(level_dat: &Path) -> Result<(i32, i32)> {
+    let data = nbt::read_gzip(level_dat)?;
+    let root = nbt::parse(&data).context("Invalid level.dat")?;
+    let level = root.get("Data").context("level.dat has no Data")?;
+    let x = level.get("SpawnX").and_then(Nbt::as_i64).unwrap_or(0) as i32;
+    let z = level.get("SpawnZ").and_then(Nbt::as_i64).unwrap_or(0) as i32;
+    Ok((x, z))
+}
+
+/// Decompressed NBT of chunk (cx, cz) from a region file, None if not generated
+pub(crate) fn read_chunk(region: &[u8], cx: i32, cz: i32) -> Option<Nbt> {
+    let index = (cx.rem_euclid(32) + cz.rem_euclid(32) * 32) as usize * 4;
+    let location = region.get(index..index + 4)?;
+    let offset = u32::from_be_bytes([0, location[0], location[1], location[2]]) as usize * 4096;
+    if offset == 0 {
+        return None;
+    }
+    let header = region.get(offset..offset + 5)?;
+    let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    let payload = region.get(offset + 5..offset + 4 + length)?;
+
+    let mut data = Vec::new();
+    match header[4] {
+        1 => {
+            flate2::read::GzDecoder::new(payload)
+                .read_to_end(&mut data)
+                .ok()?;
+        }
+        2 => {
+            flate2::read::ZlibDecoder::new(payload)
+                .read_to_end(&mut data)
+                .ok()?;
+        }
+        3 => data.extend_from_slice(payload),
+        _ => return None, // External .mcc chunks or LZ4, not worth it for a thumbnail
+    }
+    nbt::parse(&data)
+}
+
+fn draw_chunk(chunk: &Nbt, pixels: &mut [u8], px: usize, pz: usize) {
+    // 1.18+ stores everything at the root, 1.16-1.17 inside "Level"
+    let (level, min_y, sections_key, palette_key, states_key) = match chunk.get("sections") {
+        Some(_) => (chunk, -64, "sections", "palette", "data"),
+        None => match chunk.get("Level") {
+            Some(level) => (level, 0, "Sections", "Palette", "BlockStates"),
+            None => return,
+        },
+    };
+    let Some(Nbt::LongArray(heightmap)) =
+        level.get("Heightmaps").and_then(|h| h.get("WORLD_SURFACE"))
+    else {
+        return;
+    };
+    let Some(Nbt::List(section_list)) = level.get(sections_key) else {
+        return;
+    };
+
+    // Section Y -> (palette names, packed states)
+    let mut sections: HashMap<i64, (Vec<&str>, &[i64])> = HashMap::new();
+    for section in section_list {
+        let y = section.get("Y").and_then(Nbt::as_i64).unwrap_or(0);
+        let (palette, states) = if sections_key == "sections" {
+            let Some(block_states) = section.get("block_states") else {
+                continue;
+            };
+            (block_states.get(palette_key), block_states.get(states_key))
+        } else {
+            (section.get(palette_key), section.get(states_key))
+        };
+        let Some(Nbt::List(palette)) = palette else {
+            continue;
+        };
+        let names = palette
+            .iter()
+            .map(|entry| match entry.get("Name") {
+                Some(Nbt::String(name)) => name.as_str(),
+                _ => "minecraft:air",
+            })
+            .collect();
+        let states = match states {
+            Some(Nbt::LongArray(states)) => states.as_slice(),
+            _ => &[],
+        };
+        sections.insert(y, (names, states));
+    }
+
+    for z in 0..16 {
+        for x in 0..16 {
+            let column = z * 16 + x;
+            let height = packed_value(heightmap, 9, column) as i64;
+            if height == 0 {
+                continue;
+            }
+            let y = height + min_y - 1;
+            let block = block_at(&sections, x, y, z).unwrap_or("minecraft:air");
+            let [r, g, b] = block_color(block);
+
+            // Light relief: brighter on higher ground
+            let shade = (0.75 + ((y - 62) as f32 / 128.0)).clamp(0.55, 1.15);
+            let i = ((pz + z) * SIZE + px + x) * 4;
+            pixels[i] = (r as f32 * shade).min(255.0) as u8;
+            pixels[i + 1] = (g as f32 * shade).min(255.0) as u8;
+            pixels[i + 2] = (b as f32 * shade).min(255.0) as u8;
+            pixels[i + 3] = 255;
+        }
+    }
+}
+
+fn block_at<'a>(
+    sections: &HashMap<i64, (Vec<&'a str>, &[i64])>,
+    x: usize,
+    y: i64,
+    z: usize,
+) -> Option<&'a str> {
+    let (palette, states) = sections.get(&y.div_euclid(16))?;
+    if palette.len() == 1 || states.is_empty() {
+        return palette.first().copied();
+    }
+    let bits = (usize::BITS - (palette.len() - 1).leading_zeros()).max(4) as usize;
+    let index = (y.rem_euclid(16) as usize) * 256 + z * 16 + x;
+    palette.get(packed_value(states, bits, index)).copied()
+}
+
+/// Entry `index` of a 1.16+ packed long array (entries never span two longs)
+fn packed_value(data: &[i64], bits: usize, index: usize) -> usize {
+    let per_long = 64 / bits;
+    let Some(long) = data.get(index / per_long) else {
+        return 0;
+    };
+    let shift = (index % per_long) * bits;
+    ((*long as u64 >> shift) & ((1u64 << bits) - 1)) as usize
+}
+
+fn block_color(name: &str) -> [u8; 3] {
+    let name = name.trim_start_matches("minecraft:");
+    match name {
+        "grass_block" => [95, 159, 53],
+        "water" | "bubble_column" | "kelp" | "kelp_plant" | "seagrass" | "tall_seagrass" => {
+            [54, 90, 180]
+        }
+        "lava" => [207, 92, 20],
+        "sand" | "sandstone" => [219, 207, 163],
+        "red_sand" | "red_sandstone" => [190, 102, 33],
+        "gravel" => [136, 126, 126],
+        "dirt" | "coarse_dirt" | "rooted_dirt" | "dirt_path" | "farmland" => [134, 96, 67],
+        "podzol" => [91, 63, 24],
+        "mycelium" => [111, 99, 105],
+        "snow" | "snow_block" | "powder_snow" => [240, 251, 251],
+        "ice" | "packed_ice" | "blue_ice" => [145, 183, 253],
+        "clay" => [160, 166, 179],
+        "mud" => [60, 57, 61],
+        "netherrack" => [97, 38, 38],
+        "end_stone" => [219, 222, 158],
+        "obsidian" => [20, 18, 29],
+        _ if name.ends_with("_leaves") => [48, 120, 36],
+        _ if name.ends_with("_log") || name.ends_with("_wood") => [102, 81, 50],
+        _ if name.ends_with("_planks") || name.contains("_stairs") || name.ends_with("_slab") => {
+            [162, 130, 78]
+        }
+        _ if name.contains("terracotta") => [152, 94, 67],
+        _ if name.contains("stone")
+            || name.contains("andesite")
+            || name.contains("diorite")
+            || name.contains("granite")
+            || name.contains("deepslate")
+            || name.contains("tuff") =>
+        {
+            [125, 125, 125]
+        }
+        _ if name.contains("grass")
+            || name.contains("fern")
+            || name.contains("flower")
+            || name.ends_with("_tulip")
+            || name.contains("bush") =>
+        {
+            [88, 150, 48]
+        }
+        _ if name.ends_with("_ore") => [115, 115, 115],
+        _ => [140, 140, 140],
+    }
+}
+
+fn encode_png(pixels: &[u8]) -> Result<Vec<u8>> {
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, SIZE as u32, SIZE as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(pixels)?;
+    writer.finish()?;
+    Ok(png)
+}
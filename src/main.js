@@ -1,9 +1,33 @@
 // =============================================
 // State & Core API
 // =============================================
-const { invoke } = window.__TAURI__.core;
+const { invoke: tauriInvoke } = window.__TAURI__.core;
+
+// Commands reject with { code, message, ...context }; keep `${err}` printing the message
+class CommandError extends Error {
+    constructor(payload) {
+        super(payload.message);
+        Object.assign(this, payload);
+    }
+
+    toString() {
+        return this.message;
+    }
+}
+
+async function invoke(cmd, args) {
+    try {
+        return await tauriInvoke(cmd, args);
+    } catch (err) {
+        throw (err && typeof err === 'object' && err.code) ? new CommandError(err) : err;
+    }
+}
 
 let servers = [];
+let listedServers = []; // Servers shown in the list view (filtered and sorted)
+let listedTotal = 0; // Servers matching the filters, more than listed when paged
+let serverListLimit = 50; // Grows with "さらに表示"
+const worldPreviews = {}; // Server id -> data URL (null while the world does not exist)
 let systemStats = null;
 let updateInterval = null;
 let logRefreshIntervals = {};
@@ -32,6 +56,11 @@ document.addEventListener('DOMContentLoaded', async () => {
     // 1. Initialize Event Listeners
     setupLifecycleListeners();
     initializeEventListeners();
+    setupDownloadProgressListener();
+    setupTransferProgressListener();
+    setupExitListener();
+    setupStatusListener();
+    setupAutostartListener();
 
     // 2. Initial Data Load
     try {
@@ -39,7 +68,8 @@ document.addEventListener('DOMContentLoaded', async () => {
             checkUPnPStatus(),
             updateExternalIP(),
             loadServers(),
-            loadManagedPorts()
+            loadManagedPorts(),
+            checkInstanceStatus()
         ]);
 
         // 3. Start Background Tasks
@@ -51,6 +81,15 @@ document.addEventListener('DOMContentLoaded', async () => {
     }
 });
 
+// Warn when another Prismarine instance manages the same data folder
+async function checkInstanceStatus() {
+    const status = await invoke('get_instance_status');
+    if (!status.owner && status.holder) {
+        const kind = status.holder.kind === 'headless' ? 'ヘッドレス版' : '別のウィンドウ';
+        showNotification(`${kind}のPrismarine (PID ${status.holder.pid}) がこのデータを管理中のため、閲覧専用で起動しました`, 'warning');
+    }
+}
+
 function setupLifecycleListeners() {
     window.addEventListener('beforeunload', () => {
         if (updateInterval) clearInterval(updateInterval);
@@ -125,6 +164,7 @@ function initializeEventListeners() {
     document.querySelectorAll('.modal-close').forEach(btn => {
         btn.onclick = closeCreateServerModal;
     });
+    document.getElementById('remote-import-close').onclick = closeRemoteImportModal;
 
     // Close modal on background click
     document.getElementById('create-server-modal').addEventListener('click', (e) => {
@@ -170,6 +210,7 @@ function initializeEventListeners() {
     document.getElementById('decrease-players-btn').onclick = () => adjustMaxPlayers(-1);
     document.getElementById('open-plugins-btn').onclick = openPluginsFolder;
     document.getElementById('detail-folder-btn').onclick = openServerFolder;
+    document.getElementById('detail-repair-btn').onclick = repairServer;
 
     const commandInput = document.getElementById('console-command-input');
     if (commandInput) {
@@ -199,6 +240,7 @@ function initializeEventListeners() {
     };
     document.getElementById('plugin-refresh-btn').onclick = searchPlugins;
     document.getElementById('install-starter-pack-btn').onclick = installStarterPack;
+    document.getElementById('install-modpack-btn').onclick = installModpack;
     document.getElementById('install-geyser-btn').onclick = installGeyser;
     document.getElementById('install-viaversion-btn').onclick = installViaVersion;
 
@@ -273,6 +315,7 @@ function switchView(viewName) {
         viewEl.classList.add('active');
         // Initial data sync for specific views
         if (viewName === 'ports') loadManagedPorts();
+        if (viewName === 'settings') loadAppSettings();
         if (viewName === 'servers' || viewName === 'dashboard') loadServers();
     } else {
         console.error('[DEBUG] View element not found:', targetView);
@@ -312,6 +355,50 @@ function switchTab(btn) {
     }
 }
 
+// =============================================
+// Download Progress
+// =============================================
+function setupDownloadProgressListener() {
+    const { listen } = window.__TAURI__.event;
+
+    listen('download-progress', (event) => {
+        const { task_id, downloaded, total, done } = event.payload;
+
+        let container = document.getElementById('download-progress-container');
+        if (!container) {
+            container = document.createElement('div');
+            container.id = 'download-progress-container';
+            container.style.cssText = 'position: fixed; bottom: 20px; right: 20px; z-index: 99998; display: flex; flex-direction: column; gap: 8px; pointer-events: none;';
+            document.body.appendChild(container);
+        }
+
+        const itemId = `download-${task_id.replace(/[^a-zA-Z0-9_-]/g, '_')}`;
+        let item = document.getElementById(itemId);
+        if (!item) {
+            item = document.createElement('div');
+            item.id = itemId;
+            item.style.cssText = 'width: 260px; padding: 10px 14px; background: rgba(15, 23, 42, 0.9); color: white; border-radius: 10px; font-size: 12px;';
+            item.innerHTML = `
+                <div class="download-label" style="margin-bottom: 6px; overflow: hidden; text-overflow: ellipsis; white-space: nowrap;"></div>
+                <div style="height: 6px; background: rgba(255,255,255,0.15); border-radius: 3px; overflow: hidden;">
+                    <div class="download-bar" style="height: 100%; width: 0%; background: var(--primary); transition: width 0.2s;"></div>
+                </div>`;
+            container.appendChild(item);
+        }
+
+        const mb = (bytes) => (bytes / 1024 / 1024).toFixed(1);
+        const percent = total ? Math.min(100, (downloaded / total) * 100) : null;
+        item.querySelector('.download-label').textContent = total
+            ? `${task_id} - ${mb(downloaded)} / ${mb(total)} MB`
+            : `${task_id} - ${mb(downloaded)} MB`;
+        item.querySelector('.download-bar').style.width = `${done ? 100 : (percent ?? 0)}%`;
+
+        if (done) {
+            setTimeout(() => item.remove(), 2000);
+        }
+    });
+}
+
 function showNotification(message, type = 'info') {
     console.log(`[NOTIFICATION][${type.toUpperCase()}] ${message}`);
 
@@ -469,11 +556,21 @@ function openCreateServerModal() {
             modal.classList.add('active');
         });
         updateVersionList();
+        suggestFreePort();
     } else {
         console.error('[DEBUG] Modal element not found: create-server-modal');
     }
 }
 
+async function suggestFreePort() {
+    try {
+        const port = await invoke('find_free_port', {});
+        if (port) document.getElementById('server-port').value = port;
+    } catch (e) {
+        console.error(e);
+    }
+}
+
 function closeCreateServerModal() {
     const modal = document.getElementById('create-server-modal');
     if (modal) {
@@ -555,6 +652,7 @@ function setupCustomSelects() {
     const typeInput = document.getElementById('server-type');
     if (typeInput) {
         typeInput.addEventListener('change', updateVersionList);
+        document.getElementById('include-snapshots')?.addEventListener('change', updateVersionList);
     }
 }
 
@@ -562,9 +660,27 @@ function setupCustomSelects() {
 // Server Management
 // =============================================
 
+// Filters of the server list view (the full list is kept in `servers`)
+function serverListQuery() {
+    const value = id => document.getElementById(id)?.value || null;
+    return {
+        search: value('server-filter-search'),
+        status: value('server-filter-status'),
+        server_type: value('server-filter-type'),
+        sort_by: value('server-sort') || 'Name',
+        descending: false,
+        offset: 0,
+        limit: serverListLimit
+    };
+}
+
 async function loadServers() {
     try {
-        servers = await invoke('get_servers') || [];
+        const listing = await invoke('get_servers', { query: serverListQuery() });
+        servers = listing.servers || [];
+        const byId = new Map(servers.map(s => [s.id, s]));
+        listedServers = listing.page.map(id => byId.get(id)).filter(Boolean);
+        listedTotal = listing.total;
         renderServers();
         updateDashboard();
     } catch (err) {
@@ -572,6 +688,11 @@ async function loadServers() {
     }
 }
 
+function showMoreServers() {
+    serverListLimit += 50;
+    loadServers();
+}
+
 function renderServers() {
     const serversList = document.getElementById('servers-list');
     const recentList = document.getElementById('recent-servers-list');
@@ -583,8 +704,12 @@ function renderServers() {
         if (recentList) recentList.innerHTML = '<div class="empty-state-text">サーバーなし</div>';
         return;
     }
+    if (listedServers.length === 0) {
+        serversList.innerHTML = '<div class="empty-state"><h3>条件に一致するサーバーはありません</h3></div>';
+        return;
+    }
 
-    const html = servers.map(s => {
+    const html = listedServers.map(s => {
         // Handle Downloading status (comes as object like {Downloading: "message"})
         let statusText = s.status;
         let statusClass = '';
@@ -604,14 +729,22 @@ function renderServers() {
 
         return `
             <div class="server-card">
+                <img class="server-preview" data-preview-id="${s.id}" alt=""
+                    style="width: 100%; height: 96px; object-fit: cover; border-radius: 6px; margin-bottom: 8px; image-rendering: pixelated; ${worldPreviews[s.id] ? '' : 'display: none;'}"
+                    ${worldPreviews[s.id] ? `src="${worldPreviews[s.id]}"` : ''}>
                 <div class="server-card-header">
                     <h3 class="server-name">${escapeHtml(s.name)}</h3>
+                    <span class="server-health" data-health-id="${s.id}" style="display: none;">●</span>
                     <span class="server-status ${statusClass}">${escapeHtml(statusText)}</span>
                 </div>
                 <div class="server-info">
                     <div class="server-info-item"><span>タイプ:</span><span>${s.server_type}</span></div>
                     <div class="server-info-item"><span>バージョン:</span><span>${s.version}</span></div>
                     <div class="server-info-item"><span>ポート:</span><span>${s.port}</span></div>
+                    ${s.tunnel_address ? `<div class="server-info-item"><span>Bridge:</span><span>${escapeHtml(s.tunnel_address)}</span></div>` : ''}
+                    ${s.bedrock_tunnel_address ? `<div class="server-info-item"><span>Bedrock:</span><span>${escapeHtml(s.bedrock_tunnel_address)}</span></div>` : ''}
+                    ${s.tags.length ? `<div class="server-info-item"><span>タグ:</span><span>${s.tags.map(escapeHtml).join(', ')}</span></div>` : ''}
+                    ${s.group ? `<div class="server-info-item"><span>グループ:</span><span>${escapeHtml(s.group)}</span></div>` : ''}
                 </div>
                 <div class="server-actions">
                     ${isRunning ?
@@ -620,25 +753,115 @@ function renderServers() {
                     `<button class="btn btn-secondary btn-sm" disabled>準備中...</button>` :
                     `<button class="btn btn-primary btn-sm" onclick="startServer('${s.id}')">起動</button>`}
                     <button class="btn btn-secondary btn-sm" onclick="showServerDetail('${s.id}')">詳細</button>
+                    ${s.web_map_port ? `<button class="btn btn-secondary btn-sm" onclick="openWebMap('${s.id}')">マップ</button>` : ''}
                     <button class="btn btn-secondary btn-sm" onclick="deleteServer('${s.id}')">削除</button>
                 </div>
             </div>
         `;
     }).join('');
 
-    serversList.innerHTML = html;
+    const remaining = listedTotal - listedServers.length;
+    serversList.innerHTML = html + (remaining > 0
+        ? `<button class="btn btn-secondary btn-sm" onclick="showMoreServers()">さらに表示 (残り${remaining}件)</button>`
+        : '');
     if (recentList) recentList.innerHTML = html; // Simple clone for now
+    loadWorldPreviews();
+    loadServerHealth();
+}
+
+const HEALTH_COLORS = { Green: '#3fb950', Yellow: '#d29922', Red: '#f85149' };
+
+// Traffic light next to the status of running servers, reasons in the tooltip
+async function loadServerHealth() {
+    for (const s of listedServers) {
+        if (s.status !== 'Running' && s.status !== 'Starting') continue;
+        try {
+            const health = await invoke('get_server_health', { serverId: s.id });
+            document.querySelectorAll(`[data-health-id="${s.id}"]`).forEach(dot => {
+                const color = HEALTH_COLORS[health.light];
+                dot.style.display = color ? '' : 'none';
+                dot.style.color = color || '';
+                dot.title = health.reasons.length ? health.reasons.join('\n') : '正常';
+            });
+        } catch (e) {
+            console.warn('Health check failed', e);
+        }
+    }
+}
+
+// Render world thumbnails once per session (the backend caches them per world)
+async function loadWorldPreviews() {
+    for (const s of listedServers) {
+        if (s.id in worldPreviews) continue;
+        worldPreviews[s.id] = null;
+        try {
+            const preview = await invoke('render_world_preview', { serverId: s.id });
+            if (!preview) {
+                delete worldPreviews[s.id]; // Try again once the world exists
+                continue;
+            }
+            worldPreviews[s.id] = preview;
+            document.querySelectorAll(`img[data-preview-id="${s.id}"]`).forEach(img => {
+                img.src = preview;
+                img.style.display = '';
+            });
+        } catch (e) {
+            console.warn('[Preview]', s.name, e);
+        }
+    }
 }
 
 async function startServer(id) {
     try {
+        const server = servers.find(s => s.id === id);
+        if (server && server.eula_accepted === false) {
+            if (!await showEulaModal()) {
+                showNotification('EULAへの同意が必要です', 'info');
+                return;
+            }
+            await invoke('accept_eula', { serverId: id });
+        }
+
         showNotification('サーバーを起動します...', 'info');
         await invoke('start_server', { serverId: id });
         await loadServers();
         if (currentDetailServerId === id) showServerDetail(id);
     } catch (err) {
+        if (err.code === 'INSUFFICIENT_MEMORY' && err.suggested_mb) {
+            await offerMemorySuggestion(id, err);
+            return;
+        }
+        // eula.txt of an imported server declined the EULA, ask before starting again
+        if (String(err).includes('EULA') && await showEulaModal()) {
+            try {
+                await invoke('accept_eula', { serverId: id });
+                await invoke('start_server', { serverId: id });
+                await loadServers();
+                if (currentDetailServerId === id) showServerDetail(id);
+            } catch (retryErr) {
+                showNotification(`起動失敗: ${retryErr}`, 'error');
+            }
+            return;
+        }
+        showNotification(`起動失敗: ${err}`, 'error');
+    }
+}
+
+// The heap does not fit in RAM: offer the suggested size and start again
+async function offerMemorySuggestion(id, err) {
+    const suggestion = await invoke('suggest_memory', { serverId: id });
+    const message = `メモリが不足しています。このPCで使える残りは約 ${err.available_mb} MB です。` +
+        `最大メモリを ${suggestion.suggested} に変更して起動しますか？`;
+    if (!(await showConfirmModal(message))) {
         showNotification(`起動失敗: ${err}`, 'error');
+        return;
     }
+    await invoke('set_server_memory', {
+        serverId: id,
+        memory: suggestion.suggested,
+        minMemory: suggestion.suggested,
+    });
+    await startServer(id);
 }
 
 async function stopServer(id) {
@@ -652,13 +875,378 @@ async function stopServer(id) {
     }
 }
 
+async function loadAppSettings() {
+    try {
+        const settings = await invoke('get_app_settings');
+        document.getElementById('servers-dir-input').value = settings.servers_dir;
+        document.getElementById('exit-policy-select').value = settings.exit_policy || 'Ask';
+        document.getElementById('offline-mode-toggle').checked = !!settings.offline_mode;
+        document.getElementById('autostart-delay-input').value = settings.autostart_delay_secs ?? 10;
+        const limits = settings.limits || {};
+        document.getElementById('limit-running-servers').value = limits.max_running_servers ?? '';
+        document.getElementById('limit-downloads').value = limits.max_downloads ?? 4;
+        document.getElementById('limit-backup-jobs').value = limits.max_backup_jobs ?? 1;
+        const overrides = settings.paths || {};
+        document.getElementById('path-data-dir').value = overrides.data_dir || '';
+        document.getElementById('path-cache-dir').value = overrides.cache_dir || '';
+        document.getElementById('path-log-dir').value = overrides.log_dir || '';
+        const paths = await invoke('get_app_paths');
+        document.getElementById('app-paths-info').textContent =
+            `${paths.portable ? 'ポータブル版 / ' : ''}設定: ${paths.config_dir} / データ: ${paths.data_dir} / キャッシュ: ${paths.cache_dir} / ログ: ${paths.log_dir}`;
+        document.getElementById('save-servers-dir-btn').onclick = saveAppSettings;
+        const restApi = settings.rest_api || {};
+        document.getElementById('rest-api-enabled').checked = !!restApi.enabled;
+        document.getElementById('rest-api-port').value = restApi.port ?? 25581;
+        document.getElementById('rest-api-listen-all').checked = !!restApi.listen_all;
+        document.getElementById('rest-api-tls-cert').value = restApi.tls_cert || '';
+        document.getElementById('rest-api-tls-key').value = restApi.tls_key || '';
+        renderRestApiStatus(await invoke('get_rest_api_status'));
+        renderTransferReceiver(await invoke('get_transfer_receiver'));
+        await loadNotifications();
+        await loadJavaRuntimes();
+    } catch (err) {
+        showNotification(`設定の読み込みに失敗: ${err}`, 'error');
+    }
+}
+
+// Notification channels and which events go to each of them
+const NOTIFY_EVENTS = [
+    ['Crash', 'クラッシュ'],
+    ['PlayerJoin', '参加'],
+    ['BackupFailed', 'バックアップ失敗'],
+    ['Failover', 'フェイルオーバー'],
+    ['Report', '統計レポート'],
+];
+// Extra settings per channel type: [field, label, input type]
+const NOTIFY_FIELDS = {
+    Discord: [],
+    Slack: [],
+    Telegram: [['chat_id', 'チャットID', 'text']],
+    Email: [
+        ['smtp_host', 'SMTPサーバー', 'text'],
+        ['smtp_port', 'ポート', 'number'],
+        ['username', 'ユーザー名', 'text'],
+        ['from', '送信元', 'email'],
+        ['to', '宛先', 'email'],
+    ],
+    Ntfy: [['server', 'サーバー (空欄で ntfy.sh)', 'text'], ['topic', 'トピック', 'text']],
+};
+const NOTIFY_CREDENTIALS = {
+    Discord: 'Webhook URL',
+    Slack: 'Webhook URL',
+    Telegram: 'Botトークン',
+    Email: 'パスワード',
+    Ntfy: 'アクセストークン (任意)',
+};
+let notifyConfig = { channels: [], routes: {} };
+
+async function loadNotifications() {
+    notifyConfig = await invoke('get_notification_config');
+    renderNotifyChannels();
+    renderNotifyFields();
+}
+
+function renderNotifyChannels() {
+    const list = document.getElementById('notify-channel-list');
+    if (!notifyConfig.channels.length) {
+        list.innerHTML = '<p class="info-text text-muted">チャンネルがありません</p>';
+        return;
+    }
+    list.innerHTML = notifyConfig.channels.map(channel => `
+        <div class="info-text">
+            <strong>${escapeHtml(channel.name)}</strong> (${channel.kind.type})
+            ${NOTIFY_EVENTS.map(([event, label]) => `
+                <label class="checkbox-label text-sm">
+                    <input type="checkbox" ${(notifyConfig.routes[event] || []).includes(channel.id) ? 'checked' : ''}
+                        onchange="toggleNotifyRoute('${channel.id}', '${event}', this.checked)" />
+                    <span>${label}</span>
+                </label>`).join('')}
+            <button class="btn btn-secondary btn-sm" onclick="testNotifyChannel('${channel.id}')">テスト</button>
+            <button class="btn btn-secondary btn-sm" onclick="removeNotifyChannel('${channel.id}')">削除</button>
+        </div>`).join('');
+}
+
+function renderNotifyFields() {
+    const type = document.getElementById('notify-type').value;
+    document.getElementById('notify-fields').innerHTML = NOTIFY_FIELDS[type]
+        .map(([field, label, inputType]) =>
+            `<input type="${inputType}" id="notify-field-${field}" class="input input-sm" placeholder="${label}" />`)
+        .join('');
+    document.getElementById('notify-credential').placeholder = NOTIFY_CREDENTIALS[type];
+}
+
+async function saveNotifications(credentials = {}) {
+    try {
+        await invoke('set_notification_config', { config: notifyConfig, credentials });
+    } catch (err) {
+        showNotification(`通知設定の保存に失敗: ${err}`, 'error');
+    }
+    await loadNotifications();
+}
+
+async function addNotifyChannel() {
+    const type = document.getElementById('notify-type').value;
+    const name = document.getElementById('notify-name').value.trim();
+    if (!name) {
+        showNotification('チャンネル名を入力してください', 'error');
+        return;
+    }
+    const kind = { type };
+    for (const [field, , inputType] of NOTIFY_FIELDS[type]) {
+        const value = document.getElementById(`notify-field-${field}`).value.trim();
+        kind[field] = inputType === 'number' ? (parseInt(value, 10) || 587) : value;
+    }
+    if (type === 'Ntfy') kind.server = kind.server || null;
+    const id = crypto.randomUUID();
+    notifyConfig.channels.push({ id, name, kind });
+    notifyConfig.routes.Crash = [...(notifyConfig.routes.Crash || []), id];
+    const credential = document.getElementById('notify-credential').value.trim();
+    document.getElementById('notify-name').value = '';
+    document.getElementById('notify-credential').value = '';
+    await saveNotifications(credential ? { [id]: credential } : {});
+}
+
+async function toggleNotifyRoute(channelId, event, enabled) {
+    const ids = (notifyConfig.routes[event] || []).filter(id => id !== channelId);
+    notifyConfig.routes[event] = enabled ? [...ids, channelId] : ids;
+    await saveNotifications();
+}
+
+async function removeNotifyChannel(channelId) {
+    notifyConfig.channels = notifyConfig.channels.filter(c => c.id !== channelId);
+    for (const event of Object.keys(notifyConfig.routes)) {
+        notifyConfig.routes[event] = notifyConfig.routes[event].filter(id => id !== channelId);
+    }
+    await saveNotifications();
+}
+
+async function testNotifyChannel(channelId) {
+    try {
+        await invoke('test_notification_channel', { channelId });
+        showNotification('テスト通知を送信しました', 'success');
+    } catch (err) {
+        showNotification(`テスト通知に失敗: ${err}`, 'error');
+    }
+}
+
+// Java runtimes: managed ones (installed by Prismarine) can be removed
+async function loadJavaRuntimes() {
+    const list = document.getElementById('java-runtime-list');
+    const runtimes = await invoke('list_installed_javas');
+    list.innerHTML = runtimes.length
+        ? runtimes.map(r => `
+            <div class="info-text">
+                Java ${r.version} - ${escapeHtml(r.path)}
+                ${r.managed ? `<button class="btn btn-secondary btn-sm" onclick="verifyJava(${r.version})">検証</button>
+                <button class="btn btn-secondary btn-sm" onclick="removeJava(${r.version})">削除</button>` : ''}
+            </div>`).join('')
+        : '<p class="info-text text-muted">Javaが見つかりません</p>';
+}
+
+async function installJava() {
+    const version = parseInt(document.getElementById('java-install-version').value, 10);
+    try {
+        showNotification(`Java ${version} をダウンロードしています...`, 'info');
+        await invoke('install_java', { version });
+        showNotification(`Java ${version} をインストールしました`, 'success');
+        await loadJavaRuntimes();
+    } catch (err) {
+        showNotification(`Javaのインストールに失敗: ${err}`, 'error');
+    }
+}
+
+async function verifyJava(version) {
+    try {
+        const result = await invoke('verify_java_installation', { version });
+        showNotification(result.repaired ? `Java ${version} を修復しました` : `Java ${version} は正常です`, 'success');
+        await loadJavaRuntimes();
+    } catch (err) {
+        showNotification(`Javaの検証に失敗: ${err}`, 'error');
+    }
+}
+
+async function removeJava(version) {
+    if (!(await showConfirmModal(`Java ${version} を削除しますか？`))) return;
+    try {
+        await invoke('remove_java', { version });
+        await loadJavaRuntimes();
+    } catch (err) {
+        showNotification(`Javaの削除に失敗: ${err}`, 'error');
+    }
+}
+
+// Server transfer: this PC receives servers from another Prismarine
+function renderRestApiStatus(status) {
+    document.getElementById('rest-api-status').textContent = status.running
+        ? `稼働中: ${status.tls ? 'https' : 'http'}://${status.address}/api/v1/`
+        : '停止中';
+}
+
+async function regenerateRestApiToken() {
+    try {
+        const token = await invoke('regenerate_rest_api_token');
+        // Shown once, the backend never returns it again
+        document.getElementById('rest-api-status').textContent = `新しいトークン: ${token}`;
+    } catch (err) {
+        showNotification(`トークンの再発行に失敗: ${err}`, 'error');
+    }
+}
+
+function renderTransferReceiver(pairing) {
+    const status = document.getElementById('transfer-receiver-status');
+    status.textContent = pairing
+        ? `受信中 (ポート ${pairing.port}) ペアリングコード: ${pairing.code}`
+        : '停止中';
+}
+
+async function startTransferReceiver() {
+    const port = parseInt(document.getElementById('transfer-port-input').value, 10) || null;
+    try {
+        renderTransferReceiver(await invoke('start_transfer_receiver', { port }));
+    } catch (err) {
+        showNotification(`受信の開始に失敗: ${err}`, 'error');
+    }
+}
+
+async function stopTransferReceiver() {
+    await invoke('stop_transfer_receiver');
+    renderTransferReceiver(null);
+}
+
+async function transferServer() {
+    if (!currentDetailServerId) return;
+    const targetHost = document.getElementById('detail-transfer-host').value.trim();
+    const code = document.getElementById('detail-transfer-code').value.trim();
+    if (!targetHost) {
+        showNotification('転送先を入力してください', 'error');
+        return;
+    }
+    const status = document.getElementById('detail-transfer-status');
+    try {
+        // A code only has to be entered once per receiving PC
+        if (code) await invoke('pair_transfer_target', { targetHost, code });
+        status.textContent = 'サーバーをまとめています...';
+        await invoke('transfer_server', { serverId: currentDetailServerId, targetHost });
+        status.textContent = '転送が完了しました';
+        showNotification('サーバーを転送しました', 'success');
+    } catch (err) {
+        status.textContent = '';
+        showNotification(`転送失敗: ${err}`, 'error');
+    }
+}
+
+function setupTransferProgressListener() {
+    const { listen } = window.__TAURI__.event;
+    listen('server-transfer-progress', (event) => {
+        const { server_id, sent, total } = event.payload;
+        if (server_id !== currentDetailServerId) return;
+        const percent = total ? Math.floor((sent / total) * 100) : 100;
+        document.getElementById('detail-transfer-status').textContent = `転送中... ${percent}%`;
+    });
+}
+
+// Starts, stops, crashes and auto-restarts are pushed by the backend
+function setupStatusListener() {
+    const { listen } = window.__TAURI__.event;
+    listen('server-status-changed', (event) => {
+        const { server_id, new_status } = event.payload;
+        console.log(`[Status] ${server_id}: ${event.payload.old_status} -> ${new_status}`);
+        loadServers();
+        if (currentDetailServerId === server_id) showServerDetail(server_id);
+    });
+}
+
+// Servers flagged for autostart are started one by one after launch
+function setupAutostartListener() {
+    const { listen } = window.__TAURI__.event;
+    listen('autostart-progress', (event) => {
+        const { name, index, total, started, error } = event.payload;
+        if (error) {
+            showNotification(`自動起動に失敗: ${name} (${error})`, 'error');
+        } else if (!started) {
+            showNotification(`自動起動 ${index}/${total}: ${name}`, 'info');
+        }
+    });
+}
+
+function setupExitListener() {
+    const { listen } = window.__TAURI__.event;
+    listen('exit-requested', (event) => {
+        document.getElementById('exit-running-servers').textContent = event.payload.join(', ');
+        document.getElementById('exit-status').textContent = '';
+        document.querySelectorAll('.exit-action').forEach(btn => { btn.disabled = false; });
+        const modal = document.getElementById('exit-modal');
+        modal.classList.remove('hidden');
+        requestAnimationFrame(() => modal.classList.add('active'));
+    });
+}
+
+function closeExitModal() {
+    const modal = document.getElementById('exit-modal');
+    modal.classList.remove('active');
+    setTimeout(() => modal.classList.add('hidden'), 300);
+}
+
+async function exitApp(policy) {
+    const remember = document.getElementById('exit-remember').checked;
+    document.querySelectorAll('.exit-action').forEach(btn => { btn.disabled = true; });
+    if (policy === 'Stop') {
+        document.getElementById('exit-status').textContent = 'サーバーを停止しています... (最大30秒/台)';
+    }
+    try {
+        await invoke('exit_app', { policy, remember });
+    } catch (e) {
+        document.querySelectorAll('.exit-action').forEach(btn => { btn.disabled = false; });
+        showNotification(`終了に失敗: ${e}`, 'error');
+    }
+}
+
+async function saveAppSettings() {
+    const serversDir = document.getElementById('servers-dir-input').value.trim();
+    const exitPolicy = document.getElementById('exit-policy-select').value;
+    const offlineMode = document.getElementById('offline-mode-toggle').checked;
+    const autostartDelay = Math.max(0, parseInt(document.getElementById('autostart-delay-input').value, 10) || 0);
+    const count = (id, fallback) => Math.max(1, parseInt(document.getElementById(id).value, 10) || fallback);
+    const maxRunning = parseInt(document.getElementById('limit-running-servers').value, 10);
+    const limits = {
+        max_running_servers: maxRunning > 0 ? maxRunning : null,
+        max_downloads: count('limit-downloads', 4),
+        max_backup_jobs: count('limit-backup-jobs', 1),
+    };
+    const restApi = {
+        enabled: document.getElementById('rest-api-enabled').checked,
+        port: parseInt(document.getElementById('rest-api-port').value, 10) || 25581,
+        listen_all: document.getElementById('rest-api-listen-all').checked,
+        tls_cert: document.getElementById('rest-api-tls-cert').value.trim() || null,
+        tls_key: document.getElementById('rest-api-tls-key').value.trim() || null,
+    };
+    const folder = (id) => document.getElementById(id).value.trim() || null;
+    const paths = {
+        data_dir: folder('path-data-dir'),
+        cache_dir: folder('path-cache-dir'),
+        log_dir: folder('path-log-dir'),
+    };
+    try {
+        await invoke('set_app_settings', {
+            settings: {
+                servers_dir: serversDir, exit_policy: exitPolicy, offline_mode: offlineMode, limits, paths,
+                rest_api: restApi, autostart_delay_secs: autostartDelay,
+            },
+        });
+        renderRestApiStatus(await invoke('get_rest_api_status'));
+        showNotification('設定を保存しました', 'success');
+    } catch (err) {
+        showNotification(`保存失敗: ${err}`, 'error');
+    }
+}
+
 async function deleteServer(id) {
     const server = servers.find(s => s.id === id);
     const name = server ? server.name : 'サーバー';
-    const confirmed = await showConfirmModal(`「${name}」を完全に削除しますか？\nこの操作は元に戻せません。`);
+    const confirmed = await showConfirmModal(`「${name}」を削除しますか？\nサーバーはゴミ箱に移動され、後から復元できます。`);
     if (!confirmed) return;
     try {
-        await invoke('delete_server', { serverId: id });
+        await invoke('delete_server', { serverId: id, mode: 'Archive' });
         showNotification('サーバーを削除しました', 'success');
         await loadServers();
         await loadServers();
@@ -676,7 +1264,8 @@ async function deleteServer(id) {
 // =============================================
 
 async function showServerDetail(id) {
-    const server = servers.find(s => s.id === id);
+    // The list only holds summaries, fetch the full server
+    const server = await invoke('get_server', { serverId: id }).catch(() => null);
     if (!server) return;
 
     currentDetailServerId = id;
@@ -696,6 +1285,30 @@ async function showServerDetail(id) {
     // Update UI based on server type (Vanilla/Mod/Plugin)
     updateUIForServerType(server.server_type);
 
+    document.getElementById('detail-auto-open-port-toggle').checked = server.auto_open_port || false;
+    document.getElementById('detail-autostart-toggle').checked = !!server.autostart;
+    document.getElementById('detail-tags-input').value = (server.tags || []).join(', ');
+    document.getElementById('detail-group-input').value = server.group || '';
+    renderWebMapStatus(server.web_map);
+    loadRconStatus(id);
+    renderRegionWarning(server.region_warning);
+    renderResourcePackStatus(server.resource_pack);
+    document.getElementById('detail-bedrock-relay').checked = !!server.bedrock_relay;
+    document.getElementById('detail-report-weekly').checked = !!server.stats_reports?.weekly;
+    document.getElementById('detail-report-monthly').checked = !!server.stats_reports?.monthly;
+    document.getElementById('detail-report-status').textContent = '';
+    renderTunnelStatus(server.tunnel);
+    renderBedrockTunnelStatus(server.bedrock_tunnel);
+    document.getElementById('detail-java-path').value = server.java_path_override || '';
+    document.getElementById('detail-jvm-args').value = (server.custom_jvm_args || []).join(' ');
+    renderJvmProfiles(server.jvm_profile || 'Aikar');
+    loadRestorePoints(id);
+    invoke('is_server_adopted', { serverId: id })
+        .then(adopted => { document.getElementById('detail-adopted-note').style.display = adopted ? '' : 'none'; })
+        .catch(() => { });
+    renderTypeMigration(server.server_type);
+    renderRemoteSync(server.remote);
+
     // Auto Restart Settings
     const autoRestartToggle = document.getElementById('detail-auto-restart-toggle');
     autoRestartToggle.checked = server.auto_restart || false;
@@ -725,6 +1338,10 @@ async function showServerDetail(id) {
         document.getElementById('restart-timezone').value = 'Asia/Tokyo';
     }
 
+    // Set Countdown Warnings
+    const warnings = server.restart_warnings || [300, 60, 10];
+    document.getElementById('restart-warnings').value = warnings.join(', ');
+
     toggleAutoRestartSettings();
     toggleAutoRestartMode();
 
@@ -832,10 +1449,12 @@ function updateUIForServerType(serverType) {
         storeTypeSelect.style.display = category === 'hybrid' ? 'inline-flex' : 'none';
     }
 
-    // Geyser/ViaVersion areas - show for plugin and hybrid servers and proxy
-    const showGeyser = category === 'plugin' || category === 'hybrid' || category === 'proxy';
+    // ViaVersion area - show for plugin and hybrid servers and proxy
+    const showVia = category === 'plugin' || category === 'hybrid' || category === 'proxy';
+    // Geyser has Spigot, Fabric and proxy builds, but none for Forge or Mohist
+    const showGeyser = !['Vanilla', 'Forge', 'Mohist'].includes(serverType);
     if (geyserArea) geyserArea.style.display = showGeyser ? 'block' : 'none';
-    if (viaArea) viaArea.style.display = showGeyser ? 'block' : 'none';
+    if (viaArea) viaArea.style.display = showVia ? 'block' : 'none';
 
     // Starter Pack - only for plugin and hybrid servers (not proxy)
     if (starterPackBanner) {
@@ -891,6 +1510,68 @@ function updateStoreUIForType() {
     }
 }
 
+async function saveGeyserConfig() {
+    if (!currentDetailServerId) return;
+    const port = parseInt(document.getElementById('geyser-port-input').value, 10);
+    if (!port || port < 1 || port > 65535) {
+        showNotification('ポート番号が不正です', 'error');
+        return;
+    }
+    const checked = (id) => document.getElementById(id).checked;
+    const config = {
+        bedrock_port: port,
+        clone_remote_port: checked('geyser-clone-port'),
+        auth_type: document.getElementById('geyser-auth-type').value,
+        passthrough_motd: checked('geyser-passthrough-motd'),
+        passthrough_player_counts: checked('geyser-passthrough-counts'),
+        motd1: document.getElementById('geyser-motd1').value,
+        motd2: document.getElementById('geyser-motd2').value,
+    };
+    try {
+        await invoke('set_geyser_config', { serverId: currentDetailServerId, config });
+        showNotification('クロスプレイ設定を保存しました (次回起動時に反映)', 'success');
+    } catch (e) {
+        showNotification(`保存失敗: ${e}`, 'error');
+    }
+}
+
+function renderGeyserConfig(config) {
+    const authOptions = [
+        ['online', 'online (Microsoftアカウント認証)'],
+        ['floodgate', 'floodgate (Java版アカウント不要)'],
+        ['offline', 'offline'],
+    ].filter(([value]) => value !== 'floodgate' || config.floodgate_installed)
+        .map(([value, label]) => `<option value="${value}" ${config.auth_type === value ? 'selected' : ''}>${label}</option>`)
+        .join('');
+    return `
+        <div class="geyser-config" style="display: flex; flex-direction: column; gap: 6px; margin-top: 8px;">
+            <div style="display: flex; gap: 8px; align-items: center;">
+                <span class="text-sm">統合版ポート (UDP)</span>
+                <input type="number" id="geyser-port-input" class="input input-sm" style="width: 100px;"
+                    min="1" max="65535" value="${config.bedrock_port}">
+            </div>
+            <label class="checkbox-label">
+                <input type="checkbox" id="geyser-clone-port" ${config.clone_remote_port ? 'checked' : ''}>
+                <span>Java版と同じポート番号を使う (clone-remote-port)</span>
+            </label>
+            <div style="display: flex; gap: 8px; align-items: center;">
+                <span class="text-sm">認証方式</span>
+                <select id="geyser-auth-type" class="input input-sm">${authOptions}</select>
+            </div>
+            <label class="checkbox-label">
+                <input type="checkbox" id="geyser-passthrough-motd" ${config.passthrough_motd ? 'checked' : ''}>
+                <span>Java版のMOTDを表示する</span>
+            </label>
+            <label class="checkbox-label">
+                <input type="checkbox" id="geyser-passthrough-counts" ${config.passthrough_player_counts ? 'checked' : ''}>
+                <span>Java版のプレイヤー数を表示する</span>
+            </label>
+            <input type="text" id="geyser-motd1" class="input input-sm" placeholder="MOTD 1行目" value="${escapeHtml(config.motd1)}">
+            <input type="text" id="geyser-motd2" class="input input-sm" placeholder="MOTD 2行目" value="${escapeHtml(config.motd2)}">
+            <button class="btn btn-secondary btn-sm" onclick="saveGeyserConfig()">クロスプレイ設定を保存</button>
+        </div>`;
+}
+
 async function updatePresetButtons(id) {
     const geyserArea = document.getElementById('geyser-status-area');
     const viaArea = document.getElementById('viaversion-status-area');
@@ -905,15 +1586,35 @@ async function updatePresetButtons(id) {
             `<button id="install-geyser-btn" class="btn btn-secondary btn-full" onclick="installGeyser()">
                 🔄 クロスプレイを有効化
              </button>`;
+        if (geyserInstalled) {
+            const config = await invoke('get_geyser_config', { serverId: id });
+            geyserArea.insertAdjacentHTML('beforeend', renderGeyserConfig(config));
+        }
 
-        const viaInstalled = await invoke('is_viaversion_installed', { serverId: id });
-        viaArea.innerHTML = viaInstalled ?
+        const via = await invoke('is_viaversion_installed', { serverId: id });
+        document.getElementById('via-backwards').checked = via.via_backwards;
+        document.getElementById('via-rewind').checked = via.via_rewind;
+        viaArea.innerHTML = via.via_version ?
             `<button id="uninstall-viaversion-btn" class="btn btn-danger btn-full" onclick="uninstallViaVersion()">
                 🗑️ 互換性を無効化
              </button>` :
             `<button id="install-viaversion-btn" class="btn btn-secondary btn-full" onclick="installViaVersion()">
                 🔄 互換性を有効化
              </button>`;
+        if (via.via_version && !(via.via_backwards && via.via_rewind)) {
+            // Companions can be added later; ViaVersion is updated along with them
+            viaArea.insertAdjacentHTML('afterbegin',
+                `<button id="install-viaversion-btn" class="btn btn-secondary btn-full" onclick="installViaVersion()" style="margin-bottom: 8px;">
+                    ➕ 選択した互換プラグインを導入
+                 </button>`);
+        }
+
+        const server = servers.find(s => s.id === id);
+        if (!['Velocity', 'Waterfall', 'BungeeCord'].includes(server?.server_type)) {
+            const clients = await invoke('get_client_versions', { serverId: id });
+            viaArea.insertAdjacentHTML('beforeend',
+                `<p class="form-hint">参加できるJava版: ${escapeHtml(formatClientVersions(clients))}</p>`);
+        }
     } catch (e) {
         console.error('Failed to check preset status:', e);
     }
@@ -933,6 +1634,7 @@ async function refreshDetailLogs() {
                 let cls = 'log-line';
                 if (line.includes('ERROR')) cls += ' log-error';
                 if (line.includes('WARN')) cls += ' log-warn';
+                if (line.startsWith('[Prismarine]')) cls += ' log-banner';
                 return `<div class="${cls}">${escapeHtml(line)}</div>`;
             }).join('');
             container.scrollTop = container.scrollHeight;
@@ -994,36 +1696,425 @@ async function openServerFolder() {
     } catch (e) { showNotification(e, 'error'); }
 }
 
-async function openPluginsFolder() {
+window.toggleAutoOpenPort = async () => {
     if (!currentDetailServerId) return;
+    const toggle = document.getElementById('detail-auto-open-port-toggle');
     try {
-        await invoke('open_plugins_folder', { serverId: currentDetailServerId });
-    } catch (e) { showNotification(e, 'error'); }
-}
+        await invoke('set_auto_open_port', { serverId: currentDetailServerId, enabled: toggle.checked });
+        await loadServers();
+    } catch (e) {
+        toggle.checked = !toggle.checked;
+        showNotification(e, 'error');
+    }
+};
 
-// =============================================
-// Versioning & Creation
-// =============================================
+window.toggleAutostart = async () => {
+    if (!currentDetailServerId) return;
+    const toggle = document.getElementById('detail-autostart-toggle');
+    try {
+        await invoke('set_server_autostart', { serverId: currentDetailServerId, enabled: toggle.checked });
+    } catch (e) {
+        toggle.checked = !toggle.checked;
+        showNotification(e, 'error');
+    }
+};
 
-async function updateVersionList() {
-    const typeInp = document.getElementById('server-type');
-    if (!typeInp) return;
+// Per-server bridge tunnel (started and stopped together with the server)
+function renderTunnelStatus(tunnel) {
+    const status = document.getElementById('detail-tunnel-status');
+    if (!tunnel) {
+        status.textContent = '無効';
+        return;
+    }
+    document.getElementById('detail-tunnel-provider').value = tunnel.provider;
+    document.getElementById('detail-tunnel-remote').value = tunnel.remote_server || '';
+    status.textContent = tunnel.address ? `接続アドレス: ${tunnel.address}` : '有効 (サーバー起動中に接続されます)';
+}
 
-    const type = typeInp.value;
-    const wrapper = document.getElementById('wrapper-server-version');
-    const select = wrapper.querySelector('.custom-select');
-    const triggerSpan = select.querySelector('.custom-select-trigger span');
-    const optionsCont = wrapper.querySelector('.custom-options');
-    const hiddenInp = document.getElementById('server-version-select');
+async function enableServerTunnel() {
+    if (!currentDetailServerId) return;
+    const provider = document.getElementById('detail-tunnel-provider').value;
+    const remoteServer = document.getElementById('detail-tunnel-remote').value.trim() || null;
+    try {
+        await invoke('start_bridge_for_server', { serverId: currentDetailServerId, provider, remoteServer });
+        showNotification('Bridgeトンネルを有効にしました', 'success');
+        const server = await invoke('get_server', { serverId: currentDetailServerId });
+        renderTunnelStatus(server?.tunnel);
+        await loadServers();
+    } catch (e) {
+        showNotification(`有効化失敗: ${e}`, 'error');
+    }
+}
 
-    select.classList.add('disabled');
-    triggerSpan.textContent = '読み込み中...';
-    optionsCont.innerHTML = '';
+async function disableServerTunnel() {
+    if (!currentDetailServerId) return;
+    try {
+        await invoke('stop_bridge_for_server', { serverId: currentDetailServerId });
+        renderTunnelStatus(null);
+        await loadServers();
+    } catch (e) {
+        showNotification(`無効化失敗: ${e}`, 'error');
+    }
+}
+
+function renderBedrockTunnelStatus(tunnel) {
+    const status = document.getElementById('detail-bedrock-tunnel-status');
+    if (!tunnel) {
+        status.textContent = '無効';
+        return;
+    }
+    status.textContent = tunnel.address ? `Bedrock接続アドレス: ${tunnel.address}` : '有効 (サーバー起動中に接続されます)';
+}
 
+async function enableBedrockTunnel() {
+    if (!currentDetailServerId) return;
+    const remoteServer = document.getElementById('detail-tunnel-remote').value.trim() || null;
+    if (!remoteServer) {
+        showNotification('frpサーバーのアドレス (host:port) を入力してください', 'error');
+        return;
+    }
     try {
-        let versions = [];
-        if (type === 'vanilla') versions = await fetchVersions('vanilla');
-        else if (type === 'paper') versions = await fetchVersions('paper');
+        await invoke('start_bedrock_bridge', { serverId: currentDetailServerId, provider: 'frp', remoteServer });
+        showNotification('Bedrockトンネルを有効にしました', 'success');
+        const server = await invoke('get_server', { serverId: currentDetailServerId });
+        renderTunnelStatus(server?.tunnel);
+        renderBedrockTunnelStatus(server?.bedrock_tunnel);
+        await loadServers();
+    } catch (e) {
+        showNotification(`有効化失敗: ${e}`, 'error');
+    }
+}
+
+async function disableBedrockTunnel() {
+    if (!currentDetailServerId) return;
+    try {
+        await invoke('stop_bedrock_bridge', { serverId: currentDetailServerId });
+        renderBedrockTunnelStatus(null);
+        await loadServers();
+    } catch (e) {
+        showNotification(`無効化失敗: ${e}`, 'error');
+    }
+}
+
+async function setBedrockRelay(enabled) {
+    if (!currentDetailServerId) return;
+    try {
+        const restartRequired = await invoke('set_bedrock_relay', { serverId: currentDetailServerId, enabled });
+        const message = enabled ? 'BedrockをJavaと同じポート番号で中継します' : 'Bedrockの中継を停止しました';
+        showNotification(restartRequired ? `${message} (Geyserへの反映にはサーバーの再起動が必要です)` : message, 'success');
+    } catch (e) {
+        document.getElementById('detail-bedrock-relay').checked = !enabled;
+        showNotification(`設定失敗: ${e}`, 'error');
+    }
+}
+
+async function loadRconStatus(serverId) {
+    try {
+        renderRconStatus(await invoke('get_rcon_status', { serverId }));
+    } catch (e) {
+        document.getElementById('detail-rcon-status').textContent = `${e}`;
+    }
+}
+
+function renderRconStatus(status) {
+    document.getElementById('detail-rcon-enabled').checked = status.enabled;
+    document.getElementById('detail-rcon-port').value = status.port;
+    if (status.enabled) document.getElementById('detail-rcon-local').checked = status.localhost_only;
+    const lines = [];
+    if (status.enabled) {
+        lines.push(`ポート ${status.port} / ${status.localhost_only ? 'このPCからのみ' : '他のPCからも接続可能'}`);
+        if (!status.password_stored) lines.push('パスワードが資格情報ストアにありません。再生成してください');
+    }
+    if (status.restart_required) lines.push('サーバーの再起動後に反映されます');
+    document.getElementById('detail-rcon-status').textContent = lines.join(' / ');
+}
+
+async function setRconEnabled(enabled) {
+    if (!currentDetailServerId) return;
+    const port = parseInt(document.getElementById('detail-rcon-port').value, 10) || null;
+    const localhostOnly = document.getElementById('detail-rcon-local').checked;
+    try {
+        const status = await invoke('set_rcon_enabled', { serverId: currentDetailServerId, enabled, port, localhostOnly });
+        renderRconStatus(status);
+        showNotification(enabled ? 'RCONを有効にしました' : 'RCONを無効にしました', 'success');
+    } catch (e) {
+        document.getElementById('detail-rcon-enabled').checked = !enabled;
+        showNotification(`RCONの設定に失敗しました: ${e}`, 'error');
+    }
+}
+
+async function rotateRconPassword() {
+    if (!currentDetailServerId) return;
+    try {
+        renderRconStatus(await invoke('rotate_rcon_password', { serverId: currentDetailServerId }));
+        showNotification('RCONのパスワードを再生成しました', 'success');
+    } catch (e) {
+        showNotification(`パスワードの再生成に失敗しました: ${e}`, 'error');
+    }
+}
+
+function renderWebMapStatus(map) {
+    document.getElementById('detail-web-map-status').textContent = map
+        ? `${map.plugin} (ポート ${map.port}${map.public ? ', 外部公開' : ''}) — サーバー起動後に表示されます`
+        : '未導入';
+    document.getElementById('detail-web-map-open').disabled = !map;
+    document.getElementById('detail-web-map-remove').disabled = !map;
+    if (map) {
+        document.getElementById('detail-web-map-plugin').value = map.plugin;
+        document.getElementById('detail-web-map-public').checked = map.public;
+    }
+}
+
+function renderRegionWarning(report) {
+    const box = document.getElementById('detail-region-warning');
+    box.classList.toggle('hidden', !report);
+    if (!report) return;
+    const list = document.getElementById('detail-region-warning-list');
+    list.innerHTML = '';
+    for (const problem of report.problems) {
+        const item = document.createElement('li');
+        item.textContent = problem;
+        list.appendChild(item);
+    }
+}
+
+async function dismissRegionWarning() {
+    if (!currentDetailServerId) return;
+    try {
+        await invoke('dismiss_region_warning', { serverId: currentDetailServerId });
+        renderRegionWarning(null);
+    } catch (error) {
+        showNotification(`警告を閉じられませんでした: ${error}`, 'error');
+    }
+}
+
+async function installWebMap() {
+    if (!currentDetailServerId) return;
+    const plugin = document.getElementById('detail-web-map-plugin').value;
+    const isPublic = document.getElementById('detail-web-map-public').checked;
+    let acceptDownload = false;
+    if (plugin === 'BlueMap') {
+        // BlueMap downloads the Minecraft client for textures, which needs the owner's consent
+        acceptDownload = await showConfirmModal('BlueMapは描画のためにMinecraftクライアントをMojangからダウンロードします。\nこれに同意しますか？ (同意しない場合は後でcore.confで設定できます)');
+    }
+    try {
+        showNotification(`${plugin}を導入中...`, 'info');
+        const map = await invoke('install_web_map', {
+            serverId: currentDetailServerId, plugin, port: null, public: isPublic, acceptDownload
+        });
+        renderWebMapStatus(map);
+        showNotification(`${plugin}を導入しました (ポート ${map.port})`, 'success');
+        await loadServers();
+    } catch (e) {
+        showNotification(`導入失敗: ${e}`, 'error');
+    }
+}
+
+async function uninstallWebMap() {
+    if (!currentDetailServerId) return;
+    try {
+        await invoke('uninstall_web_map', { serverId: currentDetailServerId });
+        renderWebMapStatus(null);
+        await loadServers();
+    } catch (e) {
+        showNotification(`削除失敗: ${e}`, 'error');
+    }
+}
+
+function renderResourcePackStatus(pack) {
+    document.getElementById('detail-pack-status').textContent = pack
+        ? `${pack.url} (SHA-1 ${pack.sha1}${pack.required ? ', 必須' : ''})`
+        : '未設定';
+    document.getElementById('detail-pack-remove').disabled = !pack;
+    if (pack) {
+        document.getElementById('detail-pack-hosting').value = pack.hosting.kind;
+        document.getElementById('detail-pack-required').checked = pack.required;
+    }
+}
+
+async function setResourcePack() {
+    if (!currentDetailServerId) return;
+    const path = document.getElementById('detail-pack-path').value.trim();
+    if (!path) {
+        showNotification('リソースパックのzipを指定してください', 'error');
+        return;
+    }
+    const hosting = document.getElementById('detail-pack-hosting').value === 'Upload'
+        ? {
+            kind: 'Upload',
+            upload_url: document.getElementById('detail-pack-upload-url').value.trim(),
+            public_url: document.getElementById('detail-pack-public-url').value.trim(),
+        }
+        : { kind: 'BuiltIn', port: 8163 };
+    try {
+        const pack = await invoke('set_resource_pack', {
+            serverId: currentDetailServerId,
+            path,
+            hosting,
+            required: document.getElementById('detail-pack-required').checked,
+            publicHost: null,
+        });
+        renderResourcePackStatus(pack);
+        showNotification('リソースパックを設定しました (サーバーの再起動後に反映されます)', 'success');
+    } catch (e) {
+        showNotification(`設定失敗: ${e}`, 'error');
+    }
+}
+
+async function removeResourcePack() {
+    if (!currentDetailServerId) return;
+    try {
+        await invoke('remove_resource_pack', { serverId: currentDetailServerId });
+        renderResourcePackStatus(null);
+    } catch (e) {
+        showNotification(`削除失敗: ${e}`, 'error');
+    }
+}
+
+async function saveStatsReports() {
+    if (!currentDetailServerId) return;
+    try {
+        await invoke('set_stats_reports', {
+            serverId: currentDetailServerId,
+            schedule: {
+                weekly: document.getElementById('detail-report-weekly').checked,
+                monthly: document.getElementById('detail-report-monthly').checked,
+            },
+        });
+    } catch (e) {
+        showNotification(`保存失敗: ${e}`, 'error');
+    }
+}
+
+async function showReport(period) {
+    if (!currentDetailServerId) return;
+    try {
+        const r = await invoke('get_report', { serverId: currentDetailServerId, period });
+        const tps = r.average_tps != null ? r.average_tps.toFixed(1) : '—';
+        const backups = r.backup_success_rate != null
+            ? `${r.backups} 回 (成功率 ${Math.round(r.backup_success_rate)}%)`
+            : 'なし';
+        document.getElementById('detail-report-status').textContent =
+            `ユニークプレイヤー: ${r.unique_players}\n最大同時接続: ${r.peak_players}\n平均TPS: ${tps}\n` +
+            `クラッシュ: ${r.crashes}\nバックアップ: ${backups}\n稼働: ${r.hours_online.toFixed(1)} 時間`;
+    } catch (e) {
+        showNotification(`レポート取得失敗: ${e}`, 'error');
+    }
+}
+
+async function openWebMap(id) {
+    try {
+        await invoke('open_web_map', { serverId: id });
+    } catch (e) {
+        showNotification(e, 'error');
+    }
+}
+
+async function saveServerTags() {
+    if (!currentDetailServerId) return;
+    const input = document.getElementById('detail-tags-input');
+    try {
+        const tags = await invoke('set_server_tags', {
+            serverId: currentDetailServerId,
+            tags: input.value.split(',')
+        });
+        input.value = tags.join(', ');
+        showNotification('タグを保存しました', 'success');
+        await loadServers();
+    } catch (e) {
+        showNotification(`保存失敗: ${e}`, 'error');
+    }
+}
+
+async function saveServerGroup() {
+    if (!currentDetailServerId) return;
+    const input = document.getElementById('detail-group-input');
+    try {
+        const group = await invoke('set_server_group', {
+            serverId: currentDetailServerId,
+            group: input.value.trim() || null
+        });
+        input.value = group || '';
+        showNotification('グループを保存しました', 'success');
+        await loadServers();
+    } catch (e) {
+        showNotification(`保存失敗: ${e}`, 'error');
+    }
+}
+
+// Backends start first and the proxy last, stopping goes the other way
+async function startServerGroup() {
+    const group = document.getElementById('detail-group-input').value.trim();
+    if (!group) {
+        showNotification('グループ名を保存してください', 'error');
+        return;
+    }
+    try {
+        showNotification(`グループ「${group}」を起動中...`, 'info');
+        const started = await invoke('start_group', { group });
+        showNotification(`${started.length}台のサーバーを起動しました`, 'success');
+    } catch (e) {
+        showNotification(`グループ起動失敗: ${e}`, 'error');
+    }
+    await loadServers();
+}
+
+async function stopServerGroup() {
+    const group = document.getElementById('detail-group-input').value.trim();
+    if (!group) return;
+    try {
+        const stopped = await invoke('stop_group', { group });
+        showNotification(`${stopped.length}台のサーバーを停止しました`, 'success');
+    } catch (e) {
+        showNotification(`グループ停止失敗: ${e}`, 'error');
+    }
+    await loadServers();
+}
+
+async function repairServer() {
+    if (!currentDetailServerId) return;
+    const confirmed = await showConfirmModal('サーバーJARを再ダウンロードし、不足しているファイルを再生成しますか？\nワールドや設定は変更されません。');
+    if (!confirmed) return;
+    try {
+        showNotification('修復中...', 'info');
+        const report = await invoke('repair_server', { serverId: currentDetailServerId });
+        showNotification('サーバーを修復しました', 'success');
+        if (!report.java_path) {
+            showNotification(`Java ${report.required_java} 以上が見つかりません`, 'warning');
+        }
+    } catch (e) { showNotification(e, 'error'); }
+}
+
+async function openPluginsFolder() {
+    if (!currentDetailServerId) return;
+    try {
+        await invoke('open_plugins_folder', { serverId: currentDetailServerId });
+    } catch (e) { showNotification(e, 'error'); }
+}
+
+// =============================================
+// Versioning & Creation
+// =============================================
+
+async function updateVersionList() {
+    const typeInp = document.getElementById('server-type');
+    if (!typeInp) return;
+
+    const type = typeInp.value;
+    const wrapper = document.getElementById('wrapper-server-version');
+    const select = wrapper.querySelector('.custom-select');
+    const triggerSpan = select.querySelector('.custom-select-trigger span');
+    const optionsCont = wrapper.querySelector('.custom-options');
+    const hiddenInp = document.getElementById('server-version-select');
+
+    select.classList.add('disabled');
+    triggerSpan.textContent = '読み込み中...';
+    optionsCont.innerHTML = '';
+
+    try {
+        let versions = [];
+        if (type === 'vanilla') versions = await fetchVersions('vanilla');
+        else if (type === 'paper') versions = await fetchVersions('paper');
         else if (type === 'spigot') versions = await fetchVersions('spigot');
         else if (type === 'forge') versions = await fetchVersions('forge');
         else if (type === 'fabric') versions = await fetchVersions('fabric');
@@ -1057,9 +2148,11 @@ async function updateVersionList() {
 }
 
 async function fetchVersions(type) {
-    if (versionCache[type]) return versionCache[type];
-    const v = await invoke('fetch_versions', { serverType: type });
-    versionCache[type] = v;
+    const includeSnapshots = document.getElementById('include-snapshots')?.checked || false;
+    const cacheKey = includeSnapshots ? `${type}:snapshots` : type;
+    if (versionCache[cacheKey]) return versionCache[cacheKey];
+    const v = await invoke('fetch_versions', { serverType: type, includeSnapshots });
+    versionCache[cacheKey] = v;
     return v;
 }
 
@@ -1077,6 +2170,8 @@ async function createServer() {
         return;
     }
 
+    if (type === 'Spigot' && !(await checkBuildToolsPrerequisites(version))) return;
+
     // Show EULA confirmation
     const eulaAccepted = await showEulaModal();
     if (!eulaAccepted) {
@@ -1089,8 +2184,9 @@ async function createServer() {
     btn.textContent = '作成中...';
 
     try {
-        await invoke('create_server', { name, version, serverType: type, port, maxMemory: memory });
+        const created = await invoke('create_server', { name, version, serverType: type, port, maxMemory: memory, eulaAccepted: true });
         showNotification('サーバーを作成しました', 'success');
+        showReadinessIssues(created.readiness);
         closeCreateServerModal();
         await loadServers();
     } catch (err) {
@@ -1101,15 +2197,49 @@ async function createServer() {
     }
 }
 
+// Spigot is built with BuildTools, which needs git and a Java matching the version
+async function checkBuildToolsPrerequisites(version) {
+    let check;
+    try {
+        check = await invoke('check_buildtools', { mcVersion: version });
+    } catch (e) {
+        return true;
+    }
+    if (!check.git_version && check.portable_git_supported) {
+        if (!(await showConfirmModal('SpigotのビルドにはGitが必要です。ポータブル版Gitをダウンロードして使用しますか？'))) return false;
+        try {
+            showNotification('Gitをダウンロードしています...', 'info');
+            await invoke('install_portable_git');
+            showNotification('ポータブル版Gitを導入しました', 'success');
+        } catch (e) {
+            showNotification(`Gitの導入に失敗: ${e}`, 'error');
+            return false;
+        }
+        check = await invoke('check_buildtools', { mcVersion: version });
+    }
+    for (const problem of check.problems) {
+        showNotification(problem, 'error');
+    }
+    return check.problems.length === 0;
+}
+
+// Surface problems found by the post-creation checklist before the first start
+function showReadinessIssues(readiness) {
+    if (!readiness) return;
+    for (const check of readiness.checks.filter(c => !c.ok)) {
+        showNotification(check.message, check.blocker ? 'error' : 'warning');
+    }
+}
+
 // =============================================
 // Settings & System
 // =============================================
 
 async function updateExternalIP() {
     try {
-        const ip = await invoke('get_external_ip');
+        const addresses = await invoke('get_external_ip');
         const el = document.getElementById('external-ip');
-        if (el) el.textContent = ip;
+        if (el) el.textContent = formatExternalAddresses(addresses);
     } catch (e) { }
 }
 
@@ -1125,6 +2255,63 @@ async function checkUPnPStatus() {
     } catch (e) { }
 }
 
+// Router brands matched against the UPnP manufacturer/friendlyName
+const ROUTER_UPNP_GUIDES = [
+    { match: /avm|fritz/i, guide: 'Fritz!Box: ホームネットワーク → ネットワーク → ネットワーク設定 で「UPnPによる設定変更を許可」を有効にしてください。' },
+    { match: /\bnec\b|aterm/i, guide: 'Aterm: 詳細設定 → UPnP設定 で UPnP機能を「使用する」にしてください。' },
+    { match: /buffalo/i, guide: 'BUFFALO: 詳細設定 → セキュリティー → UPnP で UPnPを「使用する」にしてください。' },
+    { match: /tp-?link/i, guide: 'TP-Link: 詳細設定 → NAT転送 → UPnP を有効にしてください。' },
+    { match: /asus/i, guide: 'ASUS: WAN → インターネット接続 で「UPnPを有効にする」を「はい」にしてください。' },
+    { match: /netgear/i, guide: 'NETGEAR: 詳細設定 → 高度な設定 → UPnP で「UPnPを有効にする」にチェックしてください。' },
+];
+
+const UPNP_FAILURE_TEXT = {
+    ROUTER_NOT_FOUND: 'ルーターが見つかりません (UPnPが無効の可能性があります)',
+    NO_WAN_SERVICE: 'ルーターがポート開放機能 (UPnP IGD) に対応していません',
+    NOT_AUTHORIZED: 'ルーターが設定変更を拒否しました (UPnPが読み取り専用の可能性があります)',
+    CONFLICTING_MAPPING: 'このポートは別の機器で既に開放されています',
+    INVALID_ARGUMENTS: 'ルーターがポート開放の要求を受け付けませんでした',
+    UNREACHABLE: 'ルーターに接続できませんでした',
+    OTHER: 'ポート開放に失敗しました',
+};
+
+// Show brand specific help when the last UPnP operation failed
+async function showUpnpGuidance() {
+    try {
+        const diag = await invoke('get_upnp_diagnostics');
+        const failure = diag.last_failure;
+        if (!failure) return;
+        const router = diag.router || {};
+        const brand = `${router.manufacturer || ''} ${router.friendly_name || ''}`;
+        const guide = ROUTER_UPNP_GUIDES.find(g => g.match.test(brand));
+        const name = router.friendly_name || router.manufacturer;
+        let message = UPNP_FAILURE_TEXT[failure.failure] || UPNP_FAILURE_TEXT.OTHER;
+        if (name) message += ` (${name})`;
+        if (guide) message += `\n${guide.guide}`;
+        showNotification(message, 'warning');
+    } catch (e) {
+        console.error(e);
+    }
+}
+
+const REACHABILITY_TEXT = {
+    REACHABLE: ['外部から接続できることを確認しました', 'success'],
+    NOT_LISTENING: ['ポートは開放されましたが、サーバーが起動していないため外部接続を確認できません', 'info'],
+    NOT_MAPPED: ['ルーターにポート転送が登録されていません', 'warning'],
+    ISP_BLOCKED: ['ルーターの開放は成功しましたが、プロバイダーのCGNAT配下のため外部から接続できません。Prismarine Bridgeをご利用ください', 'warning'],
+    BLOCKED: ['ポートは開放されていますが、外部から接続できません。ファイアウォールやプロバイダーの制限を確認してください', 'warning'],
+};
+
+async function verifyPortReachability(port, protocol) {
+    try {
+        const report = await invoke('test_port_reachability', { port, protocol });
+        const text = REACHABILITY_TEXT[report.verdict];
+        if (text) showNotification(text[0], text[1]);
+    } catch (e) {
+        console.error(e);
+    }
+}
+
 function startMonitoring() {
     updateSystemStats();
     updateInterval = setInterval(updateSystemStats, 3000);
@@ -1142,7 +2329,7 @@ async function updateSystemStats() {
 
 async function updateExternalIP() {
     try {
-        rawExternalIp = await invoke('get_external_ip');
+        rawExternalIp = formatExternalAddresses(await invoke('get_external_ip'));
         refreshIpDisplay();
     } catch (e) {
         rawExternalIp = '取得失敗';
@@ -1155,6 +2342,11 @@ function toggleIpVisibility() {
     refreshIpDisplay();
 }
 
+function formatExternalAddresses(addresses) {
+    const parts = [addresses.ipv4, addresses.ipv6].filter(Boolean);
+    return parts.length ? parts.join(' / ') : '取得失敗';
+}
+
 function refreshIpDisplay() {
     const el = document.getElementById('external-ip');
     const eyeIcon = document.getElementById('eye-icon');
@@ -1227,6 +2419,10 @@ function renderManagedPorts() {
                         </div>
                         <span class="port-name">${escapeHtml(p.name)}</span>
                         <div class="port-number-display">${p.port}</div>
+                        <div class="port-live-state text-xs text-muted">
+                            ${p.upnp_mapped === null ? 'ルーター: 不明' : (p.upnp_mapped ? 'ルーター: 登録済み' : 'ルーター: 未登録')}
+                            · ${p.bound_locally ? '使用中' : '未使用'}${p.owner_server_name ? ` (${escapeHtml(p.owner_server_name)})` : ''}
+                        </div>
                     </div>
                     <div class="port-status-bar" onclick="toggleManagedPort(${slot}, ${!isActive})">
                         ${statusText}
@@ -1254,18 +2450,61 @@ async function submitOpenPort() {
     const modal = document.getElementById('port-modal');
     const port = parseInt(document.getElementById('port-number-input').value);
     const protocol = document.querySelector('input[name="protocol"]:checked').value;
+    const family = document.querySelector('input[name="address-family"]:checked').value;
     const name = document.getElementById('port-name-input').value || 'Server';
     const slot = parseInt(modal.dataset.slot);
 
     try {
-        await invoke('open_managed_port', { port, protocol, name, slot });
+        if (family !== 'IPV6') {
+            const conflicts = await invoke('find_port_conflicts', { port, protocol }).catch(() => []);
+            if (conflicts.length > 0) {
+                const list = conflicts.map(c => `${c.external_port}/${c.protocol} → ${c.internal_client}:${c.internal_port} (${c.description || '説明なし'})`).join('\n');
+                const confirmed = await showConfirmModal(`このポートはルーターで既に転送されています:\n${list}\n\nそれでも開放しますか？`);
+                if (!confirmed) return;
+            }
+        }
+        await invoke('open_managed_port', { port, protocol, name, slot, family });
         closePortModal();
         await loadManagedPorts();
+        const mapped = managedPorts.find(p => p.slot === slot);
+        if (mapped && mapped.upnp_mapped !== true) await showUpnpGuidance();
+        else await verifyPortReachability(port, protocol === 'UDP' ? 'UDP' : 'TCP');
     } catch (err) {
         showNotification(`エラー: ${err}`, 'error');
     }
 }
 
+// Port mappings currently on the router (including ones made by other apps)
+async function loadRouterMappings() {
+    const list = document.getElementById('router-mappings-list');
+    list.textContent = '読み込み中...';
+    try {
+        const mappings = await invoke('list_router_mappings');
+        if (mappings.length === 0) {
+            list.textContent = 'ポート転送はありません';
+            return;
+        }
+        list.innerHTML = mappings.map(m => `
+            <div class="server-info-item">
+                <span>${m.external_port}/${m.protocol} → ${escapeHtml(m.internal_client)}:${m.internal_port}</span>
+                <span>${escapeHtml(m.description || '説明なし')}${m.this_computer ? ' (このPC)' : ''}${m.stale ? ' <strong>不要</strong>' : ''}</span>
+            </div>
+        `).join('');
+    } catch (e) {
+        list.textContent = `取得失敗: ${e}`;
+    }
+}
+
+async function removeStaleMappings() {
+    try {
+        const removed = await invoke('remove_stale_router_mappings');
+        showNotification(removed.length > 0 ? `${removed.length}件の不要なポート転送を削除しました` : '不要なポート転送はありません', 'success');
+        await loadRouterMappings();
+    } catch (e) {
+        showNotification(`削除失敗: ${e}`, 'error');
+    }
+}
+
 window.toggleManagedPort = async (slot, active) => {
     try {
         await invoke('set_managed_port_active', { slot, active });
@@ -1304,6 +2543,10 @@ async function searchPlugins() {
         }
 
         const title = q ? '' : `<h3 class="plugin-section-title">${s} の人気プラグイン</h3>`;
+        const server = servers.find(sv => sv.id === currentDetailServerId);
+        const reloadMethod = server?.status === 'Running'
+            ? await invoke('get_plugin_reload_method', { serverId: currentDetailServerId }).catch(() => null)
+            : null;
 
         const pluginCards = await Promise.all(list.map(async p => {
             const icon = p.icon_url || 'https://cdn.modrinth.com/placeholder.svg';
@@ -1316,8 +2559,11 @@ async function searchPlugins() {
                 isInstalled = await invoke('is_plugin_installed', { serverId: currentDetailServerId, pluginId: p.id, source: s });
             } catch (e) { /* ignore */ }
 
+            const reloadBtn = isInstalled && reloadMethod
+                ? `<button class="btn btn-secondary btn-sm" onclick="reloadPlugin('${escapeHtml(p.name)}', '${reloadMethod}')">再読み込み</button>`
+                : '';
             const actionBtn = isInstalled
-                ? `<button class="btn btn-danger btn-sm" id="install-btn-${p.id}" onclick="uninstallPlugin('${p.id}', '${escapeHtml(p.name)}', '${s}')">無効化</button>`
+                ? `${reloadBtn}<button class="btn btn-danger btn-sm" id="install-btn-${p.id}" onclick="uninstallPlugin('${p.id}', '${escapeHtml(p.name)}', '${s}')">無効化</button>`
                 : `<button class="btn btn-primary btn-sm" id="install-btn-${p.id}" onclick="installPlugin('${p.id}', '${escapeHtml(p.name)}', '${s}')">追加</button>`;
 
             return `
@@ -1327,7 +2573,9 @@ async function searchPlugins() {
                         <div class="plugin-name">
                             ${escapeHtml(p.name)}
                             <span class="plugin-source-badge">${s}</span>
+                            ${p.description_language === 'ja' ? '<span class="plugin-source-badge">日本語</span>' : ''}
                         </div>
+                        ${p.matched_query ? `<div class="plugin-meta">「${escapeHtml(p.matched_query)}」で検索した結果</div>` : ''}
                         <div class="plugin-desc">${escapeHtml(p.description || '説明はありません。')}</div>
                         <div class="plugin-meta">
                             ${author} ${downloads} 
@@ -1346,6 +2594,24 @@ async function searchPlugins() {
     }
 }
 
+// Apply a plugin's config changes without restarting the server
+async function reloadPlugin(name, method) {
+    let allowFullReload = false;
+    if (method === 'FullReload') {
+        // Without PlugManX/ServerUtils only /reload is left, which reloads every plugin
+        allowFullReload = await showConfirmModal(
+            'PlugManXやServerUtilsが導入されていないため、/reload ですべてのプラグインを再読み込みします。\n' +
+            '一部のプラグインが正常に動作しなくなる場合があります。続行しますか？');
+        if (!allowFullReload) return;
+    }
+    try {
+        await invoke('reload_plugin', { serverId: currentDetailServerId, pluginName: name, allowFullReload });
+        showNotification(`${name} を再読み込みしました`, 'success');
+    } catch (e) {
+        showNotification(`再読み込みに失敗しました: ${e}`, 'error');
+    }
+}
+
 async function uninstallPlugin(id, name, source) {
     const btn = document.getElementById(`install-btn-${id}`);
     if (!btn) return;
@@ -1435,23 +2701,96 @@ async function installStarterPack() {
     }
 }
 
+// Re-running with the same pack continues a failed install instead of starting over
+async function installModpack() {
+    if (!currentDetailServerId) return;
+    const source = document.getElementById('modpack-source-input').value.trim();
+    if (!source) return;
+    const btn = document.getElementById('install-modpack-btn');
+    btn.disabled = true;
+    try {
+        showNotification('モッドパックを導入しています...', 'info');
+        const result = await invoke('install_modpack', { serverId: currentDetailServerId, source });
+        showNotification(`${result.name} ${result.version} を導入しました (${result.downloaded}ファイル)`, 'success');
+        const minecraft = result.dependencies.minecraft;
+        const server = servers.find(s => s.id === currentDetailServerId);
+        if (minecraft && server && server.version !== minecraft) {
+            showNotification(`このモッドパックはMinecraft ${minecraft}用です (サーバーは${server.version})`, 'warning');
+        }
+    } catch (e) {
+        showNotification(`モッドパックの導入に失敗しました: ${e} (もう一度実行すると続きから再開します)`, 'error');
+    } finally {
+        btn.disabled = false;
+    }
+}
+
 async function startBridge() {
+    const provider = document.getElementById('bridge-provider-select').value;
+    const remoteServer = document.getElementById('bridge-remote-input').value.trim() || null;
     showNotification('Bridgeを起動中...', 'info');
     try {
-        await invoke('start_bridge', { port: 25565 });
+        await invoke('start_bridge', { port: 25565, remoteServer, provider });
         document.getElementById('bridge-status-text').textContent = '起動中';
+        pollBridgeStatus();
     } catch (e) { showNotification(e, 'error'); }
 }
 
+// Wait for the tunnel client to report its address (or a claim URL for playit.gg)
+async function pollBridgeStatus() {
+    const statusText = document.getElementById('bridge-status-text');
+    for (let i = 0; i < 60; i++) {
+        const status = await invoke('get_bridge_status');
+        if (status.Connected) {
+            statusText.textContent = '接続済み';
+            document.getElementById('bridge-address-text').textContent = status.Connected;
+            document.getElementById('bridge-address').style.display = '';
+            return;
+        }
+        if (status.AccessRequired) {
+            // Quick tunnels need cloudflared on the player's machine too
+            statusText.textContent = `接続済み (プレイヤー側で cloudflared access tcp --hostname ${status.AccessRequired} --url localhost:25565 を実行し、localhost:25565 に接続してください)`;
+            document.getElementById('bridge-address-text').textContent = status.AccessRequired;
+            document.getElementById('bridge-address').style.display = '';
+            return;
+        }
+        if (status.ActionRequired) {
+            statusText.textContent = `次のURLを開いてアカウントと連携してください: ${status.ActionRequired}`;
+        } else if (status.Error) {
+            statusText.textContent = `エラー: ${status.Error}`;
+            return;
+        } else if (status === 'Stopped') {
+            return;
+        }
+        await new Promise(resolve => setTimeout(resolve, 1000));
+    }
+}
+
 async function stopBridge() {
     await invoke('stop_bridge');
     document.getElementById('bridge-status-text').textContent = '停止中';
+    document.getElementById('bridge-address').style.display = 'none';
 }
 
-function copyBridgeAddress() {
+// "1.21 〜 1.21.1" from get_client_versions
+function formatClientVersions(clients) {
+    return clients.oldest === clients.newest
+        ? clients.oldest
+        : `${clients.oldest} 〜 ${clients.newest}`;
+}
+
+async function copyBridgeAddress() {
     const addr = document.getElementById('bridge-address-text').textContent;
     navigator.clipboard.writeText(addr);
-    showNotification('コピーしました', 'success');
+    // Tell whoever is invited which client versions to use (the bridge forwards port 25565)
+    const server = servers.find(s => s.port === 25565 && s.status === 'Running');
+    try {
+        const clients = server ? await invoke('get_client_versions', { serverId: server.id }) : null;
+        showNotification(clients
+            ? `コピーしました（Java版 ${formatClientVersions(clients)} で参加できます）`
+            : 'コピーしました', 'success');
+    } catch (e) {
+        showNotification('コピーしました', 'success');
+    }
 }
 
 async function installGeyser() {
@@ -1461,8 +2800,15 @@ async function installGeyser() {
     btn.disabled = true;
     btn.textContent = '導入中...';
     try {
-        await invoke('install_geyser_support', { serverId: currentDetailServerId });
-        showNotification('Geyserを追加しました', 'success');
+        const optionalPack = document.getElementById('geyser-optional-pack').checked;
+        await invoke('install_geyser_support', { serverId: currentDetailServerId, optionalPack });
+        const server = servers.find(s => s.id === currentDetailServerId);
+        if (['Velocity', 'Waterfall', 'BungeeCord'].includes(server?.server_type)) {
+            // Backends get Floodgate and the proxy's key, not Geyser itself
+            showNotification('プロキシにGeyserを追加し、バックエンドにFloodgateの鍵を配布しました', 'success');
+        } else {
+            showNotification('Geyserを追加しました', 'success');
+        }
         updatePresetButtons(currentDetailServerId);
     } catch (e) {
         showNotification(e, 'error');
@@ -1478,7 +2824,9 @@ async function installViaVersion() {
     btn.disabled = true;
     btn.textContent = '導入中...';
     try {
-        await invoke('install_viaversion_support', { serverId: currentDetailServerId });
+        const backwards = document.getElementById('via-backwards').checked;
+        const rewind = document.getElementById('via-rewind').checked;
+        await invoke('install_viaversion_support', { serverId: currentDetailServerId, backwards, rewind });
         showNotification('ViaVersionを追加しました', 'success');
         updatePresetButtons(currentDetailServerId);
     } catch (e) {
@@ -1488,6 +2836,284 @@ async function installViaVersion() {
     }
 }
 
+function pluginCompatibilityLines(plugins) {
+    return plugins.map(p => {
+        const state = p.compatible === true ? '✅' : p.compatible === false ? '❌' : '❔';
+        const update = p.update ? ` → ${p.update.version}` : '';
+        return `${state} ${p.name} ${p.installed_version || ''}${update}`;
+    });
+}
+
+async function checkPluginCompatibility() {
+    if (!currentDetailServerId) return;
+    const targetVersion = document.getElementById('upgrade-version-input').value.trim();
+    const output = document.getElementById('upgrade-result');
+    if (!targetVersion) return;
+    output.textContent = '確認中...';
+    try {
+        const plugins = await invoke('check_plugin_compatibility', { serverId: currentDetailServerId, targetVersion });
+        const lines = plugins.length ? pluginCompatibilityLines(plugins) : ['プラグインはありません'];
+        output.innerHTML = lines.map(escapeHtml).join('<br>');
+    } catch (e) {
+        output.textContent = '';
+        showNotification(`互換性チェックに失敗: ${e}`, 'error');
+    }
+}
+
+async function upgradeServer() {
+    if (!currentDetailServerId) return;
+    const newVersion = document.getElementById('upgrade-version-input').value.trim();
+    if (!newVersion) return;
+    if (!(await showConfirmModal(`${newVersion} にアップグレードしますか？`))) return;
+    const btn = document.getElementById('upgrade-server-btn');
+    const output = document.getElementById('upgrade-result');
+    btn.disabled = true;
+    output.textContent = 'アップグレード中...';
+    try {
+        const report = await invoke('upgrade_server', {
+            serverId: currentDetailServerId,
+            newVersion,
+            updatePlugins: document.getElementById('upgrade-update-plugins').checked,
+            verifyBoot: document.getElementById('upgrade-verify-boot').checked,
+        });
+        const lines = pluginCompatibilityLines(report.plugins);
+        lines.push(...report.updated_plugins.map(p => `更新: ${p}`));
+        lines.push(...report.failed_updates.map(p => `更新失敗: ${p}`));
+        if (report.rolled_back) {
+            lines.push(`起動に失敗したため元に戻しました: ${report.error}`);
+            showNotification('アップグレードを元に戻しました', 'error');
+        } else {
+            showNotification(`${report.server.version} にアップグレードしました`, 'success');
+        }
+        output.innerHTML = lines.map(escapeHtml).join('<br>');
+        await loadServers();
+        showServerDetail(currentDetailServerId);
+    } catch (e) {
+        output.textContent = '';
+        showNotification(`アップグレードに失敗: ${e}`, 'error');
+    } finally {
+        btn.disabled = false;
+    }
+}
+
+// Server software that can replace each type without touching configs or worlds
+const TYPE_MIGRATIONS = {
+    Spigot: ['Paper', 'Purpur'],
+    Paper: ['Purpur'],
+    Purpur: ['Paper'],
+    BungeeCord: ['Waterfall'],
+    Waterfall: ['BungeeCord'],
+};
+
+function renderTypeMigration(serverType) {
+    const targets = TYPE_MIGRATIONS[serverType] || [];
+    document.getElementById('type-migration-card').style.display = targets.length ? '' : 'none';
+    document.getElementById('type-migration-select').innerHTML = targets
+        .map(t => `<option value="${t}">${t}</option>`)
+        .join('');
+}
+
+async function changeServerType() {
+    if (!currentDetailServerId) return;
+    const newType = document.getElementById('type-migration-select').value;
+    if (!newType) return;
+    if (!(await showConfirmModal(`${newType} に移行しますか？ 現在のjarと設定は復元ポイントに保存されます。`))) return;
+    try {
+        await invoke('change_server_type', { serverId: currentDetailServerId, newType });
+        showNotification(`${newType} に移行しました`, 'success');
+        await loadServers();
+        showServerDetail(currentDetailServerId);
+    } catch (e) {
+        showNotification(`移行に失敗: ${e}`, 'error');
+    }
+}
+
+function openRemoteImportModal() {
+    const modal = document.getElementById('remote-import-modal');
+    modal.classList.remove('hidden');
+    requestAnimationFrame(() => modal.classList.add('active'));
+}
+
+function closeRemoteImportModal() {
+    const modal = document.getElementById('remote-import-modal');
+    modal.classList.remove('active');
+    setTimeout(() => modal.classList.add('hidden'), 300);
+}
+
+async function importRemoteServer() {
+    const value = (id) => document.getElementById(id).value.trim();
+    const panelUrl = value('remote-panel-url');
+    const remote = {
+        host: value('remote-host'),
+        port: parseInt(value('remote-port')) || 22,
+        user: value('remote-user'),
+        key_path: value('remote-key') || null,
+        root: value('remote-root'),
+        panel: panelUrl ? { url: panelUrl, server_id: value('remote-panel-id') } : null,
+    };
+    const btn = document.getElementById('confirm-remote-import-btn');
+    btn.disabled = true;
+    btn.textContent = '接続中...';
+    try {
+        const server = await invoke('import_remote_server', {
+            name: value('remote-name'),
+            serverType: value('remote-type'),
+            version: value('remote-version') || '-',
+            remote,
+            panelApiKey: value('remote-panel-key') || null,
+        });
+        showNotification(`${server.name} を追加しました`, 'success');
+        closeRemoteImportModal();
+        await loadServers();
+    } catch (e) {
+        showNotification(`追加に失敗: ${e}`, 'error');
+    } finally {
+        btn.disabled = false;
+        btn.textContent = '追加';
+    }
+}
+
+function renderRemoteSync(remote) {
+    document.getElementById('remote-sync-card').style.display = remote ? '' : 'none';
+    document.getElementById('remote-sync-result').textContent = '';
+    if (!remote) return;
+    const panel = remote.panel ? ` / パネル: ${remote.panel.url}` : ' / パネルなし（起動・停止はホストの管理画面で行ってください）';
+    document.getElementById('remote-sync-host').textContent =
+        `${remote.user}@${remote.host}:${remote.port}/${remote.root}${panel}`;
+}
+
+async function syncRemoteServer(push) {
+    if (!currentDetailServerId) return;
+    const output = document.getElementById('remote-sync-result');
+    output.textContent = push ? 'アップロード中...' : '取得中...';
+    try {
+        const report = await invoke('sync_remote_server', { serverId: currentDetailServerId, push });
+        output.textContent = report.files.length
+            ? `${push ? 'アップロード' : '取得'}: ${report.files.join(', ')}`
+            : (push ? '変更されたファイルはありません' : 'ファイルがありません');
+    } catch (e) {
+        output.textContent = '';
+        showNotification(`同期に失敗: ${e}`, 'error');
+    }
+}
+
+async function loadRestorePoints(serverId) {
+    const output = document.getElementById('restore-points');
+    try {
+        const [points, rollback] = await Promise.all([
+            invoke('list_restore_points', { serverId }),
+            invoke('get_last_rollback', { serverId }),
+        ]);
+        const lines = [];
+        if (rollback) {
+            lines.push(`⚠️ ${new Date(rollback.at * 1000).toLocaleString()} ${rollback.failed_version} の起動に失敗したため「${rollback.reason}」の前に戻しました (${rollback.error})`);
+            lines.push(...rollback.removed.map(f => `削除: ${f}`));
+            lines.push(...rollback.replaced.map(f => `復元: ${f}`));
+            if (!rollback.restarted) lines.push('再起動できませんでした');
+        }
+        lines.push(...points.map(p => `${new Date(p.created_at * 1000).toLocaleString()} ${p.reason} (${p.version})`));
+        output.innerHTML = lines.length ? lines.map(escapeHtml).join('<br>') : '復元ポイントはまだありません';
+    } catch (e) {
+        output.textContent = '';
+    }
+}
+
+async function analyzeLag() {
+    if (!currentDetailServerId) return;
+    const btn = document.getElementById('analyze-lag-btn');
+    const output = document.getElementById('lag-report');
+    btn.disabled = true;
+    output.textContent = '分析中...';
+    try {
+        const report = await invoke('analyze_lag', { serverId: currentDetailServerId });
+        const lines = [];
+        if (report.tps != null) {
+            lines.push(`TPS: ${report.tps.toFixed(1)}${report.mspt != null ? ` / MSPT: ${report.mspt.toFixed(1)}ms` : ''}`);
+        }
+        lines.push(`${report.chunks_scanned}チャンク: エンティティ ${report.total_entities} / ブロックエンティティ ${report.total_block_entities}`);
+        if (report.sources.length === 0) {
+            lines.push('目立ったラグの原因は見つかりませんでした');
+        }
+        report.sources.forEach((source, i) => {
+            const top = source.top.map(t => `${t.id.replace('minecraft:', '')} ×${t.count}`).join(', ');
+            lines.push(`${i + 1}. [${source.dimension}] X ${source.x}, Z ${source.z} (スコア ${source.score}) ${top}`);
+        });
+        lines.push(...report.notes.map(n => `※ ${n}`));
+        output.innerHTML = lines.map(escapeHtml).join('<br>');
+    } catch (e) {
+        output.textContent = '';
+        showNotification(`ラグ分析に失敗: ${e}`, 'error');
+    } finally {
+        btn.disabled = false;
+    }
+}
+
+async function saveServerJava() {
+    if (!currentDetailServerId) return;
+    const path = document.getElementById('detail-java-path').value.trim() || null;
+    try {
+        const version = await invoke('set_server_java', { serverId: currentDetailServerId, path });
+        showNotification(version ? `Java ${version} を使用します` : 'Javaを自動選択に戻しました', 'success');
+    } catch (e) {
+        showNotification(`Javaの設定に失敗: ${e}`, 'error');
+    }
+}
+
+async function renderJvmProfiles(selected) {
+    const select = document.getElementById('detail-jvm-profile');
+    const profiles = await invoke('get_jvm_profiles');
+    select.innerHTML = profiles
+        .map(p => `<option value="${p.id}">${escapeHtml(p.label)}</option>`)
+        .join('');
+    select.value = selected;
+    select.dataset.saved = selected;
+}
+
+async function saveJvmProfile() {
+    if (!currentDetailServerId) return;
+    const select = document.getElementById('detail-jvm-profile');
+    try {
+        await invoke('set_jvm_profile', { serverId: currentDetailServerId, profile: select.value });
+        select.dataset.saved = select.value;
+        showNotification('GCプロファイルを保存しました (次回起動時に反映)', 'success');
+    } catch (e) {
+        select.value = select.dataset.saved;
+        showNotification(`GCプロファイルの変更に失敗: ${e}`, 'error');
+    }
+}
+
+async function saveJvmArgs() {
+    if (!currentDetailServerId) return;
+    const args = document.getElementById('detail-jvm-args').value.trim() || null;
+    try {
+        await invoke('set_jvm_args', { serverId: currentDetailServerId, args });
+        showNotification('JVM引数を保存しました (次回起動時に反映)', 'success');
+    } catch (e) {
+        showNotification(`JVM引数の保存に失敗: ${e}`, 'error');
+    }
+}
+
+async function applySecurityPreset() {
+    if (!currentDetailServerId) return;
+    const btn = document.getElementById('apply-security-preset-btn');
+    const result = document.getElementById('security-preset-result');
+    const installPlugin = document.getElementById('security-preset-plugin').checked;
+    btn.disabled = true;
+    try {
+        const report = await invoke('apply_security_preset', { serverId: currentDetailServerId, installPlugin });
+        const lines = [`${report.changes.length}件の設定を変更しました`];
+        if (report.plugin_installed) lines.push(`${report.plugin_installed} を導入しました`);
+        if (report.restart_required) lines.push('反映にはサーバーの再起動が必要です');
+        lines.push(...report.warnings.map(w => `⚠ ${w}`));
+        result.innerHTML = lines.map(escapeHtml).join('<br>');
+        showNotification('セキュリティ設定を適用しました', 'success');
+    } catch (e) {
+        showNotification(`セキュリティ設定の適用に失敗: ${e}`, 'error');
+    } finally {
+        btn.disabled = false;
+    }
+}
+
 async function uninstallGeyser() {
     if (!currentDetailServerId) return;
     const btn = document.getElementById('uninstall-geyser-btn');
@@ -1557,6 +3183,12 @@ async function saveAutoRestartSettings() {
         schedule = scheduleTime;
     }
 
+    // Get Countdown Warnings (seconds before restart)
+    const restartWarnings = document.getElementById('restart-warnings').value
+        .split(',')
+        .map(v => parseInt(v.trim()))
+        .filter(v => !isNaN(v) && v > 0);
+
     try {
         await invoke('set_auto_restart', {
             serverId: currentDetailServerId,
@@ -1564,7 +3196,8 @@ async function saveAutoRestartSettings() {
             restartType: mode,
             interval: interval,
             schedule: schedule,
-            timeZone: timezone
+            timeZone: timezone,
+            restartWarnings: restartWarnings
         });
         showNotification('自動再起動設定を保存しました', 'success');
     } catch (e) {